@@ -0,0 +1,431 @@
+//! Access control lists.
+//!
+//! Clients authenticate as a user with `AUTH username password`, or as the
+//! `default` user when the username is omitted. New connections are
+//! authenticated as `default` right away if it is enabled and requires no
+//! password.
+//!
+//! Each user holds the commands it may run, the keys it may access and the
+//! channels it may publish or subscribe to. Commands are checked against the
+//! user of the connection before being applied, and rejected with a `NOPERM`
+//! error if the user is not allowed to run them.
+//!
+//! Users are created and modified with `ACL SETUSER`, which applies rules
+//! such as `on`, `>password`, `+@read`, `-flushall`, `~cache:*` or `&news.*`
+//! in order. Commands are allowed or denied one by one, or by category. The
+//! last rule matching a command decides whether it is allowed, commands
+//! matching none being denied.
+//!
+//! Passwords are stored as their SHA-256 digest, which the rules `#<hash>`
+//! and `!<hash>` add and remove directly, so that the rules listed by `ACL
+//! LIST` recreate the users.
+//!
+//! The rules `ratelimit-commands=<n>` and `ratelimit-bytes=<n>` limit the
+//! commands and bytes per second sent by all the clients authenticated as the
+//! user together, `0` removing the limit.
+
+use crate::cmd::Command;
+use crate::db::{glob_matches, RateLimits};
+
+mod sha256;
+use sha256::sha256_hex;
+
+use std::collections::BTreeMap;
+
+/// Name of the user clients authenticate as when no username is given.
+pub(crate) const DEFAULT_USER: &str = "default";
+
+/// Categories of commands, allowed or denied with `+@category` and
+/// `-@category`. Every command belongs to `all`.
+const CATEGORIES: &[&str] = &[
+    "admin",
+    "all",
+    "connection",
+    "pubsub",
+    "read",
+    "scripting",
+    "transaction",
+    "write",
+];
+
+/// The users of the server, indexed by name.
+#[derive(Debug)]
+pub(crate) struct Acl {
+    users: BTreeMap<String, User>,
+}
+
+/// A user clients authenticate as.
+#[derive(Debug, Clone)]
+pub(crate) struct User {
+    /// Set by `on`. Clients may not authenticate as a disabled user.
+    enabled: bool,
+
+    /// Set by `nopass`, in which case any password is accepted.
+    nopass: bool,
+
+    /// SHA-256 digests of the passwords, in lowercase hexadecimal.
+    passwords: Vec<String>,
+
+    /// Rules allowing or denying commands, such as `+get` or `-@write`, in
+    /// the order they were applied.
+    commands: Vec<String>,
+
+    /// Patterns of the keys the user may access.
+    keys: Vec<String>,
+
+    /// Patterns of the channels the user may publish or subscribe to.
+    channels: Vec<String>,
+
+    /// Rates shared by the clients authenticated as the user.
+    limits: RateLimits,
+}
+
+impl Acl {
+    /// Create the access control lists, holding the `default` user which
+    /// requires no password and may run any command.
+    pub(crate) fn new() -> Acl {
+        let default = User {
+            enabled: true,
+            nopass: true,
+            passwords: vec![],
+            commands: vec!["+@all".to_string()],
+            keys: vec!["*".to_string()],
+            channels: vec!["*".to_string()],
+            limits: RateLimits::default(),
+        };
+
+        let mut users = BTreeMap::new();
+        users.insert(DEFAULT_USER.to_string(), default);
+        Acl { users }
+    }
+
+    /// Returns the user `name`, if it exists.
+    pub(crate) fn user(&self, name: &str) -> Option<&User> {
+        self.users.get(name)
+    }
+
+    /// Returns the name of the user new clients are authenticated as: the
+    /// `default` user, if it requires no password.
+    pub(crate) fn initial_user(&self) -> Option<String> {
+        self.user(DEFAULT_USER)
+            .filter(|user| user.is_nopass())
+            .map(|_| DEFAULT_USER.to_string())
+    }
+
+    /// Returns the names of the users.
+    pub(crate) fn usernames(&self) -> impl Iterator<Item = &str> {
+        self.users.keys().map(String::as_str)
+    }
+
+    /// Apply `rules` to the user `name`, creating it if it does not exist.
+    ///
+    /// The rules are applied all at once: if one of them is invalid, the user
+    /// is left unchanged.
+    pub(crate) fn set_user(&mut self, name: &str, rules: &[String]) -> Result<(), String> {
+        let mut user = self.users.get(name).cloned().unwrap_or_else(User::new);
+
+        for rule in rules {
+            user.apply(rule).map_err(|reason| {
+                format!("ERR Error in ACL SETUSER modifier '{}': {}", rule, reason)
+            })?;
+        }
+
+        self.users.insert(name.to_string(), user);
+        Ok(())
+    }
+
+    /// Delete the users `names`, returning the number of them that existed.
+    pub(crate) fn delete_users(&mut self, names: &[String]) -> Result<usize, String> {
+        if names.iter().any(|name| name == DEFAULT_USER) {
+            return Err("ERR The 'default' user cannot be removed".to_string());
+        }
+
+        Ok(names
+            .iter()
+            .filter(|name| self.users.remove(*name).is_some())
+            .count())
+    }
+
+    /// Set the only password of the `default` user to `password`, or let
+    /// clients authenticate as it without a password if `None`.
+    pub(crate) fn set_requirepass(&mut self, password: Option<&str>) {
+        let default = self.users.get_mut(DEFAULT_USER).unwrap();
+        default.passwords.clear();
+
+        match password {
+            Some(password) => {
+                default.nopass = false;
+                default.passwords.push(sha256_hex(password.as_bytes()));
+            }
+            None => default.nopass = true,
+        }
+    }
+}
+
+impl User {
+    /// Create a disabled user, with no password, which may not run any
+    /// command.
+    fn new() -> User {
+        User {
+            enabled: false,
+            nopass: false,
+            passwords: vec![],
+            commands: vec![],
+            keys: vec![],
+            channels: vec![],
+            limits: RateLimits::default(),
+        }
+    }
+
+    /// Returns `true` if clients may authenticate as the user with `password`.
+    pub(crate) fn accepts(&self, password: &str) -> bool {
+        self.enabled && (self.nopass || self.passwords.contains(&sha256_hex(password.as_bytes())))
+    }
+
+    /// Returns `true` if clients may authenticate as the user without giving
+    /// a password.
+    pub(crate) fn is_nopass(&self) -> bool {
+        self.enabled && self.nopass
+    }
+
+    /// Returns `Err` with the `NOPERM` error to reply if the user may not run
+    /// `cmd`, the user being named `name`.
+    pub(crate) fn check(&self, name: &str, cmd: &Command) -> Result<(), String> {
+        if !self.allows(cmd) {
+            return Err(format!(
+                "NOPERM User {} has no permissions to run the '{}' command",
+                name,
+                cmd.get_name()
+            ));
+        }
+
+        let matches_any = |patterns: &[String], name: &str| {
+            patterns
+                .iter()
+                .any(|pattern| glob_matches(pattern.as_bytes(), name.as_bytes()))
+        };
+
+        // Shard channels are returned as keys as well, they are only checked
+        // as channels.
+        let channels = cmd.channels();
+        let keys = if channels.is_empty() {
+            cmd.keys()
+        } else {
+            vec![]
+        };
+
+        if !keys.iter().all(|key| matches_any(&self.keys, key)) {
+            return Err("NOPERM No permissions to access a key".to_string());
+        }
+
+        if !channels
+            .iter()
+            .all(|channel| matches_any(&self.channels, channel))
+        {
+            return Err("NOPERM No permissions to access a channel".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the rules on the commands allow `cmd`.
+    fn allows(&self, cmd: &Command) -> bool {
+        // Unknown commands are rejected with their own error.
+        if let Command::Unknown(_) = cmd {
+            return true;
+        }
+
+        // The last rule matching the command decides.
+        for rule in self.commands.iter().rev() {
+            let (allow, name) = rule.split_at(1);
+            let matched = match name.strip_prefix('@') {
+                Some(category) => category == "all" || cmd.categories().contains(&category),
+                None => name == cmd.get_name(),
+            };
+            if matched {
+                return allow == "+";
+            }
+        }
+
+        false
+    }
+
+    /// Returns the flags of the user: `on` or `off`, followed by `nopass` if
+    /// set.
+    pub(crate) fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+
+    /// Returns the rates shared by the clients authenticated as the user.
+    pub(crate) fn limits(&self) -> RateLimits {
+        self.limits
+    }
+
+    /// Returns the SHA-256 digests of the passwords, in hexadecimal.
+    pub(crate) fn passwords(&self) -> &[String] {
+        &self.passwords
+    }
+
+    /// Returns the rules on the commands, separated by spaces.
+    pub(crate) fn commands(&self) -> String {
+        // Commands matching no rule are denied, as if the first rule were
+        // `-@all`.
+        let mut commands = vec!["-@all"];
+        if self
+            .commands
+            .first()
+            .is_some_and(|rule| rule[1..] == *"@all")
+        {
+            commands.clear();
+        }
+        commands.extend(self.commands.iter().map(String::as_str));
+        commands.join(" ")
+    }
+
+    /// Returns the patterns of the keys, each prefixed with `~`.
+    pub(crate) fn keys(&self) -> String {
+        prefixed('~', &self.keys)
+    }
+
+    /// Returns the patterns of the channels, each prefixed with `&`.
+    pub(crate) fn channels(&self) -> String {
+        prefixed('&', &self.channels)
+    }
+
+    /// Returns the rules recreating the user, as listed by `ACL LIST`.
+    pub(crate) fn rules(&self) -> String {
+        let mut rules = self.flags().join(" ");
+
+        for password in &self.passwords {
+            rules.push_str(" #");
+            rules.push_str(password);
+        }
+
+        if !self.keys.is_empty() {
+            rules.push(' ');
+            rules.push_str(&self.keys());
+        }
+
+        if self.channels.is_empty() {
+            rules.push_str(" resetchannels");
+        } else {
+            rules.push(' ');
+            rules.push_str(&self.channels());
+        }
+
+        rules.push(' ');
+        rules.push_str(&self.commands());
+
+        if self.limits.commands != 0 {
+            rules.push_str(&format!(" ratelimit-commands={}", self.limits.commands));
+        }
+        if self.limits.bytes != 0 {
+            rules.push_str(&format!(" ratelimit-bytes={}", self.limits.bytes));
+        }
+        rules
+    }
+
+    /// Apply a single rule of `ACL SETUSER`, returning the reason it is
+    /// invalid otherwise.
+    fn apply(&mut self, rule: &str) -> Result<(), &'static str> {
+        match &rule.to_lowercase()[..] {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allcommands" => return self.apply("+@all"),
+            "nocommands" => return self.apply("-@all"),
+            "allkeys" => return self.apply("~*"),
+            "resetkeys" => self.keys.clear(),
+            "allchannels" => return self.apply("&*"),
+            "resetchannels" => self.channels.clear(),
+            "reset" => *self = User::new(),
+            rule if rule.starts_with("ratelimit-") => {
+                let (name, rate) = rule.split_once('=').ok_or("Syntax error")?;
+                let rate = rate.parse().map_err(|_| "Invalid rate limit")?;
+                match name {
+                    "ratelimit-commands" => self.limits.commands = rate,
+                    "ratelimit-bytes" => self.limits.bytes = rate,
+                    _ => return Err("Syntax error"),
+                }
+            }
+            _ => {
+                let (prefix, value) = rule.split_at(rule.chars().next().map_or(0, char::len_utf8));
+                match prefix {
+                    ">" => self.add_password(sha256_hex(value.as_bytes())),
+                    "<" => self.remove_password(sha256_hex(value.as_bytes()))?,
+                    "#" => self.add_password(password_hash(value)?),
+                    "!" => self.remove_password(password_hash(value)?)?,
+                    "~" => self.keys.push(value.to_string()),
+                    "&" => self.channels.push(value.to_string()),
+                    "+" | "-" => {
+                        let name = value.to_lowercase();
+                        match name.strip_prefix('@') {
+                            Some(category) if !CATEGORIES.contains(&category) => {
+                                return Err("Unknown command or category name in ACL");
+                            }
+                            // Allowing or denying every command overrides the
+                            // previous rules, which are dropped.
+                            Some("all") => self.commands.clear(),
+                            Some(_) => {}
+                            None if !Command::exists(&name) => {
+                                return Err("Unknown command or category name in ACL");
+                            }
+                            None => {}
+                        }
+                        self.commands.push(format!("{}{}", prefix, name));
+                    }
+                    _ => return Err("Syntax error"),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the password whose SHA-256 digest is `hash`, which disables
+    /// `nopass`.
+    fn add_password(&mut self, hash: String) {
+        if !self.passwords.contains(&hash) {
+            self.passwords.push(hash);
+        }
+        self.nopass = false;
+    }
+
+    /// Remove the password whose SHA-256 digest is `hash`.
+    fn remove_password(&mut self, hash: String) -> Result<(), &'static str> {
+        if !self.passwords.contains(&hash) {
+            return Err("no such password");
+        }
+        self.passwords.retain(|p| *p != hash);
+        Ok(())
+    }
+}
+
+/// Returns the SHA-256 digest of a password given by a `#<hash>` or
+/// `!<hash>` rule, as 64 lowercase hexadecimal digits.
+fn password_hash(hash: &str) -> Result<String, &'static str> {
+    if hash.len() != 64 || !hash.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err("The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters");
+    }
+    Ok(hash.to_string())
+}
+
+/// Returns `patterns` separated by spaces, each prefixed with `prefix`.
+fn prefixed(prefix: char, patterns: &[String]) -> String {
+    patterns
+        .iter()
+        .map(|pattern| format!("{}{}", prefix, pattern))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -2,6 +2,7 @@ use mini_redis::{client, DEFAULT_PORT};
 
 use bytes::Bytes;
 use std::num::ParseIntError;
+use std::path::PathBuf;
 use std::str;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -17,6 +18,14 @@ struct Cli {
 
     #[structopt(name = "port", long = "--port", default_value = DEFAULT_PORT)]
     port: String,
+
+    /// Password to authenticate with, if the server requires one.
+    #[structopt(name = "password", long = "--pass")]
+    pass: Option<String>,
+
+    /// Unix domain socket to connect to, instead of the host and port.
+    #[structopt(name = "socket", long = "--socket", parse(from_os_str))]
+    socket: Option<PathBuf>,
 }
 
 #[derive(StructOpt, Debug)]
@@ -61,8 +70,16 @@ async fn main() -> mini_redis::Result<()> {
     // Get the remote address to connect to
     let addr = format!("{}:{}", cli.host, cli.port);
 
-    // Establish a connection
-    let mut client = client::connect(&addr).await?;
+    // Establish a connection, over the Unix domain socket if one is given
+    let mut client = match &cli.socket {
+        Some(path) => client::connect_unix(path).await?,
+        None => client::connect(&addr).await?,
+    };
+
+    // Authenticate if a password is given
+    if let Some(password) = &cli.pass {
+        client.auth(password).await?;
+    }
 
     // Process the requested command
     match cli.command {
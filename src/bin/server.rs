@@ -5,31 +5,614 @@
 //! `mini_redis::server`.
 //!
 //! The `clap` crate is used for parsing arguments.
+//!
+//! Settings may also be read from a configuration file in the format of
+//! `redis.conf`, whose path is the first argument. Each line holds a directive
+//! followed by its arguments, and lines starting with `#` are comments. The
+//! options given on the command line override the settings of the file.
+//!
+//! Besides the directives setting up the server, the file may set the
+//! parameters of `CONFIG SET`, such as `maxmemory` or `slowlog-max-len`.
 
-use mini_redis::{server, DEFAULT_PORT};
+use mini_redis::{server, DEFAULT_DATABASES, DEFAULT_PORT};
 
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::time::Duration;
 use structopt::StructOpt;
-use tokio::net::TcpListener;
 use tokio::signal;
+use tracing::Level;
 
 #[tokio::main]
 pub async fn main() -> mini_redis::Result<()> {
+    let cli = Cli::from_args();
+
+    let file = match &cli.config {
+        Some(path) => FileConfig::load(path)?,
+        None => FileConfig::default(),
+    };
+
     // enable logging
     // see https://docs.rs/tracing for more info
-    tracing_subscriber::fmt::try_init()?;
+    match cli.loglevel.or(file.loglevel) {
+        Some(level) => tracing_subscriber::fmt().with_max_level(level).try_init()?,
+        None => tracing_subscriber::fmt::try_init()?,
+    }
 
-    let cli = Cli::from_args();
-    let port = cli.port.as_deref().unwrap_or(DEFAULT_PORT);
+    #[cfg(feature = "tls")]
+    let tls = tls_config(&cli, &file)?;
+
+    let bind = match cli.bind {
+        bind if bind.is_empty() => file.bind.unwrap_or_else(|| vec!["127.0.0.1".to_string()]),
+        bind => bind,
+    };
+    let port = cli.port.or(file.port);
+    let port = port.as_deref().unwrap_or(DEFAULT_PORT);
+    let port = port
+        .parse()
+        .map_err(|_| format!("invalid port `{}`", port))?;
+
+    let config = server::Config {
+        databases: cli
+            .databases
+            .or(file.databases)
+            .unwrap_or(DEFAULT_DATABASES),
+        snapshot_path: cli.dbfilename.or(file.dbfilename),
+        save_points: match cli.save {
+            Some(interval) => vec![(Duration::from_secs(interval), 1)],
+            None => file
+                .save
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(interval, changes)| (Duration::from_secs(interval), changes))
+                .collect(),
+        },
+        aof_path: cli.appendfilename.or(file.appendfilename),
+        aof_fsync: cli.appendfsync.or(file.appendfsync).unwrap_or_default(),
+        replica_of: cli.replicaof.or(file.replicaof),
+        cluster_enabled: cli
+            .cluster_enabled
+            .or(file.cluster_enabled)
+            .unwrap_or(false),
+        requirepass: cli.requirepass.or(file.requirepass),
+        #[cfg(feature = "tls")]
+        tls,
+        unixsocket: cli.unixsocket.or(file.unixsocket),
+        rename_commands: file.rename_commands,
+        protected_mode: cli.protected_mode.or(file.protected_mode).unwrap_or(true),
+        tcp_keepalive: cli.tcp_keepalive.or(file.tcp_keepalive).unwrap_or(300),
+        tcp_nodelay: cli.tcp_nodelay.or(file.tcp_nodelay).unwrap_or(true),
+        timeout: cli.timeout.or(file.timeout).unwrap_or(0),
+        parameters: file.parameters,
+    };
+
+    // Bind a TCP listener to each address
+    let listeners = server::bind(&bind, port).await?;
 
-    // Bind a TCP listener
-    let listener = TcpListener::bind(&format!("127.0.0.1:{}", port)).await?;
+    server::run_with_config(listeners, config, signal::ctrl_c()).await
+}
+
+/// Returns the TLS configuration of the server, `None` unless a certificate
+/// and its key are given.
+#[cfg(feature = "tls")]
+fn tls_config(cli: &Cli, file: &FileConfig) -> mini_redis::Result<Option<server::TlsConfig>> {
+    let cert_file = cli.tls_cert_file.as_ref().or(file.tls_cert_file.as_ref());
+    let key_file = cli.tls_key_file.as_ref().or(file.tls_key_file.as_ref());
 
-    server::run(listener, signal::ctrl_c()).await
+    match (cert_file, key_file) {
+        (Some(cert_path), Some(key_path)) => Ok(Some(server::TlsConfig {
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            ca_cert_path: cli
+                .tls_ca_cert_file
+                .as_ref()
+                .or(file.tls_ca_cert_file.as_ref())
+                .cloned(),
+            auth_clients: cli
+                .tls_auth_clients
+                .or(file.tls_auth_clients)
+                .unwrap_or_default(),
+        })),
+        (None, None) => Ok(None),
+        _ => Err("TLS requires both --tls-cert-file and --tls-key-file".into()),
+    }
 }
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "mini-redis-server", version = env!("CARGO_PKG_VERSION"), author = env!("CARGO_PKG_AUTHORS"), about = "A Redis server")]
 struct Cli {
+    /// Configuration file in the format of `redis.conf`. The options given
+    /// on the command line override its settings.
+    #[structopt(name = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Addresses to listen on, IPv4 or IPv6. Defaults to `127.0.0.1`.
+    #[structopt(name = "bind", long = "--bind")]
+    bind: Vec<String>,
+
     #[structopt(name = "port", long = "--port")]
     port: Option<String>,
+
+    #[structopt(name = "databases", long = "--databases")]
+    databases: Option<usize>,
+
+    /// File the snapshots are saved to and loaded from. Snapshots are disabled
+    /// unless a file is given.
+    #[structopt(name = "dbfilename", long = "--dbfilename", parse(from_os_str))]
+    dbfilename: Option<PathBuf>,
+
+    /// Interval between two snapshots, in seconds, which are only saved if
+    /// the databases were modified. It replaces the save points of the
+    /// configuration file. Without them, a snapshot is only saved when the
+    /// server shuts down.
+    #[structopt(name = "save", long = "--save")]
+    save: Option<u64>,
+
+    /// File the commands modifying the databases are appended to and replayed
+    /// from. The AOF is disabled unless a file is given.
+    #[structopt(name = "appendfilename", long = "--appendfilename", parse(from_os_str))]
+    appendfilename: Option<PathBuf>,
+
+    /// When the AOF is flushed to disk: `always`, `everysec` or `no`. Defaults
+    /// to `everysec`.
+    #[structopt(name = "appendfsync", long = "--appendfsync")]
+    appendfsync: Option<server::Fsync>,
+
+    #[structopt(name = "replicaof", long = "--replicaof")]
+    replicaof: Option<String>,
+
+    /// Run in cluster mode, serving the hash slots assigned with `CLUSTER
+    /// ADDSLOTS`: `yes` or `no`. Defaults to `no`.
+    #[structopt(name = "cluster-enabled", long = "--cluster-enabled", parse(try_from_str = parse_yes_no))]
+    cluster_enabled: Option<bool>,
+
+    /// Password clients must authenticate with using `AUTH`.
+    #[structopt(name = "requirepass", long = "--requirepass")]
+    requirepass: Option<String>,
+
+    /// PEM file holding the certificate the connections with clients are
+    /// encrypted with. Clients connect without TLS unless it is given.
+    #[cfg(feature = "tls")]
+    #[structopt(name = "tls-cert-file", long = "--tls-cert-file", parse(from_os_str))]
+    tls_cert_file: Option<PathBuf>,
+
+    /// PEM file holding the private key of the certificate.
+    #[cfg(feature = "tls")]
+    #[structopt(name = "tls-key-file", long = "--tls-key-file", parse(from_os_str))]
+    tls_key_file: Option<PathBuf>,
+
+    /// PEM file holding the certificates of the authorities the certificates
+    /// of the clients are verified against.
+    #[cfg(feature = "tls")]
+    #[structopt(
+        name = "tls-ca-cert-file",
+        long = "--tls-ca-cert-file",
+        parse(from_os_str)
+    )]
+    tls_ca_cert_file: Option<PathBuf>,
+
+    /// Whether clients present a certificate: `yes`, `no` or `optional`.
+    /// Defaults to `yes`.
+    #[cfg(feature = "tls")]
+    #[structopt(name = "tls-auth-clients", long = "--tls-auth-clients")]
+    tls_auth_clients: Option<server::ClientAuth>,
+
+    /// Path of a Unix domain socket to accept connections on, in addition to
+    /// the TCP port.
+    #[structopt(name = "unixsocket", long = "--unixsocket", parse(from_os_str))]
+    unixsocket: Option<PathBuf>,
+
+    /// Only accept the clients connected from the loopback interface while
+    /// no password is set for the default user, unless bound to a loopback
+    /// address: `yes` or `no`. Defaults to `yes`.
+    #[structopt(name = "protected-mode", long = "--protected-mode", parse(try_from_str = parse_yes_no))]
+    protected_mode: Option<bool>,
+
+    /// Seconds a client may stay idle before TCP keepalive probes are sent
+    /// to check it is still alive, `0` to send none. Defaults to `300`.
+    #[structopt(name = "tcp-keepalive", long = "--tcp-keepalive")]
+    tcp_keepalive: Option<u64>,
+
+    /// Set `TCP_NODELAY` on the sockets of the clients: `yes` or `no`.
+    /// Defaults to `yes`.
+    #[structopt(name = "tcp-nodelay", long = "--tcp-nodelay", parse(try_from_str = parse_yes_no))]
+    tcp_nodelay: Option<bool>,
+
+    /// Seconds after which idle clients are disconnected, `0` to never
+    /// disconnect them. Defaults to `0`.
+    #[structopt(name = "timeout", long = "--timeout")]
+    timeout: Option<u64>,
+
+    /// Verbosity of the logs: `debug`, `verbose`, `notice` or `warning`.
+    #[structopt(name = "loglevel", long = "--loglevel", parse(try_from_str = parse_loglevel))]
+    loglevel: Option<Level>,
+}
+
+/// Settings read from a configuration file, `None` for the directives the
+/// file does not hold.
+#[derive(Debug, Default)]
+struct FileConfig {
+    bind: Option<Vec<String>>,
+    port: Option<String>,
+    databases: Option<usize>,
+    dbfilename: Option<PathBuf>,
+    /// Save points, as pairs of an interval in seconds and a number of
+    /// changes, none if empty.
+    save: Option<Vec<(u64, u64)>>,
+    appendfilename: Option<PathBuf>,
+    appendfsync: Option<server::Fsync>,
+    replicaof: Option<String>,
+    cluster_enabled: Option<bool>,
+    requirepass: Option<String>,
+    unixsocket: Option<PathBuf>,
+    loglevel: Option<Level>,
+    rename_commands: Vec<(String, String)>,
+    protected_mode: Option<bool>,
+    tcp_keepalive: Option<u64>,
+    tcp_nodelay: Option<bool>,
+    timeout: Option<u64>,
+    #[cfg(feature = "tls")]
+    tls_cert_file: Option<PathBuf>,
+    #[cfg(feature = "tls")]
+    tls_key_file: Option<PathBuf>,
+    #[cfg(feature = "tls")]
+    tls_ca_cert_file: Option<PathBuf>,
+    #[cfg(feature = "tls")]
+    tls_auth_clients: Option<server::ClientAuth>,
+    /// Parameters of `CONFIG SET`, with their value.
+    parameters: Vec<(String, String)>,
+}
+
+impl FileConfig {
+    /// Read the configuration file at `path`.
+    ///
+    /// Returns `Err` if the file cannot be read, or if a directive is unknown
+    /// or has invalid arguments.
+    fn load(path: &Path) -> mini_redis::Result<FileConfig> {
+        let content = fs::read_to_string(path)?;
+
+        let mut config = FileConfig::default();
+        let mut appendonly = false;
+        let mut appendfilename = PathBuf::from("appendonly.aof");
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let args = split_args(line)
+                .map_err(|reason| format!("{}:{}: {}", path.display(), i + 1, reason))?;
+            let (directive, args) = match args.split_first() {
+                Some((directive, args)) => (directive, args),
+                None => continue,
+            };
+
+            let error = |reason: &str| -> mini_redis::Error {
+                format!(
+                    "{}:{}: {} for `{}`",
+                    path.display(),
+                    i + 1,
+                    reason,
+                    directive
+                )
+                .into()
+            };
+            let arg = || match args {
+                [arg] => Ok(arg.clone()),
+                _ => Err(error("wrong number of arguments")),
+            };
+            let yes_no = || parse_yes_no(&arg()?).map_err(|_| error("expected `yes` or `no`"));
+
+            match &directive.to_lowercase()[..] {
+                "bind" if args.is_empty() => return Err(error("wrong number of arguments")),
+                "bind" => config.bind = Some(args.to_vec()),
+                "port" => config.port = Some(arg()?),
+                "databases" => {
+                    config.databases = Some(arg()?.parse().map_err(|_| error("invalid number"))?)
+                }
+                "dbfilename" => config.dbfilename = Some(arg()?.into()),
+                // `save <seconds> <changes> [<seconds> <changes> ...]` adds
+                // save points, which accumulate over the lines. A snapshot is
+                // saved once `seconds` elapsed since the last one, if the
+                // databases were modified at least `changes` times. `save ""`
+                // removes the save points, disabling the periodic snapshots.
+                "save" => match args {
+                    [points] if points.is_empty() => config.save = Some(vec![]),
+                    [_, _, ..] if args.len() % 2 == 0 => {
+                        for point in args.chunks(2) {
+                            let seconds = point[0].parse().map_err(|_| error("invalid number"))?;
+                            let changes = point[1].parse().map_err(|_| error("invalid number"))?;
+                            config
+                                .save
+                                .get_or_insert_with(Vec::new)
+                                .push((seconds, changes));
+                        }
+                    }
+                    _ => return Err(error("wrong number of arguments")),
+                },
+                "appendonly" => appendonly = yes_no()?,
+                "appendfilename" => appendfilename = arg()?.into(),
+                "appendfsync" => config.appendfsync = Some(arg()?.parse()?),
+                "replicaof" | "slaveof" => match args {
+                    [host, port] => config.replicaof = Some(format!("{}:{}", host, port)),
+                    _ => return Err(error("wrong number of arguments")),
+                },
+                "cluster-enabled" => config.cluster_enabled = Some(yes_no()?),
+                "protected-mode" => config.protected_mode = Some(yes_no()?),
+                "tcp-keepalive" => {
+                    config.tcp_keepalive =
+                        Some(arg()?.parse().map_err(|_| error("invalid number"))?)
+                }
+                "tcp-nodelay" => config.tcp_nodelay = Some(yes_no()?),
+                "timeout" => {
+                    config.timeout = Some(arg()?.parse().map_err(|_| error("invalid number"))?)
+                }
+                "requirepass" => config.requirepass = Some(arg()?),
+                "unixsocket" => config.unixsocket = Some(arg()?.into()),
+                "loglevel" => config.loglevel = Some(parse_loglevel(&arg()?)?),
+                // `rename-command <command> ""` disables the command.
+                "rename-command" => match args {
+                    [name, new_name] => config
+                        .rename_commands
+                        .push((name.clone(), new_name.clone())),
+                    _ => return Err(error("wrong number of arguments")),
+                },
+                #[cfg(feature = "tls")]
+                "tls-cert-file" => config.tls_cert_file = Some(arg()?.into()),
+                #[cfg(feature = "tls")]
+                "tls-key-file" => config.tls_key_file = Some(arg()?.into()),
+                #[cfg(feature = "tls")]
+                "tls-ca-cert-file" => config.tls_ca_cert_file = Some(arg()?.into()),
+                #[cfg(feature = "tls")]
+                "tls-auth-clients" => config.tls_auth_clients = Some(arg()?.parse()?),
+                // The other directives set the parameters of `CONFIG SET`.
+                name => {
+                    let value = args.join(" ");
+                    server::check_parameter(name, &value).map_err(|reason| match reason {
+                        "unknown parameter" => error("unknown directive"),
+                        reason => error(reason),
+                    })?;
+                    config.parameters.push((name.to_string(), value));
+                }
+            }
+        }
+
+        if appendonly {
+            config.appendfilename = Some(appendfilename);
+        }
+
+        Ok(config)
+    }
+}
+
+/// Split a line of a configuration file into its arguments, separated by
+/// whitespace, as Redis does. Arguments may be enclosed in double quotes, in
+/// which the escape sequences `\n`, `\r`, `\t`, `\b`, `\a` and `\xHH` are
+/// recognized, a backslash escaping any other character, or in single
+/// quotes, in which only `\'` is. `""` is an empty argument.
+///
+/// Returns `Err` if a quote is not closed, or not followed by whitespace.
+fn split_args(line: &str) -> Result<Vec<String>, &'static str> {
+    let mut args = vec![];
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(args);
+        }
+
+        let mut arg = vec![];
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            let quote = match c {
+                '"' | '\'' => c,
+                c => {
+                    push_char(&mut arg, c);
+                    continue;
+                }
+            };
+
+            loop {
+                match (chars.next(), quote) {
+                    (Some(c), _) if c == quote => break,
+                    (Some('\\'), '"') => match chars.next() {
+                        Some('x') => match hex_byte(&mut chars) {
+                            Some(byte) => arg.push(byte),
+                            None => arg.push(b'x'),
+                        },
+                        Some('n') => arg.push(b'\n'),
+                        Some('r') => arg.push(b'\r'),
+                        Some('t') => arg.push(b'\t'),
+                        Some('b') => arg.push(0x08),
+                        Some('a') => arg.push(0x07),
+                        Some(c) => push_char(&mut arg, c),
+                        None => return Err("unbalanced quotes"),
+                    },
+                    (Some('\\'), _) if chars.next_if_eq(&'\'').is_some() => arg.push(b'\''),
+                    (Some(c), _) => push_char(&mut arg, c),
+                    (None, _) => return Err("unbalanced quotes"),
+                }
+            }
+
+            if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                return Err("closing quote must be followed by a space");
+            }
+        }
+
+        args.push(String::from_utf8(arg).map_err(|_| "invalid UTF-8")?);
+    }
+}
+
+/// Append `c` to `arg`, encoded in UTF-8.
+fn push_char(arg: &mut Vec<u8>, c: char) {
+    arg.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+/// Read the two hexadecimal digits of a `\xHH` escape sequence, leaving
+/// `chars` untouched if they are not.
+fn hex_byte(chars: &mut Peekable<Chars<'_>>) -> Option<u8> {
+    let mut lookahead = chars.clone();
+    let high = lookahead.next()?.to_digit(16)?;
+    let low = lookahead.next()?.to_digit(16)?;
+    *chars = lookahead;
+    Some((high * 16 + low) as u8)
+}
+
+/// Parse `yes` or `no` into a boolean.
+fn parse_yes_no(src: &str) -> mini_redis::Result<bool> {
+    match &src.to_lowercase()[..] {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("expected `yes` or `no`, got `{}`", src).into()),
+    }
+}
+
+/// Parse a Redis log level into the most verbose level of the logs.
+fn parse_loglevel(src: &str) -> mini_redis::Result<Level> {
+    match &src.to_lowercase()[..] {
+        "debug" => Ok(Level::TRACE),
+        "verbose" => Ok(Level::DEBUG),
+        "notice" => Ok(Level::INFO),
+        "warning" => Ok(Level::WARN),
+        _ => Err(format!("invalid log level `{}`", src).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load a configuration file holding `content`.
+    fn load(name: &str, content: &str) -> mini_redis::Result<FileConfig> {
+        let path =
+            std::env::temp_dir().join(format!("mini-redis-{}-{}.conf", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        let config = FileConfig::load(&path);
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn split_args_quotes() {
+        assert!(split_args("  ").unwrap().is_empty());
+        assert_eq!(
+            vec!["bind", "127.0.0.1", "-::1"],
+            split_args("bind 127.0.0.1 -::1").unwrap()
+        );
+        assert_eq!(vec!["save", ""], split_args("save \"\"").unwrap());
+        assert_eq!(
+            vec!["requirepass", "a b", "it's"],
+            split_args("requirepass \"a b\" 'it\\'s'").unwrap()
+        );
+        assert_eq!(
+            vec!["a\tb\"A\\x"],
+            split_args("\"a\\tb\\\"\\x41\\\\\\x\"").unwrap()
+        );
+        assert_eq!(vec!["foobar baz"], split_args("foo\"bar baz\"").unwrap());
+        assert_eq!(vec!["a\\b"], split_args("'a\\b'").unwrap());
+
+        assert!(split_args("requirepass \"secret").is_err());
+        assert!(split_args("requirepass 'secret").is_err());
+        assert!(split_args("requirepass \"secret\\\"").is_err());
+        assert!(split_args("requirepass \"a\"b").is_err());
+    }
+
+    #[test]
+    fn load_directives() {
+        let config = load(
+            "directives",
+            "# A comment, with an 'unbalanced quote\n\
+             \n\
+             bind 127.0.0.1 -::1\n\
+             \x20 port 7000\n\
+             requirepass \"a secret\"\n\
+             save 900 1\n\
+             save 300 10 60 10000\n\
+             appendonly yes\n\
+             cluster-enabled no\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Some(vec!["127.0.0.1".to_string(), "-::1".to_string()]),
+            config.bind
+        );
+        assert_eq!(Some("7000".to_string()), config.port);
+        assert_eq!(Some("a secret".to_string()), config.requirepass);
+        assert_eq!(Some(vec![(900, 1), (300, 10), (60, 10000)]), config.save);
+        assert_eq!(Some(PathBuf::from("appendonly.aof")), config.appendfilename);
+        assert_eq!(Some(false), config.cluster_enabled);
+
+        // `save ""` removes the save points, `appendfilename` alone does not
+        // enable the AOF
+        let config = load(
+            "disabled",
+            "save 900 1\nsave \"\"\nappendfilename log.aof\nappendonly no\n",
+        )
+        .unwrap();
+        assert_eq!(Some(vec![]), config.save);
+        assert_eq!(None, config.appendfilename);
+        assert_eq!(None, config.cluster_enabled);
+
+        let config = load("appendonly", "appendfilename log.aof\nappendonly yes\n").unwrap();
+        assert_eq!(Some(PathBuf::from("log.aof")), config.appendfilename);
+    }
+
+    #[test]
+    fn load_parameters() {
+        let config = load(
+            "parameters",
+            "maxmemory 100mb\n\
+             maxmemory-policy allkeys-lru\n\
+             maxclients 100\n\
+             maxclients-policy queue\n\
+             notify-keyspace-events KEA\n\
+             slowlog-max-len 10\n\
+             latency-monitor-threshold 5\n\
+             ratelimit-commands 100\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                ("maxmemory", "100mb"),
+                ("maxmemory-policy", "allkeys-lru"),
+                ("maxclients", "100"),
+                ("maxclients-policy", "queue"),
+                ("notify-keyspace-events", "KEA"),
+                ("slowlog-max-len", "10"),
+                ("latency-monitor-threshold", "5"),
+                ("ratelimit-commands", "100"),
+            ],
+            config
+                .parameters
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        let err = load("invalid", "slowlog-max-len lots\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with(":1: invalid value for `slowlog-max-len`"));
+        assert!(load("arguments", "slowlog-max-len 1 2\n").is_err());
+        assert!(load("policy", "maxmemory-policy sometimes\n").is_err());
+        assert!(load("policy", "maxclients-policy drop\n").is_err());
+    }
+
+    #[test]
+    fn load_errors() {
+        let err = load("unknown", "port 7000\nunknown-directive yes\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with(":2: unknown directive for `unknown-directive`"));
+
+        let err = load("unbalanced", "requirepass \"secret\n").unwrap_err();
+        assert!(err.to_string().ends_with(":1: unbalanced quotes"));
+
+        assert!(load("save", "save 900\n").is_err());
+        assert!(load("save", "save 900 many\n").is_err());
+        assert!(load("appendonly", "appendonly maybe\n").is_err());
+    }
 }
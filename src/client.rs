@@ -2,15 +2,41 @@
 //!
 //! Provides an async connect and methods for issuing the supported commands.
 
-use crate::cmd::{Get, Publish, Set, Subscribe, Unsubscribe};
+use crate::cmd::Client as ClientCommand;
+use crate::cmd::Debug as DebugCommand;
+use crate::cmd::Monitor as MonitorCommand;
+use crate::cmd::{
+    Acl, Append, Asking, Auth, BLMove, BLPop, BRPop, BRPopLPush, BZPopMax, BZPopMin, BgRewriteAof,
+    BgSave, BitCount, BitField, BitFieldOp, BitOp, BitOperation, BitPos, BitUnit, Cluster,
+    Commands, Config, Copy, DbSize, Decr, DecrBy, Del, Discard, Dump, Eval, EvalSha, Exec, Exists,
+    Expire, ExpireAt, ExpireOption, FCall, FCallRo, FlushAll, FlushDb, Function, GeoAdd, GeoDist,
+    GeoOrigin, GeoPos, GeoSearch, GeoShape, GeoUnit, Get, GetBit, GetDel, GetEx, GetRange, HDel,
+    HGet, HGetAll, HIncrBy, HIncrByFloat, HMGet, HRandField, HScan, HSet, HSetNx, Hello, Incr,
+    IncrBy, IncrByFloat, Info, InsertPosition, Keys, LInsert, LLen, LMove, LPop, LPos, LPush,
+    LRange, LRem, LSet, LTrim, LastSave, Latency, Lcs, ListEnd, MGet, MSet, MSetNx, Memory,
+    Migrate, Move, Multi, PExpire, PExpireAt, PSetEx, PSubscribe, PTtl, PUnsubscribe, Persist,
+    PfAdd, PfCount, PfMerge, PubSub, Publish, RPop, RPopLPush, RPush, RandomKey, Rename, RenameNx,
+    ReplicaOf, Reset, Restore, Role, SAdd, SCard, SDiff, SDiffStore, SInter, SInterCard,
+    SInterStore, SIsMember, SMIsMember, SMembers, SPop, SPublish, SRandMember, SRem, SScan,
+    SSubscribe, SUnion, SUnionStore, SUnsubscribe, Save, Scan, Script, Select, Set, SetBit,
+    SetCondition, SetEx, SetNx, SetRange, ShutdownServer, Slowlog, Sort, StrLen, Subscribe, SwapDb,
+    Touch, Ttl, Type, Unlink, Unsubscribe, Unwatch, Wait, Watch, XAck, XAdd, XClaim, XDel, XGroup,
+    XLen, XPending, XRange, XRead, XReadGroup, XTrim, ZAdd, ZAddOption, ZCount, ZIncrBy, ZPopMax,
+    ZPopMin, ZRandMember, ZRange, ZRangeByLex, ZRangeByScore, ZRank, ZRem, ZScan, ZScore,
+};
 use crate::{Connection, Frame};
 
 use async_stream::try_stream;
 use bytes::Bytes;
 use std::io::{Error, ErrorKind};
-use std::time::Duration;
-use tokio::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpStream, ToSocketAddrs, UnixStream};
 use tokio::stream::Stream;
+#[cfg(feature = "tls")]
+use tokio_rustls::{rustls::ClientConfig, webpki::DNSNameRef, TlsConnector};
 use tracing::{debug, instrument};
 
 /// Established connection with a Redis server.
@@ -42,13 +68,137 @@ pub struct Subscriber {
 
     /// The set of channels to which the `Subscriber` is currently subscribed.
     subscribed_channels: Vec<String>,
+
+    /// The set of patterns to which the `Subscriber` is currently subscribed.
+    subscribed_patterns: Vec<String>,
+
+    /// The set of shard channels to which the `Subscriber` is currently
+    /// subscribed.
+    subscribed_shard_channels: Vec<String>,
+}
+
+/// A client that has entered monitor mode.
+///
+/// Once monitoring, clients only receive the commands the server receives.
+/// The `Client` type is transitioned to a `Monitor` type in order to prevent
+/// other methods from being called.
+pub struct Monitor {
+    /// The monitoring client.
+    client: Client,
 }
 
-/// A message received on a subscribed channel.
+/// A message received on a subscribed channel, or on a channel matching a
+/// subscribed pattern.
 #[derive(Debug, Clone)]
 pub struct Message {
     pub channel: String,
     pub content: Bytes,
+
+    /// The pattern the channel matched, if the message was received through
+    /// a pattern subscription.
+    pub pattern: Option<String>,
+}
+
+/// An entry read from a stream.
+#[derive(Debug, Clone)]
+pub struct StreamEntry {
+    pub id: String,
+    pub fields: Vec<(String, Bytes)>,
+}
+
+/// Summary of the pending entries of a stream consumer group.
+#[derive(Debug, Clone)]
+pub struct PendingSummary {
+    pub count: u64,
+    pub smallest_id: Option<String>,
+    pub greatest_id: Option<String>,
+    pub consumers: Vec<(String, u64)>,
+}
+
+/// An entry delivered to a consumer of a stream consumer group but not
+/// acknowledged yet.
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    pub id: String,
+    pub consumer: String,
+    pub idle: Duration,
+    pub delivery_count: u64,
+}
+
+/// A range of the longest common subsequence of two strings which is
+/// consecutive in both strings, as inclusive `(start, end)` offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LcsMatch {
+    pub first: (u64, u64),
+    pub second: (u64, u64),
+}
+
+/// Description of a command implemented by the server.
+#[derive(Debug, Clone)]
+pub struct CommandInfo {
+    pub name: String,
+    /// Number of arguments, including the name of the command. A negative
+    /// arity `-n` means at least `n` arguments.
+    pub arity: i64,
+    pub flags: Vec<String>,
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+}
+
+/// A command recorded by the slow log of the server.
+#[derive(Debug, Clone)]
+pub struct SlowlogEntry {
+    pub id: u64,
+    /// Time at which the command was logged.
+    pub time: SystemTime,
+    /// Execution time of the command.
+    pub duration: Duration,
+    pub args: Vec<Bytes>,
+    /// Address of the client which sent the command.
+    pub addr: String,
+}
+
+/// The latest latency spike of an event, as recorded by the latency monitor
+/// of the server.
+#[derive(Debug, Clone)]
+pub struct LatencySpike {
+    pub event: String,
+    /// Time at which the spike was recorded.
+    pub time: SystemTime,
+    pub latency: Duration,
+    /// Highest latency ever recorded for the event.
+    pub max: Duration,
+}
+
+/// Role of a server in the replication, as returned by `role`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicationRole {
+    /// The server is a primary, whose replication stream reached `offset`.
+    Primary {
+        offset: u64,
+        replicas: Vec<ReplicaInfo>,
+    },
+    /// The server replicates the primary at `host` and `port`. `state` is
+    /// the state of its connection with the primary, `connect`, `connecting`,
+    /// `sync` or `connected`, and `offset` the offset it reached in the
+    /// stream of the primary, if it ever synchronized with it.
+    Replica {
+        host: String,
+        port: u16,
+        state: String,
+        offset: Option<u64>,
+    },
+}
+
+/// A replica connected to a primary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaInfo {
+    /// Host and port the replica listens at.
+    pub host: String,
+    pub port: u16,
+    /// Offset of the replication stream the replica acknowledged.
+    pub offset: u64,
 }
 
 /// Establish a connection with the Redis server located at `addr`.
@@ -86,6 +236,77 @@ pub async fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<Client> {
     Ok(Client { connection })
 }
 
+/// Establish a connection with the Redis server listening on the Unix domain
+/// socket at `path`.
+///
+/// The client behaves the same as one connected over TCP with `connect`.
+pub async fn connect_unix(path: impl AsRef<Path>) -> crate::Result<Client> {
+    let socket = UnixStream::connect(path).await?;
+    let connection = Connection::from_stream(socket);
+
+    Ok(Client { connection })
+}
+
+/// Establish a TLS connection with the Redis server located at `addr`.
+///
+/// `domain` is sent to the server with SNI, and the certificate of the server
+/// is verified against it with the root certificates of `config`. Custom
+/// authorities are trusted by adding their certificates to
+/// `config.root_store`, and `config` also holds the certificate presented to
+/// servers authenticating their clients.
+///
+/// The client behaves the same as one connected with `connect`, and is
+/// turned into a `Subscriber` with `subscribe`, or into a `Buffer` with
+/// `buffer`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mini_redis::{client, rustls};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut config = rustls::ClientConfig::new();
+///     let mut ca = std::io::BufReader::new(std::fs::File::open("ca.crt").unwrap());
+///     config.root_store.add_pem_file(&mut ca).unwrap();
+///
+///     let client = match client::connect_tls("localhost:6379", "localhost", Arc::new(config)).await {
+///         Ok(client) => client,
+///         Err(_) => panic!("failed to establish connection"),
+///     };
+/// # drop(client);
+/// }
+/// ```
+#[cfg(feature = "tls")]
+pub async fn connect_tls<T: ToSocketAddrs>(
+    addr: T,
+    domain: &str,
+    config: Arc<ClientConfig>,
+) -> crate::Result<Client> {
+    let domain = DNSNameRef::try_from_ascii_str(domain)
+        .map_err(|_| format!("invalid domain name `{}`", domain))?;
+
+    let socket = TcpStream::connect(addr).await?;
+    let stream = TlsConnector::from(config).connect(domain, socket).await?;
+    let connection = Connection::from_stream(stream);
+
+    Ok(Client { connection })
+}
+
+/// Establish a connection with the Redis server located at `addr`, then
+/// authenticate with `password`, as servers requiring a password expect.
+///
+/// Returns `Err` if the password is rejected.
+pub async fn connect_with_password<T: ToSocketAddrs>(
+    addr: T,
+    password: &str,
+) -> crate::Result<Client> {
+    let mut client = connect(addr).await?;
+    client.auth(password).await?;
+    Ok(client)
+}
+
 impl Client {
     /// Get the value of key.
     ///
@@ -216,7 +437,95 @@ impl Client {
         self.set_cmd(Set::new(key, value, Some(expiration))).await
     }
 
-    /// The core `SET` logic, used by both `set` and `set_expires.
+    /// Set `key` to hold the given `value` if `condition` is met. When
+    /// `expiration` is given, the value expires after it.
+    ///
+    /// Returns `true` if the value was set.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    /// use mini_redis::cmd::SetCondition;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     // Acquire a lock, only if nobody holds it yet
+    ///     let acquired = client
+    ///         .set_if("lock", "me".into(), SetCondition::Nx, None)
+    ///         .await
+    ///         .unwrap();
+    ///     assert!(acquired);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_if(
+        &mut self,
+        key: &str,
+        value: Bytes,
+        condition: SetCondition,
+        expiration: Option<Duration>,
+    ) -> crate::Result<bool> {
+        let frame = Set::new(key, value, expiration)
+            .condition(condition)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(true),
+            Frame::Null => Ok(false),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, returning the value it previously
+    /// held, if any.
+    #[instrument(skip(self))]
+    pub async fn set_get(&mut self, key: &str, value: Bytes) -> crate::Result<Option<Bytes>> {
+        let frame = Set::new(key, value, None).get(true).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, retaining the time to live
+    /// associated with the key.
+    #[instrument(skip(self))]
+    pub async fn set_keepttl(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None).keep_ttl(true))
+            .await
+    }
+
+    /// Set `key` to hold the given `value`, expiring at the Unix time
+    /// `expires_at`. A time in the past deletes the key.
+    #[instrument(skip(self))]
+    pub async fn set_expires_at(
+        &mut self,
+        key: &str,
+        value: Bytes,
+        expires_at: SystemTime,
+    ) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None).expire_at(expires_at))
+            .await
+    }
+
+    /// The core `SET` logic, used by `set`, `set_expires` and the other `SET`
+    /// variants replying `OK`.
     async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
         // Convert the `Set` command into a frame
         let frame = cmd.into_frame();
@@ -235,11 +544,9 @@ impl Client {
         }
     }
 
-    /// Posts `message` to the given `channel`.
+    /// Removes the specified keys. A key is ignored if it does not exist.
     ///
-    /// Returns the number of subscribers currently listening on the channel.
-    /// There is no guarantee that these subscribers receive the message as they
-    /// may disconnect at any time.
+    /// Returns the number of keys that were removed.
     ///
     /// # Examples
     ///
@@ -252,133 +559,4727 @@ impl Client {
     /// async fn main() {
     ///     let mut client = client::connect("localhost:6379").await.unwrap();
     ///
-    ///     let val = client.publish("foo", "bar".into()).await.unwrap();
-    ///     println!("Got = {:?}", val);
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///
+    ///     let removed = client.del(vec!["foo".into()]).await.unwrap();
+    ///     assert_eq!(removed, 1);
     /// }
     /// ```
     #[instrument(skip(self))]
-    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
-        // Convert the `Publish` command into a frame
-        let frame = Publish::new(channel, message).into_frame();
+    pub async fn del(&mut self, keys: Vec<String>) -> crate::Result<u64> {
+        let frame = Del::new(keys).into_frame();
 
         debug!(request = ?frame);
 
-        // Write the frame to the socket
         self.connection.write_frame(&frame).await?;
 
-        // Read the response
         match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
+            Frame::Integer(response) => Ok(response as u64),
             frame => Err(frame.to_error()),
         }
     }
 
-    /// Subscribes the client to the specified channels.
+    /// Returns the number of `keys` that exist. A key given multiple times is
+    /// counted multiple times.
     ///
-    /// Once a client issues a subscribe command, it may no longer issue any
-    /// non-pub/sub commands. The function consumes `self` and returns a `Subscriber`.
+    /// # Examples
     ///
-    /// The `Subscriber` value is used to receive messages as well as manage the
-    /// list of channels the client is subscribed to.
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///
+    ///     let count = client.exists(vec!["foo".into(), "baz".into()]).await.unwrap();
+    ///     assert_eq!(count, 1);
+    /// }
+    /// ```
     #[instrument(skip(self))]
-    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
-        // Issue the subscribe command to the server and wait for confirmation.
-        // The client will then have been transitioned into the "subscriber"
-        // state and may only issue pub/sub commands from that point on.
-        self.subscribe_cmd(&channels).await?;
+    pub async fn exists(&mut self, keys: Vec<String>) -> crate::Result<u64> {
+        let frame = Exists::new(keys).into_frame();
 
-        // Return the `Subscriber` type
-        Ok(Subscriber {
-            client: self,
-            subscribed_channels: channels,
-        })
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
     }
 
-    /// The core `SUBSCRIBE` logic, used by misc subscribe fns
-    async fn subscribe_cmd(&mut self, channels: &[String]) -> crate::Result<()> {
-        // Convert the `Subscribe` command into a frame
-        let frame = Subscribe::new(&channels).into_frame();
+    /// Sets `fields` in the hash stored at `key`.
+    ///
+    /// Existing fields are overwritten. If `key` does not exist, a new hash is
+    /// created. Returns the number of fields that were added, not counting
+    /// fields that were updated.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.hset("user", vec![("name".into(), "ferris".into())]).await.unwrap();
+    ///
+    ///     let val = client.hget("user", "name").await.unwrap().unwrap();
+    ///     assert_eq!(val, "ferris");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hset(&mut self, key: &str, fields: Vec<(String, Bytes)>) -> crate::Result<u64> {
+        let frame = HSet::new(key, fields).into_frame();
 
         debug!(request = ?frame);
 
-        // Write the frame to the socket
         self.connection.write_frame(&frame).await?;
 
-        // For each channel being subscribed to, the server responds with a
-        // message confirming subscription to that channel.
-        for channel in channels {
-            // Read the response
-            let response = self.read_response().await?;
-
-            // Verify it is confirmation of subscription.
-            match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
-                    // The server responds with an array frame in the form of:
-                    //
-                    // ```
-                    // [ "subscribe", channel, num-subscribed ]
-                    // ```
-                    //
-                    // where channel is the name of the channel and
-                    // num-subscribed is the number of channels that the client
-                    // is currently subscribed to.
-                    [subscribe, schannel, ..]
-                        if *subscribe == "subscribe" && *schannel == channel => {}
-                    _ => return Err(response.to_error()),
-                },
-                frame => return Err(frame.to_error()),
-            };
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
         }
-
-        Ok(())
     }
 
-    /// Reads a response frame from the socket.
+    /// Sets `field` in the hash stored at `key` to `value`, only if the field
+    /// does not exist yet.
     ///
-    /// If an `Error` frame is received, it is converted to `Err`.
-    async fn read_response(&mut self) -> crate::Result<Frame> {
-        let response = self.connection.read_frame().await?;
+    /// Returns `true` if the field was set.
+    #[instrument(skip(self))]
+    pub async fn hsetnx(&mut self, key: &str, field: &str, value: Bytes) -> crate::Result<bool> {
+        let frame = HSetNx::new(key, field, value).into_frame();
 
-        debug!(?response);
+        debug!(request = ?frame);
 
-        match response {
-            // Error frames are converted to `Err`
-            Some(Frame::Error(msg)) => Err(msg.into()),
-            Some(frame) => Ok(frame),
-            None => {
-                // Receiving `None` here indicates the server has closed the
-                // connection without sending a frame. This is unexpected and is
-                // represented as a "connection reset by peer" error.
-                let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+        self.connection.write_frame(&frame).await?;
 
-                Err(err.into())
-            }
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
         }
     }
-}
 
-impl Subscriber {
-    /// Returns the set of channels currently subscribed to.
-    pub fn get_subscribed(&self) -> &[String] {
-        &self.subscribed_channels
+    /// Get the value of `field` in the hash stored at `key`.
+    ///
+    /// If either the key or the field does not exist, `None` is returned.
+    #[instrument(skip(self))]
+    pub async fn hget(&mut self, key: &str, field: &str) -> crate::Result<Option<Bytes>> {
+        let frame = HGet::new(key, field).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
     }
 
-    /// Receive the next message published on a subscribed channel, waiting if
-    /// necessary.
+    /// Removes `fields` from the hash stored at `key`.
     ///
-    /// `None` indicates the subscription has been terminated.
-    pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
-        match self.client.connection.read_frame().await? {
-            Some(mframe) => {
-                debug!(?mframe);
+    /// Returns the number of fields that were removed.
+    #[instrument(skip(self))]
+    pub async fn hdel(&mut self, key: &str, fields: Vec<String>) -> crate::Result<u64> {
+        let frame = HDel::new(key, fields).into_frame();
 
-                match mframe {
-                    Frame::Array(ref frame) => match frame.as_slice() {
-                        [message, channel, content] if *message == "message" => Ok(Some(Message {
-                            channel: channel.to_string(),
-                            content: Bytes::from(content.to_string()),
-                        })),
-                        _ => Err(mframe.to_error()),
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get all fields and values of the hash stored at `key`.
+    ///
+    /// If `key` does not exist, an empty list is returned. The order of the
+    /// returned fields is unspecified.
+    #[instrument(skip(self))]
+    pub async fn hgetall(&mut self, key: &str) -> crate::Result<Vec<(String, Bytes)>> {
+        let frame = HGetAll::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_fields(self.read_response().await?)
+    }
+
+    /// Return a field picked at random from the hash stored at `key`.
+    ///
+    /// Returns `None` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn hrandfield(&mut self, key: &str) -> crate::Result<Option<String>> {
+        let frame = HRandField::new(key, None).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_string(frame).map(Some),
+        }
+    }
+
+    /// Return fields picked at random from the hash stored at `key`.
+    ///
+    /// With a positive `count`, up to `count` distinct fields are returned.
+    /// With a negative `count`, exactly `-count` fields are returned and the
+    /// same field may be returned several times.
+    #[instrument(skip(self))]
+    pub async fn hrandfield_count(&mut self, key: &str, count: i64) -> crate::Result<Vec<String>> {
+        let frame = HRandField::new(key, Some(count)).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_string).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `hrandfield_count`, but each field is returned along with its
+    /// value.
+    #[instrument(skip(self))]
+    pub async fn hrandfield_withvalues(
+        &mut self,
+        key: &str,
+        count: i64,
+    ) -> crate::Result<Vec<(String, Bytes)>> {
+        let frame = HRandField::new(key, Some(count))
+            .with_values(true)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_fields(self.read_response().await?)
+    }
+
+    /// Get the values of `fields` in the hash stored at `key`.
+    ///
+    /// Values are returned in the order the fields were requested. Fields that
+    /// do not exist are returned as `None`.
+    #[instrument(skip(self))]
+    pub async fn hmget(
+        &mut self,
+        key: &str,
+        fields: Vec<String>,
+    ) -> crate::Result<Vec<Option<Bytes>>> {
+        let frame = HMGet::new(key, fields).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Null => Ok(None),
+                    frame => into_bytes(frame).map(Some),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Insert `values` at the head of the list stored at `key`.
+    ///
+    /// If `key` does not exist, it is created as an empty list before the
+    /// values are pushed. Returns the length of the list after the push.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.lpush("jobs", vec!["a".into(), "b".into()]).await.unwrap();
+    ///
+    ///     let val = client.lpop("jobs").await.unwrap().unwrap();
+    ///     assert_eq!(val, "b");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn lpush(&mut self, key: &str, values: Vec<Bytes>) -> crate::Result<u64> {
+        self.push_cmd(LPush::new(key, values).into_frame()).await
+    }
+
+    /// Insert `values` at the tail of the list stored at `key`.
+    ///
+    /// If `key` does not exist, it is created as an empty list before the
+    /// values are pushed. Returns the length of the list after the push.
+    #[instrument(skip(self))]
+    pub async fn rpush(&mut self, key: &str, values: Vec<Bytes>) -> crate::Result<u64> {
+        self.push_cmd(RPush::new(key, values).into_frame()).await
+    }
+
+    /// The core push logic, used by both `lpush` and `rpush`.
+    async fn push_cmd(&mut self, frame: Frame) -> crate::Result<u64> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove and return the first value of the list stored at `key`.
+    ///
+    /// Returns `None` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn lpop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.pop_cmd(LPop::new(key, None).into_frame()).await
+    }
+
+    /// Remove and return the last value of the list stored at `key`.
+    ///
+    /// Returns `None` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn rpop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.pop_cmd(RPop::new(key, None).into_frame()).await
+    }
+
+    /// The core pop logic, used by `lpop`, `rpop`, `spop`, and by
+    /// `srandmember` and `zrandmember` which reply the same way.
+    async fn pop_cmd(&mut self, frame: Frame) -> crate::Result<Option<Bytes>> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_bytes(frame).map(Some),
+        }
+    }
+
+    /// Get the values of the list stored at `key` between the `start` and
+    /// `stop` indexes, both inclusive.
+    ///
+    /// Negative indexes count from the end of the list, so `lrange(key, 0, -1)`
+    /// returns the whole list.
+    #[instrument(skip(self))]
+    pub async fn lrange(&mut self, key: &str, start: i64, stop: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = LRange::new(key, start, stop).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the index of the first value equal to `element` in the list
+    /// stored at `key`, or `None` if there is none.
+    ///
+    /// When `rank` is set, the `rank`-th match is returned instead, negative
+    /// ranks scanning the list from the tail. At most `maxlen` values are
+    /// compared.
+    #[instrument(skip(self))]
+    pub async fn lpos(
+        &mut self,
+        key: &str,
+        element: Bytes,
+        rank: Option<i64>,
+        maxlen: Option<u64>,
+    ) -> crate::Result<Option<u64>> {
+        let mut cmd = LPos::new(key, element);
+
+        if let Some(rank) = rank {
+            cmd = cmd.rank(rank);
+        }
+
+        if let Some(maxlen) = maxlen {
+            cmd = cmd.maxlen(maxlen);
+        }
+
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(Some(response as u64)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `lpos`, but returns the indexes of up to `count` matches, `0`
+    /// returning all of them.
+    #[instrument(skip(self))]
+    pub async fn lpos_count(
+        &mut self,
+        key: &str,
+        element: Bytes,
+        count: u64,
+        rank: Option<i64>,
+        maxlen: Option<u64>,
+    ) -> crate::Result<Vec<u64>> {
+        let mut cmd = LPos::new(key, element).count(count);
+
+        if let Some(rank) = rank {
+            cmd = cmd.rank(rank);
+        }
+
+        if let Some(maxlen) = maxlen {
+            cmd = cmd.maxlen(maxlen);
+        }
+
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Integer(index) => Ok(index as u64),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the length of the list stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn llen(&mut self, key: &str) -> crate::Result<u64> {
+        let frame = LLen::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Insert `element` in the list stored at `key`, before or after the first
+    /// value equal to `pivot` depending on `position`.
+    ///
+    /// Returns the length of the list after the insertion, `0` if `key` does
+    /// not exist, or `None` if `pivot` was not found.
+    #[instrument(skip(self))]
+    pub async fn linsert(
+        &mut self,
+        key: &str,
+        position: InsertPosition,
+        pivot: Bytes,
+        element: Bytes,
+    ) -> crate::Result<Option<u64>> {
+        let frame = LInsert::new(key, position, pivot, element).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(-1) => Ok(None),
+            Frame::Integer(len) => Ok(Some(len as u64)),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set the value at `index` of the list stored at `key` to `element`.
+    ///
+    /// Negative indexes count from the end of the list.
+    #[instrument(skip(self))]
+    pub async fn lset(&mut self, key: &str, index: i64, element: Bytes) -> crate::Result<()> {
+        self.ok_cmd(LSet::new(key, index, element).into_frame())
+            .await
+    }
+
+    /// Remove the first `count` values equal to `element` from the list stored
+    /// at `key`, from the tail when `count` is negative, or all of them when
+    /// `count` is `0`.
+    ///
+    /// Returns the number of removed values.
+    #[instrument(skip(self))]
+    pub async fn lrem(&mut self, key: &str, count: i64, element: Bytes) -> crate::Result<u64> {
+        let frame = LRem::new(key, count, element).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Trim the list stored at `key` so that it only contains the values
+    /// between the `start` and `stop` indexes, both inclusive.
+    #[instrument(skip(self))]
+    pub async fn ltrim(&mut self, key: &str, start: i64, stop: i64) -> crate::Result<()> {
+        self.ok_cmd(LTrim::new(key, start, stop).into_frame()).await
+    }
+
+    /// Add `members` to the set stored at `key`.
+    ///
+    /// If `key` does not exist, a new set is created. Returns the number of
+    /// members that were added, not including members already in the set.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.sadd("tags", vec!["async".into(), "rust".into()]).await.unwrap();
+    ///
+    ///     assert!(client.sismember("tags", "rust".into()).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn sadd(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let frame = SAdd::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove `members` from the set stored at `key`.
+    ///
+    /// Returns the number of members that were removed.
+    #[instrument(skip(self))]
+    pub async fn srem(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let frame = SRem::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns all members of the set stored at `key`, in no particular order.
+    #[instrument(skip(self))]
+    pub async fn smembers(&mut self, key: &str) -> crate::Result<Vec<Bytes>> {
+        let frame = SMembers::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) | Frame::Set(frames) => {
+                frames.into_iter().map(into_bytes).collect()
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns `true` if `member` is a member of the set stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn sismember(&mut self, key: &str, member: Bytes) -> crate::Result<bool> {
+        let frame = SIsMember::new(key, member).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove and return a member picked at random from the set stored at
+    /// `key`.
+    ///
+    /// Returns `None` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn spop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.pop_cmd(SPop::new(key, None).into_frame()).await
+    }
+
+    /// Remove and return up to `count` distinct members picked at random from
+    /// the set stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn spop_count(&mut self, key: &str, count: u64) -> crate::Result<Vec<Bytes>> {
+        let frame = SPop::new(key, Some(count)).into_frame();
+        self.members_cmd(frame).await
+    }
+
+    /// Return a member picked at random from the set stored at `key`, without
+    /// removing it.
+    ///
+    /// Returns `None` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn srandmember(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.pop_cmd(SRandMember::new(key, None).into_frame()).await
+    }
+
+    /// Return members picked at random from the set stored at `key`, without
+    /// removing them.
+    ///
+    /// With a positive `count`, up to `count` distinct members are returned.
+    /// With a negative `count`, exactly `-count` members are returned and the
+    /// same member may be returned several times.
+    #[instrument(skip(self))]
+    pub async fn srandmember_count(&mut self, key: &str, count: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = SRandMember::new(key, Some(count)).into_frame();
+        self.members_cmd(frame).await
+    }
+
+    /// The core logic of the commands replying with an array of members, such
+    /// as `spop_count` or `sinter`.
+    async fn members_cmd(&mut self, frame: Frame) -> crate::Result<Vec<Bytes>> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns, for each of `members`, `true` if it is a member of the set
+    /// stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn smismember(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<Vec<bool>> {
+        let frame = SMIsMember::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Integer(response) => Ok(response == 1),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of members of the intersection of the sets stored
+    /// at `keys`.
+    ///
+    /// When `limit` is set, counting stops once `limit` members are found.
+    #[instrument(skip(self))]
+    pub async fn sintercard(
+        &mut self,
+        keys: Vec<String>,
+        limit: Option<u64>,
+    ) -> crate::Result<u64> {
+        let mut cmd = SInterCard::new(keys);
+        if let Some(limit) = limit {
+            cmd = cmd.limit(limit);
+        }
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the members of the intersection of the sets stored at `keys`.
+    ///
+    /// Keys that do not exist are considered to be empty sets.
+    #[instrument(skip(self))]
+    pub async fn sinter(&mut self, keys: Vec<String>) -> crate::Result<Vec<Bytes>> {
+        self.members_cmd(SInter::new(keys).into_frame()).await
+    }
+
+    /// Returns the members of the union of the sets stored at `keys`.
+    ///
+    /// Keys that do not exist are considered to be empty sets.
+    #[instrument(skip(self))]
+    pub async fn sunion(&mut self, keys: Vec<String>) -> crate::Result<Vec<Bytes>> {
+        self.members_cmd(SUnion::new(keys).into_frame()).await
+    }
+
+    /// Returns the members of the difference between the first set stored at `keys` and
+    /// the other sets.
+    ///
+    /// Keys that do not exist are considered to be empty sets.
+    #[instrument(skip(self))]
+    pub async fn sdiff(&mut self, keys: Vec<String>) -> crate::Result<Vec<Bytes>> {
+        self.members_cmd(SDiff::new(keys).into_frame()).await
+    }
+
+    /// Same as `sinter`, but the resulting set is stored at `destination`,
+    /// replacing any previous value.
+    ///
+    /// Returns the number of members of the resulting set.
+    #[instrument(skip(self))]
+    pub async fn sinterstore(
+        &mut self,
+        destination: &str,
+        keys: Vec<String>,
+    ) -> crate::Result<u64> {
+        let frame = SInterStore::new(destination, keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `sunion`, but the resulting set is stored at `destination`,
+    /// replacing any previous value.
+    ///
+    /// Returns the number of members of the resulting set.
+    #[instrument(skip(self))]
+    pub async fn sunionstore(
+        &mut self,
+        destination: &str,
+        keys: Vec<String>,
+    ) -> crate::Result<u64> {
+        let frame = SUnionStore::new(destination, keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `sdiff`, but the resulting set is stored at `destination`,
+    /// replacing any previous value.
+    ///
+    /// Returns the number of members of the resulting set.
+    #[instrument(skip(self))]
+    pub async fn sdiffstore(&mut self, destination: &str, keys: Vec<String>) -> crate::Result<u64> {
+        let frame = SDiffStore::new(destination, keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of members of the set stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn scard(&mut self, key: &str) -> crate::Result<u64> {
+        let frame = SCard::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Add `members` with their scores to the sorted set stored at `key`.
+    ///
+    /// If a member is already in the sorted set, its score is updated. Returns
+    /// the number of members that were added, not including members whose
+    /// score was updated.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client
+    ///         .zadd("scores", vec![(10.0, "alice".into()), (20.0, "bob".into())])
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let top = client.zrange("scores", -1, -1).await.unwrap();
+    ///     assert_eq!(top, vec!["bob"]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn zadd(&mut self, key: &str, members: Vec<(f64, Bytes)>) -> crate::Result<u64> {
+        let frame = ZAdd::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Add `members` with their scores to the sorted set stored at `key`, as
+    /// `zadd` does, restricted by `options`.
+    ///
+    /// Returns the number of members that were added, or with
+    /// `ZAddOption::Ch` the number of members added or whose score changed.
+    #[instrument(skip(self))]
+    pub async fn zadd_with(
+        &mut self,
+        key: &str,
+        members: Vec<(f64, Bytes)>,
+        options: &[ZAddOption],
+    ) -> crate::Result<u64> {
+        let mut cmd = ZAdd::new(key, members);
+        for option in options {
+            cmd = cmd.option(*option);
+        }
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Increments the score of `member` in the sorted set stored at `key` by
+    /// `increment`. A missing member is added with `increment` as its score.
+    ///
+    /// Returns the score after the increment.
+    #[instrument(skip(self))]
+    pub async fn zincrby(
+        &mut self,
+        key: &str,
+        increment: f64,
+        member: Bytes,
+    ) -> crate::Result<f64> {
+        let frame = ZIncrBy::new(key, increment, member).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_float(self.read_response().await?)
+    }
+
+    /// Get the members of the sorted set stored at `key` between the `start`
+    /// and `stop` ranks, both inclusive, ordered from the lowest to the highest
+    /// score.
+    ///
+    /// Negative ranks count from the end of the sorted set.
+    #[instrument(skip(self))]
+    pub async fn zrange(&mut self, key: &str, start: i64, stop: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = ZRange::new(key, start, stop, false).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `zrange`, but each member is returned along with its score.
+    #[instrument(skip(self))]
+    pub async fn zrange_withscores(
+        &mut self,
+        key: &str,
+        start: i64,
+        stop: i64,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let frame = ZRange::new(key, start, stop, true).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_scored_members(self.read_response().await?)
+    }
+
+    /// Get the members of the sorted set stored at `key` with a score between
+    /// `min` and `max`, ordered from the lowest to the highest score.
+    ///
+    /// Bounds are inclusive unless prefixed by `(`, `-inf` and `+inf` leave
+    /// the range open. `limit` is the number of members in the range to skip
+    /// along with the maximum number of members to return.
+    #[instrument(skip(self))]
+    pub async fn zrangebyscore(
+        &mut self,
+        key: &str,
+        min: &str,
+        max: &str,
+        limit: Option<(i64, i64)>,
+    ) -> crate::Result<Vec<Bytes>> {
+        let frame = ZRangeByScore::new(key, min, max).limit(limit).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `zrangebyscore`, but each member is returned along with its
+    /// score.
+    #[instrument(skip(self))]
+    pub async fn zrangebyscore_withscores(
+        &mut self,
+        key: &str,
+        min: &str,
+        max: &str,
+        limit: Option<(i64, i64)>,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let frame = ZRangeByScore::new(key, min, max)
+            .with_scores(true)
+            .limit(limit)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_scored_members(self.read_response().await?)
+    }
+
+    /// Get the members of the sorted set stored at `key` between `min` and
+    /// `max`, compared as binary strings.
+    ///
+    /// Bounds start with `[` to be inclusive or with `(` to be exclusive, `-`
+    /// and `+` leave the range open. See `zrangebyscore` for `limit`.
+    #[instrument(skip(self))]
+    pub async fn zrangebylex(
+        &mut self,
+        key: &str,
+        min: Bytes,
+        max: Bytes,
+        limit: Option<(i64, i64)>,
+    ) -> crate::Result<Vec<Bytes>> {
+        let frame = ZRangeByLex::new(key, min, max).limit(limit).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of members of the sorted set stored at `key` with a
+    /// score between `min` and `max`, given as for `zrangebyscore`.
+    #[instrument(skip(self))]
+    pub async fn zcount(&mut self, key: &str, min: &str, max: &str) -> crate::Result<u64> {
+        let frame = ZCount::new(key, min, max).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the score of `member` in the sorted set stored at `key`.
+    ///
+    /// If either the key or the member does not exist, `None` is returned.
+    #[instrument(skip(self))]
+    pub async fn zscore(&mut self, key: &str, member: Bytes) -> crate::Result<Option<f64>> {
+        let frame = ZScore::new(key, member).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_float(frame).map(Some),
+        }
+    }
+
+    /// Remove `members` from the sorted set stored at `key`.
+    ///
+    /// Returns the number of members that were removed.
+    #[instrument(skip(self))]
+    pub async fn zrem(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let frame = ZRem::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the rank of `member` in the sorted set stored at `key`, the
+    /// member with the lowest score having rank `0`.
+    ///
+    /// If either the key or the member does not exist, `None` is returned.
+    #[instrument(skip(self))]
+    pub async fn zrank(&mut self, key: &str, member: Bytes) -> crate::Result<Option<u64>> {
+        let frame = ZRank::new(key, member).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(rank) => Ok(Some(rank as u64)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Pop up to `count` members with the lowest scores from the sorted set
+    /// stored at `key`, a single one when `count` is `None`.
+    ///
+    /// Returns the popped members along with their scores, lowest first.
+    #[instrument(skip(self))]
+    pub async fn zpopmin(
+        &mut self,
+        key: &str,
+        count: Option<u64>,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let frame = ZPopMin::new(key, count).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_scored_members(self.read_response().await?)
+    }
+
+    /// Pop up to `count` members with the highest scores from the sorted set
+    /// stored at `key`, a single one when `count` is `None`.
+    ///
+    /// Returns the popped members along with their scores, highest first.
+    #[instrument(skip(self))]
+    pub async fn zpopmax(
+        &mut self,
+        key: &str,
+        count: Option<u64>,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let frame = ZPopMax::new(key, count).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_scored_members(self.read_response().await?)
+    }
+
+    /// Return a member picked at random from the sorted set stored at `key`.
+    ///
+    /// Returns `None` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn zrandmember(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.pop_cmd(ZRandMember::new(key, None).into_frame()).await
+    }
+
+    /// Return members picked at random from the sorted set stored at `key`.
+    ///
+    /// With a positive `count`, up to `count` distinct members are returned.
+    /// With a negative `count`, exactly `-count` members are returned and the
+    /// same member may be returned several times.
+    #[instrument(skip(self))]
+    pub async fn zrandmember_count(&mut self, key: &str, count: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = ZRandMember::new(key, Some(count)).into_frame();
+        self.members_cmd(frame).await
+    }
+
+    /// Same as `zrandmember_count`, but each member is returned along with its
+    /// score.
+    #[instrument(skip(self))]
+    pub async fn zrandmember_withscores(
+        &mut self,
+        key: &str,
+        count: i64,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let frame = ZRandMember::new(key, Some(count))
+            .with_scores(true)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_scored_members(self.read_response().await?)
+    }
+
+    /// Pop the member with the lowest score from the first non-empty sorted
+    /// set stored at one of `keys`.
+    ///
+    /// When all the sorted sets are empty, the server waits up to `timeout`
+    /// for a member to be added, a zero duration waiting forever. Returns the
+    /// key the member was popped from along with the member and its score, or
+    /// `None` if the timeout elapsed.
+    #[instrument(skip(self))]
+    pub async fn bzpopmin(
+        &mut self,
+        keys: Vec<String>,
+        timeout: Duration,
+    ) -> crate::Result<Option<(String, Bytes, f64)>> {
+        self.bzpop_cmd(BZPopMin::new(keys, timeout).into_frame())
+            .await
+    }
+
+    /// Same as `bzpopmin`, but the member with the highest score is popped.
+    #[instrument(skip(self))]
+    pub async fn bzpopmax(
+        &mut self,
+        keys: Vec<String>,
+        timeout: Duration,
+    ) -> crate::Result<Option<(String, Bytes, f64)>> {
+        self.bzpop_cmd(BZPopMax::new(keys, timeout).into_frame())
+            .await
+    }
+
+    /// The core blocking pop logic, used by both `bzpopmin` and `bzpopmax`.
+    async fn bzpop_cmd(&mut self, frame: Frame) -> crate::Result<Option<(String, Bytes, f64)>> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) if frames.len() == 3 => {
+                let mut frames = frames.into_iter();
+                let key = into_string(next_frame(&mut frames)?)?;
+                let member = into_bytes(next_frame(&mut frames)?)?;
+                let score = into_float(next_frame(&mut frames)?)?;
+                Ok(Some((key, member, score)))
+            }
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Append an entry made of `fields` to the stream stored at `key`.
+    ///
+    /// `id` is the ID of the new entry. Use `*` to let the server generate it.
+    /// The stream is created if it does not exist. Returns the ID of the new
+    /// entry.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let id = client
+    ///         .xadd("events", "*", vec![("kind".into(), "login".into())])
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let entries = client.xrange("events", "-", "+", None).await.unwrap();
+    ///     assert_eq!(entries[0].id, id);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn xadd(
+        &mut self,
+        key: &str,
+        id: &str,
+        fields: Vec<(String, Bytes)>,
+    ) -> crate::Result<String> {
+        let frame = XAdd::new(key, id, fields).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Returns the number of entries in the stream stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn xlen(&mut self, key: &str) -> crate::Result<u64> {
+        let frame = XLen::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get up to `count` entries of the stream stored at `key` with an ID
+    /// between `start` and `end`, both inclusive.
+    ///
+    /// `-` and `+` may be used as the smallest and greatest possible IDs.
+    #[instrument(skip(self))]
+    pub async fn xrange(
+        &mut self,
+        key: &str,
+        start: &str,
+        end: &str,
+        count: Option<u64>,
+    ) -> crate::Result<Vec<StreamEntry>> {
+        let frame = XRange::new(key, start, end).count(count).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_entries(self.read_response().await?)
+    }
+
+    /// Read, for each `(key, id)` pair, up to `count` entries of the stream
+    /// stored at `key` with an ID greater than `id`. `$` stands for the ID of
+    /// the last entry in the stream.
+    ///
+    /// Only streams with entries to return are included in the result. If
+    /// there are none and `block` is set, the server waits up to the given
+    /// duration for new entries, a zero duration waiting forever.
+    #[instrument(skip(self))]
+    pub async fn xread(
+        &mut self,
+        streams: Vec<(String, String)>,
+        count: Option<u64>,
+        block: Option<Duration>,
+    ) -> crate::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let frame = XRead::new(streams).count(count).block(block).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_streams(self.read_response().await?)
+    }
+
+    /// Create the consumer group `group` on the stream stored at `key`,
+    /// delivering entries after `id`. `$` stands for the last ID of the stream.
+    ///
+    /// When `mkstream` is set, an empty stream is created if `key` does not
+    /// exist.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.xgroup_create("jobs", "workers", "$", true).await.unwrap();
+    ///     client
+    ///         .xadd("jobs", "*", vec![("task".into(), "resize".into())])
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let streams = vec![("jobs".to_string(), ">".to_string())];
+    ///     let read = client
+    ///         .xreadgroup("workers", "alice", streams, None, None)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let id = read[0].1[0].id.clone();
+    ///     assert_eq!(1, client.xack("jobs", "workers", vec![id]).await.unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn xgroup_create(
+        &mut self,
+        key: &str,
+        group: &str,
+        id: &str,
+        mkstream: bool,
+    ) -> crate::Result<()> {
+        let frame = XGroup::create(key, group, id, mkstream).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Destroy the consumer group `group` of the stream stored at `key`.
+    ///
+    /// Returns `true` if the group existed.
+    #[instrument(skip(self))]
+    pub async fn xgroup_destroy(&mut self, key: &str, group: &str) -> crate::Result<bool> {
+        let frame = XGroup::destroy(key, group).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Create `consumer` in the consumer group `group` of the stream stored at
+    /// `key`.
+    ///
+    /// Returns `true` if the consumer did not exist yet.
+    #[instrument(skip(self))]
+    pub async fn xgroup_createconsumer(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> crate::Result<bool> {
+        let frame = XGroup::create_consumer(key, group, consumer).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Delete `consumer` from the consumer group `group` of the stream stored
+    /// at `key`, along with its pending entries.
+    ///
+    /// Returns the number of pending entries the consumer had.
+    #[instrument(skip(self))]
+    pub async fn xgroup_delconsumer(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> crate::Result<u64> {
+        let frame = XGroup::del_consumer(key, group, consumer).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set the ID of the last entry delivered to the consumer group `group` of
+    /// the stream stored at `key`. `$` stands for the last ID of the stream.
+    #[instrument(skip(self))]
+    pub async fn xgroup_setid(&mut self, key: &str, group: &str, id: &str) -> crate::Result<()> {
+        let frame = XGroup::set_id(key, group, id).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Read, on behalf of `consumer` of the consumer group `group`, up to
+    /// `count` entries of each stream in `streams`.
+    ///
+    /// For each `(key, id)` pair, `id` is either `>`, to read entries never
+    /// delivered to the group, or the ID after which the consumer's pending
+    /// entries are read. When only new entries are requested and `block` is
+    /// set, the server waits up to the given duration for new entries, a zero
+    /// duration waiting forever.
+    #[instrument(skip(self))]
+    pub async fn xreadgroup(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        streams: Vec<(String, String)>,
+        count: Option<u64>,
+        block: Option<Duration>,
+    ) -> crate::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let frame = XReadGroup::new(group, consumer, streams)
+            .count(count)
+            .block(block)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_streams(self.read_response().await?)
+    }
+
+    /// Acknowledge the entries `ids` of the consumer group `group` of the
+    /// stream stored at `key`.
+    ///
+    /// Returns the number of entries that were pending.
+    #[instrument(skip(self))]
+    pub async fn xack(&mut self, key: &str, group: &str, ids: Vec<String>) -> crate::Result<u64> {
+        let frame = XAck::new(key, group, ids).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Summarize the pending entries of the consumer group `group` of the
+    /// stream stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn xpending(&mut self, key: &str, group: &str) -> crate::Result<PendingSummary> {
+        let frame = XPending::summary(key, group).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The server responds with the pending entries count, the smallest and
+        // greatest IDs and the pending entries count of each consumer. Without
+        // pending entries, the IDs are `Null`.
+        let frames = match self.read_response().await? {
+            Frame::Array(frames) if frames.len() == 4 => frames,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut frames = frames.into_iter();
+
+        let count = match next_frame(&mut frames)? {
+            Frame::Integer(count) => count,
+            frame => return Err(frame.to_error()),
+        };
+
+        let smallest_id = match next_frame(&mut frames)? {
+            Frame::Null => None,
+            frame => Some(into_string(frame)?),
+        };
+
+        let greatest_id = match next_frame(&mut frames)? {
+            Frame::Null => None,
+            frame => Some(into_string(frame)?),
+        };
+
+        let consumers = match next_frame(&mut frames)? {
+            Frame::Null => vec![],
+            Frame::Array(consumers) => consumers
+                .into_iter()
+                .map(|consumer| match consumer {
+                    Frame::Array(consumer) if consumer.len() == 2 => {
+                        let mut consumer = consumer.into_iter();
+                        let name = into_string(next_frame(&mut consumer)?)?;
+                        let count = into_string(next_frame(&mut consumer)?)?
+                            .parse()
+                            .map_err(|_| "protocol error; invalid number")?;
+                        Ok((name, count))
+                    }
+                    frame => Err(frame.to_error()),
+                })
+                .collect::<crate::Result<_>>()?,
+            frame => return Err(frame.to_error()),
+        };
+
+        Ok(PendingSummary {
+            count: count as u64,
+            smallest_id,
+            greatest_id,
+            consumers,
+        })
+    }
+
+    /// Get up to `count` pending entries with an ID between `start` and `end`
+    /// of the consumer group `group` of the stream stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn xpending_range(
+        &mut self,
+        key: &str,
+        group: &str,
+        start: &str,
+        end: &str,
+        count: u64,
+    ) -> crate::Result<Vec<PendingEntry>> {
+        let frame = XPending::range(key, group, start, end, count).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // Each entry is made of its ID, its owner, its idle time in
+        // milliseconds and its delivery count.
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Array(entry) if entry.len() == 4 => {
+                        let mut entry = entry.into_iter();
+                        let id = into_string(next_frame(&mut entry)?)?;
+                        let consumer = into_string(next_frame(&mut entry)?)?;
+
+                        match (next_frame(&mut entry)?, next_frame(&mut entry)?) {
+                            (Frame::Integer(idle), Frame::Integer(delivery_count)) => {
+                                Ok(PendingEntry {
+                                    id,
+                                    consumer,
+                                    idle: Duration::from_millis(idle as u64),
+                                    delivery_count: delivery_count as u64,
+                                })
+                            }
+                            (frame, _) => Err(frame.to_error()),
+                        }
+                    }
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Transfer the pending entries `ids` of the consumer group `group` of the
+    /// stream stored at `key` to `consumer`. Only entries idle for at least
+    /// `min_idle` are claimed.
+    ///
+    /// Returns the claimed entries.
+    #[instrument(skip(self))]
+    pub async fn xclaim(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: Duration,
+        ids: Vec<String>,
+    ) -> crate::Result<Vec<StreamEntry>> {
+        let frame = XClaim::new(key, group, consumer, min_idle, ids).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_entries(self.read_response().await?)
+    }
+
+    /// Trim the stream stored at `key` to at most `max_len` entries, removing
+    /// the oldest ones.
+    ///
+    /// Returns the number of entries that were removed.
+    #[instrument(skip(self))]
+    pub async fn xtrim(&mut self, key: &str, max_len: u64) -> crate::Result<u64> {
+        let frame = XTrim::max_len(key, max_len).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove the entries of the stream stored at `key` with an ID smaller
+    /// than `min_id`.
+    ///
+    /// Returns the number of entries that were removed.
+    #[instrument(skip(self))]
+    pub async fn xtrim_minid(&mut self, key: &str, min_id: &str) -> crate::Result<u64> {
+        let frame = XTrim::min_id(key, min_id).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove the entries `ids` from the stream stored at `key`.
+    ///
+    /// Returns the number of entries that were removed.
+    #[instrument(skip(self))]
+    pub async fn xdel(&mut self, key: &str, ids: Vec<String>) -> crate::Result<u64> {
+        let frame = XDel::new(key, ids).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Sets the bit at `offset` in the string stored at `key` to `bit`,
+    /// growing the string as needed.
+    ///
+    /// Returns the previous value of the bit.
+    #[instrument(skip(self))]
+    pub async fn setbit(&mut self, key: &str, offset: u64, bit: bool) -> crate::Result<bool> {
+        let frame = SetBit::new(key, offset, bit).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the bit at `offset` in the string stored at `key`.
+    ///
+    /// Bits past the end of the string, or of a missing key, are clear.
+    #[instrument(skip(self))]
+    pub async fn getbit(&mut self, key: &str, offset: u64) -> crate::Result<bool> {
+        let frame = GetBit::new(key, offset).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Counts the set bits in the string stored at `key`.
+    ///
+    /// `range` limits the count to the inclusive range from `start` to `end`,
+    /// expressed in bytes or bits.
+    #[instrument(skip(self))]
+    pub async fn bitcount(
+        &mut self,
+        key: &str,
+        range: Option<(i64, i64, BitUnit)>,
+    ) -> crate::Result<u64> {
+        let mut cmd = BitCount::new(key);
+
+        if let Some((start, end, unit)) = range {
+            cmd = cmd.range(start, end, unit);
+        }
+
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the position of the first bit set to `bit` in the string
+    /// stored at `key`.
+    ///
+    /// The search starts at `start` and ends at `end`, both expressed in
+    /// `unit`. `end` is only used if `start` is given. `None` is returned if
+    /// no such bit is found.
+    #[instrument(skip(self))]
+    pub async fn bitpos(
+        &mut self,
+        key: &str,
+        bit: bool,
+        start: Option<i64>,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> crate::Result<Option<u64>> {
+        let mut cmd = BitPos::new(key, bit).unit(unit);
+
+        if let Some(start) = start {
+            cmd = cmd.start(start);
+        }
+
+        if let Some(end) = end {
+            cmd = cmd.end(end);
+        }
+
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) if response < 0 => Ok(None),
+            Frame::Integer(response) => Ok(Some(response as u64)),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Performs `ops` on the integer fields of the string stored at `key`.
+    ///
+    /// Returns one reply per `GET`, `SET` and `INCRBY` operation. `None` is
+    /// returned for writes that overflowed with `Overflow::Fail`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    /// use mini_redis::cmd::BitFieldOp;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let ops = vec![BitFieldOp::IncrBy {
+    ///         ty: "u8".into(),
+    ///         offset: "#1".into(),
+    ///         increment: 1,
+    ///     }];
+    ///     let replies = client.bitfield("counters", ops).await.unwrap();
+    ///
+    ///     println!("Got = {:?}", replies);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn bitfield(
+        &mut self,
+        key: &str,
+        ops: Vec<BitFieldOp>,
+    ) -> crate::Result<Vec<Option<i64>>> {
+        let frame = BitField::new(key, ops).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Null => Ok(None),
+                    Frame::Integer(value) => Ok(Some(value)),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Combines the strings stored at `keys` with `op` and stores the result
+    /// at `dest`.
+    ///
+    /// Returns the length of the resulting string.
+    #[instrument(skip(self))]
+    pub async fn bitop(
+        &mut self,
+        op: BitOperation,
+        dest: &str,
+        keys: Vec<String>,
+    ) -> crate::Result<u64> {
+        let frame = BitOp::new(op, dest, keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Adds `elements` to the HyperLogLog stored at `key`.
+    ///
+    /// Returns `true` if the estimated cardinality may have changed.
+    #[instrument(skip(self))]
+    pub async fn pfadd(&mut self, key: &str, elements: Vec<Bytes>) -> crate::Result<bool> {
+        let frame = PfAdd::new(key, elements).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the estimated number of distinct elements in the union of the
+    /// HyperLogLogs stored at `keys`.
+    #[instrument(skip(self))]
+    pub async fn pfcount(&mut self, keys: Vec<String>) -> crate::Result<u64> {
+        let frame = PfCount::new(keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Merges the HyperLogLogs stored at `keys` into the one stored at `dest`.
+    #[instrument(skip(self))]
+    pub async fn pfmerge(&mut self, dest: &str, keys: Vec<String>) -> crate::Result<()> {
+        let frame = PfMerge::new(dest, keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Increments the integer stored at `key` by one. A missing key is
+    /// considered to hold `0`.
+    ///
+    /// Returns the value after the increment.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let val = client.incr("counter").await.unwrap();
+    ///     println!("Got = {}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn incr(&mut self, key: &str) -> crate::Result<i64> {
+        let frame = Incr::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Decrements the integer stored at `key` by one.
+    ///
+    /// Returns the value after the decrement.
+    #[instrument(skip(self))]
+    pub async fn decr(&mut self, key: &str) -> crate::Result<i64> {
+        let frame = Decr::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Increments the integer stored at `key` by `increment`.
+    ///
+    /// Returns the value after the increment.
+    #[instrument(skip(self))]
+    pub async fn incrby(&mut self, key: &str, increment: i64) -> crate::Result<i64> {
+        let frame = IncrBy::new(key, increment).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Decrements the integer stored at `key` by `decrement`.
+    ///
+    /// Returns the value after the decrement.
+    #[instrument(skip(self))]
+    pub async fn decrby(&mut self, key: &str, decrement: i64) -> crate::Result<i64> {
+        let frame = DecrBy::new(key, decrement).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Increments the float stored at `key` by `increment`. A missing key is
+    /// considered to hold `0`.
+    ///
+    /// Returns the value after the increment.
+    #[instrument(skip(self))]
+    pub async fn incrbyfloat(&mut self, key: &str, increment: f64) -> crate::Result<f64> {
+        let frame = IncrByFloat::new(key, increment).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_float(self.read_response().await?)
+    }
+
+    /// Increments the integer stored in `field` of the hash stored at `key` by
+    /// `increment`. A missing field is considered to hold `0`.
+    ///
+    /// Returns the value after the increment.
+    #[instrument(skip(self))]
+    pub async fn hincrby(&mut self, key: &str, field: &str, increment: i64) -> crate::Result<i64> {
+        let frame = HIncrBy::new(key, field, increment).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Increments the float stored in `field` of the hash stored at `key` by
+    /// `increment`. A missing field is considered to hold `0`.
+    ///
+    /// Returns the value after the increment.
+    #[instrument(skip(self))]
+    pub async fn hincrbyfloat(
+        &mut self,
+        key: &str,
+        field: &str,
+        increment: f64,
+    ) -> crate::Result<f64> {
+        let frame = HIncrByFloat::new(key, field, increment).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_float(self.read_response().await?)
+    }
+
+    /// Appends `value` to the string stored at `key`, creating it if needed.
+    ///
+    /// Returns the length of the string after the append.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.append("log", "hello ".into()).await.unwrap();
+    ///     let len = client.append("log", "world".into()).await.unwrap();
+    ///     assert_eq!(11, len);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn append(&mut self, key: &str, value: Bytes) -> crate::Result<u64> {
+        let frame = Append::new(key, value).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the length of the string stored at `key`, `0` if it does not
+    /// exist.
+    #[instrument(skip(self))]
+    pub async fn strlen(&mut self, key: &str) -> crate::Result<u64> {
+        let frame = StrLen::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the bytes between `start` and `end`, both inclusive, of the
+    /// string stored at `key`. Negative offsets count from the end of the
+    /// string.
+    #[instrument(skip(self))]
+    pub async fn getrange(&mut self, key: &str, start: i64, end: i64) -> crate::Result<Bytes> {
+        let frame = GetRange::new(key, start, end).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_bytes(self.read_response().await?)
+    }
+
+    /// Overwrites the string stored at `key` with `value`, starting at
+    /// `offset`. The string is zero-padded if `offset` is past its end.
+    ///
+    /// Returns the length of the string after it was modified.
+    #[instrument(skip(self))]
+    pub async fn setrange(&mut self, key: &str, offset: u64, value: Bytes) -> crate::Result<u64> {
+        let frame = SetRange::new(key, offset, value).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get the values of all the specified keys.
+    ///
+    /// `None` is returned for each key that does not exist or does not hold a
+    /// string value.
+    #[instrument(skip(self))]
+    pub async fn mget(&mut self, keys: Vec<String>) -> crate::Result<Vec<Option<Bytes>>> {
+        let frame = MGet::new(keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Null => Ok(None),
+                    frame => into_bytes(frame).map(Some),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set each key of `pairs` to its value, atomically.
+    #[instrument(skip(self))]
+    pub async fn mset(&mut self, pairs: Vec<(String, Bytes)>) -> crate::Result<()> {
+        let frame = MSet::new(pairs).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set each key of `pairs` to its value, atomically, only if none of the
+    /// keys exist.
+    ///
+    /// Returns `true` if the keys were set.
+    #[instrument(skip(self))]
+    pub async fn msetnx(&mut self, pairs: Vec<(String, Bytes)>) -> crate::Result<bool> {
+        let frame = MSetNx::new(pairs).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the remaining time to live of `key`, in seconds.
+    ///
+    /// Returns `-2` if the key does not exist and `-1` if it has no
+    /// associated expiration.
+    #[instrument(skip(self))]
+    pub async fn ttl(&mut self, key: &str) -> crate::Result<i64> {
+        let frame = Ttl::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the remaining time to live of `key`, in milliseconds.
+    ///
+    /// Returns `-2` if the key does not exist and `-1` if it has no
+    /// associated expiration.
+    #[instrument(skip(self))]
+    pub async fn pttl(&mut self, key: &str) -> crate::Result<i64> {
+        let frame = PTtl::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Removes the expiration of `key`.
+    ///
+    /// Returns `true` if the key had an expiration.
+    #[instrument(skip(self))]
+    pub async fn persist(&mut self, key: &str) -> crate::Result<bool> {
+        let frame = Persist::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Sets a timeout of `seconds` on `key`, optionally only if `option` is
+    /// satisfied.
+    ///
+    /// A timeout that is not positive deletes the key. Returns `true` if the
+    /// timeout was set.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    /// use mini_redis::cmd::ExpireOption;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///
+    ///     // Only set the timeout if the key has none yet
+    ///     let set = client.expire("foo", 10, Some(ExpireOption::Nx)).await.unwrap();
+    ///     assert!(set);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn expire(
+        &mut self,
+        key: &str,
+        seconds: i64,
+        option: Option<ExpireOption>,
+    ) -> crate::Result<bool> {
+        let mut cmd = Expire::new(key, seconds);
+        if let Some(option) = option {
+            cmd = cmd.option(option);
+        }
+        self.expire_cmd(cmd.into_frame()).await
+    }
+
+    /// Sets a timeout of `milliseconds` on `key`, optionally only if `option`
+    /// is satisfied.
+    ///
+    /// Returns `true` if the timeout was set.
+    #[instrument(skip(self))]
+    pub async fn pexpire(
+        &mut self,
+        key: &str,
+        milliseconds: i64,
+        option: Option<ExpireOption>,
+    ) -> crate::Result<bool> {
+        let mut cmd = PExpire::new(key, milliseconds);
+        if let Some(option) = option {
+            cmd = cmd.option(option);
+        }
+        self.expire_cmd(cmd.into_frame()).await
+    }
+
+    /// Sets the expiration of `key` to the Unix `timestamp`, in seconds,
+    /// optionally only if `option` is satisfied.
+    ///
+    /// A timestamp in the past deletes the key. Returns `true` if the
+    /// expiration was set.
+    #[instrument(skip(self))]
+    pub async fn expireat(
+        &mut self,
+        key: &str,
+        timestamp: i64,
+        option: Option<ExpireOption>,
+    ) -> crate::Result<bool> {
+        let mut cmd = ExpireAt::new(key, timestamp);
+        if let Some(option) = option {
+            cmd = cmd.option(option);
+        }
+        self.expire_cmd(cmd.into_frame()).await
+    }
+
+    /// Sets the expiration of `key` to the Unix `timestamp`, in milliseconds,
+    /// optionally only if `option` is satisfied.
+    ///
+    /// Returns `true` if the expiration was set.
+    #[instrument(skip(self))]
+    pub async fn pexpireat(
+        &mut self,
+        key: &str,
+        timestamp: i64,
+        option: Option<ExpireOption>,
+    ) -> crate::Result<bool> {
+        let mut cmd = PExpireAt::new(key, timestamp);
+        if let Some(option) = option {
+            cmd = cmd.option(option);
+        }
+        self.expire_cmd(cmd.into_frame()).await
+    }
+
+    /// Sends one of the expire commands and reads whether the expiration was
+    /// set.
+    async fn expire_cmd(&mut self, frame: Frame) -> crate::Result<bool> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns all the keys matching the glob-style `pattern`, in no
+    /// particular order.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.set("user:1", "alice".into()).await.unwrap();
+    ///     client.set("user:2", "bob".into()).await.unwrap();
+    ///
+    ///     let keys = client.keys("user:*").await.unwrap();
+    ///     assert_eq!(2, keys.len());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        let frame = Keys::new(pattern).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_string).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a page of the keys, starting at `cursor`, along with the cursor
+    /// of the next page. Start with cursor `0`, the iteration is complete when
+    /// the returned cursor is `0`.
+    ///
+    /// Only keys matching `pattern` and holding a value of type `ty` are
+    /// returned. `count` is the number of keys to visit, a page may hold fewer
+    /// keys. See [`scan_iter`](Client::scan_iter) to iterate over all the keys.
+    #[instrument(skip(self))]
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+        ty: Option<&str>,
+    ) -> crate::Result<(u64, Vec<String>)> {
+        let frame = Scan::new(cursor)
+            .pattern(pattern.map(str::to_string))
+            .count(count)
+            .ty(ty.map(str::to_string))
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let (cursor, keys) = into_scan_page(self.read_response().await?)?;
+        let keys = keys
+            .into_iter()
+            .map(into_string)
+            .collect::<crate::Result<_>>()?;
+        Ok((cursor, keys))
+    }
+
+    /// Returns a `Stream` yielding all the keys matching `pattern` and holding
+    /// a value of type `ty`.
+    ///
+    /// The keys are fetched one page at a time by issuing `SCAN` commands
+    /// until the iteration is complete. A key may be yielded more than once.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    /// use tokio::stream::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let keys = client.scan_iter(Some("user:*"), None, None);
+    ///     tokio::pin!(keys);
+    ///
+    ///     while let Some(key) = keys.next().await {
+    ///         println!("{}", key.unwrap());
+    ///     }
+    /// }
+    /// ```
+    pub fn scan_iter<'a>(
+        &'a mut self,
+        pattern: Option<&'a str>,
+        count: Option<u64>,
+        ty: Option<&'a str>,
+    ) -> impl Stream<Item = crate::Result<String>> + 'a {
+        try_stream! {
+            let mut cursor = 0;
+
+            loop {
+                let (next, keys) = self.scan(cursor, pattern, count, ty).await?;
+
+                for key in keys {
+                    yield key;
+                }
+
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+    }
+
+    /// Returns a page of the fields of the hash stored at `key`, with their
+    /// values, starting at `cursor`, along with the cursor of the next page.
+    ///
+    /// Same cursor semantics as [`scan`](Client::scan). Only fields matching
+    /// `pattern` are returned.
+    #[instrument(skip(self))]
+    pub async fn hscan(
+        &mut self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+    ) -> crate::Result<(u64, Vec<(String, Bytes)>)> {
+        let frame = HScan::new(key, cursor)
+            .pattern(pattern.map(str::to_string))
+            .count(count)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The page is a flat array of alternating fields and values.
+        let (cursor, frames) = into_scan_page(self.read_response().await?)?;
+        let mut fields = Vec::with_capacity(frames.len() / 2);
+        let mut frames = frames.into_iter();
+
+        while let Some(field) = frames.next() {
+            let value = match frames.next() {
+                Some(value) => value,
+                None => return Err(field.to_error()),
+            };
+
+            fields.push((into_string(field)?, into_bytes(value)?));
+        }
+
+        Ok((cursor, fields))
+    }
+
+    /// Returns a page of the members of the set stored at `key`, starting at
+    /// `cursor`, along with the cursor of the next page.
+    ///
+    /// Same cursor semantics as [`scan`](Client::scan). Only members matching
+    /// `pattern` are returned.
+    #[instrument(skip(self))]
+    pub async fn sscan(
+        &mut self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+    ) -> crate::Result<(u64, Vec<Bytes>)> {
+        let frame = SScan::new(key, cursor)
+            .pattern(pattern.map(str::to_string))
+            .count(count)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let (cursor, members) = into_scan_page(self.read_response().await?)?;
+        let members = members
+            .into_iter()
+            .map(into_bytes)
+            .collect::<crate::Result<_>>()?;
+        Ok((cursor, members))
+    }
+
+    /// Returns a page of the members of the sorted set stored at `key`, with
+    /// their scores, starting at `cursor`, along with the cursor of the next
+    /// page.
+    ///
+    /// Same cursor semantics as [`scan`](Client::scan). Only members matching
+    /// `pattern` are returned.
+    #[instrument(skip(self))]
+    pub async fn zscan(
+        &mut self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+    ) -> crate::Result<(u64, Vec<(Bytes, f64)>)> {
+        let frame = ZScan::new(key, cursor)
+            .pattern(pattern.map(str::to_string))
+            .count(count)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The page is a flat array of alternating members and scores.
+        let (cursor, frames) = into_scan_page(self.read_response().await?)?;
+        let mut members = Vec::with_capacity(frames.len() / 2);
+        let mut frames = frames.into_iter();
+
+        while let Some(member) = frames.next() {
+            let score = match frames.next() {
+                Some(score) => score,
+                None => return Err(member.to_error()),
+            };
+
+            members.push((into_bytes(member)?, into_float(score)?));
+        }
+
+        Ok((cursor, members))
+    }
+
+    /// Returns the type of the value stored at `key`: `string`, `list`,
+    /// `hash`, `set`, `zset` or `stream`, or `none` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn key_type(&mut self, key: &str) -> crate::Result<String> {
+        let frame = Type::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Rename `key` to `new_key`, keeping its expiration. If `new_key` already
+    /// exists, it is overwritten.
+    ///
+    /// Returns an error if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn rename(&mut self, key: &str, new_key: &str) -> crate::Result<()> {
+        let frame = Rename::new(key, new_key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Rename `key` to `new_key`, only if `new_key` does not exist.
+    ///
+    /// Returns `true` if the key was renamed.
+    #[instrument(skip(self))]
+    pub async fn renamenx(&mut self, key: &str, new_key: &str) -> crate::Result<bool> {
+        let frame = RenameNx::new(key, new_key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of keys in the database.
+    #[instrument(skip(self))]
+    pub async fn dbsize(&mut self) -> crate::Result<u64> {
+        let frame = DbSize::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a key picked at random, or `None` if the database is empty.
+    #[instrument(skip(self))]
+    pub async fn randomkey(&mut self) -> crate::Result<Option<String>> {
+        let frame = RandomKey::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_string(frame).map(Some),
+        }
+    }
+
+    /// Remove all the keys of the current database. When `asynchronous` is
+    /// set, the server frees the keys in the background.
+    #[instrument(skip(self))]
+    pub async fn flushdb(&mut self, asynchronous: bool) -> crate::Result<()> {
+        let frame = FlushDb::new().asynchronous(asynchronous).into_frame();
+        self.ok_cmd(frame).await
+    }
+
+    /// Remove all the keys of all the databases. When `asynchronous` is set,
+    /// the server frees the keys in the background.
+    #[instrument(skip(self))]
+    pub async fn flushall(&mut self, asynchronous: bool) -> crate::Result<()> {
+        let frame = FlushAll::new().asynchronous(asynchronous).into_frame();
+        self.ok_cmd(frame).await
+    }
+
+    /// Select the database the following commands apply to. Databases are
+    /// numbered from `0`, which is selected when connecting.
+    #[instrument(skip(self))]
+    pub async fn select(&mut self, index: u64) -> crate::Result<()> {
+        self.ok_cmd(Select::new(index).into_frame()).await
+    }
+
+    /// Exchange the keys of the databases at `index1` and `index2`.
+    #[instrument(skip(self))]
+    pub async fn swapdb(&mut self, index1: u64, index2: u64) -> crate::Result<()> {
+        self.ok_cmd(SwapDb::new(index1, index2).into_frame()).await
+    }
+
+    /// Start a transaction.
+    ///
+    /// Commands sent with `queue` are then queued by the server, until `exec`
+    /// executes them atomically or `discard` drops them.
+    #[instrument(skip(self))]
+    pub async fn multi(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Multi::new().into_frame()).await
+    }
+
+    /// Queue a command in the transaction started by `multi`. `args` holds the
+    /// name of the command followed by its arguments, its reply is returned by
+    /// `exec`.
+    ///
+    /// Returns an error if the server refused to queue the command, in which
+    /// case the transaction is aborted.
+    #[instrument(skip(self))]
+    pub async fn queue(&mut self, args: Vec<Bytes>) -> crate::Result<()> {
+        let frame = Frame::Array(args.into_iter().map(Frame::Bulk).collect());
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "QUEUED" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Execute the commands queued since `multi`, returning their replies.
+    ///
+    /// Errors returned by the commands are part of the replies, as
+    /// `Frame::Error`. Returns `None` if a key watched with `watch` was
+    /// modified, in which case the transaction is not executed. Returns an
+    /// error if the transaction was aborted because a command could not be
+    /// queued.
+    #[instrument(skip(self))]
+    pub async fn exec(&mut self) -> crate::Result<Option<Vec<Frame>>> {
+        let frame = Exec::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(replies) => Ok(Some(replies)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Abort the transaction started by `multi`, dropping the queued commands.
+    #[instrument(skip(self))]
+    pub async fn discard(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Discard::new().into_frame()).await
+    }
+
+    /// Watch `keys`, so that the next transaction is only executed if none of
+    /// them is modified until `exec`.
+    ///
+    /// `exec` returns `None` when a watched key was modified.
+    #[instrument(skip(self))]
+    pub async fn watch(&mut self, keys: Vec<String>) -> crate::Result<()> {
+        self.ok_cmd(Watch::new(keys).into_frame()).await
+    }
+
+    /// Stop watching the keys watched with `watch`.
+    #[instrument(skip(self))]
+    pub async fn unwatch(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Unwatch::new().into_frame()).await
+    }
+
+    /// Run the Lua `script` with `keys` and `args`, returning the value
+    /// returned by the script, converted to a frame.
+    ///
+    /// The script is cached by the server, so that it can later be run by
+    /// `evalsha`. Errors raised by the script are returned as `Err`.
+    #[instrument(skip(self))]
+    pub async fn eval(
+        &mut self,
+        script: &str,
+        keys: Vec<String>,
+        args: Vec<Bytes>,
+    ) -> crate::Result<Frame> {
+        let frame = Eval::new(script, keys, args).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Run the cached script whose SHA1 digest is `sha` with `keys` and
+    /// `args`, returning the value returned by the script.
+    ///
+    /// Returns an error if the script is not cached.
+    #[instrument(skip(self))]
+    pub async fn evalsha(
+        &mut self,
+        sha: &str,
+        keys: Vec<String>,
+        args: Vec<Bytes>,
+    ) -> crate::Result<Frame> {
+        let frame = EvalSha::new(sha, keys, args).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Cache `script` without running it, returning its SHA1 digest.
+    #[instrument(skip(self))]
+    pub async fn script_load(&mut self, script: &str) -> crate::Result<String> {
+        let frame = Script::load(script).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Returns, for each SHA1 digest of `shas`, `true` if the script is
+    /// cached.
+    #[instrument(skip(self))]
+    pub async fn script_exists(&mut self, shas: Vec<String>) -> crate::Result<Vec<bool>> {
+        let frame = Script::exists(shas).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Integer(response) => Ok(response == 1),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove all the scripts from the cache.
+    #[instrument(skip(self))]
+    pub async fn script_flush(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Script::flush().into_frame()).await
+    }
+
+    /// Stop the script running for longer than `lua-time-limit`.
+    ///
+    /// Fails if no script is running, or if the script already executed write
+    /// commands.
+    #[instrument(skip(self))]
+    pub async fn script_kill(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Script::kill().into_frame()).await
+    }
+
+    /// Load the library `code`, returning the name of the library.
+    ///
+    /// The first line of the code is `#!lua name=<library name>`, the code
+    /// registers the functions of the library with `redis.register_function`.
+    /// If `replace` is `true`, the library replaces the library of the same
+    /// name.
+    #[instrument(skip(self))]
+    pub async fn function_load(&mut self, code: &str, replace: bool) -> crate::Result<String> {
+        let frame = Function::load(code, replace).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Delete the library `name` and its functions.
+    #[instrument(skip(self))]
+    pub async fn function_delete(&mut self, name: &str) -> crate::Result<()> {
+        self.ok_cmd(Function::delete(name).into_frame()).await
+    }
+
+    /// Delete all the libraries.
+    #[instrument(skip(self))]
+    pub async fn function_flush(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Function::flush().into_frame()).await
+    }
+
+    /// Stop the function running for longer than `lua-time-limit`.
+    ///
+    /// Fails if no function is running, or if the function already executed
+    /// write commands.
+    #[instrument(skip(self))]
+    pub async fn function_kill(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Function::kill().into_frame()).await
+    }
+
+    /// Returns information about the libraries whose name matches the glob
+    /// `pattern`, along with their code if `code` is `true`.
+    ///
+    /// Each library is described by an array of field names followed by their
+    /// value.
+    #[instrument(skip(self))]
+    pub async fn function_list(
+        &mut self,
+        pattern: Option<&str>,
+        code: bool,
+    ) -> crate::Result<Vec<Frame>> {
+        let frame = Function::list(pattern.map(str::to_string), code).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(libraries) => Ok(libraries),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a serialized payload of the libraries, which `function_restore`
+    /// restores.
+    #[instrument(skip(self))]
+    pub async fn function_dump(&mut self) -> crate::Result<Bytes> {
+        let frame = Function::dump().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_bytes(self.read_response().await?)
+    }
+
+    /// Restore the libraries serialized in `payload` by `function_dump`.
+    ///
+    /// Fails if a library of the same name is already loaded.
+    #[instrument(skip(self))]
+    pub async fn function_restore(&mut self, payload: Bytes) -> crate::Result<()> {
+        self.ok_cmd(Function::restore(payload).into_frame()).await
+    }
+
+    /// Call the function `function` with `keys` and `args`, returning the
+    /// value returned by the function, converted to a frame.
+    #[instrument(skip(self))]
+    pub async fn fcall(
+        &mut self,
+        function: &str,
+        keys: Vec<String>,
+        args: Vec<Bytes>,
+    ) -> crate::Result<Frame> {
+        let frame = FCall::new(function, keys, args).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Call the read-only function `function` with `keys` and `args`.
+    ///
+    /// The function must be registered with the `no-writes` flag.
+    #[instrument(skip(self))]
+    pub async fn fcall_ro(
+        &mut self,
+        function: &str,
+        keys: Vec<String>,
+        args: Vec<Bytes>,
+    ) -> crate::Result<Frame> {
+        let frame = FCallRo::new(function, keys, args).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Save a snapshot of the databases to disk, returning once it is saved.
+    #[instrument(skip(self))]
+    pub async fn save(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Save::new().into_frame()).await
+    }
+
+    /// Save a snapshot of the databases to disk in the background, returning
+    /// the status message of the server.
+    #[instrument(skip(self))]
+    pub async fn bgsave(&mut self) -> crate::Result<String> {
+        let frame = BgSave::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the Unix time, in seconds, at which the last snapshot was
+    /// saved.
+    #[instrument(skip(self))]
+    pub async fn lastsave(&mut self) -> crate::Result<u64> {
+        let frame = LastSave::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Shut the server down, saving a last snapshot if `save` is `Some(true)`
+    /// and without saving it if `Some(false)`. By default, it is saved if
+    /// snapshots are enabled.
+    ///
+    /// Returns once the server closed the connection, or `Err` if it failed
+    /// to save the snapshot, in which case it keeps running.
+    #[instrument(skip(self))]
+    pub async fn shutdown(&mut self, save: Option<bool>) -> crate::Result<()> {
+        let frame = ShutdownServer::new(save).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.connection.read_frame().await? {
+            None => Ok(()),
+            Some(Frame::Error(msg)) => Err(msg.into()),
+            Some(frame) => Err(frame.to_error()),
+        }
+    }
+
+    /// Rewrite the AOF in the background, returning the status message of the
+    /// server.
+    #[instrument(skip(self))]
+    pub async fn bgrewriteaof(&mut self) -> crate::Result<String> {
+        let frame = BgRewriteAof::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Make the server a replica of the primary at `host:port`.
+    ///
+    /// The server synchronizes with the primary in the background, and rejects
+    /// the commands modifying the databases from then on.
+    #[instrument(skip(self))]
+    pub async fn replicaof(&mut self, host: &str, port: u16) -> crate::Result<()> {
+        self.ok_cmd(ReplicaOf::new(host, port).into_frame()).await
+    }
+
+    /// Stop replicating, promoting the server back to a primary.
+    #[instrument(skip(self))]
+    pub async fn replicaof_no_one(&mut self) -> crate::Result<()> {
+        self.ok_cmd(ReplicaOf::no_one().into_frame()).await
+    }
+
+    /// Returns the role of the server in the replication.
+    #[instrument(skip(self))]
+    pub async fn role(&mut self) -> crate::Result<ReplicationRole> {
+        let frame = Role::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_role(self.read_response().await?)
+    }
+
+    /// Wait until the commands previously sent are acknowledged by at least
+    /// `numreplicas` replicas, or until `timeout` elapses. A zero `timeout`
+    /// waits forever.
+    ///
+    /// Returns the number of replicas which acknowledged the commands.
+    #[instrument(skip(self))]
+    pub async fn wait(&mut self, numreplicas: u64, timeout: Duration) -> crate::Result<u64> {
+        let frame = Wait::new(numreplicas, timeout).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the ID of the node, in cluster mode.
+    #[instrument(skip(self))]
+    pub async fn cluster_myid(&mut self) -> crate::Result<String> {
+        let frame = Cluster::myid().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(id) => Ok(String::from_utf8(id.to_vec())?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the hash slot of `key`, in cluster mode.
+    #[instrument(skip(self))]
+    pub async fn cluster_keyslot(&mut self, key: &str) -> crate::Result<u16> {
+        let frame = Cluster::key_slot(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(slot) => Ok(slot as u16),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the ranges of consecutive slots served by the same node, as
+    /// the first and last slot of the range and the ID of the node, in
+    /// cluster mode.
+    #[instrument(skip(self))]
+    pub async fn cluster_slots(&mut self) -> crate::Result<Vec<(u16, u16, String)>> {
+        let frame = Cluster::slots().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let ranges = match self.read_response().await? {
+            Frame::Array(ranges) => ranges,
+            frame => return Err(frame.to_error()),
+        };
+
+        ranges
+            .into_iter()
+            .map(|range| match range {
+                Frame::Array(parts) => match &parts[..] {
+                    [Frame::Integer(start), Frame::Integer(end), Frame::Array(node)] => {
+                        match node.get(2) {
+                            Some(Frame::Bulk(id)) => {
+                                Ok((*start as u16, *end as u16, String::from_utf8(id.to_vec())?))
+                            }
+                            _ => Err(Frame::Array(parts.clone()).to_error()),
+                        }
+                    }
+                    _ => Err(Frame::Array(parts.clone()).to_error()),
+                },
+                frame => Err(frame.to_error()),
+            })
+            .collect()
+    }
+
+    /// Assign the slots from `start` to `end`, inclusive, to the node, in
+    /// cluster mode.
+    #[instrument(skip(self))]
+    pub async fn cluster_addslotsrange(&mut self, start: u16, end: u16) -> crate::Result<()> {
+        self.ok_cmd(Cluster::add_slots_range(start, end).into_frame())
+            .await
+    }
+
+    /// Add the node at `host` and `port`, along with the slots it serves, to
+    /// the cluster.
+    #[instrument(skip(self))]
+    pub async fn cluster_meet(&mut self, host: &str, port: u16) -> crate::Result<()> {
+        self.ok_cmd(Cluster::meet(host, port).into_frame()).await
+    }
+
+    /// Assign `slot` to the node `node_id`, in cluster mode.
+    #[instrument(skip(self))]
+    pub async fn cluster_setslot_node(&mut self, slot: u16, node_id: &str) -> crate::Result<()> {
+        self.ok_cmd(Cluster::set_slot_node(slot, node_id).into_frame())
+            .await
+    }
+
+    /// Set `slot`, served by the node, as migrating to the node `node_id`.
+    #[instrument(skip(self))]
+    pub async fn cluster_setslot_migrating(
+        &mut self,
+        slot: u16,
+        node_id: &str,
+    ) -> crate::Result<()> {
+        self.ok_cmd(Cluster::set_slot_migrating(slot, node_id).into_frame())
+            .await
+    }
+
+    /// Set `slot` as importing from the node `node_id`.
+    #[instrument(skip(self))]
+    pub async fn cluster_setslot_importing(
+        &mut self,
+        slot: u16,
+        node_id: &str,
+    ) -> crate::Result<()> {
+        self.ok_cmd(Cluster::set_slot_importing(slot, node_id).into_frame())
+            .await
+    }
+
+    /// Ask the node to serve the next command even if its keys belong to a
+    /// slot being imported, after an `ASK` redirection.
+    #[instrument(skip(self))]
+    pub async fn asking(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Asking::new().into_frame()).await
+    }
+
+    /// Returns the configuration parameters whose name matches the glob
+    /// `pattern`, along with their value.
+    #[instrument(skip(self))]
+    pub async fn config_get(&mut self, pattern: &str) -> crate::Result<Vec<(String, String)>> {
+        let frame = Config::get(pattern).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_fields(self.read_response().await?)?
+            .into_iter()
+            .map(|(name, value)| Ok((name, String::from_utf8(value.to_vec())?)))
+            .collect()
+    }
+
+    /// Set the configuration parameter `parameter` to `value`.
+    #[instrument(skip(self))]
+    pub async fn config_set(&mut self, parameter: &str, value: &str) -> crate::Result<()> {
+        self.ok_cmd(Config::set(parameter, value).into_frame())
+            .await
+    }
+
+    /// Returns information and statistics about the server, as `# Section`
+    /// lines followed by `field:value` lines. Only `section` is returned if
+    /// given.
+    #[instrument(skip(self))]
+    pub async fn info(&mut self, section: Option<&str>) -> crate::Result<String> {
+        let frame = Info::new(section).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Returns the number of commands implemented by the server.
+    #[instrument(skip(self))]
+    pub async fn command_count(&mut self) -> crate::Result<u64> {
+        let frame = Commands::count().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the description of the commands `names`, or `None` for the
+    /// ones the server does not implement. Every command is described if
+    /// `names` is empty.
+    #[instrument(skip(self))]
+    pub async fn command_info(
+        &mut self,
+        names: &[&str],
+    ) -> crate::Result<Vec<Option<CommandInfo>>> {
+        let names = names.iter().map(|name| name.to_string()).collect();
+        let frame = Commands::info(names).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_command_info).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the documentation of the commands `names`, as pairs of field
+    /// and value such as `summary` and `group`, indexed by name. Unknown
+    /// commands are skipped, and every command is documented if `names` is
+    /// empty.
+    #[instrument(skip(self))]
+    pub async fn command_docs(
+        &mut self,
+        names: &[&str],
+    ) -> crate::Result<Vec<(String, Vec<(String, String)>)>> {
+        let names = names.iter().map(|name| name.to_string()).collect();
+        let frame = Commands::docs(names).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_pairs(self.read_response().await?)?
+            .into_iter()
+            .map(|(name, docs)| {
+                let docs = into_pairs(docs)?
+                    .into_iter()
+                    .map(|(field, value)| Ok((field, into_string(value)?)))
+                    .collect::<crate::Result<_>>()?;
+                Ok((name, docs))
+            })
+            .collect()
+    }
+
+    /// Returns the `count` newest entries of the slow log, the newest first,
+    /// `10` if `None` or all of them if negative.
+    #[instrument(skip(self))]
+    pub async fn slowlog_get(&mut self, count: Option<i64>) -> crate::Result<Vec<SlowlogEntry>> {
+        let frame = Slowlog::get(count).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(entries) => entries.into_iter().map(into_slowlog_entry).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of entries of the slow log.
+    #[instrument(skip(self))]
+    pub async fn slowlog_len(&mut self) -> crate::Result<u64> {
+        let frame = Slowlog::len().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Drop the entries of the slow log.
+    #[instrument(skip(self))]
+    pub async fn slowlog_reset(&mut self) -> crate::Result<()> {
+        self.ok_cmd(Slowlog::reset().into_frame()).await
+    }
+
+    /// Returns the latest latency spike of each event recorded by the latency
+    /// monitor.
+    #[instrument(skip(self))]
+    pub async fn latency_latest(&mut self) -> crate::Result<Vec<LatencySpike>> {
+        let frame = Latency::latest().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(spikes) => spikes.into_iter().map(into_latency_spike).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the latest latency spikes of `event`, the oldest first, as the
+    /// time at which they were recorded and their latency.
+    #[instrument(skip(self))]
+    pub async fn latency_history(
+        &mut self,
+        event: &str,
+    ) -> crate::Result<Vec<(SystemTime, Duration)>> {
+        let frame = Latency::history(event).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(samples) => samples
+                .into_iter()
+                .map(|sample| match sample {
+                    Frame::Array(sample) => match &sample[..] {
+                        [Frame::Integer(time), Frame::Integer(latency)] => Ok((
+                            SystemTime::UNIX_EPOCH + Duration::from_secs(*time as u64),
+                            Duration::from_millis(*latency as u64),
+                        )),
+                        _ => Err("protocol error; invalid latency sample".into()),
+                    },
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Drop the latency spikes of `events`, or of every event if empty.
+    /// Returns the number of events whose spikes were dropped.
+    #[instrument(skip(self))]
+    pub async fn latency_reset(&mut self, events: &[&str]) -> crate::Result<u64> {
+        let events = events.iter().map(|event| event.to_string()).collect();
+        let frame = Latency::reset(events).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a human readable report of the latency spikes.
+    #[instrument(skip(self))]
+    pub async fn latency_doctor(&mut self) -> crate::Result<String> {
+        let frame = Latency::doctor().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Returns the approximate number of bytes used by `key` and its value,
+    /// or `None` if it does not exist.
+    ///
+    /// The size of the elements of a collection is extrapolated from the
+    /// first `samples` of them, 5 by default, all of them being measured if
+    /// `samples` is 0.
+    #[instrument(skip(self))]
+    pub async fn memory_usage(
+        &mut self,
+        key: &str,
+        samples: Option<u64>,
+    ) -> crate::Result<Option<u64>> {
+        let frame = Memory::usage(key, samples).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(size) => Ok(Some(size as u64)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the breakdown of the memory used by the databases of the
+    /// server, as pairs of field and value.
+    #[instrument(skip(self))]
+    pub async fn memory_stats(&mut self) -> crate::Result<Vec<(String, Frame)>> {
+        let frame = Memory::stats().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_pairs(self.read_response().await?)
+    }
+
+    /// Hold the databases of the server for `duration`, no other command
+    /// being applied meanwhile.
+    #[instrument(skip(self))]
+    pub async fn debug_sleep(&mut self, duration: Duration) -> crate::Result<()> {
+        self.ok_cmd(DebugCommand::sleep(duration.as_secs_f64()).into_frame())
+            .await
+    }
+
+    /// Returns the metadata of `key`, such as `Value type:string
+    /// encoding:embstr serializedlength:3 ttl:-1`: its type, the encoding
+    /// Redis would use for its value, its size in bytes and its time to live
+    /// in milliseconds, `-1` if it has none.
+    ///
+    /// Returns `Err` if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn debug_object(&mut self, key: &str) -> crate::Result<String> {
+        let frame = DebugCommand::object(key).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Enable or disable the purge of the expired keys in the background.
+    #[instrument(skip(self))]
+    pub async fn debug_set_active_expire(&mut self, enabled: bool) -> crate::Result<()> {
+        self.ok_cmd(DebugCommand::set_active_expire(enabled).into_frame())
+            .await
+    }
+
+    /// Give a new ID to the replication stream, so that the replicas fully
+    /// synchronize again.
+    #[instrument(skip(self))]
+    pub async fn debug_change_repl_id(&mut self) -> crate::Result<()> {
+        self.ok_cmd(DebugCommand::change_repl_id().into_frame())
+            .await
+    }
+
+    /// Authenticate the connection with `password`, as the default user.
+    ///
+    /// Servers requiring a password reject the other commands until the
+    /// client authenticates.
+    #[instrument(skip(self, password))]
+    pub async fn auth(&mut self, password: &str) -> crate::Result<()> {
+        self.ok_cmd(Auth::new(None, password).into_frame()).await
+    }
+
+    /// Switch the connection to the version `protocol` of the protocol, `2` or
+    /// `3`, if given, and return information about the server as pairs of
+    /// field and value.
+    ///
+    /// With RESP3, replies may use its richer types, such as maps and
+    /// doubles, which the methods of the client accept as well.
+    #[instrument(skip(self))]
+    pub async fn hello(&mut self, protocol: Option<u64>) -> crate::Result<Vec<(String, Frame)>> {
+        let frame = Hello::new(protocol).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_pairs(self.read_response().await?)
+    }
+
+    /// Return the connection to the state of a new connection: the database
+    /// `0` is selected, the transaction and tracking are discarded and the
+    /// client is authenticated again as the default user, if it requires no
+    /// password.
+    #[instrument(skip(self))]
+    pub async fn reset(&mut self) -> crate::Result<()> {
+        let frame = Reset::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The messages sent before the reply, as in the subscribed state,
+        // are dropped.
+        loop {
+            match self.read_response().await? {
+                Frame::Simple(response) if response == "RESET" => return Ok(()),
+                Frame::Array(_) | Frame::Push(_) | Frame::Simple(_) => {}
+                frame => return Err(frame.to_error()),
+            }
+        }
+    }
+
+    /// Returns the ID of the connection, to which other connections may
+    /// redirect their invalidation messages.
+    #[instrument(skip(self))]
+    pub async fn client_id(&mut self) -> crate::Result<u64> {
+        let frame = ClientCommand::id().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the connected clients, one per line, with their ID, address,
+    /// name, age, idle time, selected database, last command and user, such
+    /// as `id=3 addr=127.0.0.1:50712 name= age=2 idle=0 db=0 cmd=client
+    /// user=default`.
+    #[instrument(skip(self))]
+    pub async fn client_list(&mut self) -> crate::Result<String> {
+        let frame = ClientCommand::list().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Name the connection `name`, as shown by `CLIENT LIST`. An empty name
+    /// removes it.
+    #[instrument(skip(self))]
+    pub async fn client_setname(&mut self, name: &str) -> crate::Result<()> {
+        self.ok_cmd(ClientCommand::setname(name).into_frame()).await
+    }
+
+    /// Returns the name of the connection, if set.
+    #[instrument(skip(self))]
+    pub async fn client_getname(&mut self) -> crate::Result<Option<String>> {
+        let frame = ClientCommand::getname().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_string(frame).map(Some),
+        }
+    }
+
+    /// Close the connection of the client at `addr`.
+    ///
+    /// Returns `Err` if there is no such client.
+    #[instrument(skip(self))]
+    pub async fn client_kill_addr(&mut self, addr: &str) -> crate::Result<()> {
+        self.ok_cmd(ClientCommand::kill_addr(addr).into_frame())
+            .await
+    }
+
+    /// Close the connections of the clients matching both `id` and `addr`,
+    /// when given. This connection is spared if `skipme` is `true`.
+    ///
+    /// Returns the number of connections closed.
+    #[instrument(skip(self))]
+    pub async fn client_kill(
+        &mut self,
+        id: Option<u64>,
+        addr: Option<&str>,
+        skipme: bool,
+    ) -> crate::Result<u64> {
+        let addr = addr.map(str::to_string);
+        let frame = ClientCommand::kill(id, addr, skipme).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Hold the commands of the clients for `timeout`, or only the ones which
+    /// may modify the data set if `writes_only` is `true`. `CLIENT` commands
+    /// are never held, so that the clients may be unpaused.
+    #[instrument(skip(self))]
+    pub async fn client_pause(
+        &mut self,
+        timeout: Duration,
+        writes_only: bool,
+    ) -> crate::Result<()> {
+        let timeout = timeout.as_millis() as u64;
+        self.ok_cmd(ClientCommand::pause(timeout, writes_only).into_frame())
+            .await
+    }
+
+    /// End the pause of the clients set by `client_pause`.
+    #[instrument(skip(self))]
+    pub async fn client_unpause(&mut self) -> crate::Result<()> {
+        self.ok_cmd(ClientCommand::unpause().into_frame()).await
+    }
+
+    /// Enable or disable the tracking of the keys read by the connection.
+    ///
+    /// Once a tracked key is modified, it is sent as a message of the
+    /// `__redis__:invalidate` channel to the connection whose ID is
+    /// `redirect`, if it is subscribed to it.
+    #[instrument(skip(self))]
+    pub async fn client_tracking(
+        &mut self,
+        enabled: bool,
+        redirect: Option<u64>,
+    ) -> crate::Result<()> {
+        self.ok_cmd(ClientCommand::tracking(enabled, redirect).into_frame())
+            .await
+    }
+
+    /// Authenticate the connection as `username` with `password`.
+    ///
+    /// The connection may then run the commands the ACL rules of the user
+    /// allow.
+    #[instrument(skip(self, password))]
+    pub async fn auth_user(&mut self, username: &str, password: &str) -> crate::Result<()> {
+        self.ok_cmd(Auth::new(Some(username.to_string()), password).into_frame())
+            .await
+    }
+
+    /// Apply the ACL `rules` to the user `username`, creating it if it does
+    /// not exist, such as `on`, `>password`, `+@read` or `~cache:*`.
+    #[instrument(skip(self))]
+    pub async fn acl_setuser(&mut self, username: &str, rules: Vec<String>) -> crate::Result<()> {
+        self.ok_cmd(Acl::setuser(username, rules).into_frame())
+            .await
+    }
+
+    /// Describe the user `username`, as pairs of field and value, or `None`
+    /// if it does not exist.
+    #[instrument(skip(self))]
+    pub async fn acl_getuser(
+        &mut self,
+        username: &str,
+    ) -> crate::Result<Option<Vec<(String, Frame)>>> {
+        let frame = Acl::getuser(username).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_pairs(frame).map(Some),
+        }
+    }
+
+    /// Delete the users `usernames`, returning the number of them that
+    /// existed.
+    #[instrument(skip(self))]
+    pub async fn acl_deluser(&mut self, usernames: Vec<String>) -> crate::Result<u64> {
+        let frame = Acl::deluser(usernames).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns each user as the rules recreating it.
+    #[instrument(skip(self))]
+    pub async fn acl_list(&mut self) -> crate::Result<Vec<String>> {
+        let frame = Acl::list().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_string).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the name of the user the connection is authenticated as.
+    #[instrument(skip(self))]
+    pub async fn acl_whoami(&mut self) -> crate::Result<String> {
+        let frame = Acl::whoami().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_string(self.read_response().await?)
+    }
+
+    /// Sends a command replying `OK` on success.
+    async fn ok_cmd(&mut self, frame: Frame) -> crate::Result<()> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, only if `key` does not exist.
+    ///
+    /// Returns `true` if the value was set.
+    #[instrument(skip(self))]
+    pub async fn setnx(&mut self, key: &str, value: Bytes) -> crate::Result<bool> {
+        let frame = SetNx::new(key, value).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `seconds`.
+    #[instrument(skip(self))]
+    pub async fn setex(&mut self, key: &str, seconds: i64, value: Bytes) -> crate::Result<()> {
+        self.ok_cmd(SetEx::new(key, seconds, value).into_frame())
+            .await
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `milliseconds`.
+    #[instrument(skip(self))]
+    pub async fn psetex(
+        &mut self,
+        key: &str,
+        milliseconds: i64,
+        value: Bytes,
+    ) -> crate::Result<()> {
+        self.ok_cmd(PSetEx::new(key, milliseconds, value).into_frame())
+            .await
+    }
+
+    /// Get the value of `key` and delete the key.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn getdel(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.get_cmd(GetDel::new(key).into_frame()).await
+    }
+
+    /// Get the value of `key`, and set its time to live to `expiration`. When
+    /// `expiration` is `None`, the key is made persistent.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn getex(
+        &mut self,
+        key: &str,
+        expiration: Option<Duration>,
+    ) -> crate::Result<Option<Bytes>> {
+        let cmd = match expiration {
+            Some(expiration) => GetEx::new(key).expire(expiration),
+            None => GetEx::new(key).persist(true),
+        };
+
+        self.get_cmd(cmd.into_frame()).await
+    }
+
+    /// Sends a command replying with a string value, or `Null` when the key
+    /// does not exist.
+    async fn get_cmd(&mut self, frame: Frame) -> crate::Result<Option<Bytes>> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Copy the value stored at `source`, along with its expiration, to
+    /// `destination`. An existing `destination` is only overwritten when
+    /// `replace` is set.
+    ///
+    /// Returns `true` if the value was copied.
+    #[instrument(skip(self))]
+    pub async fn copy(
+        &mut self,
+        source: &str,
+        destination: &str,
+        replace: bool,
+    ) -> crate::Result<bool> {
+        let frame = Copy::new(source, destination).replace(replace).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Copy the value stored at `source` to `destination` in the database at
+    /// index `db`, like `copy`.
+    #[instrument(skip(self))]
+    pub async fn copy_to_db(
+        &mut self,
+        source: &str,
+        destination: &str,
+        db: u64,
+        replace: bool,
+    ) -> crate::Result<bool> {
+        let frame = Copy::new(source, destination)
+            .db(db)
+            .replace(replace)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Serialize the value stored at `key`, to be stored again by `restore`,
+    /// possibly on another server.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn dump(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.get_cmd(Dump::new(key).into_frame()).await
+    }
+
+    /// Store at `key` the value serialized by `dump` in `payload`, expiring
+    /// after `ttl` if set. An existing `key` is only overwritten when
+    /// `replace` is set, otherwise an error is returned.
+    #[instrument(skip(self, payload))]
+    pub async fn restore(
+        &mut self,
+        key: &str,
+        ttl: Option<Duration>,
+        payload: Bytes,
+        replace: bool,
+    ) -> crate::Result<()> {
+        let ttl = ttl.map_or(0, |ttl| ttl.as_millis() as i64);
+        let frame = Restore::new(key, ttl, payload)
+            .replace(replace)
+            .into_frame();
+        self.ok_cmd(frame).await
+    }
+
+    /// Store at `key` the value serialized by `dump` in `payload`, like
+    /// `restore`, expiring at the Unix time `expires_at`. A time in the past
+    /// stores nothing.
+    #[instrument(skip(self, payload))]
+    pub async fn restore_at(
+        &mut self,
+        key: &str,
+        expires_at: SystemTime,
+        payload: Bytes,
+        replace: bool,
+    ) -> crate::Result<()> {
+        let expires_at = expires_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(1, |since_epoch| since_epoch.as_millis().max(1) as i64);
+        let frame = Restore::new(key, expires_at, payload)
+            .replace(replace)
+            .absttl(true)
+            .into_frame();
+        self.ok_cmd(frame).await
+    }
+
+    /// Transfer `keys`, along with their expiration, to the database `db` of
+    /// the server at `host` and `port`, then remove them. The keys existing on
+    /// the target server are only overwritten when `replace` is set.
+    ///
+    /// `timeout` bounds the time to connect to the target server and to wait
+    /// for each of its replies. Returns `false` if none of the keys exists.
+    #[instrument(skip(self))]
+    pub async fn migrate(
+        &mut self,
+        host: &str,
+        port: u16,
+        keys: Vec<String>,
+        db: u64,
+        timeout: Duration,
+        replace: bool,
+    ) -> crate::Result<bool> {
+        let cmd = Migrate::new(host, port, keys, db, timeout.as_millis() as u64);
+        self.migrate_cmd(cmd.replace(replace)).await
+    }
+
+    /// Transfer `keys` like `migrate`, keeping them once transferred.
+    #[instrument(skip(self))]
+    pub async fn migrate_copy(
+        &mut self,
+        host: &str,
+        port: u16,
+        keys: Vec<String>,
+        db: u64,
+        timeout: Duration,
+        replace: bool,
+    ) -> crate::Result<bool> {
+        let cmd = Migrate::new(host, port, keys, db, timeout.as_millis() as u64);
+        self.migrate_cmd(cmd.copy(true).replace(replace)).await
+    }
+
+    /// The core `MIGRATE` logic, used by `migrate` and `migrate_copy`.
+    async fn migrate_cmd(&mut self, cmd: Migrate) -> crate::Result<bool> {
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(true),
+            Frame::Simple(response) if response == "NOKEY" => Ok(false),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Move `key`, along with its expiration, from the selected database to
+    /// the database at index `db`.
+    ///
+    /// Returns `true` if the key was moved, `false` if it does not exist or
+    /// already exists in the destination database.
+    #[instrument(skip(self))]
+    pub async fn move_key(&mut self, key: &str, db: u64) -> crate::Result<bool> {
+        let frame = Move::new(key, db).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove the specified `keys`, like `del`, but let the server free the
+    /// values in the background.
+    ///
+    /// Returns the number of keys that were removed.
+    #[instrument(skip(self))]
+    pub async fn unlink(&mut self, keys: Vec<String>) -> crate::Result<u64> {
+        let frame = Unlink::new(keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Mark the specified `keys` as accessed, without reading their values.
+    ///
+    /// Returns the number of keys that exist.
+    #[instrument(skip(self))]
+    pub async fn touch(&mut self, keys: Vec<String>) -> crate::Result<u64> {
+        let frame = Touch::new(keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Pop a value from the head of the first non-empty list stored at one of
+    /// `keys`.
+    ///
+    /// When all the lists are empty, the server waits up to `timeout` for a
+    /// value to be pushed, a zero duration waiting forever. Returns the key
+    /// the value was popped from along with the value, or `None` if the
+    /// timeout elapsed.
+    #[instrument(skip(self))]
+    pub async fn blpop(
+        &mut self,
+        keys: Vec<String>,
+        timeout: Duration,
+    ) -> crate::Result<Option<(String, Bytes)>> {
+        self.bpop_cmd(BLPop::new(keys, timeout).into_frame()).await
+    }
+
+    /// Pop a value from the tail of the first non-empty list stored at one of
+    /// `keys`, waiting up to `timeout` for one to be pushed.
+    ///
+    /// Same as `blpop`, but values are popped from the tail of the lists.
+    #[instrument(skip(self))]
+    pub async fn brpop(
+        &mut self,
+        keys: Vec<String>,
+        timeout: Duration,
+    ) -> crate::Result<Option<(String, Bytes)>> {
+        self.bpop_cmd(BRPop::new(keys, timeout).into_frame()).await
+    }
+
+    /// The core blocking pop logic, used by both `blpop` and `brpop`.
+    async fn bpop_cmd(&mut self, frame: Frame) -> crate::Result<Option<(String, Bytes)>> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) if frames.len() == 2 => {
+                let mut frames = frames.into_iter();
+                let key = into_string(next_frame(&mut frames)?)?;
+                let value = into_bytes(next_frame(&mut frames)?)?;
+                Ok(Some((key, value)))
+            }
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Atomically pop a value from the `from` end of the list stored at
+    /// `source` and push it to the `to` end of the list stored at
+    /// `destination`.
+    ///
+    /// Returns the moved value, or `None` if `source` does not exist.
+    #[instrument(skip(self))]
+    pub async fn lmove(
+        &mut self,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> crate::Result<Option<Bytes>> {
+        let frame = LMove::new(source, destination, from, to).into_frame();
+        self.get_cmd(frame).await
+    }
+
+    /// Blocking version of `lmove`: when `source` is empty, the server waits
+    /// up to `timeout` for a value to be pushed, a zero duration waiting
+    /// forever.
+    ///
+    /// Returns the moved value, or `None` if the timeout elapsed.
+    #[instrument(skip(self))]
+    pub async fn blmove(
+        &mut self,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Duration,
+    ) -> crate::Result<Option<Bytes>> {
+        let frame = BLMove::new(source, destination, from, to, timeout).into_frame();
+        self.get_cmd(frame).await
+    }
+
+    /// Pop the last value of the list stored at `source` and push it to the
+    /// head of the list stored at `destination`.
+    ///
+    /// Returns the moved value, or `None` if `source` does not exist.
+    #[instrument(skip(self))]
+    pub async fn rpoplpush(
+        &mut self,
+        source: &str,
+        destination: &str,
+    ) -> crate::Result<Option<Bytes>> {
+        let frame = RPopLPush::new(source, destination).into_frame();
+        self.get_cmd(frame).await
+    }
+
+    /// Blocking version of `rpoplpush`, waiting up to `timeout` for a value
+    /// to be pushed to `source`.
+    ///
+    /// Returns the moved value, or `None` if the timeout elapsed.
+    #[instrument(skip(self))]
+    pub async fn brpoplpush(
+        &mut self,
+        source: &str,
+        destination: &str,
+        timeout: Duration,
+    ) -> crate::Result<Option<Bytes>> {
+        let frame = BRPopLPush::new(source, destination, timeout).into_frame();
+        self.get_cmd(frame).await
+    }
+
+    /// Returns the elements of the list, set or sorted set stored at `key`,
+    /// sorted.
+    ///
+    /// Elements are compared as numbers unless `alpha` is set, in which case
+    /// they are compared as binary strings. `limit` is the number of sorted
+    /// elements to skip along with the maximum number of elements to return.
+    #[instrument(skip(self))]
+    pub async fn sort(
+        &mut self,
+        key: &str,
+        alpha: bool,
+        desc: bool,
+        limit: Option<(i64, i64)>,
+    ) -> crate::Result<Vec<Bytes>> {
+        let frame = Sort::new(key)
+            .alpha(alpha)
+            .desc(desc)
+            .limit(limit)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `sort`, but the sorted elements are stored as a list at
+    /// `destination`, replacing any previous value.
+    ///
+    /// Returns the number of stored elements.
+    #[instrument(skip(self))]
+    pub async fn sort_store(
+        &mut self,
+        key: &str,
+        destination: &str,
+        alpha: bool,
+        desc: bool,
+        limit: Option<(i64, i64)>,
+    ) -> crate::Result<u64> {
+        let frame = Sort::new(key)
+            .alpha(alpha)
+            .desc(desc)
+            .limit(limit)
+            .store(destination)
+            .into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Adds `members`, given as longitude / latitude / member triples, to the
+    /// geospatial index stored at `key`.
+    ///
+    /// Returns the number of members added to the index.
+    #[instrument(skip(self))]
+    pub async fn geoadd(
+        &mut self,
+        key: &str,
+        members: Vec<(f64, f64, Bytes)>,
+    ) -> crate::Result<u64> {
+        let frame = GeoAdd::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get the `(longitude, latitude)` position of each of `members` in the
+    /// geospatial index stored at `key`.
+    ///
+    /// Members that are not in the index have no position.
+    #[instrument(skip(self))]
+    pub async fn geopos(
+        &mut self,
+        key: &str,
+        members: Vec<Bytes>,
+    ) -> crate::Result<Vec<Option<(f64, f64)>>> {
+        let frame = GeoPos::new(key, members).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Null => Ok(None),
+                    frame => into_position(frame).map(Some),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get the distance, expressed in `unit`, between `from` and `to` in the
+    /// geospatial index stored at `key`.
+    ///
+    /// Returns `None` if one of the members is not in the index.
+    #[instrument(skip(self))]
+    pub async fn geodist(
+        &mut self,
+        key: &str,
+        from: Bytes,
+        to: Bytes,
+        unit: GeoUnit,
+    ) -> crate::Result<Option<f64>> {
+        let frame = GeoDist::new(key, from, to, unit).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Null => Ok(None),
+            frame => into_float(frame).map(Some),
+        }
+    }
+
+    /// Get the members of the geospatial index stored at `key` within `shape`
+    /// around `origin`.
+    ///
+    /// Members are sorted from the nearest to the farthest, or the other way
+    /// around when `desc` is set. When `count` is set, at most `count` members
+    /// are returned.
+    #[instrument(skip(self))]
+    pub async fn geosearch(
+        &mut self,
+        key: &str,
+        origin: GeoOrigin,
+        shape: GeoShape,
+        desc: bool,
+        count: Option<u64>,
+    ) -> crate::Result<Vec<Bytes>> {
+        let mut cmd = GeoSearch::new(key, origin, shape).sort(desc);
+        if let Some(count) = count {
+            cmd = cmd.count(count, false);
+        }
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_bytes).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Same as `geosearch`, but each member is returned along with its
+    /// distance to the origin, expressed in the unit of `shape`, and its
+    /// `(longitude, latitude)` position.
+    #[instrument(skip(self))]
+    pub async fn geosearch_detailed(
+        &mut self,
+        key: &str,
+        origin: GeoOrigin,
+        shape: GeoShape,
+        desc: bool,
+        count: Option<u64>,
+    ) -> crate::Result<Vec<(Bytes, f64, (f64, f64))>> {
+        let mut cmd = GeoSearch::new(key, origin, shape)
+            .sort(desc)
+            .withdist(true)
+            .withcoord(true);
+        if let Some(count) = count {
+            cmd = cmd.count(count, false);
+        }
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    Frame::Array(fields) => {
+                        let mut fields = fields.into_iter();
+
+                        match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                            (Some(member), Some(distance), Some(position), None) => Ok((
+                                into_bytes(member)?,
+                                into_float(distance)?,
+                                into_position(position)?,
+                            )),
+                            _ => Err("protocol error; invalid geosearch reply".into()),
+                        }
+                    }
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get the longest common subsequence of the strings stored at `key1` and
+    /// `key2`.
+    #[instrument(skip(self))]
+    pub async fn lcs(&mut self, key1: &str, key2: &str) -> crate::Result<Bytes> {
+        let frame = Lcs::new(key1, key2).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        into_bytes(self.read_response().await?)
+    }
+
+    /// Get the length of the longest common subsequence of the strings stored
+    /// at `key1` and `key2`.
+    #[instrument(skip(self))]
+    pub async fn lcs_len(&mut self, key1: &str, key2: &str) -> crate::Result<u64> {
+        let frame = Lcs::new(key1, key2).len().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get the ranges of the longest common subsequence of the strings stored
+    /// at `key1` and `key2` which are consecutive in both strings, along with
+    /// the length of the subsequence.
+    ///
+    /// Each range is given as the inclusive `(start, end)` offsets in the first
+    /// string followed by the ones in the second string. Ranges shorter than
+    /// `min_match_len` are omitted. Ranges are listed from the end of the
+    /// strings to their start.
+    #[instrument(skip(self))]
+    pub async fn lcs_idx(
+        &mut self,
+        key1: &str,
+        key2: &str,
+        min_match_len: u64,
+    ) -> crate::Result<(Vec<LcsMatch>, u64)> {
+        let frame = Lcs::new(key1, key2).idx(min_match_len, false).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let invalid = || "protocol error; invalid lcs reply".into();
+
+        let frames = match self.read_response().await? {
+            Frame::Array(frames) => frames,
+            frame => return Err(frame.to_error()),
+        };
+
+        let (matches, len) = match &frames[..] {
+            [_, Frame::Array(matches), _, Frame::Integer(len)] => (matches, *len as u64),
+            _ => return Err(invalid()),
+        };
+
+        let matches = matches
+            .iter()
+            .map(|range| match range {
+                Frame::Array(range) => match &range[..] {
+                    [first, second] => Ok(LcsMatch {
+                        first: into_offsets(first).ok_or_else(invalid)?,
+                        second: into_offsets(second).ok_or_else(invalid)?,
+                    }),
+                    _ => Err(invalid()),
+                },
+                _ => Err(invalid()),
+            })
+            .collect::<crate::Result<_>>()?;
+
+        Ok((matches, len))
+    }
+
+    /// Posts `message` to the given `channel`.
+    ///
+    /// Returns the number of subscribers currently listening on the channel.
+    /// There is no guarantee that these subscribers receive the message as they
+    /// may disconnect at any time.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let val = client.publish("foo", "bar".into()).await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        // Convert the `Publish` command into a frame
+        let frame = Publish::new(channel, message).into_frame();
+
+        debug!(request = ?frame);
+
+        // Write the frame to the socket
+        self.connection.write_frame(&frame).await?;
+
+        // Read the response
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Posts `message` to the given shard `channel`.
+    ///
+    /// Returns the number of subscribers currently listening on the shard
+    /// channel.
+    #[instrument(skip(self))]
+    pub async fn spublish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        let frame = SPublish::new(channel, message).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the channels with at least one subscriber, the ones matching
+    /// the glob `pattern` if given.
+    #[instrument(skip(self))]
+    pub async fn pubsub_channels(&mut self, pattern: Option<&str>) -> crate::Result<Vec<String>> {
+        let frame = PubSub::channels(pattern.map(str::to_string)).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_string).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns each of `channels` along with its number of subscribers, not
+    /// counting the subscribers to patterns.
+    #[instrument(skip(self))]
+    pub async fn pubsub_numsub(
+        &mut self,
+        channels: Vec<String>,
+    ) -> crate::Result<Vec<(String, u64)>> {
+        self.numsub_cmd(PubSub::numsub(channels).into_frame()).await
+    }
+
+    /// Returns the shard channels with at least one subscriber, the ones
+    /// matching the glob `pattern` if given.
+    #[instrument(skip(self))]
+    pub async fn pubsub_shardchannels(
+        &mut self,
+        pattern: Option<&str>,
+    ) -> crate::Result<Vec<String>> {
+        let frame = PubSub::shard_channels(pattern.map(str::to_string)).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => frames.into_iter().map(into_string).collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns each of the shard `channels` along with its number of
+    /// subscribers.
+    #[instrument(skip(self))]
+    pub async fn pubsub_shardnumsub(
+        &mut self,
+        channels: Vec<String>,
+    ) -> crate::Result<Vec<(String, u64)>> {
+        self.numsub_cmd(PubSub::shard_numsub(channels).into_frame())
+            .await
+    }
+
+    /// Sends a `PUBSUB NUMSUB` or `SHARDNUMSUB` command, replying each channel
+    /// along with its number of subscribers.
+    async fn numsub_cmd(&mut self, frame: Frame) -> crate::Result<Vec<(String, u64)>> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let frames = match self.read_response().await? {
+            Frame::Array(frames) => frames,
+            frame => return Err(frame.to_error()),
+        };
+
+        frames
+            .chunks(2)
+            .map(|pair| match pair {
+                [channel, Frame::Integer(count)] => {
+                    Ok((into_string(channel.clone())?, *count as u64))
+                }
+                _ => Err(Frame::Array(pair.to_vec()).to_error()),
+            })
+            .collect()
+    }
+
+    /// Returns the number of patterns subscribed to.
+    #[instrument(skip(self))]
+    pub async fn pubsub_numpat(&mut self) -> crate::Result<u64> {
+        let frame = PubSub::numpat().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Subscribes the client to the specified channels.
+    ///
+    /// Once a client issues a subscribe command, it may no longer issue any
+    /// non-pub/sub commands. The function consumes `self` and returns a `Subscriber`.
+    ///
+    /// The `Subscriber` value is used to receive messages as well as manage the
+    /// list of channels the client is subscribed to.
+    #[instrument(skip(self))]
+    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
+        // Issue the subscribe command to the server and wait for confirmation.
+        // The client will then have been transitioned into the "subscriber"
+        // state and may only issue pub/sub commands from that point on.
+        let frame = Subscribe::new(&channels).into_frame();
+        self.subscribe_cmd(frame, "subscribe", &channels).await?;
+
+        // Return the `Subscriber` type
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: channels,
+            subscribed_patterns: vec![],
+            subscribed_shard_channels: vec![],
+        })
+    }
+
+    /// Subscribes the client to the channels matching the specified glob-style
+    /// patterns.
+    ///
+    /// As with `subscribe`, the function consumes `self` and returns a
+    /// `Subscriber`.
+    #[instrument(skip(self))]
+    pub async fn psubscribe(mut self, patterns: Vec<String>) -> crate::Result<Subscriber> {
+        let frame = PSubscribe::new(&patterns).into_frame();
+        self.subscribe_cmd(frame, "psubscribe", &patterns).await?;
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: vec![],
+            subscribed_patterns: patterns,
+            subscribed_shard_channels: vec![],
+        })
+    }
+
+    /// Subscribes the client to the specified shard channels.
+    ///
+    /// As with `subscribe`, the function consumes `self` and returns a
+    /// `Subscriber`.
+    #[instrument(skip(self))]
+    pub async fn ssubscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
+        let frame = SSubscribe::new(&channels).into_frame();
+        self.subscribe_cmd(frame, "ssubscribe", &channels).await?;
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: vec![],
+            subscribed_patterns: vec![],
+            subscribed_shard_channels: channels,
+        })
+    }
+
+    /// Enter monitor mode, receiving every command the server receives from
+    /// now on.
+    ///
+    /// The function consumes `self` and returns a `Monitor`, as the client
+    /// may not issue other commands once monitoring.
+    #[instrument(skip(self))]
+    pub async fn monitor(mut self) -> crate::Result<Monitor> {
+        self.ok_cmd(MonitorCommand::new().into_frame()).await?;
+
+        Ok(Monitor { client: self })
+    }
+
+    /// The core `SUBSCRIBE` logic, used by misc subscribe fns. `frame` is the
+    /// `SUBSCRIBE` or `PSUBSCRIBE` command, `kind` its name.
+    async fn subscribe_cmd(
+        &mut self,
+        frame: Frame,
+        kind: &str,
+        channels: &[String],
+    ) -> crate::Result<()> {
+        debug!(request = ?frame);
+
+        // Write the frame to the socket
+        self.connection.write_frame(&frame).await?;
+
+        // For each channel being subscribed to, the server responds with a
+        // message confirming subscription to that channel.
+        for channel in channels {
+            // Read the response
+            let response = self.read_response().await?;
+
+            // Verify it is confirmation of subscription.
+            match response {
+                Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
+                    // The server responds with an array frame in the form of:
+                    //
+                    // ```
+                    // [ "subscribe", channel, num-subscribed ]
+                    // ```
+                    //
+                    // where channel is the name of the channel and
+                    // num-subscribed is the number of channels that the client
+                    // is currently subscribed to.
+                    [subscribe, schannel, ..] if *subscribe == kind && *schannel == channel => {}
+                    _ => return Err(response.to_error()),
+                },
+                frame => return Err(frame.to_error()),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Reads a response frame from the socket.
+    ///
+    /// If an `Error` frame is received, it is converted to `Err`.
+    async fn read_response(&mut self) -> crate::Result<Frame> {
+        let response = self.connection.read_frame().await?;
+
+        debug!(?response);
+
+        match response {
+            // Error frames are converted to `Err`
+            Some(Frame::Error(msg)) => Err(msg.into()),
+            Some(frame) => Ok(frame),
+            None => {
+                // Receiving `None` here indicates the server has closed the
+                // connection without sending a frame. This is unexpected and is
+                // represented as a "connection reset by peer" error.
+                let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+
+                Err(err.into())
+            }
+        }
+    }
+}
+
+impl Subscriber {
+    /// Leave the subscribed state with `RESET`, returning the client, whose
+    /// connection is reset.
+    ///
+    /// The messages received before the server replies are dropped.
+    #[instrument(skip(self))]
+    pub async fn reset(mut self) -> crate::Result<Client> {
+        self.client.reset().await?;
+        Ok(self.client)
+    }
+
+    /// Returns the set of channels currently subscribed to.
+    pub fn get_subscribed(&self) -> &[String] {
+        &self.subscribed_channels
+    }
+
+    /// Returns the set of patterns currently subscribed to.
+    pub fn get_subscribed_patterns(&self) -> &[String] {
+        &self.subscribed_patterns
+    }
+
+    /// Returns the set of shard channels currently subscribed to.
+    pub fn get_subscribed_shard_channels(&self) -> &[String] {
+        &self.subscribed_shard_channels
+    }
+
+    /// Receive the next message published on a subscribed channel, waiting if
+    /// necessary.
+    ///
+    /// `None` indicates the subscription has been terminated.
+    pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
+        match self.client.connection.read_frame().await? {
+            Some(mframe) => {
+                debug!(?mframe);
+
+                match mframe {
+                    Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
+                        [message, channel, content]
+                            if *message == "message" || *message == "smessage" =>
+                        {
+                            Ok(Some(Message {
+                                channel: channel.to_string(),
+                                content: Bytes::from(content.to_string()),
+                                pattern: None,
+                            }))
+                        }
+                        [message, pattern, channel, content] if *message == "pmessage" => {
+                            Ok(Some(Message {
+                                channel: channel.to_string(),
+                                content: Bytes::from(content.to_string()),
+                                pattern: Some(pattern.to_string()),
+                            }))
+                        }
+                        _ => Err(mframe.to_error()),
                     },
                     frame => Err(frame.to_error()),
                 }
@@ -411,7 +5312,10 @@ impl Subscriber {
     #[instrument(skip(self))]
     pub async fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         // Issue the subscribe command
-        self.client.subscribe_cmd(channels).await?;
+        let frame = Subscribe::new(channels).into_frame();
+        self.client
+            .subscribe_cmd(frame, "subscribe", channels)
+            .await?;
 
         // Update the set of subscribed channels.
         self.subscribed_channels
@@ -420,54 +5324,501 @@ impl Subscriber {
         Ok(())
     }
 
+    /// Subscribe to a list of new patterns
+    #[instrument(skip(self))]
+    pub async fn psubscribe(&mut self, patterns: &[String]) -> crate::Result<()> {
+        let frame = PSubscribe::new(patterns).into_frame();
+        self.client
+            .subscribe_cmd(frame, "psubscribe", patterns)
+            .await?;
+
+        self.subscribed_patterns
+            .extend(patterns.iter().map(Clone::clone));
+
+        Ok(())
+    }
+
+    /// Subscribe to a list of new shard channels
+    #[instrument(skip(self))]
+    pub async fn ssubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
+        let frame = SSubscribe::new(channels).into_frame();
+        self.client
+            .subscribe_cmd(frame, "ssubscribe", channels)
+            .await?;
+
+        self.subscribed_shard_channels
+            .extend(channels.iter().map(Clone::clone));
+
+        Ok(())
+    }
+
     /// Unsubscribe to a list of new channels
     #[instrument(skip(self))]
     pub async fn unsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
-        let frame = Unsubscribe::new(&channels).into_frame();
+        let frame = Unsubscribe::new(channels).into_frame();
+        unsubscribe_cmd(
+            &mut self.client,
+            frame,
+            "unsubscribe",
+            channels,
+            &mut self.subscribed_channels,
+        )
+        .await
+    }
 
-        debug!(request = ?frame);
+    /// Unsubscribe to a list of patterns
+    #[instrument(skip(self))]
+    pub async fn punsubscribe(&mut self, patterns: &[String]) -> crate::Result<()> {
+        let frame = PUnsubscribe::new(patterns).into_frame();
+        unsubscribe_cmd(
+            &mut self.client,
+            frame,
+            "punsubscribe",
+            patterns,
+            &mut self.subscribed_patterns,
+        )
+        .await
+    }
 
-        // Write the frame to the socket
-        self.client.connection.write_frame(&frame).await?;
-
-        // if the input channel list is empty, server acknowledges as unsubscribing
-        // from all subscribed channels, so we assert that the unsubscribe list received
-        // matches the client subscribed one
-        let num = if channels.is_empty() {
-            self.subscribed_channels.len()
-        } else {
-            channels.len()
-        };
+    /// Unsubscribe to a list of shard channels
+    #[instrument(skip(self))]
+    pub async fn sunsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
+        let frame = SUnsubscribe::new(channels).into_frame();
+        unsubscribe_cmd(
+            &mut self.client,
+            frame,
+            "sunsubscribe",
+            channels,
+            &mut self.subscribed_shard_channels,
+        )
+        .await
+    }
+}
 
-        // Read the response
-        for _ in 0..num {
-            let response = self.client.read_response().await?;
+impl Monitor {
+    /// Receive the next command received by the server, as its time, the
+    /// database and address of the client which sent it, and its quoted
+    /// arguments:
+    ///
+    /// ```text
+    /// 1339518083.107412 [0 127.0.0.1:60866] "set" "foo" "bar"
+    /// ```
+    ///
+    /// `None` is returned if the server closed the connection.
+    pub async fn next_command(&mut self) -> crate::Result<Option<String>> {
+        match self.client.connection.read_frame().await? {
+            Some(frame) => {
+                debug!(?frame);
+                into_string(frame).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
 
-            match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
-                    [unsubscribe, channel, ..] if *unsubscribe == "unsubscribe" => {
-                        let len = self.subscribed_channels.len();
+    /// Stop monitoring with `RESET`, returning the client, whose connection
+    /// is reset.
+    ///
+    /// The commands received before the server replies are dropped.
+    pub async fn reset(mut self) -> crate::Result<Client> {
+        self.client.reset().await?;
+        Ok(self.client)
+    }
+}
 
-                        if len == 0 {
-                            // There must be at least one channel
-                            return Err(response.to_error());
-                        }
+/// The core `UNSUBSCRIBE` logic. `frame` is the `UNSUBSCRIBE` or `PUNSUBSCRIBE`
+/// command, `kind` its name, and `subscribed` the channels or patterns
+/// subscribed to, updated as the server confirms.
+async fn unsubscribe_cmd(
+    client: &mut Client,
+    frame: Frame,
+    kind: &str,
+    channels: &[String],
+    subscribed: &mut Vec<String>,
+) -> crate::Result<()> {
+    debug!(request = ?frame);
 
-                        // unsubscribed channel should exist in the subscribed list at this point
-                        self.subscribed_channels.retain(|c| *channel != &c[..]);
+    // Write the frame to the socket
+    client.connection.write_frame(&frame).await?;
 
-                        // Only a single channel should be removed from the
-                        // list of subscribed channels.
-                        if self.subscribed_channels.len() != len - 1 {
-                            return Err(response.to_error());
-                        }
+    // if the input channel list is empty, server acknowledges as unsubscribing
+    // from all subscribed channels, so we assert that the unsubscribe list received
+    // matches the client subscribed one
+    let num = if channels.is_empty() {
+        subscribed.len()
+    } else {
+        channels.len()
+    };
+
+    // Read the response
+    for _ in 0..num {
+        let response = client.read_response().await?;
+
+        match response {
+            Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
+                [unsubscribe, channel, ..] if *unsubscribe == kind => {
+                    let len = subscribed.len();
+
+                    if len == 0 {
+                        // There must be at least one channel
+                        return Err(response.to_error());
                     }
-                    _ => return Err(response.to_error()),
-                },
-                frame => return Err(frame.to_error()),
-            };
+
+                    // unsubscribed channel should exist in the subscribed list at this point
+                    subscribed.retain(|c| *channel != &c[..]);
+
+                    // Only a single channel should be removed from the
+                    // list of subscribed channels.
+                    if subscribed.len() != len - 1 {
+                        return Err(response.to_error());
+                    }
+                }
+                _ => return Err(response.to_error()),
+            },
+            frame => return Err(frame.to_error()),
+        };
+    }
+
+    Ok(())
+}
+
+/// Converts a `Simple` or `Bulk` response frame into raw bytes.
+fn into_bytes(frame: Frame) -> crate::Result<Bytes> {
+    match frame {
+        Frame::Simple(value) => Ok(value.into()),
+        Frame::Bulk(value) => Ok(value),
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Converts a `Simple` or `Bulk` response frame into a string.
+fn into_string(frame: Frame) -> crate::Result<String> {
+    match frame {
+        Frame::Simple(value) => Ok(value),
+        Frame::Bulk(value) => {
+            String::from_utf8(value.to_vec()).map_err(|_| "protocol error; invalid string".into())
+        }
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Converts a `Map` response frame, or an array of alternating fields and
+/// values as sent with RESP2, into pairs of field and value.
+/// Returns the next frame of a reply made of several frames.
+///
+/// Returns `Err` if the reply holds fewer frames than expected.
+fn next_frame(frames: &mut impl Iterator<Item = Frame>) -> crate::Result<Frame> {
+    frames
+        .next()
+        .ok_or_else(|| "protocol error; unexpected frame".into())
+}
+
+fn into_pairs(frame: Frame) -> crate::Result<Vec<(String, Frame)>> {
+    let entries = match frame {
+        Frame::Map(entries) => entries,
+        Frame::Array(frames) if frames.len() % 2 == 0 => {
+            let mut frames = frames.into_iter();
+            let mut entries = vec![];
+            while let (Some(field), Some(value)) = (frames.next(), frames.next()) {
+                entries.push((field, value));
+            }
+            entries
         }
+        frame => return Err(frame.to_error()),
+    };
 
-        Ok(())
+    entries
+        .into_iter()
+        .map(|(field, value)| Ok((into_string(field)?, value)))
+        .collect()
+}
+
+/// Converts an entry of the reply of `COMMAND INFO` into the description of
+/// a command, `None` if the command is unknown.
+fn into_command_info(frame: Frame) -> crate::Result<Option<CommandInfo>> {
+    let frames = match frame {
+        Frame::Null => return Ok(None),
+        Frame::Array(frames) => frames,
+        frame => return Err(frame.to_error()),
+    };
+
+    use Frame::{Array, Integer};
+
+    match &frames[..] {
+        [name, Integer(arity), Array(flags), Integer(first), Integer(last), Integer(step)] => {
+            Ok(Some(CommandInfo {
+                name: into_string(name.clone())?,
+                arity: *arity,
+                flags: flags
+                    .iter()
+                    .cloned()
+                    .map(into_string)
+                    .collect::<crate::Result<_>>()?,
+                first_key: *first,
+                last_key: *last,
+                step: *step,
+            }))
+        }
+        _ => Err("protocol error; invalid command description".into()),
+    }
+}
+
+/// Converts an entry of the reply of `SLOWLOG GET`.
+fn into_role(frame: Frame) -> crate::Result<ReplicationRole> {
+    use Frame::{Array, Integer};
+
+    let frames = match frame {
+        Array(frames) => frames,
+        frame => return Err(frame.to_error()),
+    };
+
+    match &frames[..] {
+        [role, Integer(offset), Array(replicas)] if *role == "master" => {
+            let replicas = replicas
+                .iter()
+                .map(|replica| match replica {
+                    Array(fields) if fields.len() == 3 => Ok(ReplicaInfo {
+                        host: into_string(fields[0].clone())?,
+                        port: into_string(fields[1].clone())?.parse()?,
+                        offset: into_string(fields[2].clone())?.parse()?,
+                    }),
+                    frame => Err(frame.to_error()),
+                })
+                .collect::<crate::Result<_>>()?;
+
+            Ok(ReplicationRole::Primary {
+                offset: *offset as u64,
+                replicas,
+            })
+        }
+        [role, host, Integer(port), state, Integer(offset)] if *role == "slave" => {
+            Ok(ReplicationRole::Replica {
+                host: into_string(host.clone())?,
+                port: *port as u16,
+                state: into_string(state.clone())?,
+                offset: Some(*offset as u64).filter(|_| *offset >= 0),
+            })
+        }
+        _ => Err("protocol error; invalid role".into()),
+    }
+}
+
+fn into_slowlog_entry(frame: Frame) -> crate::Result<SlowlogEntry> {
+    use Frame::{Array, Integer};
+
+    let frames = match frame {
+        Array(frames) => frames,
+        frame => return Err(frame.to_error()),
+    };
+
+    match &frames[..] {
+        [Integer(id), Integer(timestamp), Integer(duration), Array(args), addr, ..] => {
+            Ok(SlowlogEntry {
+                id: *id as u64,
+                time: SystemTime::UNIX_EPOCH + Duration::from_secs(*timestamp as u64),
+                duration: Duration::from_micros(*duration as u64),
+                args: args
+                    .iter()
+                    .cloned()
+                    .map(into_bytes)
+                    .collect::<crate::Result<_>>()?,
+                addr: into_string(addr.clone())?,
+            })
+        }
+        _ => Err("protocol error; invalid slow log entry".into()),
+    }
+}
+
+fn into_latency_spike(frame: Frame) -> crate::Result<LatencySpike> {
+    use Frame::{Array, Integer};
+
+    let frames = match frame {
+        Array(frames) => frames,
+        frame => return Err(frame.to_error()),
+    };
+
+    match &frames[..] {
+        [event, Integer(time), Integer(latency), Integer(max)] => Ok(LatencySpike {
+            event: into_string(event.clone())?,
+            time: SystemTime::UNIX_EPOCH + Duration::from_secs(*time as u64),
+            latency: Duration::from_millis(*latency as u64),
+            max: Duration::from_millis(*max as u64),
+        }),
+        _ => Err("protocol error; invalid latency spike".into()),
+    }
+}
+
+/// Converts the reply of a scan command into the next cursor and the page of
+/// elements.
+fn into_scan_page(frame: Frame) -> crate::Result<(u64, Vec<Frame>)> {
+    match frame {
+        Frame::Array(frames) => {
+            let mut frames = frames.into_iter();
+
+            match (frames.next(), frames.next(), frames.next()) {
+                (Some(cursor), Some(Frame::Array(page)), None) => {
+                    let cursor = into_string(cursor)?
+                        .parse()
+                        .map_err(|_| "protocol error; invalid cursor")?;
+                    Ok((cursor, page))
+                }
+                _ => Err("protocol error; invalid scan reply".into()),
+            }
+        }
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Converts a `Simple` or `Bulk` response frame into a floating point number.
+fn into_float(frame: Frame) -> crate::Result<f64> {
+    match frame {
+        Frame::Double(value) => Ok(value),
+        frame => into_string(frame)?
+            .parse()
+            .map_err(|_| "protocol error; invalid float".into()),
+    }
+}
+
+/// Converts a flat array of alternating fields and values, as returned by the
+/// hash commands, into pairs of field and value. In RESP3, the fields and
+/// values are a map.
+fn into_fields(frame: Frame) -> crate::Result<Vec<(String, Bytes)>> {
+    match frame {
+        Frame::Map(entries) => entries
+            .into_iter()
+            .map(|(field, value)| Ok((into_string(field)?, into_bytes(value)?)))
+            .collect(),
+        Frame::Array(frames) => {
+            let mut fields = Vec::with_capacity(frames.len() / 2);
+            let mut frames = frames.into_iter();
+
+            while let Some(field) = frames.next() {
+                let value = match frames.next() {
+                    Some(value) => value,
+                    None => return Err(field.to_error()),
+                };
+
+                fields.push((into_string(field)?, into_bytes(value)?));
+            }
+
+            Ok(fields)
+        }
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Converts a flat array of alternating members and scores, as returned by
+/// sorted set commands with `WITHSCORES`, into pairs of member and score.
+fn into_scored_members(frame: Frame) -> crate::Result<Vec<(Bytes, f64)>> {
+    match frame {
+        Frame::Array(frames) => {
+            let mut members = Vec::with_capacity(frames.len() / 2);
+            let mut frames = frames.into_iter();
+
+            while let Some(member) = frames.next() {
+                let score = match frames.next() {
+                    Some(score) => score,
+                    None => return Err(member.to_error()),
+                };
+
+                members.push((into_bytes(member)?, into_float(score)?));
+            }
+
+            Ok(members)
+        }
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Converts a `[start, end]` array of integers, as returned by `LCS` with
+/// `IDX`, into a pair of offsets.
+fn into_offsets(frame: &Frame) -> Option<(u64, u64)> {
+    match frame {
+        Frame::Array(offsets) => match &offsets[..] {
+            [Frame::Integer(start), Frame::Integer(end)] => Some((*start as u64, *end as u64)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Converts a `[longitude, latitude]` array, as returned by the geospatial
+/// commands, into a position.
+fn into_position(frame: Frame) -> crate::Result<(f64, f64)> {
+    match frame {
+        Frame::Array(frames) => {
+            let mut frames = frames.into_iter();
+
+            match (frames.next(), frames.next(), frames.next()) {
+                (Some(lon), Some(lat), None) => Ok((into_float(lon)?, into_float(lat)?)),
+                _ => Err("protocol error; invalid position".into()),
+            }
+        }
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// Converts entries read from streams, as returned by `XREAD`, into pairs of
+/// stream key and entries.
+///
+/// The server responds with an array of `[key, entries]` arrays, or with
+/// `Null` when no stream has entries to return.
+fn into_streams(frame: Frame) -> crate::Result<Vec<(String, Vec<StreamEntry>)>> {
+    match frame {
+        Frame::Array(frames) => frames
+            .into_iter()
+            .map(|frame| match frame {
+                Frame::Array(frames) if frames.len() == 2 => {
+                    let mut frames = frames.into_iter();
+                    let key = into_string(next_frame(&mut frames)?)?;
+                    let entries = into_entries(next_frame(&mut frames)?)?;
+                    Ok((key, entries))
+                }
+                frame => Err(frame.to_error()),
+            })
+            .collect(),
+        Frame::Null => Ok(vec![]),
+        frame => Err(frame.to_error()),
     }
 }
+
+/// Converts an array of stream entries, as returned by `XRANGE`, into
+/// `StreamEntry` values.
+///
+/// Each entry is an array made of the entry ID and of a flat array of
+/// alternating fields and values.
+fn into_entries(frame: Frame) -> crate::Result<Vec<StreamEntry>> {
+    let entries = match frame {
+        Frame::Array(entries) => entries,
+        frame => return Err(frame.to_error()),
+    };
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let (id, values) = match entry {
+                Frame::Array(entry) if entry.len() == 2 => {
+                    let mut entry = entry.into_iter();
+                    (next_frame(&mut entry)?, next_frame(&mut entry)?)
+                }
+                frame => return Err(frame.to_error()),
+            };
+
+            let values = match values {
+                Frame::Array(values) if values.len() % 2 == 0 => values,
+                frame => return Err(frame.to_error()),
+            };
+
+            let mut fields = Vec::with_capacity(values.len() / 2);
+            let mut values = values.into_iter();
+
+            while let (Some(field), Some(value)) = (values.next(), values.next()) {
+                fields.push((into_string(field)?, into_bytes(value)?));
+            }
+
+            Ok(StreamEntry {
+                id: into_string(id)?,
+                fields,
+            })
+        })
+        .collect()
+}
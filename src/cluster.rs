@@ -0,0 +1,337 @@
+//! Cluster mode.
+//!
+//! In cluster mode, the keyspace is split into `SLOTS` hash slots, each key
+//! mapping to one of them. Each slot is served by a single node of the
+//! cluster. A node replies to the commands accessing keys of a slot it does
+//! not serve with a `MOVED` error, giving the address of the node serving the
+//! slot, to which the client sends the command instead.
+//!
+//! The keys accessed by a command must all map to the same slot. Only the
+//! part of a key between braces, its hash tag, is hashed if there is one, so
+//! that related keys such as `{user1}.following` and `{user1}.followers` are
+//! accessed together.
+//!
+//! A node serves the slots assigned to it with `CLUSTER ADDSLOTS`, and learns
+//! the slots served by another node with `CLUSTER MEET`. Nodes do not exchange
+//! their configuration afterwards: `CLUSTER SETSLOT` assigns a slot to a node
+//! on each node of the cluster.
+//!
+//! A slot is moved from a node to another by setting it as migrating on the
+//! source with `CLUSTER SETSLOT slot MIGRATING`, and as importing on the target
+//! with `CLUSTER SETSLOT slot IMPORTING`. In the meantime, the source still
+//! serves the keys it holds, and replies with an `ASK` error for the others.
+//! The client then sends the command to the target, preceded by `ASKING`.
+
+use crate::db::random_u64;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Number of hash slots the keyspace is split into.
+pub(crate) const SLOTS: usize = 16384;
+
+/// Returns the hash slot of `key`, the CRC16 of its hash tag modulo `SLOTS`.
+pub(crate) fn key_slot(key: &str) -> u16 {
+    crc16(hash_tag(key.as_bytes())) % SLOTS as u16
+}
+
+/// Returns the part of `key` which is hashed: the characters between the
+/// first `{` and the first `}` following it, if there are any, or the whole
+/// key otherwise.
+fn hash_tag(key: &[u8]) -> &[u8] {
+    let start = match key.iter().position(|&c| c == b'{') {
+        Some(start) => start + 1,
+        None => return key,
+    };
+
+    match key[start..].iter().position(|&c| c == b'}') {
+        Some(len) if len > 0 => &key[start..start + len],
+        _ => key,
+    }
+}
+
+/// CRC16, as specified by XMODEM: polynomial `0x1021` and initial value `0`.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// A node of the cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Node {
+    /// Random ID identifying the node
+    pub(crate) id: String,
+
+    /// Address clients connect to
+    pub(crate) host: String,
+    pub(crate) port: u16,
+}
+
+/// How a slot is set by `CLUSTER SETSLOT`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SlotState {
+    /// The slot is served by the node with the given ID.
+    Node(String),
+
+    /// The slot, served by this node, is moved to the node with the given ID.
+    Migrating(String),
+
+    /// The slot is moved to this node from the node with the given ID.
+    Importing(String),
+
+    /// The slot is no longer migrating nor importing.
+    Stable,
+}
+
+/// Error replied to a client sending a command to a node which does not serve
+/// the slot of its keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Redirect {
+    /// The keys map to different slots, the command cannot be served by a
+    /// single node.
+    CrossSlot,
+
+    /// The slot is served by the node at `address`.
+    Moved { slot: u16, address: String },
+
+    /// The slot is migrating to the node at `address`, which may hold the
+    /// keys.
+    Ask { slot: u16, address: String },
+
+    /// The slot is served by no node.
+    Down,
+}
+
+/// Configuration of the cluster, as known by this node.
+#[derive(Debug)]
+pub(crate) struct Cluster {
+    /// Nodes of the cluster, the first one being this node. Nodes are never
+    /// removed, slots refer to them by their position.
+    nodes: Vec<Node>,
+
+    /// Node serving each slot, if any.
+    slots: Vec<Option<usize>>,
+
+    /// Nodes the slots set as migrating are moved to.
+    migrating: HashMap<u16, usize>,
+
+    /// Nodes the slots set as importing are moved from.
+    importing: HashMap<u16, usize>,
+}
+
+impl Cluster {
+    /// Create a new cluster made of this node alone, reachable at `host` and
+    /// `port`, serving no slot.
+    pub(crate) fn new(host: String, port: u16) -> Cluster {
+        let myself = Node {
+            id: format!(
+                "{:016x}{:016x}{:08x}",
+                random_u64(),
+                random_u64(),
+                random_u64() as u32
+            ),
+            host,
+            port,
+        };
+
+        Cluster {
+            nodes: vec![myself],
+            slots: vec![None; SLOTS],
+            migrating: HashMap::new(),
+            importing: HashMap::new(),
+        }
+    }
+
+    /// Returns this node.
+    pub(crate) fn myself(&self) -> &Node {
+        &self.nodes[0]
+    }
+
+    /// Assign `slots` to this node.
+    ///
+    /// Returns `Err` and assigns none of them if one of the slots is already
+    /// served by a node.
+    pub(crate) fn add_slots(&mut self, slots: &[u16]) -> Result<(), String> {
+        if let Some(slot) = slots
+            .iter()
+            .find(|slot| self.slots[**slot as usize].is_some())
+        {
+            return Err(format!("ERR Slot {} is already busy", slot));
+        }
+
+        for &slot in slots {
+            self.slots[slot as usize] = Some(0);
+        }
+        Ok(())
+    }
+
+    /// Stop serving `slots`, whichever node serves them.
+    ///
+    /// Returns `Err` and unassigns none of them if one of the slots is not
+    /// served by any node.
+    pub(crate) fn del_slots(&mut self, slots: &[u16]) -> Result<(), String> {
+        if let Some(slot) = slots
+            .iter()
+            .find(|slot| self.slots[**slot as usize].is_none())
+        {
+            return Err(format!("ERR Slot {} is already unassigned", slot));
+        }
+
+        for &slot in slots {
+            self.slots[slot as usize] = None;
+            self.migrating.remove(&slot);
+            self.importing.remove(&slot);
+        }
+        Ok(())
+    }
+
+    /// Add `node` to the cluster, serving `slots`. Slots served by this node
+    /// are left unchanged.
+    ///
+    /// If the node is already known, its address is updated.
+    pub(crate) fn meet(&mut self, node: Node, slots: &[u16]) {
+        let index = match self.nodes.iter().position(|known| known.id == node.id) {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for &slot in slots {
+            if self.slots[slot as usize] != Some(0) {
+                self.slots[slot as usize] = Some(index);
+            }
+        }
+    }
+
+    /// Set `slot` as given by `state`.
+    pub(crate) fn set_slot(&mut self, slot: u16, state: SlotState) -> Result<(), String> {
+        let node = |id: &str| {
+            self.nodes
+                .iter()
+                .position(|node| node.id == id)
+                .ok_or_else(|| format!("ERR I don't know about node {}", id))
+        };
+
+        match state {
+            SlotState::Node(id) => {
+                let index = node(&id)?;
+                self.slots[slot as usize] = Some(index);
+                self.migrating.remove(&slot);
+                self.importing.remove(&slot);
+            }
+            SlotState::Migrating(id) => {
+                let index = node(&id)?;
+                if self.slots[slot as usize] != Some(0) {
+                    return Err(format!("ERR I'm not the owner of hash slot {}", slot));
+                }
+                self.migrating.insert(slot, index);
+            }
+            SlotState::Importing(id) => {
+                let index = node(&id)?;
+                if self.slots[slot as usize] == Some(0) {
+                    return Err(format!("ERR I'm already the owner of hash slot {}", slot));
+                }
+                self.importing.insert(slot, index);
+            }
+            SlotState::Stable => {
+                self.migrating.remove(&slot);
+                self.importing.remove(&slot);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the ranges of consecutive slots served by the same node, along
+    /// with the node, in the order of the slots.
+    pub(crate) fn ranges(&self) -> Vec<(u16, u16, &Node)> {
+        let mut ranges: Vec<(u16, u16, usize)> = vec![];
+
+        for (slot, index) in self.slots.iter().enumerate() {
+            let index = match index {
+                Some(index) => *index,
+                None => continue,
+            };
+
+            match ranges.last_mut() {
+                Some((_, end, last)) if *last == index && *end as usize + 1 == slot => {
+                    *end = slot as u16;
+                }
+                _ => ranges.push((slot as u16, slot as u16, index)),
+            }
+        }
+
+        ranges
+            .into_iter()
+            .map(|(start, end, index)| (start, end, &self.nodes[index]))
+            .collect()
+    }
+
+    /// Returns the nodes of the cluster, this node first.
+    pub(crate) fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Check that this node serves the keys of `slot`.
+    ///
+    /// `asking` is `true` if the client sent `ASKING` before the command.
+    /// `missing` is called when the slot is migrating, to check whether one
+    /// of the keys is no longer held by this node.
+    pub(crate) fn route(
+        &self,
+        slot: u16,
+        asking: bool,
+        missing: impl FnOnce() -> bool,
+    ) -> Result<(), Redirect> {
+        let address = |index: usize| {
+            let node = &self.nodes[index];
+            format!("{}:{}", node.host, node.port)
+        };
+
+        if asking && self.importing.contains_key(&slot) {
+            return Ok(());
+        }
+
+        match self.slots[slot as usize] {
+            Some(0) => match self.migrating.get(&slot) {
+                Some(&index) if missing() => Err(Redirect::Ask {
+                    slot,
+                    address: address(index),
+                }),
+                _ => Ok(()),
+            },
+            Some(index) => Err(Redirect::Moved {
+                slot,
+                address: address(index),
+            }),
+            None => Err(Redirect::Down),
+        }
+    }
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Redirect::CrossSlot => "CROSSSLOT Keys in request don't hash to the same slot".fmt(fmt),
+            Redirect::Moved { slot, address } => write!(fmt, "MOVED {} {}", slot, address),
+            Redirect::Ask { slot, address } => write!(fmt, "ASK {} {}", slot, address),
+            Redirect::Down => "CLUSTERDOWN Hash slot not served".fmt(fmt),
+        }
+    }
+}
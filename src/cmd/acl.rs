@@ -0,0 +1,211 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspects or modifies the users clients authenticate as.
+///
+/// # Subcommands
+///
+/// * SETUSER `username` [`rule` ...] -- Apply the rules to the user, creating
+///   it if it does not exist. Rules enable (`on`) or disable (`off`) the user,
+///   add (`>password`, `#hash`) or remove (`<password`, `!hash`) passwords,
+///   allow (`+command`, `+@category`) or deny (`-command`, `-@category`)
+///   commands, and add patterns of the keys (`~pattern`) and channels
+///   (`&pattern`) the user may access.
+/// * GETUSER `username` -- Returns the flags, the SHA-256 digests of the
+///   passwords, the rules on the commands and the patterns of the keys and
+///   channels of the user, or nil if it does not exist.
+/// * DELUSER `username` [`username` ...] -- Delete the users, returning the
+///   number of them that existed. The `default` user cannot be deleted.
+/// * LIST -- Returns each user as the rules recreating it.
+/// * WHOAMI -- Returns the name of the user the connection is authenticated
+///   as.
+#[derive(Debug)]
+pub struct Acl {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    SetUser(String, Vec<String>),
+    GetUser(String),
+    DelUser(Vec<String>),
+    List,
+    WhoAmI,
+}
+
+impl Acl {
+    /// Create a new `Acl` command which applies `rules` to the user
+    /// `username`.
+    pub fn setuser(username: impl ToString, rules: Vec<String>) -> Acl {
+        Acl {
+            subcommand: Subcommand::SetUser(username.to_string(), rules),
+        }
+    }
+
+    /// Create a new `Acl` command which describes the user `username`.
+    pub fn getuser(username: impl ToString) -> Acl {
+        Acl {
+            subcommand: Subcommand::GetUser(username.to_string()),
+        }
+    }
+
+    /// Create a new `Acl` command which deletes the users `usernames`.
+    pub fn deluser(usernames: Vec<String>) -> Acl {
+        Acl {
+            subcommand: Subcommand::DelUser(usernames),
+        }
+    }
+
+    /// Create a new `Acl` command which lists the users.
+    pub fn list() -> Acl {
+        Acl {
+            subcommand: Subcommand::List,
+        }
+    }
+
+    /// Create a new `Acl` command returning the user of the connection.
+    pub fn whoami() -> Acl {
+        Acl {
+            subcommand: Subcommand::WhoAmI,
+        }
+    }
+
+    /// Parse an `Acl` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ACL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Acl` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// ACL SETUSER username [rule ...]
+    /// ACL GETUSER username
+    /// ACL DELUSER username [username ...]
+    /// ACL LIST
+    /// ACL WHOAMI
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Acl> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "SETUSER" => {
+                let username = parse.next_string()?;
+                Subcommand::SetUser(username, parse_strings(parse)?)
+            }
+            "GETUSER" => Subcommand::GetUser(parse.next_string()?),
+            "DELUSER" => {
+                let mut usernames = vec![parse.next_string()?];
+                usernames.extend(parse_strings(parse)?);
+                Subcommand::DelUser(usernames)
+            }
+            "LIST" => Subcommand::List,
+            "WHOAMI" => Subcommand::WhoAmI,
+            _ => return Err(format!("unknown `ACL` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Acl { subcommand })
+    }
+
+    /// Apply the `Acl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let bulk = |s: String| Frame::Bulk(Bytes::from(s.into_bytes()));
+
+        let response = match self.subcommand {
+            Subcommand::SetUser(username, rules) => match db.acl_setuser(&username, &rules) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::GetUser(username) => match db.acl_getuser(&username) {
+                Some(user) => {
+                    let flags = user
+                        .flags()
+                        .into_iter()
+                        .map(|flag| bulk(flag.to_string()))
+                        .collect();
+                    let passwords = user.passwords().iter().cloned().map(bulk).collect();
+
+                    Frame::Map(vec![
+                        (bulk("flags".to_string()), Frame::Array(flags)),
+                        (bulk("passwords".to_string()), Frame::Array(passwords)),
+                        (bulk("commands".to_string()), bulk(user.commands())),
+                        (bulk("keys".to_string()), bulk(user.keys())),
+                        (bulk("channels".to_string()), bulk(user.channels())),
+                    ])
+                }
+                None => Frame::Null,
+            },
+            Subcommand::DelUser(usernames) => match db.acl_deluser(&usernames) {
+                Ok(deleted) => Frame::Integer(deleted as i64),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::List => Frame::Array(db.acl_list().into_iter().map(bulk).collect()),
+            Subcommand::WhoAmI => match db.acl_whoami() {
+                Some(username) => bulk(username),
+                None => Frame::Null,
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Acl` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("acl".as_bytes()));
+        match self.subcommand {
+            Subcommand::SetUser(username, rules) => {
+                frame.push_bulk(Bytes::from("setuser".as_bytes()));
+                frame.push_bulk(Bytes::from(username.into_bytes()));
+                for rule in rules {
+                    frame.push_bulk(Bytes::from(rule.into_bytes()));
+                }
+            }
+            Subcommand::GetUser(username) => {
+                frame.push_bulk(Bytes::from("getuser".as_bytes()));
+                frame.push_bulk(Bytes::from(username.into_bytes()));
+            }
+            Subcommand::DelUser(usernames) => {
+                frame.push_bulk(Bytes::from("deluser".as_bytes()));
+                for username in usernames {
+                    frame.push_bulk(Bytes::from(username.into_bytes()));
+                }
+            }
+            Subcommand::List => frame.push_bulk(Bytes::from("list".as_bytes())),
+            Subcommand::WhoAmI => frame.push_bulk(Bytes::from("whoami".as_bytes())),
+        }
+        frame
+    }
+}
+
+/// Parse the remaining strings of the frame.
+fn parse_strings(parse: &mut Parse) -> crate::Result<Vec<String>> {
+    let mut strings = vec![];
+    loop {
+        match parse.next_string() {
+            Ok(s) => strings.push(s),
+            Err(ParseError::EndOfStream) => return Ok(strings),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Appends `value` at the end of the string stored at `key`.
+///
+/// If `key` does not exist, it is created and set to `value`, so `APPEND`
+/// behaves like `SET` in this case.
+///
+/// Integer reply: the length of the string after the append operation.
+#[derive(Debug)]
+pub struct Append {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Value to append
+    value: Bytes,
+}
+
+impl Append {
+    /// Create a new `Append` command which appends `value` to the string
+    /// stored at `key`.
+    pub fn new(key: impl ToString, value: Bytes) -> Append {
+        Append {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse an `Append` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `APPEND` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Append` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// APPEND key value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Append> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        Ok(Append { key, value })
+    }
+
+    /// Apply the `Append` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.append(self.key, &self.value) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Append` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("append".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.value);
+        frame
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Authenticate the connection.
+///
+/// When the server requires a password, clients must authenticate before
+/// sending any other command, which is rejected with a `NOAUTH` error until
+/// then. The username may be omitted to authenticate as the `default` user.
+///
+/// The client is then allowed to run the commands the ACL rules of its user
+/// allow.
+#[derive(Debug)]
+pub struct Auth {
+    username: Option<String>,
+    password: String,
+}
+
+impl Auth {
+    /// Create a new `Auth` command authenticating as `username`, the default
+    /// user if `None`, with `password`.
+    pub fn new(username: Option<String>, password: impl ToString) -> Auth {
+        Auth {
+            username,
+            password: password.to_string(),
+        }
+    }
+
+    /// Get the username
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    /// Get the password
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// Parse an `Auth` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `AUTH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Auth` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// AUTH [username] password
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Auth> {
+        let first = parse.next_string()?;
+
+        match parse.next_string() {
+            Ok(password) => Ok(Auth::new(Some(first), password)),
+            Err(ParseError::EndOfStream) => Ok(Auth::new(None, first)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Apply the `Auth` command, authenticating the client of `db`.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.authenticate(self.username(), self.password()) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Auth` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("auth".as_bytes()));
+        if let Some(username) = self.username {
+            frame.push_bulk(Bytes::from(username.into_bytes()));
+        }
+        frame.push_bulk(Bytes::from(self.password.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,83 @@
+use crate::persistence::rewrite_aof;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, error, info, instrument};
+
+/// Rewrite the AOF in the background.
+///
+/// The AOF is replaced by the shortest sequence of commands recreating the
+/// databases, so that it no longer grows without bound. The databases are
+/// copied right away, then written by a background task while commands keep
+/// being served. Commands applied in the meantime are appended to both files.
+///
+/// Returns an error if the AOF is disabled or if it is already being
+/// rewritten.
+#[derive(Debug, Default)]
+pub struct BgRewriteAof;
+
+impl BgRewriteAof {
+    /// Create a new `BgRewriteAof` command.
+    pub fn new() -> BgRewriteAof {
+        BgRewriteAof
+    }
+
+    /// Parse a `BgRewriteAof` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BGREWRITEAOF` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BgRewriteAof` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// BGREWRITEAOF
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<BgRewriteAof> {
+        Ok(BgRewriteAof)
+    }
+
+    /// Apply the `BgRewriteAof` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.begin_aof_rewrite() {
+            Ok((snapshot, path)) => {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    match rewrite_aof(&db, snapshot, &path).await {
+                        Ok(()) => info!(path = %path.display(), "background AOF rewrite done"),
+                        Err(err) => error!(cause = %err, "background AOF rewrite failed"),
+                    }
+                });
+                Frame::Simple("Background append only file rewriting started".to_string())
+            }
+            Err(err) => Frame::Error(err),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BgRewriteAof` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bgrewriteaof".as_bytes()));
+        frame
+    }
+}
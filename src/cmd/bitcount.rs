@@ -0,0 +1,136 @@
+use crate::db::BitUnit;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Count the number of set bits in the string value stored at `key`.
+///
+/// By default all the bytes of the string are examined. The count can be
+/// limited to an inclusive range of bytes, or of bits when `BIT` is given.
+/// Like `GETRANGE`, negative indexes count from the end of the string.
+///
+/// If `key` does not exist, it is interpreted as an empty string and `0` is
+/// returned.
+#[derive(Debug)]
+pub struct BitCount {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Optional inclusive range to count the bits in
+    range: Option<(i64, i64, BitUnit)>,
+}
+
+impl BitCount {
+    /// Create a new `BitCount` command which counts all the set bits in the
+    /// string stored at `key`.
+    pub fn new(key: impl ToString) -> BitCount {
+        BitCount {
+            key: key.to_string(),
+            range: None,
+        }
+    }
+
+    /// Only count the bits in the inclusive range from `start` to `end`,
+    /// expressed in `unit`.
+    pub fn range(mut self, start: i64, end: i64, unit: BitUnit) -> BitCount {
+        self.range = Some((start, end, unit));
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `BitCount` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITCOUNT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitCount` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to five entries.
+    ///
+    /// ```text
+    /// BITCOUNT key [start end [BYTE | BIT]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitCount> {
+        let key = parse.next_string()?;
+
+        let range = match parse.next_signed_int() {
+            Ok(start) => {
+                let end = parse.next_signed_int()?;
+                Some((start, end, parse_unit(parse)?))
+            }
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(BitCount { key, range })
+    }
+
+    /// Apply the `BitCount` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.bitcount(&self.key, self.range) {
+            Ok(count) => Frame::Integer(count as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BitCount` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bitcount".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        if let Some((start, end, unit)) = self.range {
+            frame.push_bulk(Bytes::from(start.to_string().into_bytes()));
+            frame.push_bulk(Bytes::from(end.to_string().into_bytes()));
+            push_unit(&mut frame, unit);
+        }
+
+        frame
+    }
+}
+
+/// Parse the optional trailing `BYTE` or `BIT` argument of the bitmap range
+/// commands. Defaults to `BYTE`.
+pub(crate) fn parse_unit(parse: &mut Parse) -> crate::Result<BitUnit> {
+    match parse.next_string() {
+        Ok(s) => match &s.to_uppercase()[..] {
+            "BYTE" => Ok(BitUnit::Byte),
+            "BIT" => Ok(BitUnit::Bit),
+            _ => Err("ERR syntax error".into()),
+        },
+        Err(ParseError::EndOfStream) => Ok(BitUnit::Byte),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Encode `unit` as the trailing argument of a bitmap range command. `BYTE`
+/// is the default and is omitted.
+pub(crate) fn push_unit(frame: &mut Frame, unit: BitUnit) {
+    if unit == BitUnit::Bit {
+        frame.push_bulk(Bytes::from("BIT".as_bytes()));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::cmd::setbit::MAX_OFFSET;
+use crate::db::{Field, FieldOp, FieldType, Overflow};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Treats the string value stored at `key` as an array of bits and performs
+/// a sequence of operations on integer fields of arbitrary width and offset.
+///
+/// Fields are typed as `i<bits>` for signed integers, up to `i64`, or
+/// `u<bits>` for unsigned integers, up to `u63`. Offsets are bit offsets, or,
+/// when prefixed with `#`, multiples of the field width, which makes it easy
+/// to address an array of counters.
+///
+/// `SET` and `INCRBY` follow the overflow behavior set by the last `OVERFLOW`
+/// operation preceding them, `WRAP` by default.
+///
+/// One reply is returned per `GET`, `SET` and `INCRBY` operation, in order.
+#[derive(Debug)]
+pub struct BitField {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Operations to perform
+    ops: Vec<BitFieldOp>,
+}
+
+/// An operation performed by `BITFIELD`.
+///
+/// `ty` is the field type, such as `u8` or `i16`, and `offset` the bit
+/// offset of the field, such as `100` or `#2`.
+#[derive(Debug, Clone)]
+pub enum BitFieldOp {
+    /// Returns the value of the field.
+    Get { ty: String, offset: String },
+
+    /// Sets the field to `value` and returns its previous value.
+    Set {
+        ty: String,
+        offset: String,
+        value: i64,
+    },
+
+    /// Increments the field by `increment` and returns its new value.
+    IncrBy {
+        ty: String,
+        offset: String,
+        increment: i64,
+    },
+
+    /// Changes the overflow behavior of the following operations.
+    Overflow(Overflow),
+}
+
+impl BitField {
+    /// Create a new `BitField` command which performs `ops` on the string
+    /// stored at `key`.
+    pub fn new(key: impl ToString, ops: Vec<BitFieldOp>) -> BitField {
+        BitField {
+            key: key.to_string(),
+            ops,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the operations
+    pub fn ops(&self) -> &[BitFieldOp] {
+        &self.ops
+    }
+
+    /// Parse a `BitField` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITFIELD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitField` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// BITFIELD key [GET type offset] [SET type offset value]
+    ///     [INCRBY type offset increment] [OVERFLOW WRAP | SAT | FAIL] ...
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitField> {
+        let key = parse.next_string()?;
+        let mut ops = vec![];
+
+        loop {
+            let op = match parse.next_string() {
+                Ok(op) => op.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            let op = match &op[..] {
+                "GET" => BitFieldOp::Get {
+                    ty: parse.next_string()?,
+                    offset: parse.next_string()?,
+                },
+                "SET" => BitFieldOp::Set {
+                    ty: parse.next_string()?,
+                    offset: parse.next_string()?,
+                    value: parse.next_signed_int()?,
+                },
+                "INCRBY" => BitFieldOp::IncrBy {
+                    ty: parse.next_string()?,
+                    offset: parse.next_string()?,
+                    increment: parse.next_signed_int()?,
+                },
+                "OVERFLOW" => {
+                    let overflow = match &parse.next_string()?.to_uppercase()[..] {
+                        "WRAP" => Overflow::Wrap,
+                        "SAT" => Overflow::Sat,
+                        "FAIL" => Overflow::Fail,
+                        _ => return Err("ERR Invalid OVERFLOW type specified".into()),
+                    };
+                    BitFieldOp::Overflow(overflow)
+                }
+                _ => return Err("ERR syntax error".into()),
+            };
+
+            ops.push(op);
+        }
+
+        Ok(BitField { key, ops })
+    }
+
+    /// Apply the `BitField` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match resolve_fields(&self.ops) {
+            Ok(fields) => match db.bitfield(self.key, &fields) {
+                Ok(replies) => Frame::Array(
+                    replies
+                        .into_iter()
+                        .map(|reply| match reply {
+                            Some(value) => Frame::Integer(value),
+                            None => Frame::Null,
+                        })
+                        .collect(),
+                ),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            Err(response) => response,
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BitField` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bitfield".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        for op in self.ops {
+            let (name, ty, offset, arg) = match op {
+                BitFieldOp::Get { ty, offset } => ("GET", ty, offset, None),
+                BitFieldOp::Set { ty, offset, value } => ("SET", ty, offset, Some(value)),
+                BitFieldOp::IncrBy {
+                    ty,
+                    offset,
+                    increment,
+                } => ("INCRBY", ty, offset, Some(increment)),
+                BitFieldOp::Overflow(overflow) => {
+                    let overflow = match overflow {
+                        Overflow::Wrap => "WRAP",
+                        Overflow::Sat => "SAT",
+                        Overflow::Fail => "FAIL",
+                    };
+                    frame.push_bulk(Bytes::from("OVERFLOW".as_bytes()));
+                    frame.push_bulk(Bytes::from(overflow.as_bytes()));
+                    continue;
+                }
+            };
+
+            frame.push_bulk(Bytes::from(name.as_bytes()));
+            frame.push_bulk(Bytes::from(ty.into_bytes()));
+            frame.push_bulk(Bytes::from(offset.into_bytes()));
+
+            if let Some(arg) = arg {
+                frame.push_bulk(Bytes::from(arg.to_string().into_bytes()));
+            }
+        }
+
+        frame
+    }
+}
+
+/// Validate the types and offsets of `ops`, resolving them into the fields
+/// to operate on. On failure, the error frame to reply with is returned.
+fn resolve_fields(ops: &[BitFieldOp]) -> Result<Vec<Field>, Frame> {
+    let mut overflow = Overflow::Wrap;
+    let mut fields = vec![];
+
+    for op in ops {
+        let (op, ty, offset) = match op {
+            BitFieldOp::Get { ty, offset } => (FieldOp::Get, ty, offset),
+            BitFieldOp::Set { ty, offset, value } => (FieldOp::Set(*value), ty, offset),
+            BitFieldOp::IncrBy {
+                ty,
+                offset,
+                increment,
+            } => (FieldOp::IncrBy(*increment), ty, offset),
+            BitFieldOp::Overflow(value) => {
+                overflow = *value;
+                continue;
+            }
+        };
+
+        let ty = FieldType::parse(ty).ok_or_else(|| {
+            Frame::Error(
+                "ERR Invalid bitfield type. Use something like i16 u8. \
+                 Note that u64 is not supported but i64 is."
+                    .into(),
+            )
+        })?;
+
+        let offset = resolve_offset(offset, ty).ok_or_else(|| {
+            Frame::Error("ERR bit offset is not an integer or out of range".into())
+        })?;
+
+        fields.push(Field {
+            op,
+            ty,
+            offset,
+            overflow,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Resolve a field offset, multiplying it by the field width if prefixed with
+/// `#`. The whole field must fit within the maximum string size.
+fn resolve_offset(src: &str, ty: FieldType) -> Option<usize> {
+    let bits = ty.bits() as u64;
+
+    let offset = match src.strip_prefix('#') {
+        Some(index) => index.parse::<u64>().ok()?.checked_mul(bits)?,
+        None => src.parse::<u64>().ok()?,
+    };
+
+    if offset.checked_add(bits)? > MAX_OFFSET {
+        return None;
+    }
+
+    Some(offset as usize)
+}
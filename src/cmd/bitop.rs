@@ -0,0 +1,139 @@
+use crate::db::BitOperation;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Performs a bitwise operation between the string values stored at `keys`
+/// and stores the result at `dest`.
+///
+/// `AND`, `OR` and `XOR` accept one or more source keys, `NOT` exactly one.
+/// When the strings have different lengths, the shorter ones are padded with
+/// zero bytes. Missing keys are considered empty strings.
+///
+/// The length of the string stored at `dest` is returned.
+#[derive(Debug)]
+pub struct BitOp {
+    /// Operation to perform
+    op: BitOperation,
+
+    /// Name of the key to store the result at
+    dest: String,
+
+    /// Names of the keys holding the source strings
+    keys: Vec<String>,
+}
+
+impl BitOp {
+    /// Create a new `BitOp` command which combines the strings stored at
+    /// `keys` with `op` into `dest`.
+    pub fn new(op: BitOperation, dest: impl ToString, keys: Vec<String>) -> BitOp {
+        BitOp {
+            op,
+            dest: dest.to_string(),
+            keys,
+        }
+    }
+
+    /// Get the operation
+    pub fn op(&self) -> BitOperation {
+        self.op
+    }
+
+    /// Get the destination key
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Get the source keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `BitOp` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITOP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitOp` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// BITOP AND | OR | XOR | NOT destkey key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitOp> {
+        let op = match &parse.next_string()?.to_uppercase()[..] {
+            "AND" => BitOperation::And,
+            "OR" => BitOperation::Or,
+            "XOR" => BitOperation::Xor,
+            "NOT" => BitOperation::Not,
+            _ => return Err("ERR syntax error".into()),
+        };
+
+        let dest = parse.next_string()?;
+
+        // At least one source key is required
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(BitOp { op, dest, keys })
+    }
+
+    /// Apply the `BitOp` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.op == BitOperation::Not && self.keys.len() != 1 {
+            Frame::Error("ERR BITOP NOT must be called with a single source key.".into())
+        } else {
+            match db.bitop(self.op, self.dest, &self.keys) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BitOp` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let op = match self.op {
+            BitOperation::And => "AND",
+            BitOperation::Or => "OR",
+            BitOperation::Xor => "XOR",
+            BitOperation::Not => "NOT",
+        };
+
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bitop".as_bytes()));
+        frame.push_bulk(Bytes::from(op.as_bytes()));
+        frame.push_bulk(Bytes::from(self.dest.into_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,171 @@
+use crate::db::BitUnit;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use super::bitcount::{parse_unit, push_unit};
+
+/// Returns the position of the first bit set to `1` or `0` in the string
+/// value stored at `key`.
+///
+/// The search can be limited to a range starting at `start` and optionally
+/// ending at `end`, both inclusive. The range is expressed in bytes, or in
+/// bits when `BIT` is given, and negative indexes count from the end of the
+/// string.
+///
+/// When looking for a clear bit without an explicit `end`, the string is
+/// considered padded with zeros on the right, so the first bit past the end
+/// is returned if all the bits are set. Otherwise `-1` is returned when no
+/// such bit is found.
+#[derive(Debug)]
+pub struct BitPos {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Bit value to look for, validated to be `0` or `1` when applied
+    value: u64,
+
+    /// Index to start the search at
+    start: Option<i64>,
+
+    /// Index to end the search at
+    end: Option<i64>,
+
+    /// Unit the range is expressed in
+    unit: BitUnit,
+}
+
+impl BitPos {
+    /// Create a new `BitPos` command which looks for the first bit set to
+    /// `bit` in the string stored at `key`.
+    pub fn new(key: impl ToString, bit: bool) -> BitPos {
+        BitPos {
+            key: key.to_string(),
+            value: bit as u64,
+            start: None,
+            end: None,
+            unit: BitUnit::Byte,
+        }
+    }
+
+    /// Start the search at `start`.
+    pub fn start(mut self, start: i64) -> BitPos {
+        self.start = Some(start);
+        self
+    }
+
+    /// End the search at `end`, inclusive. Ignored if no start is set.
+    pub fn end(mut self, end: i64) -> BitPos {
+        self.end = Some(end);
+        self
+    }
+
+    /// Express the range in `unit`.
+    pub fn unit(mut self, unit: BitUnit) -> BitPos {
+        self.unit = unit;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the bit value looked for
+    pub fn bit(&self) -> bool {
+        self.value == 1
+    }
+
+    /// Parse a `BitPos` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITPOS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitPos` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three to six entries.
+    ///
+    /// ```text
+    /// BITPOS key bit [start [end [BYTE | BIT]]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitPos> {
+        let key = parse.next_string()?;
+        let value = parse.next_int()?;
+
+        let mut bitpos = BitPos {
+            key,
+            value,
+            start: None,
+            end: None,
+            unit: BitUnit::Byte,
+        };
+
+        match parse.next_signed_int() {
+            Ok(start) => bitpos.start = Some(start),
+            Err(ParseError::EndOfStream) => return Ok(bitpos),
+            Err(err) => return Err(err.into()),
+        }
+
+        match parse.next_signed_int() {
+            Ok(end) => bitpos.end = Some(end),
+            Err(ParseError::EndOfStream) => return Ok(bitpos),
+            Err(err) => return Err(err.into()),
+        }
+
+        bitpos.unit = parse_unit(parse)?;
+
+        Ok(bitpos)
+    }
+
+    /// Apply the `BitPos` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.value > 1 {
+            Frame::Error("ERR The bit argument must be 1 or 0.".into())
+        } else {
+            let bit = self.value == 1;
+            match db.bitpos(&self.key, bit, self.start, self.end, self.unit) {
+                Ok(pos) => Frame::Integer(pos),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BitPos` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bitpos".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.value.to_string().into_bytes()));
+
+        if let Some(start) = self.start {
+            frame.push_bulk(Bytes::from(start.to_string().into_bytes()));
+
+            if let Some(end) = self.end {
+                frame.push_bulk(Bytes::from(end.to_string().into_bytes()));
+                push_unit(&mut frame, self.unit);
+            }
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,146 @@
+use crate::Db;
+
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::select;
+use tokio::stream::{Stream, StreamExt, StreamMap};
+use tokio::sync::broadcast;
+use tokio::time::{self, Instant};
+
+/// Waits for keys to be written to on behalf of a blocking command, such as
+/// `XREAD` with the `BLOCK` option.
+///
+/// A `KeyWaiter` subscribes to writes on the keys when it is created. Commands
+/// must create it **before** checking the keys for data, then check again each
+/// time `wait` returns `true`. This way, no write happening between the check
+/// and the wait is missed.
+pub(crate) struct KeyWaiter {
+    /// Notifications received when one of the keys is written to. A single
+    /// `StreamMap` waits on all the keys.
+    writes: StreamMap<String, Writes>,
+
+    /// Instant at which the command stops waiting, `None` to wait forever.
+    deadline: Option<Instant>,
+}
+
+/// Stream of notifications received when a key is written to. As with
+/// subscriptions, the `broadcast::Receiver` is converted to a `Stream` using
+/// `stream!` and boxed as `stream!` values cannot be named.
+type Writes = Pin<Box<dyn Stream<Item = ()> + Send>>;
+
+/// Longest timeout of the blocking commands. As with Redis, timeouts are
+/// counted in milliseconds as an `i64`.
+const MAX_TIMEOUT: Duration = Duration::from_millis(i64::MAX as u64);
+
+/// Returns `Err` with the error to reply if the blocking `timeout` is longer
+/// than `MAX_TIMEOUT`.
+pub(crate) fn check_timeout(timeout: Duration) -> Result<(), String> {
+    if timeout > MAX_TIMEOUT {
+        return Err("ERR timeout is out of range".to_string());
+    }
+    Ok(())
+}
+
+impl KeyWaiter {
+    /// Subscribe to writes on `keys`. The waiter stops waiting after `timeout`,
+    /// a zero duration waiting forever.
+    ///
+    /// In a transaction, the waiter times out right away: blocking commands
+    /// behave as their non-blocking variant.
+    pub(crate) fn new<'a>(
+        db: &Db,
+        keys: impl IntoIterator<Item = &'a String>,
+        timeout: Duration,
+    ) -> KeyWaiter {
+        let mut writes = StreamMap::new();
+
+        if db.in_transaction() {
+            return KeyWaiter {
+                writes,
+                deadline: Some(Instant::now()),
+            };
+        }
+
+        for key in keys {
+            let mut rx = db.subscribe_writes(key.clone());
+
+            let rx = Box::pin(async_stream::stream! {
+                loop {
+                    match rx.recv().await {
+                        Ok(()) => yield,
+                        // A lagging receiver still needs to check the key
+                        // again.
+                        Err(broadcast::error::RecvError::Lagged(_)) => yield,
+                        Err(_) => break,
+                    }
+                }
+            });
+
+            writes.insert(key.clone(), rx as Writes);
+        }
+
+        // Deadlines too far away to be represented are never reached.
+        let deadline = if timeout > Duration::from_millis(0) {
+            Instant::now().checked_add(timeout)
+        } else {
+            None
+        };
+
+        KeyWaiter { writes, deadline }
+    }
+
+    /// Wait for one of the keys to be written to.
+    ///
+    /// Returns `false` if the timeout elapsed first.
+    pub(crate) async fn wait(&mut self) -> bool {
+        if matches!(self.deadline, Some(deadline) if deadline <= Instant::now()) {
+            return false;
+        }
+
+        select! {
+            Some(_) = self.writes.next() => true,
+            _ = sleep_until(self.deadline) => false,
+        }
+    }
+}
+
+/// A blocked client's place in the queues of the keys it waits on.
+///
+/// Clients blocked popping from a key are served in the order they started
+/// waiting. The `Ticket` is taken **before** checking the keys for data and
+/// leaves the queues when dropped, once the client stops waiting.
+pub(crate) struct Ticket {
+    db: Db,
+    keys: Vec<String>,
+    id: u64,
+}
+
+impl Ticket {
+    /// Queue the client on `keys`.
+    pub(crate) fn new(db: &Db, keys: &[String]) -> Ticket {
+        Ticket {
+            db: db.clone(),
+            keys: keys.to_vec(),
+            id: db.enqueue(keys),
+        }
+    }
+
+    /// Returns the identifier of the ticket.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.db.dequeue(&self.keys, self.id);
+    }
+}
+
+/// Wait until `deadline`, or forever if there is none.
+pub(crate) async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,255 @@
+use crate::cmd::blocking::{check_timeout, KeyWaiter, Ticket};
+use crate::{Connection, Db, Frame, ListEnd, Parse, ParseError, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::select;
+use tracing::{debug, instrument};
+
+/// Removes and returns the first value of the first non-empty list among the
+/// ones stored at `keys`, blocking until a value is pushed when all the lists
+/// are empty.
+///
+/// Clients blocked on the same key are served in the order they started
+/// waiting. The reply is a two elements array holding the key and the popped
+/// value, or nil when `timeout` elapses first. A zero `timeout` blocks
+/// forever.
+#[derive(Debug)]
+pub struct BLPop {
+    /// Names of the keys holding the lists, checked in order
+    keys: Vec<String>,
+
+    /// How long to wait for a value, zero waiting forever
+    timeout: Duration,
+}
+
+/// Removes and returns the last value of the first non-empty list among the
+/// ones stored at `keys`, blocking until a value is pushed when all the lists
+/// are empty.
+///
+/// Same as `BLPop`, but values are popped from the tail of the lists.
+#[derive(Debug)]
+pub struct BRPop {
+    /// Names of the keys holding the lists, checked in order
+    keys: Vec<String>,
+
+    /// How long to wait for a value, zero waiting forever
+    timeout: Duration,
+}
+
+impl BLPop {
+    /// Create a new `BLPop` command which pops from the head of the lists
+    /// stored at `keys`, waiting up to `timeout`.
+    pub fn new(keys: Vec<String>, timeout: Duration) -> BLPop {
+        BLPop { keys, timeout }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BLPop` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BLPOP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BLPop` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries. The timeout
+    /// is a number of seconds, possibly with a decimal part.
+    ///
+    /// ```text
+    /// BLPOP key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BLPop> {
+        let (keys, timeout) = parse_bpop(parse)?;
+        Ok(BLPop { keys, timeout })
+    }
+
+    /// Apply the `BLPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        apply_bpop(db, dst, shutdown, &self.keys, self.timeout, ListEnd::Left).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BLPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        bpop_frame("blpop", self.keys, self.timeout)
+    }
+}
+
+impl BRPop {
+    /// Create a new `BRPop` command which pops from the tail of the lists
+    /// stored at `keys`, waiting up to `timeout`.
+    pub fn new(keys: Vec<String>, timeout: Duration) -> BRPop {
+        BRPop { keys, timeout }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BRPop` instance from a received frame.
+    ///
+    /// The `BRPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// BRPOP key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BRPop> {
+        let (keys, timeout) = parse_bpop(parse)?;
+        Ok(BRPop { keys, timeout })
+    }
+
+    /// Apply the `BRPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        apply_bpop(db, dst, shutdown, &self.keys, self.timeout, ListEnd::Right).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BRPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        bpop_frame("brpop", self.keys, self.timeout)
+    }
+}
+
+/// Parses the arguments shared by the blocking pop commands: one or more
+/// keys followed by a timeout in seconds.
+pub(crate) fn parse_bpop(parse: &mut Parse) -> crate::Result<(Vec<String>, Duration)> {
+    let mut args = vec![parse.next_string()?, parse.next_string()?];
+
+    loop {
+        match parse.next_string() {
+            Ok(arg) => args.push(arg),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    let timeout = args.pop().unwrap();
+    let keys = args;
+
+    Ok((keys, parse_timeout(&timeout)?))
+}
+
+/// Parses a blocking timeout, expressed in seconds.
+pub(crate) fn parse_timeout(timeout: &str) -> crate::Result<Duration> {
+    let timeout: f64 = match timeout.parse() {
+        Ok(timeout) if f64::is_finite(timeout) => timeout,
+        _ => return Err("ERR timeout is not a float or out of range".into()),
+    };
+
+    if timeout < 0.0 {
+        return Err("ERR timeout is negative".into());
+    }
+
+    // Timeouts too long for a `Duration` are rejected when applied, see
+    // `check_timeout`.
+    Ok(Duration::try_from_secs_f64(timeout).unwrap_or(Duration::MAX))
+}
+
+/// Pops a value from the `end` of the first non-empty list, waiting for one
+/// to be pushed if needed, and writes the reply to `dst`.
+async fn apply_bpop(
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+    keys: &[String],
+    timeout: Duration,
+    end: ListEnd,
+) -> crate::Result<()> {
+    if let Err(err) = check_timeout(timeout) {
+        let response = Frame::Error(err);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        return Ok(());
+    }
+
+    // Subscribe to writes and queue up **before** checking the lists. This
+    // way, no value pushed after the check is missed, and clients that were
+    // already waiting are served first.
+    let mut waiter = KeyWaiter::new(db, keys, timeout);
+    let ticket = Ticket::new(db, keys);
+
+    let response = loop {
+        match db.blocking_pop(keys, end, ticket.id()) {
+            Ok(Some((key, value))) => {
+                break Frame::Array(vec![Frame::Bulk(key.into()), Frame::Bulk(value)])
+            }
+            Ok(None) => {}
+            Err(err) => break Frame::Error(err.to_string()),
+        }
+
+        // Nothing to pop yet, wait for one of the lists to be pushed to, for
+        // the timeout to elapse or for the server to shut down.
+        select! {
+            written = waiter.wait() => if !written {
+                break Frame::Null;
+            },
+            _ = shutdown.recv() => return Ok(()),
+        }
+    };
+
+    drop(ticket);
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes a blocking pop command named `name` into a frame.
+pub(crate) fn bpop_frame(name: &'static str, keys: Vec<String>, timeout: Duration) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    for key in keys {
+        frame.push_bulk(Bytes::from(key.into_bytes()));
+    }
+    frame.push_bulk(Bytes::from(timeout.as_secs_f64().to_string().into_bytes()));
+    frame
+}
@@ -0,0 +1,212 @@
+use crate::cmd::blocking::{check_timeout, KeyWaiter, Ticket};
+use crate::cmd::bpop::{bpop_frame, parse_bpop};
+use crate::db::ScoreEnd;
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::select;
+use tracing::{debug, instrument};
+
+/// Removes and returns the member with the lowest score of the first non-empty
+/// sorted set among the ones stored at `keys`, blocking until a member is
+/// added when all the sorted sets are empty.
+///
+/// Clients blocked on the same key are served in the order they started
+/// waiting. The reply is a three elements array holding the key, the popped
+/// member and its score, or nil when `timeout` elapses first. A zero `timeout`
+/// blocks forever.
+#[derive(Debug)]
+pub struct BZPopMin {
+    /// Names of the keys holding the sorted sets, checked in order
+    keys: Vec<String>,
+
+    /// How long to wait for a member, zero waiting forever
+    timeout: Duration,
+}
+
+/// Removes and returns the member with the highest score of the first
+/// non-empty sorted set among the ones stored at `keys`, blocking until a
+/// member is added when all the sorted sets are empty.
+///
+/// Same as `BZPopMin`, but the member with the highest score is popped.
+#[derive(Debug)]
+pub struct BZPopMax {
+    /// Names of the keys holding the sorted sets, checked in order
+    keys: Vec<String>,
+
+    /// How long to wait for a member, zero waiting forever
+    timeout: Duration,
+}
+
+impl BZPopMin {
+    /// Create a new `BZPopMin` command which pops the member with the lowest
+    /// score from the sorted sets stored at `keys`, waiting up to `timeout`.
+    pub fn new(keys: Vec<String>, timeout: Duration) -> BZPopMin {
+        BZPopMin { keys, timeout }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BZPopMin` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BZPOPMIN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BZPopMin` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries. The timeout
+    /// is a number of seconds, possibly with a decimal part.
+    ///
+    /// ```text
+    /// BZPOPMIN key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BZPopMin> {
+        let (keys, timeout) = parse_bpop(parse)?;
+        Ok(BZPopMin { keys, timeout })
+    }
+
+    /// Apply the `BZPopMin` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        apply_bzpop(db, dst, shutdown, &self.keys, self.timeout, ScoreEnd::Min).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BZPopMin` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        bpop_frame("bzpopmin", self.keys, self.timeout)
+    }
+}
+
+impl BZPopMax {
+    /// Create a new `BZPopMax` command which pops the member with the highest
+    /// score from the sorted sets stored at `keys`, waiting up to `timeout`.
+    pub fn new(keys: Vec<String>, timeout: Duration) -> BZPopMax {
+        BZPopMax { keys, timeout }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BZPopMax` instance from a received frame.
+    ///
+    /// The `BZPOPMAX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// BZPOPMAX key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BZPopMax> {
+        let (keys, timeout) = parse_bpop(parse)?;
+        Ok(BZPopMax { keys, timeout })
+    }
+
+    /// Apply the `BZPopMax` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        apply_bzpop(db, dst, shutdown, &self.keys, self.timeout, ScoreEnd::Max).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BZPopMax` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        bpop_frame("bzpopmax", self.keys, self.timeout)
+    }
+}
+
+/// Pops a member from the `end` of the first non-empty sorted set, waiting for
+/// one to be added if needed, and writes the reply to `dst`.
+async fn apply_bzpop(
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+    keys: &[String],
+    timeout: Duration,
+    end: ScoreEnd,
+) -> crate::Result<()> {
+    if let Err(err) = check_timeout(timeout) {
+        let response = Frame::Error(err);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        return Ok(());
+    }
+
+    // As with `BLPOP`, subscribe to writes and queue up **before** checking
+    // the sorted sets.
+    let mut waiter = KeyWaiter::new(db, keys, timeout);
+    let ticket = Ticket::new(db, keys);
+
+    let response = loop {
+        match db.blocking_zpop(keys, end, ticket.id()) {
+            Ok(Some((key, member, score))) => {
+                break Frame::Array(vec![
+                    Frame::Bulk(key.into()),
+                    Frame::Bulk(member),
+                    Frame::Bulk(Bytes::from(score.to_string().into_bytes())),
+                ])
+            }
+            Ok(None) => {}
+            Err(err) => break Frame::Error(err.to_string()),
+        }
+
+        select! {
+            written = waiter.wait() => if !written {
+                break Frame::Null;
+            },
+            _ = shutdown.recv() => return Ok(()),
+        }
+    };
+
+    drop(ticket);
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Inspects or modifies the state of the client connection.
+///
+/// # Subcommands
+///
+/// * ID -- Returns the ID of the connection.
+/// * LIST -- Returns the connected clients, one per line, with their ID,
+///   address, name, age and idle time in seconds, selected database, last
+///   command and user.
+/// * SETNAME `name` -- Name the connection, as shown by `CLIENT LIST`. An
+///   empty name removes it.
+/// * GETNAME -- Returns the name of the connection, or nil if not set.
+/// * KILL `addr` -- Close the connection of the client at `addr`.
+/// * KILL [ID `id`] [ADDR `addr`] [SKIPME yes|no] -- Close the connections
+///   matching all the filters, returning their number. The connection sending
+///   the command is spared unless `SKIPME no` is given.
+/// * PAUSE `timeout` [WRITE|ALL] -- Hold the commands of the clients for
+///   `timeout` milliseconds, or only the ones which may modify the data set
+///   with `WRITE`. `CLIENT` commands are never held.
+/// * UNPAUSE -- End the pause of the clients.
+/// * REPLY ON|OFF|SKIP -- Enable or disable the replies to the commands of
+///   the connection, or skip the reply to the next command only. The command
+///   itself is only replied to with `ON`.
+/// * TRACKING ON|OFF [REDIRECT `id`] -- Enable or disable the tracking of the
+///   keys read by the connection. Once a tracked key is modified, it is sent
+///   as a message of the `__redis__:invalidate` channel to the connection
+///   `id`, if it is subscribed to it, so that values cached by the client can
+///   be invalidated. Without `REDIRECT`, which requires RESP3, it is sent to
+///   the connection itself as an `invalidate` push. Each invalidated key is
+///   sent as its own message.
+#[derive(Debug)]
+pub struct Client {
+    subcommand: Subcommand,
+}
+
+/// Whether the server replies to the commands of a connection, as set by
+/// `CLIENT REPLY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientReply {
+    /// Every command is replied to.
+    On,
+    /// No command is replied to.
+    Off,
+    /// The next command is not replied to.
+    Skip,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Id,
+    List,
+    SetName(String),
+    GetName,
+    /// The old form of `KILL`, taking an address alone.
+    KillAddr(String),
+    Kill {
+        id: Option<u64>,
+        addr: Option<String>,
+        skipme: bool,
+    },
+    Pause {
+        timeout: u64,
+        writes_only: bool,
+    },
+    Unpause,
+    Reply(ClientReply),
+    Tracking {
+        enabled: bool,
+        redirect: Option<u64>,
+    },
+}
+
+impl Client {
+    /// Create a new `Client` command returning the ID of the connection.
+    pub fn id() -> Client {
+        Client {
+            subcommand: Subcommand::Id,
+        }
+    }
+
+    /// Create a new `Client` command listing the connected clients.
+    pub fn list() -> Client {
+        Client {
+            subcommand: Subcommand::List,
+        }
+    }
+
+    /// Create a new `Client` command naming the connection `name`.
+    pub fn setname(name: impl ToString) -> Client {
+        Client {
+            subcommand: Subcommand::SetName(name.to_string()),
+        }
+    }
+
+    /// Create a new `Client` command returning the name of the connection.
+    pub fn getname() -> Client {
+        Client {
+            subcommand: Subcommand::GetName,
+        }
+    }
+
+    /// Create a new `Client` command closing the connection of the client at
+    /// `addr`.
+    pub fn kill_addr(addr: impl ToString) -> Client {
+        Client {
+            subcommand: Subcommand::KillAddr(addr.to_string()),
+        }
+    }
+
+    /// Create a new `Client` command closing the connections of the clients
+    /// matching `id` and `addr`, other than the one sending it unless
+    /// `skipme` is `false`.
+    pub fn kill(id: Option<u64>, addr: Option<String>, skipme: bool) -> Client {
+        Client {
+            subcommand: Subcommand::Kill { id, addr, skipme },
+        }
+    }
+
+    /// Create a new `Client` command holding the commands of the clients for
+    /// `timeout` milliseconds, only the ones which may modify the data set if
+    /// `writes_only` is set.
+    pub fn pause(timeout: u64, writes_only: bool) -> Client {
+        Client {
+            subcommand: Subcommand::Pause {
+                timeout,
+                writes_only,
+            },
+        }
+    }
+
+    /// Create a new `Client` command ending the pause of the clients.
+    pub fn unpause() -> Client {
+        Client {
+            subcommand: Subcommand::Unpause,
+        }
+    }
+
+    /// Create a new `Client` command setting whether the server replies to
+    /// the commands of the connection.
+    pub fn reply(mode: ClientReply) -> Client {
+        Client {
+            subcommand: Subcommand::Reply(mode),
+        }
+    }
+
+    /// Returns the mode set by a `CLIENT REPLY` command, if it is one.
+    pub(crate) fn reply_mode(&self) -> Option<ClientReply> {
+        match self.subcommand {
+            Subcommand::Reply(mode) => Some(mode),
+            _ => None,
+        }
+    }
+
+    /// Create a new `Client` command which enables or disables tracking,
+    /// redirecting the invalidation messages to the connection `redirect`.
+    pub fn tracking(enabled: bool, redirect: Option<u64>) -> Client {
+        Client {
+            subcommand: Subcommand::Tracking { enabled, redirect },
+        }
+    }
+
+    /// Parse a `Client` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CLIENT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Client` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// CLIENT ID
+    /// CLIENT LIST
+    /// CLIENT SETNAME name
+    /// CLIENT GETNAME
+    /// CLIENT KILL addr
+    /// CLIENT KILL [ID id] [ADDR addr] [SKIPME yes|no]
+    /// CLIENT PAUSE timeout [WRITE|ALL]
+    /// CLIENT UNPAUSE
+    /// CLIENT REPLY ON|OFF|SKIP
+    /// CLIENT TRACKING ON|OFF [REDIRECT id]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Client> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "ID" => Subcommand::Id,
+            "LIST" => Subcommand::List,
+            "SETNAME" => Subcommand::SetName(parse.next_string()?),
+            "GETNAME" => Subcommand::GetName,
+            "KILL" => {
+                let mut id = None;
+                let mut addr = None;
+                let mut skipme = true;
+
+                let mut first = true;
+                let mut filter = Some(parse.next_string()?);
+                while let Some(name) = filter {
+                    match &name.to_uppercase()[..] {
+                        "ID" => id = Some(parse.next_int()?),
+                        "ADDR" => addr = Some(parse.next_string()?),
+                        "SKIPME" => {
+                            skipme = match &parse.next_string()?.to_lowercase()[..] {
+                                "yes" => true,
+                                "no" => false,
+                                _ => return Err("ERR syntax error".into()),
+                            }
+                        }
+                        // A single argument is the address of the client.
+                        _ if first => {
+                            parse.finish()?;
+                            return Ok(Client {
+                                subcommand: Subcommand::KillAddr(name),
+                            });
+                        }
+                        _ => return Err("ERR syntax error".into()),
+                    }
+
+                    first = false;
+                    filter = match parse.next_string() {
+                        Ok(name) => Some(name),
+                        Err(ParseError::EndOfStream) => None,
+                        Err(err) => return Err(err.into()),
+                    };
+                }
+
+                Subcommand::Kill { id, addr, skipme }
+            }
+            "PAUSE" => {
+                let timeout = parse.next_int()?;
+                let writes_only = match parse.next_string() {
+                    Ok(mode) if mode.to_uppercase() == "WRITE" => true,
+                    Ok(mode) if mode.to_uppercase() == "ALL" => false,
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(ParseError::EndOfStream) => false,
+                    Err(err) => return Err(err.into()),
+                };
+                Subcommand::Pause {
+                    timeout,
+                    writes_only,
+                }
+            }
+            "UNPAUSE" => Subcommand::Unpause,
+            "REPLY" => match &parse.next_string()?.to_uppercase()[..] {
+                "ON" => Subcommand::Reply(ClientReply::On),
+                "OFF" => Subcommand::Reply(ClientReply::Off),
+                "SKIP" => Subcommand::Reply(ClientReply::Skip),
+                _ => return Err("ERR syntax error".into()),
+            },
+            "TRACKING" => {
+                let enabled = match &parse.next_string()?.to_uppercase()[..] {
+                    "ON" => true,
+                    "OFF" => false,
+                    _ => return Err("ERR syntax error".into()),
+                };
+
+                let mut redirect = None;
+                loop {
+                    match parse.next_string() {
+                        Ok(s) if s.to_uppercase() == "REDIRECT" => {
+                            redirect = Some(parse.next_int()?)
+                        }
+                        Ok(_) => return Err("ERR syntax error".into()),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Subcommand::Tracking { enabled, redirect }
+            }
+            _ => return Err(format!("unknown `CLIENT` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Client { subcommand })
+    }
+
+    /// Apply the `Client` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Id => Frame::Integer(db.client_id() as i64),
+            Subcommand::List => Frame::Bulk(Bytes::from(db.client_list().into_bytes())),
+            Subcommand::SetName(name) => match db.client_setname(&name) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::GetName => match db.client_getname() {
+                Some(name) => Frame::Bulk(Bytes::from(name.into_bytes())),
+                None => Frame::Null,
+            },
+            Subcommand::KillAddr(addr) => match db.client_kill(None, Some(&addr), false) {
+                0 => Frame::Error("ERR No such client".to_string()),
+                _ => Frame::Simple("OK".to_string()),
+            },
+            Subcommand::Kill { id, addr, skipme } => {
+                Frame::Integer(db.client_kill(id, addr.as_deref(), skipme) as i64)
+            }
+            Subcommand::Pause {
+                timeout,
+                writes_only,
+            } => {
+                db.client_pause(Duration::from_millis(timeout), writes_only);
+                Frame::Simple("OK".to_string())
+            }
+            Subcommand::Unpause => {
+                db.client_unpause();
+                Frame::Simple("OK".to_string())
+            }
+            // The reply is dropped by the connection unless they are enabled.
+            Subcommand::Reply(_) => Frame::Simple("OK".to_string()),
+            Subcommand::Tracking { enabled, redirect } => {
+                match db.client_tracking(enabled, redirect, dst.protocol() >= 3) {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(err) => Frame::Error(err),
+                }
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Client` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        match self.subcommand {
+            Subcommand::Id => frame.push_bulk(Bytes::from("id".as_bytes())),
+            Subcommand::List => frame.push_bulk(Bytes::from("list".as_bytes())),
+            Subcommand::SetName(name) => {
+                frame.push_bulk(Bytes::from("setname".as_bytes()));
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+            Subcommand::GetName => frame.push_bulk(Bytes::from("getname".as_bytes())),
+            Subcommand::KillAddr(addr) => {
+                frame.push_bulk(Bytes::from("kill".as_bytes()));
+                frame.push_bulk(Bytes::from(addr.into_bytes()));
+            }
+            Subcommand::Kill { id, addr, skipme } => {
+                frame.push_bulk(Bytes::from("kill".as_bytes()));
+                if let Some(id) = id {
+                    frame.push_bulk(Bytes::from("id".as_bytes()));
+                    frame.push_int(id as i64);
+                }
+                if let Some(addr) = addr {
+                    frame.push_bulk(Bytes::from("addr".as_bytes()));
+                    frame.push_bulk(Bytes::from(addr.into_bytes()));
+                }
+                frame.push_bulk(Bytes::from("skipme".as_bytes()));
+                let skipme = if skipme { "yes" } else { "no" };
+                frame.push_bulk(Bytes::from(skipme.as_bytes()));
+            }
+            Subcommand::Pause {
+                timeout,
+                writes_only,
+            } => {
+                frame.push_bulk(Bytes::from("pause".as_bytes()));
+                frame.push_int(timeout as i64);
+                let mode = if writes_only { "write" } else { "all" };
+                frame.push_bulk(Bytes::from(mode.as_bytes()));
+            }
+            Subcommand::Unpause => frame.push_bulk(Bytes::from("unpause".as_bytes())),
+            Subcommand::Reply(mode) => {
+                frame.push_bulk(Bytes::from("reply".as_bytes()));
+                let mode = match mode {
+                    ClientReply::On => "on",
+                    ClientReply::Off => "off",
+                    ClientReply::Skip => "skip",
+                };
+                frame.push_bulk(Bytes::from(mode.as_bytes()));
+            }
+            Subcommand::Tracking { enabled, redirect } => {
+                frame.push_bulk(Bytes::from("tracking".as_bytes()));
+                let enabled = if enabled { "on" } else { "off" };
+                frame.push_bulk(Bytes::from(enabled.as_bytes()));
+                if let Some(redirect) = redirect {
+                    frame.push_bulk(Bytes::from("redirect".as_bytes()));
+                    frame.push_int(redirect as i64);
+                }
+            }
+        }
+        frame
+    }
+}
@@ -0,0 +1,531 @@
+use crate::cluster::{key_slot, Node, SlotState, SLOTS};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tokio::net::TcpStream;
+use tracing::{debug, instrument};
+
+/// Manages the cluster, in cluster mode.
+///
+/// # Subcommands
+///
+/// * MYID -- Returns the ID of the node.
+/// * KEYSLOT `key` -- Returns the hash slot of a key.
+/// * SLOTS -- Returns the ranges of consecutive slots served by the same node,
+///   each as an array holding the first and last slot of the range and the
+///   address and ID of the node.
+/// * SHARDS -- Returns, for each node, the ranges of slots it serves and a
+///   description of the node.
+/// * ADDSLOTS `slot` [`slot` ...] -- Assign slots to the node.
+/// * ADDSLOTSRANGE `start` `end` [`start` `end` ...] -- Assign ranges of slots
+///   to the node.
+/// * DELSLOTS `slot` [`slot` ...] -- Unassign slots, whichever node serves
+///   them.
+/// * MEET `host` `port` -- Add the node at `host` and `port` to the cluster,
+///   along with the slots it serves.
+/// * SETSLOT `slot` NODE|MIGRATING|IMPORTING `node` -- Assign a slot to a
+///   node, or set it as migrating to or importing from a node.
+/// * SETSLOT `slot` STABLE -- Stop migrating or importing a slot.
+#[derive(Debug)]
+pub struct Cluster {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    MyId,
+    KeySlot(String),
+    Slots,
+    Shards,
+    AddSlots(Vec<u16>),
+    AddSlotsRange(Vec<(u16, u16)>),
+    DelSlots(Vec<u16>),
+    Meet(String, u16),
+    SetSlot(u16, SlotState),
+}
+
+/// Ask the node to serve the next command if its keys belong to a slot being
+/// imported, rather than redirecting the client to the node serving the slot.
+///
+/// Sent by clients redirected by an `ASK` error. Only the next command is
+/// affected.
+#[derive(Debug, Default)]
+pub struct Asking;
+
+impl Cluster {
+    /// Create a new `Cluster` command returning the ID of the node.
+    pub fn myid() -> Cluster {
+        Cluster {
+            subcommand: Subcommand::MyId,
+        }
+    }
+
+    /// Create a new `Cluster` command returning the hash slot of `key`.
+    pub fn key_slot(key: impl ToString) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::KeySlot(key.to_string()),
+        }
+    }
+
+    /// Create a new `Cluster` command returning the slots served by each
+    /// node.
+    pub fn slots() -> Cluster {
+        Cluster {
+            subcommand: Subcommand::Slots,
+        }
+    }
+
+    /// Create a new `Cluster` command describing the nodes of the cluster.
+    pub fn shards() -> Cluster {
+        Cluster {
+            subcommand: Subcommand::Shards,
+        }
+    }
+
+    /// Create a new `Cluster` command assigning `slots` to the node.
+    pub fn add_slots(slots: Vec<u16>) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::AddSlots(slots),
+        }
+    }
+
+    /// Create a new `Cluster` command assigning the slots from `start` to
+    /// `end`, inclusive, to the node.
+    pub fn add_slots_range(start: u16, end: u16) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::AddSlotsRange(vec![(start, end)]),
+        }
+    }
+
+    /// Create a new `Cluster` command unassigning `slots`.
+    pub fn del_slots(slots: Vec<u16>) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::DelSlots(slots),
+        }
+    }
+
+    /// Create a new `Cluster` command adding the node at `host` and `port` to
+    /// the cluster.
+    pub fn meet(host: impl ToString, port: u16) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::Meet(host.to_string(), port),
+        }
+    }
+
+    /// Create a new `Cluster` command assigning `slot` to the node `node_id`.
+    pub fn set_slot_node(slot: u16, node_id: impl ToString) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::SetSlot(slot, SlotState::Node(node_id.to_string())),
+        }
+    }
+
+    /// Create a new `Cluster` command setting `slot` as migrating to the node
+    /// `node_id`.
+    pub fn set_slot_migrating(slot: u16, node_id: impl ToString) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::SetSlot(slot, SlotState::Migrating(node_id.to_string())),
+        }
+    }
+
+    /// Create a new `Cluster` command setting `slot` as importing from the
+    /// node `node_id`.
+    pub fn set_slot_importing(slot: u16, node_id: impl ToString) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::SetSlot(slot, SlotState::Importing(node_id.to_string())),
+        }
+    }
+
+    /// Create a new `Cluster` command which stops migrating or importing
+    /// `slot`.
+    pub fn set_slot_stable(slot: u16) -> Cluster {
+        Cluster {
+            subcommand: Subcommand::SetSlot(slot, SlotState::Stable),
+        }
+    }
+
+    /// Parse a `Cluster` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CLUSTER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Cluster` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// CLUSTER MYID
+    /// CLUSTER KEYSLOT key
+    /// CLUSTER SLOTS
+    /// CLUSTER SHARDS
+    /// CLUSTER ADDSLOTS slot [slot ...]
+    /// CLUSTER ADDSLOTSRANGE start end [start end ...]
+    /// CLUSTER DELSLOTS slot [slot ...]
+    /// CLUSTER MEET host port
+    /// CLUSTER SETSLOT slot NODE|MIGRATING|IMPORTING node
+    /// CLUSTER SETSLOT slot STABLE
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Cluster> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "MYID" => Subcommand::MyId,
+            "KEYSLOT" => Subcommand::KeySlot(parse.next_string()?),
+            "SLOTS" => Subcommand::Slots,
+            "SHARDS" => Subcommand::Shards,
+            "ADDSLOTS" => Subcommand::AddSlots(parse_slots(parse)?),
+            "ADDSLOTSRANGE" => {
+                let mut ranges = vec![(parse_slot(parse)?, parse_slot(parse)?)];
+                loop {
+                    match parse.next_int() {
+                        Ok(start) => ranges.push((slot(start)?, parse_slot(parse)?)),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                Subcommand::AddSlotsRange(ranges)
+            }
+            "DELSLOTS" => Subcommand::DelSlots(parse_slots(parse)?),
+            "MEET" => {
+                let host = parse.next_string()?;
+                let port = parse.next_int()?;
+                if port > u16::MAX as u64 {
+                    return Err("ERR Invalid node address specified".into());
+                }
+                Subcommand::Meet(host, port as u16)
+            }
+            "SETSLOT" => {
+                let slot = parse_slot(parse)?;
+                let state = parse.next_string()?.to_uppercase();
+                let state = match &state[..] {
+                    "NODE" => SlotState::Node(parse.next_string()?),
+                    "MIGRATING" => SlotState::Migrating(parse.next_string()?),
+                    "IMPORTING" => SlotState::Importing(parse.next_string()?),
+                    "STABLE" => SlotState::Stable,
+                    _ => return Err("ERR Invalid CLUSTER SETSLOT action".into()),
+                };
+                Subcommand::SetSlot(slot, state)
+            }
+            _ => return Err(format!("unknown `CLUSTER` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Cluster { subcommand })
+    }
+
+    /// Returns `true` for `CLUSTER MEET`, which connects to another node.
+    pub(crate) fn is_meet(&self) -> bool {
+        matches!(self.subcommand, Subcommand::Meet(..))
+    }
+
+    /// Apply the `Cluster` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::MyId => match db.cluster_myself() {
+                Ok(node) => Frame::Bulk(Bytes::from(node.id.into_bytes())),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::KeySlot(key) => match db.cluster_myself() {
+                Ok(_) => Frame::Integer(key_slot(&key) as i64),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::Slots => match db.cluster_slots() {
+                Ok(ranges) => Frame::Array(
+                    ranges
+                        .into_iter()
+                        .map(|(start, end, node)| {
+                            Frame::Array(vec![
+                                Frame::Integer(start as i64),
+                                Frame::Integer(end as i64),
+                                node_frame(node),
+                            ])
+                        })
+                        .collect(),
+                ),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::Shards => match (db.cluster_nodes(), db.cluster_slots()) {
+                (Ok(nodes), Ok(ranges)) => Frame::Array(
+                    nodes
+                        .into_iter()
+                        .map(|node| shard_frame(node, &ranges))
+                        .collect(),
+                ),
+                (Err(err), _) | (_, Err(err)) => Frame::Error(err),
+            },
+            Subcommand::AddSlots(slots) => ok_or_error(db.add_cluster_slots(&slots)),
+            Subcommand::AddSlotsRange(ranges) => {
+                let slots = ranges
+                    .into_iter()
+                    .flat_map(|(start, end)| start..=end)
+                    .collect::<Vec<_>>();
+                ok_or_error(db.add_cluster_slots(&slots))
+            }
+            Subcommand::DelSlots(slots) => ok_or_error(db.del_cluster_slots(&slots)),
+            Subcommand::Meet(host, port) => match db.cluster_myself() {
+                Ok(_) => match meet(&host, port).await {
+                    Ok((id, slots)) => {
+                        let node = Node { id, host, port };
+                        ok_or_error(db.meet_cluster_node(node, &slots))
+                    }
+                    Err(err) => Frame::Error(format!("ERR Failed to meet the node: {}", err)),
+                },
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::SetSlot(slot, state) => ok_or_error(db.set_cluster_slot(slot, state)),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Cluster` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("cluster".as_bytes()));
+
+        let mut push = |arg: String| frame.push_bulk(Bytes::from(arg.into_bytes()));
+        match self.subcommand {
+            Subcommand::MyId => push("myid".to_string()),
+            Subcommand::KeySlot(key) => {
+                push("keyslot".to_string());
+                push(key);
+            }
+            Subcommand::Slots => push("slots".to_string()),
+            Subcommand::Shards => push("shards".to_string()),
+            Subcommand::AddSlots(slots) => {
+                push("addslots".to_string());
+                for slot in slots {
+                    push(slot.to_string());
+                }
+            }
+            Subcommand::AddSlotsRange(ranges) => {
+                push("addslotsrange".to_string());
+                for (start, end) in ranges {
+                    push(start.to_string());
+                    push(end.to_string());
+                }
+            }
+            Subcommand::DelSlots(slots) => {
+                push("delslots".to_string());
+                for slot in slots {
+                    push(slot.to_string());
+                }
+            }
+            Subcommand::Meet(host, port) => {
+                push("meet".to_string());
+                push(host);
+                push(port.to_string());
+            }
+            Subcommand::SetSlot(slot, state) => {
+                push("setslot".to_string());
+                push(slot.to_string());
+                match state {
+                    SlotState::Node(id) => {
+                        push("node".to_string());
+                        push(id);
+                    }
+                    SlotState::Migrating(id) => {
+                        push("migrating".to_string());
+                        push(id);
+                    }
+                    SlotState::Importing(id) => {
+                        push("importing".to_string());
+                        push(id);
+                    }
+                    SlotState::Stable => push("stable".to_string()),
+                }
+            }
+        }
+
+        frame
+    }
+}
+
+impl Asking {
+    /// Create a new `Asking` command.
+    pub fn new() -> Asking {
+        Asking
+    }
+
+    /// Parse an `Asking` instance from a received frame.
+    ///
+    /// The `ASKING` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// ASKING
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Asking> {
+        Ok(Asking)
+    }
+
+    /// Apply the `Asking` command.
+    ///
+    /// The connection remembers that `ASKING` was received, this only replies
+    /// when the command is applied in another context, such as a transaction.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.cluster_myself() {
+            Ok(_) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Asking` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("asking".as_bytes()));
+        frame
+    }
+}
+
+/// Parses one or more slots.
+fn parse_slots(parse: &mut Parse) -> crate::Result<Vec<u16>> {
+    let mut slots = vec![parse_slot(parse)?];
+
+    loop {
+        match parse.next_int() {
+            Ok(next) => slots.push(slot(next)?),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(slots)
+}
+
+/// Parses a slot.
+fn parse_slot(parse: &mut Parse) -> crate::Result<u16> {
+    slot(parse.next_int()?)
+}
+
+/// Checks that `slot` is lower than `SLOTS`.
+fn slot(slot: u64) -> crate::Result<u16> {
+    if slot < SLOTS as u64 {
+        Ok(slot as u16)
+    } else {
+        Err("ERR Invalid or out of range slot".into())
+    }
+}
+
+fn ok_or_error(res: Result<(), String>) -> Frame {
+    match res {
+        Ok(()) => Frame::Simple("OK".to_string()),
+        Err(err) => Frame::Error(err),
+    }
+}
+
+/// Returns the address and ID of `node`, as replied by `CLUSTER SLOTS`.
+fn node_frame(node: Node) -> Frame {
+    Frame::Array(vec![
+        Frame::Bulk(Bytes::from(node.host.into_bytes())),
+        Frame::Integer(node.port as i64),
+        Frame::Bulk(Bytes::from(node.id.into_bytes())),
+    ])
+}
+
+/// Returns the description of `node` and of the `ranges` it serves, as
+/// replied by `CLUSTER SHARDS`.
+fn shard_frame(node: Node, ranges: &[(u16, u16, Node)]) -> Frame {
+    let slots = ranges
+        .iter()
+        .filter(|(_, _, owner)| owner.id == node.id)
+        .flat_map(|(start, end, _)| {
+            vec![Frame::Integer(*start as i64), Frame::Integer(*end as i64)]
+        })
+        .collect();
+
+    let bulk = |value: &str| Frame::Bulk(Bytes::from(value.to_string().into_bytes()));
+    let description = Frame::Array(vec![
+        bulk("id"),
+        bulk(&node.id),
+        bulk("port"),
+        Frame::Integer(node.port as i64),
+        bulk("ip"),
+        bulk(&node.host),
+        bulk("endpoint"),
+        bulk(&node.host),
+        bulk("role"),
+        bulk("master"),
+        bulk("health"),
+        bulk("online"),
+    ]);
+
+    Frame::Array(vec![
+        bulk("slots"),
+        Frame::Array(slots),
+        bulk("nodes"),
+        Frame::Array(vec![description]),
+    ])
+}
+
+/// Connects to the node at `host` and `port`, returning its ID and the slots
+/// it serves.
+async fn meet(host: &str, port: u16) -> crate::Result<(String, Vec<u16>)> {
+    let socket = TcpStream::connect((host, port)).await?;
+    let mut connection = Connection::new(socket);
+
+    connection
+        .write_frame(&Cluster::myid().into_frame())
+        .await?;
+    let id = match connection.read_frame().await? {
+        Some(Frame::Bulk(id)) => String::from_utf8(id.to_vec())?,
+        Some(Frame::Error(err)) => return Err(err.into()),
+        Some(frame) => return Err(frame.to_error()),
+        None => return Err("connection closed".into()),
+    };
+
+    connection
+        .write_frame(&Cluster::slots().into_frame())
+        .await?;
+    let ranges = match connection.read_frame().await? {
+        Some(Frame::Array(ranges)) => ranges,
+        Some(Frame::Error(err)) => return Err(err.into()),
+        Some(frame) => return Err(frame.to_error()),
+        None => return Err("connection closed".into()),
+    };
+
+    // Only the slots the node serves itself are kept, the other nodes it
+    // knows about are not added to the cluster.
+    let mut slots = vec![];
+    for range in ranges {
+        match &range {
+            Frame::Array(parts) => match &parts[..] {
+                [Frame::Integer(start), Frame::Integer(end), Frame::Array(node), ..] => {
+                    if matches!(node.get(2), Some(Frame::Bulk(owner)) if *owner == id) {
+                        slots.extend(*start as u16..=*end as u16);
+                    }
+                }
+                _ => return Err(range.to_error()),
+            },
+            _ => return Err(range.to_error()),
+        }
+    }
+
+    Ok((id, slots))
+}
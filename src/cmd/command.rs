@@ -0,0 +1,1657 @@
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Describes the commands implemented by the server.
+///
+/// # Subcommands
+///
+/// * (none) -- Returns the description of every command.
+/// * COUNT -- Returns the number of commands.
+/// * INFO [`name` ...] -- Returns the description of the commands `name`, or
+///   nil for the unknown ones. Every command is described if no name is
+///   given.
+/// * DOCS [`name` ...] -- Returns the summary and group of the commands
+///   `name`, as a map indexed by name. Unknown commands are skipped. Every
+///   command is documented if no name is given.
+///
+/// A command is described by an array holding its name, its arity, its
+/// flags, and the positions of its first and last keys along with the step
+/// between two keys.
+#[derive(Debug, Default)]
+pub struct Commands {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug, Default)]
+enum Subcommand {
+    #[default]
+    All,
+    Count,
+    Info(Vec<String>),
+    Docs(Vec<String>),
+}
+
+/// Static description of a command.
+struct Spec {
+    name: &'static str,
+
+    /// Number of arguments, including the name of the command. A negative
+    /// arity `-n` means at least `n` arguments.
+    arity: i64,
+
+    /// Flags, separated by spaces.
+    flags: &'static str,
+
+    /// Positions of the first and last keys in the arguments, and the step
+    /// between two keys. The last key is counted from the end if negative.
+    /// Commands taking no key, or whose keys are found by parsing their
+    /// arguments (`movablekeys`), have `(0, 0, 0)`.
+    keys: (i64, i64, i64),
+
+    /// Group the command is documented in.
+    group: &'static str,
+
+    summary: &'static str,
+}
+
+/// The commands, sorted by name.
+const COMMANDS: &[Spec] = &[
+    Spec {
+        name: "acl",
+        arity: -2,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "A container for Access List Control commands.",
+    },
+    Spec {
+        name: "append",
+        arity: 3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "asking",
+        arity: 1,
+        flags: "fast",
+        keys: (0, 0, 0),
+        group: "cluster",
+        summary: "Signals that a cluster client is following an -ASK redirect.",
+    },
+    Spec {
+        name: "auth",
+        arity: -2,
+        flags: "noscript loading stale fast no-auth",
+        keys: (0, 0, 0),
+        group: "connection",
+        summary: "Authenticates the connection.",
+    },
+    Spec {
+        name: "bgrewriteaof",
+        arity: 1,
+        flags: "admin noscript",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Asynchronously rewrites the append-only file to disk.",
+    },
+    Spec {
+        name: "bgsave",
+        arity: -1,
+        flags: "admin noscript",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Asynchronously saves the database(s) to disk.",
+    },
+    Spec {
+        name: "bitcount",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "bitmap",
+        summary: "Counts the number of set bits (population counting) in a string.",
+    },
+    Spec {
+        name: "bitfield",
+        arity: -2,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "bitmap",
+        summary: "Performs arbitrary bitfield integer operations on strings.",
+    },
+    Spec {
+        name: "bitop",
+        arity: -4,
+        flags: "write denyoom",
+        keys: (2, -1, 1),
+        group: "bitmap",
+        summary: "Performs bitwise operations on multiple strings, and stores the result.",
+    },
+    Spec {
+        name: "bitpos",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "bitmap",
+        summary: "Finds the first set (1) or clear (0) bit in a string.",
+    },
+    Spec {
+        name: "blmove",
+        arity: 6,
+        flags: "write denyoom noscript blocking",
+        keys: (1, 2, 1),
+        group: "list",
+        summary: "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise.",
+    },
+    Spec {
+        name: "blpop",
+        arity: -3,
+        flags: "write noscript blocking",
+        keys: (1, -2, 1),
+        group: "list",
+        summary: "Removes and returns the first element in a list. Blocks until an element is available otherwise.",
+    },
+    Spec {
+        name: "brpop",
+        arity: -3,
+        flags: "write noscript blocking",
+        keys: (1, -2, 1),
+        group: "list",
+        summary: "Removes and returns the last element in a list. Blocks until an element is available otherwise.",
+    },
+    Spec {
+        name: "brpoplpush",
+        arity: 4,
+        flags: "write denyoom noscript blocking",
+        keys: (1, 2, 1),
+        group: "list",
+        summary: "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise.",
+    },
+    Spec {
+        name: "bzpopmax",
+        arity: -3,
+        flags: "write noscript blocking fast",
+        keys: (1, -2, 1),
+        group: "sorted_set",
+        summary: "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member is available otherwise.",
+    },
+    Spec {
+        name: "bzpopmin",
+        arity: -3,
+        flags: "write noscript blocking fast",
+        keys: (1, -2, 1),
+        group: "sorted_set",
+        summary: "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise.",
+    },
+    Spec {
+        name: "client",
+        arity: -2,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "connection",
+        summary: "A container for client connection commands.",
+    },
+    Spec {
+        name: "cluster",
+        arity: -2,
+        flags: "",
+        keys: (0, 0, 0),
+        group: "cluster",
+        summary: "A container for Redis Cluster commands.",
+    },
+    Spec {
+        name: "command",
+        arity: -1,
+        flags: "loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Returns detailed information about all commands.",
+    },
+    Spec {
+        name: "config",
+        arity: -2,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "A container for server configuration commands.",
+    },
+    Spec {
+        name: "copy",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, 2, 1),
+        group: "generic",
+        summary: "Copies the value of a key to a new key.",
+    },
+    Spec {
+        name: "dbsize",
+        arity: 1,
+        flags: "readonly fast",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Returns the number of keys in the database.",
+    },
+    Spec {
+        name: "debug",
+        arity: -2,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "A container for debugging commands.",
+    },
+    Spec {
+        name: "decr",
+        arity: 2,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+    },
+    Spec {
+        name: "decrby",
+        arity: 3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+    },
+    Spec {
+        name: "del",
+        arity: -2,
+        flags: "write",
+        keys: (1, -1, 1),
+        group: "generic",
+        summary: "Deletes one or more keys.",
+    },
+    Spec {
+        name: "discard",
+        arity: 1,
+        flags: "noscript loading stale fast",
+        keys: (0, 0, 0),
+        group: "transactions",
+        summary: "Discards a transaction.",
+    },
+    Spec {
+        name: "dump",
+        arity: 2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Returns a serialized representation of the value stored at a key.",
+    },
+    Spec {
+        name: "eval",
+        arity: -3,
+        flags: "noscript stale movablekeys",
+        keys: (0, 0, 0),
+        group: "scripting",
+        summary: "Executes a server-side Lua script.",
+    },
+    Spec {
+        name: "evalsha",
+        arity: -3,
+        flags: "noscript stale movablekeys",
+        keys: (0, 0, 0),
+        group: "scripting",
+        summary: "Executes a server-side Lua script by SHA1 digest.",
+    },
+    Spec {
+        name: "exec",
+        arity: 1,
+        flags: "noscript loading stale",
+        keys: (0, 0, 0),
+        group: "transactions",
+        summary: "Executes all commands in a transaction.",
+    },
+    Spec {
+        name: "exists",
+        arity: -2,
+        flags: "readonly fast",
+        keys: (1, -1, 1),
+        group: "generic",
+        summary: "Determines whether one or more keys exist.",
+    },
+    Spec {
+        name: "expire",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Sets the expiration time of a key in seconds.",
+    },
+    Spec {
+        name: "expireat",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Sets the expiration time of a key to a Unix timestamp.",
+    },
+    Spec {
+        name: "fcall",
+        arity: -3,
+        flags: "noscript stale movablekeys",
+        keys: (0, 0, 0),
+        group: "scripting",
+        summary: "Invokes a function.",
+    },
+    Spec {
+        name: "fcall_ro",
+        arity: -3,
+        flags: "readonly noscript stale movablekeys",
+        keys: (0, 0, 0),
+        group: "scripting",
+        summary: "Invokes a read-only function.",
+    },
+    Spec {
+        name: "flushall",
+        arity: -1,
+        flags: "write",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Removes all keys from all databases.",
+    },
+    Spec {
+        name: "flushdb",
+        arity: -1,
+        flags: "write",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Removes all keys from the current database.",
+    },
+    Spec {
+        name: "function",
+        arity: -2,
+        flags: "",
+        keys: (0, 0, 0),
+        group: "scripting",
+        summary: "A container for function commands.",
+    },
+    Spec {
+        name: "geoadd",
+        arity: -5,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "geo",
+        summary: "Adds one or more members to a geospatial index. The key is created if it doesn't exist.",
+    },
+    Spec {
+        name: "geodist",
+        arity: -4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "geo",
+        summary: "Returns the distance between two members of a geospatial index.",
+    },
+    Spec {
+        name: "geopos",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "geo",
+        summary: "Returns the longitude and latitude of members from a geospatial index.",
+    },
+    Spec {
+        name: "geosearch",
+        arity: -7,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "geo",
+        summary: "Queries a geospatial index for members inside an area of a box or a circle.",
+    },
+    Spec {
+        name: "get",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Returns the string value of a key.",
+    },
+    Spec {
+        name: "getbit",
+        arity: 3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "bitmap",
+        summary: "Returns a bit value by offset.",
+    },
+    Spec {
+        name: "getdel",
+        arity: 2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Returns the string value of a key after deleting the key.",
+    },
+    Spec {
+        name: "getex",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Returns the string value of a key after setting its expiration time.",
+    },
+    Spec {
+        name: "getrange",
+        arity: 4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Returns a substring of the string stored at a key.",
+    },
+    Spec {
+        name: "hdel",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain.",
+    },
+    Spec {
+        name: "hello",
+        arity: -1,
+        flags: "noscript loading stale fast no-auth",
+        keys: (0, 0, 0),
+        group: "connection",
+        summary: "Handshakes with the Redis server.",
+    },
+    Spec {
+        name: "hget",
+        arity: 3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Returns the value of a field in a hash.",
+    },
+    Spec {
+        name: "hgetall",
+        arity: 2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Returns all fields and values in a hash.",
+    },
+    Spec {
+        name: "hincrby",
+        arity: 4,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist.",
+    },
+    Spec {
+        name: "hincrbyfloat",
+        arity: 4,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist.",
+    },
+    Spec {
+        name: "hmget",
+        arity: -3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Returns the values of all fields in a hash.",
+    },
+    Spec {
+        name: "hrandfield",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Returns one or more random fields from a hash.",
+    },
+    Spec {
+        name: "hscan",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Iterates over fields and values of a hash.",
+    },
+    Spec {
+        name: "hset",
+        arity: -4,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Creates or modifies the value of a field in a hash.",
+    },
+    Spec {
+        name: "hsetnx",
+        arity: 4,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "hash",
+        summary: "Sets the value of a field in a hash only when the field doesn't exist.",
+    },
+    Spec {
+        name: "incr",
+        arity: 2,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+    },
+    Spec {
+        name: "incrby",
+        arity: 3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+    },
+    Spec {
+        name: "incrbyfloat",
+        arity: 3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Increments the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+    },
+    Spec {
+        name: "info",
+        arity: -1,
+        flags: "loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Returns information and statistics about the server.",
+    },
+    Spec {
+        name: "keys",
+        arity: 2,
+        flags: "readonly",
+        keys: (0, 0, 0),
+        group: "generic",
+        summary: "Returns all key names that match a pattern.",
+    },
+    Spec {
+        name: "lastsave",
+        arity: 1,
+        flags: "loading stale fast",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Returns the Unix timestamp of the last successful save to disk.",
+    },
+    Spec {
+        name: "latency",
+        arity: -2,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "A container for latency diagnostics commands.",
+    },
+    Spec {
+        name: "lcs",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 2, 1),
+        group: "string",
+        summary: "Finds the longest common substring.",
+    },
+    Spec {
+        name: "linsert",
+        arity: 5,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Inserts an element before or after another element in a list.",
+    },
+    Spec {
+        name: "llen",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Returns the length of a list.",
+    },
+    Spec {
+        name: "lmove",
+        arity: 5,
+        flags: "write denyoom",
+        keys: (1, 2, 1),
+        group: "list",
+        summary: "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved.",
+    },
+    Spec {
+        name: "lpop",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Returns the first elements in a list after removing it. Deletes the list if the last element was popped.",
+    },
+    Spec {
+        name: "lpos",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Returns the index of matching elements in a list.",
+    },
+    Spec {
+        name: "lpush",
+        arity: -3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "lrange",
+        arity: 4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Returns a range of elements from a list.",
+    },
+    Spec {
+        name: "lrem",
+        arity: 4,
+        flags: "write",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Removes elements from a list. Deletes the list if the last element was removed.",
+    },
+    Spec {
+        name: "lset",
+        arity: 4,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Sets the value of an element in a list by its index.",
+    },
+    Spec {
+        name: "ltrim",
+        arity: 4,
+        flags: "write",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Removes elements from both ends a list. Deletes the list if all elements were trimmed.",
+    },
+    Spec {
+        name: "memory",
+        arity: -2,
+        flags: "",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "A container for memory diagnostics commands.",
+    },
+    Spec {
+        name: "mget",
+        arity: -2,
+        flags: "readonly fast",
+        keys: (1, -1, 1),
+        group: "string",
+        summary: "Atomically returns the string values of one or more keys.",
+    },
+    Spec {
+        name: "migrate",
+        arity: -6,
+        flags: "write noscript movablekeys",
+        keys: (0, 0, 0),
+        group: "generic",
+        summary: "Atomically transfers a key from one Redis instance to another.",
+    },
+    Spec {
+        name: "monitor",
+        arity: 1,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Listens for all requests received by the server in real-time.",
+    },
+    Spec {
+        name: "move",
+        arity: 3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Moves a key to another database.",
+    },
+    Spec {
+        name: "mset",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, -1, 2),
+        group: "string",
+        summary: "Atomically creates or modifies the string values of one or more keys.",
+    },
+    Spec {
+        name: "msetnx",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, -1, 2),
+        group: "string",
+        summary: "Atomically modifies the string values of one or more keys only when all keys don't exist.",
+    },
+    Spec {
+        name: "multi",
+        arity: 1,
+        flags: "noscript loading stale fast",
+        keys: (0, 0, 0),
+        group: "transactions",
+        summary: "Starts a transaction.",
+    },
+    Spec {
+        name: "persist",
+        arity: 2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Removes the expiration time of a key.",
+    },
+    Spec {
+        name: "pexpire",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Sets the expiration time of a key in milliseconds.",
+    },
+    Spec {
+        name: "pexpireat",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+    },
+    Spec {
+        name: "pfadd",
+        arity: -2,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "hyperloglog",
+        summary: "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "pfcount",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, -1, 1),
+        group: "hyperloglog",
+        summary: "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s).",
+    },
+    Spec {
+        name: "pfmerge",
+        arity: -2,
+        flags: "write denyoom",
+        keys: (1, -1, 1),
+        group: "hyperloglog",
+        summary: "Merges one or more HyperLogLog values into a single key.",
+    },
+    Spec {
+        name: "psetex",
+        arity: 4,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Sets both string value and expiration time in milliseconds of a key. The key is created if it doesn't exist.",
+    },
+    Spec {
+        name: "psubscribe",
+        arity: -2,
+        flags: "pubsub noscript loading stale",
+        keys: (0, 0, 0),
+        group: "pubsub",
+        summary: "Listens for messages published to channels that match one or more patterns.",
+    },
+    Spec {
+        name: "psync",
+        arity: -3,
+        flags: "admin noscript",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "An internal command used in replication.",
+    },
+    Spec {
+        name: "pttl",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Returns the expiration time in milliseconds of a key.",
+    },
+    Spec {
+        name: "publish",
+        arity: 3,
+        flags: "pubsub loading stale fast",
+        keys: (0, 0, 0),
+        group: "pubsub",
+        summary: "Posts a message to a channel.",
+    },
+    Spec {
+        name: "pubsub",
+        arity: -2,
+        flags: "",
+        keys: (0, 0, 0),
+        group: "pubsub",
+        summary: "A container for Pub/Sub commands.",
+    },
+    Spec {
+        name: "punsubscribe",
+        arity: -1,
+        flags: "pubsub noscript loading stale",
+        keys: (0, 0, 0),
+        group: "pubsub",
+        summary: "Stops listening to messages published to channels that match one or more patterns.",
+    },
+    Spec {
+        name: "randomkey",
+        arity: 1,
+        flags: "readonly",
+        keys: (0, 0, 0),
+        group: "generic",
+        summary: "Returns a random key name from the database.",
+    },
+    Spec {
+        name: "rename",
+        arity: 3,
+        flags: "write",
+        keys: (1, 2, 1),
+        group: "generic",
+        summary: "Renames a key and overwrites the destination.",
+    },
+    Spec {
+        name: "renamenx",
+        arity: 3,
+        flags: "write fast",
+        keys: (1, 2, 1),
+        group: "generic",
+        summary: "Renames a key only when the target key name doesn't exist.",
+    },
+    Spec {
+        name: "replconf",
+        arity: -1,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "An internal command for configuring the replication stream.",
+    },
+    Spec {
+        name: "replicaof",
+        arity: 3,
+        flags: "admin noscript stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Configures a server as replica of another, or promotes it to a master.",
+    },
+    Spec {
+        name: "reset",
+        arity: 1,
+        flags: "noscript loading stale fast no-auth",
+        keys: (0, 0, 0),
+        group: "connection",
+        summary: "Resets the connection.",
+    },
+    Spec {
+        name: "restore",
+        arity: -4,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Creates a key from the serialized representation of a value.",
+    },
+    Spec {
+        name: "role",
+        arity: 1,
+        flags: "noscript loading stale fast",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Returns the replication role.",
+    },
+    Spec {
+        name: "rpop",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Returns and removes the last elements of a list. Deletes the list if the last element was popped.",
+    },
+    Spec {
+        name: "rpoplpush",
+        arity: 3,
+        flags: "write denyoom",
+        keys: (1, 2, 1),
+        group: "list",
+        summary: "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped.",
+    },
+    Spec {
+        name: "rpush",
+        arity: -3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "list",
+        summary: "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "sadd",
+        arity: -3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Adds one or more members to a set. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "save",
+        arity: 1,
+        flags: "admin noscript",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Synchronously saves the database(s) to disk.",
+    },
+    Spec {
+        name: "scan",
+        arity: -2,
+        flags: "readonly",
+        keys: (0, 0, 0),
+        group: "generic",
+        summary: "Iterates over the key names in the database.",
+    },
+    Spec {
+        name: "scard",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Returns the number of members in a set.",
+    },
+    Spec {
+        name: "script",
+        arity: -2,
+        flags: "",
+        keys: (0, 0, 0),
+        group: "scripting",
+        summary: "A container for Lua scripts management commands.",
+    },
+    Spec {
+        name: "sdiff",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, -1, 1),
+        group: "set",
+        summary: "Returns the difference of multiple sets.",
+    },
+    Spec {
+        name: "sdiffstore",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, -1, 1),
+        group: "set",
+        summary: "Stores the difference of multiple sets in a key.",
+    },
+    Spec {
+        name: "select",
+        arity: 2,
+        flags: "loading stale fast",
+        keys: (0, 0, 0),
+        group: "connection",
+        summary: "Changes the selected database.",
+    },
+    Spec {
+        name: "set",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+    },
+    Spec {
+        name: "setbit",
+        arity: 4,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "bitmap",
+        summary: "Sets or clears the bit at offset of the string value. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "setex",
+        arity: 4,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Sets the string value and expiration time of a key. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "setnx",
+        arity: 3,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Sets the string value of a key only when the key doesn't exist.",
+    },
+    Spec {
+        name: "setrange",
+        arity: 4,
+        flags: "write denyoom",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "shutdown",
+        arity: -1,
+        flags: "admin noscript loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Synchronously saves the database(s) to disk and shuts down the Redis server.",
+    },
+    Spec {
+        name: "sinter",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, -1, 1),
+        group: "set",
+        summary: "Returns the intersect of multiple sets.",
+    },
+    Spec {
+        name: "sintercard",
+        arity: -3,
+        flags: "readonly movablekeys",
+        keys: (0, 0, 0),
+        group: "set",
+        summary: "Returns the number of members of the intersect of multiple sets.",
+    },
+    Spec {
+        name: "sinterstore",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, -1, 1),
+        group: "set",
+        summary: "Stores the intersect of multiple sets in a key.",
+    },
+    Spec {
+        name: "sismember",
+        arity: 3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Determines whether a member belongs to a set.",
+    },
+    Spec {
+        name: "slowlog",
+        arity: -2,
+        flags: "admin loading stale",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "A container for slow log commands.",
+    },
+    Spec {
+        name: "smembers",
+        arity: 2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Returns all members of a set.",
+    },
+    Spec {
+        name: "smismember",
+        arity: -3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Determines whether multiple members belong to a set.",
+    },
+    Spec {
+        name: "sort",
+        arity: -2,
+        flags: "write denyoom movablekeys",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Sorts the elements in a list, a set, or a sorted set, optionally storing the result.",
+    },
+    Spec {
+        name: "spop",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped.",
+    },
+    Spec {
+        name: "spublish",
+        arity: 3,
+        flags: "pubsub loading stale fast",
+        keys: (1, 1, 1),
+        group: "pubsub",
+        summary: "Posts a message to a shard channel.",
+    },
+    Spec {
+        name: "srandmember",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Returns one or more random members from a set.",
+    },
+    Spec {
+        name: "srem",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Removes one or more members from a set. Deletes the set if the last member was removed.",
+    },
+    Spec {
+        name: "sscan",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "set",
+        summary: "Iterates over members of a set.",
+    },
+    Spec {
+        name: "ssubscribe",
+        arity: -2,
+        flags: "pubsub noscript loading stale",
+        keys: (1, -1, 1),
+        group: "pubsub",
+        summary: "Listens for messages published to shard channels.",
+    },
+    Spec {
+        name: "strlen",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "string",
+        summary: "Returns the length of a string value.",
+    },
+    Spec {
+        name: "subscribe",
+        arity: -2,
+        flags: "pubsub noscript loading stale",
+        keys: (0, 0, 0),
+        group: "pubsub",
+        summary: "Listens for messages published to channels.",
+    },
+    Spec {
+        name: "sunion",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, -1, 1),
+        group: "set",
+        summary: "Returns the union of multiple sets.",
+    },
+    Spec {
+        name: "sunionstore",
+        arity: -3,
+        flags: "write denyoom",
+        keys: (1, -1, 1),
+        group: "set",
+        summary: "Stores the union of multiple sets in a key.",
+    },
+    Spec {
+        name: "sunsubscribe",
+        arity: -1,
+        flags: "pubsub noscript loading stale",
+        keys: (1, -1, 1),
+        group: "pubsub",
+        summary: "Stops listening to messages posted to shard channels.",
+    },
+    Spec {
+        name: "swapdb",
+        arity: 3,
+        flags: "write fast",
+        keys: (0, 0, 0),
+        group: "server",
+        summary: "Swaps two Redis databases.",
+    },
+    Spec {
+        name: "touch",
+        arity: -2,
+        flags: "readonly fast",
+        keys: (1, -1, 1),
+        group: "generic",
+        summary: "Returns the number of existing keys out of those specified after updating the time they were last accessed.",
+    },
+    Spec {
+        name: "ttl",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Returns the expiration time in seconds of a key.",
+    },
+    Spec {
+        name: "type",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "generic",
+        summary: "Determines the type of value stored at a key.",
+    },
+    Spec {
+        name: "unlink",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, -1, 1),
+        group: "generic",
+        summary: "Asynchronously deletes one or more keys.",
+    },
+    Spec {
+        name: "unsubscribe",
+        arity: -1,
+        flags: "pubsub noscript loading stale",
+        keys: (0, 0, 0),
+        group: "pubsub",
+        summary: "Stops listening to messages posted to channels.",
+    },
+    Spec {
+        name: "unwatch",
+        arity: 1,
+        flags: "noscript loading stale fast",
+        keys: (0, 0, 0),
+        group: "transactions",
+        summary: "Forgets about watched keys of a transaction.",
+    },
+    Spec {
+        name: "wait",
+        arity: 3,
+        flags: "noscript",
+        keys: (0, 0, 0),
+        group: "generic",
+        summary: "Blocks until the asynchronous replication of all preceding write commands sent by the connection is completed.",
+    },
+    Spec {
+        name: "watch",
+        arity: -2,
+        flags: "noscript loading stale fast",
+        keys: (1, -1, 1),
+        group: "transactions",
+        summary: "Monitors changes to keys to determine the execution of a transaction.",
+    },
+    Spec {
+        name: "xack",
+        arity: -4,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Returns the number of messages that were successfully acknowledged by the consumer group member of a stream.",
+    },
+    Spec {
+        name: "xadd",
+        arity: -5,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Appends a new message to a stream. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "xclaim",
+        arity: -6,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Changes, or acquires, ownership of a message in a consumer group, as if the message was delivered a consumer group member.",
+    },
+    Spec {
+        name: "xdel",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Returns the number of messages after removing them from a stream.",
+    },
+    Spec {
+        name: "xgroup",
+        arity: -2,
+        flags: "write",
+        keys: (2, 2, 1),
+        group: "stream",
+        summary: "A container for consumer groups commands.",
+    },
+    Spec {
+        name: "xlen",
+        arity: 2,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Returns the number of messages in a stream.",
+    },
+    Spec {
+        name: "xpending",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Returns the information and entries from a stream consumer group's pending entries list.",
+    },
+    Spec {
+        name: "xrange",
+        arity: -4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Returns the messages from a stream within a range of IDs.",
+    },
+    Spec {
+        name: "xread",
+        arity: -4,
+        flags: "readonly blocking movablekeys",
+        keys: (0, 0, 0),
+        group: "stream",
+        summary: "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.",
+    },
+    Spec {
+        name: "xreadgroup",
+        arity: -7,
+        flags: "write blocking movablekeys",
+        keys: (0, 0, 0),
+        group: "stream",
+        summary: "Returns new or historical messages from a stream for a consumer in a group. Blocks until a message is available otherwise.",
+    },
+    Spec {
+        name: "xtrim",
+        arity: -4,
+        flags: "write",
+        keys: (1, 1, 1),
+        group: "stream",
+        summary: "Deletes messages from the beginning of a stream.",
+    },
+    Spec {
+        name: "zadd",
+        arity: -4,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+    },
+    Spec {
+        name: "zcount",
+        arity: 4,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns the count of members in a sorted set that have scores within a range.",
+    },
+    Spec {
+        name: "zincrby",
+        arity: 4,
+        flags: "write denyoom fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Increments the score of a member in a sorted set.",
+    },
+    Spec {
+        name: "zpopmax",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+    },
+    Spec {
+        name: "zpopmin",
+        arity: -2,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+    },
+    Spec {
+        name: "zrandmember",
+        arity: -2,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns one or more random members from a sorted set.",
+    },
+    Spec {
+        name: "zrange",
+        arity: -4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns members in a sorted set within a range of indexes.",
+    },
+    Spec {
+        name: "zrangebylex",
+        arity: -4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns members in a sorted set within a lexicographical range.",
+    },
+    Spec {
+        name: "zrangebyscore",
+        arity: -4,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns members in a sorted set within a range of scores.",
+    },
+    Spec {
+        name: "zrank",
+        arity: -3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns the index of a member in a sorted set ordered by ascending scores.",
+    },
+    Spec {
+        name: "zrem",
+        arity: -3,
+        flags: "write fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed.",
+    },
+    Spec {
+        name: "zscan",
+        arity: -3,
+        flags: "readonly",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Iterates over members and scores of a sorted set.",
+    },
+    Spec {
+        name: "zscore",
+        arity: 3,
+        flags: "readonly fast",
+        keys: (1, 1, 1),
+        group: "sorted_set",
+        summary: "Returns the score of a member in a sorted set.",
+    },
+];
+
+impl Commands {
+    /// Create a new `Commands` command describing every command.
+    pub fn new() -> Commands {
+        Commands::default()
+    }
+
+    /// Create a new `Commands` command returning the number of commands.
+    pub fn count() -> Commands {
+        Commands {
+            subcommand: Subcommand::Count,
+        }
+    }
+
+    /// Create a new `Commands` command describing the commands `names`.
+    pub fn info(names: Vec<String>) -> Commands {
+        Commands {
+            subcommand: Subcommand::Info(names),
+        }
+    }
+
+    /// Create a new `Commands` command documenting the commands `names`.
+    pub fn docs(names: Vec<String>) -> Commands {
+        Commands {
+            subcommand: Subcommand::Docs(names),
+        }
+    }
+
+    /// Parse a `Commands` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `COMMAND` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Commands` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// COMMAND
+    /// COMMAND COUNT
+    /// COMMAND INFO [name ...]
+    /// COMMAND DOCS [name ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Commands> {
+        let subcommand = match parse.next_string() {
+            Ok(subcommand) => subcommand.to_uppercase(),
+            Err(ParseError::EndOfStream) => return Ok(Commands::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let subcommand = match &subcommand[..] {
+            "COUNT" => Subcommand::Count,
+            "INFO" => Subcommand::Info(parse_names(parse)?),
+            "DOCS" => Subcommand::Docs(parse_names(parse)?),
+            _ => return Err(format!("unknown `COMMAND` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Commands { subcommand })
+    }
+
+    /// Apply the `Commands` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::All => Frame::Array(COMMANDS.iter().map(Spec::to_frame).collect()),
+            Subcommand::Count => Frame::Integer(COMMANDS.len() as i64),
+            Subcommand::Info(names) if names.is_empty() => {
+                Frame::Array(COMMANDS.iter().map(Spec::to_frame).collect())
+            }
+            Subcommand::Info(names) => Frame::Array(
+                names
+                    .iter()
+                    .map(|name| find(name).map_or(Frame::Null, Spec::to_frame))
+                    .collect(),
+            ),
+            Subcommand::Docs(names) if names.is_empty() => {
+                Frame::Map(COMMANDS.iter().map(Spec::to_docs).collect())
+            }
+            Subcommand::Docs(names) => Frame::Map(
+                names
+                    .iter()
+                    .filter_map(|name| find(name))
+                    .map(Spec::to_docs)
+                    .collect(),
+            ),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Commands` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("command".as_bytes()));
+        let names = match self.subcommand {
+            Subcommand::All => vec![],
+            Subcommand::Count => {
+                frame.push_bulk(Bytes::from("count".as_bytes()));
+                vec![]
+            }
+            Subcommand::Info(names) => {
+                frame.push_bulk(Bytes::from("info".as_bytes()));
+                names
+            }
+            Subcommand::Docs(names) => {
+                frame.push_bulk(Bytes::from("docs".as_bytes()));
+                names
+            }
+        };
+        for name in names {
+            frame.push_bulk(Bytes::from(name.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl Spec {
+    /// Returns the description of the command, as returned by `COMMAND INFO`.
+    fn to_frame(&self) -> Frame {
+        let (first, last, step) = self.keys;
+        let flags = self
+            .flags
+            .split_whitespace()
+            .map(|flag| Frame::Simple(flag.to_string()))
+            .collect();
+
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from(self.name.as_bytes())),
+            Frame::Integer(self.arity),
+            Frame::Array(flags),
+            Frame::Integer(first),
+            Frame::Integer(last),
+            Frame::Integer(step),
+        ])
+    }
+
+    /// Returns the name of the command along with its documentation, as
+    /// returned by `COMMAND DOCS`.
+    fn to_docs(&self) -> (Frame, Frame) {
+        let bulk = |s: &'static str| Frame::Bulk(Bytes::from(s.as_bytes()));
+
+        let docs = Frame::Map(vec![
+            (bulk("summary"), bulk(self.summary)),
+            (bulk("group"), bulk(self.group)),
+        ]);
+        (bulk(self.name), docs)
+    }
+}
+
+/// Returns the description of the command `name`, if it exists.
+fn find(name: &str) -> Option<&'static Spec> {
+    let name = name.to_lowercase();
+    COMMANDS
+        .binary_search_by(|spec| spec.name.cmp(&name[..]))
+        .ok()
+        .map(|index| &COMMANDS[index])
+}
+
+/// Returns `true` if the command `name` exists.
+pub(super) fn exists(name: &str) -> bool {
+    find(name).is_some()
+}
+
+/// Returns `true` if the command `name` is flagged `denyoom`, as it may use
+/// more memory.
+pub(super) fn is_denyoom(name: &str) -> bool {
+    find(name).is_some_and(|spec| spec.flags.split(' ').any(|flag| flag == "denyoom"))
+}
+
+/// Parse the remaining names of the frame.
+fn parse_names(parse: &mut Parse) -> crate::Result<Vec<String>> {
+    let mut names = vec![];
+    loop {
+        match parse.next_string() {
+            Ok(name) => names.push(name),
+            Err(ParseError::EndOfStream) => return Ok(names),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
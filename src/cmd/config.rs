@@ -0,0 +1,148 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Reads or modifies the configuration of the server at runtime.
+///
+/// # Subcommands
+///
+/// * GET `pattern` -- Returns the parameters whose name matches the glob
+///   `pattern`, each followed by its value.
+/// * SET `parameter` `value` -- Set `parameter` to `value`.
+///
+/// The parameters are:
+///
+/// * `databases` -- The number of databases, which cannot be modified.
+/// * `latency-monitor-threshold` -- The duration, in milliseconds, from which
+///   the latency of an event is recorded by the latency monitor, `0` to
+///   disable it.
+/// * `maxmemory` -- The maximum number of bytes the data set may use, `0`
+///   for no limit. Once it is reached, keys are evicted before applying the
+///   commands which may use more memory.
+/// * `maxmemory-policy` -- Which keys are evicted: `noeviction`, rejecting the
+///   commands with an `OOM` error instead, `allkeys-lru`, `volatile-lru`,
+///   `allkeys-lfu`, `volatile-lfu`, `allkeys-random`, `volatile-random` or
+///   `volatile-ttl`. The `volatile` policies only evict keys with an
+///   expiration.
+/// * `maxmemory-samples` -- The number of keys sampled to pick the key to
+///   evict.
+/// * `notify-keyspace-events` -- The classes of the keyspace events published.
+/// * `proto-max-bulk-len` -- The maximum length of a string value.
+/// * `repl-backlog-size` -- The number of bytes of the replication stream
+///   kept for the replicas to resume from.
+/// * `slowlog-log-slower-than` -- The execution time, in microseconds, from
+///   which commands are recorded by the slow log, negative to disable it.
+/// * `slowlog-max-len` -- The number of commands kept by the slow log.
+/// * `timeout` -- The number of seconds after which idle clients are
+///   disconnected, `0` to never disconnect them.
+#[derive(Debug)]
+pub struct Config {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Get(String),
+    Set(String, String),
+}
+
+impl Config {
+    /// Create a new `Config` command which gets the parameters matching
+    /// `pattern`.
+    pub fn get(pattern: impl ToString) -> Config {
+        Config {
+            subcommand: Subcommand::Get(pattern.to_string()),
+        }
+    }
+
+    /// Create a new `Config` command which sets `parameter` to `value`.
+    pub fn set(parameter: impl ToString, value: impl ToString) -> Config {
+        Config {
+            subcommand: Subcommand::Set(parameter.to_string(), value.to_string()),
+        }
+    }
+
+    /// Parse a `Config` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CONFIG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Config` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// CONFIG GET pattern
+    /// CONFIG SET parameter value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Config> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "GET" => Subcommand::Get(parse.next_string()?),
+            "SET" => Subcommand::Set(parse.next_string()?, parse.next_string()?),
+            _ => return Err(format!("unknown `CONFIG` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Config { subcommand })
+    }
+
+    /// Apply the `Config` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Get(pattern) => Frame::Map(
+                db.config_get(&pattern)
+                    .into_iter()
+                    .map(|(name, value)| {
+                        (
+                            Frame::Bulk(Bytes::from(name.into_bytes())),
+                            Frame::Bulk(Bytes::from(value.into_bytes())),
+                        )
+                    })
+                    .collect(),
+            ),
+            Subcommand::Set(parameter, value) => match db.config_set(&parameter, &value) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Config` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("config".as_bytes()));
+        match self.subcommand {
+            Subcommand::Get(pattern) => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                frame.push_bulk(Bytes::from(pattern.into_bytes()));
+            }
+            Subcommand::Set(parameter, value) => {
+                frame.push_bulk(Bytes::from("set".as_bytes()));
+                frame.push_bulk(Bytes::from(parameter.into_bytes()));
+                frame.push_bulk(Bytes::from(value.into_bytes()));
+            }
+        }
+        frame
+    }
+}
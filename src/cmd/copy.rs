@@ -0,0 +1,136 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Copy the value stored at `source` to `destination`.
+///
+/// The expiration of `source`, if any, is copied as well. By default, nothing
+/// is copied when `destination` already exists, the `REPLACE` option
+/// overwrites it instead. The `DB` option names the database to copy to.
+///
+/// Integer reply: `1` if the value was copied, `0` otherwise.
+#[derive(Debug)]
+pub struct Copy {
+    /// Name of the key to copy
+    source: String,
+
+    /// Name of the key to copy to
+    destination: String,
+
+    /// Index of the database to copy to
+    db: Option<u64>,
+
+    /// Overwrite `destination` if it exists
+    replace: bool,
+}
+
+impl Copy {
+    /// Create a new `Copy` command which copies `source` to `destination`.
+    pub fn new(source: impl ToString, destination: impl ToString) -> Copy {
+        Copy {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            db: None,
+            replace: false,
+        }
+    }
+
+    /// Copy to the database at index `db`.
+    pub fn db(mut self, db: u64) -> Copy {
+        self.db = Some(db);
+        self
+    }
+
+    /// Overwrite `destination` if it exists when `replace` is set.
+    pub fn replace(mut self, replace: bool) -> Copy {
+        self.replace = replace;
+        self
+    }
+
+    /// Get the source key
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the destination key
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Parse a `Copy` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `COPY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Copy` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// COPY source destination [DB destination-db] [REPLACE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Copy> {
+        let mut copy = Copy::new(parse.next_string()?, parse.next_string()?);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "DB" => copy.db = Some(parse.next_int()?),
+                "REPLACE" => copy.replace = true,
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(copy)
+    }
+
+    /// Apply the `Copy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let index = self.db.map(|index| index as usize);
+        let response = match db.copy(&self.source, &self.destination, index, self.replace) {
+            Ok(copied) => Frame::Integer(copied as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Copy` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("copy".as_bytes()));
+        frame.push_bulk(Bytes::from(self.source.into_bytes()));
+        frame.push_bulk(Bytes::from(self.destination.into_bytes()));
+        if let Some(db) = self.db {
+            frame.push_bulk(Bytes::from("db".as_bytes()));
+            frame.push_bulk(Bytes::from(db.to_string().into_bytes()));
+        }
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Commands meant for testing the server.
+///
+/// # Subcommands
+///
+/// * SLEEP `seconds` -- Hold the databases for `seconds`, which may be
+///   fractional, no other command being applied meanwhile.
+/// * OBJECT `key` -- Returns the metadata of `key`: its type, the encoding
+///   Redis would use for its value, its size in bytes and its remaining time
+///   to live in milliseconds, `-1` if it has none.
+/// * SET-ACTIVE-EXPIRE 0|1 -- Disable or enable the background task purging
+///   the expired keys.
+/// * CHANGE-REPL-ID -- Give a new ID to the replication stream, so that the
+///   replicas fully synchronize again.
+#[derive(Debug)]
+pub struct Debug {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Sleep(f64),
+    Object(String),
+    SetActiveExpire(bool),
+    ChangeReplId,
+}
+
+impl Debug {
+    /// Create a new `Debug` command holding the databases for `seconds`.
+    pub fn sleep(seconds: f64) -> Debug {
+        Debug {
+            subcommand: Subcommand::Sleep(seconds),
+        }
+    }
+
+    /// Create a new `Debug` command returning the metadata of `key`.
+    pub fn object(key: impl ToString) -> Debug {
+        Debug {
+            subcommand: Subcommand::Object(key.to_string()),
+        }
+    }
+
+    /// Create a new `Debug` command enabling or disabling the purge of the
+    /// expired keys in the background.
+    pub fn set_active_expire(enabled: bool) -> Debug {
+        Debug {
+            subcommand: Subcommand::SetActiveExpire(enabled),
+        }
+    }
+
+    /// Create a new `Debug` command changing the ID of the replication
+    /// stream.
+    pub fn change_repl_id() -> Debug {
+        Debug {
+            subcommand: Subcommand::ChangeReplId,
+        }
+    }
+
+    /// Parse a `Debug` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `DEBUG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Debug` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// DEBUG SLEEP seconds
+    /// DEBUG OBJECT key
+    /// DEBUG SET-ACTIVE-EXPIRE 0|1
+    /// DEBUG CHANGE-REPL-ID
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Debug> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "SLEEP" => Subcommand::Sleep(parse.next_float()?),
+            "OBJECT" => Subcommand::Object(parse.next_string()?),
+            "SET-ACTIVE-EXPIRE" => Subcommand::SetActiveExpire(parse.next_int()? != 0),
+            "CHANGE-REPL-ID" => Subcommand::ChangeReplId,
+            _ => return Err(format!("unknown `DEBUG` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Debug { subcommand })
+    }
+
+    /// Apply the `Debug` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            // Negative, infinite and NaN durations, and the ones too long for
+            // a `Duration`, are rejected.
+            Subcommand::Sleep(seconds) => match Duration::try_from_secs_f64(seconds) {
+                Ok(duration) => {
+                    db.debug_sleep(duration);
+                    Frame::Simple("OK".to_string())
+                }
+                Err(_) => Frame::Error("ERR value is out of range".to_string()),
+            },
+            Subcommand::Object(key) => match db.debug_object(&key) {
+                Some(object) => Frame::Simple(object),
+                None => Frame::Error("ERR no such key".to_string()),
+            },
+            Subcommand::SetActiveExpire(enabled) => {
+                db.set_active_expire(enabled);
+                Frame::Simple("OK".to_string())
+            }
+            Subcommand::ChangeReplId => {
+                db.change_replication_id();
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Debug` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("debug".as_bytes()));
+        match self.subcommand {
+            Subcommand::Sleep(seconds) => {
+                frame.push_bulk(Bytes::from("sleep".as_bytes()));
+                frame.push_bulk(Bytes::from(seconds.to_string().into_bytes()));
+            }
+            Subcommand::Object(key) => {
+                frame.push_bulk(Bytes::from("object".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            Subcommand::SetActiveExpire(enabled) => {
+                frame.push_bulk(Bytes::from("set-active-expire".as_bytes()));
+                frame.push_int(enabled as i64);
+            }
+            Subcommand::ChangeReplId => frame.push_bulk(Bytes::from("change-repl-id".as_bytes())),
+        }
+        frame
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Serialize the value stored at `key`, to be stored again with `RESTORE`.
+///
+/// The serialized value holds the value only, not the expiration of the key.
+/// It ends with the version of its format and a checksum, so that `RESTORE`
+/// rejects the values serialized with another format, or corrupted.
+///
+/// Bulk string reply: the serialized value, or nil if `key` does not exist.
+#[derive(Debug)]
+pub struct Dump {
+    /// Name of the key to serialize
+    key: String,
+}
+
+impl Dump {
+    /// Create a new `Dump` command which serializes the value stored at
+    /// `key`.
+    pub fn new(key: impl ToString) -> Dump {
+        Dump {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Dump` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `DUMP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Dump` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DUMP key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Dump> {
+        let key = parse.next_string()?;
+
+        Ok(Dump { key })
+    }
+
+    /// Apply the `Dump` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.dump(&self.key) {
+            Some(payload) => Frame::Bulk(payload),
+            None => Frame::Null,
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Dump` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("dump".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
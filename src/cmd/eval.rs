@@ -0,0 +1,369 @@
+use crate::cmd::Command;
+use crate::db::{DbError, ScriptRun};
+use crate::script::{sha1_hex, Script};
+use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+
+use bytes::Bytes;
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+/// Run a Lua script.
+///
+/// The script accesses the keys it is given through the `KEYS` table and the
+/// other arguments through the `ARGV` table. It executes commands with
+/// `redis.call`, which raises an error if the command fails, or `redis.pcall`,
+/// which returns the error. The script is cached, so that it can later be run
+/// by `EVALSHA`.
+///
+/// Scripts are executed atomically: no command from another connection runs
+/// while the script runs.
+///
+/// The reply is the value returned by the script, converted to a reply.
+#[derive(Debug)]
+pub struct Eval {
+    /// Source of the script
+    script: Bytes,
+
+    /// Keys accessed by the script
+    keys: Vec<String>,
+
+    /// Other arguments of the script
+    args: Vec<Bytes>,
+}
+
+/// Run a script cached by `EVAL` or `SCRIPT LOAD`, identified by the SHA1
+/// digest of its source.
+///
+/// As with `EVAL`, the reply is the value returned by the script.
+#[derive(Debug)]
+pub struct EvalSha {
+    /// SHA1 digest of the script
+    sha: String,
+
+    /// Keys accessed by the script
+    keys: Vec<String>,
+
+    /// Other arguments of the script
+    args: Vec<Bytes>,
+}
+
+impl Eval {
+    /// Create a new `Eval` command which runs `script` with `keys` and `args`.
+    pub fn new(script: impl ToString, keys: Vec<String>, args: Vec<Bytes>) -> Eval {
+        Eval {
+            script: Bytes::from(script.to_string()),
+            keys,
+            args,
+        }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse an `Eval` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EVAL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Eval` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// EVAL script numkeys [key ...] [arg ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Eval> {
+        let script = parse.next_bytes()?;
+        let (keys, args) = parse_keys_and_args(parse)?;
+        Ok(Eval { script, keys, args })
+    }
+
+    /// Apply the `Eval` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let sha = sha1_hex(&self.script);
+
+        let script = match db.script(&sha) {
+            Some(script) => Some(script),
+            None => match Script::compile(&self.script) {
+                Ok(script) => {
+                    let script = Arc::new(script);
+                    db.cache_script(sha, script.clone());
+                    Some(script)
+                }
+                Err(err) => {
+                    let response = Frame::Error(format!("ERR Error compiling script: {}", err));
+                    debug!(?response);
+                    dst.write_frame(&response).await?;
+                    return Ok(());
+                }
+            },
+        };
+
+        let response = run(script, &self.keys, &self.args, db, dst, shutdown);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Eval` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("eval".as_bytes()));
+        frame.push_bulk(self.script);
+        push_keys_and_args(&mut frame, self.keys, self.args);
+        frame
+    }
+}
+
+impl EvalSha {
+    /// Create a new `EvalSha` command which runs the cached script whose SHA1
+    /// digest is `sha` with `keys` and `args`.
+    pub fn new(sha: impl ToString, keys: Vec<String>, args: Vec<Bytes>) -> EvalSha {
+        EvalSha {
+            sha: sha.to_string(),
+            keys,
+            args,
+        }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse an `EvalSha` instance from a received frame.
+    ///
+    /// The `EVALSHA` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// EVALSHA sha1 numkeys [key ...] [arg ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<EvalSha> {
+        let sha = parse.next_string()?.to_lowercase();
+        let (keys, args) = parse_keys_and_args(parse)?;
+        Ok(EvalSha { sha, keys, args })
+    }
+
+    /// Apply the `EvalSha` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let response = run(
+            db.script(&self.sha),
+            &self.keys,
+            &self.args,
+            db,
+            dst,
+            shutdown,
+        );
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `EvalSha` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("evalsha".as_bytes()));
+        frame.push_bulk(Bytes::from(self.sha.into_bytes()));
+        push_keys_and_args(&mut frame, self.keys, self.args);
+        frame
+    }
+}
+
+/// Parses the `numkeys` keys and the arguments following them.
+pub(super) fn parse_keys_and_args(parse: &mut Parse) -> crate::Result<(Vec<String>, Vec<Bytes>)> {
+    let numkeys = parse.next_int()?;
+
+    let mut keys = vec![];
+    for _ in 0..numkeys {
+        match parse.next_string() {
+            Ok(key) => keys.push(key),
+            Err(ParseError::EndOfStream) => {
+                return Err("ERR Number of keys can't be greater than number of args".into())
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    let mut args = vec![];
+    loop {
+        match parse.next_bytes() {
+            Ok(arg) => args.push(arg),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok((keys, args))
+}
+
+pub(super) fn push_keys_and_args(frame: &mut Frame, keys: Vec<String>, args: Vec<Bytes>) {
+    frame.push_bulk(Bytes::from(keys.len().to_string().into_bytes()));
+    for key in keys {
+        frame.push_bulk(Bytes::from(key.into_bytes()));
+    }
+    for arg in args {
+        frame.push_bulk(arg);
+    }
+}
+
+/// Runs `script`, returning its reply.
+fn run(
+    script: Option<Arc<Script>>,
+    keys: &[String],
+    args: &[Bytes],
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> Frame {
+    let script = match script {
+        Some(script) => script,
+        None => return Frame::Error("NOSCRIPT No matching script. Please use EVAL.".to_string()),
+    };
+
+    run_atomically(db, dst, shutdown, false, false, |run, execute| {
+        script.run(keys, args, run.interrupt(), execute)
+    })
+}
+
+/// Runs a script atomically with `run`, returning its reply. `run` is given
+/// the running script, which may be killed, and the function executing the
+/// commands of the script.
+///
+/// Unless the connection already executes a transaction, a transaction is
+/// started for the duration of the script. The commands of the script never
+/// wait, as blocking commands do not block within a transaction, so each one
+/// completes the first time it is polled.
+///
+/// If `read_only` is `true`, the script may not execute write commands. The
+/// script is a function called by `FCALL` if `function` is `true`, killed by
+/// `FUNCTION KILL` rather than `SCRIPT KILL`.
+pub(super) fn run_atomically(
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+    read_only: bool,
+    function: bool,
+    run: impl FnOnce(&ScriptRun, &mut dyn FnMut(Vec<Bytes>) -> Frame) -> Frame,
+) -> Frame {
+    let mut transaction = None;
+    let mut db = if db.in_transaction() {
+        db.clone()
+    } else {
+        transaction.insert(db.transaction()).db().clone()
+    };
+
+    // The other connections wait for the script to end, until it runs for
+    // longer than `lua-time-limit`.
+    let script = db.start_script(function);
+    let response = run(&script, &mut |args| {
+        execute(args, &mut db, dst, shutdown, read_only, &script)
+    });
+
+    // Dropping the handle before the transaction lets the transaction wake up
+    // the waiting connections.
+    drop(db);
+    drop(transaction);
+    drop(script);
+
+    response
+}
+
+/// Executes a command called by a script, returning its reply.
+fn execute(
+    args: Vec<Bytes>,
+    db: &mut Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+    read_only: bool,
+    script: &ScriptRun,
+) -> Frame {
+    let frame = Frame::Array(args.into_iter().map(Frame::Bulk).collect());
+
+    // The commands of the script are logged, rather than the script itself,
+    // so that replaying them has the same effect. As the commands sent by
+    // the clients, they are only known by their new name if renamed.
+    let renames = db.renames();
+    let mut logged = frame.clone();
+    renames.restore_name(&mut logged);
+
+    let cmd = match Command::from_renamed_frame(frame, &renames) {
+        Ok(cmd) => cmd,
+        Err(err) => return Frame::Error(err.to_string()),
+    };
+    // The commands of scripts are monitored as sent by `lua`.
+    db.feed_monitors("lua", &logged);
+    let logged = Some(logged).filter(|_| cmd.is_write());
+
+    if !cmd.allowed_in_script() {
+        return Frame::Error("ERR This Redis command is not allowed from script".to_string());
+    }
+    if let Err(err) = db.acl_check(&cmd) {
+        return Frame::Error(err);
+    }
+    if cmd.is_write() && db.is_replica() {
+        return Frame::Error(DbError::ReadOnly.to_string());
+    }
+    if read_only && cmd.is_write() {
+        return Frame::Error(
+            "ERR Write commands are not allowed from read-only scripts.".to_string(),
+        );
+    }
+    // Once the script writes, it may no longer be killed.
+    if cmd.is_write() {
+        if let Err(err) = script.record_write() {
+            return Frame::Error(err);
+        }
+    }
+
+    db.set_logged_command(logged);
+    let buffered = dst.replace_replies(Some(vec![]));
+    let res = cmd.apply_now(db, dst, shutdown);
+    db.set_logged_command(None);
+    let mut replies = dst.replace_replies(buffered).unwrap_or_default();
+
+    match res {
+        Some(Ok(())) => replies.pop().unwrap_or(Frame::Null),
+        Some(Err(err)) => Frame::Error(err.to_string()),
+        // Commands never wait within a script, as if their timeout had
+        // already elapsed.
+        None => Frame::Null,
+    }
+}
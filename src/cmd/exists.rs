@@ -0,0 +1,90 @@
+use crate::{Connection, Db, Frame};
+use crate::{Parse, ParseError};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns if the specified keys exist.
+///
+/// The same key mentioned multiple times is counted multiple times, so if
+/// `somekey` exists, `EXISTS somekey somekey` returns `2`.
+///
+/// Integer reply: The number of keys that exist among the ones specified.
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<String>,
+}
+
+impl Exists {
+    /// Create a new `Exists` command which checks whether `keys` exist.
+    pub fn new(keys: Vec<String>) -> Exists {
+        Exists { keys }
+    }
+
+    /// keys to check
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    /// Parse an `Exists` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXISTS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Exists` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a list of keys.
+    ///
+    /// ```text
+    /// EXISTS key [key...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Exists> {
+        let key = parse.next_string()?;
+        let mut keys = Vec::new();
+        keys.push(key);
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => {
+                    keys.push(s);
+                }
+                // Finish reading all the keys
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Exists { keys })
+    }
+
+    /// Apply the `Exists` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.exists(&self.keys) as i64);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Exists` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("exists".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
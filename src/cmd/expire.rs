@@ -0,0 +1,445 @@
+use crate::db::ExpireOption;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Set a timeout on `key`, in seconds. After the timeout has expired, the key
+/// is automatically deleted.
+///
+/// The timeout replaces any existing one, unless restricted by one of the
+/// `NX`, `XX`, `GT` or `LT` options. A timeout that is not positive deletes
+/// the key right away.
+///
+/// Integer reply: `1` if the timeout was set, `0` if the key does not exist
+/// or the timeout was not set because of the options.
+#[derive(Debug)]
+pub struct Expire {
+    /// Name of the key to set the timeout of
+    key: String,
+
+    /// Timeout, in seconds
+    seconds: i64,
+
+    /// Conditions to set the timeout under
+    options: Vec<ExpireOption>,
+}
+
+/// Set a timeout on `key`, in milliseconds.
+///
+/// Same as `Expire`, but with a millisecond resolution.
+#[derive(Debug)]
+pub struct PExpire {
+    /// Name of the key to set the timeout of
+    key: String,
+
+    /// Timeout, in milliseconds
+    milliseconds: i64,
+
+    /// Conditions to set the timeout under
+    options: Vec<ExpireOption>,
+}
+
+/// Set the expiration of `key` to an absolute Unix timestamp, in seconds.
+///
+/// Same as `Expire`, but a timestamp in the past deletes the key.
+#[derive(Debug)]
+pub struct ExpireAt {
+    /// Name of the key to set the expiration of
+    key: String,
+
+    /// Unix timestamp, in seconds
+    timestamp: i64,
+
+    /// Conditions to set the expiration under
+    options: Vec<ExpireOption>,
+}
+
+/// Set the expiration of `key` to an absolute Unix timestamp, in
+/// milliseconds.
+///
+/// Same as `ExpireAt`, but with a millisecond resolution.
+#[derive(Debug)]
+pub struct PExpireAt {
+    /// Name of the key to set the expiration of
+    key: String,
+
+    /// Unix timestamp, in milliseconds
+    timestamp: i64,
+
+    /// Conditions to set the expiration under
+    options: Vec<ExpireOption>,
+}
+
+impl Expire {
+    /// Create a new `Expire` command which sets a timeout of `seconds` on
+    /// `key`.
+    pub fn new(key: impl ToString, seconds: i64) -> Expire {
+        Expire {
+            key: key.to_string(),
+            seconds,
+            options: vec![],
+        }
+    }
+
+    /// Only set the timeout if `option` is satisfied.
+    pub fn option(mut self, option: ExpireOption) -> Expire {
+        self.options.push(option);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the timeout, in seconds
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Parse an `Expire` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXPIRE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Expire` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// EXPIRE key seconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Expire> {
+        let (key, seconds, options) = parse_expire(parse)?;
+        Ok(Expire {
+            key,
+            seconds,
+            options,
+        })
+    }
+
+    /// Apply the `Expire` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let when = self.seconds.checked_mul(1000).and_then(deadline_in);
+        apply_expire(db, dst, "expire", &self.key, when, &self.options).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Expire` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        expire_frame("expire", self.key, self.seconds, self.options)
+    }
+}
+
+impl PExpire {
+    /// Create a new `PExpire` command which sets a timeout of `milliseconds`
+    /// on `key`.
+    pub fn new(key: impl ToString, milliseconds: i64) -> PExpire {
+        PExpire {
+            key: key.to_string(),
+            milliseconds,
+            options: vec![],
+        }
+    }
+
+    /// Only set the timeout if `option` is satisfied.
+    pub fn option(mut self, option: ExpireOption) -> PExpire {
+        self.options.push(option);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the timeout, in milliseconds
+    pub fn milliseconds(&self) -> i64 {
+        self.milliseconds
+    }
+
+    /// Parse a `PExpire` instance from a received frame.
+    ///
+    /// The `PEXPIRE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// PEXPIRE key milliseconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PExpire> {
+        let (key, milliseconds, options) = parse_expire(parse)?;
+        Ok(PExpire {
+            key,
+            milliseconds,
+            options,
+        })
+    }
+
+    /// Apply the `PExpire` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let when = deadline_in(self.milliseconds);
+        apply_expire(db, dst, "pexpire", &self.key, when, &self.options).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PExpire` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        expire_frame("pexpire", self.key, self.milliseconds, self.options)
+    }
+}
+
+impl ExpireAt {
+    /// Create a new `ExpireAt` command which sets the expiration of `key` to
+    /// the Unix `timestamp`, in seconds.
+    pub fn new(key: impl ToString, timestamp: i64) -> ExpireAt {
+        ExpireAt {
+            key: key.to_string(),
+            timestamp,
+            options: vec![],
+        }
+    }
+
+    /// Only set the expiration if `option` is satisfied.
+    pub fn option(mut self, option: ExpireOption) -> ExpireAt {
+        self.options.push(option);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the Unix timestamp, in seconds
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Parse an `ExpireAt` instance from a received frame.
+    ///
+    /// The `EXPIREAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// EXPIREAT key unix-time-seconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ExpireAt> {
+        let (key, timestamp, options) = parse_expire(parse)?;
+        Ok(ExpireAt {
+            key,
+            timestamp,
+            options,
+        })
+    }
+
+    /// Apply the `ExpireAt` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let when = self.timestamp.checked_mul(1000).and_then(deadline_at);
+        apply_expire(db, dst, "expireat", &self.key, when, &self.options).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `ExpireAt` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        expire_frame("expireat", self.key, self.timestamp, self.options)
+    }
+}
+
+impl PExpireAt {
+    /// Create a new `PExpireAt` command which sets the expiration of `key` to
+    /// the Unix `timestamp`, in milliseconds.
+    pub fn new(key: impl ToString, timestamp: i64) -> PExpireAt {
+        PExpireAt {
+            key: key.to_string(),
+            timestamp,
+            options: vec![],
+        }
+    }
+
+    /// Only set the expiration if `option` is satisfied.
+    pub fn option(mut self, option: ExpireOption) -> PExpireAt {
+        self.options.push(option);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the Unix timestamp, in milliseconds
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Parse a `PExpireAt` instance from a received frame.
+    ///
+    /// The `PEXPIREAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// PEXPIREAT key unix-time-milliseconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PExpireAt> {
+        let (key, timestamp, options) = parse_expire(parse)?;
+        Ok(PExpireAt {
+            key,
+            timestamp,
+            options,
+        })
+    }
+
+    /// Apply the `PExpireAt` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let when = deadline_at(self.timestamp);
+        apply_expire(db, dst, "pexpireat", &self.key, when, &self.options).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PExpireAt` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        expire_frame("pexpireat", self.key, self.timestamp, self.options)
+    }
+}
+
+/// Parses the arguments shared by the expire commands: a key, a time and the
+/// options.
+fn parse_expire(parse: &mut Parse) -> crate::Result<(String, i64, Vec<ExpireOption>)> {
+    let key = parse.next_string()?;
+    let time = parse.next_signed_int()?;
+    let mut options = vec![];
+
+    loop {
+        let option = match parse.next_string() {
+            Ok(option) => option.to_uppercase(),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        options.push(match &option[..] {
+            "NX" => ExpireOption::Nx,
+            "XX" => ExpireOption::Xx,
+            "GT" => ExpireOption::Gt,
+            "LT" => ExpireOption::Lt,
+            _ => return Err(format!("ERR Unsupported option {}", option).into()),
+        });
+    }
+
+    Ok((key, time, options))
+}
+
+/// Sets the expiration of `key` to `when` and writes the outcome to `dst`.
+///
+/// A missing `when` means the requested time cannot be represented.
+async fn apply_expire(
+    db: &Db,
+    dst: &mut Connection,
+    name: &str,
+    key: &str,
+    when: Option<Instant>,
+    options: &[ExpireOption],
+) -> crate::Result<()> {
+    let has = |option| options.contains(&option);
+
+    let response = if has(ExpireOption::Nx) && options.iter().any(|o| *o != ExpireOption::Nx) {
+        Frame::Error("ERR NX and XX, GT or LT options at the same time are not compatible".into())
+    } else if has(ExpireOption::Gt) && has(ExpireOption::Lt) {
+        Frame::Error("ERR GT and LT options at the same time are not compatible".into())
+    } else {
+        match when {
+            Some(when) => Frame::Integer(db.expire(key, when, options) as i64),
+            None => Frame::Error(format!("ERR invalid expire time in '{}' command", name)),
+        }
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Returns the instant `ms` milliseconds from now. Times that are not
+/// positive are clamped to now, so that the key is deleted.
+pub(super) fn deadline_in(ms: i64) -> Option<Instant> {
+    let now = Instant::now();
+
+    match u64::try_from(ms) {
+        Ok(ms) => now.checked_add(Duration::from_millis(ms)),
+        Err(_) => Some(now),
+    }
+}
+
+/// Returns the instant corresponding to the Unix timestamp `ms`, in
+/// milliseconds.
+pub(super) fn deadline_at(ms: i64) -> Option<Instant> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as i64)
+        .unwrap_or(0);
+
+    deadline_in(ms.checked_sub(now)?)
+}
+
+/// Encodes an expire command named `name` into a frame.
+fn expire_frame(name: &'static str, key: String, time: i64, options: Vec<ExpireOption>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    frame.push_bulk(Bytes::from(time.to_string().into_bytes()));
+
+    for option in options {
+        let option = match option {
+            ExpireOption::Nx => "NX",
+            ExpireOption::Xx => "XX",
+            ExpireOption::Gt => "GT",
+            ExpireOption::Lt => "LT",
+        };
+        frame.push_bulk(Bytes::from(option.as_bytes()));
+    }
+
+    frame
+}
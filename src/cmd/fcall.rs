@@ -0,0 +1,231 @@
+use crate::cmd::eval::{parse_keys_and_args, push_keys_and_args, run_atomically};
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Call a function registered by a library loaded with `FUNCTION LOAD`.
+///
+/// The function is given the keys and the other arguments as two tables.
+/// As with `EVAL`, the function is executed atomically and the reply is the
+/// value it returns.
+#[derive(Debug)]
+pub struct FCall {
+    /// Name of the function
+    function: String,
+
+    /// Keys accessed by the function
+    keys: Vec<String>,
+
+    /// Other arguments of the function
+    args: Vec<Bytes>,
+}
+
+/// Call a function registered with the `no-writes` flag, which may not
+/// execute write commands.
+///
+/// Otherwise, `FCALL_RO` behaves like `FCALL`.
+#[derive(Debug)]
+pub struct FCallRo {
+    /// Name of the function
+    function: String,
+
+    /// Keys accessed by the function
+    keys: Vec<String>,
+
+    /// Other arguments of the function
+    args: Vec<Bytes>,
+}
+
+impl FCall {
+    /// Create a new `FCall` command which calls `function` with `keys` and
+    /// `args`.
+    pub fn new(function: impl ToString, keys: Vec<String>, args: Vec<Bytes>) -> FCall {
+        FCall {
+            function: function.to_string(),
+            keys,
+            args,
+        }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `FCall` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `FCALL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `FCall` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// FCALL function numkeys [key ...] [arg ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<FCall> {
+        let function = parse.next_string()?;
+        let (keys, args) = parse_keys_and_args(parse)?;
+        Ok(FCall {
+            function,
+            keys,
+            args,
+        })
+    }
+
+    /// Apply the `FCall` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let response = call(
+            &self.function,
+            &self.keys,
+            &self.args,
+            false,
+            db,
+            dst,
+            shutdown,
+        );
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FCall` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("fcall".as_bytes()));
+        frame.push_bulk(Bytes::from(self.function.into_bytes()));
+        push_keys_and_args(&mut frame, self.keys, self.args);
+        frame
+    }
+}
+
+impl FCallRo {
+    /// Create a new `FCallRo` command which calls the read-only `function`
+    /// with `keys` and `args`.
+    pub fn new(function: impl ToString, keys: Vec<String>, args: Vec<Bytes>) -> FCallRo {
+        FCallRo {
+            function: function.to_string(),
+            keys,
+            args,
+        }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `FCallRo` instance from a received frame.
+    ///
+    /// The `FCALL_RO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// FCALL_RO function numkeys [key ...] [arg ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<FCallRo> {
+        let function = parse.next_string()?;
+        let (keys, args) = parse_keys_and_args(parse)?;
+        Ok(FCallRo {
+            function,
+            keys,
+            args,
+        })
+    }
+
+    /// Apply the `FCallRo` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let response = call(
+            &self.function,
+            &self.keys,
+            &self.args,
+            true,
+            db,
+            dst,
+            shutdown,
+        );
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FCallRo` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("fcall_ro".as_bytes()));
+        frame.push_bulk(Bytes::from(self.function.into_bytes()));
+        push_keys_and_args(&mut frame, self.keys, self.args);
+        frame
+    }
+}
+
+/// Calls `function`, returning its reply.
+///
+/// With `read_only`, the function must be registered with the `no-writes`
+/// flag. Functions registered with the flag may not execute write commands,
+/// whether they are called by `FCALL` or `FCALL_RO`.
+fn call(
+    function: &str,
+    keys: &[String],
+    args: &[Bytes],
+    read_only: bool,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> Frame {
+    let library = match db.library_of(function) {
+        Some(library) => library,
+        None => return Frame::Error("ERR Function not found".to_string()),
+    };
+
+    let no_writes = library
+        .function(function)
+        .map(|function| function.flags.iter().any(|flag| flag == "no-writes"))
+        .unwrap_or(false);
+    if read_only && !no_writes {
+        return Frame::Error(
+            "ERR Can not execute a script with write flag using *_ro command.".to_string(),
+        );
+    }
+
+    run_atomically(db, dst, shutdown, no_writes, true, |run, execute| {
+        library.call(function, keys, args, run.interrupt(), execute)
+    })
+}
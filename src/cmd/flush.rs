@@ -0,0 +1,163 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Remove all the keys of the current database.
+///
+/// With the `ASYNC` option, the keys are freed in the background: the reply is
+/// sent as soon as the database is empty, without waiting for the memory to be
+/// released. `SYNC` frees the keys before replying, which is the default.
+#[derive(Debug, Default)]
+pub struct FlushDb {
+    /// Free the keys in the background
+    asynchronous: bool,
+}
+
+/// Remove all the keys of all the databases.
+///
+/// Accepts the same `ASYNC` and `SYNC` options as `FlushDb`.
+#[derive(Debug, Default)]
+pub struct FlushAll {
+    /// Free the keys in the background
+    asynchronous: bool,
+}
+
+impl FlushDb {
+    /// Create a new `FlushDb` command which removes all the keys of the current
+    /// database.
+    pub fn new() -> FlushDb {
+        FlushDb::default()
+    }
+
+    /// Free the keys in the background when `asynchronous` is set.
+    pub fn asynchronous(mut self, asynchronous: bool) -> FlushDb {
+        self.asynchronous = asynchronous;
+        self
+    }
+
+    /// Parse a `FlushDb` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `FLUSHDB` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `FlushDb` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// FLUSHDB [ASYNC | SYNC]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<FlushDb> {
+        let asynchronous = parse_mode(parse)?;
+
+        Ok(FlushDb { asynchronous })
+    }
+
+    /// Apply the `FlushDb` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.flush(self.asynchronous);
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FlushDb` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        flush_frame("flushdb", self.asynchronous)
+    }
+}
+
+impl FlushAll {
+    /// Create a new `FlushAll` command which removes all the keys of all the
+    /// databases.
+    pub fn new() -> FlushAll {
+        FlushAll::default()
+    }
+
+    /// Free the keys in the background when `asynchronous` is set.
+    pub fn asynchronous(mut self, asynchronous: bool) -> FlushAll {
+        self.asynchronous = asynchronous;
+        self
+    }
+
+    /// Parse a `FlushAll` instance from a received frame.
+    ///
+    /// The `FLUSHALL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// FLUSHALL [ASYNC | SYNC]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<FlushAll> {
+        let asynchronous = parse_mode(parse)?;
+
+        Ok(FlushAll { asynchronous })
+    }
+
+    /// Apply the `FlushAll` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.flush_all(self.asynchronous);
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FlushAll` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        flush_frame("flushall", self.asynchronous)
+    }
+}
+
+/// Parses the optional `ASYNC` or `SYNC` argument, returning `true` for
+/// `ASYNC`.
+fn parse_mode(parse: &mut Parse) -> crate::Result<bool> {
+    match parse.next_string() {
+        Ok(mode) => match &mode.to_uppercase()[..] {
+            "ASYNC" => Ok(true),
+            "SYNC" => Ok(false),
+            _ => Err("ERR syntax error".into()),
+        },
+        Err(ParseError::EndOfStream) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Encodes a flush command named `name` into a frame.
+fn flush_frame(name: &'static str, asynchronous: bool) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    if asynchronous {
+        frame.push_bulk(Bytes::from("async".as_bytes()));
+    }
+    frame
+}
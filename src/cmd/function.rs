@@ -0,0 +1,404 @@
+use crate::script::{sha1_hex, Library, ENGINE};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+/// Manages the libraries of functions called by `FCALL`.
+///
+/// # Subcommands
+///
+/// * LOAD [REPLACE] `code` -- Load a library. The first line of the code is
+///   `#!lua name=<library name>`, the code registers the functions of the
+///   library with `redis.register_function`. With `REPLACE`, the library
+///   replaces the library of the same name. The reply is the name of the
+///   library.
+/// * DELETE `library` -- Delete a library and its functions.
+/// * FLUSH [ASYNC|SYNC] -- Delete all the libraries.
+/// * LIST [LIBRARYNAME `pattern`] [WITHCODE] -- Returns information about the
+///   libraries and their functions, along with their code with `WITHCODE`.
+/// * DUMP -- Returns a serialized payload of the libraries.
+/// * RESTORE `payload` [FLUSH|APPEND|REPLACE] -- Restore the libraries of a
+///   payload returned by `DUMP`. By default, the libraries are appended, and
+///   fail to be restored if a library of the same name exists. `REPLACE`
+///   replaces the existing libraries of the same name, `FLUSH` deletes all
+///   the libraries first.
+/// * KILL -- Stop the function running for longer than `lua-time-limit`,
+///   unless it already executed write commands.
+#[derive(Debug)]
+pub struct Function {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Load { code: Bytes, replace: bool },
+    Delete(String),
+    Flush,
+    List { pattern: Option<String>, code: bool },
+    Dump,
+    Restore { payload: Bytes, policy: Policy },
+    Kill,
+}
+
+/// How `FUNCTION RESTORE` handles the existing libraries.
+#[derive(Debug)]
+enum Policy {
+    Append,
+    Replace,
+    Flush,
+}
+
+impl Function {
+    /// Create a new `Function` command which loads the library `code`.
+    pub fn load(code: impl ToString, replace: bool) -> Function {
+        Function {
+            subcommand: Subcommand::Load {
+                code: Bytes::from(code.to_string()),
+                replace,
+            },
+        }
+    }
+
+    /// Create a new `Function` command which deletes the library `name`.
+    pub fn delete(name: impl ToString) -> Function {
+        Function {
+            subcommand: Subcommand::Delete(name.to_string()),
+        }
+    }
+
+    /// Create a new `Function` command which deletes all the libraries.
+    pub fn flush() -> Function {
+        Function {
+            subcommand: Subcommand::Flush,
+        }
+    }
+
+    /// Create a new `Function` command which lists the libraries whose name
+    /// matches `pattern`, along with their code if `code` is `true`.
+    pub fn list(pattern: Option<String>, code: bool) -> Function {
+        Function {
+            subcommand: Subcommand::List { pattern, code },
+        }
+    }
+
+    /// Create a new `Function` command which serializes the libraries.
+    pub fn dump() -> Function {
+        Function {
+            subcommand: Subcommand::Dump,
+        }
+    }
+
+    /// Create a new `Function` command which restores the libraries
+    /// serialized in `payload`, appending them to the existing libraries.
+    pub fn restore(payload: Bytes) -> Function {
+        Function {
+            subcommand: Subcommand::Restore {
+                payload,
+                policy: Policy::Append,
+            },
+        }
+    }
+
+    /// Create a new `Function` command which stops the running function.
+    pub fn kill() -> Function {
+        Function {
+            subcommand: Subcommand::Kill,
+        }
+    }
+
+    /// Parse a `Function` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `FUNCTION` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Function` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// FUNCTION LOAD [REPLACE] code
+    /// FUNCTION DELETE library
+    /// FUNCTION FLUSH [ASYNC|SYNC]
+    /// FUNCTION LIST [LIBRARYNAME pattern] [WITHCODE]
+    /// FUNCTION DUMP
+    /// FUNCTION RESTORE payload [FLUSH|APPEND|REPLACE]
+    /// FUNCTION KILL
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Function> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "LOAD" => {
+                let mut code = parse.next_bytes()?;
+                let mut replace = false;
+                if &code[..].to_ascii_uppercase()[..] == b"REPLACE" {
+                    replace = true;
+                    code = parse.next_bytes()?;
+                }
+                Subcommand::Load { code, replace }
+            }
+            "DELETE" => Subcommand::Delete(parse.next_string()?),
+            "FLUSH" => {
+                // Libraries are freed right away, both modes behave the same.
+                match parse.next_string() {
+                    Ok(mode) if mode.eq_ignore_ascii_case("async") => {}
+                    Ok(mode) if mode.eq_ignore_ascii_case("sync") => {}
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(ParseError::EndOfStream) => {}
+                    Err(err) => return Err(err.into()),
+                }
+                Subcommand::Flush
+            }
+            "LIST" => {
+                let mut pattern = None;
+                let mut code = false;
+                loop {
+                    match parse.next_string() {
+                        Ok(s) if s.eq_ignore_ascii_case("libraryname") => {
+                            pattern = Some(parse.next_string()?);
+                        }
+                        Ok(s) if s.eq_ignore_ascii_case("withcode") => code = true,
+                        Ok(_) => return Err("ERR syntax error".into()),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                Subcommand::List { pattern, code }
+            }
+            "DUMP" => Subcommand::Dump,
+            "RESTORE" => {
+                let payload = parse.next_bytes()?;
+                let policy = match parse.next_string() {
+                    Ok(s) if s.eq_ignore_ascii_case("append") => Policy::Append,
+                    Ok(s) if s.eq_ignore_ascii_case("replace") => Policy::Replace,
+                    Ok(s) if s.eq_ignore_ascii_case("flush") => Policy::Flush,
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(ParseError::EndOfStream) => Policy::Append,
+                    Err(err) => return Err(err.into()),
+                };
+                Subcommand::Restore { payload, policy }
+            }
+            "KILL" => Subcommand::Kill,
+            _ => return Err(format!("unknown `FUNCTION` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Function { subcommand })
+    }
+
+    /// Apply the `Function` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Load { code, replace } => {
+                match Library::load(code).and_then(|library| {
+                    let name = library.name().to_string();
+                    db.load_libraries(vec![library], replace)?;
+                    Ok(name)
+                }) {
+                    Ok(name) => Frame::Bulk(Bytes::from(name.into_bytes())),
+                    Err(err) => Frame::Error(err),
+                }
+            }
+            Subcommand::Delete(name) => {
+                if db.delete_library(&name) {
+                    Frame::Simple("OK".to_string())
+                } else {
+                    Frame::Error("ERR Library not found".to_string())
+                }
+            }
+            Subcommand::Flush => {
+                db.flush_libraries();
+                Frame::Simple("OK".to_string())
+            }
+            Subcommand::List { pattern, code } => Frame::Array(
+                db.libraries(pattern.as_deref())
+                    .iter()
+                    .map(|library| library_frame(library, code))
+                    .collect(),
+            ),
+            Subcommand::Dump => Frame::Bulk(dump(&db.libraries(None))),
+            Subcommand::Restore { payload, policy } => {
+                match restore(&payload).and_then(|libraries| {
+                    if let Policy::Flush = policy {
+                        db.flush_libraries();
+                    }
+                    db.load_libraries(libraries, matches!(policy, Policy::Replace))
+                }) {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(err) => Frame::Error(err),
+                }
+            }
+            // As with `SCRIPT KILL`, a function running for longer than
+            // `lua-time-limit` is killed before the command is applied.
+            Subcommand::Kill => match db.kill_script(true) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Returns `true` if the subcommand may modify the loaded libraries.
+    pub(crate) fn is_write(&self) -> bool {
+        !matches!(
+            self.subcommand,
+            Subcommand::List { .. } | Subcommand::Dump | Subcommand::Kill
+        )
+    }
+
+    /// Returns `true` for `FUNCTION KILL`, which may be called while a
+    /// script runs for longer than `lua-time-limit`.
+    pub(crate) fn is_kill(&self) -> bool {
+        matches!(self.subcommand, Subcommand::Kill)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Function` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("function".as_bytes()));
+
+        match self.subcommand {
+            Subcommand::Load { code, replace } => {
+                frame.push_bulk(Bytes::from("load".as_bytes()));
+                if replace {
+                    frame.push_bulk(Bytes::from("replace".as_bytes()));
+                }
+                frame.push_bulk(code);
+            }
+            Subcommand::Delete(name) => {
+                frame.push_bulk(Bytes::from("delete".as_bytes()));
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+            Subcommand::Flush => frame.push_bulk(Bytes::from("flush".as_bytes())),
+            Subcommand::List { pattern, code } => {
+                frame.push_bulk(Bytes::from("list".as_bytes()));
+                if let Some(pattern) = pattern {
+                    frame.push_bulk(Bytes::from("libraryname".as_bytes()));
+                    frame.push_bulk(Bytes::from(pattern.into_bytes()));
+                }
+                if code {
+                    frame.push_bulk(Bytes::from("withcode".as_bytes()));
+                }
+            }
+            Subcommand::Dump => frame.push_bulk(Bytes::from("dump".as_bytes())),
+            Subcommand::Restore { payload, policy } => {
+                frame.push_bulk(Bytes::from("restore".as_bytes()));
+                frame.push_bulk(payload);
+                match policy {
+                    Policy::Append => {}
+                    Policy::Replace => frame.push_bulk(Bytes::from("replace".as_bytes())),
+                    Policy::Flush => frame.push_bulk(Bytes::from("flush".as_bytes())),
+                }
+            }
+            Subcommand::Kill => frame.push_bulk(Bytes::from("kill".as_bytes())),
+        }
+
+        frame
+    }
+}
+
+/// Describes `library` and its functions, as replied by `FUNCTION LIST`.
+fn library_frame(library: &Library, code: bool) -> Frame {
+    let bulk = |s: &str| Frame::Bulk(Bytes::from(s.to_string().into_bytes()));
+
+    let functions = library
+        .functions()
+        .iter()
+        .map(|function| {
+            Frame::Array(vec![
+                bulk("name"),
+                bulk(&function.name),
+                bulk("description"),
+                function
+                    .description
+                    .clone()
+                    .map(Frame::Bulk)
+                    .unwrap_or(Frame::Null),
+                bulk("flags"),
+                Frame::Array(
+                    function
+                        .flags
+                        .iter()
+                        .map(|flag| Frame::Simple(flag.clone()))
+                        .collect(),
+                ),
+            ])
+        })
+        .collect();
+
+    let mut frame = vec![
+        bulk("library_name"),
+        bulk(library.name()),
+        bulk("engine"),
+        bulk(ENGINE),
+        bulk("functions"),
+        Frame::Array(functions),
+    ];
+    if code {
+        frame.push(bulk("library_code"));
+        frame.push(Frame::Bulk(library.code().clone()));
+    }
+    Frame::Array(frame)
+}
+
+/// Length of the checksum ending a payload, the hexadecimal SHA1 digest of
+/// the preceding bytes.
+const CHECKSUM_LEN: usize = 40;
+
+/// Serializes the code of `libraries`, each one preceded by its length as a
+/// big-endian 32-bit integer, followed by the checksum.
+fn dump(libraries: &[Arc<Library>]) -> Bytes {
+    let mut payload = BytesMut::new();
+    for library in libraries {
+        payload.put_u32(library.code().len() as u32);
+        payload.put_slice(library.code());
+    }
+
+    let checksum = sha1_hex(&payload);
+    payload.put_slice(checksum.as_bytes());
+    payload.freeze()
+}
+
+/// Loads the libraries serialized in `payload` by `dump`.
+fn restore(payload: &[u8]) -> Result<Vec<Library>, String> {
+    let invalid = || "ERR payload version or checksum are wrong".to_string();
+
+    if payload.len() < CHECKSUM_LEN {
+        return Err(invalid());
+    }
+    let (mut data, checksum) = payload.split_at(payload.len() - CHECKSUM_LEN);
+    if sha1_hex(data).as_bytes() != checksum {
+        return Err(invalid());
+    }
+
+    let mut libraries = vec![];
+    while !data.is_empty() {
+        if data.len() < 4 {
+            return Err(invalid());
+        }
+        let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let code = data.get(4..4 + len).ok_or_else(invalid)?;
+        libraries.push(Library::load(Bytes::copy_from_slice(code))?);
+        data = &data[4 + len..];
+    }
+    Ok(libraries)
+}
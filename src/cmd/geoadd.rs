@@ -0,0 +1,181 @@
+use crate::db::{self, ZAddOption};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Adds the specified members, with their positions, to the geospatial index
+/// stored at `key`.
+///
+/// A geospatial index is a sorted set where the score of each member encodes
+/// its position, a new one is created if `key` does not exist. Positions are
+/// given as a longitude followed by a latitude. Latitudes near the poles
+/// cannot be indexed.
+///
+/// Integer reply: The number of members added to the index.
+///
+/// # Options
+///
+/// * NX -- Only add new members, never update existing ones.
+/// * XX -- Only update existing members, never add new ones.
+/// * CH -- Reply with the number of members added or whose position changed.
+#[derive(Debug)]
+pub struct GeoAdd {
+    /// Name of the key holding the geospatial index
+    key: String,
+
+    /// Longitude / latitude / member triples to add
+    members: Vec<(f64, f64, Bytes)>,
+
+    /// Options, validated to be compatible with each other when applied
+    options: Vec<ZAddOption>,
+}
+
+impl GeoAdd {
+    /// Create a new `GeoAdd` command which adds `members` to the geospatial
+    /// index stored at `key`.
+    pub fn new(key: impl ToString, members: Vec<(f64, f64, Bytes)>) -> GeoAdd {
+        GeoAdd {
+            key: key.to_string(),
+            members,
+            options: vec![],
+        }
+    }
+
+    /// Add `option` to the command. Only `Nx`, `Xx` and `Ch` are supported.
+    pub fn option(mut self, option: ZAddOption) -> GeoAdd {
+        self.options.push(option);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the longitude / latitude / member triples
+    pub fn members(&self) -> &[(f64, f64, Bytes)] {
+        &self.members
+    }
+
+    /// Parse a `GeoAdd` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GEOADD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GeoAdd` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 5 entries.
+    ///
+    /// ```text
+    /// GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoAdd> {
+        let key = parse.next_string()?;
+        let mut options = vec![];
+
+        // As with `ZADD`, options come before the first longitude.
+        let lon = loop {
+            let arg = parse.next_string()?;
+
+            options.push(match &arg.to_uppercase()[..] {
+                "NX" => ZAddOption::Nx,
+                "XX" => ZAddOption::Xx,
+                "CH" => ZAddOption::Ch,
+                _ => break arg,
+            });
+        };
+
+        let lon = match lon.parse::<f64>() {
+            Ok(lon) if !lon.is_nan() => lon,
+            _ => return Err("protocol error; invalid float".into()),
+        };
+
+        // At least one longitude / latitude / member triple is required.
+        let mut members = vec![(lon, parse.next_float()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_float() {
+                Ok(lon) => members.push((lon, parse.next_float()?, parse.next_bytes()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(GeoAdd {
+            key,
+            members,
+            options,
+        })
+    }
+
+    /// Apply the `GeoAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let invalid = self
+            .members
+            .iter()
+            .find(|(lon, lat, _)| !db::geo_is_valid(*lon, *lat));
+
+        let response = if let Some((lon, lat, _)) = invalid {
+            Frame::Error(format!(
+                "ERR invalid longitude,latitude pair {:.6},{:.6}",
+                lon, lat
+            ))
+        } else if self.options.contains(&ZAddOption::Nx) && self.options.contains(&ZAddOption::Xx) {
+            Frame::Error("ERR XX and NX options at the same time are not compatible".into())
+        } else {
+            let members = self
+                .members
+                .into_iter()
+                .map(|(lon, lat, member)| (db::geo_encode(lon, lat), member))
+                .collect();
+
+            match db.zadd(self.key, members, &self.options) {
+                Ok(added) => Frame::Integer(added as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GeoAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("geoadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for option in self.options {
+            let option = match option {
+                ZAddOption::Nx => "NX",
+                ZAddOption::Xx => "XX",
+                ZAddOption::Gt => "GT",
+                ZAddOption::Lt => "LT",
+                ZAddOption::Ch => "CH",
+            };
+            frame.push_bulk(Bytes::from(option.as_bytes()));
+        }
+        for (lon, lat, member) in self.members {
+            frame.push_bulk(Bytes::from(lon.to_string().into_bytes()));
+            frame.push_bulk(Bytes::from(lat.to_string().into_bytes()));
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
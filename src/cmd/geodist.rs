@@ -0,0 +1,143 @@
+use crate::cmd::GeoUnit;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the distance between two members of the geospatial index stored at
+/// `key`.
+///
+/// The distance is computed assuming the Earth is a perfect sphere, which
+/// leads to an error of up to 0.5%.
+///
+/// Bulk string reply: the distance in the requested unit, meters by default,
+/// or `Null` if one of the members is not in the index.
+#[derive(Debug)]
+pub struct GeoDist {
+    /// Name of the key holding the geospatial index
+    key: String,
+
+    /// Member to measure the distance from
+    from: Bytes,
+
+    /// Member to measure the distance to
+    to: Bytes,
+
+    /// Unit the distance is expressed in
+    unit: GeoUnit,
+}
+
+impl GeoDist {
+    /// Create a new `GeoDist` command which measures the distance between
+    /// `from` and `to` in the geospatial index stored at `key`.
+    pub fn new(key: impl ToString, from: Bytes, to: Bytes, unit: GeoUnit) -> GeoDist {
+        GeoDist {
+            key: key.to_string(),
+            from,
+            to,
+            unit,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `GeoDist` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GEODIST` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GeoDist` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// GEODIST key member1 member2 [M | KM | FT | MI]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoDist> {
+        let key = parse.next_string()?;
+        let from = parse.next_bytes()?;
+        let to = parse.next_bytes()?;
+
+        let unit = match parse.next_string() {
+            Ok(unit) => parse_unit(&unit)?,
+            Err(ParseError::EndOfStream) => GeoUnit::M,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(GeoDist {
+            key,
+            from,
+            to,
+            unit,
+        })
+    }
+
+    /// Apply the `GeoDist` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.geodist(&self.key, &self.from, &self.to) {
+            Ok(Some(distance)) => distance_frame(distance, self.unit),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GeoDist` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("geodist".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.from);
+        frame.push_bulk(self.to);
+        frame.push_bulk(Bytes::from(unit_name(self.unit).as_bytes()));
+        frame
+    }
+}
+
+/// Parse the unit argument of a geospatial command.
+pub(crate) fn parse_unit(unit: &str) -> crate::Result<GeoUnit> {
+    match &unit.to_uppercase()[..] {
+        "M" => Ok(GeoUnit::M),
+        "KM" => Ok(GeoUnit::Km),
+        "FT" => Ok(GeoUnit::Ft),
+        "MI" => Ok(GeoUnit::Mi),
+        _ => Err("ERR unsupported unit provided. please use M, KM, FT, MI".into()),
+    }
+}
+
+/// Returns the name of `unit`, as accepted by `parse_unit`.
+pub(crate) fn unit_name(unit: GeoUnit) -> &'static str {
+    match unit {
+        GeoUnit::M => "m",
+        GeoUnit::Km => "km",
+        GeoUnit::Ft => "ft",
+        GeoUnit::Mi => "mi",
+    }
+}
+
+/// Encode `distance`, in meters, as a bulk string in `unit` with the four
+/// decimals Redis replies with.
+pub(crate) fn distance_frame(distance: f64, unit: GeoUnit) -> Frame {
+    Frame::Bulk(Bytes::from(format!("{:.4}", distance / unit.meters())))
+}
@@ -0,0 +1,124 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the positions of the specified members of the geospatial index
+/// stored at `key`.
+///
+/// Positions are decoded from the geohash the members are indexed by, so they
+/// can slightly differ from the positions the members were added with.
+///
+/// Array reply: a `[longitude, latitude]` array for each member, or `Null` if
+/// the member is not in the index.
+#[derive(Debug)]
+pub struct GeoPos {
+    /// Name of the key holding the geospatial index
+    key: String,
+
+    /// Members to get the position of
+    members: Vec<Bytes>,
+}
+
+impl GeoPos {
+    /// Create a new `GeoPos` command which gets the positions of `members` in
+    /// the geospatial index stored at `key`.
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> GeoPos {
+        GeoPos {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the members
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse a `GeoPos` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GEOPOS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GeoPos` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// GEOPOS key [member [member ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoPos> {
+        let key = parse.next_string()?;
+        let mut members = vec![];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(GeoPos { key, members })
+    }
+
+    /// Apply the `GeoPos` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.geopos(&self.key, &self.members) {
+            Ok(positions) => Frame::Array(
+                positions
+                    .into_iter()
+                    .map(|position| match position {
+                        Some(position) => position_frame(position),
+                        None => Frame::Null,
+                    })
+                    .collect(),
+            ),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GeoPos` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("geopos".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for member in self.members {
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
+
+/// Encode a `(longitude, latitude)` position as a two bulk strings array, as
+/// replied by the geospatial commands.
+pub(crate) fn position_frame((lon, lat): (f64, f64)) -> Frame {
+    Frame::Array(vec![
+        Frame::Bulk(Bytes::from(lon.to_string())),
+        Frame::Bulk(Bytes::from(lat.to_string())),
+    ])
+}
@@ -0,0 +1,328 @@
+use crate::cmd::geodist::{distance_frame, parse_unit, unit_name};
+use crate::cmd::geopos::position_frame;
+use crate::cmd::{GeoOrigin, GeoShape};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::cmp::Ordering;
+use tracing::{debug, instrument};
+
+/// Returns the members of the geospatial index stored at `key` which are
+/// within a given shape around an origin.
+///
+/// The origin is either a member of the index or a longitude and a latitude.
+/// The shape is either a circle or a box, centered on the origin.
+///
+/// # Options
+///
+/// * ASC -- Sort the members from the nearest to the farthest.
+/// * DESC -- Sort the members from the farthest to the nearest.
+/// * COUNT `count` [ANY] -- Return at most `count` members. Without `ANY`, the
+///   nearest members are returned. With `ANY`, the first members found are
+///   returned, which is cheaper.
+/// * WITHCOORD -- Also return the position of each member.
+/// * WITHDIST -- Also return the distance of each member to the origin, in the
+///   unit of the shape.
+///
+/// Array reply: the members, or a `[member, distance, [longitude, latitude]]`
+/// array for each member when `WITHDIST` or `WITHCOORD` is set, the optional
+/// fields being omitted.
+#[derive(Debug)]
+pub struct GeoSearch {
+    /// Name of the key holding the geospatial index
+    key: String,
+
+    /// Point the search is centered on
+    origin: GeoOrigin,
+
+    /// Area searched around the origin, validated to not have a negative
+    /// size when applied
+    shape: GeoShape,
+
+    /// Sort the members by distance, in descending order when set
+    desc: Option<bool>,
+
+    /// Maximum number of members to return, validated to be positive when
+    /// applied
+    count: Option<i64>,
+
+    /// Return any `count` members instead of the nearest ones, validated to
+    /// only be set along with `count` when applied
+    any: bool,
+
+    /// Return the position of each member
+    withcoord: bool,
+
+    /// Return the distance of each member to the origin
+    withdist: bool,
+}
+
+impl GeoSearch {
+    /// Create a new `GeoSearch` command which searches the members of the
+    /// geospatial index stored at `key` within `shape` around `origin`.
+    pub fn new(key: impl ToString, origin: GeoOrigin, shape: GeoShape) -> GeoSearch {
+        GeoSearch {
+            key: key.to_string(),
+            origin,
+            shape,
+            desc: None,
+            count: None,
+            any: false,
+            withcoord: false,
+            withdist: false,
+        }
+    }
+
+    /// Sort the members by distance, from the farthest to the nearest when
+    /// `desc` is set.
+    pub fn sort(mut self, desc: bool) -> GeoSearch {
+        self.desc = Some(desc);
+        self
+    }
+
+    /// Return at most `count` members, any of them when `any` is set.
+    pub fn count(mut self, count: u64, any: bool) -> GeoSearch {
+        self.count = Some(count as i64);
+        self.any = any;
+        self
+    }
+
+    /// Return the position of each member when `withcoord` is set.
+    pub fn withcoord(mut self, withcoord: bool) -> GeoSearch {
+        self.withcoord = withcoord;
+        self
+    }
+
+    /// Return the distance of each member to the origin when `withdist` is
+    /// set.
+    pub fn withdist(mut self, withdist: bool) -> GeoSearch {
+        self.withdist = withdist;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `GeoSearch` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GEOSEARCH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GeoSearch` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least six entries.
+    ///
+    /// ```text
+    /// GEOSEARCH key <FROMMEMBER member | FROMLONLAT longitude latitude>
+    ///   <BYRADIUS radius <M | KM | FT | MI> | BYBOX width height <M | KM | FT | MI>>
+    ///   [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoSearch> {
+        let key = parse.next_string()?;
+        let mut origin = None;
+        let mut shape = None;
+        let mut desc = None;
+        let mut count = None;
+        let mut any = false;
+        let mut withcoord = false;
+        let mut withdist = false;
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "FROMMEMBER" | "FROMLONLAT" if origin.is_some() => return Err(
+                    "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"
+                        .into(),
+                ),
+                "FROMMEMBER" => origin = Some(GeoOrigin::Member(parse.next_bytes()?)),
+                "FROMLONLAT" => {
+                    origin = Some(GeoOrigin::LonLat(parse.next_float()?, parse.next_float()?))
+                }
+                "BYRADIUS" | "BYBOX" if shape.is_some() => {
+                    return Err(
+                        "ERR exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH"
+                            .into(),
+                    )
+                }
+                "BYRADIUS" => {
+                    let radius = parse.next_float()?;
+                    shape = Some(GeoShape::Radius(radius, parse_unit(&parse.next_string()?)?));
+                }
+                "BYBOX" => {
+                    let width = parse.next_float()?;
+                    let height = parse.next_float()?;
+                    shape = Some(GeoShape::Box(
+                        width,
+                        height,
+                        parse_unit(&parse.next_string()?)?,
+                    ));
+                }
+                "ASC" => desc = Some(false),
+                "DESC" => desc = Some(true),
+                "COUNT" => count = Some(parse.next_signed_int()?),
+                "ANY" => any = true,
+                "WITHCOORD" => withcoord = true,
+                "WITHDIST" => withdist = true,
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        let origin = origin
+            .ok_or("ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH")?;
+        let shape =
+            shape.ok_or("ERR exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH")?;
+
+        Ok(GeoSearch {
+            key,
+            origin,
+            shape,
+            desc,
+            count,
+            any,
+            withcoord,
+            withdist,
+        })
+    }
+
+    /// Apply the `GeoSearch` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.shape {
+            GeoShape::Radius(radius, _) if radius < 0.0 => {
+                Frame::Error("ERR radius cannot be negative".into())
+            }
+            GeoShape::Box(width, height, _) if width < 0.0 || height < 0.0 => {
+                Frame::Error("ERR height or width cannot be negative".into())
+            }
+            _ if matches!(self.count, Some(count) if count <= 0) => {
+                Frame::Error("ERR COUNT must be > 0".into())
+            }
+            _ if self.any && self.count.is_none() => {
+                Frame::Error("ERR the ANY argument requires COUNT argument".into())
+            }
+            _ => match db.geosearch(&self.key, &self.origin, self.shape) {
+                Ok(mut members) => {
+                    // Without `ANY`, `COUNT` returns the nearest members.
+                    let desc = match self.desc {
+                        Some(desc) => Some(desc),
+                        None if self.count.is_some() && !self.any => Some(false),
+                        None => None,
+                    };
+
+                    if let Some(desc) = desc {
+                        members.sort_by(|(_, a, _), (_, b, _)| {
+                            let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+                            if desc {
+                                ordering.reverse()
+                            } else {
+                                ordering
+                            }
+                        });
+                    }
+
+                    if let Some(count) = self.count {
+                        members.truncate(count as usize);
+                    }
+
+                    let unit = self.shape.unit();
+
+                    Frame::Array(
+                        members
+                            .into_iter()
+                            .map(|(member, distance, position)| {
+                                if !self.withdist && !self.withcoord {
+                                    return Frame::Bulk(member);
+                                }
+
+                                let mut item = vec![Frame::Bulk(member)];
+                                if self.withdist {
+                                    item.push(distance_frame(distance, unit));
+                                }
+                                if self.withcoord {
+                                    item.push(position_frame(position));
+                                }
+                                Frame::Array(item)
+                            })
+                            .collect(),
+                    )
+                }
+                Err(err) => Frame::Error(err.to_string()),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GeoSearch` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("geosearch".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        match self.origin {
+            GeoOrigin::Member(member) => {
+                frame.push_bulk(Bytes::from("frommember".as_bytes()));
+                frame.push_bulk(member);
+            }
+            GeoOrigin::LonLat(lon, lat) => {
+                frame.push_bulk(Bytes::from("fromlonlat".as_bytes()));
+                frame.push_bulk(Bytes::from(lon.to_string().into_bytes()));
+                frame.push_bulk(Bytes::from(lat.to_string().into_bytes()));
+            }
+        }
+        match self.shape {
+            GeoShape::Radius(radius, unit) => {
+                frame.push_bulk(Bytes::from("byradius".as_bytes()));
+                frame.push_bulk(Bytes::from(radius.to_string().into_bytes()));
+                frame.push_bulk(Bytes::from(unit_name(unit).as_bytes()));
+            }
+            GeoShape::Box(width, height, unit) => {
+                frame.push_bulk(Bytes::from("bybox".as_bytes()));
+                frame.push_bulk(Bytes::from(width.to_string().into_bytes()));
+                frame.push_bulk(Bytes::from(height.to_string().into_bytes()));
+                frame.push_bulk(Bytes::from(unit_name(unit).as_bytes()));
+            }
+        }
+        match self.desc {
+            Some(true) => frame.push_bulk(Bytes::from("desc".as_bytes())),
+            Some(false) => frame.push_bulk(Bytes::from("asc".as_bytes())),
+            None => {}
+        }
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+            if self.any {
+                frame.push_bulk(Bytes::from("any".as_bytes()));
+            }
+        }
+        if self.withcoord {
+            frame.push_bulk(Bytes::from("withcoord".as_bytes()));
+        }
+        if self.withdist {
+            frame.push_bulk(Bytes::from("withdist".as_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the bit value at `offset` in the string value stored at `key`.
+///
+/// When `offset` is beyond the string length, the string is assumed to be
+/// padded with zero bits. If `key` does not exist, it is interpreted as an
+/// empty string, so `0` is returned.
+#[derive(Debug)]
+pub struct GetBit {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Offset of the bit, counting from the most significant bit of the first
+    /// byte
+    offset: u64,
+}
+
+impl GetBit {
+    /// Create a new `GetBit` command which fetches the bit at `offset` in the
+    /// string stored at `key`.
+    pub fn new(key: impl ToString, offset: u64) -> GetBit {
+        GetBit {
+            key: key.to_string(),
+            offset,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Parse a `GetBit` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETBIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetBit` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// GETBIT key offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetBit> {
+        let key = parse.next_string()?;
+        let offset = parse.next_int()?;
+
+        Ok(GetBit { key, offset })
+    }
+
+    /// Apply the `GetBit` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.offset >= super::setbit::MAX_OFFSET {
+            Frame::Error("ERR bit offset is not an integer or out of range".into())
+        } else {
+            match db.getbit(&self.key, self.offset as usize) {
+                Ok(bit) => Frame::Integer(bit as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetBit` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getbit".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.offset.to_string().into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Get the value of `key` and delete the key.
+///
+/// Same as `GET`, except that the key is removed, atomically, when it holds a
+/// string. An error is returned, and the key left untouched, when it holds a
+/// non-string value.
+#[derive(Debug)]
+pub struct GetDel {
+    /// Name of the key to get and delete
+    key: String,
+}
+
+impl GetDel {
+    /// Create a new `GetDel` command which fetches and deletes `key`.
+    pub fn new(key: impl ToString) -> GetDel {
+        GetDel {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `GetDel` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETDEL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetDel` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// GETDEL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetDel> {
+        let key = parse.next_string()?;
+
+        Ok(GetDel { key })
+    }
+
+    /// Apply the `GetDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.getdel(&self.key) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetDel` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getdel".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
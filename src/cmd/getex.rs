@@ -0,0 +1,175 @@
+use crate::cmd::set::expires_at;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument};
+
+/// Get the value of `key` and optionally update its expiration.
+///
+/// # Options
+///
+/// At most one of the following options is accepted:
+///
+/// * EX `seconds` -- Set the specified expire time, in seconds.
+/// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp` -- Set the specified Unix time at which the key will
+///   expire, in seconds.
+/// * PXAT `timestamp` -- Set the specified Unix time at which the key will
+///   expire, in milliseconds.
+/// * PERSIST -- Remove the time to live associated with the key.
+///
+/// Without options, `GETEX` behaves like `GET`.
+#[derive(Debug)]
+pub struct GetEx {
+    /// Name of the key to get
+    key: String,
+
+    /// When to expire the key
+    expire: Option<Duration>,
+
+    /// Unix time at which to expire the key
+    expire_at: Option<SystemTime>,
+
+    /// Remove the time to live of the key
+    persist: bool,
+}
+
+impl GetEx {
+    /// Create a new `GetEx` command which fetches `key`, leaving its
+    /// expiration untouched.
+    pub fn new(key: impl ToString) -> GetEx {
+        GetEx {
+            key: key.to_string(),
+            expire: None,
+            expire_at: None,
+            persist: false,
+        }
+    }
+
+    /// Expire the key after `expire`.
+    pub fn expire(mut self, expire: Duration) -> GetEx {
+        self.expire = Some(expire);
+        self
+    }
+
+    /// Expire the key at the Unix time `when`.
+    pub fn expire_at(mut self, when: SystemTime) -> GetEx {
+        self.expire_at = Some(when);
+        self
+    }
+
+    /// Remove the time to live of the key when `persist` is set.
+    pub fn persist(mut self, persist: bool) -> GetEx {
+        self.persist = persist;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `GetEx` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETEX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetEx` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// GETEX key [EX seconds | PX milliseconds | EXAT timestamp | PXAT timestamp | PERSIST]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetEx> {
+        let mut getex = GetEx::new(parse.next_string()?);
+
+        let option = match parse.next_string() {
+            Ok(option) => option.to_uppercase(),
+            Err(ParseError::EndOfStream) => return Ok(getex),
+            Err(err) => return Err(err.into()),
+        };
+
+        match &option[..] {
+            "EX" => getex.expire = Some(Duration::from_secs(parse.next_int()?)),
+            "PX" => getex.expire = Some(Duration::from_millis(parse.next_int()?)),
+            "EXAT" => {
+                let secs = Duration::from_secs(parse.next_int()?);
+                getex.expire_at = Some(UNIX_EPOCH + secs);
+            }
+            "PXAT" => {
+                let ms = Duration::from_millis(parse.next_int()?);
+                getex.expire_at = Some(UNIX_EPOCH + ms);
+            }
+            "PERSIST" => getex.persist = true,
+            _ => return Err("ERR syntax error".into()),
+        }
+
+        Ok(getex)
+    }
+
+    /// Apply the `GetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // `Some(None)` leaves the expiration untouched, `Some(Some(None))`
+        // removes it. `None` is an invalid expiration.
+        let expiration = if self.persist {
+            Some(Some(None))
+        } else if self.expire.is_some() || self.expire_at.is_some() {
+            expires_at(self.expire, self.expire_at).map(Some)
+        } else {
+            Some(None)
+        };
+
+        let response = match expiration {
+            Some(expiration) => match db.getex(&self.key, expiration) {
+                Ok(Some(value)) => Frame::Bulk(value),
+                Ok(None) => Frame::Null,
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error("ERR invalid expire time in 'getex' command".into()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetEx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getex".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        if let Some(expire) = self.expire {
+            frame.push_bulk(Bytes::from("PX".as_bytes()));
+            frame.push_bulk(Bytes::from(expire.as_millis().to_string().into_bytes()));
+        }
+        if let Some(when) = self.expire_at {
+            let ms = when
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_millis())
+                .unwrap_or(0);
+            frame.push_bulk(Bytes::from("PXAT".as_bytes()));
+            frame.push_bulk(Bytes::from(ms.to_string().into_bytes()));
+        }
+        if self.persist {
+            frame.push_bulk(Bytes::from("PERSIST".as_bytes()));
+        }
+        frame
+    }
+}
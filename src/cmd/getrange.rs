@@ -0,0 +1,107 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the substring of the string value stored at `key`, determined by
+/// the offsets `start` and `end`, both inclusive.
+///
+/// Negative offsets can be used to provide an offset starting from the end of
+/// the string: `-1` is the last character, `-2` the penultimate and so on.
+/// Out of range requests are handled by limiting the resulting range to the
+/// actual length of the string.
+#[derive(Debug)]
+pub struct GetRange {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Offset of the first byte to return
+    start: i64,
+
+    /// Offset of the last byte to return
+    end: i64,
+}
+
+impl GetRange {
+    /// Create a new `GetRange` command which fetches the bytes between `start`
+    /// and `end` in the string stored at `key`.
+    pub fn new(key: impl ToString, start: i64, end: i64) -> GetRange {
+        GetRange {
+            key: key.to_string(),
+            start,
+            end,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start offset
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the end offset
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Parse a `GetRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetRange` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// GETRANGE key start end
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let end = parse.next_signed_int()?;
+
+        Ok(GetRange { key, start, end })
+    }
+
+    /// Apply the `GetRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.getrange(&self.key, self.start, self.end) {
+            Ok(value) => Frame::Bulk(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(self.end.to_string().into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes the specified fields from the hash stored at `key`.
+///
+/// Fields that do not exist within the hash are ignored. If `key` does not
+/// exist, it is treated as an empty hash.
+///
+/// Integer reply: The number of fields that were removed.
+#[derive(Debug)]
+pub struct HDel {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Fields to remove
+    fields: Vec<String>,
+}
+
+impl HDel {
+    /// Create a new `HDel` command which removes `fields` from the hash stored
+    /// at `key`.
+    pub fn new(key: impl ToString, fields: Vec<String>) -> HDel {
+        HDel {
+            key: key.to_string(),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the fields to remove
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Parse a `HDel` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HDEL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HDel` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// HDEL key field [field ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HDel> {
+        let key = parse.next_string()?;
+        let mut fields = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(field) => fields.push(field),
+                // Finish reading all the fields
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HDel { key, fields })
+    }
+
+    /// Apply the `HDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hdel(&self.key, &self.fields) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HDel` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hdel".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for field in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,192 @@
+use crate::db::check_client_name;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Switch the connection to another version of the protocol, and return
+/// information about the server.
+///
+/// Connections start with RESP2. Once switched to RESP3 with `HELLO 3`, the
+/// replies use its richer types, such as maps and doubles. Without a version,
+/// the protocol is left unchanged.
+///
+/// Along with a version, the client may authenticate, as `AUTH` would, and
+/// set its name, as `CLIENT SETNAME` would. `HELLO` is accepted before the
+/// client authenticates only if it does so. The protocol is switched once
+/// they both succeeded.
+///
+/// The reply is a map describing the server: its name and version, the
+/// version of the protocol, the ID of the connection, whether cluster mode is
+/// enabled and whether the server is a replica.
+#[derive(Debug, Default)]
+pub struct Hello {
+    protocol: Option<u64>,
+    auth: Option<(String, String)>,
+    name: Option<String>,
+}
+
+impl Hello {
+    /// Create a new `Hello` command which switches to the version `protocol`
+    /// of the protocol, if any.
+    pub fn new(protocol: Option<u64>) -> Hello {
+        Hello {
+            protocol,
+            ..Hello::default()
+        }
+    }
+
+    /// Authenticate as `username` with `password`. Requires a version of the
+    /// protocol.
+    pub fn auth(mut self, username: impl ToString, password: impl ToString) -> Hello {
+        self.auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Set the name of the client to `name`. Requires a version of the
+    /// protocol.
+    pub fn setname(mut self, name: impl ToString) -> Hello {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Returns `true` if the command authenticates the client.
+    pub(crate) fn authenticates(&self) -> bool {
+        self.auth.is_some()
+    }
+
+    /// Parse a `Hello` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HELLO` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Hello` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// HELLO [protover [AUTH username password] [SETNAME clientname]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hello> {
+        let mut hello = match parse.next_int() {
+            Ok(protocol) => Hello::new(Some(protocol)),
+            Err(ParseError::EndOfStream) => return Ok(Hello::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        loop {
+            match parse.next_string() {
+                Ok(option) => match &option.to_lowercase()[..] {
+                    "auth" => {
+                        let username = parse.next_string()?;
+                        let password = parse.next_string()?;
+                        hello = hello.auth(username, password);
+                    }
+                    "setname" => hello = hello.setname(parse.next_string()?),
+                    _ => {
+                        return Err(format!("ERR Syntax error in HELLO option '{}'", option).into())
+                    }
+                },
+                Err(ParseError::EndOfStream) => return Ok(hello),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Apply the `Hello` command, authenticating the client of `db` and
+    /// setting its name if requested, then switching `dst` to the requested
+    /// version of the protocol.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.switch(db) {
+            Ok(protocol) => {
+                if let Some(protocol) = protocol {
+                    dst.set_protocol(protocol);
+                }
+                hello(db, dst.protocol())
+            }
+            Err(err) => Frame::Error(err),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Authenticate the client and set its name, returning the version of the
+    /// protocol to switch to, if any.
+    fn switch(&self, db: &Db) -> Result<Option<u8>, String> {
+        let protocol = match self.protocol {
+            Some(protocol @ 2..=3) => Some(protocol as u8),
+            None => None,
+            Some(_) => return Err("NOPROTO unsupported protocol version".to_string()),
+        };
+
+        if let Some(name) = &self.name {
+            check_client_name(name)?;
+        }
+        if let Some((username, password)) = &self.auth {
+            db.authenticate(Some(username), password)?;
+        }
+        if let Some(name) = &self.name {
+            db.client_setname(name)?;
+        }
+
+        Ok(protocol)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Hello` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hello".as_bytes()));
+        if let Some(protocol) = self.protocol {
+            frame.push_int(protocol as i64);
+        }
+        if let Some((username, password)) = self.auth {
+            frame.push_bulk(Bytes::from("auth".as_bytes()));
+            frame.push_bulk(Bytes::from(username.into_bytes()));
+            frame.push_bulk(Bytes::from(password.into_bytes()));
+        }
+        if let Some(name) = self.name {
+            frame.push_bulk(Bytes::from("setname".as_bytes()));
+            frame.push_bulk(Bytes::from(name.into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Returns the map describing the server, replied to `HELLO`.
+fn hello(db: &Db, protocol: u8) -> Frame {
+    let bulk = |s: &str| Frame::Bulk(Bytes::from(s.to_string().into_bytes()));
+
+    let mode = if db.cluster_enabled() {
+        "cluster"
+    } else {
+        "standalone"
+    };
+    let role = if db.is_replica() { "replica" } else { "master" };
+
+    Frame::Map(vec![
+        (bulk("server"), bulk("redis")),
+        (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+        (bulk("proto"), Frame::Integer(protocol as i64)),
+        (bulk("id"), Frame::Integer(db.client_id() as i64)),
+        (bulk("mode"), bulk(mode)),
+        (bulk("role"), bulk(role)),
+        (bulk("modules"), Frame::array()),
+    ])
+}
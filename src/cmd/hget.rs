@@ -0,0 +1,94 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Get the value associated with `field` in the hash stored at `key`.
+///
+/// If either the key or the field does not exist, the special value nil is
+/// returned.
+#[derive(Debug)]
+pub struct HGet {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Name of the field to get
+    field: String,
+}
+
+impl HGet {
+    /// Create a new `HGet` command which fetches `field` from the hash stored
+    /// at `key`.
+    pub fn new(key: impl ToString, field: impl ToString) -> HGet {
+        HGet {
+            key: key.to_string(),
+            field: field.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Parse a `HGet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HGET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HGet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// HGET key field
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGet> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+
+        Ok(HGet { key, field })
+    }
+
+    /// Apply the `HGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hget(&self.key, &self.field) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HGet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hget".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.field.into_bytes()));
+        frame
+    }
+}
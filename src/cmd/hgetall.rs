@@ -0,0 +1,92 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns all fields and values of the hash stored at `key`.
+///
+/// The reply is an array in which every field name is followed by its value,
+/// so its length is twice the size of the hash. If `key` does not exist, an
+/// empty array is returned.
+#[derive(Debug)]
+pub struct HGetAll {
+    /// Name of the key holding the hash
+    key: String,
+}
+
+impl HGetAll {
+    /// Create a new `HGetAll` command which fetches the hash stored at `key`.
+    pub fn new(key: impl ToString) -> HGetAll {
+        HGetAll {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `HGetAll` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HGETALL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HGetAll` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HGETALL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGetAll> {
+        let key = parse.next_string()?;
+
+        Ok(HGetAll { key })
+    }
+
+    /// Apply the `HGetAll` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hgetall(&self.key) {
+            Ok(fields) => Frame::Map(
+                fields
+                    .into_iter()
+                    .map(|(field, value)| {
+                        (
+                            Frame::Bulk(Bytes::from(field.into_bytes())),
+                            Frame::Bulk(value),
+                        )
+                    })
+                    .collect(),
+            ),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HGetAll` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hgetall".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increments the integer stored in `field` of the hash stored at `key` by
+/// `increment`. A negative `increment` decrements the integer.
+///
+/// If the field does not exist, it is set to `0` before performing the
+/// operation, and if `key` does not exist, a new hash is created. An error is
+/// returned if the field does not hold an integer, or if the operation would
+/// overflow.
+///
+/// Integer reply: the value of the field after the increment.
+#[derive(Debug)]
+pub struct HIncrBy {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Name of the field holding the number
+    field: String,
+
+    /// Amount to add to the number
+    increment: i64,
+}
+
+impl HIncrBy {
+    /// Create a new `HIncrBy` command which adds `increment` to `field` in
+    /// the hash stored at `key`.
+    pub fn new(key: impl ToString, field: impl ToString, increment: i64) -> HIncrBy {
+        HIncrBy {
+            key: key.to_string(),
+            field: field.to_string(),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> i64 {
+        self.increment
+    }
+
+    /// Parse a `HIncrBy` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HINCRBY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HIncrBy` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// HINCRBY key field increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HIncrBy> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+        let increment = parse.next_signed_int()?;
+
+        Ok(HIncrBy {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    /// Apply the `HIncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hincr_by(self.key, self.field, self.increment) {
+            Ok(value) => Frame::Integer(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HIncrBy` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hincrby".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.field.into_bytes()));
+        frame.push_bulk(Bytes::from(self.increment.to_string().into_bytes()));
+        frame
+    }
+}
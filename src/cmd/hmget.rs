@@ -0,0 +1,111 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the values associated with the specified fields in the hash stored
+/// at `key`.
+///
+/// For every field that does not exist in the hash, a nil value is returned.
+/// Because non-existing keys are treated as empty hashes, running `HMGET`
+/// against a non-existing key returns a list of nil values.
+#[derive(Debug)]
+pub struct HMGet {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Fields to get
+    fields: Vec<String>,
+}
+
+impl HMGet {
+    /// Create a new `HMGet` command which fetches `fields` from the hash stored
+    /// at `key`.
+    pub fn new(key: impl ToString, fields: Vec<String>) -> HMGet {
+        HMGet {
+            key: key.to_string(),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the fields
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Parse a `HMGet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HMGET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HMGet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// HMGET key field [field ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HMGet> {
+        let key = parse.next_string()?;
+        let mut fields = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(field) => fields.push(field),
+                // Finish reading all the fields
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HMGet { key, fields })
+    }
+
+    /// Apply the `HMGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hmget(&self.key, &self.fields) {
+            Ok(values) => Frame::Array(
+                values
+                    .into_iter()
+                    .map(|value| value.map(Frame::Bulk).unwrap_or(Frame::Null))
+                    .collect(),
+            ),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HMGet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hmget".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for field in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,186 @@
+use crate::cmd::srandmember::{count_in_range, random_reply, OUT_OF_RANGE};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns fields picked at random from the hash stored at `key`.
+///
+/// By default, the command replies with a single field, or nil when `key`
+/// does not exist. When provided with the optional `count` argument, the reply
+/// is an array:
+///
+/// * with a positive `count`, of up to `count` distinct fields.
+/// * with a negative `count`, of exactly `-count` fields, the same field
+///   possibly being returned several times.
+///
+/// # Options
+///
+/// * WITHVALUES -- Return the value of each field following the field. Only
+///   valid along with `count`.
+#[derive(Debug)]
+pub struct HRandField {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Number of fields to return. `None` returns a single field.
+    count: Option<i64>,
+
+    /// When `true`, values are returned along with the fields
+    with_values: bool,
+}
+
+impl HRandField {
+    /// Create a new `HRandField` command which picks fields from the hash
+    /// stored at `key`.
+    pub fn new(key: impl ToString, count: Option<i64>) -> HRandField {
+        HRandField {
+            key: key.to_string(),
+            count,
+            with_values: false,
+        }
+    }
+
+    /// Return the values along with the fields when `with_values` is set.
+    /// Only used when a count is given.
+    pub fn with_values(mut self, with_values: bool) -> HRandField {
+        self.with_values = with_values;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of fields to return
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Parse a `HRandField` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HRANDFIELD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HRandField` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// HRANDFIELD key [count [WITHVALUES]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HRandField> {
+        let (key, count, with_values) = parse_random(parse, "WITHVALUES")?;
+
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
+    }
+
+    /// Apply the `HRandField` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if !count_in_range(self.count, self.with_values) {
+            Frame::Error(OUT_OF_RANGE.to_string())
+        } else {
+            match db.hrandfield(&self.key, self.count.unwrap_or(1)) {
+                Ok(fields) if self.count.is_some() => {
+                    let len = fields.len() * if self.with_values { 2 } else { 1 };
+                    match random_reply(len) {
+                        Some(mut response) => {
+                            for (field, value) in fields {
+                                response.push_bulk(Bytes::from(field.into_bytes()));
+                                if self.with_values {
+                                    response.push_bulk(value);
+                                }
+                            }
+                            response
+                        }
+                        None => Frame::Error(OUT_OF_RANGE.to_string()),
+                    }
+                }
+                Ok(mut fields) => match fields.next() {
+                    Some((field, _)) => Frame::Bulk(Bytes::from(field.into_bytes())),
+                    None => Frame::Null,
+                },
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HRandField` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        random_frame(
+            "hrandfield",
+            self.key,
+            self.count,
+            self.with_values,
+            "withvalues",
+        )
+    }
+}
+
+/// Parses the arguments shared by the random sampling commands: a key
+/// optionally followed by a count, itself optionally followed by `option`.
+pub(crate) fn parse_random(
+    parse: &mut Parse,
+    option: &str,
+) -> crate::Result<(String, Option<i64>, bool)> {
+    let key = parse.next_string()?;
+
+    let count = match parse.next_signed_int() {
+        Ok(count) => count,
+        Err(ParseError::EndOfStream) => return Ok((key, None, false)),
+        Err(err) => return Err(err.into()),
+    };
+
+    let with = match parse.next_string() {
+        Ok(arg) if arg.eq_ignore_ascii_case(option) => true,
+        Ok(_) => return Err("ERR syntax error".into()),
+        Err(ParseError::EndOfStream) => false,
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok((key, Some(count), with))
+}
+
+/// Encodes a random sampling command named `name` into a frame.
+pub(crate) fn random_frame(
+    name: &'static str,
+    key: String,
+    count: Option<i64>,
+    with: bool,
+    option: &'static str,
+) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    if let Some(count) = count {
+        frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        if with {
+            frame.push_bulk(Bytes::from(option.as_bytes()));
+        }
+    }
+    frame
+}
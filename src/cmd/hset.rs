@@ -0,0 +1,110 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Sets fields in the hash stored at `key` to their respective values.
+///
+/// Any existing fields being specified are overwritten. If `key` does not
+/// exist, a new key holding a hash is created.
+///
+/// Integer reply: The number of fields that were added.
+#[derive(Debug)]
+pub struct HSet {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Field / value pairs to set
+    fields: Vec<(String, Bytes)>,
+}
+
+impl HSet {
+    /// Create a new `HSet` command which sets `fields` in the hash stored at
+    /// `key`.
+    pub fn new(key: impl ToString, fields: Vec<(String, Bytes)>) -> HSet {
+        HSet {
+            key: key.to_string(),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the field / value pairs
+    pub fn fields(&self) -> &[(String, Bytes)] {
+        &self.fields
+    }
+
+    /// Parse a `HSet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HSET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HSet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// HSET key field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HSet> {
+        let key = parse.next_string()?;
+
+        // At least one field / value pair is required.
+        let mut fields = vec![(parse.next_string()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_string() {
+                // A field must always be followed by its value.
+                Ok(field) => fields.push((field, parse.next_bytes()?)),
+                // Finish reading all the pairs
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HSet { key, fields })
+    }
+
+    /// Apply the `HSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hset(self.key, self.fields) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hset".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for (field, value) in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+            frame.push_bulk(value);
+        }
+        frame
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increments the number stored at `key` by one.
+///
+/// If `key` does not exist, it is set to `0` before performing the operation.
+/// An error is returned if `key` holds a value that is not a string
+/// representing a 64 bit signed integer, or if the operation would overflow.
+///
+/// Integer reply: the value of `key` after the increment.
+#[derive(Debug)]
+pub struct Incr {
+    /// Name of the key holding the counter
+    key: String,
+}
+
+/// Decrements the number stored at `key` by one.
+///
+/// Same as `Incr`, but the value is decremented.
+#[derive(Debug)]
+pub struct Decr {
+    /// Name of the key holding the counter
+    key: String,
+}
+
+/// Increments the number stored at `key` by `increment`.
+///
+/// Same as `Incr`, but by an arbitrary, possibly negative, amount.
+#[derive(Debug)]
+pub struct IncrBy {
+    /// Name of the key holding the counter
+    key: String,
+
+    /// Amount to add to the counter
+    increment: i64,
+}
+
+/// Decrements the number stored at `key` by `decrement`.
+///
+/// Same as `IncrBy`, but the amount is subtracted.
+#[derive(Debug)]
+pub struct DecrBy {
+    /// Name of the key holding the counter
+    key: String,
+
+    /// Amount to subtract from the counter
+    decrement: i64,
+}
+
+impl Incr {
+    /// Create a new `Incr` command which increments the counter stored at
+    /// `key`.
+    pub fn new(key: impl ToString) -> Incr {
+        Incr {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse an `Incr` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INCR` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Incr` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// INCR key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Incr> {
+        let key = parse.next_string()?;
+        Ok(Incr { key })
+    }
+
+    /// Apply the `Incr` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_incr(db, dst, self.key, Some(1)).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Incr` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        incr_frame("incr", self.key, None)
+    }
+}
+
+impl Decr {
+    /// Create a new `Decr` command which decrements the counter stored at
+    /// `key`.
+    pub fn new(key: impl ToString) -> Decr {
+        Decr {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Decr` instance from a received frame.
+    ///
+    /// The `DECR` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DECR key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Decr> {
+        let key = parse.next_string()?;
+        Ok(Decr { key })
+    }
+
+    /// Apply the `Decr` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_incr(db, dst, self.key, Some(-1)).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Decr` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        incr_frame("decr", self.key, None)
+    }
+}
+
+impl IncrBy {
+    /// Create a new `IncrBy` command which adds `increment` to the counter
+    /// stored at `key`.
+    pub fn new(key: impl ToString, increment: i64) -> IncrBy {
+        IncrBy {
+            key: key.to_string(),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> i64 {
+        self.increment
+    }
+
+    /// Parse an `IncrBy` instance from a received frame.
+    ///
+    /// The `INCRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBY key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrBy> {
+        let key = parse.next_string()?;
+        let increment = parse.next_signed_int()?;
+        Ok(IncrBy { key, increment })
+    }
+
+    /// Apply the `IncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_incr(db, dst, self.key, Some(self.increment)).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `IncrBy` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        incr_frame("incrby", self.key, Some(self.increment))
+    }
+}
+
+impl DecrBy {
+    /// Create a new `DecrBy` command which subtracts `decrement` from the
+    /// counter stored at `key`.
+    pub fn new(key: impl ToString, decrement: i64) -> DecrBy {
+        DecrBy {
+            key: key.to_string(),
+            decrement,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the decrement
+    pub fn decrement(&self) -> i64 {
+        self.decrement
+    }
+
+    /// Parse a `DecrBy` instance from a received frame.
+    ///
+    /// The `DECRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// DECRBY key decrement
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DecrBy> {
+        let key = parse.next_string()?;
+        let decrement = parse.next_signed_int()?;
+        Ok(DecrBy { key, decrement })
+    }
+
+    /// Apply the `DecrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // The smallest integer has no positive counterpart
+        apply_incr(db, dst, self.key, self.decrement.checked_neg()).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DecrBy` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        incr_frame("decrby", self.key, Some(self.decrement))
+    }
+}
+
+/// Adds `delta` to the counter stored at `key` and writes the new value to
+/// `dst`. A missing `delta` means it could not be represented.
+async fn apply_incr(
+    db: &Db,
+    dst: &mut Connection,
+    key: String,
+    delta: Option<i64>,
+) -> crate::Result<()> {
+    let response = match delta {
+        Some(delta) => match db.incr_by(key, delta) {
+            Ok(value) => Frame::Integer(value),
+            Err(err) => Frame::Error(err.to_string()),
+        },
+        None => Frame::Error("ERR decrement would overflow".into()),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes a counter command named `name` into a frame.
+fn incr_frame(name: &'static str, key: String, amount: Option<i64>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    if let Some(amount) = amount {
+        frame.push_bulk(Bytes::from(amount.to_string().into_bytes()));
+    }
+    frame
+}
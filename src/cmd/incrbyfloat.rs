@@ -0,0 +1,97 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increments the floating point number stored at `key` by `increment`.
+///
+/// If `key` does not exist, it is set to `0` before performing the operation.
+/// A negative `increment` decrements the value. An error is returned if `key`
+/// holds a value that is not a valid float, or if the result is not finite.
+///
+/// The new value is returned as a bulk string, formatted without trailing
+/// zeros nor exponent.
+#[derive(Debug)]
+pub struct IncrByFloat {
+    /// Name of the key holding the number
+    key: String,
+
+    /// Amount to add to the number
+    increment: f64,
+}
+
+impl IncrByFloat {
+    /// Create a new `IncrByFloat` command which adds `increment` to the number
+    /// stored at `key`.
+    pub fn new(key: impl ToString, increment: f64) -> IncrByFloat {
+        IncrByFloat {
+            key: key.to_string(),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    /// Parse an `IncrByFloat` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INCRBYFLOAT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `IncrByFloat` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBYFLOAT key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrByFloat> {
+        let key = parse.next_string()?;
+        let increment = parse.next_float()?;
+
+        Ok(IncrByFloat { key, increment })
+    }
+
+    /// Apply the `IncrByFloat` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.incr_by_float(self.key, self.increment) {
+            Ok(value) => Frame::Bulk(Bytes::from(value.to_string().into_bytes())),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `IncrByFloat` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("incrbyfloat".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.increment.to_string().into_bytes()));
+        frame
+    }
+}
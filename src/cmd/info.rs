@@ -0,0 +1,199 @@
+use crate::db::Info as State;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::fmt::Write;
+use tracing::{debug, instrument};
+
+/// Returns information and statistics about the server.
+///
+/// The reply is a bulk string made of sections, each starting with a
+/// `# Section` line followed by `field:value` lines. The sections are:
+///
+/// * `server` -- The version of the server, its process ID and uptime.
+/// * `clients` -- The number of connected clients, and of the ones tracking
+///   keys.
+/// * `memory` -- The approximate number of bytes used by the data set, the
+///   highest it reached, the `maxmemory` limit and the eviction policy.
+/// * `stats` -- The number of connections accepted and commands processed,
+///   the number of keys found (hits) or not (misses) by the commands reading
+///   them, the number of keys evicted, and the number of channels and
+///   patterns subscribed to.
+/// * `replication` -- The role of the server, its primary or the number of
+///   its replicas, and the ID and offset of the replication stream.
+/// * `cluster` -- Whether cluster mode is enabled.
+/// * `keyspace` -- The number of keys of each non-empty database, and of the
+///   ones with an expiration.
+///
+/// All the sections are returned unless one is given. `all` and `everything`
+/// select all the sections as well.
+#[derive(Debug, Default)]
+pub struct Info {
+    section: Option<String>,
+}
+
+/// Names of the sections, in the order they are returned.
+const SECTIONS: &[&str] = &[
+    "server",
+    "clients",
+    "memory",
+    "stats",
+    "replication",
+    "cluster",
+    "keyspace",
+];
+
+impl Info {
+    /// Create a new `Info` command returning `section`, or all the sections
+    /// if `None`.
+    pub fn new(section: Option<impl ToString>) -> Info {
+        Info {
+            section: section.map(|section| section.to_string()),
+        }
+    }
+
+    /// Parse an `Info` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INFO` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Info` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// INFO [section]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        let section = match parse.next_string() {
+            Ok(section) => Some(section),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Info { section })
+    }
+
+    /// Apply the `Info` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let sections: Vec<&str> = match self.section.map(|section| section.to_lowercase()) {
+            None => SECTIONS.to_vec(),
+            Some(section) if section == "all" || section == "everything" => SECTIONS.to_vec(),
+            Some(section) => SECTIONS
+                .iter()
+                .copied()
+                .filter(|name| *name == section)
+                .collect(),
+        };
+
+        let state = db.info();
+
+        let mut info = String::new();
+        for section in sections {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            write_section(&mut info, section, &state);
+        }
+
+        let response = Frame::Bulk(Bytes::from(info.into_bytes()));
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Info` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("info".as_bytes()));
+        if let Some(section) = self.section {
+            frame.push_bulk(Bytes::from(section.into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Append the section `name` to `info`, with its title.
+fn write_section(info: &mut String, name: &str, state: &State) {
+    let (first, rest) = name.split_at(1);
+    info.push_str("# ");
+    info.push_str(&first.to_uppercase());
+    info.push_str(rest);
+    info.push_str("\r\n");
+
+    // Writing to a `String` cannot fail.
+    let mut field = |field: &str, value: &dyn std::fmt::Display| {
+        let _ = write!(info, "{}:{}\r\n", field, value);
+    };
+
+    match name {
+        "server" => {
+            field("redis_version", &env!("CARGO_PKG_VERSION"));
+            field("process_id", &std::process::id());
+            field("uptime_in_seconds", &state.uptime);
+            field("uptime_in_days", &(state.uptime / (24 * 60 * 60)));
+        }
+        "clients" => {
+            field("connected_clients", &state.connected_clients);
+            field("maxclients", &state.maxclients);
+            field("tracking_clients", &state.tracking_clients);
+        }
+        "memory" => {
+            field("used_memory", &state.used_memory);
+            field("used_memory_peak", &state.used_memory_peak);
+            field("maxmemory", &state.maxmemory);
+            field("maxmemory_policy", &state.maxmemory_policy);
+        }
+        "stats" => {
+            field("total_connections_received", &state.connections);
+            field("rejected_connections", &state.rejected_connections);
+            field("total_commands_processed", &state.commands);
+            field("throttled_commands", &state.throttled_commands);
+            field("keyspace_hits", &state.hits);
+            field("keyspace_misses", &state.misses);
+            field("evicted_keys", &state.evicted);
+            field("pubsub_channels", &state.pubsub_channels);
+            field("pubsub_patterns", &state.pubsub_patterns);
+        }
+        "replication" => {
+            match &state.primary {
+                Some(primary) => {
+                    let (host, port) = primary.rsplit_once(':').unwrap_or((primary, ""));
+                    field("role", &"slave");
+                    field("master_host", &host);
+                    field("master_port", &port);
+                }
+                None => field("role", &"master"),
+            }
+            field("connected_slaves", &state.connected_replicas);
+            field("master_replid", &state.replication_id);
+            field("master_repl_offset", &state.replication_offset);
+        }
+        "cluster" => {
+            field("cluster_enabled", &(state.cluster_enabled as u8));
+        }
+        "keyspace" => {
+            for (index, keys, expires) in &state.keyspace {
+                let value = format!("keys={},expires={}", keys, expires);
+                field(&format!("db{}", index), &value);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
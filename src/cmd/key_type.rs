@@ -0,0 +1,80 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the type of the value stored at `key`.
+///
+/// The type is one of `string`, `list`, `hash`, `set`, `zset` and `stream`,
+/// or `none` if the key does not exist. The reply is a simple string.
+#[derive(Debug)]
+pub struct Type {
+    /// Name of the key to get the type of
+    key: String,
+}
+
+impl Type {
+    /// Create a new `Type` command which fetches the type of the value stored
+    /// at `key`.
+    pub fn new(key: impl ToString) -> Type {
+        Type {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Type` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TYPE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Type` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// TYPE key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Type> {
+        let key = parse.next_string()?;
+
+        Ok(Type { key })
+    }
+
+    /// Apply the `Type` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let name = db.type_of(&self.key).unwrap_or("none");
+        let response = Frame::Simple(name.to_string());
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Type` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("type".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
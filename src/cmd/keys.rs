@@ -0,0 +1,85 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns all the keys matching a glob-style pattern.
+///
+/// The pattern supports `*`, `?`, character classes such as `[a-z]` or
+/// `[^abc]`, and `\` to escape special characters. Keys are returned in no
+/// particular order.
+///
+/// The whole key space is walked while holding the lock, so this command is
+/// meant for debugging rather than for production workloads.
+#[derive(Debug)]
+pub struct Keys {
+    /// Glob-style pattern to match keys against
+    pattern: String,
+}
+
+impl Keys {
+    /// Create a new `Keys` command which fetches the keys matching `pattern`.
+    pub fn new(pattern: impl ToString) -> Keys {
+        Keys {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Parse a `Keys` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `KEYS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Keys` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// KEYS pattern
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Keys> {
+        let pattern = parse.next_string()?;
+
+        Ok(Keys { pattern })
+    }
+
+    /// Apply the `Keys` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        for key in db.keys(&self.pattern) {
+            response.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Keys` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("keys".as_bytes()));
+        frame.push_bulk(Bytes::from(self.pattern.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspects the latency spikes recorded by the latency monitor, which records
+/// the events taking longer than the `latency-monitor-threshold` parameter,
+/// in milliseconds.
+///
+/// The events are `command`, for the commands applied, `expire-cycle`, for
+/// the purge of the expired keys, and `snapshot`, for the copy of the
+/// databases when saving a snapshot.
+///
+/// # Subcommands
+///
+/// * LATEST -- Returns the latest spike of each event, as an array holding
+///   the name of the event, the Unix time at which the spike was recorded,
+///   its latency and the highest latency ever recorded for the event.
+/// * HISTORY `event` -- Returns the latest spikes of `event`, the oldest
+///   first, each as the Unix time at which it was recorded and its latency.
+/// * RESET [`event` ...] -- Drops the spikes of the `event`s, or of every
+///   event if none is given. Returns the number of events dropped.
+/// * DOCTOR -- Returns a report of the spikes of each event.
+#[derive(Debug)]
+pub struct Latency {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Latest,
+    History(String),
+    Reset(Vec<String>),
+    Doctor,
+}
+
+impl Latency {
+    /// Create a new `Latency` command returning the latest spike of each
+    /// event.
+    pub fn latest() -> Latency {
+        Latency {
+            subcommand: Subcommand::Latest,
+        }
+    }
+
+    /// Create a new `Latency` command returning the latest spikes of `event`.
+    pub fn history(event: impl ToString) -> Latency {
+        Latency {
+            subcommand: Subcommand::History(event.to_string()),
+        }
+    }
+
+    /// Create a new `Latency` command dropping the spikes of `events`, or of
+    /// every event if empty.
+    pub fn reset(events: Vec<String>) -> Latency {
+        Latency {
+            subcommand: Subcommand::Reset(events),
+        }
+    }
+
+    /// Create a new `Latency` command returning a report of the spikes.
+    pub fn doctor() -> Latency {
+        Latency {
+            subcommand: Subcommand::Doctor,
+        }
+    }
+
+    /// Parse a `Latency` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LATENCY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Latency` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// LATENCY LATEST
+    /// LATENCY HISTORY event
+    /// LATENCY RESET [event ...]
+    /// LATENCY DOCTOR
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Latency> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "LATEST" => Subcommand::Latest,
+            "HISTORY" => Subcommand::History(parse.next_string()?),
+            "RESET" => {
+                let mut events = vec![];
+                loop {
+                    match parse.next_string() {
+                        Ok(event) => events.push(event),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                Subcommand::Reset(events)
+            }
+            "DOCTOR" => Subcommand::Doctor,
+            _ => return Err(format!("unknown `LATENCY` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Latency { subcommand })
+    }
+
+    /// Apply the `Latency` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Latest => Frame::Array(
+                db.latency_latest()
+                    .into_iter()
+                    .map(|spike| {
+                        Frame::Array(vec![
+                            Frame::Bulk(Bytes::from(spike.event.as_bytes())),
+                            Frame::Integer(spike.time as i64),
+                            Frame::Integer(spike.latency as i64),
+                            Frame::Integer(spike.max as i64),
+                        ])
+                    })
+                    .collect(),
+            ),
+            Subcommand::History(event) => Frame::Array(
+                db.latency_history(&event)
+                    .into_iter()
+                    .map(|(time, latency)| {
+                        Frame::Array(vec![
+                            Frame::Integer(time as i64),
+                            Frame::Integer(latency as i64),
+                        ])
+                    })
+                    .collect(),
+            ),
+            Subcommand::Reset(events) => Frame::Integer(db.latency_reset(&events) as i64),
+            Subcommand::Doctor => Frame::Bulk(Bytes::from(db.latency_doctor().into_bytes())),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Latency` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("latency".as_bytes()));
+        match self.subcommand {
+            Subcommand::Latest => frame.push_bulk(Bytes::from("latest".as_bytes())),
+            Subcommand::History(event) => {
+                frame.push_bulk(Bytes::from("history".as_bytes()));
+                frame.push_bulk(Bytes::from(event.into_bytes()));
+            }
+            Subcommand::Reset(events) => {
+                frame.push_bulk(Bytes::from("reset".as_bytes()));
+                for event in events {
+                    frame.push_bulk(Bytes::from(event.into_bytes()));
+                }
+            }
+            Subcommand::Doctor => frame.push_bulk(Bytes::from("doctor".as_bytes())),
+        }
+        frame
+    }
+}
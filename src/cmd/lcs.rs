@@ -0,0 +1,208 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the longest common subsequence of the strings stored at `key1` and
+/// `key2`.
+///
+/// Keys that do not exist are treated as empty strings.
+///
+/// Bulk string reply: the longest common subsequence.
+///
+/// # Options
+///
+/// * LEN -- Reply with the length of the subsequence instead.
+/// * IDX -- Reply with the ranges of the subsequence which are consecutive in
+///   both strings, along with the length of the subsequence, as
+///   `["matches", [[[start1, end1], [start2, end2]], ...], "len", len]`. Ranges
+///   are listed from the end of the strings to their start.
+/// * MINMATCHLEN `len` -- With `IDX`, only reply with ranges of at least `len`
+///   bytes.
+/// * WITHMATCHLEN -- With `IDX`, also reply with the length of each range.
+#[derive(Debug)]
+pub struct Lcs {
+    /// Name of the key holding the first string
+    key1: String,
+
+    /// Name of the key holding the second string
+    key2: String,
+
+    /// Reply with the length of the subsequence, validated to not be set
+    /// along with `idx` when applied
+    len: bool,
+
+    /// Reply with the matching ranges
+    idx: bool,
+
+    /// Minimum length of the matching ranges, negative values being treated
+    /// as `0`
+    min_match_len: i64,
+
+    /// Reply with the length of each matching range
+    with_match_len: bool,
+}
+
+impl Lcs {
+    /// Create a new `Lcs` command which returns the longest common subsequence
+    /// of the strings stored at `key1` and `key2`.
+    pub fn new(key1: impl ToString, key2: impl ToString) -> Lcs {
+        Lcs {
+            key1: key1.to_string(),
+            key2: key2.to_string(),
+            len: false,
+            idx: false,
+            min_match_len: 0,
+            with_match_len: false,
+        }
+    }
+
+    /// Reply with the length of the subsequence.
+    pub fn len(mut self) -> Lcs {
+        self.len = true;
+        self
+    }
+
+    /// Reply with the ranges of at least `min_match_len` bytes which are
+    /// consecutive in both strings, along with the length of each range when
+    /// `with_match_len` is set.
+    pub fn idx(mut self, min_match_len: u64, with_match_len: bool) -> Lcs {
+        self.idx = true;
+        self.min_match_len = min_match_len as i64;
+        self.with_match_len = with_match_len;
+        self
+    }
+
+    /// Get the first key
+    pub fn key1(&self) -> &str {
+        &self.key1
+    }
+
+    /// Get the second key
+    pub fn key2(&self) -> &str {
+        &self.key2
+    }
+
+    /// Parse a `Lcs` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LCS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Lcs` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LCS key1 key2 [LEN] [IDX] [MINMATCHLEN len] [WITHMATCHLEN]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Lcs> {
+        let mut lcs = Lcs::new(parse.next_string()?, parse.next_string()?);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "LEN" => lcs.len = true,
+                "IDX" => lcs.idx = true,
+                "MINMATCHLEN" => lcs.min_match_len = parse.next_signed_int()?,
+                "WITHMATCHLEN" => lcs.with_match_len = true,
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(lcs)
+    }
+
+    /// Apply the `Lcs` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.len && self.idx {
+            Frame::Error("ERR If you want both the length and indexes, please just use IDX.".into())
+        } else if self.len {
+            match db.lcs_len(&self.key1, &self.key2) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        } else {
+            match db.lcs(&self.key1, &self.key2) {
+                Ok(lcs) if self.idx => {
+                    let min_match_len = self.min_match_len.max(0) as usize;
+
+                    let matches = lcs
+                        .matches
+                        .iter()
+                        .filter(|range| range.len() >= min_match_len)
+                        .map(|range| {
+                            let mut item = vec![range_frame(range.a), range_frame(range.b)];
+                            if self.with_match_len {
+                                item.push(Frame::Integer(range.len() as i64));
+                            }
+                            Frame::Array(item)
+                        })
+                        .collect();
+
+                    Frame::Array(vec![
+                        Frame::Bulk(Bytes::from("matches".as_bytes())),
+                        Frame::Array(matches),
+                        Frame::Bulk(Bytes::from("len".as_bytes())),
+                        Frame::Integer(lcs.sequence.len() as i64),
+                    ])
+                }
+                Ok(lcs) => Frame::Bulk(lcs.sequence),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Lcs` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lcs".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key1.into_bytes()));
+        frame.push_bulk(Bytes::from(self.key2.into_bytes()));
+        if self.len {
+            frame.push_bulk(Bytes::from("len".as_bytes()));
+        }
+        if self.idx {
+            frame.push_bulk(Bytes::from("idx".as_bytes()));
+        }
+        if self.min_match_len != 0 {
+            frame.push_bulk(Bytes::from("minmatchlen".as_bytes()));
+            frame.push_bulk(Bytes::from(self.min_match_len.to_string().into_bytes()));
+        }
+        if self.with_match_len {
+            frame.push_bulk(Bytes::from("withmatchlen".as_bytes()));
+        }
+        frame
+    }
+}
+
+/// Encode the inclusive `(start, end)` offsets of a matching range.
+fn range_frame((start, end): (usize, usize)) -> Frame {
+    Frame::Array(vec![
+        Frame::Integer(start as i64),
+        Frame::Integer(end as i64),
+    ])
+}
@@ -0,0 +1,129 @@
+use crate::cmd::InsertPosition;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inserts `element` in the list stored at `key`, either before or after the
+/// first value equal to `pivot`.
+///
+/// Integer reply: the length of the list after the insertion, `-1` if `pivot`
+/// was not found, or `0` if `key` does not exist.
+#[derive(Debug)]
+pub struct LInsert {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Whether `element` is inserted before or after `pivot`
+    position: InsertPosition,
+
+    /// Value to insert `element` next to
+    pivot: Bytes,
+
+    /// Value to insert
+    element: Bytes,
+}
+
+impl LInsert {
+    /// Create a new `LInsert` command which inserts `element` next to `pivot`
+    /// in the list stored at `key`.
+    pub fn new(
+        key: impl ToString,
+        position: InsertPosition,
+        pivot: Bytes,
+        element: Bytes,
+    ) -> LInsert {
+        LInsert {
+            key: key.to_string(),
+            position,
+            pivot,
+            element,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the position relative to the pivot
+    pub fn position(&self) -> InsertPosition {
+        self.position
+    }
+
+    /// Parse a `LInsert` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LINSERT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LInsert` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing five entries.
+    ///
+    /// ```text
+    /// LINSERT key BEFORE | AFTER pivot element
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LInsert> {
+        let key = parse.next_string()?;
+
+        let position = match &parse.next_string()?.to_uppercase()[..] {
+            "BEFORE" => InsertPosition::Before,
+            "AFTER" => InsertPosition::After,
+            _ => return Err("ERR syntax error".into()),
+        };
+
+        let pivot = parse.next_bytes()?;
+        let element = parse.next_bytes()?;
+
+        Ok(LInsert {
+            key,
+            position,
+            pivot,
+            element,
+        })
+    }
+
+    /// Apply the `LInsert` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.linsert(&self.key, self.position, &self.pivot, self.element) {
+            Ok(Some(len)) => Frame::Integer(len as i64),
+            Ok(None) => Frame::Integer(-1),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LInsert` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let position = match self.position {
+            InsertPosition::Before => "before",
+            InsertPosition::After => "after",
+        };
+
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("linsert".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(position.as_bytes()));
+        frame.push_bulk(self.pivot);
+        frame.push_bulk(self.element);
+        frame
+    }
+}
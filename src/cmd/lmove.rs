@@ -0,0 +1,503 @@
+use crate::cmd::blocking::{check_timeout, KeyWaiter, Ticket};
+use crate::cmd::bpop::parse_timeout;
+use crate::{Connection, Db, Frame, ListEnd, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::select;
+use tracing::{debug, instrument};
+
+/// Atomically pops a value from one end of the list stored at `source` and
+/// pushes it to one end of the list stored at `destination`.
+///
+/// As the value is moved in a single step, it is never lost between the pop
+/// and the push, which makes reliable queues possible. `source` and
+/// `destination` may be the same list, which rotates it.
+///
+/// Bulk reply: the moved value, or nil when `source` does not exist.
+#[derive(Debug)]
+pub struct LMove {
+    /// Name of the key holding the list to pop from
+    source: String,
+
+    /// Name of the key holding the list to push to
+    destination: String,
+
+    /// End of `source` to pop from
+    from: ListEnd,
+
+    /// End of `destination` to push to
+    to: ListEnd,
+}
+
+/// Blocking version of `LMove`.
+///
+/// When the list stored at `source` is empty, the command waits up to
+/// `timeout` for a value to be pushed, a zero `timeout` blocking forever.
+/// Clients blocked on the same key are served in the order they started
+/// waiting.
+///
+/// Bulk reply: the moved value, or nil when `timeout` elapses first.
+#[derive(Debug)]
+pub struct BLMove {
+    /// Name of the key holding the list to pop from
+    source: String,
+
+    /// Name of the key holding the list to push to
+    destination: String,
+
+    /// End of `source` to pop from
+    from: ListEnd,
+
+    /// End of `destination` to push to
+    to: ListEnd,
+
+    /// How long to wait for a value, zero waiting forever
+    timeout: Duration,
+}
+
+/// Pops the last value of the list stored at `source` and pushes it to the
+/// head of the list stored at `destination`.
+///
+/// Same as `LMove` from the right end to the left end.
+#[derive(Debug)]
+pub struct RPopLPush {
+    /// Name of the key holding the list to pop from
+    source: String,
+
+    /// Name of the key holding the list to push to
+    destination: String,
+}
+
+/// Blocking version of `RPopLPush`.
+///
+/// Same as `BLMove` from the right end to the left end.
+#[derive(Debug)]
+pub struct BRPopLPush {
+    /// Name of the key holding the list to pop from
+    source: String,
+
+    /// Name of the key holding the list to push to
+    destination: String,
+
+    /// How long to wait for a value, zero waiting forever
+    timeout: Duration,
+}
+
+impl LMove {
+    /// Create a new `LMove` command which moves a value from the `from` end of
+    /// `source` to the `to` end of `destination`.
+    pub fn new(
+        source: impl ToString,
+        destination: impl ToString,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> LMove {
+        LMove {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            from,
+            to,
+        }
+    }
+
+    /// Get the source key
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the destination key
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Parse a `LMove` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LMOVE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LMove` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing five entries.
+    ///
+    /// ```text
+    /// LMOVE source destination LEFT|RIGHT LEFT|RIGHT
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LMove> {
+        let source = parse.next_string()?;
+        let destination = parse.next_string()?;
+        let from = parse_end(parse)?;
+        let to = parse_end(parse)?;
+
+        Ok(LMove {
+            source,
+            destination,
+            from,
+            to,
+        })
+    }
+
+    /// Apply the `LMove` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lmove(&self.source, &self.destination, self.from, self.to, None) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LMove` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lmove".as_bytes()));
+        frame.push_bulk(Bytes::from(self.source.into_bytes()));
+        frame.push_bulk(Bytes::from(self.destination.into_bytes()));
+        frame.push_bulk(end_bytes(self.from));
+        frame.push_bulk(end_bytes(self.to));
+        frame
+    }
+}
+
+impl BLMove {
+    /// Create a new `BLMove` command which moves a value from the `from` end
+    /// of `source` to the `to` end of `destination`, waiting up to `timeout`
+    /// for one to be available.
+    pub fn new(
+        source: impl ToString,
+        destination: impl ToString,
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Duration,
+    ) -> BLMove {
+        BLMove {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            from,
+            to,
+            timeout,
+        }
+    }
+
+    /// Get the source key
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the destination key
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BLMove` instance from a received frame.
+    ///
+    /// The `BLMOVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing six entries. The timeout is a number
+    /// of seconds, possibly with a decimal part.
+    ///
+    /// ```text
+    /// BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BLMove> {
+        let source = parse.next_string()?;
+        let destination = parse.next_string()?;
+        let from = parse_end(parse)?;
+        let to = parse_end(parse)?;
+        let timeout = parse_timeout(&parse.next_string()?)?;
+
+        Ok(BLMove {
+            source,
+            destination,
+            from,
+            to,
+            timeout,
+        })
+    }
+
+    /// Apply the `BLMove` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        apply_blocking_move(
+            db,
+            dst,
+            shutdown,
+            &self.source,
+            &self.destination,
+            (self.from, self.to),
+            self.timeout,
+        )
+        .await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BLMove` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("blmove".as_bytes()));
+        frame.push_bulk(Bytes::from(self.source.into_bytes()));
+        frame.push_bulk(Bytes::from(self.destination.into_bytes()));
+        frame.push_bulk(end_bytes(self.from));
+        frame.push_bulk(end_bytes(self.to));
+        frame.push_bulk(timeout_bytes(self.timeout));
+        frame
+    }
+}
+
+impl RPopLPush {
+    /// Create a new `RPopLPush` command which moves the last value of `source`
+    /// to the head of `destination`.
+    pub fn new(source: impl ToString, destination: impl ToString) -> RPopLPush {
+        RPopLPush {
+            source: source.to_string(),
+            destination: destination.to_string(),
+        }
+    }
+
+    /// Get the source key
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the destination key
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Parse a `RPopLPush` instance from a received frame.
+    ///
+    /// The `RPOPLPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RPOPLPUSH source destination
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPopLPush> {
+        let source = parse.next_string()?;
+        let destination = parse.next_string()?;
+
+        Ok(RPopLPush {
+            source,
+            destination,
+        })
+    }
+
+    /// Apply the `RPopLPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        LMove::new(self.source, self.destination, ListEnd::Right, ListEnd::Left)
+            .apply(db, dst)
+            .await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `RPopLPush` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("rpoplpush".as_bytes()));
+        frame.push_bulk(Bytes::from(self.source.into_bytes()));
+        frame.push_bulk(Bytes::from(self.destination.into_bytes()));
+        frame
+    }
+}
+
+impl BRPopLPush {
+    /// Create a new `BRPopLPush` command which moves the last value of
+    /// `source` to the head of `destination`, waiting up to `timeout` for one
+    /// to be available.
+    pub fn new(source: impl ToString, destination: impl ToString, timeout: Duration) -> BRPopLPush {
+        BRPopLPush {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            timeout,
+        }
+    }
+
+    /// Get the source key
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the destination key
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BRPopLPush` instance from a received frame.
+    ///
+    /// The `BRPOPLPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// BRPOPLPUSH source destination timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BRPopLPush> {
+        let source = parse.next_string()?;
+        let destination = parse.next_string()?;
+        let timeout = parse_timeout(&parse.next_string()?)?;
+
+        Ok(BRPopLPush {
+            source,
+            destination,
+            timeout,
+        })
+    }
+
+    /// Apply the `BRPopLPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        apply_blocking_move(
+            db,
+            dst,
+            shutdown,
+            &self.source,
+            &self.destination,
+            (ListEnd::Right, ListEnd::Left),
+            self.timeout,
+        )
+        .await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BRPopLPush` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("brpoplpush".as_bytes()));
+        frame.push_bulk(Bytes::from(self.source.into_bytes()));
+        frame.push_bulk(Bytes::from(self.destination.into_bytes()));
+        frame.push_bulk(timeout_bytes(self.timeout));
+        frame
+    }
+}
+
+/// Parses the `LEFT` or `RIGHT` argument naming one end of a list.
+fn parse_end(parse: &mut Parse) -> crate::Result<ListEnd> {
+    match &parse.next_string()?.to_uppercase()[..] {
+        "LEFT" => Ok(ListEnd::Left),
+        "RIGHT" => Ok(ListEnd::Right),
+        _ => Err("ERR syntax error".into()),
+    }
+}
+
+/// Moves a value from `source` to `destination`, waiting for one to be pushed
+/// to `source` if needed, and writes the reply to `dst`.
+async fn apply_blocking_move(
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+    source: &str,
+    destination: &str,
+    (from, to): (ListEnd, ListEnd),
+    timeout: Duration,
+) -> crate::Result<()> {
+    if let Err(err) = check_timeout(timeout) {
+        let response = Frame::Error(err);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        return Ok(());
+    }
+
+    let keys = [source.to_string()];
+
+    // Subscribe to writes and queue up **before** checking the list, as
+    // blocking pops do.
+    let mut waiter = KeyWaiter::new(db, &keys, timeout);
+    let ticket = Ticket::new(db, &keys);
+
+    let response = loop {
+        match db.lmove(source, destination, from, to, Some(ticket.id())) {
+            Ok(Some(value)) => break Frame::Bulk(value),
+            Ok(None) => {}
+            Err(err) => break Frame::Error(err.to_string()),
+        }
+
+        // Nothing to move yet, wait for the source list to be pushed to, for
+        // the timeout to elapse or for the server to shut down.
+        select! {
+            written = waiter.wait() => if !written {
+                break Frame::Null;
+            },
+            _ = shutdown.recv() => return Ok(()),
+        }
+    };
+
+    drop(ticket);
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes one end of a list as a command argument.
+fn end_bytes(end: ListEnd) -> Bytes {
+    match end {
+        ListEnd::Left => Bytes::from("left".as_bytes()),
+        ListEnd::Right => Bytes::from("right".as_bytes()),
+    }
+}
+
+/// Encodes a blocking timeout, in seconds, as a command argument.
+fn timeout_bytes(timeout: Duration) -> Bytes {
+    Bytes::from(timeout.as_secs_f64().to_string().into_bytes())
+}
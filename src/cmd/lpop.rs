@@ -0,0 +1,198 @@
+use crate::{Connection, Db, Frame, ListEnd, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes and returns the first values of the list stored at `key`.
+///
+/// By default, the command pops a single value from the beginning of the list
+/// and replies with it, or nil when `key` does not exist. When provided with
+/// the optional `count` argument, the reply is an array of up to `count`
+/// values, depending on the list's length.
+#[derive(Debug)]
+pub struct LPop {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Number of values to pop. `None` pops a single value.
+    count: Option<u64>,
+}
+
+/// Removes and returns the last values of the list stored at `key`.
+///
+/// Same as `LPop`, but values are popped from the tail of the list.
+#[derive(Debug)]
+pub struct RPop {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Number of values to pop. `None` pops a single value.
+    count: Option<u64>,
+}
+
+impl LPop {
+    /// Create a new `LPop` command which pops from the head of the list stored
+    /// at `key`.
+    pub fn new(key: impl ToString, count: Option<u64>) -> LPop {
+        LPop {
+            key: key.to_string(),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of values to pop
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse a `LPop` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LPOP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LPop` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// LPOP key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPop> {
+        let (key, count) = parse_pop(parse)?;
+        Ok(LPop { key, count })
+    }
+
+    /// Apply the `LPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_pop(db, dst, &self.key, self.count, ListEnd::Left).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("lpop", self.key, self.count)
+    }
+}
+
+impl RPop {
+    /// Create a new `RPop` command which pops from the tail of the list stored
+    /// at `key`.
+    pub fn new(key: impl ToString, count: Option<u64>) -> RPop {
+        RPop {
+            key: key.to_string(),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of values to pop
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse a `RPop` instance from a received frame.
+    ///
+    /// The `RPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// RPOP key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPop> {
+        let (key, count) = parse_pop(parse)?;
+        Ok(RPop { key, count })
+    }
+
+    /// Apply the `RPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_pop(db, dst, &self.key, self.count, ListEnd::Right).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `RPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("rpop", self.key, self.count)
+    }
+}
+
+/// Parses the arguments shared by the pop commands: a key optionally followed
+/// by a count.
+pub(crate) fn parse_pop(parse: &mut Parse) -> crate::Result<(String, Option<u64>)> {
+    let key = parse.next_string()?;
+
+    let count = match parse.next_int() {
+        Ok(count) => Some(count),
+        Err(ParseError::EndOfStream) => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok((key, count))
+}
+
+/// Pops values from the `end` of the list and writes them to `dst`.
+///
+/// The shape of the response depends on whether a count was requested: a
+/// single bulk value without a count and an array of values with one.
+async fn apply_pop(
+    db: &Db,
+    dst: &mut Connection,
+    key: &str,
+    count: Option<u64>,
+    end: ListEnd,
+) -> crate::Result<()> {
+    let response = match db.pop(key, end, count.unwrap_or(1) as usize) {
+        Ok(Some(values)) if count.is_some() => {
+            Frame::Array(values.into_iter().map(Frame::Bulk).collect())
+        }
+        Ok(Some(mut values)) => values.pop().map(Frame::Bulk).unwrap_or(Frame::Null),
+        Ok(None) => Frame::Null,
+        Err(err) => Frame::Error(err.to_string()),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes a pop command named `name` into a frame.
+pub(crate) fn pop_frame(name: &'static str, key: String, count: Option<u64>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    if let Some(count) = count {
+        frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+    }
+    frame
+}
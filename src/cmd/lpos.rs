@@ -0,0 +1,189 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the index of `element` in the list stored at `key`.
+///
+/// The list is scanned from the head and the index of the first match is
+/// returned, or `Null` if there is none.
+///
+/// # Options
+///
+/// * RANK `rank` -- Return the `rank`-th match instead of the first one. A
+///   negative rank scans the list from the tail, `-1` being the last match.
+///   Indexes are always counted from the head.
+/// * COUNT `num-matches` -- Return the indexes of up to `num-matches` matches
+///   as an array, `0` returning all of them.
+/// * MAXLEN `len` -- Compare at most `len` elements, `0` comparing all of
+///   them.
+#[derive(Debug)]
+pub struct LPos {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Element to look for
+    element: Bytes,
+
+    /// Rank of the first match to return, validated to not be `0` when
+    /// applied
+    rank: Option<i64>,
+
+    /// Number of matches to return, validated to not be negative
+    /// when applied
+    count: Option<i64>,
+
+    /// Number of elements to compare, validated to not be negative
+    /// when applied
+    maxlen: Option<i64>,
+}
+
+impl LPos {
+    /// Create a new `LPos` command which looks for `element` in the list
+    /// stored at `key`.
+    pub fn new(key: impl ToString, element: Bytes) -> LPos {
+        LPos {
+            key: key.to_string(),
+            element,
+            rank: None,
+            count: None,
+            maxlen: None,
+        }
+    }
+
+    /// Return the `rank`-th match.
+    pub fn rank(mut self, rank: i64) -> LPos {
+        self.rank = Some(rank);
+        self
+    }
+
+    /// Return up to `count` matches.
+    pub fn count(mut self, count: u64) -> LPos {
+        self.count = Some(count as i64);
+        self
+    }
+
+    /// Compare at most `maxlen` elements.
+    pub fn maxlen(mut self, maxlen: u64) -> LPos {
+        self.maxlen = Some(maxlen as i64);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the element looked for
+    pub fn element(&self) -> &Bytes {
+        &self.element
+    }
+
+    /// Parse a `LPos` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LPOS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LPos` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPos> {
+        let mut lpos = LPos::new(parse.next_string()?, parse.next_bytes()?);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "RANK" => lpos.rank = Some(parse.next_signed_int()?),
+                "COUNT" => lpos.count = Some(parse.next_signed_int()?),
+                "MAXLEN" => lpos.maxlen = Some(parse.next_signed_int()?),
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(lpos)
+    }
+
+    /// Apply the `LPos` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let rank = self.rank.unwrap_or(1);
+
+        let response = if rank == 0 {
+            Frame::Error(
+                "ERR RANK can't be zero: use 1 to start from the first match, \
+                 2 from the second ... or use negative to start from the end of the list"
+                    .into(),
+            )
+        } else if matches!(self.count, Some(count) if count < 0) {
+            Frame::Error("ERR COUNT can't be negative".into())
+        } else if matches!(self.maxlen, Some(maxlen) if maxlen < 0) {
+            Frame::Error("ERR MAXLEN can't be negative".into())
+        } else {
+            // Without `COUNT`, only the first match is needed.
+            let count = self.count.unwrap_or(1) as usize;
+            let maxlen = self.maxlen.unwrap_or(0) as usize;
+
+            match db.lpos(&self.key, &self.element, rank, count, maxlen) {
+                Ok(indexes) if self.count.is_some() => Frame::Array(
+                    indexes
+                        .into_iter()
+                        .map(|index| Frame::Integer(index as i64))
+                        .collect(),
+                ),
+                Ok(indexes) => match indexes.first() {
+                    Some(index) => Frame::Integer(*index as i64),
+                    None => Frame::Null,
+                },
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LPos` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lpos".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.element);
+        if let Some(rank) = self.rank {
+            frame.push_bulk(Bytes::from("rank".as_bytes()));
+            frame.push_bulk(Bytes::from(rank.to_string().into_bytes()));
+        }
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        if let Some(maxlen) = self.maxlen {
+            frame.push_bulk(Bytes::from("maxlen".as_bytes()));
+            frame.push_bulk(Bytes::from(maxlen.to_string().into_bytes()));
+        }
+        frame
+    }
+}
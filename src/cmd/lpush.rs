@@ -0,0 +1,200 @@
+use crate::{Connection, Db, Frame, ListEnd, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Insert all the specified values at the head of the list stored at `key`.
+///
+/// If `key` does not exist, it is created as an empty list before performing
+/// the push operation. Values are inserted one after the other, from the
+/// leftmost to the rightmost, so `LPUSH mylist a b c` results in a list
+/// containing `c` as first element, `b` as second element and `a` as third.
+///
+/// Integer reply: The length of the list after the push operation.
+#[derive(Debug)]
+pub struct LPush {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Values to push
+    values: Vec<Bytes>,
+}
+
+/// Insert all the specified values at the tail of the list stored at `key`.
+///
+/// Same as `LPush`, but values are appended to the tail of the list.
+///
+/// Integer reply: The length of the list after the push operation.
+#[derive(Debug)]
+pub struct RPush {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Values to push
+    values: Vec<Bytes>,
+}
+
+impl LPush {
+    /// Create a new `LPush` command which pushes `values` onto the head of the
+    /// list stored at `key`.
+    pub fn new(key: impl ToString, values: Vec<Bytes>) -> LPush {
+        LPush {
+            key: key.to_string(),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the values to push
+    pub fn values(&self) -> &[Bytes] {
+        &self.values
+    }
+
+    /// Parse a `LPush` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LPUSH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LPush` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// LPUSH key value [value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPush> {
+        let (key, values) = parse_push(parse)?;
+        Ok(LPush { key, values })
+    }
+
+    /// Apply the `LPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_push(db, dst, self.key, self.values, ListEnd::Left).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LPush` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("lpush", self.key, self.values)
+    }
+}
+
+impl RPush {
+    /// Create a new `RPush` command which pushes `values` onto the tail of the
+    /// list stored at `key`.
+    pub fn new(key: impl ToString, values: Vec<Bytes>) -> RPush {
+        RPush {
+            key: key.to_string(),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the values to push
+    pub fn values(&self) -> &[Bytes] {
+        &self.values
+    }
+
+    /// Parse a `RPush` instance from a received frame.
+    ///
+    /// The `RPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// RPUSH key value [value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPush> {
+        let (key, values) = parse_push(parse)?;
+        Ok(RPush { key, values })
+    }
+
+    /// Apply the `RPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_push(db, dst, self.key, self.values, ListEnd::Right).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `RPush` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("rpush", self.key, self.values)
+    }
+}
+
+/// Parses the arguments shared by both push commands: a key followed by one or
+/// more values.
+fn parse_push(parse: &mut Parse) -> crate::Result<(String, Vec<Bytes>)> {
+    let key = parse.next_string()?;
+    let mut values = vec![parse.next_bytes()?];
+
+    loop {
+        match parse.next_bytes() {
+            Ok(value) => values.push(value),
+            // Finish reading all the values
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok((key, values))
+}
+
+/// Pushes `values` onto the `end` of the list and writes the new length of the
+/// list to `dst`.
+async fn apply_push(
+    db: &Db,
+    dst: &mut Connection,
+    key: String,
+    values: Vec<Bytes>,
+    end: ListEnd,
+) -> crate::Result<()> {
+    let response = match db.push(key, values, end) {
+        Ok(len) => Frame::Integer(len as i64),
+        Err(err) => Frame::Error(err.to_string()),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes a push command named `name` into a frame.
+fn push_frame(name: &'static str, key: String, values: Vec<Bytes>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    for value in values {
+        frame.push_bulk(value);
+    }
+    frame
+}
@@ -0,0 +1,106 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the specified values of the list stored at `key`.
+///
+/// The offsets `start` and `stop` are zero-based indexes, with `0` being the
+/// first value of the list. They can also be negative numbers indicating
+/// offsets from the end of the list, `-1` being the last value. Both offsets
+/// are inclusive, and out of range offsets are clamped to the list bounds.
+#[derive(Debug)]
+pub struct LRange {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Index of the first value to return
+    start: i64,
+
+    /// Index of the last value to return
+    stop: i64,
+}
+
+impl LRange {
+    /// Create a new `LRange` command which fetches the values between `start`
+    /// and `stop` of the list stored at `key`.
+    pub fn new(key: impl ToString, start: i64, stop: i64) -> LRange {
+        LRange {
+            key: key.to_string(),
+            start,
+            stop,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start index
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the stop index
+    pub fn stop(&self) -> i64 {
+        self.stop
+    }
+
+    /// Parse a `LRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LRange` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LRANGE key start stop
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        Ok(LRange { key, start, stop })
+    }
+
+    /// Apply the `LRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lrange(&self.key, self.start, self.stop) {
+            Ok(values) => Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LRange` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(self.stop.to_string().into_bytes()));
+        frame
+    }
+}
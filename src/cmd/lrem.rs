@@ -0,0 +1,107 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes the first `count` values equal to `element` from the list stored
+/// at `key`.
+///
+/// * `count > 0` removes values moving from the head to the tail.
+/// * `count < 0` removes values moving from the tail to the head.
+/// * `count = 0` removes all the values equal to `element`.
+///
+/// Integer reply: the number of removed values.
+#[derive(Debug)]
+pub struct LRem {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Number of values to remove, and direction to remove them in
+    count: i64,
+
+    /// Value to remove
+    element: Bytes,
+}
+
+impl LRem {
+    /// Create a new `LRem` command which removes `count` values equal to
+    /// `element` from the list stored at `key`.
+    pub fn new(key: impl ToString, count: i64, element: Bytes) -> LRem {
+        LRem {
+            key: key.to_string(),
+            count,
+            element,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the count
+    pub fn count(&self) -> i64 {
+        self.count
+    }
+
+    /// Parse a `LRem` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LREM` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LRem` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LREM key count element
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LRem> {
+        let key = parse.next_string()?;
+        let count = parse.next_signed_int()?;
+        let element = parse.next_bytes()?;
+
+        Ok(LRem {
+            key,
+            count,
+            element,
+        })
+    }
+
+    /// Apply the `LRem` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lrem(&self.key, self.count, &self.element) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LRem` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lrem".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.count.to_string().into_bytes()));
+        frame.push_bulk(self.element);
+        frame
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Sets the value at `index` of the list stored at `key` to `element`.
+///
+/// Negative indexes count from the end of the list, `-1` being the last
+/// value. An error is returned if `key` does not exist or if `index` is out of
+/// range.
+#[derive(Debug)]
+pub struct LSet {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Index of the value to set
+    index: i64,
+
+    /// Value to set
+    element: Bytes,
+}
+
+impl LSet {
+    /// Create a new `LSet` command which sets the value at `index` of the list
+    /// stored at `key` to `element`.
+    pub fn new(key: impl ToString, index: i64, element: Bytes) -> LSet {
+        LSet {
+            key: key.to_string(),
+            index,
+            element,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the index
+    pub fn index(&self) -> i64 {
+        self.index
+    }
+
+    /// Parse a `LSet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LSET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LSet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LSET key index element
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LSet> {
+        let key = parse.next_string()?;
+        let index = parse.next_signed_int()?;
+        let element = parse.next_bytes()?;
+
+        Ok(LSet {
+            key,
+            index,
+            element,
+        })
+    }
+
+    /// Apply the `LSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lset(&self.key, self.index, self.element) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lset".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.index.to_string().into_bytes()));
+        frame.push_bulk(self.element);
+        frame
+    }
+}
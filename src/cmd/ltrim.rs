@@ -0,0 +1,96 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Trims the list stored at `key` so that it only contains the values between
+/// the `start` and `stop` indexes, both inclusive.
+///
+/// Indexes are interpreted as with `LRANGE`. If the range is empty, the key is
+/// removed. Pushing values and then trimming the list caps its length, which
+/// is how a list is used as a ring buffer.
+#[derive(Debug)]
+pub struct LTrim {
+    /// Name of the key holding the list
+    key: String,
+
+    /// Index of the first value to keep
+    start: i64,
+
+    /// Index of the last value to keep
+    stop: i64,
+}
+
+impl LTrim {
+    /// Create a new `LTrim` command which trims the list stored at `key` to
+    /// the values between `start` and `stop`.
+    pub fn new(key: impl ToString, start: i64, stop: i64) -> LTrim {
+        LTrim {
+            key: key.to_string(),
+            start,
+            stop,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `LTrim` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LTRIM` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `LTrim` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LTRIM key start stop
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LTrim> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        Ok(LTrim { key, start, stop })
+    }
+
+    /// Apply the `LTrim` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.ltrim(&self.key, self.start, self.stop) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LTrim` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("ltrim".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(self.stop.to_string().into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,178 @@
+use crate::db::MEMORY_SAMPLES;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Reports the memory used by the keys.
+///
+/// # Subcommands
+///
+/// * USAGE `key` [SAMPLES `count`] -- Returns the approximate number of bytes
+///   used by `key` and its value, or nil if it does not exist. The size of
+///   the elements of a collection is extrapolated from the first `count` of
+///   them, 5 by default, all of them being measured if `count` is 0.
+/// * STATS -- Returns the breakdown of the memory used by the databases: the
+///   peak and current memory used, the overhead of the maps of the keys and
+///   of the expirations of each database, and the number of bytes per key.
+#[derive(Debug)]
+pub struct Memory {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Usage { key: String, samples: Option<u64> },
+    Stats,
+}
+
+impl Memory {
+    /// Create a new `Memory` command returning the number of bytes used by
+    /// `key`, sampling `samples` elements if it holds a collection.
+    pub fn usage(key: impl ToString, samples: Option<u64>) -> Memory {
+        Memory {
+            subcommand: Subcommand::Usage {
+                key: key.to_string(),
+                samples,
+            },
+        }
+    }
+
+    /// Create a new `Memory` command returning the breakdown of the memory
+    /// used by the databases.
+    pub fn stats() -> Memory {
+        Memory {
+            subcommand: Subcommand::Stats,
+        }
+    }
+
+    /// Get the key measured by `MEMORY USAGE`, if this is the subcommand.
+    pub fn key(&self) -> Option<&str> {
+        match &self.subcommand {
+            Subcommand::Usage { key, .. } => Some(key),
+            Subcommand::Stats => None,
+        }
+    }
+
+    /// Parse a `Memory` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MEMORY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Memory` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// MEMORY USAGE key [SAMPLES count]
+    /// MEMORY STATS
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Memory> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "USAGE" => {
+                let key = parse.next_string()?;
+                let samples = match parse.next_string() {
+                    Ok(s) if s.to_uppercase() == "SAMPLES" => Some(parse.next_int()?),
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(ParseError::EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+                Subcommand::Usage { key, samples }
+            }
+            "STATS" => Subcommand::Stats,
+            _ => return Err(format!("unknown `MEMORY` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Memory { subcommand })
+    }
+
+    /// Apply the `Memory` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Usage { key, samples } => {
+                let samples = samples.map_or(MEMORY_SAMPLES, |samples| samples as usize);
+                match db.memory_usage(&key, samples) {
+                    Some(size) => Frame::Integer(size as i64),
+                    None => Frame::Null,
+                }
+            }
+            Subcommand::Stats => {
+                let stats = db.memory_stats();
+                let field = |name: &str| Frame::Bulk(Bytes::from(name.to_string().into_bytes()));
+
+                let mut fields = vec![
+                    (field("peak.allocated"), Frame::Integer(stats.peak as i64)),
+                    (field("total.allocated"), Frame::Integer(stats.total as i64)),
+                ];
+                for (index, main, expires) in stats.databases {
+                    fields.push((
+                        field(&format!("db.{}", index)),
+                        Frame::Map(vec![
+                            (
+                                field("overhead.hashtable.main"),
+                                Frame::Integer(main as i64),
+                            ),
+                            (
+                                field("overhead.hashtable.expires"),
+                                Frame::Integer(expires as i64),
+                            ),
+                        ]),
+                    ));
+                }
+                let dataset = stats.total.saturating_sub(stats.overhead);
+                fields.extend(vec![
+                    (
+                        field("overhead.total"),
+                        Frame::Integer(stats.overhead as i64),
+                    ),
+                    (field("keys.count"), Frame::Integer(stats.keys as i64)),
+                    (
+                        field("keys.bytes-per-key"),
+                        Frame::Integer((stats.total / stats.keys.max(1)) as i64),
+                    ),
+                    (field("dataset.bytes"), Frame::Integer(dataset as i64)),
+                ]);
+                Frame::Map(fields)
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Memory` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("memory".as_bytes()));
+        match self.subcommand {
+            Subcommand::Usage { key, samples } => {
+                frame.push_bulk(Bytes::from("usage".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+                if let Some(samples) = samples {
+                    frame.push_bulk(Bytes::from("samples".as_bytes()));
+                    frame.push_int(samples as i64);
+                }
+            }
+            Subcommand::Stats => frame.push_bulk(Bytes::from("stats".as_bytes())),
+        }
+        frame
+    }
+}
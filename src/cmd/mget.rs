@@ -0,0 +1,91 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the values of all the specified keys.
+///
+/// For every key that does not hold a string value or does not exist, the
+/// special value nil is returned. Because of this, the operation never fails.
+#[derive(Debug)]
+pub struct MGet {
+    /// Names of the keys to get
+    keys: Vec<String>,
+}
+
+impl MGet {
+    /// Create a new `MGet` command which fetches the values of `keys`.
+    pub fn new(keys: Vec<String>) -> MGet {
+        MGet { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `MGet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MGET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `MGet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// MGET key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MGet> {
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(MGet { keys })
+    }
+
+    /// Apply the `MGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Array(
+            db.mget(&self.keys)
+                .into_iter()
+                .map(|value| value.map(Frame::Bulk).unwrap_or(Frame::Null))
+                .collect(),
+        );
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `MGet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("mget".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
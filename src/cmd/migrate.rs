@@ -0,0 +1,295 @@
+use crate::cmd::{Auth, Restore, Select};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tokio::net::TcpStream;
+use tokio::time::{self, Duration};
+use tracing::{debug, instrument};
+
+/// Transfer keys to the database `db` of another server.
+///
+/// The keys are serialized as by `DUMP`, then stored on the target server with
+/// `RESTORE`, along with their time to live, through a connection the server
+/// opens. Once stored, they are removed, unless the `COPY` option is given.
+/// Keys modified while they were being transferred are not removed.
+///
+/// The keys are given either as `key`, or as the `KEYS` option, `key` being
+/// the empty string. `timeout` is the maximum time, in milliseconds, to
+/// connect to the target server and to wait for each of its replies. The
+/// `REPLACE` option overwrites the keys existing on the target server, which
+/// are otherwise kept, the target server replying with an error. The `AUTH`
+/// and `AUTH2` options authenticate the connection to the target server.
+///
+/// Simple string reply: `OK` on success, `NOKEY` if none of the keys exists.
+#[derive(Debug)]
+pub struct Migrate {
+    /// Host of the target server
+    host: String,
+
+    /// Port of the target server
+    port: u16,
+
+    /// Keys to transfer
+    keys: Vec<String>,
+
+    /// Index of the database of the target server to store the keys in
+    db: u64,
+
+    /// Timeout of the connection to the target server, in milliseconds
+    timeout: u64,
+
+    /// Keep the keys once transferred
+    copy: bool,
+
+    /// Overwrite the keys existing on the target server
+    replace: bool,
+
+    /// Username, if any, and password authenticating the connection to the
+    /// target server
+    auth: Option<(Option<String>, String)>,
+}
+
+impl Migrate {
+    /// Create a new `Migrate` command which transfers `keys` to the database
+    /// `db` of the server at `host` and `port`, with a timeout of `timeout`
+    /// milliseconds.
+    pub fn new(
+        host: impl ToString,
+        port: u16,
+        keys: Vec<String>,
+        db: u64,
+        timeout: u64,
+    ) -> Migrate {
+        Migrate {
+            host: host.to_string(),
+            port,
+            keys,
+            db,
+            timeout,
+            copy: false,
+            replace: false,
+            auth: None,
+        }
+    }
+
+    /// Keep the keys once transferred when `copy` is set.
+    pub fn copy(mut self, copy: bool) -> Migrate {
+        self.copy = copy;
+        self
+    }
+
+    /// Overwrite the keys existing on the target server when `replace` is
+    /// set.
+    pub fn replace(mut self, replace: bool) -> Migrate {
+        self.replace = replace;
+        self
+    }
+
+    /// Authenticate the connection to the target server as `username`, the
+    /// default user if `None`, with `password`.
+    pub fn auth(mut self, username: Option<String>, password: impl ToString) -> Migrate {
+        self.auth = Some((username, password.to_string()));
+        self
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `Migrate` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MIGRATE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Migrate` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least six entries.
+    ///
+    /// ```text
+    /// MIGRATE host port key|"" destination-db timeout [COPY] [REPLACE]
+    ///     [AUTH password | AUTH2 username password] [KEYS key [key ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Migrate> {
+        let host = parse.next_string()?;
+        let port = u16::try_from(parse.next_int()?)
+            .map_err(|_| "ERR value is not an integer or out of range")?;
+        let key = parse.next_string()?;
+        let db = parse.next_int()?;
+        let timeout = parse.next_int()?;
+        let mut migrate = Migrate::new(host, port, vec![], db, timeout);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "COPY" => migrate.copy = true,
+                "REPLACE" => migrate.replace = true,
+                "AUTH" => migrate.auth = Some((None, parse.next_string()?)),
+                "AUTH2" => {
+                    let username = parse.next_string()?;
+                    migrate.auth = Some((Some(username), parse.next_string()?));
+                }
+                "KEYS" => {
+                    if !key.is_empty() {
+                        return Err("ERR When using MIGRATE KEYS option, the key argument \
+                                    must be set to the empty string"
+                            .into());
+                    }
+
+                    // The keys are the last arguments.
+                    loop {
+                        match parse.next_string() {
+                            Ok(key) => migrate.keys.push(key),
+                            Err(ParseError::EndOfStream) => break,
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        if migrate.keys.is_empty() {
+            migrate.keys.push(key);
+        }
+
+        Ok(migrate)
+    }
+
+    /// Apply the `Migrate` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let migration = db.begin_migration(&self.keys);
+
+        let response = if migration.values().is_empty() {
+            Frame::Simple("NOKEY".to_string())
+        } else {
+            let (migrated, response) = self.transfer(migration.values()).await;
+            let removed = if self.copy { vec![] } else { migrated };
+            db.end_migration(migration, &removed);
+            response
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Store `values`, the keys along with their serialized value and time to
+    /// live, on the target server.
+    ///
+    /// Returns the keys stored, and the response to the command.
+    async fn transfer(&self, values: &[(String, Bytes, u64)]) -> (Vec<String>, Frame) {
+        // As in Redis, a timeout of 0 is a second.
+        let timeout = match self.timeout {
+            0 => Duration::from_secs(1),
+            timeout => Duration::from_millis(timeout),
+        };
+        let io_error = |what| Frame::Error(format!("IOERR error or timeout {}", what));
+
+        let addr = (self.host.as_str(), self.port);
+        let socket = match time::timeout(timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(socket)) => socket,
+            _ => return (vec![], io_error("connecting to the client")),
+        };
+        let mut connection = Connection::new(socket);
+
+        let mut frames = vec![];
+        if let Some((username, password)) = &self.auth {
+            frames.push(Auth::new(username.clone(), password).into_frame());
+        }
+        frames.push(Select::new(self.db).into_frame());
+
+        // The replies to the commands preceding the `RESTORE` commands.
+        let skipped = frames.len();
+
+        for (key, payload, ttl) in values {
+            let restore = Restore::new(key, *ttl as i64, payload.clone()).replace(self.replace);
+            frames.push(restore.into_frame());
+        }
+
+        for frame in &frames {
+            match time::timeout(timeout, connection.write_frame(frame)).await {
+                Ok(Ok(())) => {}
+                _ => return (vec![], io_error("writing to target instance")),
+            }
+        }
+
+        let mut migrated = vec![];
+        let mut error = None;
+        for i in 0..frames.len() {
+            let reply = match time::timeout(timeout, connection.read_frame()).await {
+                Ok(Ok(Some(reply))) => reply,
+                _ => return (migrated, io_error("reading to target instance")),
+            };
+
+            match reply {
+                Frame::Error(err) => {
+                    error.get_or_insert(err);
+                }
+                _ if i >= skipped => migrated.push(values[i - skipped].0.clone()),
+                _ => {}
+            }
+        }
+
+        let response = match error {
+            Some(err) => Frame::Error(format!("ERR Target instance replied with error: {}", err)),
+            None => Frame::Simple("OK".to_string()),
+        };
+        (migrated, response)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Migrate` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("migrate".as_bytes()));
+        frame.push_bulk(Bytes::from(self.host.into_bytes()));
+        frame.push_int(self.port as i64);
+        frame.push_bulk(Bytes::new());
+        frame.push_int(self.db as i64);
+        frame.push_int(self.timeout as i64);
+        if self.copy {
+            frame.push_bulk(Bytes::from("copy".as_bytes()));
+        }
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        match self.auth {
+            Some((Some(username), password)) => {
+                frame.push_bulk(Bytes::from("auth2".as_bytes()));
+                frame.push_bulk(Bytes::from(username.into_bytes()));
+                frame.push_bulk(Bytes::from(password.into_bytes()));
+            }
+            Some((None, password)) => {
+                frame.push_bulk(Bytes::from("auth".as_bytes()));
+                frame.push_bulk(Bytes::from(password.into_bytes()));
+            }
+            None => {}
+        }
+        frame.push_bulk(Bytes::from("keys".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
@@ -1,35 +1,609 @@
+mod geoadd;
+pub use crate::db::{GeoOrigin, GeoShape, GeoUnit};
+pub use geoadd::GeoAdd;
+
+mod geodist;
+pub use geodist::GeoDist;
+
+mod geopos;
+pub use geopos::GeoPos;
+
+mod geosearch;
+pub use geosearch::GeoSearch;
+
 mod get;
 pub use get::Get;
 
 mod publish;
-pub use publish::Publish;
+pub use publish::{Publish, SPublish};
+
+mod pubsub;
+pub use pubsub::PubSub;
 
 mod set;
+pub use crate::db::SetCondition;
 pub use set::Set;
 
 mod subscribe;
-pub use subscribe::{Subscribe, Unsubscribe};
+pub use subscribe::{PSubscribe, PUnsubscribe, SSubscribe, SUnsubscribe, Subscribe, Unsubscribe};
+
+mod acl;
+pub use acl::Acl;
+
+mod append;
+pub use append::Append;
+
+mod auth;
+pub use auth::Auth;
+
+mod bgrewriteaof;
+pub use bgrewriteaof::BgRewriteAof;
+
+mod bitcount;
+pub use crate::db::BitUnit;
+pub use bitcount::BitCount;
+
+mod bitfield;
+pub use crate::db::Overflow;
+pub use bitfield::{BitField, BitFieldOp};
+
+mod bitop;
+pub use crate::db::BitOperation;
+pub use bitop::BitOp;
+
+mod bitpos;
+pub use bitpos::BitPos;
+
+mod bpop;
+pub use bpop::{BLPop, BRPop};
+
+mod bzpop;
+pub use bzpop::{BZPopMax, BZPopMin};
+
+mod client;
+pub use client::{Client, ClientReply};
+
+mod cluster;
+pub use cluster::{Asking, Cluster};
+
+mod command;
+pub use command::Commands;
+
+mod config;
+pub use config::Config;
+
+mod copy;
+pub use copy::Copy;
+
+mod dbsize;
+pub use dbsize::DbSize;
+
+mod debug;
+pub use debug::Debug;
 
 mod del;
 pub use del::Del;
 
+mod dump;
+pub use dump::Dump;
+
+mod eval;
+pub use eval::{Eval, EvalSha};
+
+mod exists;
+pub use exists::Exists;
+
+mod expire;
+pub use crate::db::ExpireOption;
+pub use expire::{Expire, ExpireAt, PExpire, PExpireAt};
+
+mod fcall;
+pub use fcall::{FCall, FCallRo};
+
+mod flush;
+pub use flush::{FlushAll, FlushDb};
+
+mod function;
+pub use function::Function;
+
+mod getbit;
+pub use getbit::GetBit;
+
+mod getdel;
+pub use getdel::GetDel;
+
+mod getex;
+pub use getex::GetEx;
+
+mod getrange;
+pub use getrange::GetRange;
+
+mod hdel;
+pub use hdel::HDel;
+
+mod hello;
+pub use hello::Hello;
+
+mod hget;
+pub use hget::HGet;
+
+mod hgetall;
+pub use hgetall::HGetAll;
+
+mod hincrby;
+pub use hincrby::HIncrBy;
+
+mod hincrbyfloat;
+pub use hincrbyfloat::HIncrByFloat;
+
+mod hmget;
+pub use hmget::HMGet;
+
+mod hrandfield;
+pub use hrandfield::HRandField;
+
+mod hset;
+pub use hset::HSet;
+
+mod hsetnx;
+pub use hsetnx::HSetNx;
+
+mod incr;
+pub use incr::{Decr, DecrBy, Incr, IncrBy};
+
+mod incrbyfloat;
+pub use incrbyfloat::IncrByFloat;
+
+mod key_type;
+pub use key_type::Type;
+
+mod info;
+pub use info::Info;
+
+mod keys;
+pub use keys::Keys;
+
+mod latency;
+pub use latency::Latency;
+
+mod lcs;
+pub use lcs::Lcs;
+
+mod linsert;
+pub use crate::db::InsertPosition;
+pub use linsert::LInsert;
+
+mod llen;
+pub use llen::LLen;
+
+mod lmove;
+pub use crate::db::ListEnd;
+pub use lmove::{BLMove, BRPopLPush, LMove, RPopLPush};
+
+mod lpos;
+pub use lpos::LPos;
+
+mod lpop;
+pub use lpop::{LPop, RPop};
+
+mod lpush;
+pub use lpush::{LPush, RPush};
+
+mod lrange;
+pub use lrange::LRange;
+
+mod lrem;
+pub use lrem::LRem;
+
+mod lset;
+pub use lset::LSet;
+
+mod ltrim;
+pub use ltrim::LTrim;
+
+mod memory;
+pub use memory::Memory;
+
+mod mget;
+pub use mget::MGet;
+
+mod migrate;
+pub use migrate::Migrate;
+
+mod mset;
+pub use mset::{MSet, MSetNx};
+
+mod monitor;
+pub use monitor::Monitor;
+
+mod move_key;
+pub use move_key::Move;
+
+mod persist;
+pub use persist::Persist;
+
+mod pfadd;
+pub use pfadd::PfAdd;
+
+mod pfcount;
+pub use pfcount::PfCount;
+
+mod pfmerge;
+pub use pfmerge::PfMerge;
+
+mod psync;
+pub use psync::PSync;
+
+mod randomkey;
+pub use randomkey::RandomKey;
+
+mod rename;
+pub use rename::{Rename, RenameNx};
+
+mod replconf;
+pub use replconf::ReplConf;
+
+mod replicaof;
+pub use replicaof::ReplicaOf;
+
+mod reset;
+pub use reset::Reset;
+
+mod restore;
+pub use restore::Restore;
+
+mod role;
+pub use role::Role;
+
+mod sadd;
+pub use sadd::SAdd;
+
+mod save;
+pub use save::{BgSave, LastSave, Save};
+
+mod scan;
+pub use scan::{HScan, SScan, Scan, ZScan};
+
+mod script;
+pub use script::Script;
+
+mod select;
+pub use select::Select;
+
+mod scard;
+pub use scard::SCard;
+
+mod setbit;
+pub use setbit::SetBit;
+
+mod setex;
+pub use setex::{PSetEx, SetEx};
+
+mod setnx;
+pub use setnx::SetNx;
+
+mod setop;
+pub use setop::{SDiff, SDiffStore, SInter, SInterStore, SUnion, SUnionStore};
+
+mod setrange;
+pub use setrange::SetRange;
+
+mod shutdown;
+pub use shutdown::ShutdownServer;
+
+mod sintercard;
+pub use sintercard::SInterCard;
+
+mod sismember;
+pub use sismember::SIsMember;
+
+mod slowlog;
+pub use slowlog::Slowlog;
+
+mod smismember;
+pub use smismember::SMIsMember;
+
+mod smembers;
+pub use smembers::SMembers;
+
+mod sort;
+pub use sort::Sort;
+
+mod spop;
+pub use spop::SPop;
+
+mod srandmember;
+pub use srandmember::SRandMember;
+
+mod srem;
+pub use srem::SRem;
+
+mod strlen;
+pub use strlen::StrLen;
+
+mod swapdb;
+pub use swapdb::SwapDb;
+
+mod touch;
+pub use touch::Touch;
+
+mod ttl;
+pub use ttl::{PTtl, Ttl};
+
+mod transaction;
+pub use transaction::{Discard, Exec, Multi};
+
+mod unlink;
+pub use unlink::Unlink;
+
+mod wait;
+pub use wait::Wait;
+
+mod watch;
+pub use watch::{Unwatch, Watch};
+
+mod xack;
+pub use xack::XAck;
+
+mod xadd;
+pub use xadd::XAdd;
+
+mod xclaim;
+pub use xclaim::XClaim;
+
+mod xdel;
+pub use xdel::XDel;
+
+mod xgroup;
+pub use xgroup::XGroup;
+
+mod xlen;
+pub use xlen::XLen;
+
+mod xpending;
+pub use xpending::XPending;
+
+mod xrange;
+pub use xrange::XRange;
+
+mod xread;
+pub use xread::XRead;
+
+mod xreadgroup;
+pub use xreadgroup::XReadGroup;
+
+mod xtrim;
+pub use xtrim::XTrim;
+
+mod zadd;
+pub use crate::db::ZAddOption;
+pub use zadd::ZAdd;
+
+mod zcount;
+pub use zcount::ZCount;
+
+mod zincrby;
+pub use zincrby::ZIncrBy;
+
+mod zpop;
+pub use crate::db::ScoreEnd;
+pub use zpop::{ZPopMax, ZPopMin};
+
+mod zrandmember;
+pub use zrandmember::ZRandMember;
+
+mod zrange;
+pub use zrange::ZRange;
+
+mod zrangebylex;
+pub use zrangebylex::ZRangeByLex;
+
+mod zrangebyscore;
+pub use zrangebyscore::ZRangeByScore;
+
+mod zrank;
+pub use zrank::ZRank;
+
+mod zrem;
+pub use zrem::ZRem;
+
+mod zscore;
+pub use zscore::ZScore;
+
+mod blocking;
+
 mod unknown;
 pub use unknown::Unknown;
 
 use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
 
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
 /// Enumeration of supported Redis commands.
 ///
 /// Methods called on `Command` are delegated to the command implementation.
 #[derive(Debug)]
 pub enum Command {
+    Acl(Acl),
+    Append(Append),
+    Asking(Asking),
+    Auth(Auth),
+    BLMove(BLMove),
+    BLPop(BLPop),
+    BRPop(BRPop),
+    BRPopLPush(BRPopLPush),
+    BZPopMax(BZPopMax),
+    BZPopMin(BZPopMin),
+    BgRewriteAof(BgRewriteAof),
+    BgSave(BgSave),
+    BitCount(BitCount),
+    BitField(BitField),
+    BitOp(BitOp),
+    BitPos(BitPos),
+    Client(Client),
+    Cluster(Cluster),
+    Commands(Commands),
+    Config(Config),
+    Copy(Copy),
+    DbSize(DbSize),
+    Debug(Debug),
+    Decr(Decr),
+    DecrBy(DecrBy),
     Del(Del),
+    Exists(Exists),
+    Expire(Expire),
+    Discard(Discard),
+    Dump(Dump),
+    Eval(Eval),
+    EvalSha(EvalSha),
+    Exec(Exec),
+    ExpireAt(ExpireAt),
+    FCall(FCall),
+    FCallRo(FCallRo),
+    FlushAll(FlushAll),
+    FlushDb(FlushDb),
+    Function(Function),
+    GeoAdd(GeoAdd),
+    GeoDist(GeoDist),
+    GeoPos(GeoPos),
+    GeoSearch(GeoSearch),
     Get(Get),
+    GetBit(GetBit),
+    GetDel(GetDel),
+    GetEx(GetEx),
+    GetRange(GetRange),
+    HDel(HDel),
+    HGet(HGet),
+    HGetAll(HGetAll),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HMGet(HMGet),
+    HRandField(HRandField),
+    HScan(HScan),
+    HSet(HSet),
+    HSetNx(HSetNx),
+    Hello(Hello),
+    Incr(Incr),
+    IncrBy(IncrBy),
+    IncrByFloat(IncrByFloat),
+    Info(Info),
+    Keys(Keys),
+    LastSave(LastSave),
+    Latency(Latency),
+    Lcs(Lcs),
+    LInsert(LInsert),
+    LLen(LLen),
+    LMove(LMove),
+    LPop(LPop),
+    LPos(LPos),
+    LPush(LPush),
+    LRange(LRange),
+    LRem(LRem),
+    LSet(LSet),
+    LTrim(LTrim),
+    Memory(Memory),
+    MGet(MGet),
+    Migrate(Migrate),
+    MSet(MSet),
+    MSetNx(MSetNx),
+    Multi(Multi),
+    PExpire(PExpire),
+    PExpireAt(PExpireAt),
+    PSetEx(PSetEx),
+    PSubscribe(PSubscribe),
+    PSync(PSync),
+    PTtl(PTtl),
+    PUnsubscribe(PUnsubscribe),
+    Monitor(Monitor),
+    Move(Move),
+    Persist(Persist),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    PubSub(PubSub),
     Publish(Publish),
+    RPop(RPop),
+    RPopLPush(RPopLPush),
+    RPush(RPush),
+    RandomKey(RandomKey),
+    Rename(Rename),
+    RenameNx(RenameNx),
+    ReplConf(ReplConf),
+    ReplicaOf(ReplicaOf),
+    Reset(Reset),
+    Restore(Restore),
+    Role(Role),
+    SAdd(SAdd),
+    SCard(SCard),
+    SDiff(SDiff),
+    SDiffStore(SDiffStore),
+    SInter(SInter),
+    SInterCard(SInterCard),
+    SInterStore(SInterStore),
+    SIsMember(SIsMember),
+    Slowlog(Slowlog),
+    SMIsMember(SMIsMember),
+    SMembers(SMembers),
+    Sort(Sort),
+    SPop(SPop),
+    SPublish(SPublish),
+    SRandMember(SRandMember),
+    SRem(SRem),
+    SScan(SScan),
+    SSubscribe(SSubscribe),
+    SUnion(SUnion),
+    SUnionStore(SUnionStore),
+    SUnsubscribe(SUnsubscribe),
+    SwapDb(SwapDb),
+    Save(Save),
+    Scan(Scan),
+    Script(Script),
+    Select(Select),
     Set(Set),
+    SetBit(SetBit),
+    SetEx(SetEx),
+    SetNx(SetNx),
+    SetRange(SetRange),
+    Shutdown(ShutdownServer),
+    StrLen(StrLen),
     Subscribe(Subscribe),
+    Touch(Touch),
+    Ttl(Ttl),
+    Type(Type),
+    Unlink(Unlink),
     Unsubscribe(Unsubscribe),
+    Unwatch(Unwatch),
+    Wait(Wait),
+    Watch(Watch),
     Unknown(Unknown),
+    XAck(XAck),
+    XAdd(XAdd),
+    XClaim(XClaim),
+    XDel(XDel),
+    XGroup(XGroup),
+    XLen(XLen),
+    XPending(XPending),
+    XRange(XRange),
+    XRead(XRead),
+    XReadGroup(XReadGroup),
+    XTrim(XTrim),
+    ZAdd(ZAdd),
+    ZCount(ZCount),
+    ZIncrBy(ZIncrBy),
+    ZPopMax(ZPopMax),
+    ZPopMin(ZPopMin),
+    ZRandMember(ZRandMember),
+    ZRange(ZRange),
+    ZRangeByLex(ZRangeByLex),
+    ZRangeByScore(ZRangeByScore),
+    ZRank(ZRank),
+    ZRem(ZRem),
+    ZScan(ZScan),
+    ZScore(ZScore),
 }
 
 impl Command {
@@ -42,6 +616,14 @@ impl Command {
     ///
     /// On success, the command value is returned, otherwise, `Err` is returned.
     pub fn from_frame(frame: Frame) -> crate::Result<Command> {
+        Command::from_renamed_frame(frame, &Renames::default())
+    }
+
+    /// Parse a command from a frame received from a client, the commands
+    /// being renamed by `renames`.
+    ///
+    /// Behaves like `from_frame` otherwise.
+    pub(crate) fn from_renamed_frame(frame: Frame, renames: &Renames) -> crate::Result<Command> {
         // The frame  value is decorated with `Parse`. `Parse` provides a
         // "cursor" like API which makes parsing the command easier.
         //
@@ -54,15 +636,190 @@ impl Command {
         // matching.
         let command_name = parse.next_string()?.to_lowercase();
 
+        // Renamed commands are only known by their new name.
+        let command_name = match renames.resolve(&command_name) {
+            Some(name) => name.to_string(),
+            None => return Ok(Command::Unknown(Unknown::new(command_name))),
+        };
+
         // Match the command name, delegating the rest of the parsing to the
         // specific command.
         let command = match &command_name[..] {
+            "acl" => Command::Acl(Acl::parse_frames(&mut parse)?),
+            "append" => Command::Append(Append::parse_frames(&mut parse)?),
+            "asking" => Command::Asking(Asking::parse_frames(&mut parse)?),
+            "auth" => Command::Auth(Auth::parse_frames(&mut parse)?),
+            "bgrewriteaof" => Command::BgRewriteAof(BgRewriteAof::parse_frames(&mut parse)?),
+            "bgsave" => Command::BgSave(BgSave::parse_frames(&mut parse)?),
+            "bitcount" => Command::BitCount(BitCount::parse_frames(&mut parse)?),
+            "bitfield" => Command::BitField(BitField::parse_frames(&mut parse)?),
+            "bitop" => Command::BitOp(BitOp::parse_frames(&mut parse)?),
+            "bitpos" => Command::BitPos(BitPos::parse_frames(&mut parse)?),
+            "blmove" => Command::BLMove(BLMove::parse_frames(&mut parse)?),
+            "blpop" => Command::BLPop(BLPop::parse_frames(&mut parse)?),
+            "brpop" => Command::BRPop(BRPop::parse_frames(&mut parse)?),
+            "brpoplpush" => Command::BRPopLPush(BRPopLPush::parse_frames(&mut parse)?),
+            "bzpopmax" => Command::BZPopMax(BZPopMax::parse_frames(&mut parse)?),
+            "bzpopmin" => Command::BZPopMin(BZPopMin::parse_frames(&mut parse)?),
+            "client" => Command::Client(Client::parse_frames(&mut parse)?),
+            "cluster" => Command::Cluster(Cluster::parse_frames(&mut parse)?),
+            "command" => Command::Commands(Commands::parse_frames(&mut parse)?),
+            "config" => Command::Config(Config::parse_frames(&mut parse)?),
+            "copy" => Command::Copy(Copy::parse_frames(&mut parse)?),
+            "dbsize" => Command::DbSize(DbSize::parse_frames(&mut parse)?),
+            "debug" => Command::Debug(Debug::parse_frames(&mut parse)?),
+            "decr" => Command::Decr(Decr::parse_frames(&mut parse)?),
+            "decrby" => Command::DecrBy(DecrBy::parse_frames(&mut parse)?),
             "del" => Command::Del(Del::parse_frames(&mut parse)?),
+            "exists" => Command::Exists(Exists::parse_frames(&mut parse)?),
+            "expire" => Command::Expire(Expire::parse_frames(&mut parse)?),
+            "discard" => Command::Discard(Discard::parse_frames(&mut parse)?),
+            "dump" => Command::Dump(Dump::parse_frames(&mut parse)?),
+            "eval" => Command::Eval(Eval::parse_frames(&mut parse)?),
+            "evalsha" => Command::EvalSha(EvalSha::parse_frames(&mut parse)?),
+            "exec" => Command::Exec(Exec::parse_frames(&mut parse)?),
+            "expireat" => Command::ExpireAt(ExpireAt::parse_frames(&mut parse)?),
+            "fcall" => Command::FCall(FCall::parse_frames(&mut parse)?),
+            "fcall_ro" => Command::FCallRo(FCallRo::parse_frames(&mut parse)?),
+            "flushall" => Command::FlushAll(FlushAll::parse_frames(&mut parse)?),
+            "flushdb" => Command::FlushDb(FlushDb::parse_frames(&mut parse)?),
+            "function" => Command::Function(Function::parse_frames(&mut parse)?),
+            "geoadd" => Command::GeoAdd(GeoAdd::parse_frames(&mut parse)?),
+            "geodist" => Command::GeoDist(GeoDist::parse_frames(&mut parse)?),
+            "geopos" => Command::GeoPos(GeoPos::parse_frames(&mut parse)?),
+            "geosearch" => Command::GeoSearch(GeoSearch::parse_frames(&mut parse)?),
             "get" => Command::Get(Get::parse_frames(&mut parse)?),
+            "getbit" => Command::GetBit(GetBit::parse_frames(&mut parse)?),
+            "getdel" => Command::GetDel(GetDel::parse_frames(&mut parse)?),
+            "getex" => Command::GetEx(GetEx::parse_frames(&mut parse)?),
+            "getrange" => Command::GetRange(GetRange::parse_frames(&mut parse)?),
+            "hdel" => Command::HDel(HDel::parse_frames(&mut parse)?),
+            "hello" => Command::Hello(Hello::parse_frames(&mut parse)?),
+            "hget" => Command::HGet(HGet::parse_frames(&mut parse)?),
+            "hgetall" => Command::HGetAll(HGetAll::parse_frames(&mut parse)?),
+            "hincrby" => Command::HIncrBy(HIncrBy::parse_frames(&mut parse)?),
+            "hincrbyfloat" => Command::HIncrByFloat(HIncrByFloat::parse_frames(&mut parse)?),
+            "hmget" => Command::HMGet(HMGet::parse_frames(&mut parse)?),
+            "hscan" => Command::HScan(HScan::parse_frames(&mut parse)?),
+            "hrandfield" => Command::HRandField(HRandField::parse_frames(&mut parse)?),
+            "hset" => Command::HSet(HSet::parse_frames(&mut parse)?),
+            "hsetnx" => Command::HSetNx(HSetNx::parse_frames(&mut parse)?),
+            "incr" => Command::Incr(Incr::parse_frames(&mut parse)?),
+            "incrby" => Command::IncrBy(IncrBy::parse_frames(&mut parse)?),
+            "incrbyfloat" => Command::IncrByFloat(IncrByFloat::parse_frames(&mut parse)?),
+            "info" => Command::Info(Info::parse_frames(&mut parse)?),
+            "keys" => Command::Keys(Keys::parse_frames(&mut parse)?),
+            "lastsave" => Command::LastSave(LastSave::parse_frames(&mut parse)?),
+            "latency" => Command::Latency(Latency::parse_frames(&mut parse)?),
+            "lcs" => Command::Lcs(Lcs::parse_frames(&mut parse)?),
+            "linsert" => Command::LInsert(LInsert::parse_frames(&mut parse)?),
+            "llen" => Command::LLen(LLen::parse_frames(&mut parse)?),
+            "lmove" => Command::LMove(LMove::parse_frames(&mut parse)?),
+            "lpop" => Command::LPop(LPop::parse_frames(&mut parse)?),
+            "lpos" => Command::LPos(LPos::parse_frames(&mut parse)?),
+            "lpush" => Command::LPush(LPush::parse_frames(&mut parse)?),
+            "lrange" => Command::LRange(LRange::parse_frames(&mut parse)?),
+            "lrem" => Command::LRem(LRem::parse_frames(&mut parse)?),
+            "lset" => Command::LSet(LSet::parse_frames(&mut parse)?),
+            "ltrim" => Command::LTrim(LTrim::parse_frames(&mut parse)?),
+            "multi" => Command::Multi(Multi::parse_frames(&mut parse)?),
+            "memory" => Command::Memory(Memory::parse_frames(&mut parse)?),
+            "mget" => Command::MGet(MGet::parse_frames(&mut parse)?),
+            "migrate" => Command::Migrate(Migrate::parse_frames(&mut parse)?),
+            "mset" => Command::MSet(MSet::parse_frames(&mut parse)?),
+            "msetnx" => Command::MSetNx(MSetNx::parse_frames(&mut parse)?),
+            "monitor" => Command::Monitor(Monitor::parse_frames(&mut parse)?),
+            "move" => Command::Move(Move::parse_frames(&mut parse)?),
+            "persist" => Command::Persist(Persist::parse_frames(&mut parse)?),
+            "pexpire" => Command::PExpire(PExpire::parse_frames(&mut parse)?),
+            "pexpireat" => Command::PExpireAt(PExpireAt::parse_frames(&mut parse)?),
+            "pfadd" => Command::PfAdd(PfAdd::parse_frames(&mut parse)?),
+            "pfcount" => Command::PfCount(PfCount::parse_frames(&mut parse)?),
+            "pfmerge" => Command::PfMerge(PfMerge::parse_frames(&mut parse)?),
+            "psetex" => Command::PSetEx(PSetEx::parse_frames(&mut parse)?),
+            "psubscribe" => Command::PSubscribe(PSubscribe::parse_frames(&mut parse)?),
+            "psync" => Command::PSync(PSync::parse_frames(&mut parse)?),
+            "pttl" => Command::PTtl(PTtl::parse_frames(&mut parse)?),
             "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
+            "pubsub" => Command::PubSub(PubSub::parse_frames(&mut parse)?),
+            "punsubscribe" => Command::PUnsubscribe(PUnsubscribe::parse_frames(&mut parse)?),
+            "randomkey" => Command::RandomKey(RandomKey::parse_frames(&mut parse)?),
+            "rename" => Command::Rename(Rename::parse_frames(&mut parse)?),
+            "renamenx" => Command::RenameNx(RenameNx::parse_frames(&mut parse)?),
+            "replconf" => Command::ReplConf(ReplConf::parse_frames(&mut parse)?),
+            "replicaof" => Command::ReplicaOf(ReplicaOf::parse_frames(&mut parse)?),
+            "rpop" => Command::RPop(RPop::parse_frames(&mut parse)?),
+            "rpoplpush" => Command::RPopLPush(RPopLPush::parse_frames(&mut parse)?),
+            "rpush" => Command::RPush(RPush::parse_frames(&mut parse)?),
+            "reset" => Command::Reset(Reset::parse_frames(&mut parse)?),
+            "restore" => Command::Restore(Restore::parse_frames(&mut parse)?),
+            "role" => Command::Role(Role::parse_frames(&mut parse)?),
+            "sadd" => Command::SAdd(SAdd::parse_frames(&mut parse)?),
+            "save" => Command::Save(Save::parse_frames(&mut parse)?),
+            "scan" => Command::Scan(Scan::parse_frames(&mut parse)?),
+            "script" => Command::Script(Script::parse_frames(&mut parse)?),
+            "select" => Command::Select(Select::parse_frames(&mut parse)?),
+            "scard" => Command::SCard(SCard::parse_frames(&mut parse)?),
+            "sdiff" => Command::SDiff(SDiff::parse_frames(&mut parse)?),
+            "sdiffstore" => Command::SDiffStore(SDiffStore::parse_frames(&mut parse)?),
             "set" => Command::Set(Set::parse_frames(&mut parse)?),
+            "setbit" => Command::SetBit(SetBit::parse_frames(&mut parse)?),
+            "setex" => Command::SetEx(SetEx::parse_frames(&mut parse)?),
+            "setnx" => Command::SetNx(SetNx::parse_frames(&mut parse)?),
+            "setrange" => Command::SetRange(SetRange::parse_frames(&mut parse)?),
+            "sinter" => Command::SInter(SInter::parse_frames(&mut parse)?),
+            "sintercard" => Command::SInterCard(SInterCard::parse_frames(&mut parse)?),
+            "sinterstore" => Command::SInterStore(SInterStore::parse_frames(&mut parse)?),
+            "sismember" => Command::SIsMember(SIsMember::parse_frames(&mut parse)?),
+            "slowlog" => Command::Slowlog(Slowlog::parse_frames(&mut parse)?),
+            "smismember" => Command::SMIsMember(SMIsMember::parse_frames(&mut parse)?),
+            "smembers" => Command::SMembers(SMembers::parse_frames(&mut parse)?),
+            "sort" => Command::Sort(Sort::parse_frames(&mut parse)?),
+            "spop" => Command::SPop(SPop::parse_frames(&mut parse)?),
+            "spublish" => Command::SPublish(SPublish::parse_frames(&mut parse)?),
+            "srandmember" => Command::SRandMember(SRandMember::parse_frames(&mut parse)?),
+            "srem" => Command::SRem(SRem::parse_frames(&mut parse)?),
+            "sscan" => Command::SScan(SScan::parse_frames(&mut parse)?),
+            "ssubscribe" => Command::SSubscribe(SSubscribe::parse_frames(&mut parse)?),
+            "sunion" => Command::SUnion(SUnion::parse_frames(&mut parse)?),
+            "sunionstore" => Command::SUnionStore(SUnionStore::parse_frames(&mut parse)?),
+            "sunsubscribe" => Command::SUnsubscribe(SUnsubscribe::parse_frames(&mut parse)?),
+            "swapdb" => Command::SwapDb(SwapDb::parse_frames(&mut parse)?),
+            "shutdown" => Command::Shutdown(ShutdownServer::parse_frames(&mut parse)?),
+            "strlen" => Command::StrLen(StrLen::parse_frames(&mut parse)?),
             "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
+            "touch" => Command::Touch(Touch::parse_frames(&mut parse)?),
+            "ttl" => Command::Ttl(Ttl::parse_frames(&mut parse)?),
+            "type" => Command::Type(Type::parse_frames(&mut parse)?),
+            "unlink" => Command::Unlink(Unlink::parse_frames(&mut parse)?),
             "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
+            "unwatch" => Command::Unwatch(Unwatch::parse_frames(&mut parse)?),
+            "wait" => Command::Wait(Wait::parse_frames(&mut parse)?),
+            "watch" => Command::Watch(Watch::parse_frames(&mut parse)?),
+            "xack" => Command::XAck(XAck::parse_frames(&mut parse)?),
+            "xadd" => Command::XAdd(XAdd::parse_frames(&mut parse)?),
+            "xclaim" => Command::XClaim(XClaim::parse_frames(&mut parse)?),
+            "xdel" => Command::XDel(XDel::parse_frames(&mut parse)?),
+            "xgroup" => Command::XGroup(XGroup::parse_frames(&mut parse)?),
+            "xlen" => Command::XLen(XLen::parse_frames(&mut parse)?),
+            "xpending" => Command::XPending(XPending::parse_frames(&mut parse)?),
+            "xrange" => Command::XRange(XRange::parse_frames(&mut parse)?),
+            "xread" => Command::XRead(XRead::parse_frames(&mut parse)?),
+            "xreadgroup" => Command::XReadGroup(XReadGroup::parse_frames(&mut parse)?),
+            "xtrim" => Command::XTrim(XTrim::parse_frames(&mut parse)?),
+            "zadd" => Command::ZAdd(ZAdd::parse_frames(&mut parse)?),
+            "zcount" => Command::ZCount(ZCount::parse_frames(&mut parse)?),
+            "zincrby" => Command::ZIncrBy(ZIncrBy::parse_frames(&mut parse)?),
+            "zpopmax" => Command::ZPopMax(ZPopMax::parse_frames(&mut parse)?),
+            "zpopmin" => Command::ZPopMin(ZPopMin::parse_frames(&mut parse)?),
+            "zrandmember" => Command::ZRandMember(ZRandMember::parse_frames(&mut parse)?),
+            "zrange" => Command::ZRange(ZRange::parse_frames(&mut parse)?),
+            "zrangebylex" => Command::ZRangeByLex(ZRangeByLex::parse_frames(&mut parse)?),
+            "zrangebyscore" => Command::ZRangeByScore(ZRangeByScore::parse_frames(&mut parse)?),
+            "zrank" => Command::ZRank(ZRank::parse_frames(&mut parse)?),
+            "zrem" => Command::ZRem(ZRem::parse_frames(&mut parse)?),
+            "zscan" => Command::ZScan(ZScan::parse_frames(&mut parse)?),
+            "zscore" => Command::ZScore(ZScore::parse_frames(&mut parse)?),
             _ => {
                 // The command is not recognized and an Unknown command is
                 // returned.
@@ -86,39 +843,895 @@ impl Command {
 
     /// Apply the command to the specified `Db` instance.
     ///
+    /// `db` is the handle owned by the connection, which `SELECT` replaces.
     /// The response is written to `dst`. This is called by the server in order
     /// to execute a received command.
     pub(crate) async fn apply(
         self,
-        db: &Db,
+        db: &mut Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
     ) -> crate::Result<()> {
         use Command::*;
 
+        // Expired keys are removed as the command accesses them.
+        db.expire_keys(&self.keys());
+
         match self {
+            Acl(cmd) => cmd.apply(db, dst).await,
+            Append(cmd) => cmd.apply(db, dst).await,
+            Asking(cmd) => cmd.apply(db, dst).await,
+            Auth(cmd) => cmd.apply(db, dst).await,
+            BLMove(cmd) => cmd.apply(db, dst, shutdown).await,
+            BLPop(cmd) => cmd.apply(db, dst, shutdown).await,
+            BRPop(cmd) => cmd.apply(db, dst, shutdown).await,
+            BRPopLPush(cmd) => cmd.apply(db, dst, shutdown).await,
+            BZPopMax(cmd) => cmd.apply(db, dst, shutdown).await,
+            BZPopMin(cmd) => cmd.apply(db, dst, shutdown).await,
+            BgRewriteAof(cmd) => cmd.apply(db, dst).await,
+            BgSave(cmd) => cmd.apply(db, dst).await,
+            BitCount(cmd) => cmd.apply(db, dst).await,
+            BitField(cmd) => cmd.apply(db, dst).await,
+            BitOp(cmd) => cmd.apply(db, dst).await,
+            BitPos(cmd) => cmd.apply(db, dst).await,
+            Client(cmd) => cmd.apply(db, dst).await,
+            Cluster(cmd) => cmd.apply(db, dst).await,
+            Commands(cmd) => cmd.apply(dst).await,
+            Config(cmd) => cmd.apply(db, dst).await,
+            Copy(cmd) => cmd.apply(db, dst).await,
+            DbSize(cmd) => cmd.apply(db, dst).await,
+            Debug(cmd) => cmd.apply(db, dst).await,
+            Decr(cmd) => cmd.apply(db, dst).await,
+            DecrBy(cmd) => cmd.apply(db, dst).await,
             Del(cmd) => cmd.apply(db, dst).await,
+            Dump(cmd) => cmd.apply(db, dst).await,
+            Eval(cmd) => cmd.apply(db, dst, shutdown).await,
+            EvalSha(cmd) => cmd.apply(db, dst, shutdown).await,
+            Exists(cmd) => cmd.apply(db, dst).await,
+            Expire(cmd) => cmd.apply(db, dst).await,
+            ExpireAt(cmd) => cmd.apply(db, dst).await,
+            FCall(cmd) => cmd.apply(db, dst, shutdown).await,
+            FCallRo(cmd) => cmd.apply(db, dst, shutdown).await,
+            FlushAll(cmd) => cmd.apply(db, dst).await,
+            FlushDb(cmd) => cmd.apply(db, dst).await,
+            Function(cmd) => cmd.apply(db, dst).await,
+            GeoAdd(cmd) => cmd.apply(db, dst).await,
+            GeoDist(cmd) => cmd.apply(db, dst).await,
+            GeoPos(cmd) => cmd.apply(db, dst).await,
+            GeoSearch(cmd) => cmd.apply(db, dst).await,
             Get(cmd) => cmd.apply(db, dst).await,
+            GetBit(cmd) => cmd.apply(db, dst).await,
+            GetDel(cmd) => cmd.apply(db, dst).await,
+            GetEx(cmd) => cmd.apply(db, dst).await,
+            GetRange(cmd) => cmd.apply(db, dst).await,
+            HDel(cmd) => cmd.apply(db, dst).await,
+            HGet(cmd) => cmd.apply(db, dst).await,
+            HGetAll(cmd) => cmd.apply(db, dst).await,
+            HIncrBy(cmd) => cmd.apply(db, dst).await,
+            HIncrByFloat(cmd) => cmd.apply(db, dst).await,
+            HMGet(cmd) => cmd.apply(db, dst).await,
+            HRandField(cmd) => cmd.apply(db, dst).await,
+            HScan(cmd) => cmd.apply(db, dst).await,
+            HSet(cmd) => cmd.apply(db, dst).await,
+            HSetNx(cmd) => cmd.apply(db, dst).await,
+            Hello(cmd) => cmd.apply(db, dst).await,
+            Incr(cmd) => cmd.apply(db, dst).await,
+            IncrBy(cmd) => cmd.apply(db, dst).await,
+            IncrByFloat(cmd) => cmd.apply(db, dst).await,
+            Info(cmd) => cmd.apply(db, dst).await,
+            Keys(cmd) => cmd.apply(db, dst).await,
+            LastSave(cmd) => cmd.apply(db, dst).await,
+            Latency(cmd) => cmd.apply(db, dst).await,
+            Lcs(cmd) => cmd.apply(db, dst).await,
+            LInsert(cmd) => cmd.apply(db, dst).await,
+            LLen(cmd) => cmd.apply(db, dst).await,
+            LMove(cmd) => cmd.apply(db, dst).await,
+            LPop(cmd) => cmd.apply(db, dst).await,
+            LPos(cmd) => cmd.apply(db, dst).await,
+            LPush(cmd) => cmd.apply(db, dst).await,
+            LRange(cmd) => cmd.apply(db, dst).await,
+            LRem(cmd) => cmd.apply(db, dst).await,
+            LSet(cmd) => cmd.apply(db, dst).await,
+            LTrim(cmd) => cmd.apply(db, dst).await,
+            Memory(cmd) => cmd.apply(db, dst).await,
+            MGet(cmd) => cmd.apply(db, dst).await,
+            Migrate(cmd) => cmd.apply(db, dst).await,
+            MSet(cmd) => cmd.apply(db, dst).await,
+            MSetNx(cmd) => cmd.apply(db, dst).await,
+            PExpire(cmd) => cmd.apply(db, dst).await,
+            PExpireAt(cmd) => cmd.apply(db, dst).await,
+            PSetEx(cmd) => cmd.apply(db, dst).await,
+            PSubscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            PSync(cmd) => cmd.apply(db, dst, shutdown).await,
+            PTtl(cmd) => cmd.apply(db, dst).await,
+            Monitor(cmd) => cmd.apply(db, dst, shutdown).await,
+            Move(cmd) => cmd.apply(db, dst).await,
+            Persist(cmd) => cmd.apply(db, dst).await,
+            PfAdd(cmd) => cmd.apply(db, dst).await,
+            PfCount(cmd) => cmd.apply(db, dst).await,
+            PfMerge(cmd) => cmd.apply(db, dst).await,
+            PubSub(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
+            RPop(cmd) => cmd.apply(db, dst).await,
+            RPopLPush(cmd) => cmd.apply(db, dst).await,
+            RPush(cmd) => cmd.apply(db, dst).await,
+            RandomKey(cmd) => cmd.apply(db, dst).await,
+            Rename(cmd) => cmd.apply(db, dst).await,
+            RenameNx(cmd) => cmd.apply(db, dst).await,
+            ReplConf(cmd) => cmd.apply(db, dst).await,
+            ReplicaOf(cmd) => cmd.apply(db, dst).await,
+            Reset(cmd) => cmd.apply(db, dst).await,
+            Restore(cmd) => cmd.apply(db, dst).await,
+            Role(cmd) => cmd.apply(db, dst).await,
+            SAdd(cmd) => cmd.apply(db, dst).await,
+            SCard(cmd) => cmd.apply(db, dst).await,
+            SDiff(cmd) => cmd.apply(db, dst).await,
+            SDiffStore(cmd) => cmd.apply(db, dst).await,
+            SInter(cmd) => cmd.apply(db, dst).await,
+            SInterCard(cmd) => cmd.apply(db, dst).await,
+            SInterStore(cmd) => cmd.apply(db, dst).await,
+            SIsMember(cmd) => cmd.apply(db, dst).await,
+            Slowlog(cmd) => cmd.apply(db, dst).await,
+            SMIsMember(cmd) => cmd.apply(db, dst).await,
+            SMembers(cmd) => cmd.apply(db, dst).await,
+            Sort(cmd) => cmd.apply(db, dst).await,
+            SPop(cmd) => cmd.apply(db, dst).await,
+            SPublish(cmd) => cmd.apply(db, dst).await,
+            SRandMember(cmd) => cmd.apply(db, dst).await,
+            SRem(cmd) => cmd.apply(db, dst).await,
+            SScan(cmd) => cmd.apply(db, dst).await,
+            SSubscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            SUnion(cmd) => cmd.apply(db, dst).await,
+            SUnionStore(cmd) => cmd.apply(db, dst).await,
+            SwapDb(cmd) => cmd.apply(db, dst).await,
+            Save(cmd) => cmd.apply(db, dst).await,
+            Scan(cmd) => cmd.apply(db, dst).await,
+            Script(cmd) => cmd.apply(db, dst).await,
+            Select(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
+            SetBit(cmd) => cmd.apply(db, dst).await,
+            SetEx(cmd) => cmd.apply(db, dst).await,
+            SetNx(cmd) => cmd.apply(db, dst).await,
+            SetRange(cmd) => cmd.apply(db, dst).await,
+            Shutdown(cmd) => cmd.apply(db, dst).await,
+            StrLen(cmd) => cmd.apply(db, dst).await,
             Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Touch(cmd) => cmd.apply(db, dst).await,
+            Ttl(cmd) => cmd.apply(db, dst).await,
+            Type(cmd) => cmd.apply(db, dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
-            // `Unsubscribe` cannot be applied. It may only be received from the
-            // context of a `Subscribe` command.
+            Unlink(cmd) => cmd.apply(db, dst).await,
+            Unwatch(cmd) => cmd.apply(dst).await,
+            Wait(cmd) => cmd.apply(db, dst, shutdown).await,
+            XAck(cmd) => cmd.apply(db, dst).await,
+            XAdd(cmd) => cmd.apply(db, dst).await,
+            XClaim(cmd) => cmd.apply(db, dst).await,
+            XDel(cmd) => cmd.apply(db, dst).await,
+            XGroup(cmd) => cmd.apply(db, dst).await,
+            XLen(cmd) => cmd.apply(db, dst).await,
+            XPending(cmd) => cmd.apply(db, dst).await,
+            XRange(cmd) => cmd.apply(db, dst).await,
+            XRead(cmd) => cmd.apply(db, dst, shutdown).await,
+            XReadGroup(cmd) => cmd.apply(db, dst, shutdown).await,
+            XTrim(cmd) => cmd.apply(db, dst).await,
+            ZAdd(cmd) => cmd.apply(db, dst).await,
+            ZCount(cmd) => cmd.apply(db, dst).await,
+            ZIncrBy(cmd) => cmd.apply(db, dst).await,
+            ZPopMax(cmd) => cmd.apply(db, dst).await,
+            ZPopMin(cmd) => cmd.apply(db, dst).await,
+            ZRandMember(cmd) => cmd.apply(db, dst).await,
+            ZRange(cmd) => cmd.apply(db, dst).await,
+            ZRangeByLex(cmd) => cmd.apply(db, dst).await,
+            ZRangeByScore(cmd) => cmd.apply(db, dst).await,
+            ZRank(cmd) => cmd.apply(db, dst).await,
+            ZRem(cmd) => cmd.apply(db, dst).await,
+            ZScan(cmd) => cmd.apply(db, dst).await,
+            ZScore(cmd) => cmd.apply(db, dst).await,
+            // `Unsubscribe`, `PUnsubscribe` and `SUnsubscribe` cannot be
+            // applied. They may only be received from the context of a
+            // `Subscribe` command.
             Unsubscribe(_) => Err("`Unsubscribe` is unsupported in this context".into()),
+            PUnsubscribe(_) => Err("`PUnsubscribe` is unsupported in this context".into()),
+            SUnsubscribe(_) => Err("`SUnsubscribe` is unsupported in this context".into()),
+            // Transactions are handled by the connection, which holds the
+            // queued commands.
+            Discard(_) | Exec(_) | Multi(_) | Watch(_) => {
+                Err("transaction commands are unsupported in this context".into())
+            }
+        }
+    }
+
+    /// Apply the command within a transaction, polling it once rather than
+    /// awaiting it.
+    ///
+    /// While a transaction runs, the other connections wait for it to
+    /// complete, possibly on the same worker thread, so the transaction must
+    /// complete without ever yielding. Commands do not block within a
+    /// transaction and complete the first time they are polled. `None` is
+    /// returned if the command did not complete anyway, in which case it is
+    /// replied to as a blocking command whose timeout elapsed.
+    pub(crate) fn apply_now(
+        self,
+        db: &mut Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> Option<crate::Result<()>> {
+        let mut apply = pin!(self.apply(db, dst, shutdown));
+        match apply.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(res) => Some(res),
+            Poll::Pending => None,
+        }
+    }
+
+    /// Returns `false` for the commands which may not be queued in a
+    /// transaction, such as those waiting on another node or on the
+    /// connection.
+    pub(crate) fn allowed_in_transaction(&self) -> bool {
+        match self {
+            Command::Cluster(cmd) => !cmd.is_meet(),
+            cmd => !matches!(
+                cmd,
+                Command::Migrate(_)
+                    | Command::Monitor(_)
+                    | Command::PSubscribe(_)
+                    | Command::PSync(_)
+                    | Command::PUnsubscribe(_)
+                    | Command::Shutdown(_)
+                    | Command::SSubscribe(_)
+                    | Command::SUnsubscribe(_)
+                    | Command::Subscribe(_)
+                    | Command::Unsubscribe(_)
+            ),
+        }
+    }
+
+    /// Returns `false` for the commands scripts may not call, such as those
+    /// depending on the state of the connection.
+    pub(crate) fn allowed_in_script(&self) -> bool {
+        !matches!(
+            self,
+            Command::Acl(_)
+                | Command::Asking(_)
+                | Command::Auth(_)
+                | Command::BgRewriteAof(_)
+                | Command::BgSave(_)
+                | Command::Client(_)
+                | Command::Cluster(_)
+                | Command::Config(_)
+                | Command::Debug(_)
+                | Command::Discard(_)
+                | Command::Eval(_)
+                | Command::EvalSha(_)
+                | Command::Exec(_)
+                | Command::FCall(_)
+                | Command::FCallRo(_)
+                | Command::Function(_)
+                | Command::Hello(_)
+                | Command::Latency(_)
+                | Command::Migrate(_)
+                | Command::Monitor(_)
+                | Command::Multi(_)
+                | Command::PSubscribe(_)
+                | Command::PSync(_)
+                | Command::PUnsubscribe(_)
+                | Command::ReplConf(_)
+                | Command::ReplicaOf(_)
+                | Command::Reset(_)
+                | Command::Role(_)
+                | Command::Save(_)
+                | Command::Script(_)
+                | Command::Shutdown(_)
+                | Command::SSubscribe(_)
+                | Command::SUnsubscribe(_)
+                | Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::Unwatch(_)
+                | Command::Wait(_)
+                | Command::Watch(_)
+        )
+    }
+
+    /// Returns `true` if the command may modify the data set or the loaded
+    /// libraries.
+    pub(crate) fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Command::Append(_)
+                | Command::BLMove(_)
+                | Command::BLPop(_)
+                | Command::BRPop(_)
+                | Command::BRPopLPush(_)
+                | Command::BZPopMax(_)
+                | Command::BZPopMin(_)
+                | Command::BitField(_)
+                | Command::BitOp(_)
+                | Command::Copy(_)
+                | Command::Decr(_)
+                | Command::DecrBy(_)
+                | Command::Del(_)
+                | Command::Expire(_)
+                | Command::ExpireAt(_)
+                | Command::FlushAll(_)
+                | Command::FlushDb(_)
+                | Command::GeoAdd(_)
+                | Command::GetDel(_)
+                | Command::GetEx(_)
+                | Command::HDel(_)
+                | Command::HIncrBy(_)
+                | Command::HIncrByFloat(_)
+                | Command::HSet(_)
+                | Command::HSetNx(_)
+                | Command::Incr(_)
+                | Command::IncrBy(_)
+                | Command::IncrByFloat(_)
+                | Command::LInsert(_)
+                | Command::LMove(_)
+                | Command::LPop(_)
+                | Command::LPush(_)
+                | Command::LRem(_)
+                | Command::LSet(_)
+                | Command::LTrim(_)
+                | Command::Migrate(_)
+                | Command::MSet(_)
+                | Command::MSetNx(_)
+                | Command::PExpire(_)
+                | Command::PExpireAt(_)
+                | Command::PSetEx(_)
+                | Command::Move(_)
+                | Command::Persist(_)
+                | Command::PfAdd(_)
+                | Command::PfMerge(_)
+                | Command::RPop(_)
+                | Command::RPopLPush(_)
+                | Command::RPush(_)
+                | Command::Rename(_)
+                | Command::RenameNx(_)
+                | Command::Restore(_)
+                | Command::SAdd(_)
+                | Command::SDiffStore(_)
+                | Command::SInterStore(_)
+                | Command::Sort(_)
+                | Command::SPop(_)
+                | Command::SRem(_)
+                | Command::SUnionStore(_)
+                | Command::SwapDb(_)
+                | Command::Set(_)
+                | Command::SetBit(_)
+                | Command::SetEx(_)
+                | Command::SetNx(_)
+                | Command::SetRange(_)
+                | Command::Unlink(_)
+                | Command::XAck(_)
+                | Command::XAdd(_)
+                | Command::XClaim(_)
+                | Command::XDel(_)
+                | Command::XGroup(_)
+                | Command::XReadGroup(_)
+                | Command::XTrim(_)
+                | Command::ZAdd(_)
+                | Command::ZIncrBy(_)
+                | Command::ZPopMax(_)
+                | Command::ZPopMin(_)
+                | Command::ZRem(_)
+        ) || matches!(self, Command::Function(cmd) if cmd.is_write())
+    }
+
+    /// Returns `true` if the command may use more memory, as flagged
+    /// `denyoom` by `COMMAND`. Keys are evicted before applying such commands
+    /// once the data set uses more than `maxmemory`.
+    pub(crate) fn is_denyoom(&self) -> bool {
+        command::is_denyoom(self.get_name())
+    }
+
+    /// Returns `true` if the command may wait before completing, for a key to
+    /// be written to or for messages to send to the client.
+    pub(crate) fn may_block(&self) -> bool {
+        matches!(
+            self,
+            Command::BLMove(_)
+                | Command::BLPop(_)
+                | Command::BRPop(_)
+                | Command::BRPopLPush(_)
+                | Command::BZPopMax(_)
+                | Command::BZPopMin(_)
+                | Command::Monitor(_)
+                | Command::PSubscribe(_)
+                | Command::SSubscribe(_)
+                | Command::Subscribe(_)
+                | Command::Wait(_)
+                | Command::XRead(_)
+                | Command::XReadGroup(_)
+        )
+    }
+
+    /// Returns the keys the command accesses, in the selected database.
+    pub(crate) fn keys(&self) -> Vec<&str> {
+        match self {
+            Command::Append(cmd) => vec![cmd.key()],
+            Command::BitCount(cmd) => vec![cmd.key()],
+            Command::BitField(cmd) => vec![cmd.key()],
+            Command::BitPos(cmd) => vec![cmd.key()],
+            Command::Decr(cmd) => vec![cmd.key()],
+            Command::DecrBy(cmd) => vec![cmd.key()],
+            Command::Dump(cmd) => vec![cmd.key()],
+            Command::Expire(cmd) => vec![cmd.key()],
+            Command::ExpireAt(cmd) => vec![cmd.key()],
+            Command::GeoAdd(cmd) => vec![cmd.key()],
+            Command::GeoDist(cmd) => vec![cmd.key()],
+            Command::GeoPos(cmd) => vec![cmd.key()],
+            Command::GeoSearch(cmd) => vec![cmd.key()],
+            Command::Get(cmd) => vec![cmd.key()],
+            Command::GetBit(cmd) => vec![cmd.key()],
+            Command::GetDel(cmd) => vec![cmd.key()],
+            Command::GetEx(cmd) => vec![cmd.key()],
+            Command::GetRange(cmd) => vec![cmd.key()],
+            Command::HDel(cmd) => vec![cmd.key()],
+            Command::HGet(cmd) => vec![cmd.key()],
+            Command::HGetAll(cmd) => vec![cmd.key()],
+            Command::HIncrBy(cmd) => vec![cmd.key()],
+            Command::HIncrByFloat(cmd) => vec![cmd.key()],
+            Command::HMGet(cmd) => vec![cmd.key()],
+            Command::HRandField(cmd) => vec![cmd.key()],
+            Command::HScan(cmd) => vec![cmd.key()],
+            Command::HSet(cmd) => vec![cmd.key()],
+            Command::HSetNx(cmd) => vec![cmd.key()],
+            Command::Incr(cmd) => vec![cmd.key()],
+            Command::IncrBy(cmd) => vec![cmd.key()],
+            Command::IncrByFloat(cmd) => vec![cmd.key()],
+            Command::LInsert(cmd) => vec![cmd.key()],
+            Command::LLen(cmd) => vec![cmd.key()],
+            Command::LPop(cmd) => vec![cmd.key()],
+            Command::LPos(cmd) => vec![cmd.key()],
+            Command::LPush(cmd) => vec![cmd.key()],
+            Command::LRange(cmd) => vec![cmd.key()],
+            Command::LRem(cmd) => vec![cmd.key()],
+            Command::LSet(cmd) => vec![cmd.key()],
+            Command::LTrim(cmd) => vec![cmd.key()],
+            Command::PExpire(cmd) => vec![cmd.key()],
+            Command::PExpireAt(cmd) => vec![cmd.key()],
+            Command::PSetEx(cmd) => vec![cmd.key()],
+            Command::PTtl(cmd) => vec![cmd.key()],
+            Command::Move(cmd) => vec![cmd.key()],
+            Command::Persist(cmd) => vec![cmd.key()],
+            Command::PfAdd(cmd) => vec![cmd.key()],
+            Command::RPop(cmd) => vec![cmd.key()],
+            Command::RPush(cmd) => vec![cmd.key()],
+            Command::Restore(cmd) => vec![cmd.key()],
+            Command::SAdd(cmd) => vec![cmd.key()],
+            Command::SCard(cmd) => vec![cmd.key()],
+            Command::SIsMember(cmd) => vec![cmd.key()],
+            Command::SMIsMember(cmd) => vec![cmd.key()],
+            Command::SMembers(cmd) => vec![cmd.key()],
+            Command::SPop(cmd) => vec![cmd.key()],
+            Command::SRandMember(cmd) => vec![cmd.key()],
+            Command::SRem(cmd) => vec![cmd.key()],
+            Command::SScan(cmd) => vec![cmd.key()],
+            Command::Set(cmd) => vec![cmd.key()],
+            Command::SetBit(cmd) => vec![cmd.key()],
+            Command::SetEx(cmd) => vec![cmd.key()],
+            Command::SetNx(cmd) => vec![cmd.key()],
+            Command::SetRange(cmd) => vec![cmd.key()],
+            Command::StrLen(cmd) => vec![cmd.key()],
+            Command::Ttl(cmd) => vec![cmd.key()],
+            Command::Type(cmd) => vec![cmd.key()],
+            Command::XAck(cmd) => vec![cmd.key()],
+            Command::XAdd(cmd) => vec![cmd.key()],
+            Command::XClaim(cmd) => vec![cmd.key()],
+            Command::XDel(cmd) => vec![cmd.key()],
+            Command::XGroup(cmd) => vec![cmd.key()],
+            Command::XLen(cmd) => vec![cmd.key()],
+            Command::XPending(cmd) => vec![cmd.key()],
+            Command::XRange(cmd) => vec![cmd.key()],
+            Command::XTrim(cmd) => vec![cmd.key()],
+            Command::ZAdd(cmd) => vec![cmd.key()],
+            Command::ZCount(cmd) => vec![cmd.key()],
+            Command::ZIncrBy(cmd) => vec![cmd.key()],
+            Command::ZPopMax(cmd) => vec![cmd.key()],
+            Command::ZPopMin(cmd) => vec![cmd.key()],
+            Command::ZRandMember(cmd) => vec![cmd.key()],
+            Command::ZRange(cmd) => vec![cmd.key()],
+            Command::ZRangeByLex(cmd) => vec![cmd.key()],
+            Command::ZRangeByScore(cmd) => vec![cmd.key()],
+            Command::ZRank(cmd) => vec![cmd.key()],
+            Command::ZRem(cmd) => vec![cmd.key()],
+            Command::ZScan(cmd) => vec![cmd.key()],
+            Command::ZScore(cmd) => vec![cmd.key()],
+            Command::BLPop(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::BRPop(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::BZPopMax(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::BZPopMin(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Del(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Eval(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::EvalSha(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Exists(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::FCall(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::FCallRo(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::MGet(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Migrate(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::PfCount(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::SDiff(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::SInter(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::SInterCard(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::SUnion(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Touch(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Unlink(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::Watch(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Command::BitOp(cmd) => keys_with(cmd.dest(), cmd.keys()),
+            Command::PfMerge(cmd) => keys_with(cmd.dest(), cmd.keys()),
+            Command::SDiffStore(cmd) => keys_with(cmd.dest(), cmd.keys()),
+            Command::SInterStore(cmd) => keys_with(cmd.dest(), cmd.keys()),
+            Command::SUnionStore(cmd) => keys_with(cmd.dest(), cmd.keys()),
+            Command::BLMove(cmd) => vec![cmd.source(), cmd.destination()],
+            Command::BRPopLPush(cmd) => vec![cmd.source(), cmd.destination()],
+            Command::Copy(cmd) => vec![cmd.source(), cmd.destination()],
+            Command::LMove(cmd) => vec![cmd.source(), cmd.destination()],
+            Command::RPopLPush(cmd) => vec![cmd.source(), cmd.destination()],
+            Command::Lcs(cmd) => vec![cmd.key1(), cmd.key2()],
+            Command::MSet(cmd) => cmd.pairs().iter().map(|(key, _)| key.as_str()).collect(),
+            Command::MSetNx(cmd) => cmd.pairs().iter().map(|(key, _)| key.as_str()).collect(),
+            Command::Rename(cmd) => vec![cmd.key(), cmd.new_key()],
+            Command::RenameNx(cmd) => vec![cmd.key(), cmd.new_key()],
+            Command::Sort(cmd) => std::iter::once(cmd.key())
+                .chain(cmd.destination())
+                .collect(),
+            Command::Memory(cmd) => cmd.key().into_iter().collect(),
+            Command::XRead(cmd) => cmd.streams().iter().map(|(key, _)| key.as_str()).collect(),
+            Command::XReadGroup(cmd) => cmd.streams().iter().map(|(key, _)| key.as_str()).collect(),
+            // Shard channels are routed as keys in cluster mode.
+            Command::SPublish(cmd) => vec![cmd.channel()],
+            Command::SSubscribe(cmd) => cmd.channels().iter().map(String::as_str).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the channels the command publishes or subscribes to. Patterns
+    /// subscribed to are returned as is.
+    pub(crate) fn channels(&self) -> Vec<&str> {
+        match self {
+            Command::Publish(cmd) => vec![cmd.channel()],
+            Command::SPublish(cmd) => vec![cmd.channel()],
+            Command::Subscribe(cmd) => cmd.channels().iter().map(String::as_str).collect(),
+            Command::PSubscribe(cmd) => cmd.patterns().iter().map(String::as_str).collect(),
+            Command::SSubscribe(cmd) => cmd.channels().iter().map(String::as_str).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns `true` if the command `name` exists, case insensitively.
+    pub(crate) fn exists(name: &str) -> bool {
+        command::exists(name)
+    }
+
+    /// Returns the categories of the command, which ACL rules such as
+    /// `+@read` allow or deny.
+    ///
+    /// Commands which may modify the databases belong to `write`, along with
+    /// their own category if they have one, such as `FUNCTION LOAD` which
+    /// belongs to `scripting` as well. The other commands without a category
+    /// belong to `read`.
+    pub(crate) fn categories(&self) -> Vec<&'static str> {
+        let category = match self {
+            Command::Acl(_)
+            | Command::BgRewriteAof(_)
+            | Command::BgSave(_)
+            | Command::Cluster(_)
+            | Command::Config(_)
+            | Command::Debug(_)
+            | Command::LastSave(_)
+            | Command::Latency(_)
+            | Command::Monitor(_)
+            | Command::PSync(_)
+            | Command::ReplConf(_)
+            | Command::ReplicaOf(_)
+            | Command::Role(_)
+            | Command::Save(_)
+            | Command::Shutdown(_)
+            | Command::Slowlog(_) => Some("admin"),
+            Command::Asking(_)
+            | Command::Auth(_)
+            | Command::Client(_)
+            | Command::Commands(_)
+            | Command::Hello(_)
+            | Command::Reset(_)
+            | Command::Select(_)
+            | Command::Wait(_) => Some("connection"),
+            Command::PSubscribe(_)
+            | Command::PUnsubscribe(_)
+            | Command::PubSub(_)
+            | Command::Publish(_)
+            | Command::SPublish(_)
+            | Command::SSubscribe(_)
+            | Command::SUnsubscribe(_)
+            | Command::Subscribe(_)
+            | Command::Unsubscribe(_) => Some("pubsub"),
+            Command::Eval(_)
+            | Command::EvalSha(_)
+            | Command::FCall(_)
+            | Command::FCallRo(_)
+            | Command::Function(_)
+            | Command::Script(_) => Some("scripting"),
+            Command::Discard(_)
+            | Command::Exec(_)
+            | Command::Multi(_)
+            | Command::Unwatch(_)
+            | Command::Watch(_) => Some("transaction"),
+            _ => None,
+        };
+
+        match (category, self.is_write()) {
+            (Some(category), true) => vec![category, "write"],
+            (Some(category), false) => vec![category],
+            (None, true) => vec!["write"],
+            (None, false) => vec!["read"],
         }
     }
 
     /// Returns the command name
     pub(crate) fn get_name(&self) -> &str {
         match self {
+            Command::Acl(_) => "acl",
+            Command::Append(_) => "append",
+            Command::Asking(_) => "asking",
+            Command::Auth(_) => "auth",
+            Command::BLMove(_) => "blmove",
+            Command::BLPop(_) => "blpop",
+            Command::BRPop(_) => "brpop",
+            Command::BRPopLPush(_) => "brpoplpush",
+            Command::BZPopMax(_) => "bzpopmax",
+            Command::BZPopMin(_) => "bzpopmin",
+            Command::BgRewriteAof(_) => "bgrewriteaof",
+            Command::BgSave(_) => "bgsave",
+            Command::BitCount(_) => "bitcount",
+            Command::BitField(_) => "bitfield",
+            Command::BitOp(_) => "bitop",
+            Command::BitPos(_) => "bitpos",
+            Command::Client(_) => "client",
+            Command::Cluster(_) => "cluster",
+            Command::Commands(_) => "command",
+            Command::Config(_) => "config",
+            Command::Copy(_) => "copy",
+            Command::DbSize(_) => "dbsize",
+            Command::Debug(_) => "debug",
+            Command::Decr(_) => "decr",
+            Command::DecrBy(_) => "decrby",
             Command::Del(_) => "del",
+            Command::Exists(_) => "exists",
+            Command::Expire(_) => "expire",
+            Command::Discard(_) => "discard",
+            Command::Dump(_) => "dump",
+            Command::Eval(_) => "eval",
+            Command::EvalSha(_) => "evalsha",
+            Command::Exec(_) => "exec",
+            Command::ExpireAt(_) => "expireat",
+            Command::FCall(_) => "fcall",
+            Command::FCallRo(_) => "fcall_ro",
+            Command::FlushAll(_) => "flushall",
+            Command::FlushDb(_) => "flushdb",
+            Command::Function(_) => "function",
+            Command::GeoAdd(_) => "geoadd",
+            Command::GeoDist(_) => "geodist",
+            Command::GeoPos(_) => "geopos",
+            Command::GeoSearch(_) => "geosearch",
             Command::Get(_) => "get",
-            Command::Publish(_) => "pub",
+            Command::GetBit(_) => "getbit",
+            Command::GetDel(_) => "getdel",
+            Command::GetEx(_) => "getex",
+            Command::GetRange(_) => "getrange",
+            Command::HDel(_) => "hdel",
+            Command::HGet(_) => "hget",
+            Command::HGetAll(_) => "hgetall",
+            Command::HIncrBy(_) => "hincrby",
+            Command::HIncrByFloat(_) => "hincrbyfloat",
+            Command::HMGet(_) => "hmget",
+            Command::HRandField(_) => "hrandfield",
+            Command::HScan(_) => "hscan",
+            Command::HSet(_) => "hset",
+            Command::HSetNx(_) => "hsetnx",
+            Command::Hello(_) => "hello",
+            Command::Incr(_) => "incr",
+            Command::IncrBy(_) => "incrby",
+            Command::IncrByFloat(_) => "incrbyfloat",
+            Command::Info(_) => "info",
+            Command::Keys(_) => "keys",
+            Command::LastSave(_) => "lastsave",
+            Command::Latency(_) => "latency",
+            Command::Lcs(_) => "lcs",
+            Command::LInsert(_) => "linsert",
+            Command::LLen(_) => "llen",
+            Command::LMove(_) => "lmove",
+            Command::LPop(_) => "lpop",
+            Command::LPos(_) => "lpos",
+            Command::LPush(_) => "lpush",
+            Command::LRange(_) => "lrange",
+            Command::LRem(_) => "lrem",
+            Command::LSet(_) => "lset",
+            Command::LTrim(_) => "ltrim",
+            Command::Memory(_) => "memory",
+            Command::MGet(_) => "mget",
+            Command::Multi(_) => "multi",
+            Command::Migrate(_) => "migrate",
+            Command::MSet(_) => "mset",
+            Command::MSetNx(_) => "msetnx",
+            Command::PExpire(_) => "pexpire",
+            Command::PExpireAt(_) => "pexpireat",
+            Command::PSetEx(_) => "psetex",
+            Command::PSubscribe(_) => "psubscribe",
+            Command::PSync(_) => "psync",
+            Command::PTtl(_) => "pttl",
+            Command::PUnsubscribe(_) => "punsubscribe",
+            Command::Monitor(_) => "monitor",
+            Command::Move(_) => "move",
+            Command::Persist(_) => "persist",
+            Command::PfAdd(_) => "pfadd",
+            Command::PfCount(_) => "pfcount",
+            Command::PfMerge(_) => "pfmerge",
+            Command::PubSub(_) => "pubsub",
+            Command::Publish(_) => "publish",
+            Command::RPop(_) => "rpop",
+            Command::RPopLPush(_) => "rpoplpush",
+            Command::RPush(_) => "rpush",
+            Command::RandomKey(_) => "randomkey",
+            Command::Rename(_) => "rename",
+            Command::RenameNx(_) => "renamenx",
+            Command::ReplConf(_) => "replconf",
+            Command::ReplicaOf(_) => "replicaof",
+            Command::Reset(_) => "reset",
+            Command::Restore(_) => "restore",
+            Command::Role(_) => "role",
+            Command::SAdd(_) => "sadd",
+            Command::SCard(_) => "scard",
+            Command::SDiff(_) => "sdiff",
+            Command::SDiffStore(_) => "sdiffstore",
+            Command::SInter(_) => "sinter",
+            Command::SInterCard(_) => "sintercard",
+            Command::SInterStore(_) => "sinterstore",
+            Command::SIsMember(_) => "sismember",
+            Command::Slowlog(_) => "slowlog",
+            Command::SMIsMember(_) => "smismember",
+            Command::SMembers(_) => "smembers",
+            Command::Sort(_) => "sort",
+            Command::SPop(_) => "spop",
+            Command::SPublish(_) => "spublish",
+            Command::SRandMember(_) => "srandmember",
+            Command::SRem(_) => "srem",
+            Command::SScan(_) => "sscan",
+            Command::SSubscribe(_) => "ssubscribe",
+            Command::SUnion(_) => "sunion",
+            Command::SUnionStore(_) => "sunionstore",
+            Command::SUnsubscribe(_) => "sunsubscribe",
+            Command::SwapDb(_) => "swapdb",
+            Command::Save(_) => "save",
+            Command::Scan(_) => "scan",
+            Command::Script(_) => "script",
+            Command::Select(_) => "select",
             Command::Set(_) => "set",
+            Command::SetBit(_) => "setbit",
+            Command::SetEx(_) => "setex",
+            Command::SetNx(_) => "setnx",
+            Command::SetRange(_) => "setrange",
+            Command::Shutdown(_) => "shutdown",
+            Command::StrLen(_) => "strlen",
             Command::Subscribe(_) => "subscribe",
+            Command::Touch(_) => "touch",
+            Command::Ttl(_) => "ttl",
+            Command::Type(_) => "type",
+            Command::Unlink(_) => "unlink",
             Command::Unsubscribe(_) => "unsubscribe",
+            Command::Unwatch(_) => "unwatch",
+            Command::Wait(_) => "wait",
+            Command::Watch(_) => "watch",
+            Command::XAck(_) => "xack",
+            Command::XAdd(_) => "xadd",
+            Command::XClaim(_) => "xclaim",
+            Command::XDel(_) => "xdel",
+            Command::XGroup(_) => "xgroup",
+            Command::XLen(_) => "xlen",
+            Command::XPending(_) => "xpending",
+            Command::XRange(_) => "xrange",
+            Command::XRead(_) => "xread",
+            Command::XReadGroup(_) => "xreadgroup",
+            Command::XTrim(_) => "xtrim",
+            Command::ZAdd(_) => "zadd",
+            Command::ZCount(_) => "zcount",
+            Command::ZIncrBy(_) => "zincrby",
+            Command::ZPopMax(_) => "zpopmax",
+            Command::ZPopMin(_) => "zpopmin",
+            Command::ZRandMember(_) => "zrandmember",
+            Command::ZRange(_) => "zrange",
+            Command::ZRangeByLex(_) => "zrangebylex",
+            Command::ZRangeByScore(_) => "zrangebyscore",
+            Command::ZRank(_) => "zrank",
+            Command::ZRem(_) => "zrem",
+            Command::ZScan(_) => "zscan",
+            Command::ZScore(_) => "zscore",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
 }
+
+/// Commands renamed with `rename-command`, so that dangerous commands may
+/// only be run by the clients knowing their new name.
+///
+/// A renamed command is only known by its new name, its original name being
+/// an unknown command. A command renamed to the empty string is disabled.
+#[derive(Debug, Default)]
+pub(crate) struct Renames {
+    /// Original name of the renamed commands, indexed by new name.
+    originals: HashMap<String, String>,
+
+    /// Original names of the renamed commands.
+    renamed: HashSet<String>,
+}
+
+impl Renames {
+    /// Rename the commands as given by `renames`, pairs of the name of a
+    /// command and its new name. Names are case insensitive.
+    ///
+    /// Returns `Err` if a command does not exist, or if a new name is the
+    /// name of another command.
+    pub(crate) fn new(renames: &[(String, String)]) -> crate::Result<Renames> {
+        let mut names = Renames::default();
+
+        for (name, new_name) in renames {
+            let name = name.to_lowercase();
+            let new_name = new_name.to_lowercase();
+            if !command::exists(&name) {
+                return Err(format!("no such command `{}` to rename", name).into());
+            }
+            if !names.renamed.insert(name.clone()) {
+                return Err(format!("command `{}` renamed more than once", name).into());
+            }
+            if !new_name.is_empty() && names.originals.insert(new_name.clone(), name).is_some() {
+                return Err(format!("more than one command renamed to `{}`", new_name).into());
+            }
+        }
+
+        if let Some(new_name) = names
+            .originals
+            .keys()
+            .find(|new_name| command::exists(new_name) && !names.renamed.contains(*new_name))
+        {
+            return Err(format!("command `{}` already exists", new_name).into());
+        }
+
+        Ok(names)
+    }
+
+    /// Returns the name of the command sent as `name`, or `None` if it was
+    /// renamed.
+    fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        match self.originals.get(name) {
+            Some(original) => Some(original),
+            None if self.renamed.contains(name) => None,
+            None => Some(name),
+        }
+    }
+
+    /// Replace the name of the command held by `frame` with its original
+    /// name, if it was renamed.
+    ///
+    /// The commands appended to the AOF and streamed to the replicas hold
+    /// their original name, so that they are applied whatever the names of
+    /// the commands when they are.
+    pub(crate) fn restore_name(&self, frame: &mut Frame) {
+        let name = match frame {
+            Frame::Array(parts) => match parts.first_mut() {
+                Some(Frame::Bulk(name)) => name,
+                _ => return,
+            },
+            _ => return,
+        };
+
+        let new_name = String::from_utf8_lossy(name).to_lowercase();
+        if let Some(original) = self.originals.get(&new_name) {
+            *name = Bytes::from(original.clone().into_bytes());
+        }
+    }
+}
+
+/// Returns `dest` followed by `keys`, for the commands storing their result.
+fn keys_with<'a>(dest: &'a str, keys: &'a [String]) -> Vec<&'a str> {
+    std::iter::once(dest)
+        .chain(keys.iter().map(String::as_str))
+        .collect()
+}
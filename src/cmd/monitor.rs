@@ -0,0 +1,105 @@
+use crate::{Command, Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, instrument};
+
+/// Streams the commands received by the server to the client.
+///
+/// Each command is sent as a simple string holding the time it was received,
+/// the database selected by the client which sent it and the address of the
+/// client, followed by the quoted arguments of the command:
+///
+/// ```text
+/// 1339518083.107412 [0 127.0.0.1:60866] "set" "foo" "bar"
+/// ```
+///
+/// The client keeps monitoring until it disconnects or sends `RESET`, the
+/// other commands it sends in the meantime are ignored.
+#[derive(Debug, Default)]
+pub struct Monitor;
+
+impl Monitor {
+    /// Create a new `Monitor` command.
+    pub fn new() -> Monitor {
+        Monitor
+    }
+
+    /// Parse a `Monitor` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MONITOR` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Monitor` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// MONITOR
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Monitor> {
+        Ok(Monitor)
+    }
+
+    /// Apply the `Monitor` command to the specified `Db` instance.
+    ///
+    /// This function is the entry point and includes the loop streaming the
+    /// commands until the client disconnects or the server shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let mut commands = db.monitor();
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        loop {
+            select! {
+                res = commands.recv() => match res {
+                    Ok(command) => dst.write_frame(&Frame::Simple(command)).await?,
+                    // The commands which overflowed the channel are skipped.
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                res = dst.read_frame() => match res? {
+                    // `RESET` leaves `MONITOR`, then is applied by the
+                    // connection. Other commands are ignored.
+                    Some(frame) => {
+                        let cmd = Command::from_renamed_frame(frame.clone(), &db.renames());
+                        if let Ok(Command::Reset(_)) = cmd {
+                            dst.unread_frame(frame);
+                            return Ok(());
+                        }
+                    }
+                    // The client disconnected.
+                    None => return Ok(()),
+                },
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Monitor` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("monitor".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Move `key` from the selected database to the database at index `db`.
+///
+/// The expiration of the key, if any, is moved along with it. Nothing is
+/// moved when the key already exists in the destination database.
+///
+/// Integer reply: `1` if the key was moved, `0` otherwise.
+#[derive(Debug)]
+pub struct Move {
+    /// Name of the key to move
+    key: String,
+
+    /// Index of the database to move the key to
+    db: u64,
+}
+
+impl Move {
+    /// Create a new `Move` command which moves `key` to the database at index
+    /// `db`.
+    pub fn new(key: impl ToString, db: u64) -> Move {
+        Move {
+            key: key.to_string(),
+            db,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the index of the destination database
+    pub fn db(&self) -> u64 {
+        self.db
+    }
+
+    /// Parse a `Move` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MOVE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Move` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// MOVE key db
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Move> {
+        let key = parse.next_string()?;
+        let db = parse.next_int()?;
+
+        Ok(Move { key, db })
+    }
+
+    /// Apply the `Move` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.move_key(&self.key, self.db as usize) {
+            Ok(moved) => Frame::Integer(moved as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Move` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("move".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_int(self.db as i64);
+        frame
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Sets the given keys to their respective values.
+///
+/// `MSET` replaces existing values with new values, just as regular `SET`.
+/// All the keys are set at once: clients never see some of the keys updated
+/// while others are unchanged.
+#[derive(Debug)]
+pub struct MSet {
+    /// Key / value pairs to set
+    pairs: Vec<(String, Bytes)>,
+}
+
+/// Sets the given keys to their respective values, only if none of the keys
+/// exist.
+///
+/// Same as `MSet`, but no operation is performed at all if a single key
+/// already exists.
+///
+/// Integer reply: `1` if all the keys were set, `0` if no key was set.
+#[derive(Debug)]
+pub struct MSetNx {
+    /// Key / value pairs to set
+    pairs: Vec<(String, Bytes)>,
+}
+
+impl MSet {
+    /// Create a new `MSet` command which sets each key of `pairs` to its
+    /// value.
+    pub fn new(pairs: Vec<(String, Bytes)>) -> MSet {
+        MSet { pairs }
+    }
+
+    /// Get the key / value pairs
+    pub fn pairs(&self) -> &[(String, Bytes)] {
+        &self.pairs
+    }
+
+    /// Parse a `MSet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MSET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `MSet` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// MSET key value [key value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MSet> {
+        let pairs = parse_pairs(parse)?;
+        Ok(MSet { pairs })
+    }
+
+    /// Apply the `MSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.mset(self.pairs);
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `MSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pairs_frame("mset", self.pairs)
+    }
+}
+
+impl MSetNx {
+    /// Create a new `MSetNx` command which sets each key of `pairs` to its
+    /// value, unless one of the keys exists.
+    pub fn new(pairs: Vec<(String, Bytes)>) -> MSetNx {
+        MSetNx { pairs }
+    }
+
+    /// Get the key / value pairs
+    pub fn pairs(&self) -> &[(String, Bytes)] {
+        &self.pairs
+    }
+
+    /// Parse a `MSetNx` instance from a received frame.
+    ///
+    /// The `MSETNX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// MSETNX key value [key value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MSetNx> {
+        let pairs = parse_pairs(parse)?;
+        Ok(MSetNx { pairs })
+    }
+
+    /// Apply the `MSetNx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.msetnx(self.pairs) as i64);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `MSetNx` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pairs_frame("msetnx", self.pairs)
+    }
+}
+
+/// Parses the key / value pairs shared by both commands. At least one pair is
+/// required.
+fn parse_pairs(parse: &mut Parse) -> crate::Result<Vec<(String, Bytes)>> {
+    let mut pairs = vec![(parse.next_string()?, parse.next_bytes()?)];
+
+    loop {
+        match parse.next_string() {
+            // A key must always be followed by its value
+            Ok(key) => pairs.push((key, parse.next_bytes()?)),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// Encodes a command named `name` setting `pairs` into a frame.
+fn pairs_frame(name: &'static str, pairs: Vec<(String, Bytes)>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    for (key, value) in pairs {
+        frame.push_bulk(Bytes::from(key.into_bytes()));
+        frame.push_bulk(value);
+    }
+    frame
+}
@@ -0,0 +1,78 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes the existing expiration of `key`, turning the key from volatile to
+/// persistent.
+///
+/// Integer reply: `1` if the expiration was removed, `0` if the key does not
+/// exist or has no associated expiration.
+#[derive(Debug)]
+pub struct Persist {
+    /// Name of the key to make persistent
+    key: String,
+}
+
+impl Persist {
+    /// Create a new `Persist` command which removes the expiration of `key`.
+    pub fn new(key: impl ToString) -> Persist {
+        Persist {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Persist` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PERSIST` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Persist` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// PERSIST key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Persist> {
+        let key = parse.next_string()?;
+        Ok(Persist { key })
+    }
+
+    /// Apply the `Persist` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.persist(&self.key) as i64);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Persist` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("persist".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Adds the specified elements to the HyperLogLog stored at `key`.
+///
+/// If `key` does not exist, an empty HyperLogLog is created first, so the
+/// command can be called without elements to create one.
+///
+/// Integer reply: `1` if the estimated cardinality may have changed, `0`
+/// otherwise.
+#[derive(Debug)]
+pub struct PfAdd {
+    /// Name of the key holding the HyperLogLog
+    key: String,
+
+    /// Elements to add
+    elements: Vec<Bytes>,
+}
+
+impl PfAdd {
+    /// Create a new `PfAdd` command which adds `elements` to the HyperLogLog
+    /// stored at `key`.
+    pub fn new(key: impl ToString, elements: Vec<Bytes>) -> PfAdd {
+        PfAdd {
+            key: key.to_string(),
+            elements,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the elements to add
+    pub fn elements(&self) -> &[Bytes] {
+        &self.elements
+    }
+
+    /// Parse a `PfAdd` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PFADD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PfAdd` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// PFADD key [element ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PfAdd> {
+        let key = parse.next_string()?;
+        let mut elements = vec![];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(element) => elements.push(element),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PfAdd { key, elements })
+    }
+
+    /// Apply the `PfAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.pfadd(self.key, &self.elements) {
+            Ok(changed) => Frame::Integer(changed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PfAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pfadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for element in self.elements {
+            frame.push_bulk(element);
+        }
+        frame
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the approximated cardinality computed by the HyperLogLog stored at
+/// `key`.
+///
+/// When called with multiple keys, returns the approximated cardinality of the
+/// union of the HyperLogLogs, without modifying any of them. Missing keys are
+/// considered empty.
+///
+/// The estimate has a standard error of 0.81%.
+#[derive(Debug)]
+pub struct PfCount {
+    /// Names of the keys holding the HyperLogLogs
+    keys: Vec<String>,
+}
+
+impl PfCount {
+    /// Create a new `PfCount` command which estimates the cardinality of the
+    /// union of the HyperLogLogs stored at `keys`.
+    pub fn new(keys: Vec<String>) -> PfCount {
+        PfCount { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `PfCount` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PFCOUNT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PfCount` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// PFCOUNT key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PfCount> {
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PfCount { keys })
+    }
+
+    /// Apply the `PfCount` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.pfcount(&self.keys) {
+            Ok(count) => Frame::Integer(count as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PfCount` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pfcount".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Merges the HyperLogLogs stored at `keys` into the one stored at `dest`.
+///
+/// The resulting HyperLogLog approximates the cardinality of the union of the
+/// observed sets. If `dest` already holds a HyperLogLog, it is included in
+/// the union; if it does not exist, it is created.
+#[derive(Debug)]
+pub struct PfMerge {
+    /// Name of the key to store the union at
+    dest: String,
+
+    /// Names of the keys holding the HyperLogLogs to merge
+    keys: Vec<String>,
+}
+
+impl PfMerge {
+    /// Create a new `PfMerge` command which merges the HyperLogLogs stored at
+    /// `keys` into `dest`.
+    pub fn new(dest: impl ToString, keys: Vec<String>) -> PfMerge {
+        PfMerge {
+            dest: dest.to_string(),
+            keys,
+        }
+    }
+
+    /// Get the destination key
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Get the source keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `PfMerge` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PFMERGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PfMerge` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// PFMERGE destkey [sourcekey ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PfMerge> {
+        let dest = parse.next_string()?;
+        let mut keys = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PfMerge { dest, keys })
+    }
+
+    /// Apply the `PfMerge` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.pfmerge(self.dest, &self.keys) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PfMerge` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pfmerge".as_bytes()));
+        frame.push_bulk(Bytes::from(self.dest.into_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
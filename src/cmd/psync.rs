@@ -0,0 +1,148 @@
+use crate::cmd::Command;
+use crate::db::Snapshot;
+use crate::replication::Resync;
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::{Bytes, BytesMut};
+use tokio::sync::mpsc;
+use tracing::{debug, instrument};
+
+/// Synchronize a replica with the server.
+///
+/// Sent by a replica when it connects to its primary, with the replication ID
+/// of the stream of commands it received before and the offset it reached in
+/// it, `?` and `-1` if none.
+///
+/// If the primary still holds the commands the replica missed in its backlog,
+/// the reply is `CONTINUE`, followed by the missed commands. Otherwise the
+/// reply is `FULLRESYNC replicationid offset`, followed by a snapshot of the
+/// databases as a bulk string, matching the offset of the stream.
+///
+/// The connection is then dedicated to the replica: the commands modifying the
+/// databases are streamed to it as they are applied, until it disconnects. The
+/// replica only sends `REPLCONF ACK` on it, when asked by `REPLCONF GETACK`.
+#[derive(Debug)]
+pub struct PSync {
+    /// Replication ID of the stream received by the replica
+    replication_id: String,
+
+    /// Offset the replica reached in the stream
+    offset: i64,
+}
+
+impl PSync {
+    /// Create a new `PSync` command for a replica which reached `offset` of
+    /// the stream identified by `replication_id`.
+    pub fn new(replication_id: impl ToString, offset: i64) -> PSync {
+        PSync {
+            replication_id: replication_id.to_string(),
+            offset,
+        }
+    }
+
+    /// Parse a `PSync` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PSYNC` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PSync` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// PSYNC replicationid offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSync> {
+        let replication_id = parse.next_string()?;
+        let offset = parse.next_signed_int()?;
+
+        Ok(PSync {
+            replication_id,
+            offset,
+        })
+    }
+
+    /// Apply the `PSync` command to the specified `Db` instance.
+    ///
+    /// The snapshot and the commands are written to `dst`. This is called by
+    /// the server in order to execute a received command, and returns once
+    /// the replica disconnects or the server shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let (id, resync, snapshot, stream) = db.add_replica(&self.replication_id, self.offset);
+        let res = replicate(id, resync, snapshot, stream, db, dst, shutdown).await;
+        db.remove_replica(id);
+        res
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the replica when encoding a `PSync` command to send
+    /// to its primary.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("psync".as_bytes()));
+        frame.push_bulk(Bytes::from(self.replication_id.into_bytes()));
+        frame.push_bulk(Bytes::from(self.offset.to_string().into_bytes()));
+        frame
+    }
+}
+
+/// Synchronize the replica `id` as decided by `Db::add_replica`, then write
+/// `stream` to `dst` until the replica disconnects or the server shuts down.
+async fn replicate(
+    id: u64,
+    resync: Resync,
+    snapshot: Option<Snapshot>,
+    mut stream: mpsc::UnboundedReceiver<Bytes>,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> crate::Result<()> {
+    let response = match resync {
+        Resync::Full {
+            replication_id,
+            offset,
+        } => Frame::Simple(format!("FULLRESYNC {} {}", replication_id, offset)),
+        Resync::Partial => Frame::Simple("CONTINUE".to_string()),
+    };
+    debug!(?response);
+    dst.write_frame(&response).await?;
+
+    if let Some(snapshot) = snapshot {
+        let mut data = BytesMut::new();
+        snapshot.encode(&mut data);
+        dst.write_frame(&Frame::Bulk(data.freeze())).await?;
+    }
+
+    loop {
+        tokio::select! {
+            Some(commands) = stream.recv() => dst.write_encoded(&commands).await?,
+            res = dst.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    None => return Ok(()),
+                };
+                if let Ok(Command::ReplConf(cmd)) = Command::from_frame(frame) {
+                    if let Some(offset) = cmd.acked_offset() {
+                        db.ack_replica(id, offset);
+                    }
+                }
+            }
+            _ = shutdown.recv() => return Ok(()),
+        }
+    }
+}
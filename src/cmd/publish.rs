@@ -27,6 +27,11 @@ impl Publish {
         }
     }
 
+    /// Get the channel
+    pub(crate) fn channel(&self) -> &str {
+        &self.channel
+    }
+
     /// Parse a `Publish` instance from a received frame.
     ///
     /// The `Parse` argument provides a cursor-like API to read fields from the
@@ -78,7 +83,7 @@ impl Publish {
 
         // The number of subscribers is returned as the response to the publish
         // request.
-        let response = Frame::Integer(num_subscribers as u64);
+        let response = Frame::Integer(num_subscribers as i64);
 
         // Write the frame to the client.
         dst.write_frame(&response).await?;
@@ -99,3 +104,79 @@ impl Publish {
         frame
     }
 }
+
+/// Posts a message to the given shard channel.
+///
+/// Shard channels are separate from the channels of `PUBLISH`, the message is
+/// only received by the clients subscribed with `SSUBSCRIBE`. In cluster mode,
+/// the shard channel is served by the node serving the slot of its name: the
+/// message is published on that node only.
+#[derive(Debug)]
+pub struct SPublish {
+    /// Name of the shard channel on which the message should be published.
+    channel: String,
+
+    /// The message to publish.
+    message: Bytes,
+}
+
+impl SPublish {
+    /// Create a new `SPublish` command which sends `message` on the shard
+    /// `channel`.
+    pub(crate) fn new(channel: impl ToString, message: Bytes) -> SPublish {
+        SPublish {
+            channel: channel.to_string(),
+            message,
+        }
+    }
+
+    /// Get the channel
+    pub(crate) fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Parse a `SPublish` instance from a received frame.
+    ///
+    /// The `SPUBLISH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SPUBLISH shardchannel message
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SPublish> {
+        let channel = parse.next_string()?;
+        let message = parse.next_bytes()?;
+
+        Ok(SPublish { channel, message })
+    }
+
+    /// Apply the `SPublish` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // As with `PUBLISH`, the number of subscribers is only a "hint".
+        let num_subscribers = db.spublish(&self.channel, self.message);
+
+        let response = Frame::Integer(num_subscribers as i64);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SPublish` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("spublish".as_bytes()));
+        frame.push_bulk(Bytes::from(self.channel.into_bytes()));
+        frame.push_bulk(self.message);
+
+        frame
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspects the state of the pub/sub subsystem.
+///
+/// # Subcommands
+///
+/// * CHANNELS [`pattern`] -- Returns the channels with at least one
+///   subscriber, the ones matching the glob `pattern` if given. Subscribers
+///   to patterns are not counted.
+/// * NUMSUB [`channel` ...] -- Returns each channel followed by its number of
+///   subscribers, not counting the subscribers to patterns.
+/// * NUMPAT -- Returns the number of patterns subscribed to.
+/// * SHARDCHANNELS [`pattern`] -- Same as CHANNELS, for the shard channels.
+/// * SHARDNUMSUB [`shardchannel` ...] -- Same as NUMSUB, for the shard
+///   channels.
+#[derive(Debug)]
+pub struct PubSub {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    /// `true` for the shard channels
+    Channels(Option<String>, bool),
+    NumSub(Vec<String>, bool),
+    NumPat,
+}
+
+impl PubSub {
+    /// Create a new `PubSub` command which lists the active channels matching
+    /// `pattern`, or all of them.
+    pub fn channels(pattern: Option<String>) -> PubSub {
+        PubSub {
+            subcommand: Subcommand::Channels(pattern, false),
+        }
+    }
+
+    /// Create a new `PubSub` command which lists the active shard channels
+    /// matching `pattern`, or all of them.
+    pub fn shard_channels(pattern: Option<String>) -> PubSub {
+        PubSub {
+            subcommand: Subcommand::Channels(pattern, true),
+        }
+    }
+
+    /// Create a new `PubSub` command which counts the subscribers of
+    /// `channels`.
+    pub fn numsub(channels: Vec<String>) -> PubSub {
+        PubSub {
+            subcommand: Subcommand::NumSub(channels, false),
+        }
+    }
+
+    /// Create a new `PubSub` command which counts the subscribers of the shard
+    /// `channels`.
+    pub fn shard_numsub(channels: Vec<String>) -> PubSub {
+        PubSub {
+            subcommand: Subcommand::NumSub(channels, true),
+        }
+    }
+
+    /// Create a new `PubSub` command which counts the patterns subscribed to.
+    pub fn numpat() -> PubSub {
+        PubSub {
+            subcommand: Subcommand::NumPat,
+        }
+    }
+
+    /// Parse a `PubSub` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `PUBSUB` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `PubSub` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// PUBSUB CHANNELS [pattern]
+    /// PUBSUB NUMSUB [channel ...]
+    /// PUBSUB NUMPAT
+    /// PUBSUB SHARDCHANNELS [pattern]
+    /// PUBSUB SHARDNUMSUB [shardchannel ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSub> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "CHANNELS" | "SHARDCHANNELS" => {
+                let shard = subcommand == "SHARDCHANNELS";
+                match parse.next_string() {
+                    Ok(pattern) => Subcommand::Channels(Some(pattern), shard),
+                    Err(ParseError::EndOfStream) => Subcommand::Channels(None, shard),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            "NUMSUB" | "SHARDNUMSUB" => {
+                let mut channels = vec![];
+                loop {
+                    match parse.next_string() {
+                        Ok(channel) => channels.push(channel),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                Subcommand::NumSub(channels, subcommand == "SHARDNUMSUB")
+            }
+            "NUMPAT" => Subcommand::NumPat,
+            _ => return Err(format!("unknown `PUBSUB` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(PubSub { subcommand })
+    }
+
+    /// Apply the `PubSub` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Channels(pattern, shard) => Frame::Array(
+                db.pubsub_channels(pattern.as_deref(), shard)
+                    .into_iter()
+                    .map(|channel| Frame::Bulk(Bytes::from(channel.into_bytes())))
+                    .collect(),
+            ),
+            Subcommand::NumSub(channels, shard) => {
+                let counts = db.pubsub_numsub(&channels, shard);
+
+                let mut response = Frame::array();
+                for (channel, count) in channels.into_iter().zip(counts) {
+                    response.push_bulk(Bytes::from(channel.into_bytes()));
+                    response.push_int(count as i64);
+                }
+                response
+            }
+            Subcommand::NumPat => Frame::Integer(db.pubsub_numpat() as i64),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PubSub` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pubsub".as_bytes()));
+        match self.subcommand {
+            Subcommand::Channels(pattern, shard) => {
+                let name = if shard { "shardchannels" } else { "channels" };
+                frame.push_bulk(Bytes::from(name.as_bytes()));
+                if let Some(pattern) = pattern {
+                    frame.push_bulk(Bytes::from(pattern.into_bytes()));
+                }
+            }
+            Subcommand::NumSub(channels, shard) => {
+                let name = if shard { "shardnumsub" } else { "numsub" };
+                frame.push_bulk(Bytes::from(name.as_bytes()));
+                for channel in channels {
+                    frame.push_bulk(Bytes::from(channel.into_bytes()));
+                }
+            }
+            Subcommand::NumPat => frame.push_bulk(Bytes::from("numpat".as_bytes())),
+        }
+        frame
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns a random key from the database.
+///
+/// Each key has the same probability of being picked. A null reply is
+/// returned when the database is empty.
+#[derive(Debug, Default)]
+pub struct RandomKey;
+
+impl RandomKey {
+    /// Create a new `RandomKey` command.
+    pub fn new() -> RandomKey {
+        RandomKey
+    }
+
+    /// Parse a `RandomKey` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `RANDOMKEY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `RandomKey` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// RANDOMKEY
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<RandomKey> {
+        Ok(RandomKey)
+    }
+
+    /// Apply the `RandomKey` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.random_key() {
+            Some(key) => Frame::Bulk(Bytes::from(key.into_bytes())),
+            None => Frame::Null,
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `RandomKey` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("randomkey".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Renames `key` to `new_key`.
+///
+/// The expiration of `key`, if any, is kept. If `new_key` already exists, it
+/// is overwritten. An error is returned when `key` does not exist.
+#[derive(Debug)]
+pub struct Rename {
+    /// Name of the key to rename
+    key: String,
+
+    /// New name of the key
+    new_key: String,
+}
+
+/// Renames `key` to `new_key`, only if `new_key` does not exist yet.
+///
+/// Integer reply: `1` if the key was renamed, `0` if `new_key` exists.
+#[derive(Debug)]
+pub struct RenameNx {
+    /// Name of the key to rename
+    key: String,
+
+    /// New name of the key
+    new_key: String,
+}
+
+impl Rename {
+    /// Create a new `Rename` command which renames `key` to `new_key`.
+    pub fn new(key: impl ToString, new_key: impl ToString) -> Rename {
+        Rename {
+            key: key.to_string(),
+            new_key: new_key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the new name of the key
+    pub fn new_key(&self) -> &str {
+        &self.new_key
+    }
+
+    /// Parse a `Rename` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `RENAME` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Rename` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RENAME key newkey
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Rename> {
+        let key = parse.next_string()?;
+        let new_key = parse.next_string()?;
+
+        Ok(Rename { key, new_key })
+    }
+
+    /// Apply the `Rename` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.rename(&self.key, &self.new_key, false) {
+            Ok(_) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Rename` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        rename_frame("rename", self.key, self.new_key)
+    }
+}
+
+impl RenameNx {
+    /// Create a new `RenameNx` command which renames `key` to `new_key` if
+    /// `new_key` does not exist.
+    pub fn new(key: impl ToString, new_key: impl ToString) -> RenameNx {
+        RenameNx {
+            key: key.to_string(),
+            new_key: new_key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the new name of the key
+    pub fn new_key(&self) -> &str {
+        &self.new_key
+    }
+
+    /// Parse a `RenameNx` instance from a received frame.
+    ///
+    /// The `RENAMENX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RENAMENX key newkey
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RenameNx> {
+        let key = parse.next_string()?;
+        let new_key = parse.next_string()?;
+
+        Ok(RenameNx { key, new_key })
+    }
+
+    /// Apply the `RenameNx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.rename(&self.key, &self.new_key, true) {
+            Ok(renamed) => Frame::Integer(renamed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `RenameNx` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        rename_frame("renamenx", self.key, self.new_key)
+    }
+}
+
+/// Encodes a rename command named `name` into a frame.
+fn rename_frame(name: &'static str, key: String, new_key: String) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    frame.push_bulk(Bytes::from(new_key.into_bytes()));
+    frame
+}
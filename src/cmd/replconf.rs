@@ -0,0 +1,135 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Configure the replication of the server to a replica, sent by the replica
+/// to its primary.
+///
+/// `REPLCONF ACK offset` acknowledges that the replica applied the commands
+/// streamed by the primary up to `offset`, it has no reply. `REPLCONF GETACK *`
+/// is streamed by the primary to the replica, asking for an acknowledgment.
+/// `REPLCONF listening-port port` gives the port the replica listens at, as
+/// reported by `ROLE`. The other options are accepted and ignored, the reply
+/// is `OK`.
+#[derive(Debug)]
+pub struct ReplConf {
+    /// Options and their values
+    options: Vec<(String, String)>,
+}
+
+impl ReplConf {
+    /// Create a new `ReplConf` command acknowledging `offset`.
+    pub fn ack(offset: u64) -> ReplConf {
+        ReplConf {
+            options: vec![("ack".to_string(), offset.to_string())],
+        }
+    }
+
+    /// Create a new `ReplConf` command giving the `port` the replica listens
+    /// at.
+    pub fn listening_port(port: u16) -> ReplConf {
+        ReplConf {
+            options: vec![("listening-port".to_string(), port.to_string())],
+        }
+    }
+
+    /// Create a new `ReplConf` command asking for an acknowledgment.
+    pub fn getack() -> ReplConf {
+        ReplConf {
+            options: vec![("getack".to_string(), "*".to_string())],
+        }
+    }
+
+    /// Returns the offset acknowledged by the command, if it is `ACK`.
+    pub(crate) fn acked_offset(&self) -> Option<u64> {
+        match &self.options[..] {
+            [(option, offset)] if option.to_uppercase() == "ACK" => offset.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the port the replica listens at, if given by the command.
+    pub(crate) fn announced_port(&self) -> Option<u16> {
+        self.options
+            .iter()
+            .find(|(option, _)| option.eq_ignore_ascii_case("listening-port"))
+            .and_then(|(_, port)| port.parse().ok())
+    }
+
+    /// Returns `true` if the command asks for an acknowledgment.
+    pub(crate) fn is_getack(&self) -> bool {
+        matches!(&self.options[..], [(option, _)] if option.to_uppercase() == "GETACK")
+    }
+
+    /// Parse a `ReplConf` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `REPLCONF` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ReplConf` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// REPLCONF option value [option value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ReplConf> {
+        let mut options = vec![(parse.next_string()?, parse.next_string()?)];
+
+        loop {
+            match parse.next_string() {
+                Ok(option) => options.push((option, parse.next_string()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ReplConf { options })
+    }
+
+    /// Apply the `ReplConf` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Acknowledgments are read by `PSYNC` on the connection of the replica,
+        // and are never replied to.
+        if self.acked_offset().is_some() {
+            return Ok(());
+        }
+
+        if let Some(port) = self.announced_port() {
+            db.set_listening_port(port);
+        }
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the replica and the primary when encoding a
+    /// `ReplConf` command to send to each other.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("replconf".as_bytes()));
+        for (option, value) in self.options {
+            frame.push_bulk(Bytes::from(option.into_bytes()));
+            frame.push_bulk(Bytes::from(value.into_bytes()));
+        }
+        frame
+    }
+}
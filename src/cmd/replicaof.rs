@@ -0,0 +1,109 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Make the server a replica of another server, or promote it back to a
+/// primary.
+///
+/// `REPLICAOF host port` synchronizes the databases with the primary at
+/// `host:port`, replacing their content, then applies the commands the
+/// primary streams. Clients may no longer modify the databases, commands
+/// modifying them are rejected with a `READONLY` error.
+///
+/// `REPLICAOF NO ONE` stops replicating, the databases keep their content and
+/// may be modified again.
+#[derive(Debug)]
+pub struct ReplicaOf {
+    /// Host and port of the primary, `None` to stop replicating.
+    primary: Option<(String, u16)>,
+}
+
+impl ReplicaOf {
+    /// Create a new `ReplicaOf` command which makes the server a replica of
+    /// the primary at `host:port`.
+    pub fn new(host: impl ToString, port: u16) -> ReplicaOf {
+        ReplicaOf {
+            primary: Some((host.to_string(), port)),
+        }
+    }
+
+    /// Create a new `ReplicaOf` command which stops replicating.
+    pub fn no_one() -> ReplicaOf {
+        ReplicaOf { primary: None }
+    }
+
+    /// Parse a `ReplicaOf` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `REPLICAOF` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ReplicaOf` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// REPLICAOF host port
+    /// REPLICAOF NO ONE
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ReplicaOf> {
+        let host = parse.next_string()?;
+        let port = parse.next_string()?;
+
+        if host.to_uppercase() == "NO" && port.to_uppercase() == "ONE" {
+            return Ok(ReplicaOf::no_one());
+        }
+
+        match port.parse() {
+            Ok(port) => Ok(ReplicaOf::new(host, port)),
+            Err(_) => Err("ERR Invalid master port".into()),
+        }
+    }
+
+    /// Apply the `ReplicaOf` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // The databases are synchronized in the background, by the task
+        // replicating the primary.
+        db.set_primary(
+            self.primary
+                .map(|(host, port)| format!("{}:{}", host, port)),
+        );
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ReplicaOf` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("replicaof".as_bytes()));
+        match self.primary {
+            Some((host, port)) => {
+                frame.push_bulk(Bytes::from(host.into_bytes()));
+                frame.push_bulk(Bytes::from(port.to_string().into_bytes()));
+            }
+            None => {
+                frame.push_bulk(Bytes::from("no".as_bytes()));
+                frame.push_bulk(Bytes::from("one".as_bytes()));
+            }
+        }
+        frame
+    }
+}
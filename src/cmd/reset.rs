@@ -0,0 +1,66 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Return the connection to the state of a new connection, so that it may be
+/// reused by another client, as by a connection pool.
+///
+/// The connection leaves the subscribed state and `MONITOR`, its transaction
+/// is discarded and its watched keys unwatched. Its name is cleared, tracking
+/// is disabled, replies are enabled, RESP2 is used again and the database `0`
+/// is selected. The client is authenticated again as the default user if it
+/// requires no password, and is not authenticated otherwise.
+///
+/// Simple string reply: `RESET`.
+#[derive(Debug, Default)]
+pub struct Reset;
+
+impl Reset {
+    /// Create a new `Reset` command.
+    pub fn new() -> Reset {
+        Reset
+    }
+
+    /// Parse a `Reset` instance from a received frame.
+    ///
+    /// The `RESET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// RESET
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Reset> {
+        Ok(Reset)
+    }
+
+    /// Apply the `Reset` command to the specified `Db` instance.
+    ///
+    /// The connection resets the state it holds, such as its transaction,
+    /// before this resets the state of the client and replies.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &mut Db, dst: &mut Connection) -> crate::Result<()> {
+        db.reset_client();
+        *db = db.select(0)?;
+        dst.set_protocol(2);
+        dst.set_muted(false);
+
+        let response = Frame::Simple("RESET".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Reset` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("reset".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,155 @@
+use crate::cmd::expire::{deadline_at, deadline_in};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Store at `key` a value serialized by `DUMP`.
+///
+/// The key expires after `ttl` milliseconds, or never if `ttl` is 0. With the
+/// `ABSTTL` option, `ttl` is the Unix time at which it expires, in
+/// milliseconds, and a time already past stores nothing.
+///
+/// An error is returned when `key` already exists, unless the `REPLACE` option
+/// is given, and when the serialized value is invalid.
+#[derive(Debug)]
+pub struct Restore {
+    /// Name of the key to store the value at
+    key: String,
+
+    /// Time to live of the key in milliseconds, or Unix time of its
+    /// expiration with `ABSTTL`
+    ttl: i64,
+
+    /// Value serialized by `DUMP`
+    payload: Bytes,
+
+    /// Overwrite `key` if it exists
+    replace: bool,
+
+    /// `ttl` is a Unix time
+    absttl: bool,
+}
+
+impl Restore {
+    /// Create a new `Restore` command which stores `payload`, serialized by
+    /// `DUMP`, at `key`, expiring after `ttl` milliseconds unless `ttl` is 0.
+    pub fn new(key: impl ToString, ttl: i64, payload: Bytes) -> Restore {
+        Restore {
+            key: key.to_string(),
+            ttl,
+            payload,
+            replace: false,
+            absttl: false,
+        }
+    }
+
+    /// Overwrite `key` if it exists when `replace` is set.
+    pub fn replace(mut self, replace: bool) -> Restore {
+        self.replace = replace;
+        self
+    }
+
+    /// Interpret the TTL as the Unix time of the expiration, in milliseconds,
+    /// when `absttl` is set.
+    pub fn absttl(mut self, absttl: bool) -> Restore {
+        self.absttl = absttl;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Restore` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `RESTORE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Restore` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// RESTORE key ttl serialized-value [REPLACE] [ABSTTL]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Restore> {
+        let key = parse.next_string()?;
+        let ttl = parse.next_signed_int()?;
+        if ttl < 0 {
+            return Err("ERR Invalid TTL value, must be >= 0".into());
+        }
+        let mut restore = Restore::new(key, ttl, parse.next_bytes()?);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "REPLACE" => restore.replace = true,
+                "ABSTTL" => restore.absttl = true,
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(restore)
+    }
+
+    /// Apply the `Restore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expires_at = match self.ttl {
+            0 => Some(None),
+            ttl if self.absttl => deadline_at(ttl).map(Some),
+            ttl => deadline_in(ttl).map(Some),
+        };
+
+        let response = match expires_at {
+            Some(expires_at) => {
+                match db.restore_dump(&self.key, &self.payload, expires_at, self.replace) {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            None => Frame::Error("ERR Invalid TTL value, must be >= 0".to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Restore` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("restore".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.ttl.to_string().into_bytes()));
+        frame.push_bulk(self.payload);
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        if self.absttl {
+            frame.push_bulk(Bytes::from("absttl".as_bytes()));
+        }
+        frame
+    }
+}
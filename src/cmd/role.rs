@@ -0,0 +1,118 @@
+use crate::replication;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the role of the server in the replication, so that tools may
+/// decide which replica to promote when a primary fails.
+///
+/// A primary replies with `master`, the offset of its replication stream and
+/// its connected replicas, each as the host and port it listens at and the
+/// offset it acknowledged:
+///
+/// ```text
+/// 1) "master"
+/// 2) (integer) 3129659
+/// 3) 1) 1) "127.0.0.1"
+///       2) "9001"
+///       3) "3129242"
+/// ```
+///
+/// A replica replies with `slave`, the host and port of its primary, the
+/// state of its connection with it, `connect`, `connecting`, `sync` or
+/// `connected`, and the offset it reached in the stream of the primary, `-1`
+/// if it never synchronized with it:
+///
+/// ```text
+/// 1) "slave"
+/// 2) "127.0.0.1"
+/// 3) (integer) 9000
+/// 4) "connected"
+/// 5) (integer) 3167038
+/// ```
+#[derive(Debug, Default)]
+pub struct Role;
+
+impl Role {
+    /// Create a new `Role` command.
+    pub fn new() -> Role {
+        Role
+    }
+
+    /// Parse a `Role` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ROLE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Role` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// ROLE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Role> {
+        Ok(Role)
+    }
+
+    /// Apply the `Role` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.role() {
+            replication::Role::Primary { offset, replicas } => {
+                let replicas = replicas
+                    .into_iter()
+                    .map(|(host, port, acked)| {
+                        Frame::Array(vec![
+                            Frame::Bulk(Bytes::from(host.into_bytes())),
+                            Frame::Bulk(Bytes::from(port.to_string().into_bytes())),
+                            Frame::Bulk(Bytes::from(acked.to_string().into_bytes())),
+                        ])
+                    })
+                    .collect();
+
+                Frame::Array(vec![
+                    Frame::Bulk(Bytes::from("master".as_bytes())),
+                    Frame::Integer(offset as i64),
+                    Frame::Array(replicas),
+                ])
+            }
+            replication::Role::Replica { addr, link, offset } => {
+                let (host, port) = addr.rsplit_once(':').unwrap_or((&addr, "0"));
+                Frame::Array(vec![
+                    Frame::Bulk(Bytes::from("slave".as_bytes())),
+                    Frame::Bulk(Bytes::from(host.to_string().into_bytes())),
+                    Frame::Integer(port.parse().unwrap_or(0)),
+                    Frame::Bulk(Bytes::from(link.name().as_bytes())),
+                    Frame::Integer(offset.map_or(-1, |offset| offset as i64)),
+                ])
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Role` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("role".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Add the specified members to the set stored at `key`.
+///
+/// Specified members that are already a member of this set are ignored. If
+/// `key` does not exist, a new set is created before adding the specified
+/// members.
+///
+/// Integer reply: The number of members that were added to the set, not
+/// including all the members already present in the set.
+#[derive(Debug)]
+pub struct SAdd {
+    /// Name of the key holding the set
+    key: String,
+
+    /// Members to add
+    members: Vec<Bytes>,
+}
+
+impl SAdd {
+    /// Create a new `SAdd` command which adds `members` to the set stored at
+    /// `key`.
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> SAdd {
+        SAdd {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the members to add
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse a `SAdd` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SADD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SAdd` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// SADD key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SAdd> {
+        let key = parse.next_string()?;
+        let mut members = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                // Finish reading all the members
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(SAdd { key, members })
+    }
+
+    /// Apply the `SAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.sadd(self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for member in self.members {
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
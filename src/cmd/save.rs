@@ -0,0 +1,202 @@
+use crate::persistence::{save_snapshot, write_snapshot};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, error, info, instrument};
+
+/// Save a snapshot of the databases to disk.
+///
+/// The snapshot is written before replying, and no other command runs on the
+/// connection in the meantime. Returns an error if snapshots are disabled or
+/// if a snapshot is already being saved.
+#[derive(Debug, Default)]
+pub struct Save;
+
+/// Save a snapshot of the databases to disk in the background.
+///
+/// The databases are copied right away, then written to disk by a background
+/// task while commands keep being served. `LASTSAVE` reports when the snapshot
+/// was saved.
+#[derive(Debug, Default)]
+pub struct BgSave;
+
+/// Returns the Unix time, in seconds, at which the last snapshot was
+/// successfully saved, or at which the server started if none was.
+#[derive(Debug, Default)]
+pub struct LastSave;
+
+impl Save {
+    /// Create a new `Save` command.
+    pub fn new() -> Save {
+        Save
+    }
+
+    /// Parse a `Save` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SAVE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Save` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// SAVE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Save> {
+        Ok(Save)
+    }
+
+    /// Apply the `Save` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.snapshot_path() {
+            Some(path) => match save_snapshot(db, &path).await {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => snapshots_disabled(),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Save` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("save".as_bytes()));
+        frame
+    }
+}
+
+impl BgSave {
+    /// Create a new `BgSave` command.
+    pub fn new() -> BgSave {
+        BgSave
+    }
+
+    /// Parse a `BgSave` instance from a received frame.
+    ///
+    /// The `BGSAVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// BGSAVE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<BgSave> {
+        Ok(BgSave)
+    }
+
+    /// Apply the `BgSave` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.snapshot_path() {
+            // The databases are copied before replying, so that the snapshot
+            // holds every write acknowledged before `BGSAVE`.
+            Some(path) => match db.begin_snapshot() {
+                Some(snapshot) => {
+                    let db = db.clone();
+                    tokio::spawn(async move {
+                        match write_snapshot(&db, snapshot, &path).await {
+                            Ok(()) => info!(path = %path.display(), "background save done"),
+                            Err(err) => error!(cause = %err, "background save failed"),
+                        }
+                    });
+                    Frame::Simple("Background saving started".to_string())
+                }
+                None => Frame::Error("ERR Background save already in progress".to_string()),
+            },
+            None => snapshots_disabled(),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BgSave` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bgsave".as_bytes()));
+        frame
+    }
+}
+
+impl LastSave {
+    /// Create a new `LastSave` command.
+    pub fn new() -> LastSave {
+        LastSave
+    }
+
+    /// Parse a `LastSave` instance from a received frame.
+    ///
+    /// The `LASTSAVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// LASTSAVE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<LastSave> {
+        Ok(LastSave)
+    }
+
+    /// Apply the `LastSave` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let last_save = db
+            .last_save()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let response = Frame::Integer(last_save.as_secs() as i64);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `LastSave` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lastsave".as_bytes()));
+        frame
+    }
+}
+
+fn snapshots_disabled() -> Frame {
+    Frame::Error("ERR Snapshots are disabled, no snapshot file is configured".to_string())
+}
@@ -0,0 +1,566 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Number of keys visited by a `SCAN` call when `COUNT` is not given.
+const DEFAULT_COUNT: u64 = 10;
+
+/// Incrementally iterate over the keys.
+///
+/// Each call returns a few keys along with a cursor to pass to the next call.
+/// The iteration starts with cursor `0` and is complete when the server
+/// returns cursor `0` again. Unlike `KEYS`, each call only visits about
+/// `COUNT` keys, so the key space is never locked for long.
+///
+/// A key present during the whole iteration is returned at least once, but
+/// may be returned multiple times. Keys added or removed during the iteration
+/// may or may not be returned.
+///
+/// The `MATCH` and `TYPE` filters are applied after the keys are visited, a
+/// call may return no keys while the iteration is not complete.
+#[derive(Debug)]
+pub struct Scan {
+    /// Cursor to continue the iteration at, `0` to start a new one
+    cursor: u64,
+
+    /// Only return keys matching this glob-style pattern
+    pattern: Option<String>,
+
+    /// Number of keys to visit
+    count: Option<u64>,
+
+    /// Only return keys holding a value of this type
+    ty: Option<String>,
+}
+
+/// Incrementally iterate over the fields of a hash.
+///
+/// Each element returned is a field, followed by its value. Same cursor,
+/// `MATCH` and `COUNT` semantics as `Scan`, the pattern is matched against
+/// the field names.
+#[derive(Debug)]
+pub struct HScan {
+    /// Name of the key holding the hash
+    key: String,
+
+    /// Cursor to continue the iteration at, `0` to start a new one
+    cursor: u64,
+
+    /// Only return fields matching this glob-style pattern
+    pattern: Option<String>,
+
+    /// Number of fields to visit
+    count: Option<u64>,
+}
+
+/// Incrementally iterate over the members of a set.
+///
+/// Same cursor, `MATCH` and `COUNT` semantics as `Scan`.
+#[derive(Debug)]
+pub struct SScan {
+    /// Name of the key holding the set
+    key: String,
+
+    /// Cursor to continue the iteration at, `0` to start a new one
+    cursor: u64,
+
+    /// Only return members matching this glob-style pattern
+    pattern: Option<String>,
+
+    /// Number of members to visit
+    count: Option<u64>,
+}
+
+/// Incrementally iterate over the members of a sorted set.
+///
+/// Each element returned is a member, followed by its score. Same cursor,
+/// `MATCH` and `COUNT` semantics as `Scan`. Members are not returned in score
+/// order.
+#[derive(Debug)]
+pub struct ZScan {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Cursor to continue the iteration at, `0` to start a new one
+    cursor: u64,
+
+    /// Only return members matching this glob-style pattern
+    pattern: Option<String>,
+
+    /// Number of members to visit
+    count: Option<u64>,
+}
+
+impl Scan {
+    /// Create a new `Scan` command which continues the iteration at `cursor`.
+    pub fn new(cursor: u64) -> Scan {
+        Scan {
+            cursor,
+            pattern: None,
+            count: None,
+            ty: None,
+        }
+    }
+
+    /// Only return keys matching the glob-style `pattern`.
+    pub fn pattern(mut self, pattern: Option<String>) -> Scan {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Visit about `count` keys.
+    pub fn count(mut self, count: Option<u64>) -> Scan {
+        self.count = count;
+        self
+    }
+
+    /// Only return keys holding a value of type `ty`, as reported by `TYPE`.
+    pub fn ty(mut self, ty: Option<String>) -> Scan {
+        self.ty = ty;
+        self
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Parse a `Scan` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SCAN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Scan` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        let mut scan = Scan::new(parse.next_int()?);
+        let options = parse_options(parse)?;
+
+        scan.pattern = options.pattern;
+        scan.count = options.count;
+        scan.ty = options.ty;
+
+        Ok(scan)
+    }
+
+    /// Apply the `Scan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match scan_count(self.count) {
+            Some(count) => {
+                let (cursor, keys) = db.scan(
+                    self.cursor,
+                    count,
+                    self.pattern.as_deref(),
+                    self.ty.as_deref(),
+                );
+                let keys = keys.into_iter().map(|key| Bytes::from(key.into_bytes()));
+                scan_reply(cursor, keys)
+            }
+            None => Frame::Error("ERR syntax error".into()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Scan` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("scan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string().into_bytes()));
+        push_scan_options(&mut frame, self.pattern, self.count);
+
+        if let Some(ty) = self.ty {
+            frame.push_bulk(Bytes::from("type".as_bytes()));
+            frame.push_bulk(Bytes::from(ty.into_bytes()));
+        }
+
+        frame
+    }
+}
+
+impl HScan {
+    /// Create a new `HScan` command which continues the iteration over the
+    /// hash stored at `key` at `cursor`.
+    pub fn new(key: impl ToString, cursor: u64) -> HScan {
+        HScan {
+            key: key.to_string(),
+            cursor,
+            pattern: None,
+            count: None,
+        }
+    }
+
+    /// Only return fields matching the glob-style `pattern`.
+    pub fn pattern(mut self, pattern: Option<String>) -> HScan {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Visit about `count` fields.
+    pub fn count(mut self, count: Option<u64>) -> HScan {
+        self.count = count;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Parse a `HScan` instance from a received frame.
+    ///
+    /// The `HSCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// HSCAN key cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HScan> {
+        let key = parse.next_string()?;
+        let cursor = parse.next_int()?;
+        let options = parse_options(parse)?;
+
+        if options.ty.is_some() {
+            return Err("ERR syntax error".into());
+        }
+
+        Ok(HScan {
+            key,
+            cursor,
+            pattern: options.pattern,
+            count: options.count,
+        })
+    }
+
+    /// Apply the `HScan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match scan_count(self.count) {
+            Some(count) => match db.hscan(&self.key, self.cursor, count, self.pattern.as_deref()) {
+                Ok((cursor, fields)) => {
+                    let fields = fields
+                        .into_iter()
+                        .flat_map(|(field, value)| vec![Bytes::from(field.into_bytes()), value]);
+                    scan_reply(cursor, fields)
+                }
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error("ERR syntax error".into()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HScan` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hscan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string().into_bytes()));
+        push_scan_options(&mut frame, self.pattern, self.count);
+        frame
+    }
+}
+
+impl SScan {
+    /// Create a new `SScan` command which continues the iteration over the
+    /// set stored at `key` at `cursor`.
+    pub fn new(key: impl ToString, cursor: u64) -> SScan {
+        SScan {
+            key: key.to_string(),
+            cursor,
+            pattern: None,
+            count: None,
+        }
+    }
+
+    /// Only return members matching the glob-style `pattern`.
+    pub fn pattern(mut self, pattern: Option<String>) -> SScan {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Visit about `count` members.
+    pub fn count(mut self, count: Option<u64>) -> SScan {
+        self.count = count;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Parse a `SScan` instance from a received frame.
+    ///
+    /// The `SSCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SSCAN key cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SScan> {
+        let key = parse.next_string()?;
+        let cursor = parse.next_int()?;
+        let options = parse_options(parse)?;
+
+        if options.ty.is_some() {
+            return Err("ERR syntax error".into());
+        }
+
+        Ok(SScan {
+            key,
+            cursor,
+            pattern: options.pattern,
+            count: options.count,
+        })
+    }
+
+    /// Apply the `SScan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match scan_count(self.count) {
+            Some(count) => match db.sscan(&self.key, self.cursor, count, self.pattern.as_deref()) {
+                Ok((cursor, members)) => scan_reply(cursor, members.into_iter()),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error("ERR syntax error".into()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SScan` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sscan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string().into_bytes()));
+        push_scan_options(&mut frame, self.pattern, self.count);
+        frame
+    }
+}
+
+impl ZScan {
+    /// Create a new `ZScan` command which continues the iteration over the
+    /// sorted set stored at `key` at `cursor`.
+    pub fn new(key: impl ToString, cursor: u64) -> ZScan {
+        ZScan {
+            key: key.to_string(),
+            cursor,
+            pattern: None,
+            count: None,
+        }
+    }
+
+    /// Only return members matching the glob-style `pattern`.
+    pub fn pattern(mut self, pattern: Option<String>) -> ZScan {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Visit about `count` members.
+    pub fn count(mut self, count: Option<u64>) -> ZScan {
+        self.count = count;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Parse a `ZScan` instance from a received frame.
+    ///
+    /// The `ZSCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// ZSCAN key cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScan> {
+        let key = parse.next_string()?;
+        let cursor = parse.next_int()?;
+        let options = parse_options(parse)?;
+
+        if options.ty.is_some() {
+            return Err("ERR syntax error".into());
+        }
+
+        Ok(ZScan {
+            key,
+            cursor,
+            pattern: options.pattern,
+            count: options.count,
+        })
+    }
+
+    /// Apply the `ZScan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match scan_count(self.count) {
+            Some(count) => match db.zscan(&self.key, self.cursor, count, self.pattern.as_deref()) {
+                Ok((cursor, members)) => {
+                    let members = members.into_iter().flat_map(|(member, score)| {
+                        vec![member, Bytes::from(score.to_string().into_bytes())]
+                    });
+                    scan_reply(cursor, members)
+                }
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error("ERR syntax error".into()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZScan` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zscan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string().into_bytes()));
+        push_scan_options(&mut frame, self.pattern, self.count);
+        frame
+    }
+}
+
+/// Options shared by the scan commands.
+#[derive(Debug, Default)]
+struct ScanOptions {
+    pattern: Option<String>,
+    count: Option<u64>,
+    ty: Option<String>,
+}
+
+/// Parses the `MATCH`, `COUNT` and `TYPE` options of the scan commands, in
+/// any order.
+fn parse_options(parse: &mut Parse) -> crate::Result<ScanOptions> {
+    let mut options = ScanOptions::default();
+
+    loop {
+        let option = match parse.next_string() {
+            Ok(option) => option.to_uppercase(),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        match &option[..] {
+            "MATCH" => options.pattern = Some(parse.next_string()?),
+            "COUNT" => options.count = Some(parse.next_int()?),
+            "TYPE" => options.ty = Some(parse.next_string()?),
+            _ => return Err("ERR syntax error".into()),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Returns the number of elements to visit for the `COUNT` option, or `None`
+/// if it is not positive.
+fn scan_count(count: Option<u64>) -> Option<usize> {
+    match count {
+        Some(0) => None,
+        Some(count) => Some(usize::try_from(count).unwrap_or(usize::MAX)),
+        None => Some(DEFAULT_COUNT as usize),
+    }
+}
+
+/// Encodes the reply of a scan command: the next cursor followed by the array
+/// of elements.
+fn scan_reply(cursor: u64, elements: impl Iterator<Item = Bytes>) -> Frame {
+    let mut page = Frame::array();
+    for element in elements {
+        page.push_bulk(element);
+    }
+
+    Frame::Array(vec![
+        Frame::Bulk(Bytes::from(cursor.to_string().into_bytes())),
+        page,
+    ])
+}
+
+/// Appends the `MATCH` and `COUNT` options shared by the scan commands.
+fn push_scan_options(frame: &mut Frame, pattern: Option<String>, count: Option<u64>) {
+    if let Some(pattern) = pattern {
+        frame.push_bulk(Bytes::from("match".as_bytes()));
+        frame.push_bulk(Bytes::from(pattern.into_bytes()));
+    }
+
+    if let Some(count) = count {
+        frame.push_bulk(Bytes::from("count".as_bytes()));
+        frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the cardinality, that is the number of members, of the set stored at
+/// `key`.
+///
+/// Integer reply: The cardinality of the set, or `0` if `key` does not exist.
+#[derive(Debug)]
+pub struct SCard {
+    /// Name of the key holding the set
+    key: String,
+}
+
+impl SCard {
+    /// Create a new `SCard` command which fetches the cardinality of the set
+    /// stored at `key`.
+    pub fn new(key: impl ToString) -> SCard {
+        SCard {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `SCard` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SCARD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SCard` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SCARD key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SCard> {
+        let key = parse.next_string()?;
+
+        Ok(SCard { key })
+    }
+
+    /// Apply the `SCard` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.scard(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SCard` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("scard".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
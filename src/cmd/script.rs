@@ -0,0 +1,190 @@
+use crate::script::{sha1_hex, Script as Compiled};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+/// Manages the script cache.
+///
+/// # Subcommands
+///
+/// * LOAD `script` -- Cache a script without running it. The reply is the
+///   SHA1 digest of the script, which `EVALSHA` runs.
+/// * EXISTS `sha1` [`sha1` ...] -- Returns, for each digest, 1 if the script
+///   is cached and 0 otherwise.
+/// * FLUSH [ASYNC|SYNC] -- Remove all the scripts from the cache.
+/// * KILL -- Stop the script running for longer than `lua-time-limit`,
+///   unless it already executed write commands.
+#[derive(Debug)]
+pub struct Script {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Load(Bytes),
+    Exists(Vec<String>),
+    Flush,
+    Kill,
+}
+
+impl Script {
+    /// Create a new `Script` command which caches `script`.
+    pub fn load(script: impl ToString) -> Script {
+        Script {
+            subcommand: Subcommand::Load(Bytes::from(script.to_string())),
+        }
+    }
+
+    /// Create a new `Script` command which checks whether the scripts with
+    /// the SHA1 digests `shas` are cached.
+    pub fn exists(shas: Vec<String>) -> Script {
+        Script {
+            subcommand: Subcommand::Exists(shas),
+        }
+    }
+
+    /// Create a new `Script` command which removes all the scripts from the
+    /// cache.
+    pub fn flush() -> Script {
+        Script {
+            subcommand: Subcommand::Flush,
+        }
+    }
+
+    /// Create a new `Script` command which stops the running script.
+    pub fn kill() -> Script {
+        Script {
+            subcommand: Subcommand::Kill,
+        }
+    }
+
+    /// Parse a `Script` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SCRIPT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Script` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SCRIPT LOAD script
+    /// SCRIPT EXISTS sha1 [sha1 ...]
+    /// SCRIPT FLUSH [ASYNC|SYNC]
+    /// SCRIPT KILL
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Script> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "LOAD" => Subcommand::Load(parse.next_bytes()?),
+            "EXISTS" => {
+                let mut shas = vec![parse.next_string()?];
+                loop {
+                    match parse.next_string() {
+                        Ok(sha) => shas.push(sha),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                Subcommand::Exists(shas)
+            }
+            "FLUSH" => {
+                // Scripts are freed right away, both modes behave the same.
+                match parse.next_string() {
+                    Ok(mode) if mode.eq_ignore_ascii_case("async") => {}
+                    Ok(mode) if mode.eq_ignore_ascii_case("sync") => {}
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(ParseError::EndOfStream) => {}
+                    Err(err) => return Err(err.into()),
+                }
+                Subcommand::Flush
+            }
+            "KILL" => Subcommand::Kill,
+            _ => return Err(format!("unknown `SCRIPT` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Script { subcommand })
+    }
+
+    /// Apply the `Script` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Load(script) => match Compiled::compile(&script) {
+                Ok(compiled) => {
+                    let sha = sha1_hex(&script);
+                    db.cache_script(sha.clone(), Arc::new(compiled));
+                    Frame::Bulk(Bytes::from(sha.into_bytes()))
+                }
+                Err(err) => Frame::Error(format!("ERR Error compiling script: {}", err)),
+            },
+            Subcommand::Exists(shas) => Frame::Array(
+                db.scripts_exist(&shas)
+                    .into_iter()
+                    .map(|exists| Frame::Integer(exists as i64))
+                    .collect(),
+            ),
+            Subcommand::Flush => {
+                db.flush_scripts();
+                Frame::Simple("OK".to_string())
+            }
+            // A script running for longer than `lua-time-limit` is killed
+            // by the server before the command is applied, so the script
+            // already ended by now.
+            Subcommand::Kill => match db.kill_script(false) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Returns `true` for `SCRIPT KILL`, which may be called while a
+    /// script runs for longer than `lua-time-limit`.
+    pub(crate) fn is_kill(&self) -> bool {
+        matches!(self.subcommand, Subcommand::Kill)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Script` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("script".as_bytes()));
+
+        match self.subcommand {
+            Subcommand::Load(script) => {
+                frame.push_bulk(Bytes::from("load".as_bytes()));
+                frame.push_bulk(script);
+            }
+            Subcommand::Exists(shas) => {
+                frame.push_bulk(Bytes::from("exists".as_bytes()));
+                for sha in shas {
+                    frame.push_bulk(Bytes::from(sha.into_bytes()));
+                }
+            }
+            Subcommand::Flush => frame.push_bulk(Bytes::from("flush".as_bytes())),
+            Subcommand::Kill => frame.push_bulk(Bytes::from("kill".as_bytes())),
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Select the database the following commands of the connection apply to.
+///
+/// Databases are numbered from `0`, which is the one selected when a
+/// connection is established.
+#[derive(Debug)]
+pub struct Select {
+    /// Index of the database to select.
+    index: u64,
+}
+
+impl Select {
+    /// Create a new `Select` command which selects the database at `index`.
+    pub fn new(index: u64) -> Select {
+        Select { index }
+    }
+
+    /// Get the index of the database to select.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Parse a `Select` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SELECT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Select` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SELECT index
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Select> {
+        let index = parse.next_int()?;
+
+        Ok(Select { index })
+    }
+
+    /// Apply the `Select` command, replacing `db` with a handle to the
+    /// requested database.
+    ///
+    /// `db` is the handle owned by the connection, so the selection lasts
+    /// until the connection selects another database. The response is written
+    /// to `dst`. This is called by the server in order to execute a received
+    /// command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &mut Db, dst: &mut Connection) -> crate::Result<()> {
+        // In cluster mode, only the first database is used.
+        if self.index != 0 && db.cluster_enabled() {
+            let response = Frame::Error("ERR SELECT is not allowed in cluster mode".to_string());
+            debug!(?response);
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        let response = match db.select(self.index as usize) {
+            Ok(selected) => {
+                *db = selected;
+                Frame::Simple("OK".to_string())
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Select` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("select".as_bytes()));
+        frame.push_int(self.index as i64);
+        frame
+    }
+}
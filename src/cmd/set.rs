@@ -1,8 +1,10 @@
 use crate::cmd::{Parse, ParseError};
+use crate::db::{SetCondition, SetOptions};
 use crate::{Connection, Db, Frame};
 
 use bytes::Bytes;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
 use tracing::{debug, instrument};
 
 /// Set `key` to hold the string `value`.
@@ -17,6 +19,18 @@ use tracing::{debug, instrument};
 ///
 /// * EX `seconds` -- Set the specified expire time, in seconds.
 /// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp` -- Set the specified Unix time at which the key will
+///   expire, in seconds.
+/// * PXAT `timestamp` -- Set the specified Unix time at which the key will
+///   expire, in milliseconds.
+/// * NX -- Only set the key if it does not already exist.
+/// * XX -- Only set the key if it already exists.
+/// * KEEPTTL -- Retain the time to live associated with the key.
+/// * GET -- Return the old string stored at key, or nil if key did not exist.
+///
+/// Without `GET`, the reply is `OK`, or nil when the `NX` or `XX` condition
+/// was not met. With `GET`, the reply is the old value, whether the key was
+/// set or not.
 #[derive(Debug)]
 pub struct Set {
     /// the lookup key
@@ -27,6 +41,18 @@ pub struct Set {
 
     /// When to expire the key
     expire: Option<Duration>,
+
+    /// Unix time at which to expire the key
+    expire_at: Option<SystemTime>,
+
+    /// Condition under which the key is set
+    condition: Option<SetCondition>,
+
+    /// Keep the time to live of the key
+    keep_ttl: bool,
+
+    /// Return the old value
+    get: bool,
 }
 
 impl Set {
@@ -39,9 +65,38 @@ impl Set {
             key: key.to_string(),
             value,
             expire,
+            expire_at: None,
+            condition: None,
+            keep_ttl: false,
+            get: false,
         }
     }
 
+    /// Expire the key at the Unix time `when` instead of after a duration.
+    pub fn expire_at(mut self, when: SystemTime) -> Set {
+        self.expire_at = Some(when);
+        self
+    }
+
+    /// Only set the key if `condition` is met.
+    pub fn condition(mut self, condition: SetCondition) -> Set {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Retain the time to live associated with the key when `keep_ttl` is
+    /// set.
+    pub fn keep_ttl(mut self, keep_ttl: bool) -> Set {
+        self.keep_ttl = keep_ttl;
+        self
+    }
+
+    /// Return the old value of the key when `get` is set.
+    pub fn get(mut self, get: bool) -> Set {
+        self.get = get;
+        self
+    }
+
     /// Get the key
     pub fn key(&self) -> &str {
         &self.key
@@ -75,7 +130,8 @@ impl Set {
     /// Expects an array frame containing at least 3 entries.
     ///
     /// ```text
-    /// SET key value [EX seconds|PX milliseconds]
+    /// SET key value [NX | XX] [GET]
+    ///     [EX seconds | PX milliseconds | EXAT timestamp | PXAT timestamp | KEEPTTL]
     /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Set> {
         use ParseError::EndOfStream;
@@ -86,38 +142,58 @@ impl Set {
         // Read the value to set. This is a required field.
         let value = parse.next_bytes()?;
 
-        // The expiration is optional. If nothing else follows, then it is
-        // `None`.
-        let mut expire = None;
+        let mut set = Set::new(key, value, None);
+
+        // The options are optional and may be given in any order. Options
+        // that conflict with each other, such as `NX` and `XX`, are a syntax
+        // error. An error here results in the connection being terminated.
+        // Other connections will continue to operate normally.
+        loop {
+            // Attempt to parse another string.
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                // The `EndOfStream` error indicates there is no further data to
+                // parse. In this case, it is a normal run time situation and
+                // indicates there are no more `SET` options.
+                Err(EndOfStream) => break,
+                // All other errors are bubbled up, resulting in the connection
+                // being terminated.
+                Err(err) => return Err(err.into()),
+            };
+
+            let has_expire = set.expire.is_some() || set.expire_at.is_some() || set.keep_ttl;
 
-        // Attempt to parse another string.
-        match parse.next_string() {
-            Ok(s) if s == "EX" => {
+            match &option[..] {
+                "NX" | "XX" if set.condition.is_none() => {
+                    set.condition = Some(if option == "NX" {
+                        SetCondition::Nx
+                    } else {
+                        SetCondition::Xx
+                    });
+                }
+                "GET" => set.get = true,
+                "KEEPTTL" if !has_expire => set.keep_ttl = true,
                 // An expiration is specified in seconds. The next value is an
                 // integer.
-                let secs = parse.next_int()?;
-                expire = Some(Duration::from_secs(secs));
-            }
-            Ok(s) if s == "PX" => {
+                "EX" if !has_expire => set.expire = Some(Duration::from_secs(parse.next_int()?)),
                 // An expiration is specified in milliseconds. The next value is
                 // an integer.
-                let ms = parse.next_int()?;
-                expire = Some(Duration::from_millis(ms));
+                "PX" if !has_expire => set.expire = Some(Duration::from_millis(parse.next_int()?)),
+                // An absolute expiration, as a Unix time in seconds or
+                // milliseconds.
+                "EXAT" if !has_expire => {
+                    let secs = Duration::from_secs(parse.next_int()?);
+                    set.expire_at = Some(UNIX_EPOCH + secs);
+                }
+                "PXAT" if !has_expire => {
+                    let ms = Duration::from_millis(parse.next_int()?);
+                    set.expire_at = Some(UNIX_EPOCH + ms);
+                }
+                _ => return Err("ERR syntax error".into()),
             }
-            // Currently, mini-redis does not support any of the other SET
-            // options. An error here results in the connection being
-            // terminated. Other connections will continue to operate normally.
-            Ok(_) => return Err("currently `SET` only supports the expiration option".into()),
-            // The `EndOfStream` error indicates there is no further data to
-            // parse. In this case, it is a normal run time situation and
-            // indicates there are no specified `SET` options.
-            Err(EndOfStream) => {}
-            // All other errors are bubbled up, resulting in the connection
-            // being terminated.
-            Err(err) => return Err(err.into()),
         }
 
-        Ok(Set { key, value, expire })
+        Ok(set)
     }
 
     /// Apply the `Set` command to the specified `Db` instance.
@@ -126,17 +202,46 @@ impl Set {
     /// to execute a received command.
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        // Set the value in the shared database state.
-        db.set(self.key, self.value, self.expire);
+        let response = self.execute(db, "set");
 
-        // Create a success response and write it to `dst`.
-        let response = Frame::Simple("OK".to_string());
+        // Write the response to `dst`.
         debug!(?response);
         dst.write_frame(&response).await?;
 
         Ok(())
     }
 
+    /// Set the value in the shared database state and return the response.
+    ///
+    /// This is the part of `apply` shared with the legacy commands built on
+    /// top of `Set`, such as `SetNx`. `name` is the name of the command
+    /// reported in errors.
+    pub(crate) fn execute(self, db: &Db, name: &str) -> Frame {
+        let expires_at = match expires_at(self.expire, self.expire_at) {
+            Some(expires_at) => expires_at,
+            None => return Frame::Error(format!("ERR invalid expire time in '{}' command", name)),
+        };
+
+        let options = SetOptions {
+            expires_at,
+            keep_ttl: self.keep_ttl,
+            condition: self.condition,
+            get: self.get,
+        };
+
+        match db.set_with(self.key, self.value, options) {
+            // With `GET`, the old value is returned whether the key was set or
+            // not.
+            Ok((_, Some(prev))) if self.get => Frame::Bulk(prev),
+            Ok((_, None)) if self.get => Frame::Null,
+            // Create a success response.
+            Ok((true, _)) => Frame::Simple("OK".to_string()),
+            // The `NX` or `XX` condition was not met.
+            Ok((false, _)) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding a `Set` command to send to
@@ -146,6 +251,62 @@ impl Set {
         frame.push_bulk(Bytes::from("set".as_bytes()));
         frame.push_bulk(Bytes::from(self.key.into_bytes()));
         frame.push_bulk(self.value);
+        if let Some(expire) = self.expire {
+            // Expirations are always sent with a millisecond resolution.
+            frame.push_bulk(Bytes::from("PX".as_bytes()));
+            frame.push_bulk(Bytes::from(expire.as_millis().to_string().into_bytes()));
+        }
+        if let Some(when) = self.expire_at {
+            let ms = when
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_millis())
+                .unwrap_or(0);
+            frame.push_bulk(Bytes::from("PXAT".as_bytes()));
+            frame.push_bulk(Bytes::from(ms.to_string().into_bytes()));
+        }
+        match self.condition {
+            Some(SetCondition::Nx) => frame.push_bulk(Bytes::from("NX".as_bytes())),
+            Some(SetCondition::Xx) => frame.push_bulk(Bytes::from("XX".as_bytes())),
+            None => {}
+        }
+        if self.keep_ttl {
+            frame.push_bulk(Bytes::from("KEEPTTL".as_bytes()));
+        }
+        if self.get {
+            frame.push_bulk(Bytes::from("GET".as_bytes()));
+        }
         frame
     }
 }
+
+/// Returns the `Instant` at which a key expires, given either a time to live
+/// or a Unix time.
+///
+/// Returns `None` if the expiration is zero, which Redis rejects, or cannot be
+/// represented.
+pub(crate) fn expires_at(
+    expire: Option<Duration>,
+    expire_at: Option<SystemTime>,
+) -> Option<Option<Instant>> {
+    if let Some(expire) = expire {
+        if expire == Duration::from_secs(0) {
+            return None;
+        }
+        return Instant::now().checked_add(expire).map(Some);
+    }
+
+    if let Some(when) = expire_at {
+        if when == UNIX_EPOCH {
+            return None;
+        }
+
+        // Times in the past are kept, setting the key then deletes it.
+        let now = Instant::now();
+        return match when.duration_since(SystemTime::now()) {
+            Ok(duration) => now.checked_add(duration).map(Some),
+            Err(_) => Some(Some(now)),
+        };
+    }
+
+    Some(None)
+}
@@ -0,0 +1,119 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Bit offsets must fit in a 512MB string, as in Redis.
+pub(crate) const MAX_OFFSET: u64 = 1 << 32;
+
+/// Sets or clears the bit at `offset` in the string value stored at `key`.
+///
+/// The string is grown to make sure it can hold a bit at `offset`, any new
+/// bytes are zeroed. If `key` does not exist, a new string value is created.
+///
+/// The original bit value stored at `offset` is returned.
+#[derive(Debug)]
+pub struct SetBit {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Offset of the bit, counting from the most significant bit of the first
+    /// byte
+    offset: u64,
+
+    /// Value to set the bit to, validated to be `0` or `1` when applied
+    value: u64,
+}
+
+impl SetBit {
+    /// Create a new `SetBit` command which sets the bit at `offset` in the
+    /// string stored at `key` to `bit`.
+    pub fn new(key: impl ToString, offset: u64, bit: bool) -> SetBit {
+        SetBit {
+            key: key.to_string(),
+            offset,
+            value: bit as u64,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the bit value
+    pub fn bit(&self) -> bool {
+        self.value == 1
+    }
+
+    /// Parse a `SetBit` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETBIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetBit` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETBIT key offset value
+    /// ```
+    ///
+    /// `value` must be either `0` or `1`.
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetBit> {
+        let key = parse.next_string()?;
+        let offset = parse.next_int()?;
+
+        let value = parse.next_int()?;
+
+        Ok(SetBit { key, offset, value })
+    }
+
+    /// Apply the `SetBit` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.offset >= MAX_OFFSET {
+            Frame::Error("ERR bit offset is not an integer or out of range".into())
+        } else if self.value > 1 {
+            Frame::Error("ERR bit is not an integer or out of range".into())
+        } else {
+            match db.setbit(self.key, self.offset as usize, self.value == 1) {
+                Ok(prev) => Frame::Integer(prev as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetBit` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setbit".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.offset.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(self.value.to_string().into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,212 @@
+use crate::cmd::Set;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Set `key` to hold the string `value`, expiring after `seconds`.
+///
+/// Equivalent to `SET key value EX seconds`.
+#[derive(Debug)]
+pub struct SetEx {
+    /// the lookup key
+    key: String,
+
+    /// Time to live, in seconds
+    seconds: i64,
+
+    /// the value to be stored
+    value: Bytes,
+}
+
+/// Set `key` to hold the string `value`, expiring after `milliseconds`.
+///
+/// Equivalent to `SET key value PX milliseconds`.
+#[derive(Debug)]
+pub struct PSetEx {
+    /// the lookup key
+    key: String,
+
+    /// Time to live, in milliseconds
+    milliseconds: i64,
+
+    /// the value to be stored
+    value: Bytes,
+}
+
+impl SetEx {
+    /// Create a new `SetEx` command which sets `key` to `value`, expiring
+    /// after `seconds`.
+    pub fn new(key: impl ToString, seconds: i64, value: Bytes) -> SetEx {
+        SetEx {
+            key: key.to_string(),
+            seconds,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the time to live, in seconds
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetEx` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETEX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetEx` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETEX key seconds value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetEx> {
+        let key = parse.next_string()?;
+        let seconds = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetEx {
+            key,
+            seconds,
+            value,
+        })
+    }
+
+    /// Apply the `SetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expire = u64::try_from(self.seconds).ok().map(Duration::from_secs);
+        let response = apply_set_ex(db, "setex", self.key, expire, self.value);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetEx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_ex_frame("setex", self.key, self.seconds, self.value)
+    }
+}
+
+impl PSetEx {
+    /// Create a new `PSetEx` command which sets `key` to `value`, expiring
+    /// after `milliseconds`.
+    pub fn new(key: impl ToString, milliseconds: i64, value: Bytes) -> PSetEx {
+        PSetEx {
+            key: key.to_string(),
+            milliseconds,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the time to live, in milliseconds
+    pub fn milliseconds(&self) -> i64 {
+        self.milliseconds
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `PSetEx` instance from a received frame.
+    ///
+    /// The `PSETEX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// PSETEX key milliseconds value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSetEx> {
+        let key = parse.next_string()?;
+        let milliseconds = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(PSetEx {
+            key,
+            milliseconds,
+            value,
+        })
+    }
+
+    /// Apply the `PSetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expire = u64::try_from(self.milliseconds)
+            .ok()
+            .map(Duration::from_millis);
+        let response = apply_set_ex(db, "psetex", self.key, expire, self.value);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PSetEx` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_ex_frame("psetex", self.key, self.milliseconds, self.value)
+    }
+}
+
+/// Sets `key` through the `Set` apply path. A missing `expire` means the time
+/// to live given was negative.
+fn apply_set_ex(db: &Db, name: &str, key: String, expire: Option<Duration>, value: Bytes) -> Frame {
+    match expire {
+        // A zero expiration is rejected by `Set` itself.
+        Some(expire) => Set::new(key, value, Some(expire)).execute(db, name),
+        None => Frame::Error(format!("ERR invalid expire time in '{}' command", name)),
+    }
+}
+
+/// Encodes a `SETEX` like command named `name` into a frame.
+fn set_ex_frame(name: &'static str, key: String, time: i64, value: Bytes) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    frame.push_bulk(Bytes::from(time.to_string().into_bytes()));
+    frame.push_bulk(value);
+    frame
+}
@@ -0,0 +1,97 @@
+use crate::cmd::{Set, SetCondition};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Set `key` to hold the string `value`, only if `key` does not exist.
+///
+/// Equivalent to `SET key value NX`, except for the reply: `1` if the key was
+/// set, `0` otherwise.
+#[derive(Debug)]
+pub struct SetNx {
+    /// the lookup key
+    key: String,
+
+    /// the value to be stored
+    value: Bytes,
+}
+
+impl SetNx {
+    /// Create a new `SetNx` command which sets `key` to `value` if `key` does
+    /// not exist.
+    pub fn new(key: impl ToString, value: Bytes) -> SetNx {
+        SetNx {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetNx` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETNX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetNx` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SETNX key value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetNx> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetNx { key, value })
+    }
+
+    /// Apply the `SetNx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let set = Set::new(self.key, self.value, None).condition(SetCondition::Nx);
+
+        let response = match set.execute(db, "setnx") {
+            Frame::Simple(_) => Frame::Integer(1),
+            Frame::Null => Frame::Integer(0),
+            response => response,
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetNx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setnx".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.value);
+        frame
+    }
+}
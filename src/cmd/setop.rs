@@ -0,0 +1,476 @@
+use crate::db::SetOperation;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the members of the intersection of the sets stored at `keys`.
+///
+/// Keys that do not exist are considered to be empty sets, so the intersection
+/// is empty if one of them does not exist.
+///
+/// Array reply: the members of the intersection.
+#[derive(Debug)]
+pub struct SInter {
+    /// Names of the keys holding the sets
+    keys: Vec<String>,
+}
+
+/// Returns the members of the union of the sets stored at `keys`.
+///
+/// Same as `SInter`, but returns the members of any of the sets.
+#[derive(Debug)]
+pub struct SUnion {
+    /// Names of the keys holding the sets
+    keys: Vec<String>,
+}
+
+/// Returns the members of the difference between the first set stored at
+/// `keys` and the other sets.
+///
+/// Same as `SInter`, but returns the members of the first set which are not
+/// members of any of the other sets.
+#[derive(Debug)]
+pub struct SDiff {
+    /// Names of the keys holding the sets
+    keys: Vec<String>,
+}
+
+/// Stores the intersection of the sets stored at `keys` at `dest`.
+///
+/// Same as `SInter`, but the intersection is stored as a set at `dest`,
+/// replacing any previous value, instead of being returned. `dest` is removed
+/// if the intersection is empty. The sets are combined and stored atomically.
+///
+/// Integer reply: the number of members of the intersection.
+#[derive(Debug)]
+pub struct SInterStore {
+    /// Name of the key to store the result at
+    dest: String,
+
+    /// Names of the keys holding the sets
+    keys: Vec<String>,
+}
+
+/// Stores the union of the sets stored at `keys` at `dest`.
+///
+/// Same as `SInterStore`, but stores the union of the sets.
+#[derive(Debug)]
+pub struct SUnionStore {
+    /// Name of the key to store the result at
+    dest: String,
+
+    /// Names of the keys holding the sets
+    keys: Vec<String>,
+}
+
+/// Stores the difference between the first set stored at `keys` and the
+/// other sets at `dest`.
+///
+/// Same as `SInterStore`, but stores the difference of the sets.
+#[derive(Debug)]
+pub struct SDiffStore {
+    /// Name of the key to store the result at
+    dest: String,
+
+    /// Names of the keys holding the sets
+    keys: Vec<String>,
+}
+
+impl SInter {
+    /// Create a new `SInter` command which returns the intersection of the sets
+    /// stored at `keys`.
+    pub fn new(keys: Vec<String>) -> SInter {
+        SInter { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SInter` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SINTER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SInter` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SINTER key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SInter> {
+        Ok(SInter {
+            keys: parse_keys(parse)?,
+        })
+    }
+
+    /// Apply the `SInter` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_set_operation(db, dst, &self.keys, SetOperation::Inter).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SInter` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_operation_frame("sinter", None, self.keys)
+    }
+}
+
+impl SUnion {
+    /// Create a new `SUnion` command which returns the union of the sets
+    /// stored at `keys`.
+    pub fn new(keys: Vec<String>) -> SUnion {
+        SUnion { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SUnion` instance from a received frame.
+    ///
+    /// The `SUNION` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SUNION key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SUnion> {
+        Ok(SUnion {
+            keys: parse_keys(parse)?,
+        })
+    }
+
+    /// Apply the `SUnion` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_set_operation(db, dst, &self.keys, SetOperation::Union).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SUnion` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_operation_frame("sunion", None, self.keys)
+    }
+}
+
+impl SDiff {
+    /// Create a new `SDiff` command which returns the difference of the sets
+    /// stored at `keys`.
+    pub fn new(keys: Vec<String>) -> SDiff {
+        SDiff { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SDiff` instance from a received frame.
+    ///
+    /// The `SDIFF` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SDIFF key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SDiff> {
+        Ok(SDiff {
+            keys: parse_keys(parse)?,
+        })
+    }
+
+    /// Apply the `SDiff` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_set_operation(db, dst, &self.keys, SetOperation::Diff).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SDiff` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_operation_frame("sdiff", None, self.keys)
+    }
+}
+
+impl SInterStore {
+    /// Create a new `SInterStore` command which stores the intersection of the sets
+    /// stored at `keys` at `dest`.
+    pub fn new(dest: impl ToString, keys: Vec<String>) -> SInterStore {
+        SInterStore {
+            dest: dest.to_string(),
+            keys,
+        }
+    }
+
+    /// Get the destination key
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SInterStore` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SINTERSTORE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SInterStore` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SINTERSTORE destination key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SInterStore> {
+        let dest = parse.next_string()?;
+
+        Ok(SInterStore {
+            dest,
+            keys: parse_keys(parse)?,
+        })
+    }
+
+    /// Apply the `SInterStore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_set_operation_store(db, dst, self.dest, &self.keys, SetOperation::Inter).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SInterStore` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_operation_frame("sinterstore", Some(self.dest), self.keys)
+    }
+}
+
+impl SUnionStore {
+    /// Create a new `SUnionStore` command which stores the union of the sets
+    /// stored at `keys` at `dest`.
+    pub fn new(dest: impl ToString, keys: Vec<String>) -> SUnionStore {
+        SUnionStore {
+            dest: dest.to_string(),
+            keys,
+        }
+    }
+
+    /// Get the destination key
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SUnionStore` instance from a received frame.
+    ///
+    /// The `SUNIONSTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SUNIONSTORE destination key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SUnionStore> {
+        let dest = parse.next_string()?;
+
+        Ok(SUnionStore {
+            dest,
+            keys: parse_keys(parse)?,
+        })
+    }
+
+    /// Apply the `SUnionStore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_set_operation_store(db, dst, self.dest, &self.keys, SetOperation::Union).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SUnionStore` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_operation_frame("sunionstore", Some(self.dest), self.keys)
+    }
+}
+
+impl SDiffStore {
+    /// Create a new `SDiffStore` command which stores the difference of the sets
+    /// stored at `keys` at `dest`.
+    pub fn new(dest: impl ToString, keys: Vec<String>) -> SDiffStore {
+        SDiffStore {
+            dest: dest.to_string(),
+            keys,
+        }
+    }
+
+    /// Get the destination key
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SDiffStore` instance from a received frame.
+    ///
+    /// The `SDIFFSTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SDIFFSTORE destination key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SDiffStore> {
+        let dest = parse.next_string()?;
+
+        Ok(SDiffStore {
+            dest,
+            keys: parse_keys(parse)?,
+        })
+    }
+
+    /// Apply the `SDiffStore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_set_operation_store(db, dst, self.dest, &self.keys, SetOperation::Diff).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SDiffStore` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        set_operation_frame("sdiffstore", Some(self.dest), self.keys)
+    }
+}
+
+/// Parses the keys of a set operation, at least one key is required.
+fn parse_keys(parse: &mut Parse) -> crate::Result<Vec<String>> {
+    let mut keys = vec![parse.next_string()?];
+
+    loop {
+        match parse.next_string() {
+            Ok(key) => keys.push(key),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Combines the sets stored at `keys` with `op` and writes the members of the
+/// resulting set to `dst`.
+async fn apply_set_operation(
+    db: &Db,
+    dst: &mut Connection,
+    keys: &[String],
+    op: SetOperation,
+) -> crate::Result<()> {
+    let response = match db.set_operation(keys, op) {
+        Ok(members) => Frame::Array(members.into_iter().map(Frame::Bulk).collect()),
+        Err(err) => Frame::Error(err.to_string()),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Combines the sets stored at `keys` with `op`, stores the resulting set at
+/// `dest` and writes its number of members to `dst`.
+async fn apply_set_operation_store(
+    db: &Db,
+    dst: &mut Connection,
+    dest: String,
+    keys: &[String],
+    op: SetOperation,
+) -> crate::Result<()> {
+    let response = match db.set_operation_store(dest, keys, op) {
+        Ok(len) => Frame::Integer(len as i64),
+        Err(err) => Frame::Error(err.to_string()),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes a set operation command named `name` into a frame.
+fn set_operation_frame(name: &'static str, dest: Option<String>, keys: Vec<String>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    if let Some(dest) = dest {
+        frame.push_bulk(Bytes::from(dest.into_bytes()));
+    }
+    for key in keys {
+        frame.push_bulk(Bytes::from(key.into_bytes()));
+    }
+    frame
+}
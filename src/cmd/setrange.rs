@@ -0,0 +1,114 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Overwrites part of the string stored at `key`, starting at `offset`, with
+/// `value`.
+///
+/// If the string is shorter than `offset`, it is padded with zero bytes to
+/// make `offset` fit. A missing key is considered an empty string, so the
+/// command creates it unless `value` is empty.
+///
+/// Integer reply: the length of the string after it was modified.
+#[derive(Debug)]
+pub struct SetRange {
+    /// Name of the key holding the string
+    key: String,
+
+    /// Offset to write `value` at, validated to be positive when applied
+    offset: i64,
+
+    /// Value to write
+    value: Bytes,
+}
+
+impl SetRange {
+    /// Create a new `SetRange` command which writes `value` at `offset` in the
+    /// string stored at `key`.
+    pub fn new(key: impl ToString, offset: u64, value: Bytes) -> SetRange {
+        SetRange {
+            key: key.to_string(),
+            offset: offset as i64,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetRange` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETRANGE key offset value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetRange> {
+        let key = parse.next_string()?;
+        let offset = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetRange { key, offset, value })
+    }
+
+    /// Apply the `SetRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.offset < 0 {
+            Frame::Error("ERR offset is out of range".into())
+        } else if self.offset as u64 + self.value.len() as u64 > db.config().proto_max_bulk_len {
+            Frame::Error("ERR string exceeds maximum allowed size (proto-max-bulk-len)".into())
+        } else {
+            match db.setrange(self.key, self.offset as usize, &self.value) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.offset.to_string().into_bytes()));
+        frame.push_bulk(self.value);
+        frame
+    }
+}
@@ -0,0 +1,108 @@
+use crate::persistence::save_snapshot;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, error, instrument};
+
+/// Shuts the server down, as the signal given to `server::run` does: the
+/// connections are closed once their current command completes.
+///
+/// By default, a last snapshot is saved if snapshots are enabled. `SAVE`
+/// saves it before shutting down, failing if snapshots are disabled, and
+/// `NOSAVE` shuts down without saving it.
+///
+/// Nothing is replied unless the server fails to save the snapshot, in which
+/// case it keeps running.
+#[derive(Debug, Default)]
+pub struct ShutdownServer {
+    /// `Some(true)` for `SAVE`, `Some(false)` for `NOSAVE`.
+    save: Option<bool>,
+}
+
+impl ShutdownServer {
+    /// Create a new `ShutdownServer` command, saving a last snapshot if
+    /// `save` is `Some(true)` and not saving it if `Some(false)`.
+    pub fn new(save: Option<bool>) -> ShutdownServer {
+        ShutdownServer { save }
+    }
+
+    /// Parse a `ShutdownServer` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SHUTDOWN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ShutdownServer` value on success. If the frame is
+    /// malformed, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// SHUTDOWN [NOSAVE|SAVE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ShutdownServer> {
+        let save = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "SAVE" => Some(true),
+            Ok(s) if s.to_uppercase() == "NOSAVE" => Some(false),
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ShutdownServer { save })
+    }
+
+    /// Apply the `ShutdownServer` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`, only if the server fails to shut
+    /// down. This is called by the server in order to execute a received
+    /// command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        if self.save == Some(true) {
+            let saved = match db.snapshot_path() {
+                Some(path) => save_snapshot(db, &path).await,
+                None => Err("snapshots are disabled".into()),
+            };
+
+            if let Err(err) = saved {
+                error!(cause = %err, "failed to save snapshot before shutting down");
+
+                let response = Frame::Error("ERR Errors trying to SHUTDOWN. Check logs.".into());
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        }
+
+        db.request_shutdown(self.save == Some(false));
+        Ok(())
+    }
+
+    /// Returns `true` for `SHUTDOWN NOSAVE`, which may be called while a
+    /// script runs for longer than `lua-time-limit`.
+    pub(crate) fn is_nosave(&self) -> bool {
+        self.save == Some(false)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ShutdownServer` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("shutdown".as_bytes()));
+        match self.save {
+            Some(true) => frame.push_bulk(Bytes::from("save".as_bytes())),
+            Some(false) => frame.push_bulk(Bytes::from("nosave".as_bytes())),
+            None => {}
+        }
+        frame
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the number of members of the intersection of the sets stored at
+/// the specified keys, without returning the intersection itself.
+///
+/// Keys that do not exist are considered to be empty sets.
+///
+/// Integer reply: the number of members of the intersection.
+///
+/// # Options
+///
+/// * LIMIT `limit` -- Stop counting once `limit` members are found, `0`
+///   meaning no limit. This makes the command cheaper when only a lower bound
+///   is needed.
+#[derive(Debug)]
+pub struct SInterCard {
+    /// Name of the keys holding the sets
+    keys: Vec<String>,
+
+    /// Maximum number of members to count, validated to not be negative when
+    /// applied
+    limit: i64,
+}
+
+impl SInterCard {
+    /// Create a new `SInterCard` command which counts the members of the
+    /// intersection of the sets stored at `keys`.
+    pub fn new(keys: Vec<String>) -> SInterCard {
+        SInterCard { keys, limit: 0 }
+    }
+
+    /// Stop counting once `limit` members are found.
+    pub fn limit(mut self, limit: u64) -> SInterCard {
+        self.limit = limit as i64;
+        self
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `SInterCard` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SINTERCARD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SInterCard` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SINTERCARD numkeys key [key ...] [LIMIT limit]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SInterCard> {
+        let numkeys = parse.next_signed_int()?;
+        if numkeys <= 0 {
+            return Err("ERR numkeys should be greater than 0".into());
+        }
+
+        let mut keys = Vec::with_capacity(numkeys as usize);
+        for _ in 0..numkeys {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => {
+                    return Err("ERR Number of keys can't be greater than number of args".into())
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut sintercard = SInterCard::new(keys);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "LIMIT" => sintercard.limit = parse.next_signed_int()?,
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(sintercard)
+    }
+
+    /// Apply the `SInterCard` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.limit < 0 {
+            Frame::Error("ERR LIMIT can't be negative".into())
+        } else {
+            match db.sintercard(&self.keys, self.limit as usize) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SInterCard` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sintercard".as_bytes()));
+        frame.push_bulk(Bytes::from(self.keys.len().to_string().into_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        if self.limit != 0 {
+            frame.push_bulk(Bytes::from("limit".as_bytes()));
+            frame.push_bulk(Bytes::from(self.limit.to_string().into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns if `member` is a member of the set stored at `key`.
+///
+/// Integer reply: `1` if the element is a member of the set, `0` if it is not
+/// or if `key` does not exist.
+#[derive(Debug)]
+pub struct SIsMember {
+    /// Name of the key holding the set
+    key: String,
+
+    /// Member to look for
+    member: Bytes,
+}
+
+impl SIsMember {
+    /// Create a new `SIsMember` command which checks if `member` belongs to the
+    /// set stored at `key`.
+    pub fn new(key: impl ToString, member: Bytes) -> SIsMember {
+        SIsMember {
+            key: key.to_string(),
+            member,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse a `SIsMember` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SISMEMBER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SIsMember` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SISMEMBER key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SIsMember> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+
+        Ok(SIsMember { key, member })
+    }
+
+    /// Apply the `SIsMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.sismember(&self.key, &self.member) {
+            Ok(is_member) => Frame::Integer(is_member as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SIsMember` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sismember".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.member);
+        frame
+    }
+}
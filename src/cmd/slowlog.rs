@@ -0,0 +1,154 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspects the slow log, which records the commands whose execution took
+/// longer than the `slowlog-log-slower-than` parameter, in microseconds.
+///
+/// # Subcommands
+///
+/// * GET [`count`] -- Returns the `count` newest entries, the newest first,
+///   `10` by default or all of them if `count` is negative. Each entry is an
+///   array holding its unique ID, the Unix time at which the command was
+///   logged, its execution time in microseconds, its arguments and the
+///   address of the client which sent it.
+/// * LEN -- Returns the number of entries.
+/// * RESET -- Drops the entries.
+#[derive(Debug)]
+pub struct Slowlog {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Get(Option<i64>),
+    Len,
+    Reset,
+}
+
+/// Number of entries returned by `SLOWLOG GET` without a count.
+const DEFAULT_COUNT: usize = 10;
+
+impl Slowlog {
+    /// Create a new `Slowlog` command returning the `count` newest entries.
+    pub fn get(count: Option<i64>) -> Slowlog {
+        Slowlog {
+            subcommand: Subcommand::Get(count),
+        }
+    }
+
+    /// Create a new `Slowlog` command returning the number of entries.
+    pub fn len() -> Slowlog {
+        Slowlog {
+            subcommand: Subcommand::Len,
+        }
+    }
+
+    /// Create a new `Slowlog` command dropping the entries.
+    pub fn reset() -> Slowlog {
+        Slowlog {
+            subcommand: Subcommand::Reset,
+        }
+    }
+
+    /// Parse a `Slowlog` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SLOWLOG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Slowlog` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// SLOWLOG GET [count]
+    /// SLOWLOG LEN
+    /// SLOWLOG RESET
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Slowlog> {
+        let subcommand = parse.next_string()?.to_uppercase();
+
+        let subcommand = match &subcommand[..] {
+            "GET" => match parse.next_signed_int() {
+                Ok(count) => Subcommand::Get(Some(count)),
+                Err(ParseError::EndOfStream) => Subcommand::Get(None),
+                Err(err) => return Err(err.into()),
+            },
+            "LEN" => Subcommand::Len,
+            "RESET" => Subcommand::Reset,
+            _ => return Err(format!("unknown `SLOWLOG` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(Slowlog { subcommand })
+    }
+
+    /// Apply the `Slowlog` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Get(count) => {
+                let count = match count {
+                    None => DEFAULT_COUNT,
+                    Some(count) if count < 0 => usize::MAX,
+                    Some(count) => count as usize,
+                };
+
+                let entries = db
+                    .slowlog_get(count)
+                    .into_iter()
+                    .map(|entry| {
+                        Frame::Array(vec![
+                            Frame::Integer(entry.id as i64),
+                            Frame::Integer(entry.timestamp as i64),
+                            Frame::Integer(entry.duration as i64),
+                            Frame::Array(entry.args.into_iter().map(Frame::Bulk).collect()),
+                            Frame::Bulk(Bytes::from(entry.addr.into_bytes())),
+                        ])
+                    })
+                    .collect();
+                Frame::Array(entries)
+            }
+            Subcommand::Len => Frame::Integer(db.slowlog_len() as i64),
+            Subcommand::Reset => {
+                db.slowlog_reset();
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Slowlog` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("slowlog".as_bytes()));
+        match self.subcommand {
+            Subcommand::Get(count) => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                if let Some(count) = count {
+                    frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+                }
+            }
+            Subcommand::Len => frame.push_bulk(Bytes::from("len".as_bytes())),
+            Subcommand::Reset => frame.push_bulk(Bytes::from("reset".as_bytes())),
+        }
+        frame
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns all the members of the set stored at `key`.
+///
+/// If `key` does not exist, it is treated as an empty set and an empty array is
+/// returned. Members are returned in no particular order.
+#[derive(Debug)]
+pub struct SMembers {
+    /// Name of the key holding the set
+    key: String,
+}
+
+impl SMembers {
+    /// Create a new `SMembers` command which fetches the members of the set
+    /// stored at `key`.
+    pub fn new(key: impl ToString) -> SMembers {
+        SMembers {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `SMembers` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SMEMBERS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SMembers` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SMEMBERS key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SMembers> {
+        let key = parse.next_string()?;
+
+        Ok(SMembers { key })
+    }
+
+    /// Apply the `SMembers` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.smembers(&self.key) {
+            Ok(members) => Frame::Set(members.into_iter().map(Frame::Bulk).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SMembers` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("smembers".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns if each of the specified members is a member of the set stored at
+/// `key`.
+///
+/// Array reply: for each member, `1` if it is a member of the set, `0` if it
+/// is not or if `key` does not exist.
+#[derive(Debug)]
+pub struct SMIsMember {
+    /// Name of the key holding the set
+    key: String,
+
+    /// Members to look for
+    members: Vec<Bytes>,
+}
+
+impl SMIsMember {
+    /// Create a new `SMIsMember` command which checks if each of `members`
+    /// belongs to the set stored at `key`.
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> SMIsMember {
+        SMIsMember {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the members
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse a `SMIsMember` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SMISMEMBER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SMIsMember` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SMISMEMBER key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SMIsMember> {
+        let key = parse.next_string()?;
+
+        // At least one member is required.
+        let mut members = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(SMIsMember { key, members })
+    }
+
+    /// Apply the `SMIsMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.smismember(&self.key, &self.members) {
+            Ok(is_member) => Frame::Array(
+                is_member
+                    .into_iter()
+                    .map(|is_member| Frame::Integer(is_member as i64))
+                    .collect(),
+            ),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SMIsMember` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("smismember".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for member in self.members {
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
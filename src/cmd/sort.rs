@@ -0,0 +1,187 @@
+use crate::db::SortOptions;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the elements of the list, set or sorted set stored at `key`, sorted.
+///
+/// Elements are compared as numbers by default, sorting fails if one of them
+/// is not a valid number.
+///
+/// # Options
+///
+/// * ASC -- Sort from the smallest to the greatest element, the default.
+/// * DESC -- Sort from the greatest to the smallest element.
+/// * ALPHA -- Compare the elements as binary strings.
+/// * LIMIT `offset` `count` -- Skip the first `offset` sorted elements and
+///   return at most `count` elements.
+/// * STORE `destination` -- Store the sorted elements as a list at
+///   `destination` instead of returning them.
+///
+/// Array reply: the sorted elements. With `STORE`, integer reply: the number
+/// of stored elements.
+#[derive(Debug)]
+pub struct Sort {
+    /// Name of the key holding the elements to sort
+    key: String,
+
+    /// Compare the elements as binary strings
+    alpha: bool,
+
+    /// Sort in descending order
+    desc: bool,
+
+    /// Number of elements to skip and maximum number of elements to return
+    limit: Option<(i64, i64)>,
+
+    /// Name of the key to store the sorted elements at
+    store: Option<String>,
+}
+
+impl Sort {
+    /// Create a new `Sort` command which sorts the elements stored at `key`.
+    pub fn new(key: impl ToString) -> Sort {
+        Sort {
+            key: key.to_string(),
+            alpha: false,
+            desc: false,
+            limit: None,
+            store: None,
+        }
+    }
+
+    /// Compare the elements as binary strings when `alpha` is set.
+    pub fn alpha(mut self, alpha: bool) -> Sort {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sort in descending order when `desc` is set.
+    pub fn desc(mut self, desc: bool) -> Sort {
+        self.desc = desc;
+        self
+    }
+
+    /// Skip `offset` elements and return at most `count` elements.
+    pub fn limit(mut self, limit: Option<(i64, i64)>) -> Sort {
+        self.limit = limit;
+        self
+    }
+
+    /// Store the sorted elements at `destination`.
+    pub fn store(mut self, destination: impl ToString) -> Sort {
+        self.store = Some(destination.to_string());
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the key the sorted elements are stored at, if any
+    pub fn destination(&self) -> Option<&str> {
+        self.store.as_deref()
+    }
+
+    /// Parse a `Sort` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SORT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Sort` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SORT key [LIMIT offset count] [ASC | DESC] [ALPHA] [STORE destination]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Sort> {
+        let mut sort = Sort::new(parse.next_string()?);
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "ASC" => sort.desc = false,
+                "DESC" => sort.desc = true,
+                "ALPHA" => sort.alpha = true,
+                "LIMIT" => sort.limit = Some((parse.next_signed_int()?, parse.next_signed_int()?)),
+                "STORE" => sort.store = Some(parse.next_string()?),
+                "BY" | "GET" => {
+                    return Err("currently `SORT` does not support the BY and GET options".into())
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(sort)
+    }
+
+    /// Apply the `Sort` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let options = SortOptions {
+            alpha: self.alpha,
+            desc: self.desc,
+            limit: self.limit,
+        };
+
+        let response = match self.store {
+            Some(destination) => match db.sort_store(&self.key, destination, &options) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => match db.sort(&self.key, &options) {
+                Ok(elements) => Frame::Array(elements.into_iter().map(Frame::Bulk).collect()),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Sort` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sort".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        if let Some((offset, count)) = self.limit {
+            frame.push_bulk(Bytes::from("limit".as_bytes()));
+            frame.push_bulk(Bytes::from(offset.to_string().into_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        if self.desc {
+            frame.push_bulk(Bytes::from("desc".as_bytes()));
+        }
+        if self.alpha {
+            frame.push_bulk(Bytes::from("alpha".as_bytes()));
+        }
+        if let Some(store) = self.store {
+            frame.push_bulk(Bytes::from("store".as_bytes()));
+            frame.push_bulk(Bytes::from(store.into_bytes()));
+        }
+        frame
+    }
+}
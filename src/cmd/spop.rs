@@ -0,0 +1,92 @@
+use crate::cmd::lpop::{parse_pop, pop_frame};
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Removes and returns members picked at random from the set stored at `key`.
+///
+/// By default, the command pops a single member and replies with it, or nil
+/// when `key` does not exist. When provided with the optional `count`
+/// argument, the reply is an array of up to `count` distinct members.
+#[derive(Debug)]
+pub struct SPop {
+    /// Name of the key holding the set
+    key: String,
+
+    /// Number of members to pop. `None` pops a single member.
+    count: Option<u64>,
+}
+
+impl SPop {
+    /// Create a new `SPop` command which pops members from the set stored at
+    /// `key`.
+    pub fn new(key: impl ToString, count: Option<u64>) -> SPop {
+        SPop {
+            key: key.to_string(),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of members to pop
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse a `SPop` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SPOP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SPop` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// SPOP key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SPop> {
+        let (key, count) = parse_pop(parse)?;
+
+        Ok(SPop { key, count })
+    }
+
+    /// Apply the `SPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.spop(&self.key, self.count.unwrap_or(1) as usize) {
+            Ok(members) if self.count.is_some() => {
+                Frame::Array(members.into_iter().map(Frame::Bulk).collect())
+            }
+            Ok(mut members) => members.pop().map(Frame::Bulk).unwrap_or(Frame::Null),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("spop", self.key, self.count)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns members picked at random from the set stored at `key`, without
+/// removing them.
+///
+/// By default, the command replies with a single member, or nil when `key`
+/// does not exist. When provided with the optional `count` argument, the reply
+/// is an array:
+///
+/// * with a positive `count`, of up to `count` distinct members.
+/// * with a negative `count`, of exactly `-count` members, the same member
+///   possibly being returned several times.
+#[derive(Debug)]
+pub struct SRandMember {
+    /// Name of the key holding the set
+    key: String,
+
+    /// Number of members to return. `None` returns a single member.
+    count: Option<i64>,
+}
+
+impl SRandMember {
+    /// Create a new `SRandMember` command which picks members from the set
+    /// stored at `key`.
+    pub fn new(key: impl ToString, count: Option<i64>) -> SRandMember {
+        SRandMember {
+            key: key.to_string(),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of members to return
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Parse a `SRandMember` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SRANDMEMBER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SRandMember` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// SRANDMEMBER key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SRandMember> {
+        let key = parse.next_string()?;
+
+        let count = match parse.next_signed_int() {
+            Ok(count) => Some(count),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(SRandMember { key, count })
+    }
+
+    /// Apply the `SRandMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if !count_in_range(self.count, false) {
+            Frame::Error(OUT_OF_RANGE.to_string())
+        } else {
+            match db.srandmember(&self.key, self.count.unwrap_or(1)) {
+                Ok(members) if self.count.is_some() => match random_reply(members.len()) {
+                    Some(mut response) => {
+                        for member in members {
+                            response.push_bulk(member);
+                        }
+                        response
+                    }
+                    None => Frame::Error(OUT_OF_RANGE.to_string()),
+                },
+                Ok(mut members) => members.next().map(Frame::Bulk).unwrap_or(Frame::Null),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SRandMember` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("srandmember".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Returns `true` if the number of items replied for `count` fits in a signed
+/// integer, as Redis requires, counting two items per picked element if
+/// `pairs` is set.
+pub(crate) fn count_in_range(count: Option<i64>, pairs: bool) -> bool {
+    let min = if pairs { -(i64::MAX / 2) } else { -i64::MAX };
+    count.is_none_or(|count| count >= min)
+}
+
+/// Returns an empty array frame with room for `len` items, or `None` if such
+/// a reply may not be allocated, which a large negative count may ask for.
+pub(crate) fn random_reply(len: usize) -> Option<Frame> {
+    let mut items = vec![];
+    items.try_reserve_exact(len).ok()?;
+    Some(Frame::Array(items))
+}
+
+/// Error replied for a count out of range.
+pub(crate) const OUT_OF_RANGE: &str = "ERR value is out of range";
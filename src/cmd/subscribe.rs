@@ -1,4 +1,5 @@
 use crate::cmd::{Parse, ParseError, Unknown};
+use crate::db::Subscription;
 use crate::{Command, Connection, Db, Frame, Shutdown};
 
 use bytes::Bytes;
@@ -10,8 +11,8 @@ use tokio::sync::broadcast;
 /// Subscribes the client to one or more channels.
 ///
 /// Once the client enters the subscribed state, it is not supposed to issue any
-/// other commands, except for additional SUBSCRIBE, PSUBSCRIBE, UNSUBSCRIBE,
-/// PUNSUBSCRIBE, PING and QUIT commands.
+/// other commands, except for additional SUBSCRIBE, PSUBSCRIBE, SSUBSCRIBE,
+/// UNSUBSCRIBE, PUNSUBSCRIBE, SUNSUBSCRIBE, PING and QUIT commands.
 #[derive(Debug)]
 pub struct Subscribe {
     channels: Vec<String>,
@@ -26,12 +27,57 @@ pub struct Unsubscribe {
     channels: Vec<String>,
 }
 
+/// Subscribes the client to the channels matching one or more glob-style
+/// patterns.
+///
+/// Each message published to a channel matching a pattern is received along
+/// with the pattern, as a `pmessage`. As with `SUBSCRIBE`, the client enters
+/// the subscribed state.
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+/// Unsubscribes the client from one or more patterns.
+///
+/// When no patterns are specified, the client is unsubscribed from all the
+/// previously subscribed patterns.
+#[derive(Clone, Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<String>,
+}
+
+/// Subscribes the client to one or more shard channels.
+///
+/// Shard channels are separate from the channels of `SUBSCRIBE`: only the
+/// messages published with `SPUBLISH` are received, as `smessage`. In cluster
+/// mode, a shard channel is served by the node serving the slot of its name,
+/// and messages are only delivered within that node. As with `SUBSCRIBE`, the
+/// client enters the subscribed state.
+#[derive(Debug)]
+pub struct SSubscribe {
+    channels: Vec<String>,
+}
+
+/// Unsubscribes the client from one or more shard channels.
+///
+/// When no channels are specified, the client is unsubscribed from all the
+/// previously subscribed shard channels.
+#[derive(Clone, Debug)]
+pub struct SUnsubscribe {
+    channels: Vec<String>,
+}
+
 /// Stream of messages. The stream receives messages from the
 /// `broadcast::Receiver`. We use `stream!` to create a `Stream` that consumes
 /// messages. Because `stream!` values cannot be named, we box the stream using
 /// a trait object.
 type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
 
+/// Stream of the messages published to the channels matching a pattern, along
+/// with the channel.
+type PatternMessages = Pin<Box<dyn Stream<Item = (String, Bytes)> + Send>>;
+
 impl Subscribe {
     /// Creates a new `Subscribe` command to listen on the specified channels.
     pub(crate) fn new(channels: &[String]) -> Subscribe {
@@ -40,6 +86,11 @@ impl Subscribe {
         }
     }
 
+    /// Get the channels
+    pub(crate) fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
     /// Parse a `Subscribe` instance from a received frame.
     ///
     /// The `Parse` argument provides a cursor-like API to read fields from the
@@ -100,59 +151,16 @@ impl Subscribe {
     ///
     /// [here]: https://redis.io/topics/pubsub
     pub(crate) async fn apply(
-        mut self,
+        self,
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
     ) -> crate::Result<()> {
-        // Each individual channel subscription is handled using a
-        // `sync::broadcast` channel. Messages are then fanned out to all
-        // clients currently subscribed to the channels.
-        //
-        // An individual client may subscribe to multiple channels and may
-        // dynamically add and remove channels from its subscription set. To
-        // handle this, a `StreamMap` is used to track active subscriptions. The
-        // `StreamMap` merges messages from individual broadcast channels as
-        // they are received.
-        let mut subscriptions = StreamMap::new();
-
-        loop {
-            // `self.channels` is used to track additional channels to subscribe
-            // to. When new `SUBSCRIBE` commands are received during the
-            // execution of `apply`, the new channels are pushed onto this vec.
-            for channel_name in self.channels.drain(..) {
-                subscribe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
-            }
-
-            // Wait for one of the following to happen:
-            //
-            // - Receive a message from one of the subscribed channels.
-            // - Receive a subscribe or unsubscribe command from the client.
-            // - A server shutdown signal.
-            select! {
-                // Receive messages from subscribed channels
-                Some((channel_name, msg)) = subscriptions.next() => {
-                    dst.write_frame(&make_message_frame(channel_name, msg)).await?;
-                }
-                res = dst.read_frame() => {
-                    let frame = match res? {
-                        Some(frame) => frame,
-                        // This happens if the remote client has disconnected.
-                        None => return Ok(())
-                    };
-
-                    handle_command(
-                        frame,
-                        &mut self.channels,
-                        &mut subscriptions,
-                        dst,
-                    ).await?;
-                }
-                _ = shutdown.recv() => {
-                    return Ok(());
-                }
-            };
-        }
+        let subscribe_to = Names {
+            channels: self.channels,
+            ..Names::default()
+        };
+        subscribed(subscribe_to, db, dst, shutdown).await
     }
 
     /// Converts the command into an equivalent `Frame`.
@@ -169,16 +177,141 @@ impl Subscribe {
     }
 }
 
-async fn subscribe_to_channel(
-    channel_name: String,
-    subscriptions: &mut StreamMap<String, Messages>,
+/// Channels, patterns and shard channels a client subscribes to.
+#[derive(Debug, Default)]
+struct Names {
+    channels: Vec<String>,
+    patterns: Vec<String>,
+    shard_channels: Vec<String>,
+}
+
+/// Subscriptions of a client in the subscribed state.
+///
+/// Each individual channel subscription is handled using a `sync::broadcast`
+/// channel. Messages are then fanned out to all clients currently subscribed
+/// to the channels.
+///
+/// An individual client may subscribe to multiple channels and may dynamically
+/// add and remove channels from its subscription set. To handle this, a
+/// `StreamMap` is used to track active subscriptions. The `StreamMap` merges
+/// messages from individual broadcast channels as they are received.
+struct Subscriptions {
+    channels: StreamMap<String, Messages>,
+
+    /// Pattern subscriptions are tracked the same way, their messages being
+    /// received along with the channel they were published to.
+    patterns: StreamMap<String, PatternMessages>,
+
+    /// Shard channels are separate from the other channels, a message
+    /// published with `SPUBLISH` is only received by `SSUBSCRIBE` subscribers.
+    shard_channels: StreamMap<String, Messages>,
+}
+
+/// Subscribes the client to `subscribe_to`, then forwards it the messages
+/// published to them until it disconnects or the server shuts down.
+///
+/// Additional `subscribe` and `unsubscribe` commands, and their pattern and
+/// shard counterparts, may be received from the client and the list of
+/// subscriptions are updated accordingly.
+///
+/// [here]: https://redis.io/topics/pubsub
+async fn subscribed(
+    mut subscribe_to: Names,
     db: &Db,
     dst: &mut Connection,
+    shutdown: &mut Shutdown,
 ) -> crate::Result<()> {
-    let mut rx = db.subscribe(channel_name.clone());
+    let mut subscriptions = Subscriptions {
+        channels: StreamMap::new(),
+        patterns: StreamMap::new(),
+        shard_channels: StreamMap::new(),
+    };
+
+    loop {
+        // `subscribe_to` is used to track additional channels to subscribe
+        // to. When new `SUBSCRIBE` commands are received during the
+        // execution of `apply`, the new channels are pushed onto this vec.
+        for channel_name in subscribe_to.channels.drain(..) {
+            let rx = messages(db.subscribe(channel_name.clone()));
+            subscriptions.channels.insert(channel_name.clone(), rx);
+
+            // Respond with the successful subscription
+            let num_subs = subscriptions.channels.len() + subscriptions.patterns.len();
+            let response = make_subscribe_frame("subscribe", channel_name, num_subs);
+            dst.write_frame(&response).await?;
+        }
+
+        // Same as above, for the patterns of `PSUBSCRIBE` commands.
+        for pattern in subscribe_to.patterns.drain(..) {
+            let rx = messages(db.psubscribe(pattern.clone()));
+            subscriptions.patterns.insert(pattern.clone(), rx);
+
+            let num_subs = subscriptions.channels.len() + subscriptions.patterns.len();
+            let response = make_subscribe_frame("psubscribe", pattern, num_subs);
+            dst.write_frame(&response).await?;
+        }
+
+        // Same as above, for the channels of `SSUBSCRIBE` commands. Only the
+        // shard channels are counted.
+        for channel_name in subscribe_to.shard_channels.drain(..) {
+            let rx = messages(db.ssubscribe(channel_name.clone()));
+            subscriptions
+                .shard_channels
+                .insert(channel_name.clone(), rx);
+
+            let num_subs = subscriptions.shard_channels.len();
+            let response = make_subscribe_frame("ssubscribe", channel_name, num_subs);
+            dst.write_frame(&response).await?;
+        }
+
+        // Wait for one of the following to happen:
+        //
+        // - Receive a message from one of the subscribed channels.
+        // - Receive a subscribe or unsubscribe command from the client.
+        // - A server shutdown signal.
+        select! {
+            // Receive messages from subscribed channels
+            Some((channel_name, msg)) = subscriptions.channels.next() => {
+                dst.write_frame(&make_message_frame(channel_name, msg)).await?;
+            }
+            // Receive messages from channels matching subscribed patterns
+            Some((pattern, (channel_name, msg))) = subscriptions.patterns.next() => {
+                dst.write_frame(&make_pmessage_frame(pattern, channel_name, msg)).await?;
+            }
+            // Receive messages from subscribed shard channels
+            Some((channel_name, msg)) = subscriptions.shard_channels.next() => {
+                dst.write_frame(&make_smessage_frame(channel_name, msg)).await?;
+            }
+            res = dst.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    // This happens if the remote client has disconnected.
+                    None => return Ok(())
+                };
+
+                let subscribed = handle_command(
+                    frame,
+                    &mut subscribe_to,
+                    &mut subscriptions,
+                    db,
+                    dst,
+                ).await?;
+                if !subscribed {
+                    return Ok(());
+                }
+            }
+            _ = shutdown.recv() => {
+                return Ok(());
+            }
+        };
+    }
+}
 
-    // Subscribe to the channel.
-    let rx = Box::pin(async_stream::stream! {
+/// Converts a subscription into a stream of the messages it receives.
+fn messages<T: Clone + Send + Unpin + 'static>(
+    mut rx: Subscription<T>,
+) -> Pin<Box<dyn Stream<Item = T> + Send>> {
+    Box::pin(async_stream::stream! {
         loop {
             match rx.recv().await {
                 Ok(msg) => yield msg,
@@ -187,16 +320,7 @@ async fn subscribe_to_channel(
                 Err(_) => break,
             }
         }
-    });
-
-    // Track subscription in this client's subscription set.
-    subscriptions.insert(channel_name.clone(), rx);
-
-    // Respond with the successful subscription
-    let response = make_subscribe_frame(channel_name, subscriptions.len());
-    dst.write_frame(&response).await?;
-
-    Ok(())
+    })
 }
 
 /// Handle a command received while inside `Subscribe::apply`. Only subscribe
@@ -204,82 +328,173 @@ async fn subscribe_to_channel(
 ///
 /// Any new subscriptions are appended to `subscribe_to` instead of modifying
 /// `subscriptions`.
+///
+/// Returns `false` if the client left the subscribed state with `RESET`.
 async fn handle_command(
     frame: Frame,
-    subscribe_to: &mut Vec<String>,
-    subscriptions: &mut StreamMap<String, Messages>,
+    subscribe_to: &mut Names,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
     dst: &mut Connection,
-) -> crate::Result<()> {
+) -> crate::Result<bool> {
     // A command has been received from the client.
     //
-    // Only `SUBSCRIBE` and `UNSUBSCRIBE` commands are permitted
-    // in this context.
-    match Command::from_frame(frame)? {
+    // Only `SUBSCRIBE` and `UNSUBSCRIBE` commands, and `RESET`, are
+    // permitted in this context.
+    match Command::from_renamed_frame(frame.clone(), &db.renames())? {
         Command::Subscribe(subscribe) => {
             // The `apply` method will subscribe to the channels we add to this
             // vector.
-            subscribe_to.extend(subscribe.channels.into_iter());
+            subscribe_to.channels.extend(subscribe.channels);
         }
-        Command::Unsubscribe(mut unsubscribe) => {
-            // If no channels are specified, this requests unsubscribing from
-            // **all** channels. To implement this, the `unsubscribe.channels`
-            // vec is populated with the list of channels currently subscribed
-            // to.
-            if unsubscribe.channels.is_empty() {
-                unsubscribe.channels = subscriptions
-                    .keys()
-                    .map(|channel_name| channel_name.to_string())
-                    .collect();
+        Command::PSubscribe(psubscribe) => {
+            subscribe_to.patterns.extend(psubscribe.patterns);
+        }
+        Command::SSubscribe(ssubscribe) => {
+            // Shard channels are routed as keys, in cluster mode.
+            let channels: Vec<&str> = ssubscribe.channels.iter().map(String::as_str).collect();
+            if let Err(redirect) = db.route(&channels, false) {
+                dst.write_frame(&Frame::Error(redirect.to_string())).await?;
+                return Ok(true);
             }
 
-            for channel_name in unsubscribe.channels {
-                subscriptions.remove(&channel_name);
-
-                let response = make_unsubscribe_frame(channel_name, subscriptions.len());
-                dst.write_frame(&response).await?;
-            }
+            subscribe_to.shard_channels.extend(ssubscribe.channels);
+        }
+        Command::Unsubscribe(unsubscribe) => {
+            let num_other = subscriptions.patterns.len();
+            unsubscribe_from(
+                "unsubscribe",
+                unsubscribe.channels,
+                &mut subscriptions.channels,
+                num_other,
+                dst,
+            )
+            .await?;
+        }
+        Command::PUnsubscribe(punsubscribe) => {
+            let num_other = subscriptions.channels.len();
+            unsubscribe_from(
+                "punsubscribe",
+                punsubscribe.patterns,
+                &mut subscriptions.patterns,
+                num_other,
+                dst,
+            )
+            .await?;
+        }
+        Command::SUnsubscribe(sunsubscribe) => {
+            unsubscribe_from(
+                "sunsubscribe",
+                sunsubscribe.channels,
+                &mut subscriptions.shard_channels,
+                0,
+                dst,
+            )
+            .await?;
+        }
+        Command::Reset(_) => {
+            // The subscriptions are dropped, then `RESET` is applied by the
+            // connection, as it was received.
+            dst.unread_frame(frame);
+            return Ok(false);
         }
         command => {
             let cmd = Unknown::new(command.get_name());
             cmd.apply(dst).await?;
         }
     }
+    Ok(true)
+}
+
+/// Removes `names` from `subscriptions`, replying with a `kind` frame for each
+/// of them. The number of subscriptions replied includes `num_other`, the
+/// subscriptions of another kind also counted.
+async fn unsubscribe_from<T>(
+    kind: &'static str,
+    mut names: Vec<String>,
+    subscriptions: &mut StreamMap<String, T>,
+    num_other: usize,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    // If no channels are specified, this requests unsubscribing from
+    // **all** channels. To implement this, the `names` vec is populated
+    // with the list of channels currently subscribed to.
+    if names.is_empty() {
+        names = subscriptions
+            .keys()
+            .map(|channel_name| channel_name.to_string())
+            .collect();
+    }
+
+    for channel_name in names {
+        subscriptions.remove(&channel_name);
+
+        let num_subs = subscriptions.len() + num_other;
+        let response = make_unsubscribe_frame(kind, channel_name, num_subs);
+        dst.write_frame(&response).await?;
+    }
+
     Ok(())
 }
 
-/// Creates the response to a subcribe request.
+/// Creates the response to a subcribe request, or to one of its pattern
+/// and shard counterparts, given by `kind`.
 ///
 /// All of these functions take the `channel_name` as a `String` instead of
 /// a `&str` since `Bytes::from` can reuse the allocation in the `String`, and
 /// taking a `&str` would require copying the data. This allows the caller to
 /// decide whether to clone the channel name or not.
-fn make_subscribe_frame(channel_name: String, num_subs: usize) -> Frame {
-    let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"subscribe"));
+///
+/// The frames are pushed to the client, out of band of the replies in RESP3.
+fn make_subscribe_frame(kind: &'static str, channel_name: String, num_subs: usize) -> Frame {
+    let mut response = Frame::Push(vec![]);
+    response.push_bulk(Bytes::from_static(kind.as_bytes()));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
-/// Creates the response to an unsubcribe request.
-fn make_unsubscribe_frame(channel_name: String, num_subs: usize) -> Frame {
-    let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"unsubscribe"));
+/// Creates the response to an unsubcribe request, or to one of its pattern
+/// and shard counterparts, given by `kind`.
+fn make_unsubscribe_frame(kind: &'static str, channel_name: String, num_subs: usize) -> Frame {
+    let mut response = Frame::Push(vec![]);
+    response.push_bulk(Bytes::from_static(kind.as_bytes()));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
 /// Creates a message informing the client about a new message on a channel that
 /// the client subscribes to.
 fn make_message_frame(channel_name: String, msg: Bytes) -> Frame {
-    let mut response = Frame::array();
+    let mut response = Frame::Push(vec![]);
     response.push_bulk(Bytes::from_static(b"message"));
     response.push_bulk(Bytes::from(channel_name));
     response.push_bulk(msg);
     response
 }
 
+/// Creates a message informing the client about a new message on a channel
+/// matching a pattern that the client subscribes to.
+fn make_pmessage_frame(pattern: String, channel_name: String, msg: Bytes) -> Frame {
+    let mut response = Frame::Push(vec![]);
+    response.push_bulk(Bytes::from_static(b"pmessage"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_bulk(Bytes::from(channel_name));
+    response.push_bulk(msg);
+    response
+}
+
+/// Creates a message informing the client about a new message on a shard
+/// channel that the client subscribes to.
+fn make_smessage_frame(channel_name: String, msg: Bytes) -> Frame {
+    let mut response = Frame::Push(vec![]);
+    response.push_bulk(Bytes::from_static(b"smessage"));
+    response.push_bulk(Bytes::from(channel_name));
+    response.push_bulk(msg);
+    response
+}
+
 impl Unsubscribe {
     /// Create a new `Unsubscribe` command with the given `channels`.
     pub(crate) fn new(channels: &[String]) -> Unsubscribe {
@@ -349,3 +564,226 @@ impl Unsubscribe {
         frame
     }
 }
+
+impl PSubscribe {
+    /// Creates a new `PSubscribe` command to listen on the channels matching
+    /// the specified patterns.
+    pub(crate) fn new(patterns: &[String]) -> PSubscribe {
+        PSubscribe {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// Get the patterns
+    pub(crate) fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Parse a `PSubscribe` instance from a received frame.
+    ///
+    /// The `PSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// PSUBSCRIBE pattern [pattern ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSubscribe> {
+        let mut patterns = vec![parse.next_string()?];
+        patterns.extend(parse_names(parse)?);
+
+        Ok(PSubscribe { patterns })
+    }
+
+    /// Apply the `PSubscribe` command to the specified `Db` instance.
+    ///
+    /// As with `Subscribe::apply`, the client then remains subscribed until it
+    /// disconnects.
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let subscribe_to = Names {
+            patterns: self.patterns,
+            ..Names::default()
+        };
+        subscribed(subscribe_to, db, dst, shutdown).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PSubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("psubscribe".as_bytes()));
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl PUnsubscribe {
+    /// Create a new `PUnsubscribe` command with the given `patterns`.
+    pub(crate) fn new(patterns: &[String]) -> PUnsubscribe {
+        PUnsubscribe {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// Parse a `PUnsubscribe` instance from a received frame.
+    ///
+    /// The `PUNSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// PUNSUBSCRIBE [pattern [pattern ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<PUnsubscribe, ParseError> {
+        Ok(PUnsubscribe {
+            patterns: parse_names(parse)?,
+        })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PUnsubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("punsubscribe".as_bytes()));
+
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+
+        frame
+    }
+}
+
+/// Parses the remaining channels or patterns of the frame.
+fn parse_names(parse: &mut Parse) -> Result<Vec<String>, ParseError> {
+    let mut names = vec![];
+
+    loop {
+        match parse.next_string() {
+            Ok(name) => names.push(name),
+            Err(ParseError::EndOfStream) => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(names)
+}
+
+impl SSubscribe {
+    /// Creates a new `SSubscribe` command to listen on the specified shard
+    /// channels.
+    pub(crate) fn new(channels: &[String]) -> SSubscribe {
+        SSubscribe {
+            channels: channels.to_vec(),
+        }
+    }
+
+    /// Get the channels
+    pub(crate) fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Parse a `SSubscribe` instance from a received frame.
+    ///
+    /// The `SSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// SSUBSCRIBE shardchannel [shardchannel ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SSubscribe> {
+        let mut channels = vec![parse.next_string()?];
+        channels.extend(parse_names(parse)?);
+
+        Ok(SSubscribe { channels })
+    }
+
+    /// Apply the `SSubscribe` command to the specified `Db` instance.
+    ///
+    /// As with `Subscribe::apply`, the client then remains subscribed until it
+    /// disconnects.
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let subscribe_to = Names {
+            shard_channels: self.channels,
+            ..Names::default()
+        };
+        subscribed(subscribe_to, db, dst, shutdown).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SSubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("ssubscribe".as_bytes()));
+        for channel in self.channels {
+            frame.push_bulk(Bytes::from(channel.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl SUnsubscribe {
+    /// Create a new `SUnsubscribe` command with the given shard `channels`.
+    pub(crate) fn new(channels: &[String]) -> SUnsubscribe {
+        SUnsubscribe {
+            channels: channels.to_vec(),
+        }
+    }
+
+    /// Parse a `SUnsubscribe` instance from a received frame.
+    ///
+    /// The `SUNSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// SUNSUBSCRIBE [shardchannel [shardchannel ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SUnsubscribe, ParseError> {
+        Ok(SUnsubscribe {
+            channels: parse_names(parse)?,
+        })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SUnsubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sunsubscribe".as_bytes()));
+
+        for channel in self.channels {
+            frame.push_bulk(Bytes::from(channel.into_bytes()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Exchange the keys of two databases.
+///
+/// The swap is atomic: no command observes one database swapped without the
+/// other. Connections keep their selected database and see the keys of the
+/// other one from then on.
+#[derive(Debug)]
+pub struct SwapDb {
+    /// Index of the first database
+    index1: u64,
+
+    /// Index of the second database
+    index2: u64,
+}
+
+impl SwapDb {
+    /// Create a new `SwapDb` command which exchanges the databases at `index1`
+    /// and `index2`.
+    pub fn new(index1: u64, index2: u64) -> SwapDb {
+        SwapDb { index1, index2 }
+    }
+
+    /// Get the index of the first database
+    pub fn index1(&self) -> u64 {
+        self.index1
+    }
+
+    /// Get the index of the second database
+    pub fn index2(&self) -> u64 {
+        self.index2
+    }
+
+    /// Parse a `SwapDb` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SWAPDB` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SwapDb` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SWAPDB index1 index2
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SwapDb> {
+        let index1 = parse.next_int()?;
+        let index2 = parse.next_int()?;
+
+        Ok(SwapDb { index1, index2 })
+    }
+
+    /// Apply the `SwapDb` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.swap(self.index1 as usize, self.index2 as usize) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SwapDb` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("swapdb".as_bytes()));
+        frame.push_int(self.index1 as i64);
+        frame.push_int(self.index2 as i64);
+        frame
+    }
+}
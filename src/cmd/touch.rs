@@ -0,0 +1,88 @@
+use crate::{Connection, Db, Frame};
+use crate::{Parse, ParseError};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Mark the specified keys as accessed, as if their values were read. A key is
+/// ignored if it does not exist.
+///
+/// Integer reply: The number of keys that were touched.
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<String>,
+}
+
+impl Touch {
+    /// Create a new `Touch` command which touches `keys`.
+    pub fn new(keys: Vec<String>) -> Touch {
+        Touch { keys }
+    }
+
+    /// keys to touch
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    /// Parse a `Touch` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TOUCH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Touch` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a list of keys.
+    ///
+    /// ```text
+    /// TOUCH key [key...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Touch> {
+        let key = parse.next_string()?;
+        let mut keys = Vec::new();
+        keys.push(key);
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => {
+                    keys.push(s);
+                }
+                // Finish reading all the keys
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Touch { keys })
+    }
+
+    /// Apply the `Touch` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.touch(&self.keys) as i64);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Touch` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("touch".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{Frame, Parse};
+
+use bytes::Bytes;
+
+/// Mark the start of a transaction.
+///
+/// The following commands of the connection are queued instead of being
+/// executed, each one replying `QUEUED`, until `EXEC` executes them or
+/// `DISCARD` aborts the transaction.
+///
+/// Transactions are handled by the connection, which holds the queued
+/// commands, so `Multi` is never applied to a `Db` directly.
+#[derive(Debug, Default)]
+pub struct Multi;
+
+/// Execute the commands queued since `MULTI`.
+///
+/// The commands are executed atomically: no command from another connection
+/// runs in between. The reply is an array holding the reply of each command.
+/// If a command could not be queued, none is executed and an `EXECABORT`
+/// error is returned instead.
+#[derive(Debug, Default)]
+pub struct Exec;
+
+/// Abort a transaction, discarding the commands queued since `MULTI`.
+#[derive(Debug, Default)]
+pub struct Discard;
+
+impl Multi {
+    /// Create a new `Multi` command.
+    pub fn new() -> Multi {
+        Multi
+    }
+
+    /// Parse a `Multi` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MULTI` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Multi` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// MULTI
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Multi> {
+        Ok(Multi)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Multi` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        transaction_frame("multi")
+    }
+}
+
+impl Exec {
+    /// Create a new `Exec` command.
+    pub fn new() -> Exec {
+        Exec
+    }
+
+    /// Parse an `Exec` instance from a received frame.
+    ///
+    /// The `EXEC` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// EXEC
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Exec> {
+        Ok(Exec)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Exec` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        transaction_frame("exec")
+    }
+}
+
+impl Discard {
+    /// Create a new `Discard` command.
+    pub fn new() -> Discard {
+        Discard
+    }
+
+    /// Parse a `Discard` instance from a received frame.
+    ///
+    /// The `DISCARD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// DISCARD
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Discard> {
+        Ok(Discard)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Discard` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        transaction_frame("discard")
+    }
+}
+
+/// Encodes the transaction command `name`, which has no arguments, into a
+/// frame.
+fn transaction_frame(name: &'static str) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame
+}
@@ -0,0 +1,160 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tokio::time::Duration;
+use tracing::{debug, instrument};
+
+/// Returns the remaining time to live of a key that has an expiration, in
+/// seconds.
+///
+/// `-2` is returned if the key does not exist, and `-1` if the key exists but
+/// has no associated expiration.
+#[derive(Debug)]
+pub struct Ttl {
+    /// Name of the key to get the time to live of
+    key: String,
+}
+
+/// Returns the remaining time to live of a key that has an expiration, in
+/// milliseconds.
+///
+/// Same as `Ttl`, but with a millisecond resolution.
+#[derive(Debug)]
+pub struct PTtl {
+    /// Name of the key to get the time to live of
+    key: String,
+}
+
+impl Ttl {
+    /// Create a new `Ttl` command which fetches the time to live of `key`.
+    pub fn new(key: impl ToString) -> Ttl {
+        Ttl {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `Ttl` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TTL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Ttl` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// TTL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Ttl> {
+        let key = parse.next_string()?;
+        Ok(Ttl { key })
+    }
+
+    /// Apply the `Ttl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Round to the closest second, as Redis does
+        apply_ttl(db, dst, &self.key, |ttl| {
+            (ttl + Duration::from_millis(500)).as_secs() as i64
+        })
+        .await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Ttl` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        ttl_frame("ttl", self.key)
+    }
+}
+
+impl PTtl {
+    /// Create a new `PTtl` command which fetches the time to live of `key`.
+    pub fn new(key: impl ToString) -> PTtl {
+        PTtl {
+            key: key.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `PTtl` instance from a received frame.
+    ///
+    /// The `PTTL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// PTTL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PTtl> {
+        let key = parse.next_string()?;
+        Ok(PTtl { key })
+    }
+
+    /// Apply the `PTtl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_ttl(db, dst, &self.key, |ttl| ttl.as_millis() as i64).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PTtl` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        ttl_frame("pttl", self.key)
+    }
+}
+
+/// Writes the time to live of `key` to `dst`, converted to an integer by
+/// `unit`, or one of the `-2` / `-1` sentinels.
+async fn apply_ttl(
+    db: &Db,
+    dst: &mut Connection,
+    key: &str,
+    unit: impl FnOnce(Duration) -> i64,
+) -> crate::Result<()> {
+    let response = match db.ttl(key) {
+        Some(Some(ttl)) => Frame::Integer(unit(ttl)),
+        Some(None) => Frame::Integer(-1),
+        None => Frame::Integer(-2),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
+
+/// Encodes a time to live command named `name` into a frame.
+fn ttl_frame(name: &'static str, key: String) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.into_bytes()));
+    frame
+}
@@ -0,0 +1,91 @@
+#![allow(clippy::string_lit_as_bytes)]
+use crate::{Connection, Db, Frame};
+use crate::{Parse, ParseError};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Delete the specified keys. A key is ignored if it does not exist.
+///
+/// Unlike `DEL`, the keys are removed right away but large values are freed in
+/// the background, so unlinking a large value does not block other clients.
+///
+/// Integer reply: The number of keys that were removed.
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<String>,
+}
+
+impl Unlink {
+    /// Create a new `Unlink` command which deletes `key`s.
+    pub fn new(keys: Vec<String>) -> Unlink {
+        Unlink { keys }
+    }
+
+    /// keys to delete
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    /// Parse a `Unlink` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `UNLINK` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of keys that were removed on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a list of keys.
+    ///
+    /// ```text
+    /// UNLINK key [key...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Unlink> {
+        let key = parse.next_string()?;
+        let mut keys = Vec::new();
+        keys.push(key);
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => {
+                    keys.push(s);
+                }
+                // Finish reading all the keys
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Unlink { keys })
+    }
+
+    /// Apply the `Unlink` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.unlink(&self.keys) as i64);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Unlink` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("unlink".as_bytes()));
+        for key in self.keys.iter() {
+            frame.push_bulk(Bytes::from(key.clone().into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,133 @@
+use crate::cmd::blocking::sleep_until;
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::select;
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+/// Blocks until the commands previously applied are acknowledged by at least
+/// `numreplicas` replicas, or until `timeout` elapses.
+///
+/// The replicas are asked to acknowledge the offset they reached in the
+/// stream of commands. The reply is the number of replicas which acknowledged
+/// the commands, which may be lower than `numreplicas` if the timeout elapsed
+/// first. A zero `timeout` blocks forever.
+///
+/// Replication remains asynchronous: the commands are applied before being
+/// acknowledged, and a command whose `WAIT` times out is not rolled back.
+#[derive(Debug)]
+pub struct Wait {
+    /// Number of replicas to wait for
+    numreplicas: u64,
+
+    /// How long to wait, zero waiting forever
+    timeout: Duration,
+}
+
+impl Wait {
+    /// Create a new `Wait` command which waits for `numreplicas` replicas to
+    /// acknowledge the previous commands, up to `timeout`.
+    pub fn new(numreplicas: u64, timeout: Duration) -> Wait {
+        Wait {
+            numreplicas,
+            timeout,
+        }
+    }
+
+    /// Parse a `Wait` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `WAIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Wait` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries. The timeout is a
+    /// number of milliseconds.
+    ///
+    /// ```text
+    /// WAIT numreplicas timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Wait> {
+        let numreplicas = parse.next_int()?;
+        let timeout = Duration::from_millis(parse.next_int()?);
+
+        Ok(Wait {
+            numreplicas,
+            timeout,
+        })
+    }
+
+    /// Apply the `Wait` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        if db.is_replica() {
+            let response = Frame::Error("ERR WAIT cannot be used with replica instances.".into());
+            debug!(?response);
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        // Subscribe to acknowledgments **before** counting them. This way, no
+        // acknowledgment received after the count is missed.
+        let (offset, mut acks) = db.request_replica_acks();
+        let deadline = if self.timeout.is_zero() {
+            None
+        } else {
+            Instant::now().checked_add(self.timeout)
+        };
+
+        let acked = loop {
+            let acked = db.acked_replicas(offset);
+
+            // In a transaction, the command does not block.
+            if acked as u64 >= self.numreplicas || db.in_transaction() {
+                break acked;
+            }
+
+            select! {
+                // Lagging behind still requires counting the acknowledgments
+                // again.
+                _ = acks.recv() => {}
+                _ = sleep_until(deadline) => break acked,
+                _ = shutdown.recv() => return Ok(()),
+            }
+        };
+
+        let response = Frame::Integer(acked as i64);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Wait` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("wait".as_bytes()));
+        frame.push_bulk(Bytes::from(self.numreplicas.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(
+            self.timeout.as_millis().to_string().into_bytes(),
+        ));
+        frame
+    }
+}
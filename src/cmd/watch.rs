@@ -0,0 +1,124 @@
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Watch keys, making the next transaction conditional to their state.
+///
+/// If any of the watched keys is modified before `EXEC`, by this connection
+/// or another one, `EXEC` does not execute the transaction and replies nil.
+/// Keys are watched until `EXEC`, `DISCARD` or `UNWATCH`.
+///
+/// Watched keys are held by the connection, so `Watch` is never applied to a
+/// `Db` directly.
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+/// Stop watching all the keys watched by the connection.
+#[derive(Debug, Default)]
+pub struct Unwatch;
+
+impl Watch {
+    /// Create a new `Watch` command which watches `keys`.
+    pub fn new(keys: Vec<String>) -> Watch {
+        Watch { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `Watch` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `WATCH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Watch` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// WATCH key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Watch> {
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Watch { keys })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Watch` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("watch".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl Unwatch {
+    /// Create a new `Unwatch` command.
+    pub fn new() -> Unwatch {
+        Unwatch
+    }
+
+    /// Parse an `Unwatch` instance from a received frame.
+    ///
+    /// The `UNWATCH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// UNWATCH
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Unwatch> {
+        Ok(Unwatch)
+    }
+
+    /// Apply the `Unwatch` command queued in a transaction.
+    ///
+    /// `EXEC` stops watching the keys before executing the transaction, so
+    /// there is nothing left to unwatch and the command only replies `OK`.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Unwatch` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("unwatch".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,123 @@
+use crate::cmd::xrange::INVALID_ID;
+use crate::db::StreamId;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Acknowledges entries delivered to a consumer group, removing them from the
+/// group's pending entries list.
+///
+/// Returns the number of entries that were pending.
+#[derive(Debug)]
+pub struct XAck {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// Name of the consumer group
+    group: String,
+
+    /// IDs of the entries to acknowledge
+    ids: Vec<String>,
+}
+
+impl XAck {
+    /// Create a new `XAck` command which acknowledges the entries `ids` of the
+    /// consumer group `group` of the stream stored at `key`.
+    pub fn new(key: impl ToString, group: impl ToString, ids: Vec<String>) -> XAck {
+        XAck {
+            key: key.to_string(),
+            group: group.to_string(),
+            ids,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the group
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Get the IDs
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Parse a `XAck` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XACK` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XAck` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// XACK key group id [id ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAck> {
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+
+        // At least one ID is required.
+        let mut ids = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(id) => ids.push(id),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(XAck { key, group, ids })
+    }
+
+    /// Apply the `XAck` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let ids: Option<Vec<_>> = self.ids.iter().map(|id| StreamId::parse(id, 0)).collect();
+
+        let response = match ids {
+            Some(ids) => match db.xack(&self.key, &self.group, &ids) {
+                Ok(acked) => Frame::Integer(acked as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error(INVALID_ID.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XAck` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xack".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.group.into_bytes()));
+        for id in self.ids {
+            frame.push_bulk(Bytes::from(id.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,211 @@
+use crate::cmd::xrange::INVALID_ID;
+use crate::cmd::xtrim::TrimArgs;
+use crate::db::NewStreamId;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Appends an entry made of the given field / value pairs to the stream stored
+/// at `key`. If `key` does not exist, a new stream is created.
+///
+/// The ID of the new entry is either explicit, `<ms>-<seq>`, or generated by
+/// the server: `*` generates the whole ID from the current time and `<ms>-*`
+/// only generates the sequence number. The ID must be greater than the ID of
+/// the last entry in the stream.
+///
+/// # Options
+///
+/// * NOMKSTREAM -- Do not create the stream if `key` does not exist.
+/// * MAXLEN [= | ~] `threshold` -- Once the entry is added, trim the stream to
+///   at most `threshold` entries, as `XTRIM` does.
+/// * MINID [= | ~] `threshold` -- Once the entry is added, remove the entries
+///   with an ID smaller than `threshold`, as `XTRIM` does.
+/// * LIMIT `count` -- Remove at most `count` entries when trimming
+///   approximately.
+#[derive(Debug)]
+pub struct XAdd {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// ID of the new entry, possibly with generated parts
+    id: String,
+
+    /// Fields and values of the new entry
+    fields: Vec<(String, Bytes)>,
+
+    /// When `true`, the stream is not created if missing
+    nomkstream: bool,
+
+    /// How to trim the stream once the entry is added, if at all
+    trim: Option<TrimArgs>,
+}
+
+impl XAdd {
+    /// Create a new `XAdd` command which appends an entry with the given `id`
+    /// and `fields` to the stream stored at `key`.
+    pub fn new(key: impl ToString, id: impl ToString, fields: Vec<(String, Bytes)>) -> XAdd {
+        XAdd {
+            key: key.to_string(),
+            id: id.to_string(),
+            fields,
+            nomkstream: false,
+            trim: None,
+        }
+    }
+
+    /// Set the `NOMKSTREAM` option.
+    pub fn nomkstream(mut self, nomkstream: bool) -> XAdd {
+        self.nomkstream = nomkstream;
+        self
+    }
+
+    /// Trim the stream to at most `max_len` entries once the entry is added.
+    pub fn max_len(mut self, max_len: u64, approximate: bool) -> XAdd {
+        self.trim = Some(TrimArgs::max_len(max_len, approximate));
+        self
+    }
+
+    /// Remove the entries with an ID smaller than `min_id` once the entry is
+    /// added.
+    pub fn min_id(mut self, min_id: impl ToString, approximate: bool) -> XAdd {
+        self.trim = Some(TrimArgs::min_id(min_id, approximate));
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get the fields
+    pub fn fields(&self) -> &[(String, Bytes)] {
+        &self.fields
+    }
+
+    /// Parse a `XAdd` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XADD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XAdd` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 5 entries.
+    ///
+    /// ```text
+    /// XADD key [NOMKSTREAM] [<MAXLEN | MINID> [= | ~] threshold [LIMIT count]]
+    ///     <* | id> field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAdd> {
+        let key = parse.next_string()?;
+
+        let mut nomkstream = false;
+        let mut trim: Option<TrimArgs> = None;
+
+        // Options come before the ID. Option names are never valid IDs.
+        let id = loop {
+            let arg = parse.next_string()?;
+
+            match &arg.to_uppercase()[..] {
+                "NOMKSTREAM" => nomkstream = true,
+                strategy @ "MAXLEN" | strategy @ "MINID" => {
+                    trim = Some(TrimArgs::parse_frames(strategy, parse)?);
+                }
+                "LIMIT" => match &mut trim {
+                    Some(trim) => trim.set_limit(parse.next_int()?),
+                    None => {
+                        return Err("protocol error; `XADD` LIMIT requires MAXLEN or MINID".into())
+                    }
+                },
+                _ => break arg,
+            }
+        };
+
+        // At least one field / value pair is required.
+        let mut fields = vec![(parse.next_string()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_string() {
+                // A field must always be followed by its value.
+                Ok(field) => fields.push((field, parse.next_bytes()?)),
+                // Finish reading all the pairs
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(XAdd {
+            key,
+            id,
+            fields,
+            nomkstream,
+            trim,
+        })
+    }
+
+    /// Apply the `XAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Invalid arguments are reported to the client, the connection
+        // remains usable.
+        let trim = match self.trim.as_ref().map(TrimArgs::to_trim).transpose() {
+            Ok(trim) => trim,
+            Err(response) => {
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let response = match NewStreamId::parse(&self.id) {
+            Some(id) => match db.xadd(self.key, id, self.fields, self.nomkstream, trim) {
+                Ok(Some(id)) => Frame::Bulk(Bytes::from(id.to_string().into_bytes())),
+                Ok(None) => Frame::Null,
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error(INVALID_ID.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        if self.nomkstream {
+            frame.push_bulk(Bytes::from("nomkstream".as_bytes()));
+        }
+        if let Some(trim) = self.trim {
+            trim.push_frames(&mut frame);
+        }
+        frame.push_bulk(Bytes::from(self.id.into_bytes()));
+        for (field, value) in self.fields {
+            frame.push_bulk(Bytes::from(field.into_bytes()));
+            frame.push_bulk(value);
+        }
+        frame
+    }
+}
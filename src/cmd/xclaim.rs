@@ -0,0 +1,254 @@
+use crate::cmd::xrange::{entries_frame, INVALID_ID};
+use crate::db::{ClaimOptions, StreamId};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Transfers the ownership of pending entries of a consumer group to another
+/// consumer.
+///
+/// Only entries idle for at least `min-idle-time` milliseconds are claimed.
+/// Claiming an entry resets its idle time and increments its delivery count.
+/// This is used to recover entries delivered to a consumer that failed to
+/// process them.
+///
+/// # Options
+///
+/// * IDLE `ms` -- Set the idle time of the claimed entries.
+/// * RETRYCOUNT `count` -- Set the delivery count of the claimed entries.
+/// * FORCE -- Create pending entries for IDs that are not pending yet, as long
+///   as they exist in the stream.
+/// * JUSTID -- Only return the IDs of the claimed entries, without
+///   incrementing their delivery count.
+#[derive(Debug)]
+pub struct XClaim {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// Name of the consumer group
+    group: String,
+
+    /// Name of the consumer claiming the entries
+    consumer: String,
+
+    /// Minimum idle time of the entries to claim
+    min_idle: Duration,
+
+    /// IDs of the entries to claim
+    ids: Vec<String>,
+
+    /// Idle time to set on the claimed entries
+    idle: Option<Duration>,
+
+    /// Delivery count to set on the claimed entries
+    retry_count: Option<u64>,
+
+    /// When `true`, IDs not pending yet are claimed as well
+    force: bool,
+
+    /// When `true`, only IDs are returned
+    just_id: bool,
+}
+
+impl XClaim {
+    /// Create a new `XClaim` command which transfers the pending entries `ids`
+    /// idle for at least `min_idle` to `consumer` of the consumer group `group`
+    /// of the stream stored at `key`.
+    pub fn new(
+        key: impl ToString,
+        group: impl ToString,
+        consumer: impl ToString,
+        min_idle: Duration,
+        ids: Vec<String>,
+    ) -> XClaim {
+        XClaim {
+            key: key.to_string(),
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            min_idle,
+            ids,
+            idle: None,
+            retry_count: None,
+            force: false,
+            just_id: false,
+        }
+    }
+
+    /// Set the idle time of the claimed entries.
+    pub fn idle(mut self, idle: Option<Duration>) -> XClaim {
+        self.idle = idle;
+        self
+    }
+
+    /// Set the delivery count of the claimed entries.
+    pub fn retry_count(mut self, retry_count: Option<u64>) -> XClaim {
+        self.retry_count = retry_count;
+        self
+    }
+
+    /// Set the `FORCE` option.
+    pub fn force(mut self, force: bool) -> XClaim {
+        self.force = force;
+        self
+    }
+
+    /// Set the `JUSTID` option.
+    pub fn just_id(mut self, just_id: bool) -> XClaim {
+        self.just_id = just_id;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the group
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Get the consumer
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    /// Get the IDs
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Parse a `XClaim` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XCLAIM` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XClaim` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 6 entries.
+    ///
+    /// ```text
+    /// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms]
+    ///     [RETRYCOUNT count] [FORCE] [JUSTID]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XClaim> {
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+        let consumer = parse.next_string()?;
+        let min_idle = Duration::from_millis(parse.next_int()?);
+
+        let mut claim = XClaim::new(key, group, consumer, min_idle, vec![parse.next_string()?]);
+
+        // IDs are followed by the options. Option names are never valid IDs.
+        loop {
+            let arg = match parse.next_string() {
+                Ok(arg) => arg,
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &arg.to_uppercase()[..] {
+                "IDLE" => claim.idle = Some(Duration::from_millis(parse.next_int()?)),
+                "RETRYCOUNT" => claim.retry_count = Some(parse.next_int()?),
+                "FORCE" => claim.force = true,
+                "JUSTID" => claim.just_id = true,
+                "TIME" | "LASTID" => {
+                    return Err(
+                        "currently `XCLAIM` only supports the IDLE, RETRYCOUNT, FORCE and JUSTID options"
+                            .into(),
+                    )
+                }
+                _ => claim.ids.push(arg),
+            }
+        }
+
+        Ok(claim)
+    }
+
+    /// Apply the `XClaim` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let ids: Option<Vec<_>> = self.ids.iter().map(|id| StreamId::parse(id, 0)).collect();
+
+        let options = ClaimOptions {
+            idle: self.idle,
+            retry_count: self.retry_count,
+            force: self.force,
+            just_id: self.just_id,
+        };
+
+        let response = match ids {
+            Some(ids) => {
+                match db.xclaim(
+                    &self.key,
+                    &self.group,
+                    &self.consumer,
+                    self.min_idle,
+                    &ids,
+                    &options,
+                ) {
+                    Ok(entries) if self.just_id => {
+                        let mut response = Frame::array();
+                        for (id, _) in entries {
+                            response.push_bulk(Bytes::from(id.to_string().into_bytes()));
+                        }
+                        response
+                    }
+                    Ok(entries) => entries_frame(entries),
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            None => Frame::Error(INVALID_ID.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XClaim` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xclaim".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.group.into_bytes()));
+        frame.push_bulk(Bytes::from(self.consumer.into_bytes()));
+        frame.push_bulk(Bytes::from(
+            self.min_idle.as_millis().to_string().into_bytes(),
+        ));
+        for id in self.ids {
+            frame.push_bulk(Bytes::from(id.into_bytes()));
+        }
+        if let Some(idle) = self.idle {
+            frame.push_bulk(Bytes::from("idle".as_bytes()));
+            frame.push_bulk(Bytes::from(idle.as_millis().to_string().into_bytes()));
+        }
+        if let Some(retry_count) = self.retry_count {
+            frame.push_bulk(Bytes::from("retrycount".as_bytes()));
+            frame.push_bulk(Bytes::from(retry_count.to_string().into_bytes()));
+        }
+        if self.force {
+            frame.push_bulk(Bytes::from("force".as_bytes()));
+        }
+        if self.just_id {
+            frame.push_bulk(Bytes::from("justid".as_bytes()));
+        }
+        frame
+    }
+}
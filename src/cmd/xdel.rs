@@ -0,0 +1,112 @@
+use crate::cmd::xrange::INVALID_ID;
+use crate::db::StreamId;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes entries from the stream stored at `key`.
+///
+/// Returns the number of entries that were removed. IDs that do not exist are
+/// ignored.
+#[derive(Debug)]
+pub struct XDel {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// IDs of the entries to remove
+    ids: Vec<String>,
+}
+
+impl XDel {
+    /// Create a new `XDel` command which removes the entries `ids` from the
+    /// stream stored at `key`.
+    pub fn new(key: impl ToString, ids: Vec<String>) -> XDel {
+        XDel {
+            key: key.to_string(),
+            ids,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the IDs
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Parse a `XDel` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XDEL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XDel` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// XDEL key id [id ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XDel> {
+        let key = parse.next_string()?;
+
+        // At least one ID is required.
+        let mut ids = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(id) => ids.push(id),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(XDel { key, ids })
+    }
+
+    /// Apply the `XDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let ids: Option<Vec<_>> = self.ids.iter().map(|id| StreamId::parse(id, 0)).collect();
+
+        let response = match ids {
+            Some(ids) => match db.xdel(&self.key, &ids) {
+                Ok(removed) => Frame::Integer(removed as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => Frame::Error(INVALID_ID.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XDel` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xdel".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for id in self.ids {
+            frame.push_bulk(Bytes::from(id.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,269 @@
+use crate::cmd::xrange::INVALID_ID;
+use crate::db::StreamId;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Manages the consumer groups of the stream stored at `key`.
+///
+/// Consumer groups allow multiple consumers to share the entries of a stream,
+/// each entry being delivered to a single consumer of the group.
+///
+/// # Subcommands
+///
+/// * CREATE `key` `group` `id` [MKSTREAM] -- Create a consumer group delivering
+///   entries after `id`. `$` stands for the last ID of the stream. With
+///   `MKSTREAM`, an empty stream is created if `key` does not exist.
+/// * DESTROY `key` `group` -- Destroy a consumer group.
+/// * CREATECONSUMER `key` `group` `consumer` -- Create a consumer in a group.
+/// * DELCONSUMER `key` `group` `consumer` -- Delete a consumer from a group,
+///   along with its pending entries.
+/// * SETID `key` `group` `id` -- Set the ID of the last entry delivered to the
+///   group.
+#[derive(Debug)]
+pub struct XGroup {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// Name of the consumer group
+    group: String,
+
+    /// Operation to apply to the group
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    Create { id: String, mkstream: bool },
+    Destroy,
+    CreateConsumer(String),
+    DelConsumer(String),
+    SetId(String),
+}
+
+impl XGroup {
+    /// Create a new `XGroup` command which creates the consumer group `group`
+    /// on the stream stored at `key`.
+    pub fn create(
+        key: impl ToString,
+        group: impl ToString,
+        id: impl ToString,
+        mkstream: bool,
+    ) -> XGroup {
+        XGroup::new(
+            key,
+            group,
+            Subcommand::Create {
+                id: id.to_string(),
+                mkstream,
+            },
+        )
+    }
+
+    /// Create a new `XGroup` command which destroys the consumer group `group`
+    /// of the stream stored at `key`.
+    pub fn destroy(key: impl ToString, group: impl ToString) -> XGroup {
+        XGroup::new(key, group, Subcommand::Destroy)
+    }
+
+    /// Create a new `XGroup` command which creates `consumer` in the consumer
+    /// group `group` of the stream stored at `key`.
+    pub fn create_consumer(
+        key: impl ToString,
+        group: impl ToString,
+        consumer: impl ToString,
+    ) -> XGroup {
+        XGroup::new(key, group, Subcommand::CreateConsumer(consumer.to_string()))
+    }
+
+    /// Create a new `XGroup` command which deletes `consumer` from the consumer
+    /// group `group` of the stream stored at `key`.
+    pub fn del_consumer(
+        key: impl ToString,
+        group: impl ToString,
+        consumer: impl ToString,
+    ) -> XGroup {
+        XGroup::new(key, group, Subcommand::DelConsumer(consumer.to_string()))
+    }
+
+    /// Create a new `XGroup` command which sets the ID of the last entry
+    /// delivered to the consumer group `group` of the stream stored at `key`.
+    pub fn set_id(key: impl ToString, group: impl ToString, id: impl ToString) -> XGroup {
+        XGroup::new(key, group, Subcommand::SetId(id.to_string()))
+    }
+
+    fn new(key: impl ToString, group: impl ToString, subcommand: Subcommand) -> XGroup {
+        XGroup {
+            key: key.to_string(),
+            group: group.to_string(),
+            subcommand,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the group
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Parse a `XGroup` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XGROUP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XGroup` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// XGROUP CREATE key group <id | $> [MKSTREAM]
+    /// XGROUP DESTROY key group
+    /// XGROUP CREATECONSUMER key group consumer
+    /// XGROUP DELCONSUMER key group consumer
+    /// XGROUP SETID key group <id | $>
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XGroup> {
+        let subcommand = parse.next_string()?.to_uppercase();
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+
+        let subcommand = match &subcommand[..] {
+            "CREATE" => {
+                let id = parse.next_string()?;
+
+                let mkstream = match parse.next_string() {
+                    Ok(s) if s.to_uppercase() == "MKSTREAM" => true,
+                    Ok(_) => {
+                        return Err(
+                            "currently `XGROUP CREATE` only supports the MKSTREAM option".into(),
+                        )
+                    }
+                    Err(ParseError::EndOfStream) => false,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Subcommand::Create { id, mkstream }
+            }
+            "DESTROY" => Subcommand::Destroy,
+            "CREATECONSUMER" => Subcommand::CreateConsumer(parse.next_string()?),
+            "DELCONSUMER" => Subcommand::DelConsumer(parse.next_string()?),
+            "SETID" => Subcommand::SetId(parse.next_string()?),
+            _ => return Err(format!("unknown `XGROUP` subcommand `{}`", subcommand).into()),
+        };
+
+        Ok(XGroup {
+            key,
+            group,
+            subcommand,
+        })
+    }
+
+    /// Apply the `XGroup` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let XGroup {
+            key,
+            group,
+            subcommand,
+        } = self;
+
+        let response = match subcommand {
+            Subcommand::Create { id, mkstream } => match parse_group_id(&id) {
+                Some(id) => match db.xgroup_create(key, group, id, mkstream) {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(err) => Frame::Error(err.to_string()),
+                },
+                None => Frame::Error(INVALID_ID.to_string()),
+            },
+            Subcommand::Destroy => match db.xgroup_destroy(&key, &group) {
+                Ok(destroyed) => Frame::Integer(destroyed as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            Subcommand::CreateConsumer(consumer) => {
+                match db.xgroup_createconsumer(&key, &group, consumer) {
+                    Ok(created) => Frame::Integer(created as i64),
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            Subcommand::DelConsumer(consumer) => {
+                match db.xgroup_delconsumer(&key, &group, &consumer) {
+                    Ok(pending) => Frame::Integer(pending as i64),
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            Subcommand::SetId(id) => match parse_group_id(&id) {
+                Some(id) => match db.xgroup_setid(&key, &group, id) {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(err) => Frame::Error(err.to_string()),
+                },
+                None => Frame::Error(INVALID_ID.to_string()),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XGroup` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let subcommand = match &self.subcommand {
+            Subcommand::Create { .. } => "create",
+            Subcommand::Destroy => "destroy",
+            Subcommand::CreateConsumer(_) => "createconsumer",
+            Subcommand::DelConsumer(_) => "delconsumer",
+            Subcommand::SetId(_) => "setid",
+        };
+
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xgroup".as_bytes()));
+        frame.push_bulk(Bytes::from(subcommand.as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.group.into_bytes()));
+
+        match self.subcommand {
+            Subcommand::Create { id, mkstream } => {
+                frame.push_bulk(Bytes::from(id.into_bytes()));
+                if mkstream {
+                    frame.push_bulk(Bytes::from("mkstream".as_bytes()));
+                }
+            }
+            Subcommand::Destroy => {}
+            Subcommand::CreateConsumer(consumer) | Subcommand::DelConsumer(consumer) => {
+                frame.push_bulk(Bytes::from(consumer.into_bytes()));
+            }
+            Subcommand::SetId(id) => frame.push_bulk(Bytes::from(id.into_bytes())),
+        }
+        frame
+    }
+}
+
+/// Parse the ID of the last entry delivered to a group. `$` stands for the
+/// last ID of the stream and is returned as `Some(None)`.
+///
+/// Returns `None` if `src` is not a valid ID.
+fn parse_group_id(src: &str) -> Option<Option<StreamId>> {
+    match src {
+        "$" => Some(None),
+        _ => StreamId::parse(src, 0).map(Some),
+    }
+}
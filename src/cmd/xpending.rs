@@ -0,0 +1,276 @@
+use crate::cmd::xrange::{parse_range_bound, INVALID_ID};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Inspects the pending entries list of a consumer group: the entries
+/// delivered to the group's consumers but not acknowledged yet.
+///
+/// Without a range, a summary is returned: the number of pending entries, the
+/// smallest and greatest pending IDs and the number of pending entries of each
+/// consumer. With a range, the details of up to `count` pending entries with an
+/// ID between `start` and `end` are returned.
+///
+/// # Options
+///
+/// * IDLE `min-idle-time` -- Only return entries idle for at least the given
+///   number of milliseconds.
+/// * `consumer` -- Only return entries owned by `consumer`.
+#[derive(Debug)]
+pub struct XPending {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// Name of the consumer group
+    group: String,
+
+    /// Detailed range of entries to return, `None` for the summary
+    range: Option<Range>,
+}
+
+#[derive(Debug)]
+struct Range {
+    min_idle: Option<Duration>,
+    start: String,
+    end: String,
+    count: u64,
+    consumer: Option<String>,
+}
+
+impl XPending {
+    /// Create a new `XPending` command which summarizes the pending entries of
+    /// the consumer group `group` of the stream stored at `key`.
+    pub fn summary(key: impl ToString, group: impl ToString) -> XPending {
+        XPending {
+            key: key.to_string(),
+            group: group.to_string(),
+            range: None,
+        }
+    }
+
+    /// Create a new `XPending` command which fetches up to `count` pending
+    /// entries with an ID between `start` and `end` of the consumer group
+    /// `group` of the stream stored at `key`.
+    pub fn range(
+        key: impl ToString,
+        group: impl ToString,
+        start: impl ToString,
+        end: impl ToString,
+        count: u64,
+    ) -> XPending {
+        XPending {
+            key: key.to_string(),
+            group: group.to_string(),
+            range: Some(Range {
+                min_idle: None,
+                start: start.to_string(),
+                end: end.to_string(),
+                count,
+                consumer: None,
+            }),
+        }
+    }
+
+    /// Only fetch entries idle for at least `min_idle`. Ignored for summaries.
+    pub fn min_idle(mut self, min_idle: Option<Duration>) -> XPending {
+        if let Some(range) = &mut self.range {
+            range.min_idle = min_idle;
+        }
+        self
+    }
+
+    /// Only fetch entries owned by `consumer`. Ignored for summaries.
+    pub fn consumer(mut self, consumer: Option<String>) -> XPending {
+        if let Some(range) = &mut self.range {
+            range.consumer = consumer;
+        }
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the group
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Parse a `XPending` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XPENDING` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XPending` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries for the summary, or at
+    /// least six entries for a range.
+    ///
+    /// ```text
+    /// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XPending> {
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+
+        let mut start = match parse.next_string() {
+            Ok(start) => start,
+            Err(ParseError::EndOfStream) => {
+                return Ok(XPending {
+                    key,
+                    group,
+                    range: None,
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut min_idle = None;
+
+        if start.to_uppercase() == "IDLE" {
+            min_idle = Some(Duration::from_millis(parse.next_int()?));
+            start = parse.next_string()?;
+        }
+
+        let end = parse.next_string()?;
+        let count = parse.next_int()?;
+
+        let consumer = match parse.next_string() {
+            Ok(consumer) => Some(consumer),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(XPending {
+            key,
+            group,
+            range: Some(Range {
+                min_idle,
+                start,
+                end,
+                count,
+                consumer,
+            }),
+        })
+    }
+
+    /// Apply the `XPending` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.range {
+            None => match db.xpending_summary(&self.key, &self.group) {
+                Ok(summary) => {
+                    // The summary is made of the pending entries count, the
+                    // smallest and greatest IDs and the count of each
+                    // consumer. Without pending entries, the IDs are `Null`
+                    // and there are no consumers.
+                    let count = Frame::Integer(summary.count as i64);
+
+                    match summary.bounds {
+                        Some((first, last)) => {
+                            let consumers = summary
+                                .consumers
+                                .into_iter()
+                                .map(|(consumer, count)| {
+                                    let mut frame = Frame::array();
+                                    frame.push_bulk(Bytes::from(consumer.into_bytes()));
+                                    frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+                                    frame
+                                })
+                                .collect();
+
+                            Frame::Array(vec![
+                                count,
+                                Frame::Bulk(Bytes::from(first.to_string().into_bytes())),
+                                Frame::Bulk(Bytes::from(last.to_string().into_bytes())),
+                                Frame::Array(consumers),
+                            ])
+                        }
+                        None => Frame::Array(vec![count, Frame::Null, Frame::Null, Frame::array()]),
+                    }
+                }
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            Some(range) => {
+                let start = parse_range_bound(&range.start, true);
+                let end = parse_range_bound(&range.end, false);
+
+                match (start, end) {
+                    (Some(start), Some(end)) => match db.xpending(
+                        &self.key,
+                        &self.group,
+                        start..=end,
+                        range.count as usize,
+                        range.consumer.as_deref(),
+                        range.min_idle.unwrap_or_default(),
+                    ) {
+                        Ok(entries) => {
+                            // Each entry is made of its ID, its owner, its
+                            // idle time in milliseconds and its delivery
+                            // count.
+                            let entries = entries
+                                .into_iter()
+                                .map(|(id, entry)| {
+                                    let idle = entry.delivered_at.elapsed().as_millis() as i64;
+
+                                    let mut frame = Frame::array();
+                                    frame.push_bulk(Bytes::from(id.to_string().into_bytes()));
+                                    frame.push_bulk(Bytes::from(entry.consumer.into_bytes()));
+                                    frame.push_int(idle);
+                                    frame.push_int(entry.delivery_count as i64);
+                                    frame
+                                })
+                                .collect();
+
+                            Frame::Array(entries)
+                        }
+                        Err(err) => Frame::Error(err.to_string()),
+                    },
+                    _ => Frame::Error(INVALID_ID.to_string()),
+                }
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XPending` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xpending".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.group.into_bytes()));
+        if let Some(range) = self.range {
+            if let Some(min_idle) = range.min_idle {
+                frame.push_bulk(Bytes::from("idle".as_bytes()));
+                frame.push_bulk(Bytes::from(min_idle.as_millis().to_string().into_bytes()));
+            }
+            frame.push_bulk(Bytes::from(range.start.into_bytes()));
+            frame.push_bulk(Bytes::from(range.end.into_bytes()));
+            frame.push_bulk(Bytes::from(range.count.to_string().into_bytes()));
+            if let Some(consumer) = range.consumer {
+                frame.push_bulk(Bytes::from(consumer.into_bytes()));
+            }
+        }
+        frame
+    }
+}
@@ -0,0 +1,201 @@
+use crate::db::{StreamEntry, StreamId};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Error message returned when a stream command receives a malformed ID.
+pub(crate) const INVALID_ID: &str = "ERR Invalid stream ID specified as stream command argument";
+
+/// Returns the entries of the stream stored at `key` with an ID in the range
+/// delimited by `start` and `end`.
+///
+/// Both ends of the range are inclusive, unless prefixed by `(`. The special
+/// `-` and `+` IDs respectively are the smallest and the greatest possible IDs.
+/// When the sequence number is omitted, it defaults to the smallest sequence
+/// number for `start` and to the greatest for `end`.
+///
+/// # Options
+///
+/// * COUNT `count` -- Return at most `count` entries.
+#[derive(Debug)]
+pub struct XRange {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// Start of the ID range
+    start: String,
+
+    /// End of the ID range
+    end: String,
+
+    /// Maximum number of entries to return
+    count: Option<u64>,
+}
+
+impl XRange {
+    /// Create a new `XRange` command which fetches the entries of the stream
+    /// stored at `key` with an ID between `start` and `end`.
+    pub fn new(key: impl ToString, start: impl ToString, end: impl ToString) -> XRange {
+        XRange {
+            key: key.to_string(),
+            start: start.to_string(),
+            end: end.to_string(),
+            count: None,
+        }
+    }
+
+    /// Set the maximum number of entries to return.
+    pub fn count(mut self, count: Option<u64>) -> XRange {
+        self.count = count;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start of the range
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    /// Get the end of the range
+    pub fn end(&self) -> &str {
+        &self.end
+    }
+
+    /// Parse a `XRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XRange` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or six entries.
+    ///
+    /// ```text
+    /// XRANGE key start end [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_string()?;
+        let end = parse.next_string()?;
+
+        let count = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "COUNT" => Some(parse.next_int()?),
+            Ok(_) => return Err("currently `XRANGE` only supports the COUNT option".into()),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(XRange {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+
+    /// Apply the `XRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let start = parse_range_bound(&self.start, true);
+        let end = parse_range_bound(&self.end, false);
+
+        let response = match (start, end) {
+            (Some(start), Some(end)) => {
+                let count = self.count.map(|count| count as usize);
+
+                match db.xrange(&self.key, start, end, count) {
+                    Ok(entries) => entries_frame(entries),
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            _ => Frame::Error(INVALID_ID.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XRange` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.into_bytes()));
+        frame.push_bulk(Bytes::from(self.end.into_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Parse one end of an `XRANGE` interval into an inclusive bound.
+///
+/// Returns `None` if `src` is not a valid ID.
+pub(crate) fn parse_range_bound(src: &str, is_start: bool) -> Option<StreamId> {
+    match src {
+        "-" => Some(StreamId::MIN),
+        "+" => Some(StreamId::MAX),
+        _ => {
+            let (exclusive, src) = match src.strip_prefix('(') {
+                Some(src) => (true, src),
+                None => (false, src),
+            };
+
+            let default_seq = if is_start { 0 } else { u64::MAX };
+            let id = StreamId::parse(src, default_seq)?;
+
+            // Exclusive bounds are converted to the adjacent inclusive bound.
+            match (exclusive, is_start) {
+                (false, _) => Some(id),
+                (true, true) => id.next(),
+                (true, false) => id.prev(),
+            }
+        }
+    }
+}
+
+/// Converts stream entries into the frame sent to clients.
+///
+/// Each entry is an array made of the entry ID and of a flat array of its
+/// fields, each followed by its value.
+pub(crate) fn entries_frame(entries: Vec<StreamEntry>) -> Frame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| {
+            let mut values = Frame::array();
+            for (field, value) in fields {
+                values.push_bulk(Bytes::from(field.into_bytes()));
+                values.push_bulk(value);
+            }
+
+            Frame::Array(vec![
+                Frame::Bulk(Bytes::from(id.to_string().into_bytes())),
+                values,
+            ])
+        })
+        .collect();
+
+    Frame::Array(entries)
+}
@@ -0,0 +1,242 @@
+use crate::cmd::blocking::KeyWaiter;
+use crate::cmd::xrange::{entries_frame, INVALID_ID};
+use crate::db::{StreamEntry, StreamId};
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::select;
+use tracing::{debug, instrument};
+
+/// Reads entries from one or more streams, returning for each stream the
+/// entries with an ID greater than the given ID.
+///
+/// The special `$` ID stands for the ID of the last entry in the stream when
+/// the command is received. Combined with `BLOCK`, it waits for new entries.
+///
+/// # Options
+///
+/// * COUNT `count` -- Return at most `count` entries per stream.
+/// * BLOCK `milliseconds` -- When none of the streams has entries to return,
+///   wait for new entries up to the given duration. `0` waits forever.
+#[derive(Debug)]
+pub struct XRead {
+    /// Names of the keys holding the streams, along with the ID after which
+    /// entries are read
+    streams: Vec<(String, String)>,
+
+    /// Maximum number of entries to return per stream
+    count: Option<u64>,
+
+    /// How long to wait for new entries, if at all
+    block: Option<Duration>,
+}
+
+impl XRead {
+    /// Create a new `XRead` command which fetches, for each `(key, id)` pair,
+    /// the entries of the stream stored at `key` with an ID after `id`.
+    pub fn new(streams: Vec<(String, String)>) -> XRead {
+        XRead {
+            streams,
+            count: None,
+            block: None,
+        }
+    }
+
+    /// Set the maximum number of entries to return per stream.
+    pub fn count(mut self, count: Option<u64>) -> XRead {
+        self.count = count;
+        self
+    }
+
+    /// Set how long to wait for new entries. A zero duration waits forever.
+    pub fn block(mut self, block: Option<Duration>) -> XRead {
+        self.block = block;
+        self
+    }
+
+    /// Get the streams and IDs
+    pub fn streams(&self) -> &[(String, String)] {
+        &self.streams
+    }
+
+    /// Parse a `XRead` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XREAD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XRead` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries. There must be as
+    /// many IDs as keys.
+    ///
+    /// ```text
+    /// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRead> {
+        let mut count = None;
+        let mut block = None;
+
+        // Read options until the `STREAMS` keyword.
+        loop {
+            match &parse.next_string()?.to_uppercase()[..] {
+                "COUNT" => count = Some(parse.next_int()?),
+                "BLOCK" => block = Some(Duration::from_millis(parse.next_int()?)),
+                "STREAMS" => break,
+                _ => {
+                    return Err(
+                        "currently `XREAD` only supports the COUNT and BLOCK options".into(),
+                    )
+                }
+            }
+        }
+
+        // All remaining entries are keys, followed by as many IDs.
+        let mut args = vec![parse.next_string()?];
+
+        while let Ok(arg) = parse.next_string() {
+            args.push(arg);
+        }
+
+        if args.len() % 2 != 0 {
+            return Err("protocol error; `XREAD` expects an ID for each stream key".into());
+        }
+
+        let ids = args.split_off(args.len() / 2);
+        let streams = args.into_iter().zip(ids).collect();
+
+        Ok(XRead {
+            streams,
+            count,
+            block,
+        })
+    }
+
+    /// Apply the `XRead` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    ///
+    /// When `BLOCK` is set and no entry is available, the connection waits for
+    /// entries to be added to one of the streams, for the block duration to
+    /// elapse or for the server to shut down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let streams = match resolve_ids(db, self.streams) {
+            Ok(streams) => streams,
+            Err(response) => {
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let count = self.count.map(|count| count as usize);
+
+        // Subscribe to writes on the streams **before** reading them. This
+        // way, entries added after the read are never missed.
+        let mut waiter = self
+            .block
+            .map(|block| KeyWaiter::new(db, streams.iter().map(|(key, _)| key), block));
+
+        let response = loop {
+            match db.xread(&streams, count) {
+                Ok(result) if !result.is_empty() => break streams_frame(result),
+                Ok(_) => {}
+                Err(err) => break Frame::Error(err.to_string()),
+            }
+
+            let waiter = match &mut waiter {
+                Some(waiter) => waiter,
+                None => break Frame::Null,
+            };
+
+            // Nothing to read yet, wait for one of the streams to be written
+            // to, for the block duration to elapse or for the server to shut
+            // down.
+            select! {
+                written = waiter.wait() => if !written {
+                    break Frame::Null;
+                },
+                _ = shutdown.recv() => return Ok(()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XRead` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xread".as_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        if let Some(block) = self.block {
+            frame.push_bulk(Bytes::from("block".as_bytes()));
+            frame.push_bulk(Bytes::from(block.as_millis().to_string().into_bytes()));
+        }
+        frame.push_bulk(Bytes::from("streams".as_bytes()));
+
+        let (keys, ids): (Vec<_>, Vec<_>) = self.streams.into_iter().unzip();
+        for arg in keys.into_iter().chain(ids) {
+            frame.push_bulk(Bytes::from(arg.into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Resolve the IDs received from the client. `$` is replaced with the ID of
+/// the last entry in the stream.
+///
+/// On failure, the error frame to send to the client is returned.
+fn resolve_ids(db: &Db, streams: Vec<(String, String)>) -> Result<Vec<(String, StreamId)>, Frame> {
+    streams
+        .into_iter()
+        .map(|(key, id)| {
+            let id = if id == "$" {
+                db.xlast_id(&key)
+                    .map_err(|err| Frame::Error(err.to_string()))?
+            } else {
+                StreamId::parse(&id, 0).ok_or_else(|| Frame::Error(INVALID_ID.to_string()))?
+            };
+
+            Ok((key, id))
+        })
+        .collect()
+}
+
+/// Converts entries read from streams into the frame sent to clients: an array
+/// of `[key, entries]` arrays.
+pub(crate) fn streams_frame(streams: Vec<(String, Vec<StreamEntry>)>) -> Frame {
+    let streams = streams
+        .into_iter()
+        .map(|(key, entries)| {
+            Frame::Array(vec![
+                Frame::Bulk(Bytes::from(key.into_bytes())),
+                entries_frame(entries),
+            ])
+        })
+        .collect();
+
+    Frame::Array(streams)
+}
@@ -0,0 +1,280 @@
+use crate::cmd::blocking::KeyWaiter;
+use crate::cmd::xrange::INVALID_ID;
+use crate::cmd::xread::streams_frame;
+use crate::db::StreamId;
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::select;
+use tracing::{debug, instrument};
+
+/// Reads entries from one or more streams on behalf of a consumer of a
+/// consumer group.
+///
+/// The special `>` ID reads entries never delivered to any consumer of the
+/// group. These entries are added to the group's pending entries list until
+/// acknowledged with `XACK`. Any other ID reads the consumer's pending entries
+/// with a greater ID.
+///
+/// # Options
+///
+/// * COUNT `count` -- Return at most `count` entries per stream.
+/// * BLOCK `milliseconds` -- When reading new entries and none is available,
+///   wait for new entries up to the given duration. `0` waits forever.
+/// * NOACK -- Do not add the entries to the pending entries list.
+#[derive(Debug)]
+pub struct XReadGroup {
+    /// Name of the consumer group
+    group: String,
+
+    /// Name of the consumer reading the entries
+    consumer: String,
+
+    /// Names of the keys holding the streams, along with the ID after which
+    /// entries are read
+    streams: Vec<(String, String)>,
+
+    /// Maximum number of entries to return per stream
+    count: Option<u64>,
+
+    /// How long to wait for new entries, if at all
+    block: Option<Duration>,
+
+    /// When `true`, entries are not added to the pending entries list
+    noack: bool,
+}
+
+impl XReadGroup {
+    /// Create a new `XReadGroup` command which reads, for each `(key, id)`
+    /// pair, entries of the stream stored at `key` on behalf of `consumer` of
+    /// the consumer group `group`.
+    pub fn new(
+        group: impl ToString,
+        consumer: impl ToString,
+        streams: Vec<(String, String)>,
+    ) -> XReadGroup {
+        XReadGroup {
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            streams,
+            count: None,
+            block: None,
+            noack: false,
+        }
+    }
+
+    /// Set the maximum number of entries to return per stream.
+    pub fn count(mut self, count: Option<u64>) -> XReadGroup {
+        self.count = count;
+        self
+    }
+
+    /// Set how long to wait for new entries. A zero duration waits forever.
+    pub fn block(mut self, block: Option<Duration>) -> XReadGroup {
+        self.block = block;
+        self
+    }
+
+    /// Set the `NOACK` option.
+    pub fn noack(mut self, noack: bool) -> XReadGroup {
+        self.noack = noack;
+        self
+    }
+
+    /// Get the group
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Get the consumer
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    /// Get the streams and IDs
+    pub fn streams(&self) -> &[(String, String)] {
+        &self.streams
+    }
+
+    /// Parse a `XReadGroup` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XREADGROUP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XReadGroup` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 7 entries. There must be as
+    /// many IDs as keys.
+    ///
+    /// ```text
+    /// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds]
+    ///     [NOACK] STREAMS key [key ...] id [id ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XReadGroup> {
+        if parse.next_string()?.to_uppercase() != "GROUP" {
+            return Err("protocol error; `XREADGROUP` expects the GROUP option".into());
+        }
+
+        let group = parse.next_string()?;
+        let consumer = parse.next_string()?;
+
+        let mut count = None;
+        let mut block = None;
+        let mut noack = false;
+
+        // Read options until the `STREAMS` keyword.
+        loop {
+            match &parse.next_string()?.to_uppercase()[..] {
+                "COUNT" => count = Some(parse.next_int()?),
+                "BLOCK" => block = Some(Duration::from_millis(parse.next_int()?)),
+                "NOACK" => noack = true,
+                "STREAMS" => break,
+                _ => {
+                    return Err(
+                        "currently `XREADGROUP` only supports the COUNT, BLOCK and NOACK options"
+                            .into(),
+                    )
+                }
+            }
+        }
+
+        // All remaining entries are keys, followed by as many IDs.
+        let mut args = vec![parse.next_string()?];
+
+        while let Ok(arg) = parse.next_string() {
+            args.push(arg);
+        }
+
+        if args.len() % 2 != 0 {
+            return Err("protocol error; `XREADGROUP` expects an ID for each stream key".into());
+        }
+
+        let ids = args.split_off(args.len() / 2);
+        let streams = args.into_iter().zip(ids).collect();
+
+        Ok(XReadGroup {
+            group,
+            consumer,
+            streams,
+            count,
+            block,
+            noack,
+        })
+    }
+
+    /// Apply the `XReadGroup` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    ///
+    /// When `BLOCK` is set, only new entries are requested and none is
+    /// available, the connection waits for entries to be added to one of the
+    /// streams, for the block duration to elapse or for the server to shut
+    /// down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        // `>` is represented as `None`.
+        let streams: Option<Vec<_>> = self
+            .streams
+            .into_iter()
+            .map(|(key, id)| match &id[..] {
+                ">" => Some((key, None)),
+                _ => StreamId::parse(&id, 0).map(|id| (key, Some(id))),
+            })
+            .collect();
+
+        let streams = match streams {
+            Some(streams) => streams,
+            None => {
+                let response = Frame::Error(INVALID_ID.to_string());
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let count = self.count.map(|count| count as usize);
+
+        // Reading pending entries never blocks.
+        let block = match self.block {
+            Some(block) if streams.iter().all(|(_, id)| id.is_none()) => Some(block),
+            _ => None,
+        };
+
+        // Subscribe to writes on the streams **before** reading them. This
+        // way, entries added after the read are never missed.
+        let mut waiter =
+            block.map(|block| KeyWaiter::new(db, streams.iter().map(|(key, _)| key), block));
+
+        let response = loop {
+            match db.xreadgroup(&self.group, &self.consumer, &streams, count, self.noack) {
+                Ok(result) if !result.is_empty() => break streams_frame(result),
+                Ok(_) => {}
+                Err(err) => break Frame::Error(err.to_string()),
+            }
+
+            let waiter = match &mut waiter {
+                Some(waiter) => waiter,
+                None => break Frame::Null,
+            };
+
+            // Nothing to read yet, wait for one of the streams to be written
+            // to, for the block duration to elapse or for the server to shut
+            // down.
+            select! {
+                written = waiter.wait() => if !written {
+                    break Frame::Null;
+                },
+                _ = shutdown.recv() => return Ok(()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XReadGroup` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xreadgroup".as_bytes()));
+        frame.push_bulk(Bytes::from("group".as_bytes()));
+        frame.push_bulk(Bytes::from(self.group.into_bytes()));
+        frame.push_bulk(Bytes::from(self.consumer.into_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        if let Some(block) = self.block {
+            frame.push_bulk(Bytes::from("block".as_bytes()));
+            frame.push_bulk(Bytes::from(block.as_millis().to_string().into_bytes()));
+        }
+        if self.noack {
+            frame.push_bulk(Bytes::from("noack".as_bytes()));
+        }
+        frame.push_bulk(Bytes::from("streams".as_bytes()));
+
+        let (keys, ids): (Vec<_>, Vec<_>) = self.streams.into_iter().unzip();
+        for arg in keys.into_iter().chain(ids) {
+            frame.push_bulk(Bytes::from(arg.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,242 @@
+use crate::cmd::xrange::INVALID_ID;
+use crate::db::{StreamId, Trim, TrimStrategy};
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Trims the stream stored at `key` by removing its oldest entries.
+///
+/// With `MAXLEN`, entries are removed until the stream has at most `threshold`
+/// entries. With `MINID`, entries with an ID smaller than `threshold` are
+/// removed. The threshold may be prefixed with `~` to request approximate
+/// trimming, in which case `LIMIT` caps the number of removed entries.
+///
+/// Returns the number of entries that were removed.
+#[derive(Debug)]
+pub struct XTrim {
+    /// Name of the key holding the stream
+    key: String,
+
+    /// How to trim the stream
+    trim: TrimArgs,
+}
+
+/// Trimming arguments, shared by `XTRIM` and `XADD`.
+#[derive(Debug, Clone)]
+pub(crate) struct TrimArgs {
+    /// What entries to remove
+    threshold: Threshold,
+
+    /// When `true`, trimming may keep more entries than the threshold
+    approximate: bool,
+
+    /// Maximum number of entries to remove when trimming approximately
+    limit: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+enum Threshold {
+    MaxLen(u64),
+    MinId(String),
+}
+
+impl XTrim {
+    /// Create a new `XTrim` command which trims the stream stored at `key` to
+    /// at most `max_len` entries.
+    pub fn max_len(key: impl ToString, max_len: u64) -> XTrim {
+        XTrim {
+            key: key.to_string(),
+            trim: TrimArgs::max_len(max_len, false),
+        }
+    }
+
+    /// Create a new `XTrim` command which removes the entries of the stream
+    /// stored at `key` with an ID smaller than `min_id`.
+    pub fn min_id(key: impl ToString, min_id: impl ToString) -> XTrim {
+        XTrim {
+            key: key.to_string(),
+            trim: TrimArgs::min_id(min_id, false),
+        }
+    }
+
+    /// Request approximate trimming.
+    pub fn approximate(mut self, approximate: bool) -> XTrim {
+        self.trim.approximate = approximate;
+        self
+    }
+
+    /// Set the maximum number of entries to remove. Only used with
+    /// approximate trimming.
+    pub fn limit(mut self, limit: Option<u64>) -> XTrim {
+        self.trim.limit = limit;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Parse a `XTrim` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `XTRIM` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `XTrim` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// XTRIM key <MAXLEN | MINID> [= | ~] threshold [LIMIT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XTrim> {
+        let key = parse.next_string()?;
+
+        let strategy = parse.next_string()?;
+        let mut trim = TrimArgs::parse_frames(&strategy, parse)?;
+
+        match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "LIMIT" => trim.limit = Some(parse.next_int()?),
+            Ok(_) => return Err("currently `XTRIM` only supports the LIMIT option".into()),
+            Err(ParseError::EndOfStream) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(XTrim { key, trim })
+    }
+
+    /// Apply the `XTrim` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.trim.to_trim() {
+            Ok(trim) => match db.xtrim(&self.key, trim) {
+                Ok(removed) => Frame::Integer(removed as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            Err(response) => response,
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `XTrim` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("xtrim".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        self.trim.push_frames(&mut frame);
+        frame
+    }
+}
+
+impl TrimArgs {
+    /// Trim the stream to at most `max_len` entries.
+    pub(crate) fn max_len(max_len: u64, approximate: bool) -> TrimArgs {
+        TrimArgs::new(Threshold::MaxLen(max_len), approximate)
+    }
+
+    /// Trim the entries with an ID smaller than `min_id`.
+    pub(crate) fn min_id(min_id: impl ToString, approximate: bool) -> TrimArgs {
+        TrimArgs::new(Threshold::MinId(min_id.to_string()), approximate)
+    }
+
+    fn new(threshold: Threshold, approximate: bool) -> TrimArgs {
+        TrimArgs {
+            threshold,
+            approximate,
+            limit: None,
+        }
+    }
+
+    /// Set the maximum number of entries to remove.
+    pub(crate) fn set_limit(&mut self, limit: u64) {
+        self.limit = Some(limit);
+    }
+
+    /// Parse the trimming arguments following the `strategy` keyword, either
+    /// `MAXLEN` or `MINID`. The `LIMIT` option is left to the caller.
+    ///
+    /// ```text
+    /// <MAXLEN | MINID> [= | ~] threshold
+    /// ```
+    pub(crate) fn parse_frames(strategy: &str, parse: &mut Parse) -> crate::Result<TrimArgs> {
+        let mut threshold = parse.next_string()?;
+        let mut approximate = false;
+
+        if threshold == "~" || threshold == "=" {
+            approximate = threshold == "~";
+            threshold = parse.next_string()?;
+        }
+
+        let threshold = match &strategy.to_uppercase()[..] {
+            "MAXLEN" => match threshold.parse() {
+                Ok(max_len) => Threshold::MaxLen(max_len),
+                Err(_) => return Err("protocol error; invalid MAXLEN threshold".into()),
+            },
+            "MINID" => Threshold::MinId(threshold),
+            _ => return Err(format!("unknown trimming strategy `{}`", strategy).into()),
+        };
+
+        Ok(TrimArgs::new(threshold, approximate))
+    }
+
+    /// Converts the arguments into a `Trim` applied by the `Db`.
+    ///
+    /// On failure, the error frame to send to the client is returned.
+    pub(crate) fn to_trim(&self) -> Result<Trim, Frame> {
+        let strategy = match &self.threshold {
+            Threshold::MaxLen(max_len) => TrimStrategy::MaxLen(*max_len as usize),
+            Threshold::MinId(min_id) => match StreamId::parse(min_id, 0) {
+                Some(min_id) => TrimStrategy::MinId(min_id),
+                None => return Err(Frame::Error(INVALID_ID.to_string())),
+            },
+        };
+
+        // `LIMIT 0` stands for no limit.
+        let limit = match self.limit {
+            Some(_) if !self.approximate => {
+                let msg = "ERR syntax error, LIMIT cannot be used without the special ~ option";
+                return Err(Frame::Error(msg.to_string()));
+            }
+            Some(0) | None => None,
+            Some(limit) => Some(limit as usize),
+        };
+
+        Ok(Trim { strategy, limit })
+    }
+
+    /// Append the trimming arguments to the command `frame`.
+    pub(crate) fn push_frames(self, frame: &mut Frame) {
+        let (strategy, threshold) = match self.threshold {
+            Threshold::MaxLen(max_len) => ("maxlen", max_len.to_string()),
+            Threshold::MinId(min_id) => ("minid", min_id),
+        };
+
+        frame.push_bulk(Bytes::from(strategy.as_bytes()));
+        if self.approximate {
+            frame.push_bulk(Bytes::from("~".as_bytes()));
+        }
+        frame.push_bulk(Bytes::from(threshold.into_bytes()));
+        if let Some(limit) = self.limit {
+            frame.push_bulk(Bytes::from("limit".as_bytes()));
+            frame.push_bulk(Bytes::from(limit.to_string().into_bytes()));
+        }
+    }
+}
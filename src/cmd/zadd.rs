@@ -0,0 +1,180 @@
+use crate::db::ZAddOption;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Adds all the specified members with the specified scores to the sorted set
+/// stored at `key`.
+///
+/// If a specified member is already a member of the sorted set, the score is
+/// updated and the element reinserted at the right position to ensure the
+/// correct ordering. If `key` does not exist, a new sorted set with the
+/// specified members as sole members is created.
+///
+/// Integer reply: The number of members added to the sorted set, not including
+/// members already existing for which the score was updated.
+///
+/// # Options
+///
+/// * NX -- Only add new members, never update existing ones.
+/// * XX -- Only update existing members, never add new ones.
+/// * GT -- Only update existing members if the new score is greater.
+/// * LT -- Only update existing members if the new score is lower.
+/// * CH -- Reply with the number of members added or whose score changed.
+#[derive(Debug)]
+pub struct ZAdd {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Score / member pairs to add
+    members: Vec<(f64, Bytes)>,
+
+    /// Options, validated to be compatible with each other when applied
+    options: Vec<ZAddOption>,
+}
+
+impl ZAdd {
+    /// Create a new `ZAdd` command which adds `members` to the sorted set stored
+    /// at `key`.
+    pub fn new(key: impl ToString, members: Vec<(f64, Bytes)>) -> ZAdd {
+        ZAdd {
+            key: key.to_string(),
+            members,
+            options: vec![],
+        }
+    }
+
+    /// Add `option` to the command.
+    pub fn option(mut self, option: ZAddOption) -> ZAdd {
+        self.options.push(option);
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the score / member pairs
+    pub fn members(&self) -> &[(f64, Bytes)] {
+        &self.members
+    }
+
+    /// Parse a `ZAdd` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZADD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZAdd` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// ZADD key [NX | XX] [GT | LT] [CH] score member [score member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZAdd> {
+        let key = parse.next_string()?;
+        let mut options = vec![];
+
+        // Options come before the first score, which ends them.
+        let score = loop {
+            let arg = parse.next_string()?;
+
+            options.push(match &arg.to_uppercase()[..] {
+                "NX" => ZAddOption::Nx,
+                "XX" => ZAddOption::Xx,
+                "GT" => ZAddOption::Gt,
+                "LT" => ZAddOption::Lt,
+                "CH" => ZAddOption::Ch,
+                _ => break arg,
+            });
+        };
+
+        let score = match score.parse::<f64>() {
+            Ok(score) if !score.is_nan() => score,
+            _ => return Err("protocol error; invalid float".into()),
+        };
+
+        // At least one score / member pair is required.
+        let mut members = vec![(score, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_float() {
+                // A score must always be followed by its member.
+                Ok(score) => members.push((score, parse.next_bytes()?)),
+                // Finish reading all the pairs
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ZAdd {
+            key,
+            members,
+            options,
+        })
+    }
+
+    /// Apply the `ZAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let has = |option| self.options.contains(&option);
+
+        let response = if has(ZAddOption::Nx) && has(ZAddOption::Xx) {
+            Frame::Error("ERR XX and NX options at the same time are not compatible".into())
+        } else if [ZAddOption::Nx, ZAddOption::Gt, ZAddOption::Lt]
+            .iter()
+            .filter(|option| has(**option))
+            .count()
+            > 1
+        {
+            Frame::Error("ERR GT, LT, and/or NX options at the same time are not compatible".into())
+        } else {
+            match db.zadd(self.key, self.members, &self.options) {
+                Ok(added) => Frame::Integer(added as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for option in self.options {
+            let option = match option {
+                ZAddOption::Nx => "NX",
+                ZAddOption::Xx => "XX",
+                ZAddOption::Gt => "GT",
+                ZAddOption::Lt => "LT",
+                ZAddOption::Ch => "CH",
+            };
+            frame.push_bulk(Bytes::from(option.as_bytes()));
+        }
+        for (score, member) in self.members {
+            frame.push_bulk(Bytes::from(score.to_string().into_bytes()));
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
@@ -0,0 +1,113 @@
+use crate::cmd::zrangebyscore::INVALID_SCORE_BOUND;
+use crate::db::ScoreBound;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the number of members of the sorted set stored at `key` with a
+/// score between `min` and `max`.
+///
+/// The range is given as with `ZRANGEBYSCORE`: inclusive unless prefixed by
+/// `(`, `-inf` and `+inf` leaving it open.
+#[derive(Debug)]
+pub struct ZCount {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Lowest score of the range
+    min: String,
+
+    /// Highest score of the range
+    max: String,
+}
+
+impl ZCount {
+    /// Create a new `ZCount` command which counts the members of the sorted
+    /// set stored at `key` with a score between `min` and `max`.
+    pub fn new(key: impl ToString, min: impl ToString, max: impl ToString) -> ZCount {
+        ZCount {
+            key: key.to_string(),
+            min: min.to_string(),
+            max: max.to_string(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the lowest score of the range
+    pub fn min(&self) -> &str {
+        &self.min
+    }
+
+    /// Get the highest score of the range
+    pub fn max(&self) -> &str {
+        &self.max
+    }
+
+    /// Parse a `ZCount` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZCOUNT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZCount` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// ZCOUNT key min max
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZCount> {
+        let key = parse.next_string()?;
+        let min = parse.next_string()?;
+        let max = parse.next_string()?;
+
+        Ok(ZCount { key, min, max })
+    }
+
+    /// Apply the `ZCount` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let min = ScoreBound::parse(&self.min);
+        let max = ScoreBound::parse(&self.max);
+
+        let response = match (min, max) {
+            (Some(min), Some(max)) => match db.zcount(&self.key, min, max) {
+                Ok(count) => Frame::Integer(count as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            _ => Frame::Error(INVALID_SCORE_BOUND.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZCount` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zcount".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.min.into_bytes()));
+        frame.push_bulk(Bytes::from(self.max.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increments the score of `member` in the sorted set stored at `key` by
+/// `increment`.
+///
+/// If `member` does not exist, it is added with `increment` as its score, and
+/// if `key` does not exist, a new sorted set is created. The new score is
+/// returned as a bulk string.
+#[derive(Debug)]
+pub struct ZIncrBy {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Amount to add to the score
+    increment: f64,
+
+    /// Member to increment the score of
+    member: Bytes,
+}
+
+impl ZIncrBy {
+    /// Create a new `ZIncrBy` command which adds `increment` to the score of
+    /// `member` in the sorted set stored at `key`.
+    pub fn new(key: impl ToString, increment: f64, member: Bytes) -> ZIncrBy {
+        ZIncrBy {
+            key: key.to_string(),
+            increment,
+            member,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse a `ZIncrBy` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZINCRBY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZIncrBy` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// ZINCRBY key increment member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZIncrBy> {
+        let key = parse.next_string()?;
+        let increment = parse.next_float()?;
+        let member = parse.next_bytes()?;
+
+        Ok(ZIncrBy {
+            key,
+            increment,
+            member,
+        })
+    }
+
+    /// Apply the `ZIncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zincr_by(self.key, self.increment, self.member) {
+            Ok(score) => Frame::Bulk(Bytes::from(score.to_string().into_bytes())),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZIncrBy` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zincrby".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.increment.to_string().into_bytes()));
+        frame.push_bulk(self.member);
+        frame
+    }
+}
@@ -0,0 +1,177 @@
+use crate::cmd::lpop::{parse_pop, pop_frame};
+use crate::db::ScoreEnd;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes and returns the members with the lowest scores in the sorted set
+/// stored at `key`.
+///
+/// A single member is popped unless `count` is given. The reply is a flat
+/// array where each member is followed by its score, empty when `key` does
+/// not exist.
+#[derive(Debug)]
+pub struct ZPopMin {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Number of members to pop. `None` pops a single member.
+    count: Option<u64>,
+}
+
+/// Removes and returns the members with the highest scores in the sorted set
+/// stored at `key`.
+///
+/// Same as `ZPopMin`, but members are popped from the highest score down.
+#[derive(Debug)]
+pub struct ZPopMax {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Number of members to pop. `None` pops a single member.
+    count: Option<u64>,
+}
+
+impl ZPopMin {
+    /// Create a new `ZPopMin` command which pops the members with the lowest
+    /// scores from the sorted set stored at `key`.
+    pub fn new(key: impl ToString, count: Option<u64>) -> ZPopMin {
+        ZPopMin {
+            key: key.to_string(),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of members to pop
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse a `ZPopMin` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZPOPMIN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZPopMin` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// ZPOPMIN key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZPopMin> {
+        let (key, count) = parse_pop(parse)?;
+        Ok(ZPopMin { key, count })
+    }
+
+    /// Apply the `ZPopMin` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_zpop(db, dst, &self.key, self.count, ScoreEnd::Min).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZPopMin` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("zpopmin", self.key, self.count)
+    }
+}
+
+impl ZPopMax {
+    /// Create a new `ZPopMax` command which pops the members with the highest
+    /// scores from the sorted set stored at `key`.
+    pub fn new(key: impl ToString, count: Option<u64>) -> ZPopMax {
+        ZPopMax {
+            key: key.to_string(),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of members to pop
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse a `ZPopMax` instance from a received frame.
+    ///
+    /// The `ZPOPMAX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// ZPOPMAX key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZPopMax> {
+        let (key, count) = parse_pop(parse)?;
+        Ok(ZPopMax { key, count })
+    }
+
+    /// Apply the `ZPopMax` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        apply_zpop(db, dst, &self.key, self.count, ScoreEnd::Max).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZPopMax` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pop_frame("zpopmax", self.key, self.count)
+    }
+}
+
+/// Pops members from the `end` of the sorted set and writes them, each
+/// followed by its score, to `dst`.
+async fn apply_zpop(
+    db: &Db,
+    dst: &mut Connection,
+    key: &str,
+    count: Option<u64>,
+    end: ScoreEnd,
+) -> crate::Result<()> {
+    let response = match db.zpop(key, end, count.unwrap_or(1) as usize) {
+        Ok(members) => {
+            let mut response = Frame::array();
+            for (member, score) in members {
+                response.push_bulk(member);
+                response.push_bulk(Bytes::from(score.to_string().into_bytes()));
+            }
+            response
+        }
+        Err(err) => Frame::Error(err.to_string()),
+    };
+
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
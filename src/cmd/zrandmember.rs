@@ -0,0 +1,143 @@
+use crate::cmd::hrandfield::{parse_random, random_frame};
+use crate::cmd::srandmember::{count_in_range, random_reply, OUT_OF_RANGE};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns members picked at random from the sorted set stored at `key`.
+///
+/// By default, the command replies with a single member, or nil when `key`
+/// does not exist. When provided with the optional `count` argument, the reply
+/// is an array:
+///
+/// * with a positive `count`, of up to `count` distinct members.
+/// * with a negative `count`, of exactly `-count` members, the same member
+///   possibly being returned several times.
+///
+/// # Options
+///
+/// * WITHSCORES -- Return the score of each member following the member. Only
+///   valid along with `count`.
+#[derive(Debug)]
+pub struct ZRandMember {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Number of members to return. `None` returns a single member.
+    count: Option<i64>,
+
+    /// When `true`, scores are returned along with the members
+    with_scores: bool,
+}
+
+impl ZRandMember {
+    /// Create a new `ZRandMember` command which picks members from the sorted
+    /// set stored at `key`.
+    pub fn new(key: impl ToString, count: Option<i64>) -> ZRandMember {
+        ZRandMember {
+            key: key.to_string(),
+            count,
+            with_scores: false,
+        }
+    }
+
+    /// Return the scores along with the members when `with_scores` is set.
+    /// Only used when a count is given.
+    pub fn with_scores(mut self, with_scores: bool) -> ZRandMember {
+        self.with_scores = with_scores;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the number of members to return
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Parse a `ZRandMember` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANDMEMBER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRandMember` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// ZRANDMEMBER key [count [WITHSCORES]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRandMember> {
+        let (key, count, with_scores) = parse_random(parse, "WITHSCORES")?;
+
+        Ok(ZRandMember {
+            key,
+            count,
+            with_scores,
+        })
+    }
+
+    /// Apply the `ZRandMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if !count_in_range(self.count, self.with_scores) {
+            Frame::Error(OUT_OF_RANGE.to_string())
+        } else {
+            match db.zrandmember(&self.key, self.count.unwrap_or(1)) {
+                Ok(members) if self.count.is_some() => {
+                    let len = members.len() * if self.with_scores { 2 } else { 1 };
+                    match random_reply(len) {
+                        Some(mut response) => {
+                            for (member, score) in members {
+                                response.push_bulk(member);
+                                if self.with_scores {
+                                    response.push_bulk(Bytes::from(score.to_string().into_bytes()));
+                                }
+                            }
+                            response
+                        }
+                        None => Frame::Error(OUT_OF_RANGE.to_string()),
+                    }
+                }
+                Ok(mut members) => match members.next() {
+                    Some((member, _)) => Frame::Bulk(member),
+                    None => Frame::Null,
+                },
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRandMember` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        random_frame(
+            "zrandmember",
+            self.key,
+            self.count,
+            self.with_scores,
+            "withscores",
+        )
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the specified range of members in the sorted set stored at `key`.
+///
+/// Members are ordered from the lowest to the highest score. `start` and `stop`
+/// are zero-based, inclusive, ranks. Negative ranks count from the end of the
+/// sorted set, `-1` being the member with the highest score.
+///
+/// # Options
+///
+/// * WITHSCORES -- Return the score of each member following the member.
+#[derive(Debug)]
+pub struct ZRange {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Rank of the first member to return
+    start: i64,
+
+    /// Rank of the last member to return
+    stop: i64,
+
+    /// When `true`, scores are returned along with the members
+    with_scores: bool,
+}
+
+impl ZRange {
+    /// Create a new `ZRange` command which fetches the members between the
+    /// `start` and `stop` ranks of the sorted set stored at `key`.
+    pub fn new(key: impl ToString, start: i64, stop: i64, with_scores: bool) -> ZRange {
+        ZRange {
+            key: key.to_string(),
+            start,
+            stop,
+            with_scores,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start rank
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the stop rank
+    pub fn stop(&self) -> i64 {
+        self.stop
+    }
+
+    /// Returns `true` if scores are requested
+    pub fn with_scores(&self) -> bool {
+        self.with_scores
+    }
+
+    /// Parse a `ZRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRange` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or five entries.
+    ///
+    /// ```text
+    /// ZRANGE key start stop [WITHSCORES]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        let with_scores = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "WITHSCORES" => true,
+            Ok(_) => return Err("currently `ZRANGE` only supports the WITHSCORES option".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ZRange {
+            key,
+            start,
+            stop,
+            with_scores,
+        })
+    }
+
+    /// Apply the `ZRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrange(&self.key, self.start, self.stop) {
+            Ok(members) => {
+                let mut response = Frame::array();
+                for (member, score) in members {
+                    response.push_bulk(member);
+                    // With `WITHSCORES`, the reply is a flat array where each
+                    // member is followed by its score.
+                    if self.with_scores {
+                        response.push_bulk(Bytes::from(score.to_string().into_bytes()));
+                    }
+                }
+                response
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRange` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrange".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(self.stop.to_string().into_bytes()));
+        if self.with_scores {
+            frame.push_bulk(Bytes::from("withscores".as_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,146 @@
+use crate::db::LexBound;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the members of the sorted set stored at `key` between `min` and
+/// `max`, compared as binary strings.
+///
+/// This is meant for sorted sets where all the members have the same score,
+/// the result is unspecified otherwise. Bounds must start with `[` to be
+/// inclusive or with `(` to be exclusive. `-` and `+` are respectively lower
+/// and greater than any member.
+///
+/// # Options
+///
+/// * LIMIT `offset` `count` -- Skip the first `offset` members of the range and
+///   return at most `count` members. A negative `count` returns all the
+///   remaining members.
+#[derive(Debug)]
+pub struct ZRangeByLex {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Start of the range
+    min: Bytes,
+
+    /// End of the range
+    max: Bytes,
+
+    /// Number of members to skip and maximum number of members to return
+    limit: Option<(i64, i64)>,
+}
+
+impl ZRangeByLex {
+    /// Create a new `ZRangeByLex` command which fetches the members of the
+    /// sorted set stored at `key` between `min` and `max`.
+    pub fn new(key: impl ToString, min: Bytes, max: Bytes) -> ZRangeByLex {
+        ZRangeByLex {
+            key: key.to_string(),
+            min,
+            max,
+            limit: None,
+        }
+    }
+
+    /// Skip `offset` members and return at most `count` members.
+    pub fn limit(mut self, limit: Option<(i64, i64)>) -> ZRangeByLex {
+        self.limit = limit;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start of the range
+    pub fn min(&self) -> &Bytes {
+        &self.min
+    }
+
+    /// Get the end of the range
+    pub fn max(&self) -> &Bytes {
+        &self.max
+    }
+
+    /// Parse a `ZRangeByLex` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANGEBYLEX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRangeByLex` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or seven entries.
+    ///
+    /// ```text
+    /// ZRANGEBYLEX key min max [LIMIT offset count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRangeByLex> {
+        let mut cmd = ZRangeByLex::new(
+            parse.next_string()?,
+            parse.next_bytes()?,
+            parse.next_bytes()?,
+        );
+
+        match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "LIMIT" => {
+                cmd.limit = Some((parse.next_signed_int()?, parse.next_signed_int()?))
+            }
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(ParseError::EndOfStream) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(cmd)
+    }
+
+    /// Apply the `ZRangeByLex` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let min = LexBound::parse(&self.min);
+        let max = LexBound::parse(&self.max);
+
+        let response = match (min, max) {
+            (Some(min), Some(max)) => match db.zrange_by_lex(&self.key, &min, &max, self.limit) {
+                Ok(members) => Frame::Array(members.into_iter().map(Frame::Bulk).collect()),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            _ => Frame::Error("ERR min or max not valid string range item".into()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRangeByLex` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrangebylex".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.min);
+        frame.push_bulk(self.max);
+        if let Some((offset, count)) = self.limit {
+            frame.push_bulk(Bytes::from("limit".as_bytes()));
+            frame.push_bulk(Bytes::from(offset.to_string().into_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        frame
+    }
+}
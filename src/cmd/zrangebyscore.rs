@@ -0,0 +1,178 @@
+use crate::db::ScoreBound;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Error message returned when a score range bound is malformed.
+pub(crate) const INVALID_SCORE_BOUND: &str = "ERR min or max is not a float";
+
+/// Returns the members of the sorted set stored at `key` with a score between
+/// `min` and `max`.
+///
+/// Members are ordered from the lowest to the highest score. Both ends of the
+/// range are inclusive, unless prefixed by `(`. `-inf` and `+inf` can be used
+/// to leave the range open.
+///
+/// # Options
+///
+/// * WITHSCORES -- Return the score of each member following the member.
+/// * LIMIT `offset` `count` -- Skip the first `offset` members of the range and
+///   return at most `count` members. A negative `count` returns all the
+///   remaining members.
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Lowest score of the range
+    min: String,
+
+    /// Highest score of the range
+    max: String,
+
+    /// When `true`, scores are returned along with the members
+    with_scores: bool,
+
+    /// Number of members to skip and maximum number of members to return
+    limit: Option<(i64, i64)>,
+}
+
+impl ZRangeByScore {
+    /// Create a new `ZRangeByScore` command which fetches the members of the
+    /// sorted set stored at `key` with a score between `min` and `max`.
+    pub fn new(key: impl ToString, min: impl ToString, max: impl ToString) -> ZRangeByScore {
+        ZRangeByScore {
+            key: key.to_string(),
+            min: min.to_string(),
+            max: max.to_string(),
+            with_scores: false,
+            limit: None,
+        }
+    }
+
+    /// Return scores along with the members when `with_scores` is set.
+    pub fn with_scores(mut self, with_scores: bool) -> ZRangeByScore {
+        self.with_scores = with_scores;
+        self
+    }
+
+    /// Skip `offset` members and return at most `count` members.
+    pub fn limit(mut self, limit: Option<(i64, i64)>) -> ZRangeByScore {
+        self.limit = limit;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the lowest score of the range
+    pub fn min(&self) -> &str {
+        &self.min
+    }
+
+    /// Get the highest score of the range
+    pub fn max(&self) -> &str {
+        &self.max
+    }
+
+    /// Parse a `ZRangeByScore` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANGEBYSCORE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRangeByScore` value on success. If the frame is
+    /// malformed, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRangeByScore> {
+        let mut cmd = ZRangeByScore::new(
+            parse.next_string()?,
+            parse.next_string()?,
+            parse.next_string()?,
+        );
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "WITHSCORES" => cmd.with_scores = true,
+                "LIMIT" => cmd.limit = Some((parse.next_signed_int()?, parse.next_signed_int()?)),
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        Ok(cmd)
+    }
+
+    /// Apply the `ZRangeByScore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let min = ScoreBound::parse(&self.min);
+        let max = ScoreBound::parse(&self.max);
+
+        let response = match (min, max) {
+            (Some(min), Some(max)) => match db.zrange_by_score(&self.key, min, max, self.limit) {
+                Ok(members) => {
+                    let mut response = Frame::array();
+                    for (member, score) in members {
+                        response.push_bulk(member);
+                        // As with `ZRANGE`, each member is followed by its
+                        // score.
+                        if self.with_scores {
+                            response.push_bulk(Bytes::from(score.to_string().into_bytes()));
+                        }
+                    }
+                    response
+                }
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            _ => Frame::Error(INVALID_SCORE_BOUND.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRangeByScore` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrangebyscore".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.min.into_bytes()));
+        frame.push_bulk(Bytes::from(self.max.into_bytes()));
+        if self.with_scores {
+            frame.push_bulk(Bytes::from("withscores".as_bytes()));
+        }
+        if let Some((offset, count)) = self.limit {
+            frame.push_bulk(Bytes::from("limit".as_bytes()));
+            frame.push_bulk(Bytes::from(offset.to_string().into_bytes()));
+            frame.push_bulk(Bytes::from(count.to_string().into_bytes()));
+        }
+        frame
+    }
+}
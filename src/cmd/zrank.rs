@@ -0,0 +1,96 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the rank of `member` in the sorted set stored at `key`, with the
+/// scores ordered from low to high.
+///
+/// The rank is zero-based, which means that the member with the lowest score
+/// has rank `0`. If `member` does not exist in the sorted set, or `key` does
+/// not exist, nil is returned.
+#[derive(Debug)]
+pub struct ZRank {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Member to get the rank of
+    member: Bytes,
+}
+
+impl ZRank {
+    /// Create a new `ZRank` command which fetches the rank of `member` in the
+    /// sorted set stored at `key`.
+    pub fn new(key: impl ToString, member: Bytes) -> ZRank {
+        ZRank {
+            key: key.to_string(),
+            member,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse a `ZRank` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANK` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRank` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// ZRANK key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRank> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+
+        Ok(ZRank { key, member })
+    }
+
+    /// Apply the `ZRank` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrank(&self.key, &self.member) {
+            Ok(Some(rank)) => Frame::Integer(rank as i64),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRank` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrank".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.member);
+        frame
+    }
+}
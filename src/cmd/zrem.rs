@@ -0,0 +1,106 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Remove the specified members from the sorted set stored at `key`.
+///
+/// Non existing members are ignored. If `key` does not exist, it is treated as
+/// an empty sorted set and this command returns `0`.
+///
+/// Integer reply: The number of members removed from the sorted set.
+#[derive(Debug)]
+pub struct ZRem {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Members to remove
+    members: Vec<Bytes>,
+}
+
+impl ZRem {
+    /// Create a new `ZRem` command which removes `members` from the sorted set
+    /// stored at `key`.
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> ZRem {
+        ZRem {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the members to remove
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse a `ZRem` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZREM` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRem` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// ZREM key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRem> {
+        let key = parse.next_string()?;
+        let mut members = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                // Finish reading all the members
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ZRem { key, members })
+    }
+
+    /// Apply the `ZRem` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrem(&self.key, &self.members) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRem` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrem".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for member in self.members {
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the score of `member` in the sorted set stored at `key`.
+///
+/// The score is returned as a bulk string. If `member` does not exist in the
+/// sorted set, or `key` does not exist, nil is returned.
+#[derive(Debug)]
+pub struct ZScore {
+    /// Name of the key holding the sorted set
+    key: String,
+
+    /// Member to get the score of
+    member: Bytes,
+}
+
+impl ZScore {
+    /// Create a new `ZScore` command which fetches the score of `member` in the
+    /// sorted set stored at `key`.
+    pub fn new(key: impl ToString, member: Bytes) -> ZScore {
+        ZScore {
+            key: key.to_string(),
+            member,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse a `ZScore` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZSCORE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZScore` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// ZSCORE key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScore> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+
+        Ok(ZScore { key, member })
+    }
+
+    /// Apply the `ZScore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zscore(&self.key, &self.member) {
+            Ok(Some(score)) => Frame::Double(score),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZScore` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zscore".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.member);
+        frame
+    }
+}
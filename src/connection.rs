@@ -1,10 +1,30 @@
 use crate::frame::{self, Frame};
 
-use bytes::{Buf, BytesMut};
+#[cfg(feature = "tls")]
+use crate::tls::Acceptor;
+use bytes::{Buf, Bytes, BytesMut};
+use std::fmt;
 use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 use tokio::net::TcpStream;
 
+/// Stream a `Connection` reads frames from and writes frames to.
+///
+/// Connections with clients are backed by a `TcpStream` or a `UnixStream`,
+/// while commands replayed from disk are read from a file.
+pub(crate) trait Stream: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug> Stream for T {}
+
+/// Maximum length of an inline command, above which the connection is closed
+/// rather than waiting for the end of the line.
+const MAX_INLINE_LEN: usize = 64 * 1024;
+
+/// Error returned by `read_frame` for a malformed inline command. The server
+/// replies with it before closing the connection.
+#[derive(Debug)]
+pub(crate) struct ProtocolError(&'static str);
+
 /// Send and receive `Frame` values from a remote peer.
 ///
 /// When implementing networking protocols, a message on that protocol is
@@ -19,32 +39,131 @@ use tokio::net::TcpStream;
 /// The contents of the write buffer are then written to the socket.
 #[derive(Debug)]
 pub struct Connection {
-    // The `TcpStream`. It is decorated with a `BufWriter`, which provides write
-    // level buffering. The `BufWriter` implementation provided by Tokio is
-    // sufficient for our needs.
-    stream: BufWriter<TcpStream>,
+    // The `TcpStream`, or another `Stream`. It is decorated with a
+    // `BufWriter`, which provides write level buffering. The `BufWriter`
+    // implementation provided by Tokio is sufficient for our needs.
+    stream: BufWriter<Box<dyn Stream>>,
 
     // The buffer for reading frames. Unfortunately, Tokio's `BufReader`
     // currently requires you to empty its buffer before you can ask it to
     // retrieve more data from the underlying stream, so we have to manually
     // implement buffering. This should be fixed in Tokio v0.3.
     buffer: BytesMut,
+
+    // Frames written while replies are buffered, `None` when they are written
+    // to the stream. A transaction buffers the replies of its commands, which
+    // are sent at once when it completes.
+    replies: Option<Vec<Frame>>,
+
+    // Version of the protocol the frames are written with, `2` unless the
+    // peer switched to RESP3 with `HELLO 3`. RESP3 frames are written as
+    // their RESP2 equivalent with RESP2.
+    protocol: u8,
+
+    // Set while the frames written are dropped, as the replies to the
+    // commands are after `CLIENT REPLY OFF`.
+    muted: bool,
+
+    // Frame returned by the next call to `read_frame`, before reading the
+    // stream. See `unread_frame`.
+    unread: Option<Frame>,
+
+    // Number of bytes of the frames read so far.
+    bytes_read: u64,
 }
 
 impl Connection {
     /// Create a new `Connection`, backed by `socket`. Read and write buffers
     /// are initialized.
     pub fn new(socket: TcpStream) -> Connection {
+        Connection::from_stream(socket)
+    }
+
+    /// Create a new `Connection`, backed by `stream`.
+    pub(crate) fn from_stream(stream: impl Stream + 'static) -> Connection {
         Connection {
-            stream: BufWriter::new(socket),
+            stream: BufWriter::new(Box::new(stream)),
             // Default to a 4KB read buffer. For the use case of mini redis,
             // this is fine. However, real applications will want to tune this
             // value to their specific use case. There is a high likelihood that
             // a larger read buffer will work better.
             buffer: BytesMut::with_capacity(4 * 1024),
+            replies: None,
+            protocol: 2,
+            muted: false,
+            unread: None,
+            bytes_read: 0,
         }
     }
 
+    /// Performs the TLS handshake with the peer as the server, the frames are
+    /// then read from and written to the encrypted stream.
+    ///
+    /// Must be called before any frame is read or written.
+    #[cfg(feature = "tls")]
+    pub(crate) async fn accept_tls(&mut self, acceptor: &Acceptor) -> io::Result<()> {
+        // The stream is moved out of the connection during the handshake,
+        // leaving an empty one in its place.
+        let empty = BufWriter::new(Box::new(Cursor::new(Vec::new())) as Box<dyn Stream>);
+        let stream = std::mem::replace(&mut self.stream, empty).into_inner();
+
+        let stream = acceptor.accept(stream).await?;
+        self.stream = BufWriter::new(Box::new(stream));
+        Ok(())
+    }
+
+    /// Returns the version of the protocol the frames are written with.
+    pub(crate) fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    /// Write the frames with the version `protocol` of the protocol from now
+    /// on, `2` or `3`.
+    pub(crate) fn set_protocol(&mut self, protocol: u8) {
+        self.protocol = protocol;
+    }
+
+    /// Drop the frames written from now on if `muted` is set, instead of
+    /// writing them to the stream.
+    pub(crate) fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Buffer the frames written from now on instead of writing them to the
+    /// stream, until `take_replies` is called.
+    pub(crate) fn buffer_replies(&mut self) {
+        self.replies = Some(Vec::new());
+    }
+
+    /// Returns the frames buffered since `buffer_replies` was called. Frames
+    /// are written to the stream again from now on.
+    pub(crate) fn take_replies(&mut self) -> Vec<Frame> {
+        self.replies.take().unwrap_or_default()
+    }
+
+    /// Replace the buffered frames with `replies`, returning the frames
+    /// buffered so far. `None` writes the frames to the stream again.
+    ///
+    /// This captures the reply of a single command, even when the replies of
+    /// a transaction are already buffered.
+    pub(crate) fn replace_replies(&mut self, replies: Option<Vec<Frame>>) -> Option<Vec<Frame>> {
+        std::mem::replace(&mut self.replies, replies)
+    }
+
+    /// Returns the number of bytes of the frames read so far.
+    pub(crate) fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Return `frame` from the next call to `read_frame`.
+    ///
+    /// A command leaving a mode of the connection, such as `RESET` in the
+    /// subscribed state, is read by the mode, then given back to be applied
+    /// as any other command.
+    pub(crate) fn unread_frame(&mut self, frame: Frame) {
+        self.unread = Some(frame);
+    }
+
     /// Read a single `Frame` value from the underlying stream.
     ///
     /// The function waits until it has retrieved enough data to parse a frame.
@@ -57,6 +176,10 @@ impl Connection {
     /// is closed in a way that doesn't break a frame in half, it returns
     /// `None`. Otherwise, an error is returned.
     pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
+        if let Some(frame) = self.unread.take() {
+            return Ok(Some(frame));
+        }
+
         loop {
             // Attempt to parse a frame from the buffered data. If enough data
             // has been buffered, the frame is returned.
@@ -90,6 +213,13 @@ impl Connection {
     fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
         use frame::Error::Incomplete;
 
+        // A line not starting with a frame type is an inline command, as typed
+        // in `telnet` or `netcat`.
+        match self.buffer.first() {
+            Some(&byte) if !frame::is_frame_type(byte) => return self.parse_inline(),
+            _ => {}
+        }
+
         // Cursor is used to track the "current" location in the
         // buffer. Cursor also implements `Buf` from the `bytes` crate
         // which provides a number of helpful utilities for working
@@ -129,6 +259,7 @@ impl Connection {
                 // left to `BytesMut`. This is often done by moving an internal
                 // cursor, but it may be done by reallocating and copying data.
                 self.buffer.advance(len);
+                self.bytes_read += len as u64;
 
                 // Return the parsed frame to the caller.
                 Ok(Some(frame))
@@ -148,6 +279,44 @@ impl Connection {
         }
     }
 
+    /// Tries to parse an inline command from the buffer: a line of arguments
+    /// separated by spaces, which may be quoted as in `redis-cli`. The command
+    /// is returned as an array of bulk strings, as if it had been sent as
+    /// such.
+    ///
+    /// Empty lines are skipped. `Ok(None)` is returned until the whole line
+    /// has been buffered.
+    fn parse_inline(&mut self) -> crate::Result<Option<Frame>> {
+        // Empty lines are skipped in a loop rather than by recursing, as a
+        // client may send any number of them.
+        loop {
+            let end = match self.buffer.iter().position(|&byte| byte == b'\n') {
+                Some(end) => end,
+                None if self.buffer.len() > MAX_INLINE_LEN => {
+                    return Err(ProtocolError("too big inline request").into())
+                }
+                None => return Ok(None),
+            };
+
+            // The line may end with `\r\n` or a single `\n`.
+            let line = self.buffer.split_to(end + 1);
+            self.bytes_read += line.len() as u64;
+            let args = split_inline(&line[..end])?;
+
+            if !args.is_empty() {
+                return Ok(Some(Frame::Array(
+                    args.into_iter().map(Frame::Bulk).collect(),
+                )));
+            }
+
+            // The next line may be a frame rather than an inline command.
+            match self.buffer.first() {
+                Some(&byte) if frame::is_frame_type(byte) => return self.parse_frame(),
+                _ => {}
+            }
+        }
+    }
+
     /// Write a single `Frame` value to the underlying stream.
     ///
     /// The `Frame` value is written to the socket using the various `write_*`
@@ -157,35 +326,56 @@ impl Connection {
     /// write stream. The data will be written to the buffer. Once the buffer is
     /// full, it is flushed to the underlying socket.
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        // Arrays are encoded by encoding each entry. All other frame types are
-        // considered literals. For now, mini-redis is not able to encode
-        // recursive frame structures. See below for more details.
-        match frame {
-            Frame::Array(val) => {
-                // Encode the frame type prefix. For an array, it is `*`.
-                self.stream.write_u8(b'*').await?;
-
-                // Encode the length of the array.
-                self.write_decimal(val.len() as u64).await?;
+        if let Some(replies) = &mut self.replies {
+            replies.push(frame.clone());
+            return Ok(());
+        }
 
-                // Iterate and encode each entry in the array.
-                for entry in &**val {
-                    self.write_value(entry).await?;
-                }
-            }
-            // The frame type is a literal. Encode the value directly.
-            _ => self.write_value(frame).await?,
+        if self.muted {
+            return Ok(());
         }
 
+        self.write_value(frame).await?;
+
         // Ensure the encoded frame is written to the socket. The calls above
         // are to the buffered stream and writes. Calling `flush` writes the
         // remaining contents of the buffer to the socket.
         self.stream.flush().await
     }
 
-    /// Write a frame literal to the stream
+    /// Write frames already encoded with `Frame::encode` to the underlying
+    /// stream, as the commands streamed to replicas are.
+    pub(crate) async fn write_encoded(&mut self, src: &[u8]) -> io::Result<()> {
+        self.stream.write_all(src).await?;
+        self.stream.flush().await
+    }
+
+    /// Write a frame value to the stream
     async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
+        let resp3 = self.protocol >= 3;
+
         match frame {
+            Frame::Array(val) | Frame::Set(val) | Frame::Push(val) => {
+                // Encode the frame type prefix. For an array, it is `*`. Sets
+                // and pushes are arrays in RESP2.
+                let prefix = match frame {
+                    Frame::Set(_) if resp3 => b'~',
+                    Frame::Push(_) if resp3 => b'>',
+                    _ => b'*',
+                };
+                self.stream.write_u8(prefix).await?;
+
+                // Encode the length of the array.
+                self.write_decimal(val.len() as i64).await?;
+
+                // Iterate and encode each entry in the array. Entries may be
+                // arrays themselves, as is the case for `XRANGE` responses. An
+                // async fn cannot call itself directly as its future would
+                // have an infinite size, so the recursive call is boxed.
+                for entry in &**val {
+                    Box::pin(self.write_value(entry)).await?;
+                }
+            }
             Frame::Simple(val) => {
                 self.stream.write_u8(b'+').await?;
                 self.stream.write_all(val.as_bytes()).await?;
@@ -200,33 +390,68 @@ impl Connection {
                 self.stream.write_u8(b':').await?;
                 self.write_decimal(*val).await?;
             }
+            Frame::Map(val) => {
+                // A map is a flat array of keys and values in RESP2.
+                if resp3 {
+                    self.stream.write_u8(b'%').await?;
+                    self.write_decimal(val.len() as i64).await?;
+                } else {
+                    self.stream.write_u8(b'*').await?;
+                    self.write_decimal(2 * val.len() as i64).await?;
+                }
+
+                for (key, value) in &**val {
+                    Box::pin(self.write_value(key)).await?;
+                    Box::pin(self.write_value(value)).await?;
+                }
+            }
+            Frame::Null if resp3 => {
+                self.stream.write_all(b"_\r\n").await?;
+            }
             Frame::Null => {
                 self.stream.write_all(b"$-1\r\n").await?;
             }
+            Frame::Double(val) => {
+                // A double is a bulk string in RESP2.
+                let val = frame::format_double(*val);
+                if resp3 {
+                    self.stream.write_u8(b',').await?;
+                } else {
+                    self.stream.write_u8(b'$').await?;
+                    self.write_decimal(val.len() as i64).await?;
+                }
+                self.stream.write_all(val.as_bytes()).await?;
+                self.stream.write_all(b"\r\n").await?;
+            }
+            Frame::Boolean(val) if resp3 => {
+                let val: &[u8] = if *val { b"#t\r\n" } else { b"#f\r\n" };
+                self.stream.write_all(val).await?;
+            }
+            Frame::Boolean(val) => {
+                // A boolean is the integer `1` or `0` in RESP2.
+                self.stream.write_u8(b':').await?;
+                self.write_decimal(*val as i64).await?;
+            }
             Frame::Bulk(val) => {
                 let len = val.len();
 
                 self.stream.write_u8(b'$').await?;
-                self.write_decimal(len as u64).await?;
+                self.write_decimal(len as i64).await?;
                 self.stream.write_all(val).await?;
                 self.stream.write_all(b"\r\n").await?;
             }
-            // Encoding an `Array` from within a value cannot be done using a
-            // recursive strategy. In general, async fns do not support
-            // recursion. Mini-redis has not needed to encode nested arrays yet,
-            // so for now it is skipped.
-            Frame::Array(_val) => unreachable!(),
         }
 
         Ok(())
     }
 
     /// Write a decimal frame to the stream
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
+    async fn write_decimal(&mut self, val: i64) -> io::Result<()> {
         use std::io::Write;
 
-        // Convert the value to a string
-        let mut buf = [0u8; 12];
+        // Convert the value to a string. The buffer fits any `i64`, sign
+        // included.
+        let mut buf = [0u8; 20];
         let mut buf = Cursor::new(&mut buf[..]);
         write!(&mut buf, "{}", val)?;
 
@@ -237,3 +462,77 @@ impl Connection {
         Ok(())
     }
 }
+
+/// Split the line of an inline command into its arguments.
+///
+/// Arguments are separated by whitespace. An argument within double quotes
+/// may hold whitespace and the escape sequences `\n`, `\r`, `\t`, `\b`, `\a`
+/// and `\xHH`, any other escaped character standing for itself. Within single
+/// quotes, only `\'` is escaped.
+fn split_inline(line: &[u8]) -> crate::Result<Vec<Bytes>> {
+    let unbalanced = || ProtocolError("unbalanced quotes in request");
+
+    let mut args = vec![];
+    let mut chars = line.iter().copied().peekable();
+
+    loop {
+        while chars.peek().is_some_and(u8::is_ascii_whitespace) {
+            chars.next();
+        }
+
+        let quote = match chars.peek() {
+            None => return Ok(args),
+            Some(&c) if c == b'"' || c == b'\'' => chars.next(),
+            Some(_) => None,
+        };
+
+        let mut arg = vec![];
+        loop {
+            let c = match (chars.next(), quote) {
+                (None, None) => break,
+                (None, Some(_)) => return Err(unbalanced().into()),
+                (Some(c), None) if c.is_ascii_whitespace() => break,
+                (Some(c), Some(quote)) if c == quote => {
+                    // The closing quote must end the argument.
+                    match chars.peek() {
+                        Some(c) if !c.is_ascii_whitespace() => return Err(unbalanced().into()),
+                        _ => break,
+                    }
+                }
+                (Some(b'\\'), Some(b'"')) => match chars.next().ok_or_else(unbalanced)? {
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'b' => 8,
+                    b'a' => 7,
+                    b'x' => {
+                        let hex = [chars.next(), chars.next()];
+                        let hex: Option<Vec<u8>> = hex.iter().copied().collect();
+                        hex.and_then(|hex| {
+                            let hex = std::str::from_utf8(&hex).ok()?;
+                            u8::from_str_radix(hex, 16).ok()
+                        })
+                        .ok_or(ProtocolError("invalid escape in request"))?
+                    }
+                    c => c,
+                },
+                (Some(b'\\'), Some(b'\'')) if chars.peek() == Some(&b'\'') => {
+                    chars.next();
+                    b'\''
+                }
+                (Some(c), _) => c,
+            };
+            arg.push(c);
+        }
+
+        args.push(Bytes::from(arg));
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "ERR Protocol error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
@@ -1,27 +1,197 @@
-use tokio::sync::{broadcast, Notify};
+mod bitmap;
+pub use bitmap::{BitOperation, BitUnit, Overflow};
+pub(crate) use bitmap::{Field, FieldOp, FieldType};
+
+mod clients;
+use clients::{ClientState, MaxClientsPolicy};
+
+mod config;
+pub(crate) use config::Config;
+
+mod eviction;
+pub(crate) use eviction::EvictionPolicy;
+use eviction::LFU_INIT;
+
+mod expirations;
+use expirations::Expirations;
+
+mod geo;
+pub(crate) use geo::{encode as geo_encode, is_valid as geo_is_valid};
+pub use geo::{GeoOrigin, GeoShape, GeoUnit};
+
+mod glob;
+pub(crate) use glob::matches as glob_matches;
+
+mod hash;
+
+mod hyperloglog;
+
+mod latency;
+use latency::LatencyMonitor;
+pub(crate) use latency::LatestSpike;
+
+mod lcs;
+
+mod list;
+
+mod monitor;
+use monitor::{redacted, Monitors};
+
+mod notify;
+pub(crate) use notify::KeyspaceEvents;
+
+mod persistence;
+
+mod ratelimit;
+pub(crate) use ratelimit::RateLimits;
+use ratelimit::{Exceeded, RateLimiter};
+
+mod replication;
+
+mod rewrite;
+
+mod set;
+
+mod snapshot;
+pub(crate) use snapshot::Snapshot;
+
+mod slowlog;
+use slowlog::Slowlog;
+pub(crate) use slowlog::SlowlogEntry;
+
+mod sorted_set;
+use sorted_set::SortedSet;
+pub(crate) use sorted_set::{LexBound, ScoreBound};
+
+mod stats;
+use stats::Stats;
+pub(crate) use stats::{Info, MemoryStats};
+
+mod string;
+
+mod stream;
+use stream::Stream;
+pub(crate) use stream::{
+    ClaimOptions, NewStreamId, StreamEntry, StreamFields, StreamId, Trim, TrimStrategy,
+};
+
+use crate::acl::{Acl, User, DEFAULT_USER};
+use crate::cluster::{key_slot, Cluster, Node, Redirect, SlotState};
+use crate::cmd::{Command, Renames};
+use crate::persistence::Aof;
+use crate::replication::{Link, Replicas};
+use crate::script::{Library, Script};
+use crate::Frame;
+
+use tokio::sync::{broadcast, watch, Notify};
 use tokio::time::{self, Duration, Instant};
+use tracing::error;
 
 use bytes::Bytes;
-use std::collections::{BTreeMap, HashMap};
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::SystemTime;
+
+/// Number of keys the background task purges at once, before releasing the
+/// lock so that the commands of the clients are not delayed.
+const ACTIVE_EXPIRE_KEYS_PER_LOOP: usize = 20;
+
+/// Percentage of the keys purged at once above which the background task
+/// purges the database again without waiting, there likely being more keys
+/// to purge.
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: usize = 10;
+
+/// Time the background task may spend purging the keys in each cycle. Once
+/// it is spent, the purge resumes on the next cycle, so that a burst of
+/// expirations does not monopolize the lock.
+const ACTIVE_EXPIRE_CYCLE_BUDGET: Duration = Duration::from_millis(25);
+
+/// Period of the cycles of the background task, while there are expired keys
+/// left to purge.
+const ACTIVE_EXPIRE_CYCLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Number of elements above which `UNLINK` frees a collection in the
+/// background.
+const LAZYFREE_THRESHOLD: usize = 64;
+
+/// Length, in bytes, above which `UNLINK` frees a string in the background.
+const LAZYFREE_STRING_LEN: usize = 1024 * 1024;
+
+/// Number of elements of a collection sampled by default to estimate its
+/// size, as by `MEMORY USAGE` without the `SAMPLES` option.
+pub(crate) const MEMORY_SAMPLES: usize = 5;
+
+/// Bytes used by an entry in the key-value map, not counting the content of
+/// its key and value.
+const ENTRY_OVERHEAD: usize = std::mem::size_of::<(String, Entry)>();
+
+/// Bytes used by an entry in the index of the expirations.
+const EXPIRATION_OVERHEAD: usize = std::mem::size_of::<(u64, (Instant, String))>();
+
+/// Channel the clients tracking keys redirect their invalidation messages to.
+const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// Maps holding the keys removed from a database by `FLUSHDB` or `FLUSHALL`.
+type Flushed = (HashMap<String, Entry>, BTreeSet<(u64, String)>, Expirations);
 
 /// Server state shared across all connections.
 ///
-/// `Db` contains a `HashMap` storing the key/value data and all
-/// `broadcast::Sender` values for active pub/sub channels.
+/// `Db` contains the numbered databases, each a `HashMap` storing key/value
+/// data, and all `broadcast::Sender` values for active pub/sub channels.
 ///
-/// A `Db` instance is a handle to shared state. Cloning `Db` is shallow and
-/// only incurs an atomic ref count increment.
+/// A `Db` instance is a handle to shared state, through which commands access
+/// one of the databases, the selected one. Cloning `Db` is shallow and only
+/// incurs an atomic ref count increment.
 ///
 /// When a `Db` value is created, a background task is spawned. This task is
 /// used to expire values after the requested duration has elapsed. The task
 /// runs until all instances of `Db` are dropped, at which point the task
 /// terminates.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub(crate) struct Db {
     /// Handle to shared state. The background task will also have an
     /// `Arc<Shared>`.
     shared: Arc<Shared>,
+
+    /// Index of the selected database.
+    index: usize,
+
+    /// Identifier of the transaction the handle executes, if any.
+    transaction: Option<u64>,
+
+    /// Identifier of the client connection the handle belongs to, `0` for the
+    /// handles of the server itself.
+    client: u64,
+
+    /// Frame of the command being applied through the handle, appended to the
+    /// AOF once the command modifies the databases. See `set_logged_command`.
+    command: Mutex<Option<Frame>>,
+}
+
+/// Handle to the script being run by a client, returned by `Db::start_script`.
+///
+/// The script stops running, as far as the other clients are concerned, when
+/// the `ScriptRun` is dropped.
+#[derive(Debug)]
+pub(crate) struct ScriptRun {
+    shared: Arc<Shared>,
+    script: RunningScript,
+}
+
+/// Handle executing a transaction, returned by `Db::transaction`.
+///
+/// While a transaction runs, only the handle returned by `db` accesses the
+/// databases, other handles wait for the transaction to complete. The
+/// transaction completes when the `Transaction` is dropped.
+#[derive(Debug)]
+pub(crate) struct Transaction {
+    db: Db,
 }
 
 #[derive(Debug)]
@@ -38,44 +208,385 @@ struct Shared {
     /// operations), then the entire operation, including waiting for the mutex,
     /// is considered a "blocking" operation and `tokio::task::spawn_blocking`
     /// should be used.
-    state: Mutex<State>,
+    ///
+    /// All the databases are guarded by the same mutex, so that commands
+    /// involving several of them are atomic.
+    databases: Mutex<Databases>,
+
+    /// Notified when a transaction completes, waking up the handles waiting
+    /// to access the databases.
+    transaction_done: Condvar,
 
     /// Notifies the background task handling entry expiration. The background
     /// task waits on this to be notified, then checks for expired values or the
     /// shutdown signal.
     background_task: Notify,
+
+    /// Notifies the task replicating the primary that the primary changed.
+    primary_changed: Notify,
+
+    /// Notifies the listener, waiting for fewer than `maxclients` clients to
+    /// be connected, that a client disconnected or that the configuration
+    /// changed.
+    clients_changed: Notify,
+
+    /// Notifies the server that a client requested it to shut down with
+    /// `SHUTDOWN`.
+    shutdown_requested: Notify,
+
+    /// Counters reported by `INFO`.
+    stats: Stats,
+
+    /// Connections running `MONITOR`.
+    monitors: Monitors,
+
+    /// Latest commands slower than the `slowlog-log-slower-than` parameter.
+    /// It has its own lock, as it is updated once the commands completed.
+    slowlog: Mutex<Slowlog>,
+
+    /// Latency spikes longer than the `latency-monitor-threshold` parameter.
+    /// As the slow log, it has its own lock.
+    latency: Mutex<LatencyMonitor>,
+
+    /// Pause of the clients set by `CLIENT PAUSE`, `None` once they are
+    /// unpaused. The receiver is cloned by the handles waiting for the pause
+    /// to end.
+    pause: (watch::Sender<Option<Pause>>, watch::Receiver<Option<Pause>>),
+
+    /// Script being run, if any. The receiver is cloned by the handles
+    /// waiting for the script to complete.
+    script: (
+        watch::Sender<Option<RunningScript>>,
+        watch::Receiver<Option<RunningScript>>,
+    ),
+}
+
+/// Commands of the clients are held until `until`, only the ones which may
+/// modify the data set if `writes_only` is set.
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    writes_only: bool,
+}
+
+/// A script run by `client`, since `started`.
+#[derive(Debug, Clone)]
+struct RunningScript {
+    client: u64,
+    started: Instant,
+
+    /// Whether the script is a function called by `FCALL`, killed by
+    /// `FUNCTION KILL` rather than `SCRIPT KILL`.
+    function: bool,
+
+    /// One of `SCRIPT_RUNNING`, `SCRIPT_WROTE` and `SCRIPT_KILLED`, shared
+    /// with the thread running the script.
+    state: Arc<AtomicU8>,
+}
+
+/// The script did not execute any write command yet.
+const SCRIPT_RUNNING: u8 = 0;
+
+/// The script executed a write command, after which it may only be killed by
+/// `SHUTDOWN NOSAVE`.
+const SCRIPT_WROTE: u8 = 1;
+
+/// The script was killed, it raises an error as soon as it sees it.
+const SCRIPT_KILLED: u8 = 2;
+
+#[derive(Debug)]
+struct Databases {
+    /// The state of each database, indexed by database number.
+    states: Vec<State>,
+
+    /// The pub/sub key-space. Redis uses a **separate** key space for key-value
+    /// and pub/sub. `mini-redis` handles this by using a separate `HashMap`,
+    /// shared by all the databases.
+    pub_sub: HashMap<String, PubSubChannel<Bytes>>,
+
+    /// Channels of the clients subscribed to a pattern with `PSUBSCRIBE`,
+    /// indexed by the pattern. Messages are sent along with the channel they
+    /// were published to.
+    pattern_pub_sub: HashMap<String, PubSubChannel<(String, Bytes)>>,
+
+    /// Shard channels of `SSUBSCRIBE` and `SPUBLISH`, separate from the
+    /// channels of `SUBSCRIBE` and `PUBLISH`.
+    shard_pub_sub: HashMap<String, PubSubChannel<Bytes>>,
+
+    /// True when the Db instance is shutting down. This happens when all `Db`
+    /// values drop. Setting this to `true` signals to the background task to
+    /// exit.
+    shutdown: bool,
+
+    /// Identifier of the transaction being executed, if any.
+    transaction: Option<u64>,
+
+    /// Identifier to use for the next transaction.
+    next_transaction: u64,
+
+    /// Scripts cached by `EVAL` and `SCRIPT LOAD`, indexed by the SHA1 digest
+    /// of their source. Scripts are shared by all the databases.
+    scripts: HashMap<String, Arc<Script>>,
+
+    /// Libraries loaded by `FUNCTION LOAD`, indexed by name. Libraries are
+    /// shared by all the databases.
+    libraries: BTreeMap<String, Arc<Library>>,
+
+    /// Number of modifications of the databases when the last snapshot was
+    /// taken.
+    saved_changes: u64,
+
+    /// File the snapshots are saved to, if snapshots are enabled.
+    snapshot_path: Option<PathBuf>,
+
+    /// True while a snapshot is being saved. Only one snapshot is saved at a
+    /// time.
+    saving: bool,
+
+    /// When the last snapshot was successfully saved, or when the server
+    /// started if none was.
+    last_save: SystemTime,
+
+    /// Number of modifications of the loaded libraries.
+    library_changes: u64,
+
+    /// Writer appending the commands modifying the databases to the AOF, if
+    /// it is enabled.
+    aof: Option<Aof>,
+
+    /// Streams of the commands modifying the databases to the replicas.
+    replicas: Replicas,
+
+    /// Address of the primary the databases replicate, as `host:port`, or
+    /// `None` if they do not replicate any.
+    primary: Option<String>,
+
+    /// State of the connection with the primary, if any.
+    primary_link: Link,
+
+    /// Offset reached in the stream of the primary, if the databases were
+    /// synchronized with it.
+    primary_offset: Option<u64>,
+
+    /// Configuration of the cluster, if cluster mode is enabled.
+    cluster: Option<Cluster>,
+
+    /// Highest number of bytes used by the keys, as reported by `MEMORY
+    /// STATS`.
+    peak_memory: usize,
+
+    /// Set by `SHUTDOWN NOSAVE`, in which case no snapshot is saved when the
+    /// server shuts down.
+    shutdown_nosave: bool,
+
+    /// Whether the background task purges the expired keys, unset by `DEBUG
+    /// SET-ACTIVE-EXPIRE 0`.
+    active_expire: bool,
+
+    /// Parameters read and modified with `CONFIG GET` and `CONFIG SET`.
+    config: Config,
+
+    /// Keyspace events recorded while holding the lock, as the database, the
+    /// event and the key. They are published when the lock is released.
+    notifications: Vec<(usize, &'static str, String)>,
+
+    /// Users clients authenticate as using `AUTH`.
+    acl: Acl,
+
+    /// Rate limiters shared by the clients authenticated as a user, indexed
+    /// by user. Created as the users with rate limits send commands.
+    user_limiters: HashMap<String, RateLimiter>,
+
+    /// The connected clients, indexed by identifier.
+    clients: HashMap<u64, ClientState>,
+
+    /// Identifier to use for the next client. Client IDs start at `1`, `0`
+    /// identifying the server itself.
+    next_client: u64,
+
+    /// Clients with tracking enabled by `CLIENT TRACKING`, along with the
+    /// client their invalidation messages are redirected to, if any.
+    tracking: HashMap<u64, Option<u64>>,
+
+    /// Channels of the clients subscribed to `__redis__:invalidate`, indexed
+    /// by client. They receive the keys invalidated for the clients
+    /// redirecting to them.
+    invalidations: HashMap<u64, PubSubChannel<Bytes>>,
+
+    /// Channels of the RESP3 clients tracking keys without redirecting their
+    /// invalidation messages, indexed by client. Their connection pushes the
+    /// keys invalidated for them.
+    pushed_invalidations: HashMap<u64, broadcast::Sender<Bytes>>,
+
+    /// Commands renamed with `rename-command`.
+    renames: Arc<Renames>,
 }
 
+/// State of a single database.
 #[derive(Debug)]
 struct State {
     /// The key-value data. We are not trying to do anything fancy so a
     /// `std::collections::HashMap` works fine.
     entries: HashMap<String, Entry>,
 
-    /// The pub/sub key-space. Redis uses a **separate** key space for key-value
-    /// and pub/sub. `mini-redis` handles this by using a separate `HashMap`.
-    pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
+    /// Every key of `entries`, ordered by a hash of the key.
+    ///
+    /// `SCAN` cursors are positions in this order. Unlike positions in the
+    /// `HashMap`, they remain valid as keys are inserted and removed, so that
+    /// a key present during a whole iteration is always returned.
+    scan_index: BTreeSet<(u64, String)>,
+
+    /// Wakes up clients blocked waiting for a key to be written to, such as
+    /// `XREAD` with the `BLOCK` option. As with pub/sub, a broadcast channel is
+    /// associated with each key clients are blocked on.
+    blocking: HashMap<String, broadcast::Sender<()>>,
+
+    /// Tickets of the clients blocked popping from a key, in the order they
+    /// started waiting. When a value is pushed, every blocked client is woken
+    /// up but only the longest waiting ones may pop it.
+    queued: HashMap<String, VecDeque<u64>>,
 
     /// Tracks key TTLs.
     ///
-    /// A `BTreeMap` is used to maintain expirations sorted by when they expire.
-    /// This allows the background task to iterate this map to find the value
-    /// expiring next.
-    ///
-    /// While highly unlikely, it is possible for more than one expiration to be
-    /// created for the same instant. Because of this, the `Instant` is
-    /// insufficient for the key. A unique expiration identifier (`u64`) is used
-    /// to break these ties.
-    expirations: BTreeMap<(Instant, u64), String>,
+    /// A timer wheel is used to find the value expiring next in constant
+    /// time, however many keys have an expiration. This allows the background
+    /// task to purge the keys as they expire.
+    expirations: Expirations,
 
     /// Identifier to use for the next expiration. Each expiration is associated
     /// with a unique identifier. See above for why.
     next_id: u64,
 
-    /// True when the Db instance is shutting down. This happens when all `Db`
-    /// values drop. Setting this to `true` signals to the background task to
-    /// exit.
-    shutdown: bool,
+    /// Keys watched by at least one client with `WATCH`. Only these keys have
+    /// their modifications tracked.
+    watched: HashMap<String, WatchedKey>,
+
+    /// Number of modifications of the database, compared to the number of
+    /// modifications when the last snapshot was taken to determine whether
+    /// it must be saved again.
+    changes: u64,
+
+    /// Expirations set since the last command was logged. They are logged
+    /// as Unix times after the command, which may have set them relative to
+    /// the current time.
+    logged_expirations: Vec<(String, Instant)>,
+
+    /// Clients with tracking enabled which read each key since it was last
+    /// modified.
+    tracked: HashMap<String, HashSet<u64>>,
+
+    /// Keys modified since the invalidation messages were last sent, along
+    /// with the clients which read them.
+    invalidated: Vec<(String, HashSet<u64>)>,
+
+    /// Approximate number of bytes used by the entries, the sum of their
+    /// `size`.
+    used_memory: usize,
+
+    /// Keys modified since the size of their entry was last updated.
+    resized: HashSet<String>,
+
+    /// Keys evicted, or transferred by `MIGRATE`, since the last command was
+    /// logged. They are logged as `DEL` commands, so that the replicas and the
+    /// AOF remove them as well.
+    logged_deletions: Vec<String>,
+}
+
+/// Modifications of a key watched by clients.
+#[derive(Debug)]
+struct WatchedKey {
+    /// Incremented each time the key is modified.
+    version: u64,
+
+    /// Number of clients watching the key.
+    watchers: usize,
+}
+
+/// Key watched by a client, returned by `Db::watch`.
+#[derive(Debug)]
+pub(crate) struct Watched {
+    /// Index of the database holding the key.
+    index: usize,
+
+    /// The watched key.
+    key: String,
+
+    /// Version of the key when the client started watching it.
+    version: u64,
+}
+
+/// Keys being transferred by `MIGRATE`, returned by `Db::begin_migration`.
+#[derive(Debug)]
+pub(crate) struct Migration {
+    /// The keys, along with their serialized value and their time to live in
+    /// milliseconds, `0` if they do not expire.
+    values: Vec<(String, Bytes, u64)>,
+
+    /// The keys are watched, so that only the ones not modified since are
+    /// removed once transferred.
+    watched: Vec<Watched>,
+}
+
+/// A pub/sub channel, or pattern, with at least one subscriber.
+#[derive(Debug)]
+struct PubSubChannel<T> {
+    /// Sends the messages published to the subscribers
+    tx: broadcast::Sender<T>,
+
+    /// Number of subscriptions, the channel being removed once the last one
+    /// is dropped.
+    subscribers: usize,
+}
+
+/// Subscription to a pub/sub channel or pattern, returned by `Db::subscribe`,
+/// `Db::psubscribe` and `Db::ssubscribe`.
+///
+/// Subscribers are counted for `PUBSUB`. The subscription is counted until it
+/// is dropped.
+#[derive(Debug)]
+pub(crate) struct Subscription<T> {
+    rx: broadcast::Receiver<T>,
+
+    /// The subscription does not keep the shared state alive, so that the
+    /// background task is still shut down when the last `Db` is dropped.
+    shared: Weak<Shared>,
+
+    /// Channel or pattern subscribed to
+    name: String,
+
+    /// What `name` is
+    kind: ChannelKind,
+}
+
+/// Kind of a pub/sub channel subscribed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelKind {
+    Channel,
+    Pattern,
+    Shard,
+
+    /// `__redis__:invalidate`, subscribed to by the given client
+    Invalidation(u64),
+}
+
+/// Guard giving access to the selected database while holding the lock on all
+/// the databases.
+struct StateGuard<'a> {
+    databases: DatabasesGuard<'a>,
+    index: usize,
+}
+
+/// Guard giving access to all the databases while holding the lock, on behalf
+/// of a handle applying a command which may modify them.
+///
+/// When the guard is dropped, the command is logged if the databases were
+/// modified.
+struct DatabasesGuard<'a> {
+    databases: MutexGuard<'a, Databases>,
+    db: &'a Db,
+
+    /// Number of modifications of the databases when the lock was acquired.
+    changes: u64,
 }
 
 /// Entry in the key-value store
@@ -85,258 +596,3742 @@ struct Entry {
     id: u64,
 
     /// Stored data
-    data: Bytes,
+    data: Value,
 
     /// Instant at which the entry expires and should be removed from the
     /// database.
     expires_at: Option<Instant>,
+
+    /// Instant at which the entry was last read or written.
+    last_access: Instant,
+
+    /// Logarithmic counter of the accesses to the entry, decaying over time,
+    /// used by the LFU eviction policies.
+    frequency: u8,
+
+    /// Approximate number of bytes used by the entry, its key and its value,
+    /// as of the last time it was written.
+    size: usize,
 }
 
-impl Db {
-    /// Create a new, empty, `Db` instance. Allocates shared state and spawns a
-    /// background task to manage key expiration.
-    pub(crate) fn new() -> Db {
-        let shared = Arc::new(Shared {
-            state: Mutex::new(State {
-                entries: HashMap::new(),
-                pub_sub: HashMap::new(),
-                expirations: BTreeMap::new(),
-                next_id: 0,
-                shutdown: false,
-            }),
-            background_task: Notify::new(),
-        });
+/// Value associated with a key.
+///
+/// Redis values are typed. Each command operates on a specific type of value
+/// and fails with a `WRONGTYPE` error when the key holds a value of a different
+/// type.
+#[derive(Debug, Clone)]
+enum Value {
+    /// A string value. Strings are binary safe and stored as raw bytes.
+    String(Bytes),
 
-        // Start the background task.
-        tokio::spawn(purge_expired_tasks(shared.clone()));
+    /// A map of field names to values.
+    Hash(HashMap<String, Bytes>),
 
-        Db { shared }
-    }
+    /// A list of values sorted by insertion order. A `VecDeque` is used as
+    /// values are pushed to and popped from both ends.
+    List(VecDeque<Bytes>),
 
-    /// Get the value associated with a key.
-    ///
-    /// Returns `None` if there is no value associated with the key. This may be
-    /// due to never having assigned a value to the key or a previously assigned
-    /// value expired.
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
-        // Acquire the lock, get the entry and clone the value.
-        //
-        // Because data is stored using `Bytes`, a clone here is a shallow
-        // clone. Data is not copied.
-        let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
-    }
+    /// An unordered collection of unique values.
+    Set(HashSet<Bytes>),
 
-    pub(crate) fn del(&self, key: &str) -> bool {
-        let mut state = self.shared.state.lock().unwrap();
-        if let Some(entry) = state.entries.remove(key) {
-            if let Some(when) = entry.expires_at {
-                // clear expiration
-                state.expirations.remove(&(when, entry.id));
-            }
-            true
-        } else {
-            false
-        }
-    }
+    /// A collection of unique values ordered by an associated score.
+    SortedSet(SortedSet),
 
-    /// Set the value associated with a key along with an optional expiration
-    /// Duration.
-    ///
-    /// If a value is already associated with the key, it is removed.
-    pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
-        let mut state = self.shared.state.lock().unwrap();
+    /// An append-only log of entries, each identified by a unique,
+    /// increasing, ID.
+    Stream(Stream),
+}
 
-        // Get and increment the next insertion ID. Guarded by the lock, this
-        // ensures a unique identifier is associated with each `set` operation.
-        let id = state.next_id;
-        state.next_id += 1;
+/// One of the two ends of a list.
+///
+/// Redis refers to the head of a list as its "left" end and to the tail as its
+/// "right" end, which is where the `L` and `R` command prefixes come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
 
-        // If this `set` becomes the key that expires **next**, the background
-        // task needs to be notified so it can update its state.
-        //
-        // Whether or not the task needs to be notified is computed during the
-        // `set` routine.
-        let mut notify = false;
-
-        let expires_at = expire.map(|duration| {
-            // `Instant` at which the key expires.
-            let when = Instant::now() + duration;
-
-            // Only notify the worker task if the newly inserted expiration is the
-            // **next** key to evict. In this case, the worker needs to be woken up
-            // to update its state.
-            notify = state
-                .next_expiration()
-                .map(|expiration| expiration > when)
-                .unwrap_or(true);
+/// Where `LINSERT` inserts an element, relative to the pivot element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    Before,
+    After,
+}
 
-            // Track the expiration.
-            state.expirations.insert((when, id), key.clone());
-            when
-        });
+/// One of the two ends of a sorted set, from which `ZPOPMIN` and `ZPOPMAX`
+/// pop members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreEnd {
+    /// The member with the lowest score.
+    Min,
 
-        // Insert the entry into the `HashMap`.
-        let prev = state.entries.insert(
-            key,
-            Entry {
-                id,
-                data: value,
-                expires_at,
-            },
-        );
+    /// The member with the highest score.
+    Max,
+}
 
-        // If there was a value previously associated with the key **and** it
-        // had an expiration time. The associated entry in the `expirations` map
-        // must also be removed. This avoids leaking data.
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                // clear expiration
-                state.expirations.remove(&(when, prev.id));
-            }
-        }
+/// Condition under which `EXPIRE` and similar commands update the expiration
+/// of a key.
+///
+/// For `Gt` and `Lt`, a key without an expiration is considered to have an
+/// infinite time to live.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireOption {
+    /// Only set the expiration if the key has none.
+    Nx,
 
-        // Release the mutex before notifying the background task. This helps
-        // reduce contention by avoiding the background task waking up only to
-        // be unable to acquire the mutex due to this function still holding it.
-        drop(state);
+    /// Only set the expiration if the key already has one.
+    Xx,
 
-        if notify {
-            // Finally, only notify the background task if it needs to update
-            // its state to reflect a new expiration.
-            self.shared.background_task.notify_one();
-        }
-    }
+    /// Only set the expiration if it is later than the current one.
+    Gt,
 
-    /// Returns a `Receiver` for the requested channel.
-    ///
-    /// The returned `Receiver` is used to receive values broadcast by `PUBLISH`
-    /// commands.
-    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
-        use std::collections::hash_map::Entry;
+    /// Only set the expiration if it is sooner than the current one.
+    Lt,
+}
 
-        // Acquire the mutex
-        let mut state = self.shared.state.lock().unwrap();
-
-        // If there is no entry for the requested channel, then create a new
-        // broadcast channel and associate it with the key. If one already
-        // exists, return an associated receiver.
-        match state.pub_sub.entry(key) {
-            Entry::Occupied(e) => e.get().subscribe(),
-            Entry::Vacant(e) => {
-                // No broadcast channel exists yet, so create one.
-                //
-                // The channel is created with a capacity of `1024` messages. A
-                // message is stored in the channel until **all** subscribers
-                // have seen it. This means that a slow subscriber could result
-                // in messages being held indefinitely.
-                //
-                // When the channel's capacity fills up, publishing will result
-                // in old messages being dropped. This prevents slow consumers
-                // from blocking the entire system.
-                let (tx, rx) = broadcast::channel(1024);
-                e.insert(tx);
-                rx
-            }
-        }
-    }
+/// Option of `ZADD` controlling which members are added or updated, and what
+/// the command replies with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZAddOption {
+    /// Only add new members, never update the score of existing ones.
+    Nx,
 
-    /// Publish a message to the channel. Returns the number of subscribers
-    /// listening on the channel.
-    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
-        let state = self.shared.state.lock().unwrap();
+    /// Only update the score of existing members, never add new ones.
+    Xx,
 
-        state
-            .pub_sub
-            .get(key)
-            // On a successful message send on the broadcast channel, the number
-            // of subscribers is returned. An error indicates there are no
-            // receivers, in which case, `0` should be returned.
-            .map(|tx| tx.send(value).unwrap_or(0))
-            // If there is no entry for the channel key, then there are no
-            // subscribers. In this case, return `0`.
-            .unwrap_or(0)
-    }
+    /// Only update the score of existing members if the new score is greater.
+    Gt,
+
+    /// Only update the score of existing members if the new score is lower.
+    Lt,
+
+    /// Count the members whose score changed along with the added members.
+    Ch,
 }
 
-impl Drop for Db {
-    fn drop(&mut self) {
-        // If this is the last active `Db` instance, the background task must be
-        // notified to shut down.
-        //
-        // First, determine if this is the last `Db` instance. This is done by
-        // checking `strong_count`. The count will be 2. One for this `Db`
-        // instance and one for the handle held by the background task.
-        if Arc::strong_count(&self.shared) == 2 {
-            // The background task must be signaled to shutdown. This is done by
-            // setting `State::shutdown` to `true` and signalling the task.
-            let mut state = self.shared.state.lock().unwrap();
-            state.shutdown = true;
+/// Condition under which `SET` writes the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// Only set the value if the key does not exist.
+    Nx,
 
-            // Drop the lock before signalling the background task. This helps
-            // reduce lock contention by ensuring the background task doesn't
-            // wake up only to be unable to acquire the mutex.
-            drop(state);
-            self.shared.background_task.notify_one();
-        }
-    }
+    /// Only set the value if the key already exists.
+    Xx,
 }
 
-impl Shared {
-    /// Purge all expired keys and return the `Instant` at which the **next**
-    /// key will expire. The background task will sleep until this instant.
-    fn purge_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
-
-        if state.shutdown {
-            // The database is shutting down. All handles to the shared state
-            // have dropped. The background task should exit.
-            return None;
-        }
+/// Options of `Db::set_with`, mirroring the options of the `SET` command.
+#[derive(Debug, Default)]
+pub(crate) struct SetOptions {
+    /// `Instant` at which the key expires.
+    pub(crate) expires_at: Option<Instant>,
 
-        // This is needed to make the borrow checker happy. In short, `lock()`
-        // returns a `MutexGuard` and not a `&mut State`. The borrow checker is
-        // not able to see "through" the mutex guard and determine that it is
-        // safe to access both `state.expirations` and `state.entries` mutably,
-        // so we get a "real" mutable reference to `State` outside of the loop.
-        let state = &mut *state;
+    /// Keep the expiration of the previous value. Overrides `expires_at`.
+    pub(crate) keep_ttl: bool,
 
-        // Find all keys scheduled to expire **before** now.
-        let now = Instant::now();
+    /// Condition under which the value is set.
+    pub(crate) condition: Option<SetCondition>,
 
-        while let Some((&(when, id), key)) = state.expirations.iter().next() {
-            if when > now {
-                // Done purging, `when` is the instant at which the next key
-                // expires. The worker task will wait until this instant.
-                return Some(when);
-            }
+    /// Return the previous value.
+    pub(crate) get: bool,
+}
 
-            // The key expired, remove it
-            state.entries.remove(key);
-            state.expirations.remove(&(when, id));
-        }
+/// Options of `Db::sort`, mirroring the options of the `SORT` command.
+#[derive(Debug, Default)]
+pub(crate) struct SortOptions {
+    /// Compare elements as binary strings instead of as numbers.
+    pub(crate) alpha: bool,
+
+    /// Sort from the greatest to the smallest element.
+    pub(crate) desc: bool,
+
+    /// Number of sorted elements to skip and maximum number of elements to
+    /// return. A negative count returns all the remaining elements.
+    pub(crate) limit: Option<(i64, i64)>,
+}
+
+/// Operation combining sets, performed by `SINTER`, `SUNION`, `SDIFF` and
+/// their `STORE` variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SetOperation {
+    /// Members of all the sets.
+    Inter,
+
+    /// Members of any of the sets.
+    Union,
+
+    /// Members of the first set which are not members of the other sets.
+    Diff,
+}
+
+/// Error returned when a `Db` operation cannot be applied to the state of the
+/// key space.
+///
+/// These errors are not fatal to the connection. Commands convert them to
+/// `Frame::Error` responses, the `Display` implementation provides the message
+/// in the same format Redis uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DbError {
+    /// The operation was applied to a key holding the wrong kind of value.
+    WrongType,
+
+    /// `XADD` was given the ID `0-0`, which is never a valid entry ID.
+    StreamIdZero,
+
+    /// `XADD` was given an ID that is not greater than the stream's last ID.
+    StreamIdTooSmall,
+
+    /// `XGROUP` was applied to a key that does not exist.
+    NoStream,
+
+    /// The stream or the consumer group does not exist.
+    NoGroup,
+
+    /// The consumer group being created already exists.
+    BusyGroup,
+
+    /// A HyperLogLog command was applied to a string that is not a
+    /// HyperLogLog.
+    InvalidHll,
+
+    /// A HyperLogLog command was applied to a HyperLogLog whose registers
+    /// were corrupted, such as with `SETRANGE`.
+    CorruptedHll,
+
+    /// The value is not a string representing a 64 bit signed integer.
+    NotInteger,
+
+    /// Incrementing or decrementing the integer would overflow.
+    IncrOverflow,
+
+    /// The string value is not a valid float.
+    NotFloat,
+
+    /// The hash field value is not a string representing a 64 bit signed
+    /// integer.
+    HashNotInteger,
+
+    /// The hash field value is not a valid float.
+    HashNotFloat,
+
+    /// Incrementing the float would result in `NaN` or an infinity.
+    NanOrInfinity,
+
+    /// The key the command applies to does not exist.
+    NoSuchKey,
+
+    /// The list index is out of range.
+    IndexOutOfRange,
+
+    /// There is no database at the requested index.
+    DbIndexOutOfRange,
+
+    /// The source and destination of a command are the same key.
+    SameObject,
+
+    /// The member `GEOSEARCH` searches from is not in the geospatial index.
+    NoGeoMember,
+
+    /// Incrementing a score resulted in `NaN`.
+    ScoreNan,
+
+    /// `SORT` was applied to an element that is not a valid number without the
+    /// `ALPHA` option.
+    SortNotNumber,
+
+    /// A client sent a command modifying the databases to a replica.
+    ReadOnly,
+
+    /// A command which may use more memory was sent while the data set uses
+    /// more than `maxmemory`, and no key could be evicted.
+    OutOfMemory,
+
+    /// `RESTORE` was applied to a key that exists without the `REPLACE`
+    /// option.
+    BusyKey,
+
+    /// `RESTORE` was given a value not serialized by `DUMP`, or corrupted.
+    InvalidDump,
+
+    /// `RESTORE` was given a value with a valid checksum, which could not be
+    /// deserialized.
+    BadDataFormat,
+
+    /// The strings compared by `LCS` are too long for the table of the
+    /// longest common subsequence to fit in memory.
+    LcsTooLong,
+}
+
+impl Db {
+    /// Create a new `Db` instance with `count` empty databases, the first one
+    /// being selected. Allocates shared state and spawns a background task to
+    /// manage key expiration.
+    pub(crate) fn new(count: usize) -> Db {
+        let states = (0..count)
+            .map(|_| State {
+                entries: HashMap::new(),
+                scan_index: BTreeSet::new(),
+                blocking: HashMap::new(),
+                queued: HashMap::new(),
+                expirations: Expirations::new(),
+                next_id: 0,
+                watched: HashMap::new(),
+                changes: 0,
+                logged_expirations: vec![],
+                tracked: HashMap::new(),
+                invalidated: vec![],
+                used_memory: 0,
+                resized: HashSet::new(),
+                logged_deletions: vec![],
+            })
+            .collect();
+
+        let shared = Arc::new(Shared {
+            databases: Mutex::new(Databases {
+                states,
+                pub_sub: HashMap::new(),
+                pattern_pub_sub: HashMap::new(),
+                shard_pub_sub: HashMap::new(),
+                shutdown: false,
+                transaction: None,
+                next_transaction: 0,
+                scripts: HashMap::new(),
+                libraries: BTreeMap::new(),
+                saved_changes: 0,
+                snapshot_path: None,
+                saving: false,
+                last_save: SystemTime::now(),
+                library_changes: 0,
+                aof: None,
+                replicas: Replicas::new(),
+                primary: None,
+                primary_link: Link::Connect,
+                primary_offset: None,
+                cluster: None,
+                peak_memory: 0,
+                shutdown_nosave: false,
+                active_expire: true,
+                config: Config::new(count),
+                notifications: vec![],
+                acl: Acl::new(),
+                user_limiters: HashMap::new(),
+                clients: HashMap::new(),
+                next_client: 1,
+                tracking: HashMap::new(),
+                invalidations: HashMap::new(),
+                pushed_invalidations: HashMap::new(),
+                renames: Arc::new(Renames::default()),
+            }),
+            transaction_done: Condvar::new(),
+            background_task: Notify::new(),
+            primary_changed: Notify::new(),
+            clients_changed: Notify::new(),
+            shutdown_requested: Notify::new(),
+            stats: Stats::new(),
+            monitors: Monitors::new(),
+            slowlog: Mutex::new(Slowlog::new()),
+            latency: Mutex::new(LatencyMonitor::new()),
+            pause: watch::channel(None),
+            script: watch::channel(None),
+        });
+
+        // Start the background task.
+        tokio::spawn(purge_expired_tasks(shared.clone()));
+
+        Db {
+            shared,
+            index: 0,
+            transaction: None,
+            client: 0,
+            command: Mutex::new(None),
+        }
+    }
+
+    /// Returns the index of the selected database.
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the number of databases.
+    pub(crate) fn count(&self) -> usize {
+        self.shared.lock(self.transaction).states.len()
+    }
+
+    /// Returns a handle to the same shared state, with the database at `index`
+    /// selected.
+    ///
+    /// Returns `Err` if there is no database at `index`.
+    pub(crate) fn select(&self, index: usize) -> Result<Db, DbError> {
+        if index >= self.count() {
+            return Err(DbError::DbIndexOutOfRange);
+        }
+
+        let mut databases = self.shared.lock(self.transaction);
+        if let Some(client) = databases.clients.get_mut(&self.client) {
+            client.select(index);
+        }
+        drop(databases);
+
+        Ok(Db {
+            shared: self.shared.clone(),
+            index,
+            transaction: self.transaction,
+            client: self.client,
+            command: Mutex::new(None),
+        })
+    }
+
+    /// Start a transaction, waiting for the one being executed by another
+    /// handle to complete, if any.
+    ///
+    /// Commands applied with the returned handle are atomic: no other handle
+    /// accesses the databases until the transaction completes. The other
+    /// handles block their thread meanwhile, so the transaction must never
+    /// yield: its commands are applied with `Command::apply_now`. As scripts
+    /// may run for long, the handlers first wait for them with `wait_script`.
+    pub(crate) fn transaction(&self) -> Transaction {
+        let mut databases = self.shared.lock(None);
+
+        let id = databases.next_transaction;
+        databases.next_transaction += 1;
+        databases.transaction = Some(id);
+
+        Transaction {
+            db: Db {
+                shared: self.shared.clone(),
+                index: self.index,
+                transaction: Some(id),
+                client: self.client,
+                command: Mutex::new(None),
+            },
+        }
+    }
+
+    /// Returns a handle for a new client connection from `addr`, identified
+    /// by a new client ID, to the same shared state.
+    pub(crate) fn connect(&self, addr: String) -> Db {
+        let mut databases = self.shared.lock_now();
+
+        let client = databases.next_client;
+        databases.next_client += 1;
+        self.shared.stats.count_connection();
+
+        let user = databases.acl.initial_user();
+        databases
+            .clients
+            .insert(client, ClientState::new(addr, user));
+
+        Db {
+            shared: self.shared.clone(),
+            index: self.index,
+            transaction: self.transaction,
+            client,
+            command: Mutex::new(None),
+        }
+    }
+
+    /// Forget the client of the handle, once its connection is closed.
+    pub(crate) fn disconnect(&self) {
+        let mut databases = self.shared.lock_now();
+        databases.clients.remove(&self.client);
+        databases.tracking.remove(&self.client);
+        databases.pushed_invalidations.remove(&self.client);
+        drop(databases);
+
+        self.shared.clients_changed.notify_one();
+    }
+
+    /// Returns `true` once a new client may connect, fewer than `maxclients`
+    /// clients being connected.
+    ///
+    /// If `maxclients` clients are connected, waits for one of them to
+    /// disconnect when the connections over it are queued. Returns `false`
+    /// when they are rejected, counting the connection as rejected.
+    pub(crate) async fn admit_client(&self) -> bool {
+        loop {
+            let policy = {
+                let databases = self.shared.lock_now();
+                if (databases.clients.len() as u64) < databases.config.maxclients {
+                    return true;
+                }
+                databases.config.maxclients_policy
+            };
+
+            if policy == MaxClientsPolicy::Reject {
+                self.shared.stats.count_rejected_connection();
+                return false;
+            }
+            self.shared.clients_changed.notified().await;
+        }
+    }
+
+    /// Returns `true` if only the clients connected from the loopback
+    /// interface are accepted by the listeners bound to other interfaces:
+    /// the `protected-mode` parameter is set, and clients may authenticate
+    /// as the default user without a password.
+    pub(crate) fn is_protected(&self) -> bool {
+        let databases = self.shared.lock_now();
+        databases.config.protected_mode && databases.acl.initial_user().is_some()
+    }
+
+    /// Modify the parameters with `update`, when the server starts.
+    pub(crate) fn update_config(&self, update: impl FnOnce(&mut Config)) {
+        update(&mut self.shared.lock(self.transaction).config);
+    }
+
+    /// Return the client of the handle to the state of a new client, as by
+    /// `RESET`: it has no name, tracks no keys, and is authenticated as the
+    /// user new clients are authenticated as, if any.
+    pub(crate) fn reset_client(&self) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.tracking.remove(&self.client);
+        databases.pushed_invalidations.remove(&self.client);
+
+        let user = databases.acl.initial_user();
+        if let Some(client) = databases.clients.get_mut(&self.client) {
+            client.name = None;
+            client.user = user;
+        }
+    }
+
+    /// Returns the ID of the client the handle belongs to.
+    pub(crate) fn client_id(&self) -> u64 {
+        self.client
+    }
+
+    /// Returns a notification received once the client is killed with
+    /// `CLIENT KILL`, at which point its connection is to be closed.
+    pub(crate) fn client_killed(&self) -> Arc<Notify> {
+        self.shared.lock_now().clients[&self.client].killed.clone()
+    }
+
+    /// Returns the connected clients, one per line, as listed by `CLIENT
+    /// LIST`.
+    pub(crate) fn client_list(&self) -> String {
+        let databases = self.shared.lock(self.transaction);
+
+        let mut ids: Vec<u64> = databases.clients.keys().copied().collect();
+        ids.sort_unstable();
+        ids.iter()
+            .map(|id| databases.clients[id].describe(*id))
+            .collect()
+    }
+
+    /// Set the name of the client, or clear it if `name` is empty.
+    ///
+    /// Returns `Err` if `name` holds spaces or special characters.
+    pub(crate) fn client_setname(&self, name: &str) -> Result<(), String> {
+        check_client_name(name)?;
+
+        let mut databases = self.shared.lock(self.transaction);
+        if let Some(client) = databases.clients.get_mut(&self.client) {
+            client.name = Some(name.to_string()).filter(|name| !name.is_empty());
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the client, if set.
+    pub(crate) fn client_getname(&self) -> Option<String> {
+        self.shared
+            .lock(self.transaction)
+            .clients
+            .get(&self.client)
+            .and_then(|client| client.name.clone())
+    }
+
+    /// Kill the clients matching both the identifier `id` and the address
+    /// `addr`, when given, their connection being closed. The client of the
+    /// handle is spared if `skipme` is set.
+    ///
+    /// Returns the number of clients killed.
+    pub(crate) fn client_kill(&self, id: Option<u64>, addr: Option<&str>, skipme: bool) -> usize {
+        let databases = self.shared.lock(self.transaction);
+
+        databases
+            .clients
+            .iter()
+            .filter(|(client_id, client)| {
+                id.is_none_or(|id| id == **client_id)
+                    && addr.is_none_or(|addr| addr == client.addr)
+                    && !(skipme && **client_id == self.client)
+            })
+            .map(|(_, client)| client.killed.notify_one())
+            .count()
+    }
+
+    /// Hold the commands of the clients for `duration`, only the ones which
+    /// may modify the data set if `writes_only` is set. A pause replaces the
+    /// previous one.
+    pub(crate) fn client_pause(&self, duration: Duration, writes_only: bool) {
+        let pause = Pause {
+            until: Instant::now() + duration,
+            writes_only,
+        };
+        let _ = self.shared.pause.0.send(Some(pause));
+    }
+
+    /// End the pause of the clients, if any.
+    pub(crate) fn client_unpause(&self) {
+        let _ = self.shared.pause.0.send(None);
+    }
+
+    /// Wait for the clients to be unpaused, if paused, before applying a
+    /// command. `write` is set if the command may modify the data set.
+    pub(crate) async fn wait_unpaused(&self, write: bool) {
+        let mut pause = self.shared.pause.1.clone();
+
+        loop {
+            let until = match *pause.borrow() {
+                Some(pause) if write || !pause.writes_only => pause.until,
+                _ => return,
+            };
+            if until <= Instant::now() {
+                return;
+            }
+
+            // The pause may be ended or replaced before it expires.
+            tokio::select! {
+                _ = time::sleep_until(until) => {}
+                _ = pause.changed() => {}
+            }
+        }
+    }
+
+    /// Start running a script on behalf of the client, a function called by
+    /// `FCALL` if `function` is set. The other clients wait for the script to
+    /// complete, see `wait_script`, until the returned `ScriptRun` is dropped.
+    pub(crate) fn start_script(&self, function: bool) -> ScriptRun {
+        let script = RunningScript {
+            client: self.client,
+            started: Instant::now(),
+            function,
+            state: Arc::new(AtomicU8::new(SCRIPT_RUNNING)),
+        };
+        let _ = self.shared.script.0.send(Some(script.clone()));
+
+        ScriptRun {
+            shared: self.shared.clone(),
+            script,
+        }
+    }
+
+    /// Wait for the script run by another client to complete, if any, before
+    /// applying a command.
+    ///
+    /// Returns `Err` with the `BUSY` error to reply once the script ran for
+    /// longer than the `lua-time-limit` parameter, `0` waiting however long
+    /// it runs.
+    pub(crate) async fn wait_script(&self) -> Result<(), String> {
+        let mut script = self.shared.script.1.clone();
+
+        loop {
+            let (started, function) = match &*script.borrow() {
+                Some(script) if script.client != self.client => (script.started, script.function),
+                _ => return Ok(()),
+            };
+
+            let limit = self.config().lua_time_limit;
+            let deadline = Some(limit)
+                .filter(|limit| *limit > 0)
+                .and_then(|limit| started.checked_add(Duration::from_millis(limit)));
+
+            // The script may complete, and another one start, before the
+            // deadline.
+            match deadline {
+                Some(deadline) if deadline <= Instant::now() => return Err(busy_error(function)),
+                Some(deadline) => tokio::select! {
+                    _ = time::sleep_until(deadline) => {}
+                    _ = script.changed() => {}
+                },
+                None => {
+                    let _ = script.changed().await;
+                }
+            }
+        }
+    }
+
+    /// Kill the script being run, a function called by `FCALL` if `function`
+    /// is set, as `SCRIPT KILL` and `FUNCTION KILL` do.
+    ///
+    /// Returns `Err` with the error to reply if there is no such script, or
+    /// if it already executed write commands.
+    pub(crate) fn kill_script(&self, function: bool) -> Result<(), String> {
+        let script = self.shared.script.1.borrow();
+        let script = match &*script {
+            Some(script) => script,
+            None => return Err("NOTBUSY No scripts in execution right now.".to_string()),
+        };
+        if script.function != function {
+            return Err(busy_error(script.function));
+        }
+
+        match script.state.compare_exchange(
+            SCRIPT_RUNNING,
+            SCRIPT_KILLED,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        ) {
+            Ok(_) | Err(SCRIPT_KILLED) => Ok(()),
+            Err(_) => Err(
+                "UNKILLABLE Sorry the script already executed write commands \
+                           against the dataset. You can either wait the script termination \
+                           or kill the server in a hard way using the SHUTDOWN NOSAVE command."
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Kill the script being run, if any, even if it executed write commands,
+    /// as `SHUTDOWN NOSAVE` does.
+    pub(crate) fn abort_script(&self) {
+        if let Some(script) = &*self.shared.script.1.borrow() {
+            script.state.store(SCRIPT_KILLED, Ordering::SeqCst);
+        }
+    }
+
+    /// Enable or disable the tracking of the keys read by the client.
+    ///
+    /// Once a tracked key is modified, it is sent to the client `redirect`,
+    /// if it is subscribed to `__redis__:invalidate`. Without `redirect`, the
+    /// key is pushed on the connection of the client itself, which must speak
+    /// RESP3 as set by `resp3`, see `tracking_invalidations`. Returns `Err` if
+    /// there is no client `redirect`.
+    pub(crate) fn client_tracking(
+        &self,
+        enabled: bool,
+        redirect: Option<u64>,
+        resp3: bool,
+    ) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+
+        if !enabled {
+            databases.tracking.remove(&self.client);
+            databases.pushed_invalidations.remove(&self.client);
+            return Ok(());
+        }
+
+        match redirect {
+            Some(redirect) if !databases.clients.contains_key(&redirect) => {
+                return Err("ERR The client ID you want redirect to does not exist".to_string());
+            }
+            Some(_) => {
+                databases.pushed_invalidations.remove(&self.client);
+            }
+            None if !resp3 => {
+                return Err(
+                    "ERR Tracking without REDIRECT requires RESP3, switch to it with HELLO 3"
+                        .to_string(),
+                );
+            }
+            None => {
+                databases
+                    .pushed_invalidations
+                    .entry(self.client)
+                    .or_insert_with(|| broadcast::channel(1024).0);
+            }
+        }
+
+        databases.tracking.insert(self.client, redirect);
+        Ok(())
+    }
+
+    /// Returns a receiver of the keys invalidated for the client, if it tracks
+    /// keys without redirecting their invalidation messages.
+    pub(crate) fn tracking_invalidations(&self) -> Option<broadcast::Receiver<Bytes>> {
+        self.shared
+            .lock(self.transaction)
+            .pushed_invalidations
+            .get(&self.client)
+            .map(broadcast::Sender::subscribe)
+    }
+
+    /// Returns `true` if the client tracks the keys it reads.
+    pub(crate) fn is_tracking(&self) -> bool {
+        self.shared
+            .lock(self.transaction)
+            .tracking
+            .contains_key(&self.client)
+    }
+
+    /// Record that the client read `keys` of the selected database, so that
+    /// it is notified of their next modification if it tracks them.
+    pub(crate) fn track(&self, keys: &[&str]) {
+        let mut databases = self.shared.lock(self.transaction);
+
+        if !databases.tracking.contains_key(&self.client) {
+            return;
+        }
+
+        let client = self.client;
+        let state = &mut databases.states[self.index];
+        for key in keys {
+            state
+                .tracked
+                .entry(key.to_string())
+                .or_default()
+                .insert(client);
+        }
+    }
+
+    /// Returns `true` if the handle executes a transaction.
+    pub(crate) fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Start watching `keys` of the selected database.
+    ///
+    /// The returned values must be passed to `unwatch` once the client no
+    /// longer watches the keys.
+    pub(crate) fn watch(&self, keys: &[String]) -> Vec<Watched> {
+        let mut state = self.lock();
+
+        keys.iter()
+            .map(|key| state.watch(self.index, key))
+            .collect()
+    }
+
+    /// Stop watching keys previously returned by `watch`.
+    pub(crate) fn unwatch(&self, watched: Vec<Watched>) {
+        let mut databases = self.shared.lock(self.transaction);
+
+        for Watched { index, key, .. } in watched {
+            let state = &mut databases.states[index];
+
+            if let Some(watched) = state.watched.get_mut(&key) {
+                watched.watchers -= 1;
+                if watched.watchers == 0 {
+                    state.watched.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if any of the `watched` keys was modified since the
+    /// client started watching it.
+    pub(crate) fn watched_modified(&self, watched: &[Watched]) -> bool {
+        let databases = self.shared.lock(self.transaction);
+
+        watched.iter().any(|watched| {
+            databases.states[watched.index]
+                .watched
+                .get(&watched.key)
+                .map(|key| key.version != watched.version)
+                .unwrap_or(true)
+        })
+    }
+
+    /// Returns the cached script whose SHA1 digest is `sha`.
+    pub(crate) fn script(&self, sha: &str) -> Option<Arc<Script>> {
+        self.shared.lock(self.transaction).scripts.get(sha).cloned()
+    }
+
+    /// Cache `script`, whose SHA1 digest is `sha`.
+    pub(crate) fn cache_script(&self, sha: String, script: Arc<Script>) {
+        self.shared
+            .lock(self.transaction)
+            .scripts
+            .insert(sha, script);
+    }
+
+    /// Returns, for each digest of `shas`, `true` if the script is cached.
+    pub(crate) fn scripts_exist(&self, shas: &[String]) -> Vec<bool> {
+        let databases = self.shared.lock(self.transaction);
+        shas.iter()
+            .map(|sha| databases.scripts.contains_key(&sha.to_lowercase()))
+            .collect()
+    }
+
+    /// Remove all the cached scripts.
+    pub(crate) fn flush_scripts(&self) {
+        let scripts = std::mem::take(&mut self.shared.lock(self.transaction).scripts);
+
+        // The scripts are dropped after releasing the lock.
+        drop(scripts);
+    }
+
+    /// Returns the library registering the function `name`.
+    pub(crate) fn library_of(&self, name: &str) -> Option<Arc<Library>> {
+        self.shared
+            .lock(self.transaction)
+            .libraries
+            .values()
+            .find(|library| library.function(name).is_some())
+            .cloned()
+    }
+
+    /// Returns the loaded libraries whose name matches the glob `pattern`,
+    /// sorted by name.
+    pub(crate) fn libraries(&self, pattern: Option<&str>) -> Vec<Arc<Library>> {
+        self.shared
+            .lock(self.transaction)
+            .libraries
+            .values()
+            .filter(|library| matches_pattern(pattern, library.name().as_bytes()))
+            .cloned()
+            .collect()
+    }
+
+    /// Add `libraries` to the loaded libraries.
+    ///
+    /// If `replace` is `true`, a library replaces the library of the same
+    /// name. Returns `Err` with the error reply, adding none of the libraries,
+    /// if a library of the same name is loaded and `replace` is `false`, or if
+    /// a function is already registered by another library.
+    pub(crate) fn load_libraries(
+        &self,
+        libraries: Vec<Library>,
+        replace: bool,
+    ) -> Result<(), String> {
+        let mut databases = self.lock_all();
+
+        for library in &libraries {
+            if !replace && databases.libraries.contains_key(library.name()) {
+                return Err(format!("ERR Library '{}' already exists", library.name()));
+            }
+
+            for function in library.functions() {
+                let registered = databases.libraries.values().any(|other| {
+                    other.name() != library.name() && other.function(&function.name).is_some()
+                });
+                if registered {
+                    return Err(format!("ERR Function {} already exists", function.name));
+                }
+            }
+        }
+
+        for library in libraries {
+            databases
+                .libraries
+                .insert(library.name().to_string(), Arc::new(library));
+        }
+        databases.library_changes += 1;
+        Ok(())
+    }
+
+    /// Remove the library `name`. Returns `false` if there is no such
+    /// library.
+    pub(crate) fn delete_library(&self, name: &str) -> bool {
+        let mut databases = self.lock_all();
+
+        let deleted = databases.libraries.remove(name).is_some();
+        if deleted {
+            databases.library_changes += 1;
+        }
+        deleted
+    }
+
+    /// Remove all the loaded libraries.
+    pub(crate) fn flush_libraries(&self) {
+        let mut databases = self.lock_all();
+        let libraries = std::mem::take(&mut databases.libraries);
+        databases.library_changes += 1;
+        drop(databases);
+
+        // The libraries are dropped after releasing the lock.
+        drop(libraries);
+    }
+
+    /// Request the server to shut down, without saving a last snapshot if
+    /// `nosave` is set.
+    pub(crate) fn request_shutdown(&self, nosave: bool) {
+        self.shared.lock(self.transaction).shutdown_nosave = nosave;
+        self.shared.shutdown_requested.notify_one();
+    }
+
+    /// Waits for a client to request the server to shut down.
+    pub(crate) async fn shutdown_requested(&self) {
+        self.shared.shutdown_requested.notified().await
+    }
+
+    /// Returns `true` if the server shuts down without saving a last
+    /// snapshot.
+    pub(crate) fn shutdown_nosave(&self) -> bool {
+        self.shared.lock(self.transaction).shutdown_nosave
+    }
+
+    /// Rename the commands sent by the clients as set by `renames`.
+    pub(crate) fn set_renames(&self, renames: Renames) {
+        self.shared.lock(self.transaction).renames = Arc::new(renames);
+    }
+
+    /// Returns the commands renamed with `rename-command`.
+    pub(crate) fn renames(&self) -> Arc<Renames> {
+        self.shared.lock_now().renames.clone()
+    }
+
+    /// Set the password of the default user, `None` letting clients send
+    /// commands without authenticating.
+    pub(crate) fn set_requirepass(&self, password: Option<String>) {
+        self.shared
+            .lock(self.transaction)
+            .acl
+            .set_requirepass(password.as_deref());
+    }
+
+    /// Check the credentials given to `AUTH`, authenticating the client as
+    /// `username`, the default user if `None`, once they are valid.
+    pub(crate) fn authenticate(
+        &self,
+        username: Option<&str>,
+        password: &str,
+    ) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+
+        let name = username.unwrap_or(DEFAULT_USER);
+        let user = match databases.acl.user(name) {
+            Some(user) => user,
+            None => return Err(wrong_pass()),
+        };
+
+        if username.is_none() && user.is_nopass() {
+            return Err(
+                "ERR AUTH <password> called without any password configured for \
+                        the default user. Are you sure your configuration is correct?"
+                    .to_string(),
+            );
+        }
+        if !user.accepts(password) {
+            return Err(wrong_pass());
+        }
+
+        if let Some(client) = databases.clients.get_mut(&self.client) {
+            client.user = Some(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns `Err` with the error to reply if the client may not run `cmd`:
+    /// `NOAUTH` if it is not authenticated, `NOPERM` if its user is not
+    /// allowed to run it.
+    pub(crate) fn acl_check(&self, cmd: &Command) -> Result<(), String> {
+        let databases = self.shared.lock_now();
+
+        // Clients authenticated as a user which was deleted since then must
+        // authenticate again.
+        let name = databases
+            .clients
+            .get(&self.client)
+            .and_then(|client| client.user.clone());
+        match name.as_ref().and_then(|name| databases.acl.user(name)) {
+            Some(user) => user.check(name.as_ref().unwrap(), cmd),
+            None => Err("NOAUTH Authentication required.".to_string()),
+        }
+    }
+
+    /// Returns `Err` with the `THROTTLED` error to reply if the client, or the
+    /// clients of its user together, exceeded their rate limits. Otherwise,
+    /// a command of `len` bytes is counted against the limits.
+    pub(crate) fn throttle(&self, len: usize) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+        let databases = &mut *databases;
+        let now = Instant::now();
+
+        let client = match databases.clients.get_mut(&self.client) {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+        let limits = RateLimits {
+            commands: databases.config.ratelimit_commands,
+            bytes: databases.config.ratelimit_bytes,
+        };
+        let mut res = client
+            .limiter
+            .check(limits, now)
+            .map_err(|exceeded| throttled(exceeded, ""));
+
+        // The limiter of a user is only created once the user has limits.
+        let acl = &databases.acl;
+        let user = client
+            .user
+            .as_ref()
+            .and_then(|name| Some((name, acl.user(name)?.limits())))
+            .filter(|(_, limits)| *limits != RateLimits::default());
+        let user_limiters = &mut databases.user_limiters;
+        let user_limiter = user.map(|(name, limits)| {
+            let limiter = user_limiters.entry(name.clone()).or_default();
+            if let (Ok(()), Err(exceeded)) = (&res, limiter.check(limits, now)) {
+                res = Err(throttled(exceeded, &format!(" of user '{}'", name)));
+            }
+            limiter
+        });
+
+        if res.is_err() {
+            self.shared.stats.count_throttled_command();
+            return res;
+        }
+
+        client.limiter.take(len);
+        if let Some(limiter) = user_limiter {
+            limiter.take(len);
+        }
+        Ok(())
+    }
+
+    /// Apply the ACL `rules` to the user `name`, creating it if needed.
+    pub(crate) fn acl_setuser(&self, name: &str, rules: &[String]) -> Result<(), String> {
+        self.shared.lock(self.transaction).acl.set_user(name, rules)
+    }
+
+    /// Returns the user `name`, if it exists.
+    pub(crate) fn acl_getuser(&self, name: &str) -> Option<User> {
+        self.shared.lock(self.transaction).acl.user(name).cloned()
+    }
+
+    /// Delete the users `names`, returning the number of them that existed.
+    pub(crate) fn acl_deluser(&self, names: &[String]) -> Result<usize, String> {
+        let mut databases = self.shared.lock(self.transaction);
+        let deleted = databases.acl.delete_users(names)?;
+        for name in names {
+            databases.user_limiters.remove(name);
+        }
+        Ok(deleted)
+    }
+
+    /// Returns each user as the rules recreating it, such as
+    /// `user default on nopass ~* &* +@all`.
+    pub(crate) fn acl_list(&self) -> Vec<String> {
+        let databases = self.shared.lock(self.transaction);
+        databases
+            .acl
+            .usernames()
+            .map(|name| {
+                let rules = databases.acl.user(name).unwrap().rules();
+                format!("user {} {}", name, rules)
+            })
+            .collect()
+    }
+
+    /// Returns the name of the user the client is authenticated as, if any.
+    pub(crate) fn acl_whoami(&self) -> Option<String> {
+        self.shared
+            .lock(self.transaction)
+            .clients
+            .get(&self.client)
+            .and_then(|client| client.user.clone())
+    }
+
+    /// Enable cluster mode, this node being reachable at `host` and `port`.
+    pub(crate) fn enable_cluster(&self, host: String, port: u16) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.cluster = Some(Cluster::new(host, port));
+    }
+
+    /// Returns `true` if cluster mode is enabled.
+    pub(crate) fn cluster_enabled(&self) -> bool {
+        self.shared.lock(self.transaction).cluster.is_some()
+    }
+
+    /// Returns this node, in cluster mode.
+    pub(crate) fn cluster_myself(&self) -> Result<Node, String> {
+        let databases = self.shared.lock(self.transaction);
+        Ok(cluster(&databases.cluster)?.myself().clone())
+    }
+
+    /// Returns the nodes of the cluster, this node first, in cluster mode.
+    pub(crate) fn cluster_nodes(&self) -> Result<Vec<Node>, String> {
+        let databases = self.shared.lock(self.transaction);
+        Ok(cluster(&databases.cluster)?.nodes().to_vec())
+    }
+
+    /// Returns the ranges of consecutive slots served by the same node, along
+    /// with the node, in cluster mode.
+    pub(crate) fn cluster_slots(&self) -> Result<Vec<(u16, u16, Node)>, String> {
+        let databases = self.shared.lock(self.transaction);
+        let ranges = cluster(&databases.cluster)?
+            .ranges()
+            .into_iter()
+            .map(|(start, end, node)| (start, end, node.clone()))
+            .collect();
+        Ok(ranges)
+    }
+
+    /// Assign `slots` to this node, in cluster mode.
+    pub(crate) fn add_cluster_slots(&self, slots: &[u16]) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+        cluster_mut(&mut databases.cluster)?.add_slots(slots)
+    }
+
+    /// Unassign `slots`, in cluster mode.
+    pub(crate) fn del_cluster_slots(&self, slots: &[u16]) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+        cluster_mut(&mut databases.cluster)?.del_slots(slots)
+    }
+
+    /// Add `node`, serving `slots`, to the cluster, in cluster mode.
+    pub(crate) fn meet_cluster_node(&self, node: Node, slots: &[u16]) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+        cluster_mut(&mut databases.cluster)?.meet(node, slots);
+        Ok(())
+    }
+
+    /// Set `slot` as given by `state`, in cluster mode.
+    pub(crate) fn set_cluster_slot(&self, slot: u16, state: SlotState) -> Result<(), String> {
+        let mut databases = self.shared.lock(self.transaction);
+        cluster_mut(&mut databases.cluster)?.set_slot(slot, state)
+    }
+
+    /// Check that this node serves `keys`, of the selected database, in
+    /// cluster mode. `asking` is `true` if the client sent `ASKING` before the
+    /// command.
+    ///
+    /// The keys must all map to the same slot. Returns `Err` with the
+    /// redirection to send the client if this node does not serve it.
+    pub(crate) fn route(&self, keys: &[&str], asking: bool) -> Result<(), Redirect> {
+        let databases = self.shared.lock(self.transaction);
+        let (cluster, key) = match (&databases.cluster, keys.first()) {
+            (Some(cluster), Some(key)) => (cluster, key),
+            _ => return Ok(()),
+        };
+
+        let slot = key_slot(key);
+        if keys.iter().any(|key| key_slot(key) != slot) {
+            return Err(Redirect::CrossSlot);
+        }
+
+        let state = &databases.states[self.index];
+        cluster.route(slot, asking, || {
+            keys.iter().any(|key| !state.entries.contains_key(*key))
+        })
+    }
+
+    /// Returns the configuration parameters whose name matches the glob
+    /// `pattern`, along with their value.
+    pub(crate) fn config_get(&self, pattern: &str) -> Vec<(String, String)> {
+        self.shared.lock(self.transaction).config.get(pattern)
+    }
+
+    /// Set the configuration parameter `name` to `value`.
+    ///
+    /// Returns `Err` if there is no such parameter or if `value` is invalid.
+    pub(crate) fn config_set(&self, name: &str, value: &str) -> Result<(), String> {
+        self.shared.lock(self.transaction).config.set(name, value)?;
+
+        // Raising `maxclients` lets queued connections in.
+        self.shared.clients_changed.notify_one();
+        Ok(())
+    }
+
+    /// Returns a receiver of the commands received by the server from now on,
+    /// formatted as streamed by `MONITOR`.
+    pub(crate) fn monitor(&self) -> broadcast::Receiver<String> {
+        self.shared.monitors.subscribe()
+    }
+
+    /// Stream the command `frame`, received from the client at `addr`, to the
+    /// connections running `MONITOR`, if any. Passwords are redacted.
+    pub(crate) fn feed_monitors(&self, addr: &str, frame: &Frame) {
+        self.shared.monitors.feed(self.index, addr, frame);
+    }
+
+    /// Record the command `frame`, sent by the client at `addr`, in the slow
+    /// log if its execution took `duration` or longer than the
+    /// `slowlog-log-slower-than` parameter. Passwords are redacted.
+    pub(crate) fn log_if_slow(&self, frame: &Frame, duration: Duration, addr: &str) {
+        let config = self.config();
+        // The slow log is disabled by a negative threshold.
+        if config.slowlog_log_slower_than < 0 {
+            return;
+        }
+
+        if duration >= Duration::from_micros(config.slowlog_log_slower_than as u64) {
+            self.shared.slowlog.lock().unwrap().push(
+                &redacted(frame),
+                duration,
+                addr,
+                config.slowlog_max_len as usize,
+            );
+        }
+    }
+
+    /// Record a latency spike of `event` if it took `duration` or longer than
+    /// the `latency-monitor-threshold` parameter.
+    pub(crate) fn add_latency_sample(&self, event: &'static str, duration: Duration) {
+        let threshold = self.config().latency_monitor_threshold;
+        self.shared.add_latency_sample(threshold, event, duration);
+    }
+
+    /// Returns the latest latency spike of each event.
+    pub(crate) fn latency_latest(&self) -> Vec<LatestSpike> {
+        self.shared.latency.lock().unwrap().latest()
+    }
+
+    /// Returns the latency spikes of `event`, as the Unix time they were
+    /// recorded at and their latency in milliseconds, the oldest first.
+    pub(crate) fn latency_history(&self, event: &str) -> Vec<(u64, u64)> {
+        self.shared.latency.lock().unwrap().history(event)
+    }
+
+    /// Drop the latency spikes of `events`, or of every event if empty,
+    /// returning the number of events whose spikes were dropped.
+    pub(crate) fn latency_reset(&self, events: &[String]) -> usize {
+        self.shared.latency.lock().unwrap().reset(events)
+    }
+
+    /// Returns a report of the latency spikes of each event.
+    pub(crate) fn latency_doctor(&self) -> String {
+        let threshold = self.config().latency_monitor_threshold;
+        self.shared.latency.lock().unwrap().doctor(threshold)
+    }
+
+    /// Returns the `count` newest entries of the slow log, the newest first.
+    pub(crate) fn slowlog_get(&self, count: usize) -> Vec<SlowlogEntry> {
+        self.shared.slowlog.lock().unwrap().get(count)
+    }
+
+    /// Returns the number of entries of the slow log.
+    pub(crate) fn slowlog_len(&self) -> usize {
+        self.shared.slowlog.lock().unwrap().len()
+    }
+
+    /// Drop the entries of the slow log.
+    pub(crate) fn slowlog_reset(&self) {
+        self.shared.slowlog.lock().unwrap().reset();
+    }
+
+    /// Count the command `name` received from the client, as reported by
+    /// `INFO`, and record it as its last command, as listed by `CLIENT LIST`.
+    pub(crate) fn count_command(&self, name: &str) {
+        self.shared.stats.count_command();
+
+        let mut databases = self.shared.lock(self.transaction);
+        if let Some(client) = databases.clients.get_mut(&self.client) {
+            client.record(name);
+        }
+    }
+
+    /// Count the `keys` of the selected database read by a command, as hits
+    /// if they exist and as misses otherwise.
+    pub(crate) fn count_lookups(&self, keys: &[&str]) {
+        if keys.is_empty() {
+            return;
+        }
+
+        let state = self.lock();
+        let now = Instant::now();
+
+        let hits = keys
+            .iter()
+            .filter(|key| {
+                state
+                    .entries
+                    .get(**key)
+                    .is_some_and(|entry| entry.expires_at.is_none_or(|when| when > now))
+            })
+            .count() as u64;
+        self.shared
+            .stats
+            .count_lookups(hits, keys.len() as u64 - hits);
+    }
+
+    /// Hold the lock of the databases for `duration`, no other command being
+    /// applied meanwhile.
+    pub(crate) fn debug_sleep(&self, duration: Duration) {
+        let _databases = self.shared.lock(self.transaction);
+        std::thread::sleep(duration);
+    }
+
+    /// Returns the metadata of `key`, as reported by `DEBUG OBJECT`, or `None`
+    /// if it does not exist.
+    pub(crate) fn debug_object(&self, key: &str) -> Option<String> {
+        let state = self.lock();
+        let entry = state.entries.get(key)?;
+
+        let ttl = match entry.expires_at {
+            Some(when) => when.saturating_duration_since(Instant::now()).as_millis() as i64,
+            None => -1,
+        };
+        Some(format!(
+            "Value type:{} encoding:{} serializedlength:{} ttl:{}",
+            entry.data.type_name(),
+            entry.data.encoding(),
+            entry.data.memory_usage(),
+            ttl
+        ))
+    }
+
+    /// Returns the approximate number of bytes used by `key` and its value,
+    /// or `None` if it does not exist.
+    ///
+    /// The size of the elements of a collection is extrapolated from the
+    /// first `samples` of them, all of them being measured if `samples` is 0.
+    pub(crate) fn memory_usage(&self, key: &str, samples: usize) -> Option<usize> {
+        let state = self.lock();
+        let entry = state.entries.get(key)?;
+        Some(key.len() + ENTRY_OVERHEAD + entry.data.allocated_size(samples))
+    }
+
+    /// Returns the breakdown of the memory used by the databases, as reported
+    /// by `MEMORY STATS`.
+    pub(crate) fn memory_stats(&self) -> MemoryStats {
+        let mut databases = self.shared.lock(self.transaction);
+        let total = databases.used_memory();
+
+        let overheads: Vec<_> = databases
+            .states
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| !state.entries.is_empty())
+            .map(|(index, state)| {
+                (
+                    index,
+                    state.entries.len() * ENTRY_OVERHEAD,
+                    state.expirations.len() * EXPIRATION_OVERHEAD,
+                )
+            })
+            .collect();
+        let overhead = overheads
+            .iter()
+            .map(|(_, main, expires)| main + expires)
+            .sum();
+        let keys = databases
+            .states
+            .iter()
+            .map(|state| state.entries.len())
+            .sum();
+
+        MemoryStats {
+            peak: databases.peak_memory,
+            total,
+            overhead,
+            keys,
+            databases: overheads,
+        }
+    }
+
+    /// Evict keys according to the `maxmemory-policy` parameter until the
+    /// data set uses no more than `maxmemory` bytes, before applying a command
+    /// which may use more memory.
+    ///
+    /// Returns `Err` if the data set still uses more than `maxmemory` bytes,
+    /// in which case the command must be rejected. Replicas never evict keys,
+    /// the primary sending them the keys it evicts.
+    pub(crate) fn evict(&self) -> Result<(), DbError> {
+        let mut databases = self.lock_all();
+        let maxmemory = databases.config.maxmemory as usize;
+        if maxmemory == 0 || databases.primary.is_some() {
+            return Ok(());
+        }
+
+        let policy = databases.config.maxmemory_policy;
+        let samples = databases.config.maxmemory_samples;
+        while databases.used_memory() > maxmemory {
+            if policy == EvictionPolicy::NoEviction {
+                return Err(DbError::OutOfMemory);
+            }
+
+            let (index, key) = databases
+                .eviction_candidate(policy, samples)
+                .ok_or(DbError::OutOfMemory)?;
+
+            let state = &mut databases.states[index];
+            state.remove(&key);
+            state.logged_deletions.push(key.clone());
+            databases.notify(index, b'e', "evicted", &key);
+            self.shared.stats.count_eviction();
+        }
+
+        Ok(())
+    }
+
+    /// Enable or disable the purge of the expired keys by the background
+    /// task.
+    pub(crate) fn set_active_expire(&self, enabled: bool) {
+        self.shared.lock(self.transaction).active_expire = enabled;
+        self.shared.background_task.notify_one();
+    }
+
+    /// Returns the state of the server, as reported by `INFO`.
+    pub(crate) fn info(&self) -> Info {
+        let mut databases = self.shared.lock(self.transaction);
+        let now = Instant::now();
+        let used_memory = databases.used_memory();
+
+        let keyspace = databases
+            .states
+            .iter_mut()
+            .enumerate()
+            .map(|(index, state)| {
+                let expired = state.expirations.count_expired(now);
+                let keys = state.entries.len() - expired;
+                (index, keys, state.expirations.len() - expired)
+            })
+            .filter(|(_, keys, _)| *keys > 0)
+            .collect();
+
+        let mut info = Info {
+            uptime: 0,
+            cluster_enabled: databases.cluster.is_some(),
+            connected_clients: databases.clients.len(),
+            maxclients: databases.config.maxclients,
+            tracking_clients: databases.tracking.len(),
+            used_memory: used_memory as u64,
+            used_memory_peak: databases.peak_memory as u64,
+            maxmemory: databases.config.maxmemory,
+            maxmemory_policy: databases.config.maxmemory_policy,
+            connections: 0,
+            rejected_connections: 0,
+            commands: 0,
+            throttled_commands: 0,
+            hits: 0,
+            misses: 0,
+            evicted: 0,
+            pubsub_channels: databases.pub_sub.len(),
+            pubsub_patterns: databases.pattern_pub_sub.len(),
+            primary: databases.primary.clone(),
+            connected_replicas: databases.replicas.len(),
+            replication_id: databases.replicas.replication_id().to_string(),
+            replication_offset: databases.replicas.offset(),
+            keyspace,
+        };
+        self.shared.stats.fill(&mut info);
+        info
+    }
+
+    /// Returns the current value of the configuration parameters.
+    pub(crate) fn config(&self) -> Config {
+        self.shared.lock_now().config
+    }
+
+    /// Set the frame of the command applied through the handle, or `None`
+    /// once it completes.
+    ///
+    /// The frame is logged, that is appended to the AOF and streamed to the
+    /// replicas, when the command first modifies the databases, while still
+    /// holding the lock, so that the commands are logged in the order they are
+    /// applied. Commands which do not modify the databases, such as a `SET`
+    /// with the `NX` option on an existing key, are not logged.
+    pub(crate) fn set_logged_command(&self, frame: Option<Frame>) {
+        *self.command.lock().unwrap() = frame;
+    }
+
+    /// Acquire the lock and return a guard giving access to the selected
+    /// database.
+    fn lock(&self) -> StateGuard<'_> {
+        StateGuard {
+            databases: self.lock_all(),
+            index: self.index,
+        }
+    }
+
+    /// Acquire the lock and return a guard giving access to all the
+    /// databases, on behalf of a command which may modify them.
+    fn lock_all(&self) -> DatabasesGuard<'_> {
+        let databases = self.shared.lock(self.transaction);
+        let changes = databases.changes();
+
+        DatabasesGuard {
+            databases,
+            db: self,
+            changes,
+        }
+    }
+
+    /// Remove the `keys` which expired, before a command accesses them.
+    ///
+    /// Expired keys are removed lazily as they are accessed, so that commands
+    /// never see them, even when the background task did not purge them yet.
+    pub(crate) fn expire_keys(&self, keys: &[&str]) {
+        if keys.is_empty() {
+            return;
+        }
+
+        let mut databases = self.shared.lock(self.transaction);
+        let now = Instant::now();
+
+        for key in keys {
+            let state = &mut databases.states[self.index];
+            match state.entries.get(*key) {
+                Some(entry) if matches!(entry.expires_at, Some(when) if when <= now) => {}
+                _ => continue,
+            }
+
+            state.remove(key);
+            databases.notify(self.index, b'x', "expired", key);
+        }
+
+        databases.publish_notifications();
+        databases.send_invalidations();
+    }
+
+    /// Mark `keys` as accessed, without reading their values.
+    ///
+    /// Returns the number of `keys` that exist. A key given multiple times is
+    /// counted multiple times.
+    pub(crate) fn touch(&self, keys: &[String]) -> usize {
+        let mut state = self.lock();
+        let now = Instant::now();
+
+        let mut touched = 0;
+        for key in keys {
+            if let Some(entry) = state.entries.get_mut(key) {
+                entry.access(now);
+                touched += 1;
+            }
+        }
+
+        touched
+    }
+
+    /// Remove the value associated with a key along with its expiration.
+    ///
+    /// Returns `true` if a value was removed.
+    pub(crate) fn del(&self, key: &str) -> bool {
+        let mut state = self.lock();
+
+        let removed = state.remove(key).is_some();
+        if removed {
+            state.notify(b'g', "del", key);
+        }
+        removed
+    }
+
+    /// Remove `keys` from the database, dropping their values in the
+    /// background.
+    ///
+    /// The keys are removed immediately, while holding the lock. Freeing a
+    /// large value may take a while though, so large values are dropped on a
+    /// blocking thread instead, once the lock is released.
+    ///
+    /// Returns the number of keys that were removed.
+    pub(crate) fn unlink(&self, keys: &[String]) -> usize {
+        let mut state = self.lock();
+
+        let mut removed = 0;
+        let mut large = Vec::new();
+        for key in keys {
+            if let Some(entry) = state.remove(key) {
+                state.notify(b'g', "del", key);
+                removed += 1;
+                if entry.data.is_large() {
+                    large.push(entry);
+                }
+                // Small values are dropped right away, this is cheaper than
+                // handing them to another thread.
+            }
+        }
+
+        drop(state);
+
+        if !large.is_empty() {
+            tokio::task::spawn_blocking(move || drop(large));
+        }
+
+        removed
+    }
+
+    /// Returns the number of `keys` that exist. A key given multiple times is
+    /// counted multiple times.
+    pub(crate) fn exists(&self, keys: &[String]) -> usize {
+        let state = self.lock();
+        keys.iter()
+            .filter(|key| state.entries.contains_key(*key))
+            .count()
+    }
+
+    /// Returns all the keys matching the glob `pattern`, in no particular
+    /// order.
+    pub(crate) fn keys(&self, pattern: &str) -> Vec<String> {
+        let state = self.lock();
+        state
+            .entries
+            .keys()
+            .filter(|key| glob::matches(pattern.as_bytes(), key.as_bytes()))
+            .cloned()
+            .collect()
+    }
+
+    /// Iterate over the keys, starting at `cursor`.
+    ///
+    /// About `count` keys are visited, from which the ones matching the glob
+    /// `pattern` and holding a value of type `ty` are returned, along with the
+    /// cursor to continue the iteration at. The iteration is complete when the
+    /// returned cursor is `0`.
+    ///
+    /// A key present during the whole iteration is returned at least once. It
+    /// may be returned more than once.
+    pub(crate) fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+        ty: Option<&str>,
+    ) -> (u64, Vec<String>) {
+        let state = self.lock();
+        let now = Instant::now();
+
+        let index = state
+            .scan_index
+            .range((cursor, String::new())..)
+            .map(|(hash, key)| (*hash, key));
+
+        let (cursor, keys) = scan_page(index, count, |key| {
+            let entry = &state.entries[*key];
+
+            let is_type = match ty {
+                Some(ty) => ty.eq_ignore_ascii_case(entry.data.type_name()),
+                None => true,
+            };
+
+            !matches!(entry.expires_at, Some(when) if when <= now)
+                && matches_pattern(pattern, key.as_bytes())
+                && is_type
+        });
+
+        (cursor, keys.into_iter().cloned().collect())
+    }
+
+    /// Returns the name of the type of the value stored at `key`, or `None` if
+    /// the key does not exist.
+    pub(crate) fn type_of(&self, key: &str) -> Option<&'static str> {
+        let state = self.lock();
+        state.entries.get(key).map(|entry| entry.data.type_name())
+    }
+
+    /// Rename `key` to `new_key`, along with its expiration. If `new_key`
+    /// already exists, its value is overwritten, unless `nx` is set.
+    ///
+    /// Returns `false` if the key was not renamed because `nx` is set and
+    /// `new_key` exists, and `Err` if `key` does not exist.
+    pub(crate) fn rename(&self, key: &str, new_key: &str, nx: bool) -> Result<bool, DbError> {
+        let mut state = self.lock();
+
+        if !state.entries.contains_key(key) {
+            return Err(DbError::NoSuchKey);
+        }
+
+        if nx && state.entries.contains_key(new_key) {
+            return Ok(false);
+        }
+
+        if key != new_key {
+            let entry = state.remove(key).unwrap();
+            state.remove(new_key);
+
+            // The expiration keeps the same instant, the background task does
+            // not need to be notified.
+            state.insert(new_key.to_string(), entry);
+            state.notify_writes(new_key);
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the number of keys, not counting the ones that expired but were
+    /// not purged yet.
+    pub(crate) fn dbsize(&self) -> usize {
+        let mut state = self.lock();
+        let expired = state.expirations.count_expired(Instant::now());
+
+        state.entries.len() - expired
+    }
+
+    /// Returns a key picked uniformly at random, or `None` if there are no
+    /// keys.
+    pub(crate) fn random_key(&self) -> Option<String> {
+        let state = self.lock();
+        let now = Instant::now();
+
+        if state.entries.is_empty() {
+            return None;
+        }
+
+        // Expired keys are skipped by picking again. Give up after a few
+        // attempts, in case most keys just expired.
+        for _ in 0..100 {
+            let index = random_u64() as usize % state.entries.len();
+            let (key, entry) = state.entries.iter().nth(index).unwrap();
+
+            if !matches!(entry.expires_at, Some(when) if when <= now) {
+                return Some(key.clone());
+            }
+        }
 
         None
     }
 
-    /// Returns `true` if the database is shutting down
+    /// Remove all the keys of the selected database.
+    ///
+    /// The maps are swapped out under the lock, so that freeing the values
+    /// does not block other connections. When `asynchronous` is set, they are
+    /// freed on a blocking task instead of the current one, so that flushing a
+    /// huge key space does not stall the event loop.
+    pub(crate) fn flush(&self, asynchronous: bool) {
+        let mut state = self.lock();
+        let flushed = state.take_keys();
+        drop(state);
+
+        drop_flushed(vec![flushed], asynchronous);
+    }
+
+    /// Remove all the keys of all the databases, as `flush` does.
+    pub(crate) fn flush_all(&self, asynchronous: bool) {
+        let mut databases = self.lock_all();
+        let flushed = databases.states.iter_mut().map(State::take_keys).collect();
+        drop(databases);
+
+        drop_flushed(flushed, asynchronous);
+    }
+
+    /// Exchange the keys of the databases at `index1` and `index2`.
+    ///
+    /// Connections keep the database they selected, they see the keys of the
+    /// other database from now on.
+    pub(crate) fn swap(&self, index1: usize, index2: usize) -> Result<(), DbError> {
+        let mut databases = self.lock_all();
+        let count = databases.states.len();
+
+        if index1 >= count || index2 >= count {
+            return Err(DbError::DbIndexOutOfRange);
+        }
+
+        if index1 != index2 {
+            let (low, high) = (index1.min(index2), index1.max(index2));
+            let (head, tail) = databases.states.split_at_mut(high);
+            head[low].swap_keys(&mut tail[0]);
+        }
+
+        // The background task purges all the databases and the expiration
+        // instants did not change, it does not need to be notified.
+        Ok(())
+    }
+
+    /// Move `key`, along with its expiration, to the database at `index`.
+    ///
+    /// Returns `false` if `key` does not exist or already exists in the
+    /// destination database, in which case nothing is moved.
+    pub(crate) fn move_key(&self, key: &str, index: usize) -> Result<bool, DbError> {
+        let mut databases = self.lock_all();
+
+        if index >= databases.states.len() {
+            return Err(DbError::DbIndexOutOfRange);
+        }
+
+        if index == self.index {
+            return Err(DbError::SameObject);
+        }
+
+        let now = Instant::now();
+        match databases.states[self.index].entries.get(key) {
+            // The key expired but has not been purged yet.
+            Some(entry) if matches!(entry.expires_at, Some(when) if when <= now) => {
+                return Ok(false)
+            }
+            Some(_) => {}
+            None => return Ok(false),
+        }
+
+        if databases.states[index].entries.contains_key(key) {
+            return Ok(false);
+        }
+
+        let mut entry = databases.states[self.index].remove(key).unwrap();
+
+        // Expirations are identified by the entry id, which must be unique in
+        // the destination database. The instant does not change, so the
+        // background task does not need to be notified.
+        let state = &mut databases.states[index];
+        entry.id = state.next_id;
+        state.next_id += 1;
+
+        state.insert(key.to_string(), entry);
+        state.notify_writes(key);
+
+        Ok(true)
+    }
+
+    /// Copy the value stored at `source`, along with its expiration, to
+    /// `destination` in the database at index `db`, or in the selected
+    /// database when `db` is `None`. If `destination` already exists, the
+    /// value is only copied when `replace` is set.
+    ///
+    /// Returns `true` if the value was copied.
+    pub(crate) fn copy(
+        &self,
+        source: &str,
+        destination: &str,
+        db: Option<usize>,
+        replace: bool,
+    ) -> Result<bool, DbError> {
+        let mut databases = self.lock_all();
+        let index = db.unwrap_or(self.index);
+
+        if index >= databases.states.len() {
+            return Err(DbError::DbIndexOutOfRange);
+        }
+
+        if source == destination && index == self.index {
+            return Err(DbError::SameObject);
+        }
+
+        let now = Instant::now();
+
+        let (data, expires_at) = match databases.states[self.index].entries.get(source) {
+            // The key expired but has not been purged yet.
+            Some(entry) if matches!(entry.expires_at, Some(when) if when <= now) => {
+                return Ok(false)
+            }
+            Some(entry) => (entry.data.clone(), entry.expires_at),
+            None => return Ok(false),
+        };
+
+        let state = &mut databases.states[index];
+        if state.entries.contains_key(destination) {
+            if !replace {
+                return Ok(false);
+            }
+            state.remove(destination);
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+
+        // The copy expires at the same instant as the source, the background
+        // task does not need to be notified.
+        let entry = Entry {
+            id,
+            data,
+            expires_at,
+            last_access: now,
+            frequency: LFU_INIT,
+            size: 0,
+        };
+        state.insert(destination.to_string(), entry);
+        state.notify_writes(destination);
+
+        Ok(true)
+    }
+
+    /// Returns the value stored at `key` serialized by `DUMP`, or `None` if
+    /// `key` does not exist.
+    pub(crate) fn dump(&self, key: &str) -> Option<Bytes> {
+        let state = self.lock();
+        state.entries.get(key).map(|entry| entry.data.dump())
+    }
+
+    /// Store at `key` the value serialized by `DUMP` in `payload`, expiring at
+    /// `expires_at`. With `replace`, an existing key is overwritten, otherwise
+    /// `Err` is returned. An expiration already past removes the key instead.
+    pub(crate) fn restore_dump(
+        &self,
+        key: &str,
+        payload: &[u8],
+        expires_at: Option<Instant>,
+        replace: bool,
+    ) -> Result<(), DbError> {
+        let data = Value::undump(payload)?;
+
+        let mut state = self.lock();
+        if state.entries.contains_key(key) && !replace {
+            return Err(DbError::BusyKey);
+        }
+
+        let now = Instant::now();
+        if matches!(expires_at, Some(when) if when <= now) {
+            if state.remove(key).is_some() {
+                state.notify(b'g', "del", key);
+            }
+            return Ok(());
+        }
+
+        state.remove(key);
+        let id = state.next_id;
+        state.next_id += 1;
+        let entry = Entry {
+            id,
+            data,
+            expires_at: None,
+            last_access: now,
+            frequency: LFU_INIT,
+            size: 0,
+        };
+        state.insert(key.to_string(), entry);
+        let notify = state.set_expiration(key, expires_at);
+        state.notify(b'g', "restore", key);
+        state.notify_writes(key);
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the values stored at `keys` for `MIGRATE`, as `dump` does.
+    /// The keys which do not exist are skipped.
+    ///
+    /// The keys are watched until the migration is passed to `end_migration`.
+    pub(crate) fn begin_migration(&self, keys: &[String]) -> Migration {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let mut migration = Migration {
+            values: vec![],
+            watched: vec![],
+        };
+
+        for key in keys {
+            let entry = match state.entries.get(key) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            // A time to live of 0 never expires, keys about to expire have 1
+            // millisecond left.
+            let ttl = entry.expires_at.map_or(0, |when| {
+                when.saturating_duration_since(now).as_millis().max(1) as u64
+            });
+            migration.values.push((key.clone(), entry.data.dump(), ttl));
+            migration.watched.push(state.watch(self.index, key));
+        }
+
+        migration
+    }
+
+    /// Complete `migration`, removing the keys of `removed` which were not
+    /// modified since `begin_migration`.
+    ///
+    /// The removed keys are logged as `DEL` commands, rather than `MIGRATE`,
+    /// which the replicas and the AOF must not apply again.
+    pub(crate) fn end_migration(&self, migration: Migration, removed: &[String]) {
+        let mut databases = self.lock_all();
+        self.set_logged_command(None);
+
+        for watched in &migration.watched {
+            let state = &mut databases.states[watched.index];
+            let modified = state
+                .watched
+                .get(&watched.key)
+                .map(|key| key.version != watched.version)
+                .unwrap_or(true);
+
+            if modified || !removed.contains(&watched.key) {
+                continue;
+            }
+
+            if state.remove(&watched.key).is_some() {
+                state.logged_deletions.push(watched.key.clone());
+                databases.notify(watched.index, b'g', "del", &watched.key);
+            }
+        }
+
+        drop(databases);
+        self.unwatch(migration.watched);
+    }
+
+    /// Returns the elements of the list, set or sorted set stored at `key`,
+    /// sorted according to `options`.
+    ///
+    /// An empty list is returned if `key` does not exist.
+    pub(crate) fn sort(&self, key: &str, options: &SortOptions) -> Result<Vec<Bytes>, DbError> {
+        let state = self.lock();
+        state.sort(key, options)
+    }
+
+    /// Sort the elements stored at `key` as `sort` does and store them as a
+    /// list at `dest`, replacing any previous value. When there is nothing to
+    /// store, `dest` is removed.
+    ///
+    /// Returns the number of stored elements.
+    pub(crate) fn sort_store(
+        &self,
+        key: &str,
+        dest: String,
+        options: &SortOptions,
+    ) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let sorted = state.sort(key, options)?;
+        let len = sorted.len();
+
+        state.remove(&dest);
+
+        if len > 0 {
+            state.entry_or_insert_with(dest.clone(), || Value::List(sorted.into()));
+            state.notify_writes(&dest);
+        }
+
+        Ok(len)
+    }
+
+    /// Returns the remaining time to live of `key`.
+    ///
+    /// Returns `None` if the key does not exist, and `Some(None)` if the key
+    /// exists but has no associated expiration. A key whose expiration has
+    /// passed but that was not purged yet does not exist.
+    pub(crate) fn ttl(&self, key: &str) -> Option<Option<Duration>> {
+        let state = self.lock();
+        let entry = state.entries.get(key)?;
+
+        match entry.expires_at {
+            Some(when) => {
+                let now = Instant::now();
+                if when <= now {
+                    None
+                } else {
+                    Some(Some(when - now))
+                }
+            }
+            None => Some(None),
+        }
+    }
+
+    /// Set the expiration of `key` to `when`, if all of `options` are
+    /// satisfied. A `when` in the past deletes the key right away.
+    ///
+    /// Returns `true` if the key exists and the expiration was updated.
+    pub(crate) fn expire(&self, key: &str, when: Instant, options: &[ExpireOption]) -> bool {
+        let mut state = self.lock();
+
+        let current = match state.entries.get(key) {
+            Some(entry) => entry.expires_at,
+            None => return false,
+        };
+
+        let now = Instant::now();
+
+        // The key expired but has not been purged yet.
+        if matches!(current, Some(current) if current <= now) {
+            return false;
+        }
+
+        let allowed = options.iter().all(|option| match option {
+            ExpireOption::Nx => current.is_none(),
+            ExpireOption::Xx => current.is_some(),
+            ExpireOption::Gt => matches!(current, Some(current) if when > current),
+            ExpireOption::Lt => match current {
+                Some(current) => when < current,
+                None => true,
+            },
+        });
+
+        if !allowed {
+            return false;
+        }
+
+        if when <= now {
+            state.remove(key);
+            state.notify(b'g', "del", key);
+            return true;
+        }
+
+        let notify = state.set_expiration(key, Some(when));
+        state.notify(b'g', "expire", key);
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        true
+    }
+
+    /// Remove the expiration of `key`, making it persistent.
+    ///
+    /// Returns `true` if the key exists and had an expiration.
+    pub(crate) fn persist(&self, key: &str) -> bool {
+        let mut state = self.lock();
+        let state = &mut *state;
+
+        let entry = match state.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        match entry.expires_at.take() {
+            Some(when) => {
+                // The background task may still wake up for this expiration,
+                // it finds nothing left to purge.
+                state.expirations.remove(when, entry.id);
+                state.modified(key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a `Receiver` notified each time a value is written to `key` by a
+    /// command that may unblock clients, such as `XADD`.
+    ///
+    /// Blocking commands subscribe **before** checking the key for data. This
+    /// way, no write happening between the check and the wait is missed.
+    pub(crate) fn subscribe_writes(&self, key: String) -> broadcast::Receiver<()> {
+        let mut state = self.lock();
+
+        match state.blocking.get(&key) {
+            Some(tx) => tx.subscribe(),
+            None => {
+                // The notification does not carry any data and blocked clients
+                // check the key again once woken up, so there is no need to
+                // buffer more than one notification. A lagging receiver is
+                // still woken up.
+                let (tx, rx) = broadcast::channel(1);
+                state.blocking.insert(key, tx);
+                rx
+            }
+        }
+    }
+
+    /// Queue a blocked client on `keys`, returning the ticket identifying it.
+    ///
+    /// The client must leave the queues with `dequeue` once it stops waiting.
+    pub(crate) fn enqueue(&self, keys: &[String]) -> u64 {
+        let mut state = self.lock();
+
+        let ticket = state.next_id;
+        state.next_id += 1;
+
+        for key in keys {
+            state
+                .queued
+                .entry(key.clone())
+                .or_default()
+                .push_back(ticket);
+        }
+
+        ticket
+    }
+
+    /// Remove the blocked client holding `ticket` from the queues of `keys`.
+    pub(crate) fn dequeue(&self, keys: &[String], ticket: u64) {
+        let mut state = self.lock();
+        state.dequeue(keys, ticket);
+    }
+
+    /// Returns a `Subscription` to the requested channel.
+    ///
+    /// The returned `Subscription` is used to receive values broadcast by
+    /// `PUBLISH` commands.
+    ///
+    /// A client subscribed to `__redis__:invalidate` only receives the keys
+    /// invalidated for the clients redirecting their invalidation messages to
+    /// it with `CLIENT TRACKING`.
+    pub(crate) fn subscribe(&self, key: String) -> Subscription<Bytes> {
+        // Acquire the mutex
+        let mut databases = self.shared.lock(self.transaction);
+
+        let (rx, kind) = if key == INVALIDATE_CHANNEL && self.client != 0 {
+            let rx = subscribe_channel(&mut databases.invalidations, self.client);
+            (rx, ChannelKind::Invalidation(self.client))
+        } else {
+            let rx = subscribe_channel(&mut databases.pub_sub, key.clone());
+            (rx, ChannelKind::Channel)
+        };
+
+        Subscription {
+            rx,
+            shared: Arc::downgrade(&self.shared),
+            name: key,
+            kind,
+        }
+    }
+
+    /// Returns a `Subscription` to the channels matching the glob `pattern`.
+    ///
+    /// The returned `Subscription` is used to receive values broadcast by
+    /// `PUBLISH` commands, along with the channel they were published to.
+    pub(crate) fn psubscribe(&self, pattern: String) -> Subscription<(String, Bytes)> {
+        let mut databases = self.shared.lock(self.transaction);
+        let rx = subscribe_channel(&mut databases.pattern_pub_sub, pattern.clone());
+
+        Subscription {
+            rx,
+            shared: Arc::downgrade(&self.shared),
+            name: pattern,
+            kind: ChannelKind::Pattern,
+        }
+    }
+
+    /// Returns a `Subscription` to the requested shard channel.
+    ///
+    /// The returned `Subscription` is used to receive values broadcast by
+    /// `SPUBLISH` commands.
+    pub(crate) fn ssubscribe(&self, key: String) -> Subscription<Bytes> {
+        let mut databases = self.shared.lock(self.transaction);
+        let rx = subscribe_channel(&mut databases.shard_pub_sub, key.clone());
+
+        Subscription {
+            rx,
+            shared: Arc::downgrade(&self.shared),
+            name: key,
+            kind: ChannelKind::Shard,
+        }
+    }
+
+    /// Returns the channels with at least one subscriber, among the ones
+    /// matching the glob `pattern` if any. `shard` selects the shard channels
+    /// rather than the global ones.
+    pub(crate) fn pubsub_channels(&self, pattern: Option<&str>, shard: bool) -> Vec<String> {
+        let databases = self.shared.lock(self.transaction);
+        let channels = if shard {
+            databases.shard_pub_sub.keys()
+        } else {
+            databases.pub_sub.keys()
+        };
+
+        channels
+            .filter(|channel| matches_pattern(pattern, channel.as_bytes()))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of subscribers of each of `channels`, not counting
+    /// the subscribers to patterns. `shard` selects the shard channels rather
+    /// than the global ones.
+    pub(crate) fn pubsub_numsub(&self, channels: &[String], shard: bool) -> Vec<usize> {
+        let databases = self.shared.lock(self.transaction);
+        let pub_sub = if shard {
+            &databases.shard_pub_sub
+        } else {
+            &databases.pub_sub
+        };
+
+        channels
+            .iter()
+            .map(|channel| {
+                pub_sub
+                    .get(channel)
+                    .map_or(0, |channel| channel.subscribers)
+            })
+            .collect()
+    }
+
+    /// Returns the number of patterns with at least one subscriber.
+    pub(crate) fn pubsub_numpat(&self) -> usize {
+        self.shared.lock(self.transaction).pattern_pub_sub.len()
+    }
+
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel, or on a pattern matching it.
+    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+        self.shared.lock(self.transaction).publish(key, value)
+    }
+
+    /// Publish a message to the shard channel. Returns the number of
+    /// subscribers listening on the shard channel.
+    pub(crate) fn spublish(&self, key: &str, value: Bytes) -> usize {
+        let databases = self.shared.lock(self.transaction);
+
+        databases
+            .shard_pub_sub
+            .get(key)
+            .map(|channel| channel.tx.send(value).unwrap_or(0))
+            .unwrap_or(0)
+    }
+}
+
+impl Clone for Db {
+    /// Returns a handle to the same shared state, selecting the same database.
+    /// The command being applied through the handle, if any, is not cloned.
+    fn clone(&self) -> Db {
+        Db {
+            shared: self.shared.clone(),
+            index: self.index,
+            transaction: self.transaction,
+            client: self.client,
+            command: Mutex::new(None),
+        }
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        // If this is the last active `Db` instance, the background task must be
+        // notified to shut down.
+        //
+        // First, determine if this is the last `Db` instance. This is done by
+        // checking `strong_count`. The count will be 2. One for this `Db`
+        // instance and one for the handle held by the background task.
+        if Arc::strong_count(&self.shared) == 2 {
+            // The background task must be signaled to shutdown. This is done by
+            // setting `Databases::shutdown` to `true` and signalling the task.
+            let mut databases = self.shared.lock(self.transaction);
+            databases.shutdown = true;
+
+            // Drop the lock before signalling the background task. This helps
+            // reduce lock contention by ensuring the background task doesn't
+            // wake up only to be unable to acquire the mutex.
+            drop(databases);
+            self.shared.background_task.notify_one();
+        }
+    }
+}
+
+impl<T: Clone> Subscription<T> {
+    /// Receive the next message, see `broadcast::Receiver::recv`.
+    pub(crate) async fn recv(&mut self) -> Result<T, broadcast::error::RecvError> {
+        self.rx.recv().await
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            let mut databases = shared.lock(None);
+
+            match self.kind {
+                ChannelKind::Channel => unsubscribe_channel(&mut databases.pub_sub, &self.name),
+                ChannelKind::Pattern => {
+                    unsubscribe_channel(&mut databases.pattern_pub_sub, &self.name)
+                }
+                ChannelKind::Shard => unsubscribe_channel(&mut databases.shard_pub_sub, &self.name),
+                ChannelKind::Invalidation(client) => {
+                    unsubscribe_channel(&mut databases.invalidations, &client)
+                }
+            }
+        }
+    }
+}
+
+impl Transaction {
+    /// Returns the handle executing the transaction.
+    pub(crate) fn db(&mut self) -> &mut Db {
+        &mut self.db
+    }
+
+    /// Complete the transaction, returning a handle selecting the same
+    /// database as the one executing it.
+    pub(crate) fn commit(self) -> Db {
+        Db {
+            shared: self.db.shared.clone(),
+            index: self.db.index,
+            transaction: None,
+            client: self.db.client,
+            command: Mutex::new(None),
+        }
+    }
+}
+
+impl ScriptRun {
+    /// Returns a function checked by the thread running the script as it
+    /// runs, returning the error stopping it once it is killed.
+    pub(crate) fn interrupt(&self) -> impl Fn() -> Option<String> + Send + 'static {
+        let state = self.script.state.clone();
+        let function = self.script.function;
+
+        move || {
+            if state.load(Ordering::SeqCst) == SCRIPT_KILLED {
+                Some(killed_error(function))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Record that the script executes a write command, after which it may no
+    /// longer be killed by `SCRIPT KILL`.
+    ///
+    /// Returns `Err` with the error stopping the script if it was killed, in
+    /// which case it must not execute the command.
+    pub(crate) fn record_write(&self) -> Result<(), String> {
+        match self.script.state.compare_exchange(
+            SCRIPT_RUNNING,
+            SCRIPT_WROTE,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        ) {
+            Err(SCRIPT_KILLED) => Err(killed_error(self.script.function)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for ScriptRun {
+    fn drop(&mut self) {
+        let _ = self.shared.script.0.send(None);
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        let mut databases = self.db.shared.lock(self.db.transaction);
+        databases.transaction = None;
+        drop(databases);
+
+        self.db.shared.transaction_done.notify_all();
+    }
+}
+
+impl Migration {
+    /// Returns the keys, along with their serialized value and their time to
+    /// live in milliseconds, `0` if they do not expire.
+    pub(crate) fn values(&self) -> &[(String, Bytes, u64)] {
+        &self.values
+    }
+}
+
+impl Databases {
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel, or on a pattern matching it.
+    fn publish(&self, key: &str, value: Bytes) -> usize {
+        let subscribers = self
+            .pub_sub
+            .get(key)
+            // On a successful message send on the broadcast channel, the number
+            // of subscribers is returned. An error indicates there are no
+            // receivers, in which case, `0` should be returned.
+            .map(|channel| channel.tx.send(value.clone()).unwrap_or(0))
+            // If there is no entry for the channel key, then there are no
+            // subscribers. In this case, return `0`.
+            .unwrap_or(0);
+
+        // A client subscribed to several patterns matching the channel
+        // receives the message once per pattern.
+        let psubscribers: usize = self
+            .pattern_pub_sub
+            .iter()
+            .filter(|(pattern, _)| glob::matches(pattern.as_bytes(), key.as_bytes()))
+            .map(|(_, channel)| {
+                channel
+                    .tx
+                    .send((key.to_string(), value.clone()))
+                    .unwrap_or(0)
+            })
+            .sum();
+
+        subscribers + psubscribers
+    }
+
+    /// Record that `event`, of `class`, happened to `key` of database `index`,
+    /// if events of `class` are published.
+    fn notify(&mut self, index: usize, class: u8, event: &'static str, key: &str) {
+        if self.config.notify_keyspace_events.allows(class) {
+            self.notifications.push((index, event, key.to_string()));
+        }
+    }
+
+    /// Publish the keyspace events recorded since they were last published.
+    fn publish_notifications(&mut self) {
+        let events = self.config.notify_keyspace_events;
+
+        for (index, event, key) in std::mem::take(&mut self.notifications) {
+            if events.keyspace() {
+                let channel = format!("__keyspace@{}__:{}", index, key);
+                self.publish(&channel, Bytes::from(event));
+            }
+            if events.keyevent() {
+                let channel = format!("__keyevent@{}__:{}", index, event);
+                self.publish(&channel, Bytes::from(key));
+            }
+        }
+    }
+
+    /// Send the keys invalidated since they were last sent to the clients
+    /// tracking them, through the clients they redirect to, or through their
+    /// own connection.
+    fn send_invalidations(&mut self) {
+        let tracking = &self.tracking;
+        let invalidations = &self.invalidations;
+        let pushed_invalidations = &self.pushed_invalidations;
+
+        for state in &mut self.states {
+            for (key, clients) in state.invalidated.drain(..) {
+                for client in &clients {
+                    if let Some(channel) = pushed_invalidations.get(client) {
+                        let _ = channel.send(Bytes::from(key.clone()));
+                    }
+                }
+
+                // Several clients may redirect to the same one, which receives
+                // the key once.
+                let redirects: HashSet<u64> = clients
+                    .iter()
+                    .filter_map(|client| tracking.get(client).copied().flatten())
+                    .collect();
+
+                for redirect in redirects {
+                    if let Some(channel) = invalidations.get(&redirect) {
+                        let _ = channel.tx.send(Bytes::from(key.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the approximate number of bytes used by the keys of all the
+    /// databases, updating the size of the modified entries first.
+    fn used_memory(&mut self) -> usize {
+        let mut used_memory = 0;
+        for state in &mut self.states {
+            state.update_sizes();
+            used_memory += state.used_memory + state.expirations.len() * EXPIRATION_OVERHEAD;
+        }
+
+        self.peak_memory = self.peak_memory.max(used_memory);
+        used_memory
+    }
+
+    /// Returns the key to evict according to `policy`, and the index of its
+    /// database, among `samples` keys of each database picked at random.
+    ///
+    /// Returns `None` if there is no key the policy may evict.
+    fn eviction_candidate(
+        &self,
+        policy: EvictionPolicy,
+        samples: usize,
+    ) -> Option<(usize, String)> {
+        let now = Instant::now();
+
+        self.states
+            .iter()
+            .enumerate()
+            .flat_map(|(index, state)| {
+                state
+                    .sample_keys(samples, policy.is_volatile())
+                    .into_iter()
+                    .map(move |key| (index, state, key))
+            })
+            .max_by_key(|(_, state, key)| {
+                let entry = &state.entries[*key];
+                policy.rank(now, entry.last_access, entry.frequency, entry.expires_at)
+            })
+            .map(|(index, _, key)| (index, key.clone()))
+    }
+}
+
+impl Shared {
+    /// Acquire the lock on the databases on behalf of a handle executing
+    /// `transaction`, if any.
+    ///
+    /// While another transaction is being executed, the lock is released until
+    /// it completes. Transactions never yield, so this waits at most for the
+    /// thread executing it to apply its commands, or to run its script.
+    fn lock(&self, transaction: Option<u64>) -> MutexGuard<'_, Databases> {
+        // A command panicking while holding the lock poisons it. The databases
+        // remain usable by the other connections nonetheless.
+        let mut databases = self
+            .databases
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        while databases.transaction.is_some() && databases.transaction != transaction {
+            databases = self
+                .transaction_done
+                .wait(databases)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        databases
+    }
+
+    /// Acquire the lock on the databases without waiting for the transaction
+    /// being executed, if any, to access the state of the connections and the
+    /// configuration.
+    ///
+    /// While a script runs for longer than the `lua-time-limit` parameter,
+    /// clients keep connecting, and authenticating to kill it with `SCRIPT
+    /// KILL`. The transaction may see this state change, but never the data
+    /// set.
+    fn lock_now(&self) -> MutexGuard<'_, Databases> {
+        self.databases
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Purge the expired keys and return the `Instant` at which the
+    /// background task should purge them again: when the **next** key will
+    /// expire, or the next cycle if keys are left to purge.
+    ///
+    /// As in Redis, keys are purged adaptively. At most
+    /// `ACTIVE_EXPIRE_KEYS_PER_LOOP` keys, the ones expiring first, are
+    /// sampled at once, the lock being released in between. A database is
+    /// purged again while more than `ACTIVE_EXPIRE_ACCEPTABLE_STALE` percent
+    /// of the sampled keys expired, until `ACTIVE_EXPIRE_CYCLE_BUDGET` is
+    /// spent. The keys left expired are removed by the next cycle, or when
+    /// accessed.
+    async fn purge_expired_keys(&self) -> Option<Instant> {
+        let start = Instant::now();
+
+        // The task sleeps until the next key of any database expires.
+        let mut next = None;
+        let mut index = 0;
+        loop {
+            // The lock is released before yielding.
+            let (now, expired, when) = {
+                let mut databases = self.lock(None);
+
+                if databases.shutdown {
+                    // The database is shutting down. All handles to the shared
+                    // state have dropped. The background task should exit.
+                    return None;
+                }
+
+                // The task waits until it is enabled again.
+                if !databases.active_expire {
+                    return None;
+                }
+
+                let state = match databases.states.get_mut(index) {
+                    Some(state) => state,
+                    None => break,
+                };
+
+                // Find the keys scheduled to expire **before** now.
+                let now = Instant::now();
+                let mut expired = vec![];
+                let when = state.purge_expired_keys(now, ACTIVE_EXPIRE_KEYS_PER_LOOP, &mut expired);
+
+                for key in &expired {
+                    databases.notify(index, b'x', "expired", key);
+                }
+                databases.publish_notifications();
+                databases.send_invalidations();
+                (now, expired, when)
+            };
+
+            let stale =
+                expired.len() * 100 > ACTIVE_EXPIRE_KEYS_PER_LOOP * ACTIVE_EXPIRE_ACCEPTABLE_STALE;
+            if stale && start.elapsed() >= ACTIVE_EXPIRE_CYCLE_BUDGET {
+                // Let the clients access the databases, the purge resumes on
+                // the next cycle.
+                next = Some(start + ACTIVE_EXPIRE_CYCLE_PERIOD);
+                break;
+            }
+
+            if stale && matches!(when, Some(when) if when <= now) {
+                // Let the tasks waiting for the lock acquire it before
+                // purging more keys. The output of `yield_now` is wrongly
+                // marked as `must_use`.
+                #[allow(unused_must_use)]
+                tokio::task::yield_now().await;
+            } else {
+                next = next.into_iter().chain(when).min();
+                index += 1;
+            }
+        }
+
+        let threshold = self.lock(None).config.latency_monitor_threshold;
+        self.add_latency_sample(threshold, "expire-cycle", start.elapsed());
+        next
+    }
+
+    /// Record a latency spike of `event` if it took `duration` or longer than
+    /// `threshold` milliseconds, `0` disabling the latency monitor.
+    fn add_latency_sample(&self, threshold: u64, event: &'static str, duration: Duration) {
+        let latency = duration.as_millis() as u64;
+        if threshold > 0 && latency >= threshold {
+            self.latency.lock().unwrap().add_sample(event, latency);
+        }
+    }
+
+    /// Returns `true` if the database is shutting down
+    ///
+    /// The `shutdown` flag is set when all `Db` values have dropped, indicating
+    /// that the shared state can no longer be accessed.
+    fn is_shutdown(&self) -> bool {
+        self.lock(None).shutdown
+    }
+}
+
+impl Deref for DatabasesGuard<'_> {
+    type Target = Databases;
+
+    fn deref(&self) -> &Databases {
+        &self.databases
+    }
+}
+
+impl DerefMut for DatabasesGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Databases {
+        &mut self.databases
+    }
+}
+
+impl Drop for DatabasesGuard<'_> {
+    fn drop(&mut self) {
+        let databases = &mut *self.databases;
+        databases.publish_notifications();
+        databases.send_invalidations();
+
+        if databases.changes() == self.changes {
+            return;
+        }
+        databases.used_memory();
+
+        // Only the first modification of the command logs it, as the command
+        // is taken.
+        let mut frames = vec![];
+        if let Some(frame) = self.db.command.lock().unwrap().take() {
+            frames.push((self.db.index, frame));
+        }
+
+        for (index, state) in databases.states.iter_mut().enumerate() {
+            for key in state.logged_deletions.drain(..) {
+                let mut frame = Frame::array();
+                frame.push_bulk(Bytes::from("del".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+                frames.push((index, frame));
+            }
+
+            for (key, when) in state.logged_expirations.drain(..) {
+                let mut frame = Frame::array();
+                frame.push_bulk(Bytes::from("pexpireat".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+                frame.push_bulk(Bytes::from(
+                    snapshot::unix_time(when).to_string().into_bytes(),
+                ));
+                frames.push((index, frame));
+            }
+        }
+
+        if let Some(aof) = &mut databases.aof {
+            for (index, frame) in &frames {
+                aof.append(*index, frame);
+            }
+
+            if let Err(err) = aof.flush() {
+                error!(cause = %err, "failed to append to the AOF");
+            }
+        }
+
+        for (index, frame) in &frames {
+            databases.replicas.append(*index, frame);
+        }
+        let backlog_size = databases.config.repl_backlog_size as usize;
+        databases.replicas.flush(backlog_size);
+    }
+}
+
+impl StateGuard<'_> {
+    /// Record that `event`, of `class`, happened to `key` of the selected
+    /// database. See `Databases::notify`.
+    fn notify(&mut self, class: u8, event: &'static str, key: &str) {
+        let index = self.index;
+        self.databases.notify(index, class, event, key);
+    }
+}
+
+impl Deref for StateGuard<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        &self.databases.states[self.index]
+    }
+}
+
+impl DerefMut for StateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        &mut self.databases.states[self.index]
+    }
+}
+
+impl State {
+    /// Purge up to `limit` of the keys that expired at `now`, pushing them to
+    /// `expired`, and return the `Instant` at which the **next** key will
+    /// expire, which is already past if `limit` keys were purged.
+    fn purge_expired_keys(
+        &mut self,
+        now: Instant,
+        limit: usize,
+        expired: &mut Vec<String>,
+    ) -> Option<Instant> {
+        while expired.len() < limit {
+            let key = match self.expirations.pop_expired(now) {
+                Some(key) => key,
+                None => break,
+            };
+
+            // The key expired, remove it
+            self.remove(&key);
+            expired.push(key);
+        }
+
+        // Done purging, this is the instant at which the next key expires.
+        // The worker task will wait until this instant.
+        self.next_expiration()
+    }
+
+    /// Exchange the keys of `self` and `other`.
+    ///
+    /// Blocked clients stay in their database, the ones waiting for a key that
+    /// now exists are woken up.
+    fn swap_keys(&mut self, other: &mut State) {
+        self.modified_all();
+        other.modified_all();
+
+        std::mem::swap(&mut self.entries, &mut other.entries);
+        std::mem::swap(&mut self.scan_index, &mut other.scan_index);
+        std::mem::swap(&mut self.expirations, &mut other.expirations);
+        std::mem::swap(&mut self.used_memory, &mut other.used_memory);
+        std::mem::swap(&mut self.resized, &mut other.resized);
+
+        // Entry ids and tickets must remain unique within each database.
+        let next_id = self.next_id.max(other.next_id);
+        self.next_id = next_id;
+        other.next_id = next_id;
+
+        for state in [self, other] {
+            let keys: Vec<String> = state
+                .blocking
+                .keys()
+                .filter(|key| state.entries.contains_key(*key))
+                .cloned()
+                .collect();
+
+            for key in keys {
+                state.notify_writes(&key);
+            }
+        }
+    }
+
+    /// Remove all the keys, returning the maps holding them.
+    fn take_keys(&mut self) -> Flushed {
+        self.modified_all();
+        self.used_memory = 0;
+        self.resized.clear();
+
+        (
+            std::mem::take(&mut self.entries),
+            std::mem::take(&mut self.scan_index),
+            std::mem::take(&mut self.expirations),
+        )
+    }
+
+    /// Start watching `key` on behalf of a client, the `State` being the
+    /// database at `index`.
+    fn watch(&mut self, index: usize, key: &str) -> Watched {
+        let watched = self.watched.entry(key.to_string()).or_insert(WatchedKey {
+            version: 0,
+            watchers: 0,
+        });
+        watched.watchers += 1;
+
+        Watched {
+            index,
+            key: key.to_string(),
+            version: watched.version,
+        }
+    }
+
+    /// Record a modification of `key`, invalidating the transactions of the
+    /// clients watching it.
+    fn modified(&mut self, key: &str) {
+        self.changes += 1;
+        self.resized.insert(key.to_string());
+
+        if let Some(watched) = self.watched.get_mut(key) {
+            watched.version += 1;
+        }
+
+        if let Some(clients) = self.tracked.remove(key) {
+            self.invalidated.push((key.to_string(), clients));
+        }
+    }
+
+    /// Record a modification of every key, as when the database is flushed.
+    fn modified_all(&mut self) {
+        self.changes += 1;
+
+        for watched in self.watched.values_mut() {
+            watched.version += 1;
+        }
+
+        self.invalidated.extend(self.tracked.drain());
+    }
+
+    /// Update the size of the entries modified since it was last updated,
+    /// and `used_memory` accordingly.
+    ///
+    /// Sizes are updated once the command modifying the entries completes,
+    /// as commands may modify an entry after recording the modification.
+    fn update_sizes(&mut self) {
+        for key in self.resized.drain() {
+            if let Some(entry) = self.entries.get_mut(&key) {
+                self.used_memory -= entry.size;
+                entry.size = key.len() + ENTRY_OVERHEAD + entry.data.allocated_size(MEMORY_SAMPLES);
+                self.used_memory += entry.size;
+            }
+        }
+    }
+
+    /// Returns up to `count` distinct keys picked at random, only among the
+    /// keys with an expiration if `volatile`.
+    ///
+    /// Keys are picked from random positions of the `scan_index`. If too few
+    /// keys with an expiration are found this way, the keys expiring first
+    /// are returned instead.
+    fn sample_keys(&self, count: usize, volatile: bool) -> Vec<&String> {
+        let mut keys = Vec::with_capacity(count);
+        if self.entries.is_empty() {
+            return keys;
+        }
+
+        for _ in 0..count * 10 {
+            if keys.len() == count {
+                break;
+            }
+
+            let position = (random_u64(), String::new());
+            let (_, key) = match self.scan_index.range(position..).next() {
+                Some(indexed) => indexed,
+                None => self.scan_index.iter().next().unwrap(),
+            };
+
+            if volatile && self.entries[key].expires_at.is_none() {
+                continue;
+            }
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        if keys.is_empty() && volatile {
+            keys.extend(self.expirations.keys().take(count));
+        }
+
+        keys
+    }
+
+    fn next_expiration(&self) -> Option<Instant> {
+        self.expirations.next_expiration()
+    }
+
+    /// Returns `true` if the client holding `ticket` may take one of the
+    /// `available` values of `key`: fewer than `available` clients must have
+    /// been waiting on `key` for longer.
+    fn is_next_in_queue(&self, key: &str, ticket: u64, available: usize) -> bool {
+        match self.queued.get(key) {
+            Some(queue) => queue.iter().take(available).any(|queued| *queued == ticket),
+            None => true,
+        }
+    }
+
+    /// Remove `ticket` from the queues of `keys`.
     ///
-    /// The `shutdown` flag is set when all `Db` values have dropped, indicating
-    /// that the shared state can no longer be accessed.
-    fn is_shutdown(&self) -> bool {
-        self.state.lock().unwrap().shutdown
+    /// Clients queued behind `ticket` may have been denied a value it was
+    /// entitled to, so they are woken up to check the keys again.
+    fn dequeue(&mut self, keys: &[String], ticket: u64) {
+        for key in keys {
+            let queue = match self.queued.get_mut(key) {
+                Some(queue) => queue,
+                None => continue,
+            };
+
+            let position = match queue.iter().position(|queued| *queued == ticket) {
+                Some(position) => position,
+                None => continue,
+            };
+            queue.remove(position);
+
+            if queue.is_empty() {
+                self.queued.remove(key);
+            } else if self.entries.contains_key(key) {
+                self.notify_writes(key);
+            }
+        }
+    }
+
+    /// Wake up clients blocked waiting for `key` to be written to.
+    fn notify_writes(&mut self, key: &str) {
+        if let Some(tx) = self.blocking.get(key) {
+            // An error indicates there are no more blocked clients, in which
+            // case the channel is no longer needed.
+            if tx.send(()).is_err() {
+                self.blocking.remove(key);
+            }
+        }
+    }
+
+    /// Returns the entry associated with `key`. If there is none, a new entry
+    /// without an expiration is inserted with the value returned by `init`.
+    /// Either way, the entry is marked as accessed.
+    ///
+    /// This is used by commands that create the value on first write, such as
+    /// `HSET` on a missing key.
+    fn entry_or_insert_with(&mut self, key: String, init: impl FnOnce() -> Value) -> &mut Entry {
+        if !self.entries.contains_key(&key) {
+            self.scan_index
+                .insert((scan_hash(key.as_bytes()), key.clone()));
+        }
+
+        self.modified(&key);
+
+        let next_id = &mut self.next_id;
+        let now = Instant::now();
+
+        let entry = self.entries.entry(key).or_insert_with(|| {
+            let id = *next_id;
+            *next_id += 1;
+
+            Entry {
+                id,
+                data: init(),
+                expires_at: None,
+                last_access: now,
+                frequency: LFU_INIT,
+                size: 0,
+            }
+        });
+        entry.access(now);
+        entry
+    }
+
+    /// Replace the expiration of `key`, which must exist, with `when`. `None`
+    /// removes the expiration.
+    ///
+    /// Returns `true` if the background task must be notified, because the key
+    /// now expires **next**.
+    fn set_expiration(&mut self, key: &str, when: Option<Instant>) -> bool {
+        let entry = match self.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        if let Some(current) = std::mem::replace(&mut entry.expires_at, when) {
+            self.expirations.remove(current, entry.id);
+        }
+
+        let id = entry.id;
+        self.modified(key);
+
+        let when = match when {
+            Some(when) => when,
+            None => return false,
+        };
+
+        // As in `set`, the background task only needs to be notified if this
+        // key now expires **next**.
+        let notify = self
+            .next_expiration()
+            .map(|expiration| expiration > when)
+            .unwrap_or(true);
+
+        self.track_expiration(when, id, key.to_string());
+        notify
+    }
+
+    /// Track the expiration at `when` of `key`, whose entry has the identifier
+    /// `id`.
+    fn track_expiration(&mut self, when: Instant, id: u64, key: String) {
+        self.logged_expirations.push((key.clone(), when));
+
+        self.expirations.insert(when, id, key);
+    }
+
+    /// Insert `entry` at `key`, which must not exist, tracking its expiration
+    /// if it has one.
+    ///
+    /// The caller is responsible for notifying the background task if the
+    /// entry now expires **next**.
+    fn insert(&mut self, key: String, entry: Entry) {
+        self.modified(&key);
+        self.scan_index
+            .insert((scan_hash(key.as_bytes()), key.clone()));
+
+        if let Some(when) = entry.expires_at {
+            self.track_expiration(when, entry.id, key.clone());
+        }
+
+        self.used_memory += entry.size;
+        self.entries.insert(key, entry);
+    }
+
+    /// Returns the elements of the collection stored at `key`, sorted
+    /// according to `options`.
+    fn sort(&self, key: &str, options: &SortOptions) -> Result<Vec<Bytes>, DbError> {
+        let mut elements: Vec<Bytes> = match self.entries.get(key).map(|entry| &entry.data) {
+            Some(Value::List(list)) => list.iter().cloned().collect(),
+            Some(Value::Set(set)) => set.iter().cloned().collect(),
+            Some(Value::SortedSet(zset)) => zset.iter().map(|(member, _)| member.clone()).collect(),
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        if options.alpha {
+            elements.sort_unstable();
+        } else {
+            let mut scored = vec![];
+            for element in elements {
+                match parse_float(&element) {
+                    Some(score) => scored.push((score, element)),
+                    None => return Err(DbError::SortNotNumber),
+                }
+            }
+
+            // As in Redis, elements with the same value are ordered as binary
+            // strings so that the result does not depend on the storage order.
+            scored.sort_unstable_by(|(a, a_elem), (b, b_elem)| {
+                a.partial_cmp(b).unwrap().then_with(|| a_elem.cmp(b_elem))
+            });
+            elements = scored.into_iter().map(|(_, element)| element).collect();
+        }
+
+        if options.desc {
+            elements.reverse();
+        }
+
+        if let Some((offset, count)) = options.limit {
+            let offset = offset.max(0) as usize;
+            let count = if count < 0 {
+                usize::MAX
+            } else {
+                count as usize
+            };
+            elements = elements.into_iter().skip(offset).take(count).collect();
+        }
+
+        Ok(elements)
+    }
+
+    /// Remove the entry associated with `key`, clearing its expiration if it
+    /// has one.
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.modified(key);
+        self.used_memory -= entry.size;
+        self.scan_index
+            .remove(&(scan_hash(key.as_bytes()), key.to_string()));
+
+        if let Some(when) = entry.expires_at {
+            // clear expiration
+            self.expirations.remove(when, entry.id);
+        }
+
+        Some(entry)
+    }
+
+    /// Remove the entry associated with `key` if it holds an empty collection.
+    ///
+    /// Redis does not store empty collections: a key is removed as soon as the
+    /// last element of its collection is removed.
+    fn remove_if_empty(&mut self, key: &str) {
+        let is_empty = match self.entries.get(key) {
+            Some(entry) => entry.data.is_empty(),
+            None => false,
+        };
+
+        if is_empty {
+            self.remove(key);
+        }
     }
 }
 
-impl State {
-    fn next_expiration(&self) -> Option<Instant> {
-        self.expirations
-            .keys()
-            .next()
-            .map(|expiration| expiration.0)
+impl Entry {
+    /// Mark the entry as accessed at `now`, updating its access frequency.
+    fn access(&mut self, now: Instant) {
+        self.frequency =
+            eviction::accessed_frequency(now, self.last_access, self.frequency, random_u64());
+        self.last_access = now;
+    }
+}
+
+impl Value {
+    /// Returns the name of the type of the value, as reported by `TYPE`.
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Returns the name of the encoding Redis would use for the value, as
+    /// reported by `DEBUG OBJECT`. Values are stored the same way whatever
+    /// their size, unlike in Redis.
+    fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(data) if atoi::atoi::<i64>(data).is_some() => "int",
+            Value::String(data) if data.len() <= 44 => "embstr",
+            Value::String(_) => "raw",
+            Value::Hash(_) | Value::Set(_) => "hashtable",
+            Value::List(_) => "quicklist",
+            Value::SortedSet(_) => "skiplist",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Returns an approximation of the number of bytes used by the value: the
+    /// length of the strings it holds, not counting the overhead of their
+    /// allocations. Streams are measured by their serialized length.
+    fn memory_usage(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::Hash(hash) => hash
+                .iter()
+                .map(|(field, value)| field.len() + value.len())
+                .sum(),
+            Value::List(list) => list.iter().map(Bytes::len).sum(),
+            Value::Set(set) => set.iter().map(Bytes::len).sum(),
+            // Scores are 8 bytes floats.
+            Value::SortedSet(zset) => zset.iter().map(|(member, _)| member.len() + 8).sum(),
+            Value::Stream(stream) => {
+                let mut encoded = bytes::BytesMut::new();
+                stream.encode(&mut encoded);
+                encoded.len()
+            }
+        }
+    }
+
+    /// Returns an approximation of the number of bytes allocated for the
+    /// value, including the overhead of its collection.
+    ///
+    /// The size of the elements of a collection is extrapolated from its
+    /// first `samples` elements, all of them being measured if `samples` is
+    /// 0, so that large collections are measured in constant time.
+    fn allocated_size(&self, samples: usize) -> usize {
+        use std::mem::size_of;
+
+        let elements = match self {
+            Value::String(data) => data.len(),
+            Value::Hash(hash) => estimate_size(
+                hash.len(),
+                samples,
+                hash.iter()
+                    .map(|(field, value)| size_of::<(String, Bytes)>() + field.len() + value.len()),
+            ),
+            Value::List(list) => estimate_size(
+                list.len(),
+                samples,
+                list.iter().map(|value| size_of::<Bytes>() + value.len()),
+            ),
+            Value::Set(set) => estimate_size(
+                set.len(),
+                samples,
+                set.iter().map(|value| size_of::<Bytes>() + value.len()),
+            ),
+            // Members are stored both by score and in the map of the scores.
+            Value::SortedSet(zset) => estimate_size(
+                zset.len(),
+                samples,
+                zset.iter()
+                    .map(|(member, _)| 2 * (size_of::<(Bytes, f64)>() + member.len())),
+            ),
+            Value::Stream(stream) => estimate_size(
+                stream.len(),
+                samples,
+                stream
+                    .range(StreamId::MIN..=StreamId::MAX)
+                    .map(|(_, fields)| {
+                        size_of::<(StreamId, StreamFields)>()
+                            + fields
+                                .iter()
+                                .map(|(field, value)| {
+                                    size_of::<(String, Bytes)>() + field.len() + value.len()
+                                })
+                                .sum::<usize>()
+                    }),
+            ),
+        };
+
+        size_of::<Value>() + elements
+    }
+
+    /// Returns `true` if freeing the value is expensive enough to be done in
+    /// the background by `UNLINK`.
+    ///
+    /// As in Redis, this is the case for collections of more than
+    /// `LAZYFREE_THRESHOLD` elements. Strings are a single allocation, but
+    /// releasing a large one to the system still takes time.
+    fn is_large(&self) -> bool {
+        match self {
+            Value::String(data) => data.len() > LAZYFREE_STRING_LEN,
+            Value::Hash(hash) => hash.len() > LAZYFREE_THRESHOLD,
+            Value::List(list) => list.len() > LAZYFREE_THRESHOLD,
+            Value::Set(set) => set.len() > LAZYFREE_THRESHOLD,
+            Value::SortedSet(zset) => zset.len() > LAZYFREE_THRESHOLD,
+            Value::Stream(stream) => stream.len() > LAZYFREE_THRESHOLD,
+        }
+    }
+
+    /// Returns `true` if the value is a collection without any elements.
+    /// Strings are never considered empty. Neither are streams: as in Redis, a
+    /// stream is kept when its entries are removed.
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) | Value::Stream(_) => false,
+            Value::Hash(hash) => hash.is_empty(),
+            Value::List(list) => list.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(zset) => zset.is_empty(),
+        }
+    }
+
+    /// Returns the string value, or `WrongType` if the value is not a string.
+    fn as_string(&self) -> Result<&Bytes, DbError> {
+        match self {
+            Value::String(data) => Ok(data),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns a mutable reference to the string value, or `WrongType` if the
+    /// value is not a string.
+    fn as_string_mut(&mut self) -> Result<&mut Bytes, DbError> {
+        match self {
+            Value::String(data) => Ok(data),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns the hash value, or `WrongType` if the value is not a hash.
+    fn as_hash(&self) -> Result<&HashMap<String, Bytes>, DbError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns a mutable reference to the hash value, or `WrongType` if the
+    /// value is not a hash.
+    fn as_hash_mut(&mut self) -> Result<&mut HashMap<String, Bytes>, DbError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns the list value, or `WrongType` if the value is not a list.
+    fn as_list(&self) -> Result<&VecDeque<Bytes>, DbError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns a mutable reference to the list value, or `WrongType` if the
+    /// value is not a list.
+    fn as_list_mut(&mut self) -> Result<&mut VecDeque<Bytes>, DbError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns the set value, or `WrongType` if the value is not a set.
+    fn as_set(&self) -> Result<&HashSet<Bytes>, DbError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns a mutable reference to the set value, or `WrongType` if the
+    /// value is not a set.
+    fn as_set_mut(&mut self) -> Result<&mut HashSet<Bytes>, DbError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns the sorted set value, or `WrongType` if the value is not a
+    /// sorted set.
+    fn as_sorted_set(&self) -> Result<&SortedSet, DbError> {
+        match self {
+            Value::SortedSet(zset) => Ok(zset),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns a mutable reference to the sorted set value, or `WrongType` if
+    /// the value is not a sorted set.
+    fn as_sorted_set_mut(&mut self) -> Result<&mut SortedSet, DbError> {
+        match self {
+            Value::SortedSet(zset) => Ok(zset),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns the stream value, or `WrongType` if the value is not a stream.
+    fn as_stream(&self) -> Result<&Stream, DbError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(DbError::WrongType),
+        }
+    }
+
+    /// Returns a mutable reference to the stream value, or `WrongType` if the
+    /// value is not a stream.
+    fn as_stream_mut(&mut self) -> Result<&mut Stream, DbError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(DbError::WrongType),
+        }
+    }
+}
+
+/// Hash of `name` ordering the keys, fields or members iterated by the scan
+/// commands.
+///
+/// The hasher is created with fixed keys, the order is the same across calls.
+/// Extrapolates the size of the `len` elements of a collection from the
+/// `sizes` of its first `samples` elements, or of all of them if `samples` is
+/// 0.
+fn estimate_size(len: usize, samples: usize, sizes: impl Iterator<Item = usize>) -> usize {
+    let samples = if samples == 0 { len } else { samples.min(len) };
+    if samples == 0 {
+        return 0;
+    }
+
+    let sampled: usize = sizes.take(samples).sum();
+    (sampled as u128 * len as u128 / samples as u128) as usize
+}
+
+fn scan_hash(name: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Free the keys removed by a flush, on a blocking task when `asynchronous` is
+/// set.
+fn drop_flushed(flushed: Vec<Flushed>, asynchronous: bool) {
+    if asynchronous {
+        tokio::task::spawn_blocking(move || drop(flushed));
+    } else {
+        drop(flushed);
+    }
+}
+
+/// Returns a random number.
+///
+/// Each `RandomState` is seeded with random keys, hashing nothing with it
+/// provides random bits without depending on a random number generator.
+pub(crate) fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Items picked at random from a collection, returned by `random_sample`.
+///
+/// The items are picked as they are iterated over, once the lock is released,
+/// so that a large negative count does not hold the lock while the reply is
+/// built.
+#[derive(Debug)]
+pub(crate) struct RandomSample<T> {
+    items: Vec<T>,
+
+    /// Number of items left to pick.
+    remaining: usize,
+
+    /// Whether the picked items are distinct, in which case `items[..picked]`
+    /// holds the items already picked.
+    distinct: bool,
+    picked: usize,
+}
+
+/// Pick `count` of `items` at random.
+///
+/// With a positive `count`, the picked items are distinct and all the items
+/// are returned, in random order, if there are fewer than `count`. With a
+/// negative `count`, exactly `-count` items are picked, possibly the same one
+/// several times.
+fn random_sample<T: Clone>(items: Vec<T>, count: i64) -> RandomSample<T> {
+    let (remaining, distinct) = if items.is_empty() {
+        (0, true)
+    } else if count < 0 {
+        (count.unsigned_abs() as usize, false)
+    } else {
+        ((count as usize).min(items.len()), true)
+    };
+
+    RandomSample {
+        items,
+        remaining,
+        distinct,
+        picked: 0,
+    }
+}
+
+impl<T: Clone> Iterator for RandomSample<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let len = self.items.len();
+        if !self.distinct {
+            return Some(self.items[random_u64() as usize % len].clone());
+        }
+
+        // Partial Fisher-Yates shuffle, one item at a time.
+        let i = self.picked;
+        let j = i + random_u64() as usize % (len - i);
+        self.items.swap(i, j);
+        self.picked += 1;
+
+        Some(self.items[i].clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for RandomSample<T> {}
+
+/// Returns `true` if `name` matches the glob `pattern`, or if there is no
+/// pattern.
+fn matches_pattern(pattern: Option<&str>, name: &[u8]) -> bool {
+    match pattern {
+        Some(pattern) => glob::matches(pattern.as_bytes(), name),
+        None => true,
+    }
+}
+
+/// Returns a page of the `elements`, sorted by hash, along with the cursor of
+/// the next page.
+///
+/// About `count` elements are visited, of which the ones for which `keep`
+/// returns `true` are returned. Elements with the same hash cannot be told
+/// apart by the cursor, they are always visited together.
+fn scan_page<T>(
+    elements: impl Iterator<Item = (u64, T)>,
+    count: usize,
+    mut keep: impl FnMut(&T) -> bool,
+) -> (u64, Vec<T>) {
+    let mut page = vec![];
+    let mut last = None;
+
+    for (visited, (hash, element)) in elements.enumerate() {
+        if visited >= count && last != Some(hash) {
+            return (hash, page);
+        }
+
+        last = Some(hash);
+
+        if keep(&element) {
+            page.push(element);
+        }
+    }
+
+    (0, page)
+}
+
+/// Returns a page of the elements of a collection, starting at `cursor`.
+///
+/// Collections do not maintain an order by hash as the key space does, the
+/// elements from the cursor onwards are sorted on each call.
+fn scan_collection<'a, T>(
+    elements: impl Iterator<Item = (&'a [u8], T)>,
+    cursor: u64,
+    count: usize,
+    pattern: Option<&str>,
+) -> (u64, Vec<T>) {
+    let mut elements: Vec<_> = elements
+        .filter(|(name, _)| matches_pattern(pattern, name))
+        .map(|(name, element)| (scan_hash(name), element))
+        .filter(|(hash, _)| *hash >= cursor)
+        .collect();
+    elements.sort_unstable_by_key(|(hash, _)| *hash);
+
+    scan_page(elements.into_iter(), count, |_| true)
+}
+
+/// Parses a string value as a 64 bit signed integer. As in Redis, the value
+/// must be in its canonical form: no leading `+` and no surrounding spaces.
+fn parse_integer(data: &[u8]) -> Option<i64> {
+    if data.first() == Some(&b'+') {
+        return None;
+    }
+
+    std::str::from_utf8(data).ok()?.parse().ok()
+}
+
+/// Parses a string value as a float. `NaN` is rejected.
+fn parse_float(data: &[u8]) -> Option<f64> {
+    let value: f64 = std::str::from_utf8(data).ok()?.parse().ok()?;
+
+    if value.is_nan() {
+        return None;
+    }
+
+    Some(value)
+}
+
+/// Converts a Redis style inclusive `start` / `stop` index range into a pair of
+/// valid indexes into a sequence of length `len`.
+///
+/// Negative indexes count from the end of the sequence. Returns `None` if the
+/// range does not contain any element.
+fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start > stop || start >= len {
+        return None;
+    }
+
+    Some((start as usize, stop as usize))
+}
+
+/// Converts a Redis style `LIMIT offset count` into a number of elements to
+/// skip and a maximum number of elements to return.
+///
+/// Returns `None` if the offset is negative, in which case nothing is
+/// returned. A negative count does not limit the number of elements.
+fn normalize_limit(limit: Option<(i64, i64)>) -> Option<(usize, usize)> {
+    match limit {
+        Some((offset, _)) if offset < 0 => None,
+        Some((offset, count)) if count < 0 => Some((offset as usize, usize::MAX)),
+        Some((offset, count)) => Some((offset as usize, count as usize)),
+        None => Some((0, usize::MAX)),
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbError::WrongType => {
+                "WRONGTYPE Operation against a key holding the wrong kind of value".fmt(fmt)
+            }
+            DbError::StreamIdZero => {
+                "ERR The ID specified in XADD must be greater than 0-0".fmt(fmt)
+            }
+            DbError::StreamIdTooSmall => {
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+                    .fmt(fmt)
+            }
+            DbError::NoStream => "ERR The XGROUP subcommand requires the key to exist".fmt(fmt),
+            DbError::NoGroup => "NOGROUP No such key or consumer group".fmt(fmt),
+            DbError::BusyGroup => "BUSYGROUP Consumer Group name already exists".fmt(fmt),
+            DbError::InvalidHll => {
+                "WRONGTYPE Key is not a valid HyperLogLog string value.".fmt(fmt)
+            }
+            DbError::CorruptedHll => "INVALIDOBJ Corrupted HLL object detected".fmt(fmt),
+            DbError::NotInteger => "ERR value is not an integer or out of range".fmt(fmt),
+            DbError::IncrOverflow => "ERR increment or decrement would overflow".fmt(fmt),
+            DbError::NotFloat => "ERR value is not a valid float".fmt(fmt),
+            DbError::HashNotInteger => "ERR hash value is not an integer".fmt(fmt),
+            DbError::HashNotFloat => "ERR hash value is not a float".fmt(fmt),
+            DbError::NanOrInfinity => "ERR increment would produce NaN or Infinity".fmt(fmt),
+            DbError::NoSuchKey => "ERR no such key".fmt(fmt),
+            DbError::IndexOutOfRange => "ERR index out of range".fmt(fmt),
+            DbError::DbIndexOutOfRange => "ERR DB index is out of range".fmt(fmt),
+            DbError::SameObject => "ERR source and destination objects are the same".fmt(fmt),
+            DbError::NoGeoMember => "ERR could not decode requested zset member".fmt(fmt),
+            DbError::ScoreNan => "ERR resulting score is not a number (NaN)".fmt(fmt),
+            DbError::SortNotNumber => {
+                "ERR One or more scores can't be converted into double".fmt(fmt)
+            }
+            DbError::ReadOnly => "READONLY You can't write against a read only replica.".fmt(fmt),
+            DbError::OutOfMemory => {
+                "OOM command not allowed when used memory > 'maxmemory'.".fmt(fmt)
+            }
+            DbError::BusyKey => "BUSYKEY Target key name already exists.".fmt(fmt),
+            DbError::InvalidDump => "ERR DUMP payload version or checksum are wrong".fmt(fmt),
+            DbError::BadDataFormat => "ERR Bad data format".fmt(fmt),
+            DbError::LcsTooLong => "ERR String too long for LCS".fmt(fmt),
+        }
     }
 }
 
+impl std::error::Error for DbError {}
+
 /// Routine executed by the background task.
 ///
 /// Wait to be notified. On notification, purge any expired keys from the shared
@@ -347,7 +4342,7 @@ async fn purge_expired_tasks(shared: Arc<Shared>) {
         // Purge all keys that are expired. The function returns the instant at
         // which the **next** key will expire. The worker should wait until the
         // instant has passed then purge again.
-        if let Some(when) = shared.purge_expired_keys() {
+        if let Some(when) = shared.purge_expired_keys().await {
             // Wait until the next key expires **or** until the background task
             // is notified. If the task is notified, then it must reload its
             // state as new keys have been set to expire early. This is done by
@@ -363,3 +4358,119 @@ async fn purge_expired_tasks(shared: Arc<Shared>) {
         }
     }
 }
+
+/// Subscribe to the channel `name` of `channels`, creating it if there is no
+/// subscriber yet.
+fn subscribe_channel<K: Hash + Eq, T: Clone>(
+    channels: &mut HashMap<K, PubSubChannel<T>>,
+    name: K,
+) -> broadcast::Receiver<T> {
+    use std::collections::hash_map::Entry;
+
+    // If there is no entry for the requested channel, then create a new
+    // broadcast channel and associate it with the key. If one already
+    // exists, return an associated receiver.
+    match channels.entry(name) {
+        Entry::Occupied(mut e) => {
+            e.get_mut().subscribers += 1;
+            e.get().tx.subscribe()
+        }
+        Entry::Vacant(e) => {
+            // No broadcast channel exists yet, so create one.
+            //
+            // The channel is created with a capacity of `1024` messages. A
+            // message is stored in the channel until **all** subscribers
+            // have seen it. This means that a slow subscriber could result
+            // in messages being held indefinitely.
+            //
+            // When the channel's capacity fills up, publishing will result
+            // in old messages being dropped. This prevents slow consumers
+            // from blocking the entire system.
+            let (tx, rx) = broadcast::channel(1024);
+            e.insert(PubSubChannel { tx, subscribers: 1 });
+            rx
+        }
+    }
+}
+
+/// Remove a subscriber of the channel `name` of `channels`, removing the
+/// channel along with the last one.
+fn unsubscribe_channel<K: Hash + Eq, T>(channels: &mut HashMap<K, PubSubChannel<T>>, name: &K) {
+    if let Some(channel) = channels.get_mut(name) {
+        channel.subscribers -= 1;
+        if channel.subscribers == 0 {
+            channels.remove(name);
+        }
+    }
+}
+
+/// Returns `Err` if the client name `name` holds spaces or special
+/// characters.
+pub(crate) fn check_client_name(name: &str) -> Result<(), String> {
+    if !clients::is_valid_name(name) {
+        return Err(
+            "ERR Client names cannot contain spaces, newlines or special characters.".to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn wrong_pass() -> String {
+    "WRONGPASS invalid username-password pair or user is disabled.".to_string()
+}
+
+/// Returns the error replied to a command exceeding the rate limit
+/// `exceeded`, `whose` telling whose limit it is.
+fn throttled(exceeded: Exceeded, whose: &str) -> String {
+    let what = match exceeded {
+        Exceeded::Commands => "commands",
+        Exceeded::Bytes => "bytes",
+    };
+    format!(
+        "THROTTLED max number of {} per second{} reached",
+        what, whose
+    )
+}
+
+/// Returns the error replied to the commands of the other clients while a
+/// script runs for longer than the `lua-time-limit` parameter, `function`
+/// being set if it is a function called by `FCALL`.
+fn busy_error(function: bool) -> String {
+    format!(
+        "BUSY Redis is busy running a script. You can only call {} or SHUTDOWN NOSAVE.",
+        kill_command(function)
+    )
+}
+
+/// Returns the error stopping a killed script.
+fn killed_error(function: bool) -> String {
+    format!(
+        "ERR Script killed by user with {}...",
+        kill_command(function)
+    )
+}
+
+/// Returns the command killing a script, a function called by `FCALL` if
+/// `function` is set.
+fn kill_command(function: bool) -> &'static str {
+    if function {
+        "FUNCTION KILL"
+    } else {
+        "SCRIPT KILL"
+    }
+}
+
+/// Returns the configuration of the cluster, or `Err` if cluster mode is
+/// disabled.
+fn cluster(cluster: &Option<Cluster>) -> Result<&Cluster, String> {
+    cluster.as_ref().ok_or_else(cluster_disabled)
+}
+
+/// Same as `cluster`, giving mutable access to the configuration.
+fn cluster_mut(cluster: &mut Option<Cluster>) -> Result<&mut Cluster, String> {
+    cluster.as_mut().ok_or_else(cluster_disabled)
+}
+
+fn cluster_disabled() -> String {
+    "ERR This instance has cluster support disabled".to_string()
+}
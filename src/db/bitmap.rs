@@ -0,0 +1,463 @@
+//! Bit-level operations over string values.
+//!
+//! Redis numbers the bits of a string from the most significant bit of the
+//! first byte: bit `0` is the highest bit of byte `0` and bit `8` is the
+//! highest bit of byte `1`.
+
+use crate::db::{normalize_range, Db, DbError, Value};
+
+use bytes::Bytes;
+
+/// Unit in which the range arguments of `BITCOUNT` and `BITPOS` are
+/// expressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit {
+    /// Indexes are byte offsets. This is the default.
+    Byte,
+
+    /// Indexes are bit offsets.
+    Bit,
+}
+
+/// Returns the value of the bit at `offset`. Bits past the end of `data` are
+/// `0`.
+pub(crate) fn get_bit(data: &[u8], offset: usize) -> bool {
+    match data.get(offset / 8) {
+        Some(byte) => byte & mask(offset) != 0,
+        None => false,
+    }
+}
+
+/// Set the bit at `offset` to `bit`, growing `data` with zero bytes as needed.
+///
+/// Returns the previous value of the bit.
+pub(crate) fn set_bit(data: &mut Vec<u8>, offset: usize, bit: bool) -> bool {
+    let index = offset / 8;
+
+    if index >= data.len() {
+        data.resize(index + 1, 0);
+    }
+
+    let prev = data[index] & mask(offset) != 0;
+
+    if bit {
+        data[index] |= mask(offset);
+    } else {
+        data[index] &= !mask(offset);
+    }
+
+    prev
+}
+
+/// Returns the number of bits set to `1` within `range`. A missing range
+/// covers the whole string.
+///
+/// Range indexes are inclusive and, as with `GETRANGE`, negative indexes count
+/// from the end of the string.
+pub(crate) fn count(data: &[u8], range: Option<(i64, i64, BitUnit)>) -> usize {
+    let (start, end) = match bit_range(data, range) {
+        Some(range) => range,
+        None => return 0,
+    };
+
+    let (first, last) = (start / 8, end / 8);
+    let ones: usize = data[first..=last]
+        .iter()
+        .map(|byte| byte.count_ones() as usize)
+        .sum();
+
+    // Do not count the bits of the first and last bytes that are outside of
+    // the range.
+    let before = data[first] & !(0xff >> (start % 8));
+    let after = data[last] & ((0xff >> (end % 8)) >> 1);
+
+    ones - before.count_ones() as usize - after.count_ones() as usize
+}
+
+/// Returns the offset of the first bit set to `bit` within the `start` /
+/// `end` range, or `-1` if there is none. `end` defaults to the end of the
+/// string and a missing `start` covers the whole string.
+///
+/// When looking for a `0` without an explicit end, the string is considered
+/// padded with zeros on the right: if all bits of the range are set, the
+/// offset right after the range is returned.
+pub(crate) fn position(
+    data: &[u8],
+    bit: bool,
+    start: Option<i64>,
+    end: Option<i64>,
+    unit: BitUnit,
+) -> i64 {
+    let range = start.map(|start| (start, end.unwrap_or(-1), unit));
+
+    let (start, last) = match bit_range(data, range) {
+        Some(range) => range,
+        None => return -1,
+    };
+
+    // Bytes without any bit set to `bit` are skipped as a whole.
+    let skip = if bit { 0x00 } else { 0xff };
+    let mut offset = start;
+
+    while offset <= last {
+        if offset % 8 == 0 && offset + 7 <= last && data[offset / 8] == skip {
+            offset += 8;
+            continue;
+        }
+
+        if get_bit(data, offset) == bit {
+            return offset as i64;
+        }
+
+        offset += 1;
+    }
+
+    if !bit && end.is_none() {
+        last as i64 + 1
+    } else {
+        -1
+    }
+}
+
+/// Bitwise operation performed by `BITOP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// Combine `sources` with `op`. The result is as long as the longest source,
+/// shorter sources being padded with zero bytes.
+///
+/// `Not` only uses the first source.
+pub(crate) fn combine(op: BitOperation, sources: &[&[u8]]) -> Vec<u8> {
+    let len = sources.iter().map(|src| src.len()).max().unwrap_or(0);
+    let byte = |src: &[u8], i: usize| src.get(i).copied().unwrap_or(0);
+
+    (0..len)
+        .map(|i| {
+            let mut bytes = sources.iter().map(|src| byte(src, i));
+
+            match op {
+                BitOperation::And => bytes.fold(0xff, |acc, byte| acc & byte),
+                BitOperation::Or => bytes.fold(0x00, |acc, byte| acc | byte),
+                BitOperation::Xor => bytes.fold(0x00, |acc, byte| acc ^ byte),
+                BitOperation::Not => !bytes.next().unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+/// Behavior of `BITFIELD` when a `SET` or `INCRBY` does not fit in the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wrap around, both for signed and unsigned fields. This is the default.
+    Wrap,
+
+    /// Saturate to the minimum or maximum value of the field.
+    Sat,
+
+    /// Leave the field unchanged and reply with nil.
+    Fail,
+}
+
+/// Integer type of a `BITFIELD` field, such as `i8` or `u16`.
+///
+/// Signed fields are up to 64 bits wide, unsigned fields up to 63 bits so
+/// that all values fit in a signed integer reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FieldType {
+    signed: bool,
+    bits: u32,
+}
+
+/// Operation performed by `BITFIELD` on a single field.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FieldOp {
+    Get,
+    Set(i64),
+    IncrBy(i64),
+}
+
+/// A single `BITFIELD` operation, resolved to an absolute bit offset.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Field {
+    pub(crate) op: FieldOp,
+    pub(crate) ty: FieldType,
+    pub(crate) offset: usize,
+    pub(crate) overflow: Overflow,
+}
+
+impl FieldType {
+    /// Parse a field type of the form `i<bits>` or `u<bits>`.
+    pub(crate) fn parse(src: &str) -> Option<FieldType> {
+        let signed = match src.as_bytes().first()? {
+            b'i' | b'I' => true,
+            b'u' | b'U' => false,
+            _ => return None,
+        };
+
+        let bits: u32 = src[1..].parse().ok()?;
+        let max = if signed { 64 } else { 63 };
+
+        if bits == 0 || bits > max {
+            return None;
+        }
+
+        Some(FieldType { signed, bits })
+    }
+
+    /// Returns the width of the field, in bits.
+    pub(crate) fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    fn min(&self) -> i128 {
+        if self.signed {
+            -(1 << (self.bits - 1))
+        } else {
+            0
+        }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed {
+            (1 << (self.bits - 1)) - 1
+        } else {
+            (1 << self.bits) - 1
+        }
+    }
+
+    /// Fit `value` in the field according to `overflow`. Returns `None` if it
+    /// does not fit and `overflow` is `Fail`.
+    fn fit(&self, value: i128, overflow: Overflow) -> Option<i64> {
+        let (min, max) = (self.min(), self.max());
+
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+
+        match overflow {
+            Overflow::Wrap => Some(((value - min).rem_euclid(1 << self.bits) + min) as i64),
+            Overflow::Sat => Some(value.clamp(min, max) as i64),
+            Overflow::Fail => None,
+        }
+    }
+}
+
+/// Returns the value of the field of type `ty` starting at bit `offset`.
+pub(crate) fn get_field(data: &[u8], offset: usize, ty: FieldType) -> i64 {
+    let bits = ty.bits as usize;
+    let raw = (offset..offset + bits).fold(0u64, |acc, offset| {
+        (acc << 1) | get_bit(data, offset) as u64
+    });
+
+    if ty.signed && (raw >> (bits - 1)) & 1 == 1 {
+        (raw as i128 - (1 << bits)) as i64
+    } else {
+        raw as i64
+    }
+}
+
+/// Apply a `BITFIELD` operation to `data`, growing it as needed when the
+/// field is written.
+///
+/// `GET` returns the value of the field, `SET` its previous value and
+/// `INCRBY` its new value. `None` is returned, and the field left unchanged,
+/// when the value overflows with `Overflow::Fail`.
+pub(crate) fn apply_field(data: &mut Vec<u8>, field: &Field) -> Option<i64> {
+    let current = get_field(data, field.offset, field.ty);
+
+    let (value, reply) = match field.op {
+        FieldOp::Get => return Some(current),
+        FieldOp::Set(value) => {
+            let value = field.ty.fit(value as i128, field.overflow)?;
+            (value, current)
+        }
+        FieldOp::IncrBy(incr) => {
+            let value = field
+                .ty
+                .fit(current as i128 + incr as i128, field.overflow)?;
+            (value, value)
+        }
+    };
+
+    let bits = field.ty.bits as usize;
+    for i in 0..bits {
+        let bit = ((value as u64) >> (bits - 1 - i)) & 1 == 1;
+        set_bit(data, field.offset + i, bit);
+    }
+
+    Some(reply)
+}
+
+/// Converts a range, in the given unit, into an inclusive range of bit
+/// offsets within `data`. A missing range covers the whole string.
+///
+/// Returns `None` if the range does not contain any bit.
+fn bit_range(data: &[u8], range: Option<(i64, i64, BitUnit)>) -> Option<(usize, usize)> {
+    match range {
+        Some((start, end, BitUnit::Byte)) => {
+            let (start, end) = normalize_range(start, end, data.len())?;
+            Some((start * 8, end * 8 + 7))
+        }
+        Some((start, end, BitUnit::Bit)) => normalize_range(start, end, data.len() * 8),
+        None => normalize_range(0, -1, data.len() * 8),
+    }
+}
+
+/// Mask selecting the bit at `offset` within its byte.
+fn mask(offset: usize) -> u8 {
+    0x80 >> (offset % 8)
+}
+
+impl Db {
+    /// Set the bit at `offset` in the string stored at `key` to `bit`. The
+    /// string is grown with zero bytes as needed. If `key` does not exist, a
+    /// new string is created.
+    ///
+    /// Returns the previous value of the bit.
+    pub(crate) fn setbit(&self, key: String, offset: usize, bit: bool) -> Result<bool, DbError> {
+        let mut state = self.lock();
+        let data = state
+            .entry_or_insert_with(key, || Value::String(Bytes::new()))
+            .data
+            .as_string_mut()?;
+
+        // `Bytes` is immutable, the string is copied to be updated.
+        let mut buf = data.to_vec();
+        let prev = set_bit(&mut buf, offset, bit);
+        *data = Bytes::from(buf);
+
+        Ok(prev)
+    }
+
+    /// Returns the value of the bit at `offset` in the string stored at `key`.
+    ///
+    /// Bits past the end of the string, or of a missing key, are `0`.
+    pub(crate) fn getbit(&self, key: &str, offset: usize) -> Result<bool, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(get_bit(entry.data.as_string()?, offset)),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the number of bits set to `1` in the string stored at `key`,
+    /// optionally limited to an inclusive `(start, end, unit)` range.
+    pub(crate) fn bitcount(
+        &self,
+        key: &str,
+        range: Option<(i64, i64, BitUnit)>,
+    ) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(count(entry.data.as_string()?, range)),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the offset of the first bit set to `bit` in the string stored
+    /// at `key`, optionally limited to the range starting at `start` and
+    /// ending at `end`, or `-1` if there is none.
+    ///
+    /// A missing key is considered an empty string padded with zeros.
+    pub(crate) fn bitpos(
+        &self,
+        key: &str,
+        bit: bool,
+        start: Option<i64>,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> Result<i64, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(position(entry.data.as_string()?, bit, start, end, unit)),
+            None if bit => Ok(-1),
+            None => Ok(0),
+        }
+    }
+
+    /// Combine the strings stored at `keys` with `op` and store the result at
+    /// `dest`, replacing any existing value. Missing keys are considered empty
+    /// strings, and `dest` is removed if the result is empty.
+    ///
+    /// Returns the length of the result.
+    pub(crate) fn bitop(
+        &self,
+        op: BitOperation,
+        dest: String,
+        keys: &[String],
+    ) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        // The sources are all read and the destination written under the same
+        // lock, so no other command observes the operation half done.
+        let mut sources = vec![];
+        for key in keys {
+            match state.entries.get(key) {
+                Some(entry) => sources.push(&entry.data.as_string()?[..]),
+                None => sources.push(&[][..]),
+            }
+        }
+
+        let result = combine(op, &sources);
+        let len = result.len();
+
+        state.remove(&dest);
+
+        if len > 0 {
+            state.entry_or_insert_with(dest, || Value::String(Bytes::from(result)));
+        }
+
+        Ok(len)
+    }
+
+    /// Apply the `BITFIELD` operations in `fields` to the string stored at
+    /// `key`, in order.
+    ///
+    /// The key is only created if at least one operation writes to it. Returns
+    /// one reply per operation, `None` for writes that failed on overflow.
+    pub(crate) fn bitfield(
+        &self,
+        key: String,
+        fields: &[Field],
+    ) -> Result<Vec<Option<i64>>, DbError> {
+        let mut state = self.lock();
+
+        let read_only = fields.iter().all(|field| matches!(field.op, FieldOp::Get));
+
+        if read_only {
+            let data: &[u8] = match state.entries.get(&key) {
+                Some(entry) => entry.data.as_string()?,
+                None => &[],
+            };
+
+            let replies = fields
+                .iter()
+                .map(|field| Some(get_field(data, field.offset, field.ty)))
+                .collect();
+
+            return Ok(replies);
+        }
+
+        let data = state
+            .entry_or_insert_with(key, || Value::String(Bytes::new()))
+            .data
+            .as_string_mut()?;
+
+        let mut buf = data.to_vec();
+        let replies = fields
+            .iter()
+            .map(|field| apply_field(&mut buf, field))
+            .collect();
+        *data = Bytes::from(buf);
+
+        Ok(replies)
+    }
+}
@@ -0,0 +1,140 @@
+//! Connected clients, as listed by `CLIENT LIST`.
+
+use super::RateLimiter;
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Notify;
+
+/// State of a connected client.
+#[derive(Debug)]
+pub(crate) struct ClientState {
+    /// Address of the client, as `ip:port`.
+    pub(crate) addr: String,
+
+    /// Name set with `CLIENT SETNAME`, if any.
+    pub(crate) name: Option<String>,
+
+    /// User the client is authenticated as, if any.
+    pub(crate) user: Option<String>,
+
+    /// Instant the client connected at.
+    connected_at: Instant,
+
+    /// Instant the client last sent a command at.
+    last_interaction: Instant,
+
+    /// Name of the last command sent by the client.
+    last_command: String,
+
+    /// Database selected by the client.
+    db: usize,
+
+    /// Port the client listens at, if it is a replica which gave it with
+    /// `REPLCONF listening-port`.
+    pub(crate) listening_port: Option<u16>,
+
+    /// Limits the commands of the client to `ratelimit-commands` and
+    /// `ratelimit-bytes`.
+    pub(crate) limiter: RateLimiter,
+
+    /// Notified when the client is killed with `CLIENT KILL`, its connection
+    /// being closed.
+    pub(crate) killed: Arc<Notify>,
+}
+
+impl ClientState {
+    pub(crate) fn new(addr: String, user: Option<String>) -> ClientState {
+        let now = Instant::now();
+        ClientState {
+            addr,
+            name: None,
+            user,
+            connected_at: now,
+            last_interaction: now,
+            last_command: "NULL".to_string(),
+            db: 0,
+            listening_port: None,
+            limiter: RateLimiter::default(),
+            killed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Record the command `name` sent by the client.
+    pub(crate) fn record(&mut self, name: &str) {
+        self.last_interaction = Instant::now();
+        self.last_command = name.to_string();
+    }
+
+    /// Record that the client selected the database `db`.
+    pub(crate) fn select(&mut self, db: usize) {
+        self.db = db;
+    }
+
+    /// Returns the host and port the client, a replica, listens at: the
+    /// host it connected from, and the port given with `REPLCONF
+    /// listening-port`, or the port it connected from.
+    pub(crate) fn replica_addr(&self) -> (String, u16) {
+        let (host, port) = match self.addr.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().unwrap_or(0)),
+            None => (&self.addr[..], 0),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        (host.to_string(), self.listening_port.unwrap_or(port))
+    }
+
+    /// Returns the line describing the client `id` in `CLIENT LIST`.
+    pub(crate) fn describe(&self, id: u64) -> String {
+        let now = Instant::now();
+        format!(
+            "id={} addr={} name={} age={} idle={} db={} cmd={} user={}\n",
+            id,
+            self.addr,
+            self.name.as_deref().unwrap_or(""),
+            now.duration_since(self.connected_at).as_secs(),
+            now.duration_since(self.last_interaction).as_secs(),
+            self.db,
+            self.last_command,
+            self.user.as_deref().unwrap_or(""),
+        )
+    }
+}
+
+/// What happens to the connections accepted while `maxclients` clients are
+/// connected, as set by `maxclients-policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MaxClientsPolicy {
+    /// The connection is closed right away, with an error.
+    Reject,
+
+    /// The connection is only accepted once another client disconnects,
+    /// waiting in the backlog of the listener in the meantime.
+    Queue,
+}
+
+impl MaxClientsPolicy {
+    /// Parse a policy from its name, returning `None` if it is unknown.
+    pub(crate) fn parse(value: &str) -> Option<MaxClientsPolicy> {
+        match &value.to_lowercase()[..] {
+            "reject" => Some(MaxClientsPolicy::Reject),
+            "queue" => Some(MaxClientsPolicy::Queue),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MaxClientsPolicy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaxClientsPolicy::Reject => "reject".fmt(fmt),
+            MaxClientsPolicy::Queue => "queue".fmt(fmt),
+        }
+    }
+}
+
+/// Returns `true` if `name` may be set with `CLIENT SETNAME`. Names are made
+/// of printable characters, other than spaces.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    name.bytes().all(|b| (b'!'..=b'~').contains(&b))
+}
@@ -0,0 +1,362 @@
+//! Configuration parameters of the server, read and modified at runtime with
+//! `CONFIG GET` and `CONFIG SET`.
+//!
+//! Each parameter is named as in `redis.conf`. Sizes accept the units of
+//! Redis: `k`, `m` and `g` multiply by powers of 1000, `kb`, `mb` and `gb` by
+//! powers of 1024.
+
+use super::{glob, EvictionPolicy, KeyspaceEvents, MaxClientsPolicy};
+
+/// Values of the parameters.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Config {
+    /// Number of databases, fixed when the server starts.
+    pub(crate) databases: usize,
+
+    /// Duration, in milliseconds, from which the latency of an event is
+    /// recorded by the latency monitor. `0` disables the latency monitor.
+    pub(crate) latency_monitor_threshold: u64,
+
+    /// Duration, in milliseconds, after which the commands of the other
+    /// clients are rejected with a `BUSY` error while a script runs, rather
+    /// than waiting for it to complete. `0` to always wait.
+    pub(crate) lua_time_limit: u64,
+
+    /// Maximum number of clients connected at the same time.
+    pub(crate) maxclients: u64,
+
+    /// What happens to the connections accepted while `maxclients` clients
+    /// are connected.
+    pub(crate) maxclients_policy: MaxClientsPolicy,
+
+    /// Maximum number of bytes the data set may use, `0` for no limit.
+    pub(crate) maxmemory: u64,
+
+    /// Which keys are evicted once the data set uses more than `maxmemory`.
+    pub(crate) maxmemory_policy: EvictionPolicy,
+
+    /// Number of keys of each database sampled to pick the key to evict.
+    pub(crate) maxmemory_samples: usize,
+
+    /// Seconds after which idle clients are disconnected, `0` to never
+    /// disconnect them.
+    pub(crate) timeout: u64,
+
+    /// Seconds a client connected over TCP may stay idle before TCP
+    /// keepalive probes are sent to check it is still alive, `0` to send no
+    /// probes.
+    pub(crate) tcp_keepalive: u64,
+
+    /// Whether `TCP_NODELAY` is set on the sockets of the clients connected
+    /// over TCP, so that the replies are sent right away.
+    pub(crate) tcp_nodelay: bool,
+
+    /// Classes of the keyspace events published.
+    pub(crate) notify_keyspace_events: KeyspaceEvents,
+
+    /// Whether only the clients connected from the loopback interface are
+    /// accepted while the default user requires no password and the server
+    /// listens on other interfaces.
+    pub(crate) protected_mode: bool,
+
+    /// Maximum length of a string value, in bytes.
+    pub(crate) proto_max_bulk_len: u64,
+
+    /// Maximum number of bytes of commands each client may send per second,
+    /// `0` for no limit.
+    pub(crate) ratelimit_bytes: u64,
+
+    /// Maximum number of commands each client may send per second, `0` for
+    /// no limit.
+    pub(crate) ratelimit_commands: u64,
+
+    /// Maximum number of bytes of the replication stream kept for the
+    /// replicas to resume from.
+    pub(crate) repl_backlog_size: u64,
+
+    /// Execution time, in microseconds, from which commands are recorded by
+    /// the slow log. Negative to disable the slow log, `0` to record every
+    /// command.
+    pub(crate) slowlog_log_slower_than: i64,
+
+    /// Maximum number of commands kept by the slow log.
+    pub(crate) slowlog_max_len: u64,
+}
+
+/// A parameter, along with the functions reading and parsing its value.
+struct Parameter {
+    name: &'static str,
+    get: fn(&Config) -> String,
+    /// `None` for the parameters which cannot be modified at runtime.
+    /// Returns `None` if the value is invalid.
+    set: Option<fn(&mut Config, &str) -> Option<()>>,
+}
+
+/// The parameters, sorted by name.
+const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        name: "databases",
+        get: |config| config.databases.to_string(),
+        set: None,
+    },
+    Parameter {
+        name: "latency-monitor-threshold",
+        get: |config| config.latency_monitor_threshold.to_string(),
+        set: Some(|config, value| {
+            config.latency_monitor_threshold = value.parse().ok()?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "lua-time-limit",
+        get: |config| config.lua_time_limit.to_string(),
+        set: Some(|config, value| {
+            config.lua_time_limit = value.parse().ok()?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "maxclients",
+        get: |config| config.maxclients.to_string(),
+        set: Some(|config, value| {
+            config.maxclients = value.parse().ok().filter(|maxclients| *maxclients > 0)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "maxclients-policy",
+        get: |config| config.maxclients_policy.to_string(),
+        set: Some(|config, value| {
+            config.maxclients_policy = MaxClientsPolicy::parse(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "maxmemory",
+        get: |config| config.maxmemory.to_string(),
+        set: Some(|config, value| {
+            config.maxmemory = parse_memory(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "maxmemory-policy",
+        get: |config| config.maxmemory_policy.to_string(),
+        set: Some(|config, value| {
+            config.maxmemory_policy = EvictionPolicy::parse(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "maxmemory-samples",
+        get: |config| config.maxmemory_samples.to_string(),
+        set: Some(|config, value| {
+            config.maxmemory_samples = value.parse().ok().filter(|samples| *samples > 0)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "notify-keyspace-events",
+        get: |config| config.notify_keyspace_events.to_string(),
+        set: Some(|config, value| {
+            config.notify_keyspace_events = KeyspaceEvents::parse(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "protected-mode",
+        get: |config| format_yes_no(config.protected_mode),
+        set: Some(|config, value| {
+            config.protected_mode = parse_yes_no(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "proto-max-bulk-len",
+        get: |config| config.proto_max_bulk_len.to_string(),
+        set: Some(|config, value| {
+            config.proto_max_bulk_len = parse_memory(value).filter(|len| *len >= 1024 * 1024)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "ratelimit-bytes",
+        get: |config| config.ratelimit_bytes.to_string(),
+        set: Some(|config, value| {
+            config.ratelimit_bytes = parse_memory(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "ratelimit-commands",
+        get: |config| config.ratelimit_commands.to_string(),
+        set: Some(|config, value| {
+            config.ratelimit_commands = value.parse().ok()?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "repl-backlog-size",
+        get: |config| config.repl_backlog_size.to_string(),
+        set: Some(|config, value| {
+            config.repl_backlog_size = parse_memory(value).filter(|size| *size > 0)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "slowlog-log-slower-than",
+        get: |config| config.slowlog_log_slower_than.to_string(),
+        set: Some(|config, value| {
+            config.slowlog_log_slower_than = value.parse().ok()?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "slowlog-max-len",
+        get: |config| config.slowlog_max_len.to_string(),
+        set: Some(|config, value| {
+            config.slowlog_max_len = value.parse().ok()?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "tcp-keepalive",
+        get: |config| config.tcp_keepalive.to_string(),
+        set: Some(|config, value| {
+            config.tcp_keepalive = value.parse().ok()?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "tcp-nodelay",
+        get: |config| format_yes_no(config.tcp_nodelay),
+        set: Some(|config, value| {
+            config.tcp_nodelay = parse_yes_no(value)?;
+            Some(())
+        }),
+    },
+    Parameter {
+        name: "timeout",
+        get: |config| config.timeout.to_string(),
+        set: Some(|config, value| {
+            config.timeout = value.parse().ok()?;
+            Some(())
+        }),
+    },
+];
+
+impl Config {
+    /// Create the configuration of a server with `databases` databases, the
+    /// other parameters holding their default value.
+    pub(crate) fn new(databases: usize) -> Config {
+        Config {
+            databases,
+            latency_monitor_threshold: 0,
+            lua_time_limit: 5000,
+            maxclients: 10_000,
+            maxclients_policy: MaxClientsPolicy::Reject,
+            maxmemory: 0,
+            maxmemory_policy: EvictionPolicy::NoEviction,
+            maxmemory_samples: 5,
+            timeout: 0,
+            tcp_keepalive: 300,
+            tcp_nodelay: true,
+            notify_keyspace_events: KeyspaceEvents::default(),
+            protected_mode: true,
+            proto_max_bulk_len: 512 * 1024 * 1024,
+            ratelimit_bytes: 0,
+            ratelimit_commands: 0,
+            repl_backlog_size: 1024 * 1024,
+            slowlog_log_slower_than: 10_000,
+            slowlog_max_len: 128,
+        }
+    }
+
+    /// Returns the parameters whose name matches the glob `pattern`, along
+    /// with their value.
+    pub(crate) fn get(&self, pattern: &str) -> Vec<(String, String)> {
+        let pattern = pattern.to_lowercase();
+
+        PARAMETERS
+            .iter()
+            .filter(|parameter| glob::matches(pattern.as_bytes(), parameter.name.as_bytes()))
+            .map(|parameter| (parameter.name.to_string(), (parameter.get)(self)))
+            .collect()
+    }
+
+    /// Set the parameter `name` to `value`.
+    ///
+    /// Returns `Err` if there is no such parameter, if it cannot be modified
+    /// or if `value` is invalid.
+    pub(crate) fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let parameter = find(name).ok_or_else(|| {
+            format!(
+                "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                name
+            )
+        })?;
+
+        let set = parameter.set.ok_or_else(|| {
+            format!(
+                "ERR CONFIG SET failed (possibly related to argument '{}') - can't set \
+                 immutable config",
+                parameter.name
+            )
+        })?;
+
+        set(self, value)
+            .ok_or_else(|| format!("ERR Invalid argument '{}' for CONFIG SET '{}'", value, name))
+    }
+
+    /// Returns `Err` with the reason the parameter `name` may not be set to
+    /// `value`, as `set` does: there is no such parameter, it cannot be
+    /// modified, or `value` is invalid.
+    pub(crate) fn check(name: &str, value: &str) -> Result<(), &'static str> {
+        let parameter = find(name).ok_or("unknown parameter")?;
+        let set = parameter.set.ok_or("immutable parameter")?;
+        set(&mut Config::new(1), value).ok_or("invalid value")
+    }
+}
+
+/// Returns the parameter `name`, case insensitively.
+fn find(name: &str) -> Option<&'static Parameter> {
+    PARAMETERS
+        .iter()
+        .find(|parameter| parameter.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns `yes` or `no`, as boolean parameters are written.
+fn format_yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+/// Parse `yes` or `no` into a boolean.
+fn parse_yes_no(value: &str) -> Option<bool> {
+    match &value.to_lowercase()[..] {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a number of bytes, optionally followed by a unit.
+fn parse_memory(value: &str) -> Option<u64> {
+    let value = value.to_lowercase();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+
+    let multiplier = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
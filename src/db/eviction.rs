@@ -0,0 +1,154 @@
+//! Eviction of keys once the data set uses more than the `maxmemory`
+//! parameter.
+//!
+//! As in Redis, the keys to evict are not picked among all the keys: a few of
+//! them are sampled at random, as many as the `maxmemory-samples` parameter,
+//! and the best candidate according to the `maxmemory-policy` parameter is
+//! evicted.
+
+use std::fmt;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Initial access frequency of a key, so that new keys are not evicted right
+/// away by the LFU policies.
+pub(crate) const LFU_INIT: u8 = 5;
+
+/// How quickly the access frequency saturates: the frequency `f` is
+/// incremented with a probability of `1 / ((f - LFU_INIT) * LFU_LOG_FACTOR +
+/// 1)`, reaching its maximum after about a million accesses.
+const LFU_LOG_FACTOR: f64 = 10.0;
+
+/// The access frequency of a key is decremented for each period it was not
+/// accessed.
+const LFU_DECAY_PERIOD: Duration = Duration::from_secs(60);
+
+/// Which keys are evicted, as set by `maxmemory-policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EvictionPolicy {
+    /// Keys are never evicted, the commands which may use more memory are
+    /// rejected instead.
+    NoEviction,
+
+    /// The least recently used keys are evicted.
+    AllKeysLru,
+
+    /// The least recently used keys with an expiration are evicted.
+    VolatileLru,
+
+    /// The least frequently used keys are evicted.
+    AllKeysLfu,
+
+    /// The least frequently used keys with an expiration are evicted.
+    VolatileLfu,
+
+    /// Random keys are evicted.
+    AllKeysRandom,
+
+    /// Random keys with an expiration are evicted.
+    VolatileRandom,
+
+    /// The keys with an expiration expiring first are evicted.
+    VolatileTtl,
+}
+
+/// The policies, along with their name.
+const POLICIES: &[(EvictionPolicy, &str)] = &[
+    (EvictionPolicy::NoEviction, "noeviction"),
+    (EvictionPolicy::AllKeysLru, "allkeys-lru"),
+    (EvictionPolicy::VolatileLru, "volatile-lru"),
+    (EvictionPolicy::AllKeysLfu, "allkeys-lfu"),
+    (EvictionPolicy::VolatileLfu, "volatile-lfu"),
+    (EvictionPolicy::AllKeysRandom, "allkeys-random"),
+    (EvictionPolicy::VolatileRandom, "volatile-random"),
+    (EvictionPolicy::VolatileTtl, "volatile-ttl"),
+];
+
+impl EvictionPolicy {
+    /// Parse a policy from its name, returning `None` if it is unknown.
+    pub(crate) fn parse(value: &str) -> Option<EvictionPolicy> {
+        POLICIES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(value))
+            .map(|(policy, _)| *policy)
+    }
+
+    /// Returns `true` if only the keys with an expiration are evicted.
+    pub(crate) fn is_volatile(self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+
+    /// Returns how much a key should be evicted, the key with the highest
+    /// rank among the sampled ones being evicted.
+    ///
+    /// `last_access` and `frequency` are the instant the key was last
+    /// accessed and its access frequency, `expires_at` its expiration.
+    pub(crate) fn rank(
+        self,
+        now: Instant,
+        last_access: Instant,
+        frequency: u8,
+        expires_at: Option<Instant>,
+    ) -> u128 {
+        match self {
+            EvictionPolicy::AllKeysLru | EvictionPolicy::VolatileLru => {
+                now.saturating_duration_since(last_access).as_micros()
+            }
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => {
+                (u8::MAX - decayed_frequency(now, last_access, frequency)) as u128
+            }
+            EvictionPolicy::VolatileTtl => match expires_at {
+                Some(when) => u128::MAX - when.saturating_duration_since(now).as_micros(),
+                None => 0,
+            },
+            EvictionPolicy::NoEviction
+            | EvictionPolicy::AllKeysRandom
+            | EvictionPolicy::VolatileRandom => 0,
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (_, name) = POLICIES.iter().find(|(policy, _)| policy == self).unwrap();
+        name.fmt(fmt)
+    }
+}
+
+/// Returns the access frequency of a key accessed at `now`, whose frequency
+/// was `frequency` when last accessed at `last_access`.
+///
+/// The frequency is decayed first, then incremented with a probability
+/// decreasing as it grows, `random` being a random number.
+pub(crate) fn accessed_frequency(
+    now: Instant,
+    last_access: Instant,
+    frequency: u8,
+    random: u64,
+) -> u8 {
+    let frequency = decayed_frequency(now, last_access, frequency);
+    if frequency == u8::MAX {
+        return frequency;
+    }
+
+    let base = frequency.saturating_sub(LFU_INIT) as f64;
+    let probability = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+    if (random as f64) < probability * u64::MAX as f64 {
+        frequency + 1
+    } else {
+        frequency
+    }
+}
+
+/// Returns the access frequency of a key at `now`, decremented for each
+/// period elapsed since it was last accessed.
+fn decayed_frequency(now: Instant, last_access: Instant, frequency: u8) -> u8 {
+    let periods = now.saturating_duration_since(last_access).as_secs() / LFU_DECAY_PERIOD.as_secs();
+    frequency.saturating_sub(periods.min(u8::MAX as u64) as u8)
+}
@@ -0,0 +1,249 @@
+//! Index of the expirations of the keys of a database, as a hierarchical
+//! timer wheel.
+//!
+//! Time is divided in ticks of a millisecond since the wheel was created.
+//! Each level of the wheel has `SLOTS` slots, a slot of level `n` spanning
+//! `SLOTS^n` ticks, so that an expiration is inserted in constant time in the
+//! slot of the lowest level covering it. As time advances, the slots of the
+//! higher levels are emptied into the lower ones, until the expirations are
+//! due. Only the due expirations, the ones of the elapsed ticks, are kept
+//! sorted.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Number of bits of a tick indexing the slots of each level.
+const SLOT_BITS: u32 = 6;
+
+/// Number of slots of each level.
+const SLOTS: usize = 1 << SLOT_BITS;
+
+/// The expirations of a slot, indexed by the identifier of their entry.
+type Slot = HashMap<u64, (Instant, String)>;
+
+/// The expirations of the keys, by the instant at which they expire.
+///
+/// While highly unlikely, more than one expiration may be set for the same
+/// instant. Because of this, each expiration is identified by the instant
+/// and the unique identifier (`u64`) of the entry of the key.
+#[derive(Debug)]
+pub(crate) struct Expirations {
+    /// Instant of the tick `0`. Expirations before it are due at tick `0`.
+    start: Instant,
+
+    /// Last tick elapsed: the expirations of this tick and of the previous
+    /// ones are `due`, the other ones are in the levels.
+    elapsed: u64,
+
+    /// The levels of the wheel, added as the expirations need them.
+    levels: Vec<Level>,
+
+    /// The expirations of the elapsed ticks, sorted by when they expire.
+    /// Some may be a fraction of a tick in the future.
+    due: BTreeMap<(Instant, u64), String>,
+
+    /// Number of expirations, due or not.
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Level {
+    slots: Vec<Slot>,
+
+    /// Bit field of the slots holding expirations.
+    occupied: u64,
+}
+
+impl Expirations {
+    pub(crate) fn new() -> Expirations {
+        Expirations {
+            start: Instant::now(),
+            elapsed: 0,
+            levels: vec![],
+            due: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of expirations.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Track the expiration at `when` of `key`, whose entry has the
+    /// identifier `id`.
+    pub(crate) fn insert(&mut self, when: Instant, id: u64, key: String) {
+        self.len += 1;
+        self.place(when, id, key);
+    }
+
+    /// Stop tracking the expiration at `when` of the entry `id`, returning
+    /// its key.
+    pub(crate) fn remove(&mut self, when: Instant, id: u64) -> Option<String> {
+        let tick = self.tick(when);
+        let key = if tick <= self.elapsed {
+            self.due.remove(&(when, id))
+        } else {
+            // The expiration may be in any level the wheel advanced it to,
+            // but always in the slot covering its tick.
+            self.levels.iter_mut().enumerate().find_map(|(n, level)| {
+                let slot = slot_for(n, tick);
+                let (_, key) = level.slots[slot].remove(&id)?;
+                if level.slots[slot].is_empty() {
+                    level.occupied &= !(1 << slot);
+                }
+                Some(key)
+            })
+        };
+
+        if key.is_some() {
+            self.len -= 1;
+        }
+        key
+    }
+
+    /// Stop tracking the first expiration due at or before `now`, returning
+    /// its key.
+    pub(crate) fn pop_expired(&mut self, now: Instant) -> Option<String> {
+        self.advance(now);
+
+        let (&(when, id), _) = self.due.iter().next()?;
+        if when > now {
+            return None;
+        }
+
+        let key = self.due.remove(&(when, id))?;
+        self.len -= 1;
+        Some(key)
+    }
+
+    /// Returns the number of expirations due at or before `now`.
+    pub(crate) fn count_expired(&mut self, now: Instant) -> usize {
+        self.advance(now);
+        self.due.range(..=(now, u64::MAX)).count()
+    }
+
+    /// Returns the instant at which the next key expires, or an earlier
+    /// instant: the start of the slot holding it, if it is not due yet.
+    ///
+    /// Waiting until this instant, then advancing the wheel, either finds
+    /// the key expired or a closer instant.
+    pub(crate) fn next_expiration(&self) -> Option<Instant> {
+        if let Some((&(when, _), _)) = self.due.iter().next() {
+            return Some(when);
+        }
+
+        let (_, _, tick) = self.next_slot()?;
+        Some(self.start + Duration::from_millis(tick))
+    }
+
+    /// Returns the keys with an expiration, roughly in the order they
+    /// expire.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &String> {
+        self.due
+            .values()
+            .chain(self.levels.iter().flat_map(|level| {
+                level
+                    .slots
+                    .iter()
+                    .flat_map(|slot| slot.values().map(|(_, key)| key))
+            }))
+    }
+
+    /// Returns the tick at which an expiration at `when` is due.
+    fn tick(&self, when: Instant) -> u64 {
+        when.saturating_duration_since(self.start).as_millis() as u64
+    }
+
+    /// Insert the expiration in the due ones or in the slot covering it.
+    fn place(&mut self, when: Instant, id: u64, key: String) {
+        let tick = self.tick(when);
+        if tick <= self.elapsed {
+            self.due.insert((when, id), key);
+            return;
+        }
+
+        let n = level_for(self.elapsed, tick);
+        while self.levels.len() <= n {
+            self.levels.push(Level {
+                slots: (0..SLOTS).map(|_| Slot::new()).collect(),
+                occupied: 0,
+            });
+        }
+
+        let slot = slot_for(n, tick);
+        let level = &mut self.levels[n];
+        level.slots[slot].insert(id, (when, key));
+        level.occupied |= 1 << slot;
+    }
+
+    /// Returns the level and the slot holding the expirations due next, and
+    /// the first tick it covers.
+    ///
+    /// The slots of a level all come after the ones of the lower levels, so
+    /// the first occupied slot of the lowest occupied level is the next one.
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        let (n, level) = self
+            .levels
+            .iter()
+            .enumerate()
+            .find(|(_, level)| level.occupied != 0)?;
+
+        let shift = n as u32 * SLOT_BITS;
+        let current = slot_for(n, self.elapsed);
+        let slot = (current
+            + level.occupied.rotate_right(current as u32).trailing_zeros() as usize)
+            % SLOTS;
+
+        // The ticks covered by the level around the elapsed one.
+        let span = 1u64.checked_shl(shift + SLOT_BITS).unwrap_or(0);
+        let level_start = self.elapsed & !span.wrapping_sub(1);
+        let tick = level_start + ((slot as u64) << shift);
+
+        Some((n, slot, tick.max(self.elapsed)))
+    }
+
+    /// Advance the wheel to the tick of `now`, moving the expirations of the
+    /// slots elapsed to the lower levels, or to the due ones.
+    fn advance(&mut self, now: Instant) {
+        let now = self.tick(now);
+
+        while let Some((n, slot, tick)) = self.next_slot() {
+            if tick > now {
+                break;
+            }
+
+            let level = &mut self.levels[n];
+            level.occupied &= !(1 << slot);
+            let expirations = std::mem::take(&mut level.slots[slot]);
+
+            self.elapsed = tick;
+            for (id, (when, key)) in expirations {
+                self.place(when, id, key);
+            }
+        }
+
+        self.elapsed = self.elapsed.max(now);
+    }
+}
+
+impl Default for Expirations {
+    fn default() -> Expirations {
+        Expirations::new()
+    }
+}
+
+/// Returns the level holding an expiration at `tick`, the wheel having
+/// elapsed up to `elapsed`: the level of the most significant bit they
+/// differ in.
+fn level_for(elapsed: u64, tick: u64) -> usize {
+    let significant = 63 - ((elapsed ^ tick) | (SLOTS as u64 - 1)).leading_zeros();
+    (significant / SLOT_BITS) as usize
+}
+
+/// Returns the slot of level `n` covering `tick`.
+fn slot_for(n: usize, tick: u64) -> usize {
+    let shift = n as u32 * SLOT_BITS;
+    (tick.checked_shr(shift).unwrap_or(0) & (SLOTS as u64 - 1)) as usize
+}
@@ -0,0 +1,263 @@
+//! Geospatial indexes, stored as sorted sets.
+//!
+//! As in Redis, the position of a member is encoded as a 52 bits geohash
+//! which is used as its score. The longitude and the latitude are each
+//! quantized to 26 bits and their bits are interleaved, so that members close
+//! to each other usually have close scores. Distances are computed with the
+//! haversine formula, using the same Earth radius as Redis.
+
+use super::{Db, DbError};
+
+use bytes::Bytes;
+
+/// Lowest latitude that can be indexed. Latitudes are limited to the range
+/// covered by the Web Mercator projection.
+const LAT_MIN: f64 = -85.05112878;
+
+/// Highest latitude that can be indexed.
+const LAT_MAX: f64 = 85.05112878;
+
+/// Lowest longitude that can be indexed.
+const LON_MIN: f64 = -180.0;
+
+/// Highest longitude that can be indexed.
+const LON_MAX: f64 = 180.0;
+
+/// Number of bits each coordinate is quantized to.
+const STEP: u32 = 26;
+
+/// Radius of the Earth, in meters.
+const EARTH_RADIUS: f64 = 6372797.560856;
+
+/// Member found by `GEOSEARCH`, along with its distance in meters to the
+/// origin and its `(longitude, latitude)` position.
+pub(crate) type GeoMatch = (Bytes, f64, (f64, f64));
+
+/// Unit in which distances are expressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoUnit {
+    /// Meters. This is the default.
+    M,
+
+    /// Kilometers.
+    Km,
+
+    /// Feet.
+    Ft,
+
+    /// Miles.
+    Mi,
+}
+
+/// Point from which `GEOSEARCH` searches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    /// The position of a member of the index.
+    Member(Bytes),
+
+    /// A longitude and a latitude.
+    LonLat(f64, f64),
+}
+
+/// Area around the origin in which `GEOSEARCH` searches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    /// A circle of the given radius.
+    Radius(f64, GeoUnit),
+
+    /// A box of the given width and height, aligned with the meridians and
+    /// parallels.
+    Box(f64, f64, GeoUnit),
+}
+
+impl GeoUnit {
+    /// Returns the number of meters in one unit.
+    pub(crate) fn meters(self) -> f64 {
+        match self {
+            GeoUnit::M => 1.0,
+            GeoUnit::Km => 1000.0,
+            GeoUnit::Ft => 0.3048,
+            GeoUnit::Mi => 1609.34,
+        }
+    }
+}
+
+impl GeoShape {
+    /// Returns the unit the shape is expressed in.
+    pub(crate) fn unit(&self) -> GeoUnit {
+        match *self {
+            GeoShape::Radius(_, unit) | GeoShape::Box(_, _, unit) => unit,
+        }
+    }
+
+    /// Returns the distance in meters between `origin` and `point`, both
+    /// `(longitude, latitude)` pairs, or `None` if `point` is outside of the
+    /// shape centered on `origin`.
+    pub(crate) fn distance(&self, origin: (f64, f64), point: (f64, f64)) -> Option<f64> {
+        match *self {
+            GeoShape::Radius(radius, unit) => {
+                let distance = distance(origin, point);
+                if distance > radius * unit.meters() {
+                    return None;
+                }
+                Some(distance)
+            }
+            GeoShape::Box(width, height, unit) => {
+                // The latitude distance is the cheapest to compute, it is
+                // checked first.
+                let lat_distance =
+                    EARTH_RADIUS * (point.1.to_radians() - origin.1.to_radians()).abs();
+                if lat_distance > height * unit.meters() / 2.0 {
+                    return None;
+                }
+
+                let lon_distance = distance(origin, (point.0, origin.1));
+                if lon_distance > width * unit.meters() / 2.0 {
+                    return None;
+                }
+
+                Some(distance(origin, point))
+            }
+        }
+    }
+}
+
+/// Returns `true` if `lon` and `lat` can be indexed.
+pub(crate) fn is_valid(lon: f64, lat: f64) -> bool {
+    (LON_MIN..=LON_MAX).contains(&lon) && (LAT_MIN..=LAT_MAX).contains(&lat)
+}
+
+/// Encode a position, which must be valid, into the score of a sorted set
+/// member.
+pub(crate) fn encode(lon: f64, lat: f64) -> f64 {
+    let scale = (1u64 << STEP) as f64;
+    let max = (1 << STEP) - 1;
+
+    // The highest latitude and longitude would be quantized past the last
+    // cell.
+    let lat_offset = (((lat - LAT_MIN) / (LAT_MAX - LAT_MIN) * scale) as u32).min(max);
+    let lon_offset = (((lon - LON_MIN) / (LON_MAX - LON_MIN) * scale) as u32).min(max);
+
+    // Latitude bits take the even positions, longitude bits the odd ones.
+    (spread(lat_offset) | spread(lon_offset) << 1) as f64
+}
+
+/// Decode the score of a sorted set member into the `(longitude, latitude)`
+/// of the center of the geohash cell.
+pub(crate) fn decode(score: f64) -> (f64, f64) {
+    let bits = score as u64;
+    let lat_offset = squash(bits) as f64;
+    let lon_offset = squash(bits >> 1) as f64;
+    let scale = (1u64 << STEP) as f64;
+
+    let lat_min = LAT_MIN + lat_offset / scale * (LAT_MAX - LAT_MIN);
+    let lat_max = LAT_MIN + (lat_offset + 1.0) / scale * (LAT_MAX - LAT_MIN);
+    let lon_min = LON_MIN + lon_offset / scale * (LON_MAX - LON_MIN);
+    let lon_max = LON_MIN + (lon_offset + 1.0) / scale * (LON_MAX - LON_MIN);
+
+    let lon = ((lon_min + lon_max) / 2.0).clamp(LON_MIN, LON_MAX);
+    let lat = ((lat_min + lat_max) / 2.0).clamp(LAT_MIN, LAT_MAX);
+    (lon, lat)
+}
+
+/// Returns the distance in meters between two `(longitude, latitude)` pairs.
+pub(crate) fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (from.0.to_radians(), from.1.to_radians());
+    let (lon2, lat2) = (to.0.to_radians(), to.1.to_radians());
+
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2 - lon1) / 2.0).sin();
+    2.0 * EARTH_RADIUS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+/// Spread the bits of `value` to the even positions of a `u64`.
+fn spread(value: u32) -> u64 {
+    (0..STEP).fold(0, |bits, i| bits | ((value as u64 >> i) & 1) << (2 * i))
+}
+
+/// Gather the bits at the even positions of `bits`, the inverse of `spread`.
+fn squash(bits: u64) -> u32 {
+    (0..STEP).fold(0, |value, i| value | (((bits >> (2 * i)) & 1) as u32) << i)
+}
+
+impl Db {
+    /// Returns the `(longitude, latitude)` position of each of `members` in
+    /// the geospatial index stored at `key`, `None` for members that are not
+    /// in the index.
+    pub(crate) fn geopos(
+        &self,
+        key: &str,
+        members: &[Bytes],
+    ) -> Result<Vec<Option<(f64, f64)>>, DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok(vec![None; members.len()]),
+        };
+
+        Ok(members
+            .iter()
+            .map(|member| zset.score(member).map(decode))
+            .collect())
+    }
+
+    /// Returns the distance in meters between `from` and `to` in the
+    /// geospatial index stored at `key`, or `None` if one of them is not in
+    /// the index.
+    pub(crate) fn geodist(
+        &self,
+        key: &str,
+        from: &[u8],
+        to: &[u8],
+    ) -> Result<Option<f64>, DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok(None),
+        };
+
+        match (zset.score(from), zset.score(to)) {
+            (Some(from), Some(to)) => Ok(Some(distance(decode(from), decode(to)))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the members of the geospatial index stored at `key` within
+    /// `shape` around `origin`, along with their distance in meters to
+    /// `origin` and their `(longitude, latitude)` position.
+    ///
+    /// Members are returned in index order. Redis only looks at the geohash
+    /// cells covering the shape, every member is checked here instead.
+    pub(crate) fn geosearch(
+        &self,
+        key: &str,
+        origin: &GeoOrigin,
+        shape: GeoShape,
+    ) -> Result<Vec<GeoMatch>, DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok(vec![]),
+        };
+
+        let origin = match origin {
+            GeoOrigin::Member(member) => match zset.score(member) {
+                Some(score) => decode(score),
+                None => return Err(DbError::NoGeoMember),
+            },
+            GeoOrigin::LonLat(lon, lat) => (*lon, *lat),
+        };
+
+        Ok(zset
+            .iter()
+            .filter_map(|(member, score)| {
+                let position = decode(score);
+                let distance = shape.distance(origin, position)?;
+                Some((member.clone(), distance, position))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,112 @@
+//! Glob-style pattern matching, as used by `KEYS`.
+//!
+//! The supported syntax is the one Redis supports:
+//!
+//! * `?` matches any single byte.
+//! * `*` matches any sequence of bytes, including an empty one.
+//! * `[abc]` matches one of the listed bytes, `[^abc]` any byte not listed
+//!   and `[a-z]` any byte in the range.
+//! * `\` escapes the next byte, so that `\*` matches a literal `*`.
+//!
+//! Matching is done on bytes, patterns that are not valid UTF-8 work as well.
+
+/// Returns `true` if `string` matches the glob `pattern`.
+pub(crate) fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    // Position in `pattern` right after the last `*` seen, and the position in
+    // `string` that `*` is currently expected to match up to. When matching
+    // fails afterwards, the `*` is made to consume one more byte instead of
+    // backtracking through every possibility.
+    let mut star: Option<(usize, usize)> = None;
+
+    let mut p = 0;
+    let mut s = 0;
+
+    while s < string.len() {
+        if let Some(next) = match_one(pattern, p, string[s]) {
+            p = next;
+            s += 1;
+            continue;
+        }
+
+        if pattern.get(p) == Some(&b'*') {
+            // Collapse consecutive stars, they match the same strings as one.
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+            star = Some((p, s));
+            continue;
+        }
+
+        match star {
+            Some((after, matched)) => {
+                p = after;
+                s = matched + 1;
+                star = Some((after, s));
+            }
+            None => return false,
+        }
+    }
+
+    // The string is consumed, only stars may be left in the pattern.
+    pattern[p..].iter().all(|byte| *byte == b'*')
+}
+
+/// Matches the single byte `byte` against the pattern element at `p`.
+///
+/// Returns the position of the next pattern element on success. Stars are
+/// never matched here, they are handled by `matches`.
+fn match_one(pattern: &[u8], p: usize, byte: u8) -> Option<usize> {
+    match *pattern.get(p)? {
+        b'*' => None,
+        b'?' => Some(p + 1),
+        b'[' => match_class(pattern, p + 1, byte),
+        b'\\' if p + 1 < pattern.len() => {
+            if pattern[p + 1] == byte {
+                Some(p + 2)
+            } else {
+                None
+            }
+        }
+        literal if literal == byte => Some(p + 1),
+        _ => None,
+    }
+}
+
+/// Matches `byte` against the character class starting at `p`, right after
+/// the opening `[`.
+///
+/// An unterminated class extends to the end of the pattern.
+fn match_class(pattern: &[u8], mut p: usize, byte: u8) -> Option<usize> {
+    let negate = pattern.get(p) == Some(&b'^');
+    if negate {
+        p += 1;
+    }
+
+    let mut found = false;
+
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            found |= pattern[p + 1] == byte;
+            p += 2;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' && pattern[p + 2] != b']' {
+            let (start, end) = (pattern[p], pattern[p + 2]);
+            let (low, high) = if start <= end {
+                (start, end)
+            } else {
+                (end, start)
+            };
+            found |= low <= byte && byte <= high;
+            p += 3;
+        } else {
+            found |= pattern[p] == byte;
+            p += 1;
+        }
+    }
+
+    if found != negate {
+        // Skip the closing `]`, if any.
+        Some((p + 1).min(pattern.len()))
+    } else {
+        None
+    }
+}
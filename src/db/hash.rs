@@ -0,0 +1,246 @@
+//! Commands on hash values.
+
+use super::{
+    parse_float, parse_integer, random_sample, scan_collection, Db, DbError, RandomSample, Value,
+};
+
+use bytes::Bytes;
+use std::collections::HashMap;
+
+impl Db {
+    /// Set `fields` in the hash stored at `key`. If `key` does not exist, a new
+    /// hash is created.
+    ///
+    /// Returns the number of fields that were added. Fields that already
+    /// existed in the hash and had their value updated are not counted.
+    pub(crate) fn hset(&self, key: String, fields: Vec<(String, Bytes)>) -> Result<usize, DbError> {
+        let mut state = self.lock();
+        let hash = state
+            .entry_or_insert_with(key, || Value::Hash(HashMap::new()))
+            .data
+            .as_hash_mut()?;
+
+        let mut added = 0;
+
+        for (field, value) in fields {
+            if hash.insert(field, value).is_none() {
+                added += 1;
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Set `field` of the hash stored at `key` to `value`, only if the field
+    /// does not exist yet. If `key` does not exist, a new hash is created.
+    ///
+    /// Returns `true` if the field was set.
+    pub(crate) fn hsetnx(&self, key: String, field: String, value: Bytes) -> Result<bool, DbError> {
+        use std::collections::hash_map::Entry;
+
+        let mut state = self.lock();
+        let hash = state
+            .entry_or_insert_with(key, || Value::Hash(HashMap::new()))
+            .data
+            .as_hash_mut()?;
+
+        match hash.entry(field) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Increment the integer stored in `field` of the hash stored at `key` by
+    /// `delta`. Missing keys and fields are considered to hold `0`.
+    ///
+    /// Returns the value after the increment.
+    pub(crate) fn hincr_by(&self, key: String, field: String, delta: i64) -> Result<i64, DbError> {
+        let mut state = self.lock();
+
+        let current = match state.entries.get(&key) {
+            Some(entry) => match entry.data.as_hash()?.get(&field) {
+                Some(value) => parse_integer(value).ok_or(DbError::HashNotInteger)?,
+                None => 0,
+            },
+            None => 0,
+        };
+
+        let value = current.checked_add(delta).ok_or(DbError::IncrOverflow)?;
+
+        state
+            .entry_or_insert_with(key, || Value::Hash(HashMap::new()))
+            .data
+            .as_hash_mut()?
+            .insert(field, Bytes::from(value.to_string().into_bytes()));
+
+        Ok(value)
+    }
+
+    /// Increment the float stored in `field` of the hash stored at `key` by
+    /// `delta`. Missing keys and fields are considered to hold `0`.
+    ///
+    /// Returns the value after the increment.
+    pub(crate) fn hincr_by_float(
+        &self,
+        key: String,
+        field: String,
+        delta: f64,
+    ) -> Result<f64, DbError> {
+        let mut state = self.lock();
+
+        let current = match state.entries.get(&key) {
+            Some(entry) => match entry.data.as_hash()?.get(&field) {
+                Some(value) => parse_float(value).ok_or(DbError::HashNotFloat)?,
+                None => 0.0,
+            },
+            None => 0.0,
+        };
+
+        let value = current + delta;
+        if !value.is_finite() {
+            return Err(DbError::NanOrInfinity);
+        }
+
+        state
+            .entry_or_insert_with(key, || Value::Hash(HashMap::new()))
+            .data
+            .as_hash_mut()?
+            .insert(field, Bytes::from(value.to_string().into_bytes()));
+
+        Ok(value)
+    }
+
+    /// Get the value associated with `field` in the hash stored at `key`.
+    ///
+    /// Returns `None` if either the key or the field does not exist.
+    pub(crate) fn hget(&self, key: &str, field: &str) -> Result<Option<Bytes>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_hash()?.get(field).cloned()),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the values associated with each of `fields` in the hash stored at
+    /// `key`. The values are returned in the same order as the fields were
+    /// requested. Fields that do not exist are `None`.
+    pub(crate) fn hmget(
+        &self,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<Bytes>>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => {
+                let hash = entry.data.as_hash()?;
+                Ok(fields
+                    .iter()
+                    .map(|field| hash.get(field).cloned())
+                    .collect())
+            }
+            None => Ok(vec![None; fields.len()]),
+        }
+    }
+
+    /// Get all fields and values of the hash stored at `key`.
+    ///
+    /// An empty list is returned if `key` does not exist.
+    pub(crate) fn hgetall(&self, key: &str) -> Result<Vec<(String, Bytes)>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry
+                .data
+                .as_hash()?
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns `count` fields, along with their values, picked at random from
+    /// the hash stored at `key`.
+    ///
+    /// As with `srandmember`, fields are distinct with a positive `count` and
+    /// may repeat with a negative `count`.
+    pub(crate) fn hrandfield(
+        &self,
+        key: &str,
+        count: i64,
+    ) -> Result<RandomSample<(String, Bytes)>, DbError> {
+        let state = self.lock();
+
+        let fields = match state.entries.get(key) {
+            Some(entry) => entry
+                .data
+                .as_hash()?
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+            None => vec![],
+        };
+        Ok(random_sample(fields, count))
+    }
+
+    /// Iterate over the fields of the hash stored at `key`, starting at
+    /// `cursor`. See `scan` for the semantics of the arguments.
+    pub(crate) fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<(String, Bytes)>), DbError> {
+        let state = self.lock();
+
+        let hash = match state.entries.get(key) {
+            Some(entry) => entry.data.as_hash()?,
+            None => return Ok((0, vec![])),
+        };
+
+        let fields = hash
+            .iter()
+            .map(|(field, value)| (field.as_bytes(), (field, value)));
+        let (cursor, fields) = scan_collection(fields, cursor, count, pattern);
+
+        Ok((
+            cursor,
+            fields
+                .into_iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+        ))
+    }
+
+    /// Remove `fields` from the hash stored at `key`.
+    ///
+    /// Returns the number of fields that were removed. When the last field is
+    /// removed, the key is removed as well.
+    pub(crate) fn hdel(&self, key: &str, fields: &[String]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let removed = match state.entries.get_mut(key) {
+            Some(entry) => {
+                let hash = entry.data.as_hash_mut()?;
+                fields
+                    .iter()
+                    .filter(|field| hash.remove(&field[..]).is_some())
+                    .count()
+            }
+            None => return Ok(0),
+        };
+
+        if removed > 0 {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(removed)
+    }
+}
@@ -0,0 +1,342 @@
+//! HyperLogLog cardinality estimation, stored as a string value.
+//!
+//! The layout follows the dense encoding used by Redis: a 16 bytes header
+//! starting with the `HYLL` magic, followed by 16384 registers of 6 bits
+//! each. Elements are hashed with MurmurHash64A using the same seed, and the
+//! cardinality is computed with the same estimator, so counts match the ones
+//! Redis reports for the same elements.
+
+use super::{Db, DbError, State, Value};
+
+use bytes::Bytes;
+use std::convert::TryInto;
+
+/// Number of bits of the hash used to select a register.
+const P: u32 = 14;
+
+/// Number of registers.
+const REGISTERS: usize = 1 << P;
+
+/// Number of bits of the hash used to count leading zeros.
+const Q: u32 = 64 - P;
+
+/// Width of a register, in bits.
+const REGISTER_BITS: usize = 6;
+
+/// Maximum value of a register.
+const REGISTER_MAX: u8 = (1 << REGISTER_BITS) - 1;
+
+/// Size of the header preceding the registers.
+const HEADER_LEN: usize = 16;
+
+/// Size of a dense HyperLogLog string.
+const DENSE_LEN: usize = HEADER_LEN + REGISTERS * REGISTER_BITS / 8;
+
+/// Encoding byte of the dense representation.
+const DENSE: u8 = 0;
+
+/// Set in the last byte of the cached cardinality when the cache is stale.
+const CACHE_INVALID: u8 = 1 << 7;
+
+/// Reason a string value is not a valid HyperLogLog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Invalid {
+    /// The header is not the one of a dense HyperLogLog.
+    NotHyperLogLog,
+
+    /// A register holds a value no element may set.
+    Corrupted,
+}
+
+/// A HyperLogLog, with its registers unpacked for easy manipulation.
+#[derive(Debug, Clone)]
+pub(crate) struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Create a new HyperLogLog, counting no elements.
+    pub(crate) fn new() -> HyperLogLog {
+        HyperLogLog {
+            registers: vec![0; REGISTERS],
+        }
+    }
+
+    /// Decode a HyperLogLog from a string value.
+    ///
+    /// Registers are checked as well as the header, as a string modified with
+    /// `SETRANGE` may hold registers greater than a hash can set.
+    pub(crate) fn decode(data: &[u8]) -> Result<HyperLogLog, Invalid> {
+        if data.len() != DENSE_LEN || &data[..4] != b"HYLL" || data[4] != DENSE {
+            return Err(Invalid::NotHyperLogLog);
+        }
+
+        let registers = &data[HEADER_LEN..];
+        let registers = (0..REGISTERS)
+            .map(|index| get_register(registers, index))
+            .collect::<Vec<_>>();
+
+        if registers.iter().any(|&value| value > Q as u8 + 1) {
+            return Err(Invalid::Corrupted);
+        }
+
+        Ok(HyperLogLog { registers })
+    }
+
+    /// Encode the HyperLogLog as a string value.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut data = vec![0; DENSE_LEN];
+
+        data[..4].copy_from_slice(b"HYLL");
+        data[4] = DENSE;
+
+        // The cardinality is always computed from the registers, the cache is
+        // kept for compatibility with the Redis layout.
+        data[HEADER_LEN - 1] = CACHE_INVALID;
+
+        let registers = &mut data[HEADER_LEN..];
+        for (index, value) in self.registers.iter().enumerate() {
+            set_register(registers, index, *value);
+        }
+
+        data
+    }
+
+    /// Add `element` to the HyperLogLog.
+    ///
+    /// Returns `true` if a register was updated, meaning the estimated
+    /// cardinality may have changed.
+    pub(crate) fn add(&mut self, element: &[u8]) -> bool {
+        let hash = murmur_hash64a(element, 0xadc8_3b19);
+        let index = (hash & (REGISTERS as u64 - 1)) as usize;
+
+        // Count the run of zeros after the register index, plus one. The bit
+        // set at `Q` bounds the count when the remaining bits are all zeros.
+        let rest = (hash >> P) | (1 << Q);
+        let count = rest.trailing_zeros() as u8 + 1;
+
+        if count > self.registers[index] {
+            self.registers[index] = count;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Merge `other` into `self`. The result counts the union of the elements
+    /// of both HyperLogLogs.
+    pub(crate) fn merge(&mut self, other: &HyperLogLog) {
+        for (register, value) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*value);
+        }
+    }
+
+    /// Returns the estimated number of distinct elements added.
+    ///
+    /// Uses the estimator described by Otmar Ertl in "New cardinality
+    /// estimation algorithms for HyperLogLog sketches", as Redis does.
+    pub(crate) fn count(&self) -> u64 {
+        let mut histogram = [0u32; Q as usize + 2];
+        for value in &self.registers {
+            histogram[*value as usize] += 1;
+        }
+
+        let m = REGISTERS as f64;
+        let mut z = m * tau((m - histogram[Q as usize + 1] as f64) / m);
+
+        for count in histogram[1..=Q as usize].iter().rev() {
+            z += *count as f64;
+            z *= 0.5;
+        }
+
+        z += m * sigma(histogram[0] as f64 / m);
+
+        const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+        (ALPHA_INF * m * m / z).round() as u64
+    }
+}
+
+/// Returns the register at `index` in the packed `registers`.
+///
+/// Registers are stored from the least significant bit of each byte, a
+/// register possibly spanning two bytes.
+fn get_register(registers: &[u8], index: usize) -> u8 {
+    let byte = index * REGISTER_BITS / 8;
+    let shift = index * REGISTER_BITS % 8;
+
+    let low = registers[byte] as u16;
+    let high = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+
+    (((low | (high << 8)) >> shift) as u8) & REGISTER_MAX
+}
+
+/// Set the register at `index` in the packed `registers` to `value`.
+fn set_register(registers: &mut [u8], index: usize, value: u8) {
+    let byte = index * REGISTER_BITS / 8;
+    let shift = index * REGISTER_BITS % 8;
+
+    let mask = (REGISTER_MAX as u16) << shift;
+    let value = (value as u16) << shift;
+
+    registers[byte] = (registers[byte] & !(mask as u8)) | value as u8;
+
+    if let Some(next) = registers.get_mut(byte + 1) {
+        *next = (*next & !((mask >> 8) as u8)) | (value >> 8) as u8;
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut y = 1.0;
+    let mut z = x;
+
+    loop {
+        x *= x;
+        let prev = z;
+        z += x * y;
+        y += y;
+
+        if prev == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+
+    loop {
+        x = x.sqrt();
+        let prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+
+        if prev == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// MurmurHash64A, by Austin Appleby, as used by Redis to hash HyperLogLog
+/// elements. Blocks are read as little endian integers.
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+
+    h
+}
+
+impl Db {
+    /// Add `elements` to the HyperLogLog stored at `key`. If `key` does not
+    /// exist, a new HyperLogLog is created.
+    ///
+    /// Returns `true` if the estimated cardinality may have changed, which is
+    /// always the case when the key is created.
+    pub(crate) fn pfadd(&self, key: String, elements: &[Bytes]) -> Result<bool, DbError> {
+        let mut state = self.lock();
+
+        let (mut hll, mut changed) = match state.hyperloglog(&key)? {
+            Some(hll) => (hll, false),
+            None => (HyperLogLog::new(), true),
+        };
+
+        for element in elements {
+            changed |= hll.add(element);
+        }
+
+        if changed {
+            let data = Bytes::from(hll.encode());
+            *state
+                .entry_or_insert_with(key, || Value::String(Bytes::new()))
+                .data
+                .as_string_mut()? = data;
+        }
+
+        Ok(changed)
+    }
+
+    /// Returns the estimated number of distinct elements in the union of the
+    /// HyperLogLogs stored at `keys`. Missing keys are skipped.
+    pub(crate) fn pfcount(&self, keys: &[String]) -> Result<u64, DbError> {
+        let state = self.lock();
+
+        let mut union = HyperLogLog::new();
+        for key in keys {
+            if let Some(hll) = state.hyperloglog(key)? {
+                union.merge(&hll);
+            }
+        }
+
+        Ok(union.count())
+    }
+
+    /// Merge the HyperLogLogs stored at `keys` into the one stored at `dest`,
+    /// creating it if needed. Missing keys are skipped.
+    pub(crate) fn pfmerge(&self, dest: String, keys: &[String]) -> Result<(), DbError> {
+        let mut state = self.lock();
+
+        let mut union = state.hyperloglog(&dest)?.unwrap_or_else(HyperLogLog::new);
+        for key in keys {
+            if let Some(hll) = state.hyperloglog(key)? {
+                union.merge(&hll);
+            }
+        }
+
+        let data = Bytes::from(union.encode());
+        *state
+            .entry_or_insert_with(dest, || Value::String(Bytes::new()))
+            .data
+            .as_string_mut()? = data;
+
+        Ok(())
+    }
+}
+
+impl State {
+    /// Decode the HyperLogLog stored at `key`, or `None` if the key does not
+    /// exist.
+    fn hyperloglog(&self, key: &str) -> Result<Option<HyperLogLog>, DbError> {
+        match self.entries.get(key) {
+            Some(entry) => match HyperLogLog::decode(entry.data.as_string()?) {
+                Ok(hll) => Ok(Some(hll)),
+                Err(Invalid::NotHyperLogLog) => Err(DbError::InvalidHll),
+                Err(Invalid::Corrupted) => Err(DbError::CorruptedHll),
+            },
+            None => Ok(None),
+        }
+    }
+}
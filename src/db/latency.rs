@@ -0,0 +1,179 @@
+//! Latency spikes of the server, recorded when an event takes longer than the
+//! `latency-monitor-threshold` parameter and inspected with `LATENCY`.
+//!
+//! The events are `command`, for commands applied by the clients,
+//! `expire-cycle`, for the purge of the expired keys, and `snapshot`, for the
+//! saving of snapshots.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of samples kept for each event.
+const HISTORY_LEN: usize = 160;
+
+/// The latency spikes of each event, indexed by event name.
+#[derive(Debug)]
+pub(crate) struct LatencyMonitor {
+    events: BTreeMap<&'static str, History>,
+}
+
+/// The latency spikes of an event.
+#[derive(Debug)]
+struct History {
+    /// The latest samples, as the Unix time they were recorded at in seconds
+    /// and their latency in milliseconds, the oldest first. Samples recorded
+    /// within the same second are merged, keeping the highest latency.
+    samples: VecDeque<(u64, u64)>,
+
+    /// Highest latency ever recorded, in milliseconds.
+    max: u64,
+}
+
+/// The latest spike of an event, as reported by `LATENCY LATEST`.
+#[derive(Debug)]
+pub(crate) struct LatestSpike {
+    pub(crate) event: &'static str,
+    /// Unix time at which it was recorded, in seconds.
+    pub(crate) time: u64,
+    /// Latency of the spike, in milliseconds.
+    pub(crate) latency: u64,
+    /// Highest latency ever recorded for the event, in milliseconds.
+    pub(crate) max: u64,
+}
+
+impl LatencyMonitor {
+    pub(crate) fn new() -> LatencyMonitor {
+        LatencyMonitor {
+            events: BTreeMap::new(),
+        }
+    }
+
+    /// Record a spike of `latency` milliseconds of `event`.
+    pub(crate) fn add_sample(&mut self, event: &'static str, latency: u64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let history = self.events.entry(event).or_insert_with(|| History {
+            samples: VecDeque::new(),
+            max: 0,
+        });
+        history.max = history.max.max(latency);
+
+        match history.samples.back_mut() {
+            Some((time, sample)) if *time == now => *sample = (*sample).max(latency),
+            _ => {
+                if history.samples.len() == HISTORY_LEN {
+                    history.samples.pop_front();
+                }
+                history.samples.push_back((now, latency));
+            }
+        }
+    }
+
+    /// Returns the latest spike of each event.
+    pub(crate) fn latest(&self) -> Vec<LatestSpike> {
+        self.events
+            .iter()
+            .filter_map(|(event, history)| {
+                let (time, latency) = *history.samples.back()?;
+                Some(LatestSpike {
+                    event,
+                    time,
+                    latency,
+                    max: history.max,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the samples of `event`, the oldest first.
+    pub(crate) fn history(&self, event: &str) -> Vec<(u64, u64)> {
+        self.events
+            .get(event)
+            .map(|history| history.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop the samples of `events`, or of every event if empty. Returns the
+    /// number of events whose samples were dropped.
+    pub(crate) fn reset(&mut self, events: &[String]) -> usize {
+        if events.is_empty() {
+            let count = self.events.len();
+            self.events.clear();
+            return count;
+        }
+
+        events
+            .iter()
+            .filter(|event| self.events.remove(event.as_str()).is_some())
+            .count()
+    }
+
+    /// Returns a report of the spikes of each event, as `LATENCY DOCTOR`
+    /// does. `threshold` is the `latency-monitor-threshold` parameter.
+    pub(crate) fn doctor(&self, threshold: u64) -> String {
+        if threshold == 0 {
+            return "Latency monitoring is disabled. Use \"CONFIG SET \
+                    latency-monitor-threshold <milliseconds>\" to enable it.\n"
+                .to_string();
+        }
+
+        if self.events.is_empty() {
+            return "No latency spike was observed since the server started.\n".to_string();
+        }
+
+        let mut report = "Latency spikes were observed for the following events:\n\n".to_string();
+        for (i, (event, history)) in self.events.iter().enumerate() {
+            let samples = &history.samples;
+            let count = samples.len() as u64;
+            let average = samples.iter().map(|(_, latency)| latency).sum::<u64>() / count.max(1);
+            let deviation = samples
+                .iter()
+                .map(|(_, latency)| latency.abs_diff(average))
+                .sum::<u64>()
+                / count.max(1);
+            let period = match (samples.front(), samples.back()) {
+                (Some((first, _)), Some((last, _))) if count > 1 => (last - first) / (count - 1),
+                _ => 0,
+            };
+
+            // Writing to a `String` cannot fail.
+            let _ = writeln!(
+                report,
+                "{}. {}: {} latency spikes (average {}ms, mean deviation {}ms, period {} sec). \
+                 Worst all time event {}ms.",
+                i + 1,
+                event,
+                count,
+                average,
+                deviation,
+                period,
+                history.max
+            );
+        }
+
+        if self.events.contains_key("command") {
+            report.push_str(
+                "\nSlow commands were observed: inspect them with SLOWLOG GET, and avoid \
+                 commands of O(N) complexity on large values.\n",
+            );
+        }
+        if self.events.contains_key("expire-cycle") {
+            report.push_str(
+                "\nPurging the expired keys was slow: many keys may be expiring at the same \
+                 time.\n",
+            );
+        }
+        if self.events.contains_key("snapshot") {
+            report.push_str(
+                "\nSaving snapshots was slow: the disk may be too slow, or the data set too \
+                 large.\n",
+            );
+        }
+
+        report
+    }
+}
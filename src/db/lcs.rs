@@ -0,0 +1,132 @@
+//! Longest common subsequence of two strings.
+//!
+//! The classic dynamic programming algorithm is used, which takes time
+//! proportional to the product of the lengths of the strings. Finding the
+//! subsequence itself keeps the whole table, which takes memory proportional
+//! to this product as well, up to `MAX_TABLE_LEN` entries. Finding only its
+//! length keeps two rows of the table.
+
+use bytes::Bytes;
+
+/// Longest common subsequence of two strings.
+#[derive(Debug)]
+pub(crate) struct Lcs {
+    /// The common subsequence.
+    pub(crate) sequence: Bytes,
+
+    /// Ranges of consecutive bytes of the subsequence which are also
+    /// consecutive in both strings, from the end of the strings to their
+    /// start, as Redis lists them.
+    pub(crate) matches: Vec<LcsMatch>,
+}
+
+/// Range of bytes common to both strings, the offsets being inclusive.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LcsMatch {
+    /// Offsets of the range in the first string.
+    pub(crate) a: (usize, usize),
+
+    /// Offsets of the range in the second string.
+    pub(crate) b: (usize, usize),
+}
+
+impl LcsMatch {
+    /// Returns the number of bytes in the range.
+    pub(crate) fn len(&self) -> usize {
+        self.a.1 - self.a.0 + 1
+    }
+}
+
+/// Maximum number of entries of the table, taking 512 MB as the largest
+/// strings Redis accepts.
+const MAX_TABLE_LEN: usize = 128 * 1024 * 1024;
+
+/// Compute the longest common subsequence of `a` and `b`, or `None` if the
+/// strings are too long for the table to fit in `MAX_TABLE_LEN` entries.
+pub(crate) fn lcs(a: &[u8], b: &[u8]) -> Option<Lcs> {
+    // `table[i][j]` is the length of the longest common subsequence of the
+    // first `i` bytes of `a` and the first `j` bytes of `b`.
+    let width = b.len() + 1;
+    let len = (a.len() + 1)
+        .checked_mul(width)
+        .filter(|&len| len <= MAX_TABLE_LEN)?;
+    let mut table = vec![0u32; len];
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * width + j] = if a[i - 1] == b[j - 1] {
+                table[(i - 1) * width + j - 1] + 1
+            } else {
+                table[(i - 1) * width + j].max(table[i * width + j - 1])
+            };
+        }
+    }
+
+    // Walk the table back from the end of both strings, collecting the
+    // subsequence backward.
+    let mut sequence = Vec::with_capacity(table[a.len() * width + b.len()] as usize);
+    let mut matches = vec![];
+    let mut current: Option<LcsMatch> = None;
+    let (mut i, mut j) = (a.len(), b.len());
+
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] {
+            sequence.push(a[i - 1]);
+
+            current = match current {
+                // Walking back diagonally, so the range can be extended.
+                Some(range) if range.a.0 == i && range.b.0 == j => Some(LcsMatch {
+                    a: (i - 1, range.a.1),
+                    b: (j - 1, range.b.1),
+                }),
+                range => {
+                    matches.extend(range);
+                    Some(LcsMatch {
+                        a: (i - 1, i - 1),
+                        b: (j - 1, j - 1),
+                    })
+                }
+            };
+
+            i -= 1;
+            j -= 1;
+        } else {
+            matches.extend(current.take());
+
+            if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+        }
+    }
+
+    matches.extend(current);
+    sequence.reverse();
+
+    Some(Lcs {
+        sequence: sequence.into(),
+        matches,
+    })
+}
+
+/// Compute the length of the longest common subsequence of `a` and `b`.
+///
+/// Only the previous row of the table is needed to compute the next one.
+pub(crate) fn lcs_len(a: &[u8], b: &[u8]) -> usize {
+    let mut previous = vec![0usize; b.len() + 1];
+    let mut row = vec![0usize; b.len() + 1];
+
+    for &x in a {
+        for (j, &y) in b.iter().enumerate() {
+            row[j + 1] = if x == y {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(row[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut row);
+    }
+
+    previous[b.len()]
+}
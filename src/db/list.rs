@@ -0,0 +1,395 @@
+//! Commands on list values, including the blocking pops.
+
+use super::{normalize_range, Db, DbError, InsertPosition, ListEnd, Value};
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+
+impl Db {
+    /// Push `values` onto the `end` of the list stored at `key`. If `key` does
+    /// not exist, a new list is created.
+    ///
+    /// Values are pushed one after the other, so pushing `a b c` onto the left
+    /// end results in the list `c b a`.
+    ///
+    /// Returns the length of the list after the push.
+    pub(crate) fn push(
+        &self,
+        key: String,
+        values: Vec<Bytes>,
+        end: ListEnd,
+    ) -> Result<usize, DbError> {
+        let mut state = self.lock();
+        let list = state
+            .entry_or_insert_with(key.clone(), || Value::List(VecDeque::new()))
+            .data
+            .as_list_mut()?;
+
+        for value in values {
+            match end {
+                ListEnd::Left => list.push_front(value),
+                ListEnd::Right => list.push_back(value),
+            }
+        }
+
+        let len = list.len();
+        state.notify_writes(&key);
+
+        Ok(len)
+    }
+
+    /// Pop up to `count` values from the `end` of the list stored at `key`.
+    ///
+    /// Returns `None` if `key` does not exist. When the last value is popped,
+    /// the key is removed as well.
+    pub(crate) fn pop(
+        &self,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<Bytes>>, DbError> {
+        let mut state = self.lock();
+
+        let values: Vec<Bytes> = match state.entries.get_mut(key) {
+            Some(entry) => {
+                let list = entry.data.as_list_mut()?;
+                let count = count.min(list.len());
+
+                match end {
+                    ListEnd::Left => list.drain(..count).collect(),
+                    ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+                }
+            }
+            None => return Ok(None),
+        };
+
+        if !values.is_empty() {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(Some(values))
+    }
+
+    /// Pop a value from the `end` of the first non-empty list stored at one of
+    /// `keys`, on behalf of the blocked client holding `ticket`.
+    ///
+    /// A value is only popped if no client waiting longer on the same key is
+    /// entitled to it. Once a value is popped, `ticket` leaves the queues of
+    /// all `keys`.
+    ///
+    /// Returns the key the value was popped from along with the value, or
+    /// `None` if there is nothing to pop yet.
+    pub(crate) fn blocking_pop(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+        ticket: u64,
+    ) -> Result<Option<(String, Bytes)>, DbError> {
+        let mut state = self.lock();
+
+        for key in keys {
+            let len = match state.entries.get(key) {
+                Some(entry) => entry.data.as_list()?.len(),
+                None => continue,
+            };
+
+            if !state.is_next_in_queue(key, ticket, len) {
+                continue;
+            }
+
+            let list = state.entries.get_mut(key).unwrap().data.as_list_mut()?;
+            let value = match end {
+                ListEnd::Left => list.pop_front(),
+                ListEnd::Right => list.pop_back(),
+            };
+
+            state.modified(key);
+            state.remove_if_empty(key);
+            state.dequeue(keys, ticket);
+
+            return Ok(value.map(|value| (key.clone(), value)));
+        }
+
+        Ok(None)
+    }
+
+    /// Atomically pop a value from the `from` end of the list stored at
+    /// `source` and push it to the `to` end of the list stored at
+    /// `destination`. `source` and `destination` may be the same list, which
+    /// rotates it.
+    ///
+    /// When `ticket` is set, the move is made on behalf of a blocked client
+    /// and only happens if no client waiting longer on `source` is entitled
+    /// to the value, as with `blocking_pop`.
+    ///
+    /// Returns the moved value, or `None` if `source` does not exist.
+    pub(crate) fn lmove(
+        &self,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+        ticket: Option<u64>,
+    ) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.lock();
+
+        let len = match state.entries.get(source) {
+            Some(entry) => entry.data.as_list()?.len(),
+            None => return Ok(None),
+        };
+
+        // Check the destination before popping, so that the value is never
+        // lost.
+        if let Some(entry) = state.entries.get(destination) {
+            entry.data.as_list()?;
+        }
+
+        if let Some(ticket) = ticket {
+            if !state.is_next_in_queue(source, ticket, len) {
+                return Ok(None);
+            }
+        }
+
+        let list = state.entries.get_mut(source).unwrap().data.as_list_mut()?;
+        let value = match from {
+            ListEnd::Left => list.pop_front(),
+            ListEnd::Right => list.pop_back(),
+        };
+        let value = value.unwrap();
+
+        state.modified(source);
+        state.remove_if_empty(source);
+
+        let list = state
+            .entry_or_insert_with(destination.to_string(), || Value::List(VecDeque::new()))
+            .data
+            .as_list_mut()?;
+        match to {
+            ListEnd::Left => list.push_front(value.clone()),
+            ListEnd::Right => list.push_back(value.clone()),
+        }
+
+        if let Some(ticket) = ticket {
+            state.dequeue(&[source.to_string()], ticket);
+        }
+        state.notify_writes(destination);
+
+        Ok(Some(value))
+    }
+
+    /// Get the values of the list stored at `key` between the `start` and
+    /// `stop` indexes, both inclusive.
+    ///
+    /// Negative indexes count from the end of the list, `-1` being the last
+    /// value. Out of range indexes do not produce an error: they are clamped to
+    /// the bounds of the list.
+    pub(crate) fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<Bytes>, DbError> {
+        let state = self.lock();
+
+        let list = match state.entries.get(key) {
+            Some(entry) => entry.data.as_list()?,
+            None => return Ok(vec![]),
+        };
+
+        match normalize_range(start, stop, list.len()) {
+            Some((start, stop)) => Ok(list.range(start..=stop).cloned().collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the indexes of the values of the list stored at `key` equal to
+    /// `element`, in the order they are found.
+    ///
+    /// Matches before the `rank`-th are skipped. A negative `rank` scans the
+    /// list from the tail, but indexes are always counted from the head. At
+    /// most `count` indexes are returned and `maxlen` values compared, `0`
+    /// meaning no limit for both.
+    pub(crate) fn lpos(
+        &self,
+        key: &str,
+        element: &[u8],
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Result<Vec<usize>, DbError> {
+        let state = self.lock();
+
+        let list = match state.entries.get(key) {
+            Some(entry) => entry.data.as_list()?,
+            None => return Ok(vec![]),
+        };
+
+        let count = if count == 0 { usize::MAX } else { count };
+        let maxlen = if maxlen == 0 { usize::MAX } else { maxlen };
+        let skip = (rank.unsigned_abs() - 1) as usize;
+
+        let indexes: Box<dyn Iterator<Item = (usize, &Bytes)>> = if rank > 0 {
+            Box::new(list.iter().enumerate())
+        } else {
+            Box::new(list.iter().enumerate().rev())
+        };
+
+        Ok(indexes
+            .take(maxlen)
+            .filter(|(_, value)| &value[..] == element)
+            .skip(skip)
+            .take(count)
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// Insert `element` in the list stored at `key`, before or after the first
+    /// value equal to `pivot` depending on `position`.
+    ///
+    /// Returns the length of the list after the insertion, `None` if `pivot`
+    /// was not found, or `0` if `key` does not exist.
+    pub(crate) fn linsert(
+        &self,
+        key: &str,
+        position: InsertPosition,
+        pivot: &[u8],
+        element: Bytes,
+    ) -> Result<Option<usize>, DbError> {
+        let mut state = self.lock();
+
+        let list = match state.entries.get_mut(key) {
+            Some(entry) => entry.data.as_list_mut()?,
+            None => return Ok(Some(0)),
+        };
+
+        let index = match list.iter().position(|value| &value[..] == pivot) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        match position {
+            InsertPosition::Before => list.insert(index, element),
+            InsertPosition::After => list.insert(index + 1, element),
+        }
+
+        let len = list.len();
+        state.modified(key);
+
+        Ok(Some(len))
+    }
+
+    /// Set the value at `index` of the list stored at `key` to `element`.
+    ///
+    /// As with `lrange`, a negative index counts from the end of the list.
+    pub(crate) fn lset(&self, key: &str, index: i64, element: Bytes) -> Result<(), DbError> {
+        let mut state = self.lock();
+
+        let list = match state.entries.get_mut(key) {
+            Some(entry) => entry.data.as_list_mut()?,
+            None => return Err(DbError::NoSuchKey),
+        };
+
+        let index = if index < 0 {
+            index + list.len() as i64
+        } else {
+            index
+        };
+
+        if index < 0 {
+            return Err(DbError::IndexOutOfRange);
+        }
+
+        match list.get_mut(index as usize) {
+            Some(value) => {
+                *value = element;
+                state.modified(key);
+                Ok(())
+            }
+            None => Err(DbError::IndexOutOfRange),
+        }
+    }
+
+    /// Remove the first `count` values equal to `element` from the list
+    /// stored at `key`.
+    ///
+    /// A positive `count` removes values from the head to the tail, a
+    /// negative `count` from the tail to the head, and `0` removes all the
+    /// values equal to `element`. When the last value is removed, the key is
+    /// removed as well.
+    ///
+    /// Returns the number of removed values.
+    pub(crate) fn lrem(&self, key: &str, count: i64, element: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let list = match state.entries.get_mut(key) {
+            Some(entry) => entry.data.as_list_mut()?,
+            None => return Ok(0),
+        };
+
+        let limit = if count == 0 {
+            usize::MAX
+        } else {
+            count.unsigned_abs() as usize
+        };
+
+        let indexes: Box<dyn Iterator<Item = (usize, &Bytes)>> = if count >= 0 {
+            Box::new(list.iter().enumerate())
+        } else {
+            Box::new(list.iter().enumerate().rev())
+        };
+
+        let mut indexes: Vec<usize> = indexes
+            .filter(|(_, value)| &value[..] == element)
+            .take(limit)
+            .map(|(index, _)| index)
+            .collect();
+
+        // Remove from the tail first, so that the remaining indexes stay
+        // valid.
+        indexes.sort_unstable_by(|a, b| b.cmp(a));
+        for index in &indexes {
+            list.remove(*index);
+        }
+
+        if !indexes.is_empty() {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(indexes.len())
+    }
+
+    /// Trim the list stored at `key` so that it only contains the values
+    /// between the `start` and `stop` indexes, both inclusive.
+    ///
+    /// Indexes are interpreted as with `lrange`. If the range is empty, the
+    /// key is removed.
+    pub(crate) fn ltrim(&self, key: &str, start: i64, stop: i64) -> Result<(), DbError> {
+        let mut state = self.lock();
+
+        let list = match state.entries.get_mut(key) {
+            Some(entry) => entry.data.as_list_mut()?,
+            None => return Ok(()),
+        };
+
+        match normalize_range(start, stop, list.len()) {
+            Some((start, stop)) => {
+                list.truncate(stop + 1);
+                list.drain(..start);
+            }
+            None => list.clear(),
+        }
+
+        state.modified(key);
+        state.remove_if_empty(key);
+
+        Ok(())
+    }
+
+    /// Returns the length of the list stored at `key`, `0` if `key` does not
+    /// exist.
+    pub(crate) fn llen(&self, key: &str) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_list()?.len()),
+            None => Ok(0),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Stream of the commands received by the server, sent to the connections
+//! running `MONITOR`.
+
+use crate::Frame;
+
+use bytes::Bytes;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Broadcasts the commands to the monitoring connections. A slow monitor
+/// misses the commands which overflow the channel.
+#[derive(Debug)]
+pub(crate) struct Monitors {
+    tx: broadcast::Sender<String>,
+}
+
+impl Monitors {
+    pub(crate) fn new() -> Monitors {
+        let (tx, _) = broadcast::channel(1024);
+        Monitors { tx }
+    }
+
+    /// Returns a receiver of the commands received from now on.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.tx.subscribe()
+    }
+
+    /// Send the command `frame`, received from the client at `addr` with the
+    /// database `index` selected, to the monitors.
+    ///
+    /// The command is only formatted if there is a monitor to receive it, so
+    /// that commands cost nothing more while no connection monitors them.
+    pub(crate) fn feed(&self, index: usize, addr: &str, frame: &Frame) {
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut line = format!(
+            "{}.{:06} [{} {}]",
+            now.as_secs(),
+            now.subsec_micros(),
+            index,
+            addr
+        );
+
+        if let Frame::Array(args) = &*redacted(frame) {
+            for arg in args {
+                line.push(' ');
+                match arg {
+                    Frame::Bulk(arg) => quote(arg, &mut line),
+                    arg => quote(arg.to_string().as_bytes(), &mut line),
+                }
+            }
+        }
+
+        // Monitors may have disconnected in the meantime.
+        let _ = self.tx.send(line);
+    }
+}
+
+/// Returns `frame` with the arguments which may hold passwords replaced with
+/// `(redacted)`, as Redis does before streaming a command to the monitors or
+/// recording it in the slow log.
+pub(crate) fn redacted(frame: &Frame) -> Cow<'_, Frame> {
+    let args = match frame {
+        Frame::Array(args) if !args.is_empty() => args,
+        _ => return Cow::Borrowed(frame),
+    };
+
+    let arg = |i: usize| match args.get(i) {
+        Some(Frame::Bulk(arg)) => String::from_utf8_lossy(arg).to_ascii_lowercase(),
+        Some(Frame::Simple(arg)) => arg.to_ascii_lowercase(),
+        _ => String::new(),
+    };
+
+    // Indices of the arguments to redact.
+    let mut redact = vec![];
+    match &arg(0)[..] {
+        "auth" => redact.extend(1..args.len()),
+        "acl" if arg(1) == "setuser" => redact.extend(3..args.len()),
+        "config" if arg(1) == "set" => {
+            for i in (2..args.len()).step_by(2) {
+                if matches!(&arg(i)[..], "requirepass" | "masterauth") {
+                    redact.push(i + 1);
+                }
+            }
+        }
+        "hello" | "migrate" => {
+            for i in 1..args.len() {
+                match &arg(i)[..] {
+                    "auth" if arg(0) == "migrate" => redact.push(i + 1),
+                    "auth" | "auth2" => redact.extend([i + 1, i + 2]),
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    redact.retain(|&i| i < args.len());
+
+    if redact.is_empty() {
+        return Cow::Borrowed(frame);
+    }
+
+    let mut args = args.clone();
+    for i in redact {
+        args[i] = Frame::Bulk(Bytes::from_static(b"(redacted)"));
+    }
+    Cow::Owned(Frame::Array(args))
+}
+
+/// Append `arg` to `line` as a quoted string, escaping the quotes, the
+/// backslashes and the non-printable characters.
+fn quote(arg: &[u8], line: &mut String) {
+    line.push('"');
+    for &byte in arg {
+        match byte {
+            b'\\' => line.push_str("\\\\"),
+            b'"' => line.push_str("\\\""),
+            b'\n' => line.push_str("\\n"),
+            b'\r' => line.push_str("\\r"),
+            b'\t' => line.push_str("\\t"),
+            byte if byte.is_ascii_graphic() || byte == b' ' => line.push(byte as char),
+            byte => {
+                // Writing to a `String` cannot fail.
+                let _ = write!(line, "\\x{:02x}", byte);
+            }
+        }
+    }
+    line.push('"');
+}
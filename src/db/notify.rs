@@ -0,0 +1,100 @@
+//! Keyspace notifications.
+//!
+//! When enabled, the modifications of the keys are published to pub/sub
+//! channels: the name of the event to `__keyspace@<db>__:<key>` and the key to
+//! `__keyevent@<db>__:<event>`. Each event belongs to a class, and only the
+//! events of the classes enabled with `CONFIG SET notify-keyspace-events` are
+//! published.
+
+use std::fmt;
+
+/// Classes of the events to publish, along with the channels to publish them
+/// to, as set by `notify-keyspace-events`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct KeyspaceEvents {
+    /// Flags enabled, one bit per character of `FLAGS`
+    flags: u16,
+}
+
+/// Characters enabling each flag, in the order of their bits.
+///
+/// `K` and `E` select the keyspace and keyevent channels. The others are the
+/// classes of events: generic commands, strings, lists, sets, hashes, sorted
+/// sets, expired keys, evicted keys and streams.
+const FLAGS: &[u8] = b"KEg$lshzxet";
+
+/// Characters enabled by `A`, an alias for every class of events.
+const ALL: &[u8] = b"g$lshzxet";
+
+impl KeyspaceEvents {
+    /// Parse the flags from a `notify-keyspace-events` value.
+    ///
+    /// Returns `None` if the value holds an unknown character.
+    pub(crate) fn parse(value: &str) -> Option<KeyspaceEvents> {
+        let mut flags = 0;
+
+        for c in value.bytes() {
+            let chars = if c == b'A' {
+                ALL
+            } else {
+                std::slice::from_ref(&c)
+            };
+
+            for c in chars {
+                let bit = FLAGS.iter().position(|flag| flag == c)?;
+                flags |= 1 << bit;
+            }
+        }
+
+        Some(KeyspaceEvents { flags })
+    }
+
+    /// Returns `true` if events of `class` are published to at least one of
+    /// the channels.
+    pub(crate) fn allows(self, class: u8) -> bool {
+        (self.keyspace() || self.keyevent()) && self.has(class)
+    }
+
+    /// Returns `true` if events are published to the `__keyspace` channels.
+    pub(crate) fn keyspace(self) -> bool {
+        self.has(b'K')
+    }
+
+    /// Returns `true` if events are published to the `__keyevent` channels.
+    pub(crate) fn keyevent(self) -> bool {
+        self.has(b'E')
+    }
+
+    fn has(self, flag: u8) -> bool {
+        match FLAGS.iter().position(|c| *c == flag) {
+            Some(bit) => self.flags & 1 << bit != 0,
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for KeyspaceEvents {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut flags = String::new();
+
+        // Every class being enabled is written as `A`, as Redis does.
+        if ALL.iter().all(|class| self.has(*class)) {
+            flags.push('A');
+        } else {
+            flags.extend(
+                ALL.iter()
+                    .filter(|class| self.has(**class))
+                    .map(|class| *class as char),
+            );
+        }
+
+        if self.keyspace() {
+            flags.push('K');
+        }
+        if self.keyevent() {
+            flags.push('E');
+        }
+
+        flags.fmt(fmt)
+    }
+}
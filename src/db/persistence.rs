@@ -0,0 +1,207 @@
+//! Saving the databases to disk, as snapshots and as an append-only file.
+
+use super::snapshot;
+use super::{Databases, Db, Entry, Snapshot, LFU_INIT};
+use crate::persistence::Aof;
+use crate::script::Library;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::time::Instant;
+
+impl Db {
+    /// Set the file the snapshots are saved to.
+    pub(crate) fn set_snapshot_path(&self, path: PathBuf) {
+        self.shared.lock(self.transaction).snapshot_path = Some(path);
+    }
+
+    /// Returns the file the snapshots are saved to, or `None` if snapshots are
+    /// disabled.
+    pub(crate) fn snapshot_path(&self) -> Option<PathBuf> {
+        self.shared.lock(self.transaction).snapshot_path.clone()
+    }
+
+    /// Returns when the last snapshot was successfully saved.
+    pub(crate) fn last_save(&self) -> SystemTime {
+        self.shared.lock(self.transaction).last_save
+    }
+
+    /// Returns a copy of the databases and of the loaded libraries, to be
+    /// saved to disk, or `None` if a snapshot is already being saved.
+    ///
+    /// `end_snapshot` must be called once the snapshot is saved, or failed to
+    /// be.
+    pub(crate) fn begin_snapshot(&self) -> Option<Snapshot> {
+        let mut databases = self.shared.lock(self.transaction);
+        if databases.saving {
+            return None;
+        }
+        databases.saving = true;
+
+        // The databases are held while they are copied.
+        let start = Instant::now();
+        let snapshot = databases.snapshot();
+        let threshold = databases.config.latency_monitor_threshold;
+        drop(databases);
+
+        self.shared
+            .add_latency_sample(threshold, "snapshot", start.elapsed());
+        Some(snapshot)
+    }
+
+    /// Replace the content of the databases and the loaded libraries with the
+    /// content of `snapshot`.
+    ///
+    /// Keys which expired since the snapshot was taken are skipped, and so are
+    /// the databases of the snapshot beyond the number of databases.
+    pub(crate) fn restore(&self, snapshot: Snapshot) -> crate::Result<()> {
+        let libraries = snapshot
+            .libraries
+            .into_iter()
+            .map(|code| {
+                Library::load(code).map(|library| (library.name().to_string(), Arc::new(library)))
+            })
+            .collect::<Result<BTreeMap<_, _>, String>>()?;
+
+        let mut databases = self.shared.lock(self.transaction);
+        let now = Instant::now();
+        let mut flushed = vec![];
+
+        for (state, entries) in databases.states.iter_mut().zip(snapshot.databases) {
+            flushed.push(state.take_keys());
+
+            for (key, data, expires_at) in entries {
+                let expires_at = expires_at.map(snapshot::instant);
+                if matches!(expires_at, Some(when) if when <= now) {
+                    continue;
+                }
+
+                let id = state.next_id;
+                state.next_id += 1;
+                let entry = Entry {
+                    id,
+                    data,
+                    expires_at,
+                    last_access: now,
+                    frequency: LFU_INIT,
+                    size: 0,
+                };
+                state.insert(key, entry);
+            }
+        }
+
+        databases.libraries = libraries;
+        databases.saved_changes = databases.changes();
+        drop(databases);
+
+        // The previous keys are dropped after releasing the lock, and the
+        // background task purges the keys of the snapshot as they expire.
+        drop(flushed);
+        self.shared.background_task.notify_one();
+        Ok(())
+    }
+
+    /// Returns the number of modifications of the databases since the last
+    /// snapshot was taken.
+    pub(crate) fn unsaved_changes(&self) -> u64 {
+        let databases = self.shared.lock(self.transaction);
+        databases.changes() - databases.saved_changes
+    }
+
+    /// Record that the snapshot returned by `begin_snapshot` is no longer
+    /// being saved. `saved` is the snapshot if it was successfully saved.
+    pub(crate) fn end_snapshot(&self, saved: Option<&Snapshot>) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.saving = false;
+
+        if let Some(snapshot) = saved {
+            databases.saved_changes = databases.saved_changes.max(snapshot.changes());
+            databases.last_save = SystemTime::now();
+        }
+    }
+
+    /// Append the commands modifying the databases to `aof` from now on.
+    pub(crate) fn set_aof(&self, aof: Aof) {
+        self.shared.lock(self.transaction).aof = Some(aof);
+    }
+
+    /// Returns a new handle to the AOF, or `None` if it is disabled.
+    pub(crate) fn aof_file(&self) -> Option<io::Result<File>> {
+        let databases = self.shared.lock(self.transaction);
+        databases.aof.as_ref().map(Aof::try_clone_file)
+    }
+
+    /// Returns a copy of the databases and of the loaded libraries, to be
+    /// written to the AOF in place of the commands appended so far, along with
+    /// the path of the AOF.
+    ///
+    /// The commands appended from now on are buffered until `end_aof_rewrite`
+    /// is called. Returns an error if the AOF is disabled or if it is already
+    /// being rewritten.
+    pub(crate) fn begin_aof_rewrite(&self) -> Result<(Snapshot, PathBuf), String> {
+        let mut databases = self.shared.lock(self.transaction);
+        match &databases.aof {
+            Some(aof) if aof.rewriting() => {
+                return Err(
+                    "ERR Background append only file rewriting already in progress".to_string(),
+                )
+            }
+            Some(_) => {}
+            None => return Err("ERR Append only file is disabled".to_string()),
+        }
+
+        let snapshot = databases.snapshot();
+        let aof = databases.aof.as_mut().unwrap();
+        aof.start_rewrite();
+        Ok((snapshot, aof.path().to_path_buf()))
+    }
+
+    /// Complete the rewrite started by `begin_aof_rewrite`. `rewritten` is the
+    /// file the copy of the databases was written to and its path, or `None`
+    /// if it could not be written.
+    pub(crate) fn end_aof_rewrite(&self, rewritten: Option<(File, &Path)>) -> io::Result<()> {
+        let mut databases = self.shared.lock(self.transaction);
+        match &mut databases.aof {
+            Some(aof) => aof.finish_rewrite(rewritten),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Databases {
+    /// Returns a copy of the databases and of the loaded libraries.
+    pub(super) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            databases: self
+                .states
+                .iter()
+                .map(|state| {
+                    state
+                        .entries
+                        .iter()
+                        .map(|(key, entry)| {
+                            let expires_at = entry.expires_at.map(snapshot::unix_time);
+                            (key.clone(), entry.data.clone(), expires_at)
+                        })
+                        .collect()
+                })
+                .collect(),
+            libraries: self
+                .libraries
+                .values()
+                .map(|library| library.code().clone())
+                .collect(),
+            changes: self.changes(),
+        }
+    }
+
+    /// Returns the number of modifications of all the databases and of the
+    /// loaded libraries.
+    pub(super) fn changes(&self) -> u64 {
+        self.library_changes + self.states.iter().map(|state| state.changes).sum::<u64>()
+    }
+}
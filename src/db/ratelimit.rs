@@ -0,0 +1,97 @@
+//! Rate limiting of the commands sent by the clients.
+//!
+//! Each client may send at most `ratelimit-commands` commands and
+//! `ratelimit-bytes` bytes of commands per second. A user may be given its
+//! own limits with the ACL rules `ratelimit-commands=<n>` and
+//! `ratelimit-bytes=<n>`, shared by all the clients authenticated as it, so
+//! that the clients of a user do not starve the ones of the other users.
+//!
+//! The limits are enforced with token buckets, holding up to a second worth
+//! of tokens. A command is rejected while a bucket is empty. Otherwise, its
+//! cost is taken from the bucket, which may then go into debt, so that
+//! commands larger than the bucket are accepted, then delay the next ones.
+
+use tokio::time::Instant;
+
+/// Rates of a rate limiter, `0` for no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RateLimits {
+    /// Number of commands per second.
+    pub(crate) commands: u64,
+
+    /// Number of bytes of commands per second.
+    pub(crate) bytes: u64,
+}
+
+/// Which limit a command exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Exceeded {
+    Commands,
+    Bytes,
+}
+
+/// Token buckets limiting the commands of a client, or of the clients of a
+/// user.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    commands: TokenBucket,
+    bytes: TokenBucket,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// Tokens left, infinite while there is no limit.
+    tokens: f64,
+
+    /// Instant the tokens were last refilled at.
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Returns the limit exceeded if a command may not be sent at `now`, the
+    /// limits being `limits`.
+    ///
+    /// No tokens are taken, see `take`.
+    pub(crate) fn check(&mut self, limits: RateLimits, now: Instant) -> Result<(), Exceeded> {
+        if !self.commands.refill(limits.commands, now) {
+            return Err(Exceeded::Commands);
+        }
+        if !self.bytes.refill(limits.bytes, now) {
+            return Err(Exceeded::Bytes);
+        }
+        Ok(())
+    }
+
+    /// Take the tokens of a command of `len` bytes.
+    pub(crate) fn take(&mut self, len: usize) {
+        self.commands.tokens -= 1.0;
+        self.bytes.tokens -= len as f64;
+    }
+}
+
+impl TokenBucket {
+    /// Add the tokens earned since the last refill at the rate of `rate`
+    /// tokens per second, up to `rate` tokens.
+    ///
+    /// Returns `true` if the bucket is not empty.
+    fn refill(&mut self, rate: u64, now: Instant) -> bool {
+        if rate == 0 {
+            self.tokens = f64::INFINITY;
+        } else {
+            let elapsed = now.saturating_duration_since(self.refilled_at);
+            self.tokens = (self.tokens + elapsed.as_secs_f64() * rate as f64).min(rate as f64);
+        }
+        self.refilled_at = now;
+
+        self.tokens > 0.0
+    }
+}
+
+impl Default for TokenBucket {
+    fn default() -> TokenBucket {
+        TokenBucket {
+            tokens: f64::INFINITY,
+            refilled_at: Instant::now(),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! State of the replication, as a primary streaming its writes to replicas
+//! or as a replica of another server.
+
+use super::{Db, Snapshot};
+use crate::replication::{Link, Resync, Role};
+
+use bytes::Bytes;
+use tokio::sync::{broadcast, mpsc};
+
+impl Db {
+    /// Returns the stream of the commands modifying the databases, for a new
+    /// replica which reached `offset` of the stream identified by
+    /// `replication_id`, along with how it is synchronized.
+    ///
+    /// If the replica is fully synchronized, a copy of the databases and of
+    /// the loaded libraries is returned as well, matching the offset the
+    /// stream starts from. The replica is identified by the returned ID until
+    /// it is removed with `remove_replica`.
+    pub(crate) fn add_replica(
+        &self,
+        replication_id: &str,
+        offset: i64,
+    ) -> (
+        u64,
+        Resync,
+        Option<Snapshot>,
+        mpsc::UnboundedReceiver<Bytes>,
+    ) {
+        let mut databases = self.shared.lock(self.transaction);
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let addr = match databases.clients.get(&self.client) {
+            Some(client) => client.replica_addr(),
+            None => (String::new(), 0),
+        };
+        let (id, resync) = databases.replicas.add(tx, addr, replication_id, offset);
+        let snapshot = match resync {
+            Resync::Full { .. } => Some(databases.snapshot()),
+            Resync::Partial => None,
+        };
+        (id, resync, snapshot, rx)
+    }
+
+    /// Remove the replica `id`, added by `add_replica`, once it disconnects.
+    pub(crate) fn remove_replica(&self, id: u64) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.replicas.remove(id);
+    }
+
+    /// Record that the replica `id` applied the commands streamed to it up to
+    /// `offset`.
+    pub(crate) fn ack_replica(&self, id: u64, offset: u64) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.replicas.ack(id, offset);
+    }
+
+    /// Ask the replicas to acknowledge the commands streamed to them so far.
+    ///
+    /// Returns the offset of the stream the replicas must acknowledge for the
+    /// commands applied so far to be replicated, and a receiver notified
+    /// whenever a replica acknowledges an offset.
+    pub(crate) fn request_replica_acks(&self) -> (u64, broadcast::Receiver<()>) {
+        let mut databases = self.shared.lock(self.transaction);
+        let offset = databases.replicas.offset();
+        let acks = databases.replicas.subscribe_acks();
+        let backlog_size = databases.config.repl_backlog_size as usize;
+        databases.replicas.request_acks(backlog_size);
+        (offset, acks)
+    }
+
+    /// Returns the number of replicas which acknowledged `offset`.
+    pub(crate) fn acked_replicas(&self, offset: u64) -> usize {
+        let databases = self.shared.lock(self.transaction);
+        databases.replicas.acked(offset)
+    }
+
+    /// Replicate the primary at `addr`, as `host:port`, or stop replicating
+    /// with `None`.
+    ///
+    /// Clients may not modify the databases of a replica.
+    pub(crate) fn set_primary(&self, addr: Option<String>) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.primary = addr;
+        databases.primary_link = Link::Connect;
+        databases.primary_offset = None;
+        drop(databases);
+
+        self.shared.primary_changed.notify_one();
+    }
+
+    /// Record the state of the connection with the primary, and the offset
+    /// reached in its stream, if the databases were synchronized with it.
+    pub(crate) fn set_primary_link(&self, link: Link, offset: Option<u64>) {
+        let mut databases = self.shared.lock(self.transaction);
+        databases.primary_link = link;
+        databases.primary_offset = offset;
+    }
+
+    /// Record that the client, a replica, listens at `port`.
+    pub(crate) fn set_listening_port(&self, port: u16) {
+        let mut databases = self.shared.lock(self.transaction);
+        if let Some(client) = databases.clients.get_mut(&self.client) {
+            client.listening_port = Some(port);
+        }
+    }
+
+    /// Returns the role of the server in the replication.
+    pub(crate) fn role(&self) -> Role {
+        let databases = self.shared.lock(self.transaction);
+
+        match &databases.primary {
+            Some(addr) => Role::Replica {
+                addr: addr.clone(),
+                link: databases.primary_link,
+                offset: databases.primary_offset,
+            },
+            None => Role::Primary {
+                offset: databases.replicas.offset(),
+                replicas: databases.replicas.list(),
+            },
+        }
+    }
+
+    /// Returns the address of the primary the databases replicate, or `None`
+    /// if they do not replicate any.
+    pub(crate) fn primary(&self) -> Option<String> {
+        self.shared.lock(self.transaction).primary.clone()
+    }
+
+    /// Returns `true` if the databases replicate a primary.
+    pub(crate) fn is_replica(&self) -> bool {
+        self.shared.lock(self.transaction).primary.is_some()
+    }
+
+    /// Waits until the primary is changed by `set_primary`.
+    pub(crate) async fn primary_changed(&self) {
+        self.shared.primary_changed.notified().await
+    }
+
+    /// Give a new ID to the replication stream, so that the replicas fully
+    /// synchronize again.
+    pub(crate) fn change_replication_id(&self) {
+        self.shared
+            .lock(self.transaction)
+            .replicas
+            .change_replication_id();
+    }
+}
@@ -0,0 +1,114 @@
+use super::{Snapshot, Value};
+use crate::Frame;
+
+use bytes::Bytes;
+
+/// Maximum number of elements added by each of the commands recreating a
+/// list, set, hash or sorted set, so that large values do not result in huge
+/// frames.
+const ITEMS_PER_COMMAND: usize = 64;
+
+impl Snapshot {
+    /// Returns the commands recreating the databases and the libraries of the
+    /// snapshot, as written to a rewritten AOF.
+    ///
+    /// Each database is selected before the commands recreating its keys, and
+    /// expirations are set with `PEXPIREAT`.
+    pub(crate) fn commands(&self) -> Vec<Frame> {
+        let mut dst = vec![];
+
+        for code in &self.libraries {
+            dst.push(command(vec![
+                "function".into(),
+                "load".into(),
+                code.clone(),
+            ]));
+        }
+
+        for (index, entries) in self.databases.iter().enumerate() {
+            if entries.is_empty() {
+                continue;
+            }
+            dst.push(command(vec!["select".into(), index.to_string().into()]));
+
+            for (key, value, expires_at) in entries {
+                value.commands(key, &mut dst);
+
+                if let Some(when) = expires_at {
+                    dst.push(command(vec![
+                        "pexpireat".into(),
+                        key.clone().into(),
+                        when.to_string().into(),
+                    ]));
+                }
+            }
+        }
+
+        dst
+    }
+}
+
+impl Value {
+    /// Pushes the commands recreating the value stored at `key` to `dst`.
+    fn commands(&self, key: &str, dst: &mut Vec<Frame>) {
+        match self {
+            Value::String(value) => {
+                dst.push(command(vec![
+                    "set".into(),
+                    key.to_string().into(),
+                    value.clone(),
+                ]));
+            }
+            Value::List(list) => {
+                let values = list.iter().cloned().collect::<Vec<_>>();
+                chunked(dst, "rpush", key, 1, values);
+            }
+            Value::Set(set) => {
+                let members = set.iter().cloned().collect::<Vec<_>>();
+                chunked(dst, "sadd", key, 1, members);
+            }
+            Value::Hash(hash) => {
+                let fields = hash
+                    .iter()
+                    .flat_map(|(field, value)| vec![field.clone().into(), value.clone()])
+                    .collect::<Vec<_>>();
+                chunked(dst, "hset", key, 2, fields);
+            }
+            Value::SortedSet(zset) => {
+                let members = zset
+                    .iter()
+                    .flat_map(|(member, score)| vec![format_score(score).into(), member.clone()])
+                    .collect::<Vec<_>>();
+                chunked(dst, "zadd", key, 2, members);
+            }
+            Value::Stream(stream) => stream.commands(key, dst),
+        }
+    }
+}
+
+/// Pushes the commands `name` adding `args` to the value stored at `key` to
+/// `dst`, with at most `ITEMS_PER_COMMAND` elements of `arity` arguments per
+/// command.
+fn chunked(dst: &mut Vec<Frame>, name: &'static str, key: &str, arity: usize, args: Vec<Bytes>) {
+    for chunk in args.chunks(ITEMS_PER_COMMAND * arity) {
+        let mut args = vec![name.into(), key.to_string().into()];
+        args.extend_from_slice(chunk);
+        dst.push(command(args));
+    }
+}
+
+/// Formats a score so that `ZADD` parses it back to the same value.
+fn format_score(score: f64) -> String {
+    if score == f64::INFINITY {
+        "+inf".to_string()
+    } else if score == f64::NEG_INFINITY {
+        "-inf".to_string()
+    } else {
+        score.to_string()
+    }
+}
+
+/// Returns the frame of the command made of `args`.
+pub(super) fn command(args: Vec<Bytes>) -> Frame {
+    Frame::Array(args.into_iter().map(Frame::Bulk).collect())
+}
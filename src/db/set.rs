@@ -0,0 +1,282 @@
+//! Commands on set values, including the operations combining several sets.
+
+use super::{
+    random_sample, scan_collection, Db, DbError, RandomSample, SetOperation, State, Value,
+};
+
+use bytes::Bytes;
+use std::collections::HashSet;
+
+impl Db {
+    /// Add `members` to the set stored at `key`. If `key` does not exist, a new
+    /// set is created.
+    ///
+    /// Returns the number of members that were added, not including members
+    /// already present in the set.
+    pub(crate) fn sadd(&self, key: String, members: Vec<Bytes>) -> Result<usize, DbError> {
+        let mut state = self.lock();
+        let set = state
+            .entry_or_insert_with(key, || Value::Set(HashSet::new()))
+            .data
+            .as_set_mut()?;
+
+        Ok(members
+            .into_iter()
+            .filter(|member| set.insert(member.clone()))
+            .count())
+    }
+
+    /// Remove `members` from the set stored at `key`.
+    ///
+    /// Returns the number of members that were removed. When the last member
+    /// is removed, the key is removed as well.
+    pub(crate) fn srem(&self, key: &str, members: &[Bytes]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let removed = match state.entries.get_mut(key) {
+            Some(entry) => {
+                let set = entry.data.as_set_mut()?;
+                members.iter().filter(|member| set.remove(*member)).count()
+            }
+            None => return Ok(0),
+        };
+
+        if removed > 0 {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(removed)
+    }
+
+    /// Returns all members of the set stored at `key`.
+    ///
+    /// An empty list is returned if `key` does not exist.
+    pub(crate) fn smembers(&self, key: &str) -> Result<Vec<Bytes>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_set()?.iter().cloned().collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Iterate over the members of the set stored at `key`, starting at
+    /// `cursor`. See `scan` for the semantics of the arguments.
+    pub(crate) fn sscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<Bytes>), DbError> {
+        let state = self.lock();
+
+        let set = match state.entries.get(key) {
+            Some(entry) => entry.data.as_set()?,
+            None => return Ok((0, vec![])),
+        };
+
+        let members = set.iter().map(|member| (&member[..], member));
+        let (cursor, members) = scan_collection(members, cursor, count, pattern);
+
+        Ok((cursor, members.into_iter().cloned().collect()))
+    }
+
+    /// Returns `true` if `member` is a member of the set stored at `key`.
+    pub(crate) fn sismember(&self, key: &str, member: &[u8]) -> Result<bool, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_set()?.contains(member)),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns, for each of `members`, `true` if it is a member of the set
+    /// stored at `key`.
+    pub(crate) fn smismember(&self, key: &str, members: &[Bytes]) -> Result<Vec<bool>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => {
+                let set = entry.data.as_set()?;
+                Ok(members.iter().map(|member| set.contains(member)).collect())
+            }
+            None => Ok(vec![false; members.len()]),
+        }
+    }
+
+    /// Returns the members of the set resulting from combining the sets stored
+    /// at `keys` with `op`. Keys that do not exist are considered to be empty
+    /// sets.
+    pub(crate) fn set_operation(
+        &self,
+        keys: &[String],
+        op: SetOperation,
+    ) -> Result<Vec<Bytes>, DbError> {
+        let state = self.lock();
+        Ok(state.set_operation(keys, op)?.into_iter().collect())
+    }
+
+    /// Same as `set_operation`, but the resulting set is stored at `dest`,
+    /// replacing any previous value. `dest` is removed if the resulting set is
+    /// empty.
+    ///
+    /// The sets are combined and stored while holding the lock, other clients
+    /// never observe a partially stored set.
+    ///
+    /// Returns the number of members of the resulting set.
+    pub(crate) fn set_operation_store(
+        &self,
+        dest: String,
+        keys: &[String],
+        op: SetOperation,
+    ) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let result = state.set_operation(keys, op)?;
+        let len = result.len();
+
+        state.remove(&dest);
+
+        if len > 0 {
+            state.entry_or_insert_with(dest, || Value::Set(result));
+        }
+
+        Ok(len)
+    }
+
+    /// Returns the number of members of the intersection of the sets stored
+    /// at `keys`, counting at most `limit` members unless `limit` is `0`.
+    ///
+    /// Keys that do not exist are empty sets, so the intersection is empty.
+    pub(crate) fn sintercard(&self, keys: &[String], limit: usize) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        // All the keys are checked to hold sets, even when one of them does
+        // not exist.
+        let mut sets = vec![];
+        let mut missing = false;
+        for key in keys {
+            match state.entries.get(key) {
+                Some(entry) => sets.push(entry.data.as_set()?),
+                None => missing = true,
+            }
+        }
+
+        if missing {
+            return Ok(0);
+        }
+
+        // Only the members of the smallest set need to be checked.
+        sets.sort_by_key(|set| set.len());
+        let (smallest, others) = match sets.split_first() {
+            Some(split) => split,
+            None => return Ok(0),
+        };
+
+        let limit = if limit == 0 { usize::MAX } else { limit };
+
+        Ok(smallest
+            .iter()
+            .filter(|member| others.iter().all(|set| set.contains(*member)))
+            .take(limit)
+            .count())
+    }
+
+    /// Remove up to `count` members picked at random from the set stored at
+    /// `key`, and return them.
+    ///
+    /// When the last member is removed, the key is removed as well.
+    pub(crate) fn spop(&self, key: &str, count: usize) -> Result<Vec<Bytes>, DbError> {
+        let mut state = self.lock();
+
+        let popped = match state.entries.get_mut(key) {
+            Some(entry) => {
+                let set = entry.data.as_set_mut()?;
+                let count = count.min(i64::MAX as usize) as i64;
+                let popped: Vec<Bytes> = random_sample(set.iter().collect(), count)
+                    .cloned()
+                    .collect();
+
+                for member in &popped {
+                    set.remove(member);
+                }
+
+                popped
+            }
+            None => return Ok(vec![]),
+        };
+
+        if !popped.is_empty() {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(popped)
+    }
+
+    /// Returns `count` members picked at random from the set stored at `key`.
+    ///
+    /// With a positive `count`, members are distinct and at most the whole set
+    /// is returned. With a negative `count`, exactly `-count` members are
+    /// returned and the same member may be returned several times.
+    pub(crate) fn srandmember(
+        &self,
+        key: &str,
+        count: i64,
+    ) -> Result<RandomSample<Bytes>, DbError> {
+        let state = self.lock();
+
+        let members = match state.entries.get(key) {
+            Some(entry) => entry.data.as_set()?.iter().cloned().collect(),
+            None => vec![],
+        };
+        Ok(random_sample(members, count))
+    }
+
+    /// Returns the number of members of the set stored at `key`, `0` if `key`
+    /// does not exist.
+    pub(crate) fn scard(&self, key: &str) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_set()?.len()),
+            None => Ok(0),
+        }
+    }
+}
+
+impl State {
+    /// Combine the sets stored at `keys` with `op`. Keys that do not exist
+    /// are considered to be empty sets.
+    fn set_operation(&self, keys: &[String], op: SetOperation) -> Result<HashSet<Bytes>, DbError> {
+        let mut sets = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.entries.get(key) {
+                Some(entry) => sets.push(Some(entry.data.as_set()?)),
+                None => sets.push(None),
+            }
+        }
+
+        let empty = HashSet::new();
+        let mut sets = sets.into_iter().map(|set| set.unwrap_or(&empty));
+
+        let first = match sets.next() {
+            Some(first) => first,
+            None => return Ok(HashSet::new()),
+        };
+
+        let mut result = first.clone();
+        for set in sets {
+            match op {
+                SetOperation::Inter => result.retain(|member| set.contains(member)),
+                SetOperation::Union => result.extend(set.iter().cloned()),
+                SetOperation::Diff => result.retain(|member| !set.contains(member)),
+            }
+        }
+
+        Ok(result)
+    }
+}
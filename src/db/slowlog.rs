@@ -0,0 +1,115 @@
+//! Log of the commands whose execution took longer than the
+//! `slowlog-log-slower-than` parameter, inspected with `SLOWLOG`.
+
+use crate::Frame;
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Maximum number of arguments kept for a command, the last one kept
+/// counting the others.
+const MAX_ARGS: usize = 32;
+
+/// Maximum number of bytes kept for an argument, followed by the number of
+/// bytes dropped.
+const MAX_ARG_LEN: usize = 128;
+
+/// The latest slow commands, the newest first.
+#[derive(Debug)]
+pub(crate) struct Slowlog {
+    entries: VecDeque<SlowlogEntry>,
+
+    /// Identifier of the next entry.
+    next_id: u64,
+}
+
+/// A command recorded by the slow log.
+#[derive(Debug, Clone)]
+pub(crate) struct SlowlogEntry {
+    /// Unique identifier of the entry, incremented for each entry.
+    pub(crate) id: u64,
+
+    /// Unix time at which the command was logged, in seconds.
+    pub(crate) timestamp: u64,
+
+    /// Execution time of the command, in microseconds.
+    pub(crate) duration: u64,
+
+    pub(crate) args: Vec<Bytes>,
+
+    /// Address of the client which sent the command.
+    pub(crate) addr: String,
+}
+
+impl Slowlog {
+    pub(crate) fn new() -> Slowlog {
+        Slowlog {
+            entries: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Record the command `frame`, sent by the client at `addr`, which took
+    /// `duration` to execute. The oldest entries are dropped to keep at most
+    /// `max_len` entries.
+    pub(crate) fn push(&mut self, frame: &Frame, duration: Duration, addr: &str, max_len: usize) {
+        let args = match frame {
+            Frame::Array(args) => args,
+            _ => return,
+        };
+
+        let mut kept: Vec<Bytes> = args
+            .iter()
+            .take(MAX_ARGS)
+            .map(|arg| {
+                let arg = match arg {
+                    Frame::Bulk(arg) => arg.clone(),
+                    arg => Bytes::from(arg.to_string().into_bytes()),
+                };
+                if arg.len() <= MAX_ARG_LEN {
+                    return arg;
+                }
+
+                let mut truncated = arg[..MAX_ARG_LEN].to_vec();
+                truncated.extend_from_slice(
+                    format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN).as_bytes(),
+                );
+                Bytes::from(truncated)
+            })
+            .collect();
+        if args.len() > MAX_ARGS {
+            let more = format!("... ({} more arguments)", args.len() - MAX_ARGS + 1);
+            kept[MAX_ARGS - 1] = Bytes::from(more.into_bytes());
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.entries.push_front(SlowlogEntry {
+            id: self.next_id,
+            timestamp,
+            duration: duration.as_micros() as u64,
+            args: kept,
+            addr: addr.to_string(),
+        });
+        self.next_id += 1;
+        self.entries.truncate(max_len);
+    }
+
+    /// Returns the `count` newest entries, the newest first.
+    pub(crate) fn get(&self, count: usize) -> Vec<SlowlogEntry> {
+        self.entries.iter().take(count).cloned().collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Drop the entries. Identifiers keep increasing.
+    pub(crate) fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,310 @@
+use super::{DbError, SortedSet, Stream, Value};
+use crate::persistence::crc64;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+
+/// Copy of the databases, taken by `Db::snapshot` in order to be saved to disk.
+///
+/// Expirations are stored as Unix times, so that they remain correct once the
+/// snapshot is loaded by another process.
+#[derive(Debug, Default)]
+pub(crate) struct Snapshot {
+    /// Entries of each database, along with the Unix time in milliseconds at
+    /// which they expire.
+    pub(super) databases: Vec<Vec<(String, Value, Option<u64>)>>,
+
+    /// Code of the loaded libraries.
+    pub(super) libraries: Vec<Bytes>,
+
+    /// Number of modifications of the databases when the snapshot was taken.
+    pub(super) changes: u64,
+}
+
+/// Tags identifying the type of an encoded value.
+const STRING: u8 = 0;
+const LIST: u8 = 1;
+const SET: u8 = 2;
+const HASH: u8 = 3;
+const SORTED_SET: u8 = 4;
+const STREAM: u8 = 5;
+
+/// Version of the format of the values serialized by `DUMP`, incremented each
+/// time the encoding of the values changes.
+const DUMP_VERSION: u16 = 1;
+
+impl Snapshot {
+    /// Returns the number of modifications of the databases when the snapshot
+    /// was taken.
+    pub(crate) fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    /// Serializes the snapshot into `dst`.
+    ///
+    /// Integers are big-endian, strings are preceded by their length and
+    /// values by a tag identifying their type.
+    pub(crate) fn encode(&self, dst: &mut BytesMut) {
+        dst.put_u64(self.databases.len() as u64);
+        for entries in &self.databases {
+            dst.put_u64(entries.len() as u64);
+            for (key, value, expires_at) in entries {
+                put_bytes(dst, key.as_bytes());
+                match expires_at {
+                    Some(when) => {
+                        dst.put_u8(1);
+                        dst.put_u64(*when);
+                    }
+                    None => dst.put_u8(0),
+                }
+                value.encode(dst);
+            }
+        }
+
+        dst.put_u64(self.libraries.len() as u64);
+        for code in &self.libraries {
+            put_bytes(dst, code);
+        }
+    }
+
+    /// Deserializes a snapshot serialized by `encode`.
+    pub(crate) fn decode(src: &[u8]) -> crate::Result<Snapshot> {
+        let mut src = Reader::new(src);
+
+        let mut databases = vec![];
+        for _ in 0..src.u64()? {
+            let mut entries = vec![];
+            for _ in 0..src.u64()? {
+                let key = src.string()?;
+                let expires_at = match src.u8()? {
+                    0 => None,
+                    _ => Some(src.u64()?),
+                };
+                entries.push((key, Value::decode(&mut src)?, expires_at));
+            }
+            databases.push(entries);
+        }
+
+        let mut libraries = vec![];
+        for _ in 0..src.u64()? {
+            libraries.push(src.bytes()?);
+        }
+
+        if !src.is_empty() {
+            return Err("invalid snapshot: trailing data".into());
+        }
+
+        Ok(Snapshot {
+            databases,
+            libraries,
+            changes: 0,
+        })
+    }
+}
+
+impl Value {
+    /// Serializes the value as `DUMP` does: encoded as in snapshots, followed
+    /// by `DUMP_VERSION` and the CRC-64 checksum of both.
+    pub(super) fn dump(&self) -> Bytes {
+        let mut dst = BytesMut::new();
+        self.encode(&mut dst);
+        dst.put_u16(DUMP_VERSION);
+        let checksum = crc64(&dst);
+        dst.put_u64(checksum);
+        dst.freeze()
+    }
+
+    /// Deserializes a value serialized by `dump`. Returns `Err` if the version
+    /// or the checksum are wrong, or if the value is malformed.
+    pub(super) fn undump(payload: &[u8]) -> Result<Value, DbError> {
+        if payload.len() < 10 {
+            return Err(DbError::InvalidDump);
+        }
+
+        let (data, checksum) = payload.split_at(payload.len() - 8);
+        if crc64(data).to_be_bytes() != checksum {
+            return Err(DbError::InvalidDump);
+        }
+
+        let (data, version) = data.split_at(data.len() - 2);
+        if version != DUMP_VERSION.to_be_bytes() {
+            return Err(DbError::InvalidDump);
+        }
+
+        let mut src = Reader::new(data);
+        match Value::decode(&mut src) {
+            Ok(value) if src.is_empty() => Ok(value),
+            _ => Err(DbError::BadDataFormat),
+        }
+    }
+
+    fn encode(&self, dst: &mut BytesMut) {
+        match self {
+            Value::String(value) => {
+                dst.put_u8(STRING);
+                put_bytes(dst, value);
+            }
+            Value::List(list) => {
+                dst.put_u8(LIST);
+                dst.put_u64(list.len() as u64);
+                for value in list {
+                    put_bytes(dst, value);
+                }
+            }
+            Value::Set(set) => {
+                dst.put_u8(SET);
+                dst.put_u64(set.len() as u64);
+                for member in set {
+                    put_bytes(dst, member);
+                }
+            }
+            Value::Hash(hash) => {
+                dst.put_u8(HASH);
+                dst.put_u64(hash.len() as u64);
+                for (field, value) in hash {
+                    put_bytes(dst, field.as_bytes());
+                    put_bytes(dst, value);
+                }
+            }
+            Value::SortedSet(zset) => {
+                dst.put_u8(SORTED_SET);
+                dst.put_u64(zset.len() as u64);
+                for (member, score) in zset.iter() {
+                    put_bytes(dst, member);
+                    dst.put_f64(score);
+                }
+            }
+            Value::Stream(stream) => {
+                dst.put_u8(STREAM);
+                stream.encode(dst);
+            }
+        }
+    }
+
+    fn decode(src: &mut Reader<'_>) -> crate::Result<Value> {
+        let value = match src.u8()? {
+            STRING => Value::String(src.bytes()?),
+            LIST => {
+                let mut list = VecDeque::new();
+                for _ in 0..src.u64()? {
+                    list.push_back(src.bytes()?);
+                }
+                Value::List(list)
+            }
+            SET => {
+                let mut set = HashSet::new();
+                for _ in 0..src.u64()? {
+                    set.insert(src.bytes()?);
+                }
+                Value::Set(set)
+            }
+            HASH => {
+                let mut hash = HashMap::new();
+                for _ in 0..src.u64()? {
+                    hash.insert(src.string()?, src.bytes()?);
+                }
+                Value::Hash(hash)
+            }
+            SORTED_SET => {
+                let mut zset = SortedSet::new();
+                for _ in 0..src.u64()? {
+                    let member = src.bytes()?;
+                    let score = src.f64()?;
+                    if score.is_nan() {
+                        return Err("invalid snapshot: NaN score".into());
+                    }
+                    zset.insert(member, score);
+                }
+                Value::SortedSet(zset)
+            }
+            STREAM => Value::Stream(Stream::decode(src)?),
+            tag => return Err(format!("invalid snapshot: unknown value type {}", tag).into()),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Writes `bytes` preceded by their length.
+pub(super) fn put_bytes(dst: &mut BytesMut, bytes: &[u8]) {
+    dst.put_u64(bytes.len() as u64);
+    dst.put_slice(bytes);
+}
+
+/// Reads the fields of a serialized snapshot.
+pub(super) struct Reader<'a> {
+    src: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(src: &'a [u8]) -> Reader<'a> {
+        Reader { src }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.src.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if self.src.len() < len {
+            return Err("invalid snapshot: unexpected end of data".into());
+        }
+
+        let (head, tail) = self.src.split_at(len);
+        self.src = tail;
+        Ok(head)
+    }
+
+    pub(super) fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(super) fn u64(&mut self) -> crate::Result<u64> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    pub(super) fn f64(&mut self) -> crate::Result<f64> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    pub(super) fn bytes(&mut self) -> crate::Result<Bytes> {
+        let len = self.u64()? as usize;
+        Ok(Bytes::copy_from_slice(self.take(len)?))
+    }
+
+    pub(super) fn string(&mut self) -> crate::Result<String> {
+        let len = self.u64()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| "invalid snapshot: invalid string".into())
+    }
+}
+
+/// Converts `instant` to a Unix time in milliseconds.
+pub(super) fn unix_time(instant: Instant) -> u64 {
+    let now = Instant::now();
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let unix_time = if instant >= now {
+        since_epoch + (instant - now)
+    } else {
+        since_epoch.checked_sub(now - instant).unwrap_or_default()
+    };
+    unix_time.as_millis() as u64
+}
+
+/// Converts a Unix time in milliseconds to an `Instant`. Times in the past
+/// are converted to the current instant.
+pub(super) fn instant(unix_time: u64) -> Instant {
+    let now = Instant::now();
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    now + Duration::from_millis(unix_time).saturating_sub(since_epoch)
+}
@@ -0,0 +1,630 @@
+use super::{
+    normalize_limit, normalize_range, random_sample, scan_collection, Db, DbError, RandomSample,
+    ScoreEnd, Value, ZAddOption,
+};
+
+use bytes::Bytes;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+/// A set of unique members, each associated with a floating point score.
+///
+/// Members are kept ordered by score. Members with the same score are ordered
+/// lexicographically. Two structures are maintained: a `HashMap` providing
+/// constant time lookup of a member's score and a `BTreeSet` of `(score,
+/// member)` pairs providing the ordering. Both structures must always be
+/// updated together.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SortedSet {
+    /// Score of each member.
+    scores: HashMap<Bytes, f64>,
+
+    /// Members ordered by score, then by member.
+    ordered: BTreeSet<(Score, Bytes)>,
+}
+
+/// One end of a range of scores, as given to `ZRANGEBYSCORE` and `ZCOUNT`.
+///
+/// Bounds are inclusive unless prefixed by `(`. `-inf` and `+inf` are the
+/// lowest and the highest possible scores.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ScoreBound {
+    /// Score delimiting the range.
+    pub(crate) score: f64,
+
+    /// When `true`, members with exactly `score` are not in the range.
+    pub(crate) exclusive: bool,
+}
+
+/// One end of a range of members, as given to `ZRANGEBYLEX`.
+///
+/// Members are compared as binary strings. This is only meaningful when all
+/// the members of the sorted set have the same score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LexBound {
+    /// `-`, lower than any member.
+    Min,
+
+    /// `+`, greater than any member.
+    Max,
+
+    /// `[member`, the member is in the range.
+    Inclusive(Bytes),
+
+    /// `(member`, the member is not in the range.
+    Exclusive(Bytes),
+}
+
+/// A score with a total ordering.
+///
+/// `f64` only implements `PartialOrd` because of `NaN`. Scores are never
+/// `NaN`, as commands reject such values when parsing, so the comparison
+/// between two scores is always defined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl SortedSet {
+    /// Create a new, empty, `SortedSet`.
+    pub(crate) fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    /// Returns the number of members in the set.
+    pub(crate) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Returns `true` if the set has no members.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Insert `member` with the given `score`. If `member` is already present,
+    /// its score is updated.
+    ///
+    /// Returns `true` if `member` was not previously in the set.
+    pub(crate) fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(prev) => {
+                self.ordered.remove(&(Score(prev), member.clone()));
+                self.ordered.insert((Score(score), member));
+                false
+            }
+            None => {
+                self.ordered.insert((Score(score), member));
+                true
+            }
+        }
+    }
+
+    /// Remove `member` from the set.
+    ///
+    /// Returns `true` if `member` was in the set.
+    pub(crate) fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove_entry(member) {
+            Some((member, score)) => {
+                self.ordered.remove(&(Score(score), member));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove and return the member with the lowest score, or `None` if the
+    /// set is empty.
+    pub(crate) fn pop_min(&mut self) -> Option<(Bytes, f64)> {
+        let (score, member) = self.ordered.pop_first()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// Remove and return the member with the highest score, or `None` if the
+    /// set is empty.
+    pub(crate) fn pop_max(&mut self) -> Option<(Bytes, f64)> {
+        let (score, member) = self.ordered.pop_last()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// Returns the score of `member`, or `None` if it is not in the set.
+    pub(crate) fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Returns the zero-based position of `member` in the set ordered from the
+    /// lowest to the highest score, or `None` if it is not in the set.
+    pub(crate) fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+
+        // `BTreeSet` does not track subtree sizes, so the rank is found by
+        // counting the members ordered before `member`.
+        Some(
+            self.ordered
+                .range(..(Score(score), Bytes::copy_from_slice(member)))
+                .count(),
+        )
+    }
+
+    /// Iterate the members in order, from the lowest to the highest score.
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Iterate, in order, the members with a score between `min` and `max`.
+    pub(crate) fn range_by_score(
+        &self,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> impl Iterator<Item = (&Bytes, f64)> {
+        // The empty member is ordered before all the members with the same
+        // score.
+        self.ordered
+            .range((Score(min.score), Bytes::new())..)
+            .skip_while(move |(score, _)| min.exclusive && score.0 == min.score)
+            .take_while(move |(score, _)| {
+                score.0 < max.score || (!max.exclusive && score.0 == max.score)
+            })
+            .map(|(score, member)| (member, score.0))
+    }
+
+    /// Iterate, in order, the members between `min` and `max`.
+    pub(crate) fn range_by_lex<'a>(
+        &'a self,
+        min: &'a LexBound,
+        max: &'a LexBound,
+    ) -> impl Iterator<Item = &'a Bytes> {
+        self.ordered
+            .iter()
+            .map(|(_, member)| member)
+            .skip_while(move |member| !min.is_below(member))
+            .take_while(move |member| max.is_above(member))
+    }
+}
+
+impl ScoreBound {
+    /// Parse a score bound, returning `None` if `src` is not a valid float,
+    /// optionally prefixed by `(`.
+    pub(crate) fn parse(src: &str) -> Option<ScoreBound> {
+        let (exclusive, src) = match src.strip_prefix('(') {
+            Some(src) => (true, src),
+            None => (false, src),
+        };
+
+        let score: f64 = src.parse().ok()?;
+        if score.is_nan() {
+            return None;
+        }
+
+        Some(ScoreBound { score, exclusive })
+    }
+}
+
+impl LexBound {
+    /// Parse a lex bound, returning `None` if `src` is neither `-` nor `+` and
+    /// does not start with `[` or `(`.
+    pub(crate) fn parse(src: &Bytes) -> Option<LexBound> {
+        match src.first()? {
+            b'-' if src.len() == 1 => Some(LexBound::Min),
+            b'+' if src.len() == 1 => Some(LexBound::Max),
+            b'[' => Some(LexBound::Inclusive(src.slice(1..))),
+            b'(' => Some(LexBound::Exclusive(src.slice(1..))),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `member` is in a range starting at this bound.
+    fn is_below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(bound) => &bound[..] <= member,
+            LexBound::Exclusive(bound) => &bound[..] < member,
+        }
+    }
+
+    /// Returns `true` if `member` is in a range ending at this bound.
+    fn is_above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(bound) => member <= &bound[..],
+            LexBound::Exclusive(bound) => member < &bound[..],
+        }
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        // Scores are never `NaN`, see the `Score` documentation.
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl Db {
+    /// Add `members` with their scores to the sorted set stored at `key`. If a
+    /// member is already in the sorted set, its score is updated. If `key` does
+    /// not exist, a new sorted set is created.
+    ///
+    /// `options` restrict which members are added or updated, see
+    /// `ZAddOption`.
+    ///
+    /// Returns the number of members that were added, not including members
+    /// for which the score was updated unless `ZAddOption::Ch` is given.
+    pub(crate) fn zadd(
+        &self,
+        key: String,
+        members: Vec<(f64, Bytes)>,
+        options: &[ZAddOption],
+    ) -> Result<usize, DbError> {
+        let has = |option| options.contains(&option);
+
+        let mut state = self.lock();
+        let zset = state
+            .entry_or_insert_with(key.clone(), || Value::SortedSet(SortedSet::new()))
+            .data
+            .as_sorted_set_mut()?;
+
+        let mut added = 0;
+        let mut changed = 0;
+
+        for (score, member) in members {
+            // `GT` and `LT` are never given together.
+            let allowed = match zset.score(&member) {
+                Some(_) if has(ZAddOption::Nx) => false,
+                Some(current) if has(ZAddOption::Gt) => score > current,
+                Some(current) if has(ZAddOption::Lt) => score < current,
+                Some(_) => true,
+                None => !has(ZAddOption::Xx),
+            };
+
+            if !allowed {
+                continue;
+            }
+
+            match zset.score(&member) {
+                Some(current) if current == score => {}
+                Some(_) => changed += 1,
+                None => added += 1,
+            }
+
+            zset.insert(member, score);
+        }
+
+        // With `XX`, nothing may have been added to a new sorted set.
+        state.remove_if_empty(&key);
+        state.notify_writes(&key);
+
+        if has(ZAddOption::Ch) {
+            Ok(added + changed)
+        } else {
+            Ok(added)
+        }
+    }
+
+    /// Increment the score of `member` in the sorted set stored at `key` by
+    /// `increment`. A missing member is added with `increment` as its score.
+    ///
+    /// Returns the new score of `member`.
+    pub(crate) fn zincr_by(
+        &self,
+        key: String,
+        increment: f64,
+        member: Bytes,
+    ) -> Result<f64, DbError> {
+        let mut state = self.lock();
+        let zset = state
+            .entry_or_insert_with(key.clone(), || Value::SortedSet(SortedSet::new()))
+            .data
+            .as_sorted_set_mut()?;
+
+        let score = zset.score(&member).unwrap_or(0.0) + increment;
+
+        // Adding infinities of opposite signs.
+        if score.is_nan() {
+            state.remove_if_empty(&key);
+            return Err(DbError::ScoreNan);
+        }
+
+        zset.insert(member, score);
+        state.notify_writes(&key);
+
+        Ok(score)
+    }
+
+    /// Pop up to `count` members, along with their scores, from the `end` of
+    /// the sorted set stored at `key`.
+    ///
+    /// An empty list is returned if `key` does not exist. When the last member
+    /// is popped, the key is removed as well.
+    pub(crate) fn zpop(
+        &self,
+        key: &str,
+        end: ScoreEnd,
+        count: usize,
+    ) -> Result<Vec<(Bytes, f64)>, DbError> {
+        let mut state = self.lock();
+
+        let members: Vec<_> = match state.entries.get_mut(key) {
+            Some(entry) => {
+                let zset = entry.data.as_sorted_set_mut()?;
+                let pop = match end {
+                    ScoreEnd::Min => SortedSet::pop_min,
+                    ScoreEnd::Max => SortedSet::pop_max,
+                };
+
+                (0..count).map_while(|_| pop(zset)).collect()
+            }
+            None => return Ok(vec![]),
+        };
+
+        if !members.is_empty() {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(members)
+    }
+
+    /// Pop a member from the `end` of the first non-empty sorted set stored at
+    /// one of `keys`, on behalf of the blocked client holding `ticket`.
+    ///
+    /// Blocked clients are served in order, as with `blocking_pop`. Returns the
+    /// key the member was popped from along with the member and its score, or
+    /// `None` if there is nothing to pop yet.
+    pub(crate) fn blocking_zpop(
+        &self,
+        keys: &[String],
+        end: ScoreEnd,
+        ticket: u64,
+    ) -> Result<Option<(String, Bytes, f64)>, DbError> {
+        let mut state = self.lock();
+
+        for key in keys {
+            let len = match state.entries.get(key) {
+                Some(entry) => entry.data.as_sorted_set()?.len(),
+                None => continue,
+            };
+
+            if !state.is_next_in_queue(key, ticket, len) {
+                continue;
+            }
+
+            let zset = state
+                .entries
+                .get_mut(key)
+                .unwrap()
+                .data
+                .as_sorted_set_mut()?;
+            let popped = match end {
+                ScoreEnd::Min => zset.pop_min(),
+                ScoreEnd::Max => zset.pop_max(),
+            };
+
+            state.modified(key);
+            state.remove_if_empty(key);
+            state.dequeue(keys, ticket);
+
+            return Ok(popped.map(|(member, score)| (key.clone(), member, score)));
+        }
+
+        Ok(None)
+    }
+
+    /// Returns `count` members, along with their scores, picked at random
+    /// from the sorted set stored at `key`.
+    ///
+    /// As with `srandmember`, members are distinct with a positive `count` and
+    /// may repeat with a negative `count`.
+    pub(crate) fn zrandmember(
+        &self,
+        key: &str,
+        count: i64,
+    ) -> Result<RandomSample<(Bytes, f64)>, DbError> {
+        let state = self.lock();
+
+        let members = match state.entries.get(key) {
+            Some(entry) => entry
+                .data
+                .as_sorted_set()?
+                .iter()
+                .map(|(member, score)| (member.clone(), score))
+                .collect(),
+            None => vec![],
+        };
+        Ok(random_sample(members, count))
+    }
+
+    /// Get the members, along with their scores, of the sorted set stored at
+    /// `key` between the `start` and `stop` ranks, both inclusive.
+    ///
+    /// Members are ordered from the lowest to the highest score. As with
+    /// `lrange`, negative indexes count from the end of the sorted set.
+    pub(crate) fn zrange(
+        &self,
+        key: &str,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(Bytes, f64)>, DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok(vec![]),
+        };
+
+        match normalize_range(start, stop, zset.len()) {
+            Some((start, stop)) => Ok(zset
+                .iter()
+                .skip(start)
+                .take(stop - start + 1)
+                .map(|(member, score)| (member.clone(), score))
+                .collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Get the members of the sorted set stored at `key` with a score between
+    /// `min` and `max`, along with their scores.
+    ///
+    /// `limit` is the number of members in the range to skip and the maximum
+    /// number of members to return. A negative offset returns no member, a
+    /// negative count returns all the remaining members.
+    pub(crate) fn zrange_by_score(
+        &self,
+        key: &str,
+        min: ScoreBound,
+        max: ScoreBound,
+        limit: Option<(i64, i64)>,
+    ) -> Result<Vec<(Bytes, f64)>, DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok(vec![]),
+        };
+
+        let (offset, count) = match normalize_limit(limit) {
+            Some(limit) => limit,
+            None => return Ok(vec![]),
+        };
+
+        Ok(zset
+            .range_by_score(min, max)
+            .skip(offset)
+            .take(count)
+            .map(|(member, score)| (member.clone(), score))
+            .collect())
+    }
+
+    /// Get the members of the sorted set stored at `key` between `min` and
+    /// `max`, compared as binary strings. See `zrange_by_score` for `limit`.
+    pub(crate) fn zrange_by_lex(
+        &self,
+        key: &str,
+        min: &LexBound,
+        max: &LexBound,
+        limit: Option<(i64, i64)>,
+    ) -> Result<Vec<Bytes>, DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok(vec![]),
+        };
+
+        let (offset, count) = match normalize_limit(limit) {
+            Some(limit) => limit,
+            None => return Ok(vec![]),
+        };
+
+        Ok(zset
+            .range_by_lex(min, max)
+            .skip(offset)
+            .take(count)
+            .cloned()
+            .collect())
+    }
+
+    /// Returns the number of members of the sorted set stored at `key` with a
+    /// score between `min` and `max`.
+    pub(crate) fn zcount(
+        &self,
+        key: &str,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_sorted_set()?.range_by_score(min, max).count()),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the score of `member` in the sorted set stored at `key`.
+    ///
+    /// Returns `None` if either the key or the member does not exist.
+    pub(crate) fn zscore(&self, key: &str, member: &[u8]) -> Result<Option<f64>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_sorted_set()?.score(member)),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterate over the members of the sorted set stored at `key`, along with
+    /// their scores, starting at `cursor`. See `scan` for the semantics of the
+    /// arguments.
+    pub(crate) fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<(Bytes, f64)>), DbError> {
+        let state = self.lock();
+
+        let zset = match state.entries.get(key) {
+            Some(entry) => entry.data.as_sorted_set()?,
+            None => return Ok((0, vec![])),
+        };
+
+        let members = zset
+            .iter()
+            .map(|(member, score)| (&member[..], (member, score)));
+        let (cursor, members) = scan_collection(members, cursor, count, pattern);
+
+        Ok((
+            cursor,
+            members
+                .into_iter()
+                .map(|(member, score)| (member.clone(), score))
+                .collect(),
+        ))
+    }
+
+    /// Returns the rank of `member` in the sorted set stored at `key`, with the
+    /// lowest scoring member having rank `0`.
+    ///
+    /// Returns `None` if either the key or the member does not exist.
+    pub(crate) fn zrank(&self, key: &str, member: &[u8]) -> Result<Option<usize>, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_sorted_set()?.rank(member)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove `members` from the sorted set stored at `key`.
+    ///
+    /// Returns the number of members that were removed. When the last member
+    /// is removed, the key is removed as well.
+    pub(crate) fn zrem(&self, key: &str, members: &[Bytes]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let removed = match state.entries.get_mut(key) {
+            Some(entry) => {
+                let zset = entry.data.as_sorted_set_mut()?;
+                members.iter().filter(|member| zset.remove(member)).count()
+            }
+            None => return Ok(0),
+        };
+
+        if removed > 0 {
+            state.modified(key);
+        }
+        state.remove_if_empty(key);
+
+        Ok(removed)
+    }
+}
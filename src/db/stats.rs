@@ -0,0 +1,137 @@
+//! Statistics of the server, reported by `INFO`.
+
+use super::EvictionPolicy;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Counters updated as the server runs. They are atomic so that they are
+/// updated without holding the lock of the databases.
+#[derive(Debug)]
+pub(crate) struct Stats {
+    /// Instant the server started at.
+    started: Instant,
+
+    /// Number of connections accepted.
+    connections: AtomicU64,
+
+    /// Number of connections rejected because `maxclients` clients were
+    /// connected.
+    rejected_connections: AtomicU64,
+
+    /// Number of commands received from the clients.
+    commands: AtomicU64,
+
+    /// Number of commands rejected because a client, or its user, exceeded
+    /// its rate limits.
+    throttled_commands: AtomicU64,
+
+    /// Number of keys read by commands which existed.
+    hits: AtomicU64,
+
+    /// Number of keys read by commands which did not exist.
+    misses: AtomicU64,
+
+    /// Number of keys evicted to keep the data set under `maxmemory`.
+    evicted: AtomicU64,
+}
+
+/// State of the server at some instant, as reported by `INFO`.
+#[derive(Debug)]
+pub(crate) struct Info {
+    pub(crate) uptime: u64,
+    pub(crate) cluster_enabled: bool,
+    pub(crate) connected_clients: usize,
+    pub(crate) maxclients: u64,
+    pub(crate) tracking_clients: usize,
+    pub(crate) used_memory: u64,
+    pub(crate) used_memory_peak: u64,
+    pub(crate) maxmemory: u64,
+    pub(crate) maxmemory_policy: EvictionPolicy,
+    pub(crate) connections: u64,
+    pub(crate) rejected_connections: u64,
+    pub(crate) commands: u64,
+    pub(crate) throttled_commands: u64,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) evicted: u64,
+    pub(crate) pubsub_channels: usize,
+    pub(crate) pubsub_patterns: usize,
+    /// Address of the primary, as `host:port`, if the server is a replica.
+    pub(crate) primary: Option<String>,
+    pub(crate) connected_replicas: usize,
+    pub(crate) replication_id: String,
+    pub(crate) replication_offset: u64,
+    /// For each database holding keys, its index, its number of keys and
+    /// its number of keys with an expiration.
+    pub(crate) keyspace: Vec<(usize, usize, usize)>,
+}
+
+/// Breakdown of the memory used by the databases, as reported by `MEMORY
+/// STATS`. Sizes are in bytes.
+#[derive(Debug)]
+pub(crate) struct MemoryStats {
+    /// Highest memory used since the server started.
+    pub(crate) peak: usize,
+    pub(crate) total: usize,
+    /// Memory used by the maps of the keys and the expirations, rather than
+    /// by the keys and values themselves.
+    pub(crate) overhead: usize,
+    pub(crate) keys: usize,
+    /// For each database holding keys, its index and the overhead of its
+    /// map of the keys and of its map of the expirations.
+    pub(crate) databases: Vec<(usize, usize, usize)>,
+}
+
+impl Stats {
+    pub(crate) fn new() -> Stats {
+        Stats {
+            started: Instant::now(),
+            connections: AtomicU64::new(0),
+            rejected_connections: AtomicU64::new(0),
+            commands: AtomicU64::new(0),
+            throttled_commands: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn count_connection(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_rejected_connection(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_command(&self) {
+        self.commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_throttled_command(&self) {
+        self.throttled_commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count `hits` keys read which existed, and `misses` which did not.
+    pub(crate) fn count_lookups(&self, hits: u64, misses: u64) {
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses.fetch_add(misses, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_eviction(&self) {
+        self.evicted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Fill the counters of `info`.
+    pub(crate) fn fill(&self, info: &mut Info) {
+        info.uptime = self.started.elapsed().as_secs();
+        info.connections = self.connections.load(Ordering::Relaxed);
+        info.rejected_connections = self.rejected_connections.load(Ordering::Relaxed);
+        info.commands = self.commands.load(Ordering::Relaxed);
+        info.throttled_commands = self.throttled_commands.load(Ordering::Relaxed);
+        info.hits = self.hits.load(Ordering::Relaxed);
+        info.misses = self.misses.load(Ordering::Relaxed);
+        info.evicted = self.evicted.load(Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,1156 @@
+use crate::db::rewrite::command;
+use crate::db::snapshot::{put_bytes, Reader};
+use crate::db::{Db, DbError, State, Value};
+use crate::Frame;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+
+/// Identifies an entry in a stream.
+///
+/// IDs are made of two parts: a millisecond timestamp and a sequence number
+/// used to order entries added within the same millisecond. The textual
+/// representation is `<ms>-<seq>`. Entries in a stream are always ordered by
+/// ID, and each new entry must have an ID greater than the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub(crate) struct StreamId {
+    pub(crate) ms: u64,
+    pub(crate) seq: u64,
+}
+
+/// The ID argument of `XADD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NewStreamId {
+    /// `*`: the ID is fully generated by the server.
+    Auto,
+
+    /// `<ms>-*`: the timestamp is provided and the sequence number generated.
+    AutoSeq(u64),
+
+    /// `<ms>-<seq>`: the ID is fully specified.
+    Explicit(StreamId),
+}
+
+/// Fields and values of a stream entry.
+pub(crate) type StreamFields = Vec<(String, Bytes)>;
+
+/// A stream entry: its ID along with its fields and values.
+pub(crate) type StreamEntry = (StreamId, StreamFields);
+
+/// An append-only log of entries, each holding a set of field / value pairs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Stream {
+    /// Entries ordered by ID.
+    entries: BTreeMap<StreamId, StreamFields>,
+
+    /// ID of the last entry ever added to the stream. This is tracked
+    /// separately from `entries` as IDs must keep increasing, even once
+    /// entries are removed.
+    last_id: StreamId,
+
+    /// Consumer groups reading the stream, by name.
+    groups: HashMap<String, ConsumerGroup>,
+}
+
+/// A group of consumers sharing the entries of a stream.
+///
+/// Each entry is delivered to a single consumer of the group. Delivered
+/// entries are tracked in the pending entries list until the consumer
+/// acknowledges them with `XACK`.
+#[derive(Debug, Clone, Default)]
+struct ConsumerGroup {
+    /// ID of the last entry delivered to the group. Entries after this one have
+    /// never been delivered to any consumer of the group.
+    last_delivered: StreamId,
+
+    /// Entries delivered to a consumer but not acknowledged yet, by ID.
+    pending: BTreeMap<StreamId, PendingEntry>,
+
+    /// Consumers of the group, along with the instant they were last active.
+    consumers: HashMap<String, Instant>,
+}
+
+/// An entry of a consumer group's pending entries list.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingEntry {
+    /// Consumer owning the entry
+    pub(crate) consumer: String,
+
+    /// Instant at which the entry was last delivered
+    pub(crate) delivered_at: Instant,
+
+    /// Number of times the entry was delivered
+    pub(crate) delivery_count: u64,
+}
+
+/// Summary of a consumer group's pending entries list, as returned by the
+/// short form of `XPENDING`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingSummary {
+    /// Number of pending entries
+    pub(crate) count: usize,
+
+    /// Smallest and greatest pending IDs, `None` if there are no pending
+    /// entries.
+    pub(crate) bounds: Option<(StreamId, StreamId)>,
+
+    /// Number of pending entries of each consumer with at least one.
+    pub(crate) consumers: Vec<(String, usize)>,
+}
+
+/// How to trim a stream, as requested by `XTRIM` or the trimming options of
+/// `XADD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Trim {
+    /// What entries to remove
+    pub(crate) strategy: TrimStrategy,
+
+    /// Maximum number of entries to remove, `None` for no limit
+    pub(crate) limit: Option<usize>,
+}
+
+/// What entries `Trim` removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrimStrategy {
+    /// Remove the oldest entries until the stream has at most this length.
+    MaxLen(usize),
+
+    /// Remove the entries with an ID smaller than this one.
+    MinId(StreamId),
+}
+
+/// Options of `XCLAIM`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClaimOptions {
+    /// Idle time to set on the claimed entries, instead of resetting it
+    pub(crate) idle: Option<Duration>,
+
+    /// Delivery count to set on the claimed entries
+    pub(crate) retry_count: Option<u64>,
+
+    /// Create pending entries for IDs not pending yet, if they exist in the
+    /// stream
+    pub(crate) force: bool,
+
+    /// Do not increment the delivery count of the claimed entries
+    pub(crate) just_id: bool,
+}
+
+impl StreamId {
+    /// The smallest possible ID, used for the `-` range bound.
+    pub(crate) const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+
+    /// The greatest possible ID, used for the `+` range bound.
+    pub(crate) const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// Parse an ID from its `<ms>-<seq>` representation.
+    ///
+    /// The sequence number may be omitted, in which case `default_seq` is used.
+    /// Returns `None` if `src` is not a valid ID.
+    pub(crate) fn parse(src: &str, default_seq: u64) -> Option<StreamId> {
+        let (ms, seq) = match src.find('-') {
+            Some(i) => (src[..i].parse().ok()?, src[i + 1..].parse().ok()?),
+            None => (src.parse().ok()?, default_seq),
+        };
+
+        Some(StreamId { ms, seq })
+    }
+
+    /// Returns the ID immediately following this one, or `None` if this is the
+    /// greatest possible ID.
+    pub(crate) fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_add(1)?,
+                seq: 0,
+            }),
+        }
+    }
+
+    /// Returns the ID immediately preceding this one, or `None` if this is the
+    /// smallest possible ID.
+    pub(crate) fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_sub(1)?,
+                seq: u64::MAX,
+            }),
+        }
+    }
+}
+
+impl NewStreamId {
+    /// Parse the ID argument of `XADD`. Returns `None` if `src` is not valid.
+    pub(crate) fn parse(src: &str) -> Option<NewStreamId> {
+        if src == "*" {
+            return Some(NewStreamId::Auto);
+        }
+
+        if let Some(ms) = src.strip_suffix("-*") {
+            return ms.parse().ok().map(NewStreamId::AutoSeq);
+        }
+
+        StreamId::parse(src, 0).map(NewStreamId::Explicit)
+    }
+}
+
+impl Stream {
+    /// Create a new, empty, `Stream`.
+    pub(crate) fn new() -> Stream {
+        Stream::default()
+    }
+
+    /// Returns the number of entries in the stream.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the ID of the last entry added to the stream, `0-0` if no entry
+    /// was ever added.
+    pub(crate) fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// Append a new entry to the stream.
+    ///
+    /// The final ID is computed from `id` and must be greater than the ID of
+    /// the last entry. Returns the ID of the new entry.
+    pub(crate) fn add(
+        &mut self,
+        id: NewStreamId,
+        fields: StreamFields,
+    ) -> Result<StreamId, DbError> {
+        let id = match id {
+            NewStreamId::Auto => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_millis() as u64)
+                    .unwrap_or(0);
+
+                // If the clock went backwards, keep using the timestamp of the
+                // last entry so IDs keep increasing.
+                if now > self.last_id.ms {
+                    StreamId { ms: now, seq: 0 }
+                } else {
+                    self.last_id.next().ok_or(DbError::StreamIdTooSmall)?
+                }
+            }
+            NewStreamId::AutoSeq(ms) if ms == self.last_id.ms => {
+                self.last_id.next().ok_or(DbError::StreamIdTooSmall)?
+            }
+            NewStreamId::AutoSeq(ms) => StreamId { ms, seq: 0 },
+            NewStreamId::Explicit(id) => id,
+        };
+
+        if id == StreamId::MIN {
+            return Err(DbError::StreamIdZero);
+        }
+
+        if id <= self.last_id {
+            return Err(DbError::StreamIdTooSmall);
+        }
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+
+        Ok(id)
+    }
+
+    /// Iterate the entries with an ID within `range`, in ID order.
+    pub(crate) fn range(
+        &self,
+        range: RangeInclusive<StreamId>,
+    ) -> impl Iterator<Item = (&StreamId, &StreamFields)> {
+        // `BTreeMap::range` panics when the start of the range is greater than
+        // its end. Such a range is simply empty.
+        let range = if range.start() <= range.end() {
+            Some(self.entries.range(range))
+        } else {
+            None
+        };
+
+        range.into_iter().flatten()
+    }
+
+    /// Remove the entries `ids` from the stream. Returns the number of entries
+    /// that were removed.
+    ///
+    /// Removed entries may still be referenced by the pending entries lists
+    /// of consumer groups, they are skipped when read.
+    pub(crate) fn delete(&mut self, ids: &[StreamId]) -> usize {
+        ids.iter()
+            .filter(|id| self.entries.remove(id).is_some())
+            .count()
+    }
+
+    /// Remove the oldest entries of the stream, according to `trim`. Returns
+    /// the number of entries that were removed.
+    ///
+    /// Redis supports approximate trimming, which only removes whole nodes of
+    /// its internal radix tree. Entries are stored in a `BTreeMap` here, so
+    /// trimming is always exact. It still honors the `limit` of approximate
+    /// trimming.
+    pub(crate) fn trim(&mut self, trim: Trim) -> usize {
+        let mut removed = 0;
+
+        while removed < trim.limit.unwrap_or(usize::MAX) {
+            let first = match self.entries.keys().next() {
+                Some(first) => *first,
+                None => break,
+            };
+
+            let done = match trim.strategy {
+                TrimStrategy::MaxLen(max_len) => self.entries.len() <= max_len,
+                TrimStrategy::MinId(min_id) => first >= min_id,
+            };
+
+            if done {
+                break;
+            }
+
+            self.entries.remove(&first);
+            removed += 1;
+        }
+
+        removed
+    }
+
+    /// Create the consumer group `name`, delivering entries after `id`.
+    /// `None` stands for the last ID of the stream.
+    pub(crate) fn create_group(
+        &mut self,
+        name: String,
+        id: Option<StreamId>,
+    ) -> Result<(), DbError> {
+        if self.groups.contains_key(&name) {
+            return Err(DbError::BusyGroup);
+        }
+
+        let group = ConsumerGroup {
+            last_delivered: id.unwrap_or(self.last_id),
+            ..ConsumerGroup::default()
+        };
+        self.groups.insert(name, group);
+
+        Ok(())
+    }
+
+    /// Destroy the consumer group `name`. Returns `true` if it existed.
+    pub(crate) fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// Set the ID of the last entry delivered to the group. `None` stands for
+    /// the last ID of the stream.
+    pub(crate) fn set_group_id(
+        &mut self,
+        group: &str,
+        id: Option<StreamId>,
+    ) -> Result<(), DbError> {
+        let last_id = self.last_id;
+        self.group_mut(group)?.last_delivered = id.unwrap_or(last_id);
+        Ok(())
+    }
+
+    /// Create `consumer` in `group`. Returns `true` if it did not exist yet.
+    pub(crate) fn create_consumer(
+        &mut self,
+        group: &str,
+        consumer: String,
+    ) -> Result<bool, DbError> {
+        let group = self.group_mut(group)?;
+
+        if group.consumers.contains_key(&consumer) {
+            return Ok(false);
+        }
+
+        group.consumers.insert(consumer, Instant::now());
+        Ok(true)
+    }
+
+    /// Delete `consumer` from `group` along with its pending entries.
+    ///
+    /// Returns the number of pending entries the consumer had.
+    pub(crate) fn delete_consumer(
+        &mut self,
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize, DbError> {
+        let group = self.group_mut(group)?;
+
+        if group.consumers.remove(consumer).is_none() {
+            return Ok(0);
+        }
+
+        let before = group.pending.len();
+        group.pending.retain(|_, entry| entry.consumer != consumer);
+
+        Ok(before - group.pending.len())
+    }
+
+    /// Read entries on behalf of `consumer` of `group`, creating the consumer
+    /// if needed.
+    ///
+    /// When `id` is `None`, up to `count` entries never delivered to the group
+    /// are returned and added to the pending entries list, unless `noack` is
+    /// set. Otherwise, the consumer's pending entries with an ID greater than
+    /// `id` are returned.
+    pub(crate) fn read_group(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        id: Option<StreamId>,
+        count: usize,
+        noack: bool,
+    ) -> Result<Vec<StreamEntry>, DbError> {
+        let entries = &self.entries;
+        let group = self.groups.get_mut(group).ok_or(DbError::NoGroup)?;
+        let now = Instant::now();
+
+        group.consumers.insert(consumer.to_string(), now);
+
+        let id = match id {
+            Some(id) => {
+                // Read the history of entries delivered to the consumer.
+                // Entries removed from the stream since then are skipped.
+                let start = match id.next() {
+                    Some(start) => start,
+                    None => return Ok(vec![]),
+                };
+
+                return Ok(group
+                    .pending
+                    .range(start..)
+                    .filter(|(_, pending)| pending.consumer == consumer)
+                    .filter_map(|(id, _)| Some((*id, entries.get(id)?.clone())))
+                    .take(count)
+                    .collect());
+            }
+            None => group.last_delivered,
+        };
+
+        let start = match id.next() {
+            Some(start) => start,
+            None => return Ok(vec![]),
+        };
+
+        let read: Vec<_> = entries
+            .range(start..)
+            .take(count)
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect();
+
+        for (id, _) in &read {
+            group.last_delivered = *id;
+
+            if !noack {
+                let pending = PendingEntry {
+                    consumer: consumer.to_string(),
+                    delivered_at: now,
+                    delivery_count: 1,
+                };
+                group.pending.insert(*id, pending);
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// Acknowledge entries, removing them from the pending entries list of
+    /// `group`. Returns the number of entries that were pending.
+    pub(crate) fn ack(&mut self, group: &str, ids: &[StreamId]) -> Result<usize, DbError> {
+        let group = self.group_mut(group)?;
+
+        Ok(ids
+            .iter()
+            .filter(|id| group.pending.remove(id).is_some())
+            .count())
+    }
+
+    /// Summarize the pending entries list of `group`.
+    pub(crate) fn pending_summary(&self, group: &str) -> Result<PendingSummary, DbError> {
+        let group = self.groups.get(group).ok_or(DbError::NoGroup)?;
+
+        let first = group.pending.keys().next();
+        let last = group.pending.keys().next_back();
+        let bounds = first.and_then(|first| Some((*first, *last?)));
+
+        let mut consumers: HashMap<&str, usize> = HashMap::new();
+        for entry in group.pending.values() {
+            *consumers.entry(&entry.consumer).or_default() += 1;
+        }
+
+        let mut consumers: Vec<_> = consumers
+            .into_iter()
+            .map(|(consumer, count)| (consumer.to_string(), count))
+            .collect();
+        consumers.sort();
+
+        Ok(PendingSummary {
+            count: group.pending.len(),
+            bounds,
+            consumers,
+        })
+    }
+
+    /// Returns up to `count` entries of the pending entries list of `group`
+    /// with an ID within `range`.
+    ///
+    /// Only entries idle for at least `min_idle` and, if set, owned by
+    /// `consumer` are returned.
+    pub(crate) fn pending(
+        &self,
+        group: &str,
+        range: RangeInclusive<StreamId>,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: Duration,
+    ) -> Result<Vec<(StreamId, PendingEntry)>, DbError> {
+        let group = self.groups.get(group).ok_or(DbError::NoGroup)?;
+
+        if range.start() > range.end() {
+            return Ok(vec![]);
+        }
+
+        let now = Instant::now();
+
+        Ok(group
+            .pending
+            .range(range)
+            .filter(|(_, entry)| match consumer {
+                Some(consumer) => entry.consumer == consumer,
+                None => true,
+            })
+            .filter(|(_, entry)| now - entry.delivered_at >= min_idle)
+            .take(count)
+            .map(|(id, entry)| (*id, entry.clone()))
+            .collect())
+    }
+
+    /// Transfer the ownership of pending entries of `group` idle for at least
+    /// `min_idle` to `consumer`.
+    ///
+    /// Returns the claimed entries. Pending entries removed from the stream
+    /// are dropped from the pending entries list instead.
+    pub(crate) fn claim(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        min_idle: Duration,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<StreamEntry>, DbError> {
+        let entries = &self.entries;
+        let group = self.groups.get_mut(group).ok_or(DbError::NoGroup)?;
+        let now = Instant::now();
+
+        group.consumers.insert(consumer.to_string(), now);
+
+        let mut claimed = vec![];
+
+        for id in ids {
+            let fields = match entries.get(id) {
+                Some(fields) => fields,
+                None => {
+                    group.pending.remove(id);
+                    continue;
+                }
+            };
+
+            let pending = match group.pending.get_mut(id) {
+                Some(pending) if now - pending.delivered_at >= min_idle => pending,
+                Some(_) => continue,
+                None if options.force => group.pending.entry(*id).or_insert(PendingEntry {
+                    consumer: consumer.to_string(),
+                    delivered_at: now,
+                    delivery_count: 0,
+                }),
+                None => continue,
+            };
+
+            pending.consumer = consumer.to_string();
+            pending.delivered_at = match options.idle {
+                Some(idle) => now.checked_sub(idle).unwrap_or(now),
+                None => now,
+            };
+
+            if let Some(retry_count) = options.retry_count {
+                pending.delivery_count = retry_count;
+            } else if !options.just_id {
+                pending.delivery_count += 1;
+            }
+
+            claimed.push((*id, fields.clone()));
+        }
+
+        Ok(claimed)
+    }
+
+    /// Serializes the stream, along with its consumer groups, into `dst`.
+    ///
+    /// Instants are serialized as the number of milliseconds elapsed since
+    /// then.
+    pub(super) fn encode(&self, dst: &mut BytesMut) {
+        let now = Instant::now();
+        let put_id = |dst: &mut BytesMut, id: &StreamId| {
+            dst.put_u64(id.ms);
+            dst.put_u64(id.seq);
+        };
+        let put_idle = |dst: &mut BytesMut, instant: Instant| {
+            dst.put_u64(now.saturating_duration_since(instant).as_millis() as u64)
+        };
+
+        put_id(dst, &self.last_id);
+        dst.put_u64(self.entries.len() as u64);
+        for (id, fields) in &self.entries {
+            put_id(dst, id);
+            dst.put_u64(fields.len() as u64);
+            for (field, value) in fields {
+                put_bytes(dst, field.as_bytes());
+                put_bytes(dst, value);
+            }
+        }
+
+        dst.put_u64(self.groups.len() as u64);
+        for (name, group) in &self.groups {
+            put_bytes(dst, name.as_bytes());
+            put_id(dst, &group.last_delivered);
+
+            dst.put_u64(group.consumers.len() as u64);
+            for (consumer, active_at) in &group.consumers {
+                put_bytes(dst, consumer.as_bytes());
+                put_idle(dst, *active_at);
+            }
+
+            dst.put_u64(group.pending.len() as u64);
+            for (id, pending) in &group.pending {
+                put_id(dst, id);
+                put_bytes(dst, pending.consumer.as_bytes());
+                put_idle(dst, pending.delivered_at);
+                dst.put_u64(pending.delivery_count);
+            }
+        }
+    }
+
+    /// Deserializes a stream serialized by `encode`.
+    pub(super) fn decode(src: &mut Reader<'_>) -> crate::Result<Stream> {
+        let now = Instant::now();
+        let id = |src: &mut Reader<'_>| -> crate::Result<StreamId> {
+            Ok(StreamId {
+                ms: src.u64()?,
+                seq: src.u64()?,
+            })
+        };
+        let idle = |src: &mut Reader<'_>| -> crate::Result<Instant> {
+            let idle = Duration::from_millis(src.u64()?);
+            Ok(now.checked_sub(idle).unwrap_or(now))
+        };
+
+        let mut stream = Stream::new();
+        stream.last_id = id(src)?;
+        for _ in 0..src.u64()? {
+            let entry_id = id(src)?;
+            let mut fields = vec![];
+            for _ in 0..src.u64()? {
+                fields.push((src.string()?, src.bytes()?));
+            }
+            stream.entries.insert(entry_id, fields);
+        }
+
+        for _ in 0..src.u64()? {
+            let name = src.string()?;
+            let mut group = ConsumerGroup {
+                last_delivered: id(src)?,
+                ..ConsumerGroup::default()
+            };
+
+            for _ in 0..src.u64()? {
+                let consumer = src.string()?;
+                group.consumers.insert(consumer, idle(src)?);
+            }
+
+            for _ in 0..src.u64()? {
+                let pending_id = id(src)?;
+                let pending = PendingEntry {
+                    consumer: src.string()?,
+                    delivered_at: idle(src)?,
+                    delivery_count: src.u64()?,
+                };
+                group.pending.insert(pending_id, pending);
+            }
+
+            stream.groups.insert(name, group);
+        }
+
+        Ok(stream)
+    }
+
+    /// Pushes the commands recreating the stream stored at `key`, along with
+    /// its consumer groups, to `dst`.
+    pub(super) fn commands(&self, key: &str, dst: &mut Vec<Frame>) {
+        let now = Instant::now();
+        let key = || Bytes::from(key.to_string());
+
+        for (id, fields) in &self.entries {
+            let mut args = vec!["xadd".into(), key(), id.to_string().into()];
+            for (field, value) in fields {
+                args.push(field.clone().into());
+                args.push(value.clone());
+            }
+            dst.push(command(args));
+        }
+
+        // The last ID is restored by adding then deleting an entry with this ID,
+        // as it may be greater than the ID of the last entry.
+        let last_entry = self.entries.keys().next_back().copied();
+        if self.last_id != StreamId::MIN && Some(self.last_id) != last_entry {
+            let id = Bytes::from(self.last_id.to_string());
+            dst.push(command(vec![
+                "xadd".into(),
+                key(),
+                id.clone(),
+                "field".into(),
+                "value".into(),
+            ]));
+            dst.push(command(vec!["xdel".into(), key(), id]));
+        }
+
+        // An empty stream without consumer groups is created by creating then
+        // destroying a consumer group.
+        if self.entries.is_empty() && self.last_id == StreamId::MIN && self.groups.is_empty() {
+            dst.push(command(vec![
+                "xgroup".into(),
+                "create".into(),
+                key(),
+                "rewrite".into(),
+                "0".into(),
+                "mkstream".into(),
+            ]));
+            dst.push(command(vec![
+                "xgroup".into(),
+                "destroy".into(),
+                key(),
+                "rewrite".into(),
+            ]));
+        }
+
+        for (name, group) in &self.groups {
+            dst.push(command(vec![
+                "xgroup".into(),
+                "create".into(),
+                key(),
+                name.clone().into(),
+                group.last_delivered.to_string().into(),
+                "mkstream".into(),
+            ]));
+
+            for consumer in group.consumers.keys() {
+                dst.push(command(vec![
+                    "xgroup".into(),
+                    "createconsumer".into(),
+                    key(),
+                    name.clone().into(),
+                    consumer.clone().into(),
+                ]));
+            }
+
+            for (id, pending) in &group.pending {
+                let idle = now.saturating_duration_since(pending.delivered_at);
+                dst.push(command(vec![
+                    "xclaim".into(),
+                    key(),
+                    name.clone().into(),
+                    pending.consumer.clone().into(),
+                    "0".into(),
+                    id.to_string().into(),
+                    "idle".into(),
+                    idle.as_millis().to_string().into(),
+                    "retrycount".into(),
+                    pending.delivery_count.to_string().into(),
+                    "force".into(),
+                    "justid".into(),
+                ]));
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the consumer group `name`, or `NoGroup`
+    /// if there is none.
+    fn group_mut(&mut self, name: &str) -> Result<&mut ConsumerGroup, DbError> {
+        self.groups.get_mut(name).ok_or(DbError::NoGroup)
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Db {
+    /// Append an entry made of `fields` to the stream stored at `key`. If `key`
+    /// does not exist, a new stream is created unless `nomkstream` is set.
+    ///
+    /// Once the entry is added, the stream is trimmed according to `trim`, if
+    /// set.
+    ///
+    /// Returns the ID of the new entry, or `None` if the stream does not exist
+    /// and `nomkstream` is set. Clients blocked reading the stream are woken
+    /// up.
+    pub(crate) fn xadd(
+        &self,
+        key: String,
+        id: NewStreamId,
+        fields: StreamFields,
+        nomkstream: bool,
+        trim: Option<Trim>,
+    ) -> Result<Option<StreamId>, DbError> {
+        let mut state = self.lock();
+
+        if nomkstream && !state.entries.contains_key(&key) {
+            return Ok(None);
+        }
+
+        let stream = state
+            .entry_or_insert_with(key.clone(), || Value::Stream(Stream::new()))
+            .data
+            .as_stream_mut()?;
+        let id = stream.add(id, fields)?;
+
+        if let Some(trim) = trim {
+            stream.trim(trim);
+        }
+
+        state.notify_writes(&key);
+
+        Ok(Some(id))
+    }
+
+    /// Trim the stream stored at `key` according to `trim`.
+    ///
+    /// Returns the number of entries that were removed.
+    pub(crate) fn xtrim(&self, key: &str, trim: Trim) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let removed = match state.entries.get_mut(key) {
+            Some(entry) => entry.data.as_stream_mut()?.trim(trim),
+            None => return Ok(0),
+        };
+
+        if removed > 0 {
+            state.modified(key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Remove the entries `ids` from the stream stored at `key`.
+    ///
+    /// Returns the number of entries that were removed. As with trimming, the
+    /// stream is kept once all its entries are removed.
+    pub(crate) fn xdel(&self, key: &str, ids: &[StreamId]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        let removed = match state.entries.get_mut(key) {
+            Some(entry) => entry.data.as_stream_mut()?.delete(ids),
+            None => return Ok(0),
+        };
+
+        if removed > 0 {
+            state.modified(key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Returns the number of entries in the stream stored at `key`, `0` if
+    /// `key` does not exist.
+    pub(crate) fn xlen(&self, key: &str) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_stream()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Get the entries of the stream stored at `key` with an ID between `start`
+    /// and `end`, both inclusive. At most `count` entries are returned.
+    pub(crate) fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+    ) -> Result<Vec<StreamEntry>, DbError> {
+        let state = self.lock();
+
+        let stream = match state.entries.get(key) {
+            Some(entry) => entry.data.as_stream()?,
+            None => return Ok(vec![]),
+        };
+
+        Ok(stream
+            .range(start..=end)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect())
+    }
+
+    /// Get, for each stream key, up to `count` entries with an ID greater than
+    /// the associated ID.
+    ///
+    /// Streams without any such entry are not included in the result.
+    pub(crate) fn xread(
+        &self,
+        streams: &[(String, StreamId)],
+        count: Option<usize>,
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>, DbError> {
+        let state = self.lock();
+        let count = count.unwrap_or(usize::MAX);
+
+        let mut result = vec![];
+
+        for (key, id) in streams {
+            let stream = match state.entries.get(key) {
+                Some(entry) => entry.data.as_stream()?,
+                None => continue,
+            };
+
+            // Entries are read starting right **after** the given ID.
+            let start = match id.next() {
+                Some(start) => start,
+                None => continue,
+            };
+
+            let entries: Vec<_> = stream
+                .range(start..=StreamId::MAX)
+                .take(count)
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect();
+
+            if !entries.is_empty() {
+                result.push((key.clone(), entries));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the ID of the last entry added to the stream stored at `key`,
+    /// `0-0` if `key` does not exist.
+    pub(crate) fn xlast_id(&self, key: &str) -> Result<StreamId, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_stream()?.last_id()),
+            None => Ok(StreamId::MIN),
+        }
+    }
+
+    /// Create the consumer group `group` on the stream stored at `key`,
+    /// delivering entries after `id`. `None` stands for the last ID of the
+    /// stream.
+    ///
+    /// If `key` does not exist, an empty stream is created when `mkstream` is
+    /// set. Otherwise, `NoStream` is returned.
+    pub(crate) fn xgroup_create(
+        &self,
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        mkstream: bool,
+    ) -> Result<(), DbError> {
+        let mut state = self.lock();
+
+        if !mkstream && !state.entries.contains_key(&key) {
+            return Err(DbError::NoStream);
+        }
+
+        state
+            .entry_or_insert_with(key, || Value::Stream(Stream::new()))
+            .data
+            .as_stream_mut()?
+            .create_group(group, id)
+    }
+
+    /// Destroy the consumer group `group` of the stream stored at `key`.
+    ///
+    /// Returns `true` if the group existed.
+    pub(crate) fn xgroup_destroy(&self, key: &str, group: &str) -> Result<bool, DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoStream)?;
+
+        Ok(stream.destroy_group(group))
+    }
+
+    /// Set the ID of the last entry delivered to the consumer group `group` of
+    /// the stream stored at `key`. `None` stands for the last ID of the stream.
+    pub(crate) fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+    ) -> Result<(), DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoStream)?;
+
+        stream.set_group_id(group, id)
+    }
+
+    /// Create `consumer` in the consumer group `group` of the stream stored at
+    /// `key`.
+    ///
+    /// Returns `true` if the consumer did not exist yet.
+    pub(crate) fn xgroup_createconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: String,
+    ) -> Result<bool, DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoStream)?;
+
+        stream.create_consumer(group, consumer)
+    }
+
+    /// Delete `consumer` from the consumer group `group` of the stream stored
+    /// at `key`, along with its pending entries.
+    ///
+    /// Returns the number of pending entries the consumer had.
+    pub(crate) fn xgroup_delconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize, DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoStream)?;
+
+        stream.delete_consumer(group, consumer)
+    }
+
+    /// Read entries from the streams on behalf of `consumer` of the consumer
+    /// group `group`.
+    ///
+    /// For each stream key, the associated ID is either `None`, to read up to
+    /// `count` entries never delivered to the group, or the ID after which the
+    /// consumer's pending entries are read. Entries never delivered before are
+    /// added to the group's pending entries list, unless `noack` is set.
+    ///
+    /// When reading new entries, streams without any are not included in the
+    /// result.
+    pub(crate) fn xreadgroup(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>, DbError> {
+        let mut state = self.lock();
+        let count = count.unwrap_or(usize::MAX);
+
+        let mut result = vec![];
+
+        for (key, id) in streams {
+            let stream = state.stream_mut(key, DbError::NoGroup)?;
+            let entries = stream.read_group(group, consumer, *id, count, noack)?;
+
+            if id.is_some() || !entries.is_empty() {
+                result.push((key.clone(), entries));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Acknowledge entries of the consumer group `group` of the stream stored
+    /// at `key`.
+    ///
+    /// Returns the number of entries that were pending.
+    pub(crate) fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        match state.entries.get_mut(key) {
+            Some(entry) => match entry.data.as_stream_mut()?.ack(group, ids) {
+                Err(DbError::NoGroup) => Ok(0),
+                res => res,
+            },
+            None => Ok(0),
+        }
+    }
+
+    /// Summarize the pending entries list of the consumer group `group` of the
+    /// stream stored at `key`.
+    pub(crate) fn xpending_summary(
+        &self,
+        key: &str,
+        group: &str,
+    ) -> Result<PendingSummary, DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoGroup)?;
+
+        stream.pending_summary(group)
+    }
+
+    /// Returns up to `count` entries of the pending entries list of the
+    /// consumer group `group` of the stream stored at `key`, with an ID within
+    /// `range`.
+    ///
+    /// Only entries idle for at least `min_idle` and, if set, owned by
+    /// `consumer` are returned.
+    pub(crate) fn xpending(
+        &self,
+        key: &str,
+        group: &str,
+        range: RangeInclusive<StreamId>,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: Duration,
+    ) -> Result<Vec<(StreamId, PendingEntry)>, DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoGroup)?;
+
+        stream.pending(group, range, count, consumer, min_idle)
+    }
+
+    /// Transfer the ownership of the pending entries `ids` of the consumer
+    /// group `group` of the stream stored at `key` to `consumer`. Only entries
+    /// idle for at least `min_idle` are claimed.
+    ///
+    /// Returns the claimed entries.
+    pub(crate) fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: Duration,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<StreamEntry>, DbError> {
+        let mut state = self.lock();
+        let stream = state.stream_mut(key, DbError::NoGroup)?;
+
+        stream.claim(group, consumer, min_idle, ids, options)
+    }
+}
+
+impl State {
+    /// Returns the stream stored at `key`, or `missing` if `key` does not exist.
+    fn stream_mut(&mut self, key: &str, missing: DbError) -> Result<&mut Stream, DbError> {
+        match self.entries.get_mut(key) {
+            Some(entry) => entry.data.as_stream_mut(),
+            None => Err(missing),
+        }
+    }
+}
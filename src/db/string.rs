@@ -0,0 +1,428 @@
+//! Commands on string values, including the ones treating them as integers
+//! or floating point numbers.
+
+use super::lcs::{self, Lcs};
+use super::{
+    normalize_range, parse_float, parse_integer, scan_hash, Db, DbError, Entry, SetCondition,
+    SetOptions, Value, LFU_INIT,
+};
+
+use bytes::Bytes;
+use tokio::time::Instant;
+
+impl Db {
+    /// Get the value associated with a key.
+    ///
+    /// Returns `None` if there is no value associated with the key. This may be
+    /// due to never having assigned a value to the key or a previously assigned
+    /// value expired. Returns `Err` if the key holds a value that is not a
+    /// string.
+    pub(crate) fn get(&self, key: &str) -> Result<Option<Bytes>, DbError> {
+        // Acquire the lock, get the entry and clone the value.
+        //
+        // Because data is stored using `Bytes`, a clone here is a shallow
+        // clone. Data is not copied.
+        let mut state = self.lock();
+
+        match state.entries.get_mut(key) {
+            Some(entry) => {
+                entry.access(Instant::now());
+                entry.data.as_string().map(|data| Some(data.clone()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Set the value associated with a key, honoring the `SET` command
+    /// `options`.
+    ///
+    /// If a value is already associated with the key, it is removed. Returns
+    /// whether the value was set, along with the previous string value of the
+    /// key when `options.get` is set. If the previous value is not a string and
+    /// `options.get` is set, `Err` is returned and nothing is set.
+    pub(crate) fn set_with(
+        &self,
+        key: String,
+        value: Bytes,
+        options: SetOptions,
+    ) -> Result<(bool, Option<Bytes>), DbError> {
+        let mut state = self.lock();
+        let now = Instant::now();
+
+        // A key that expired but was not purged yet does not exist.
+        let current = state
+            .entries
+            .get(&key)
+            .filter(|entry| !matches!(entry.expires_at, Some(when) if when <= now));
+
+        let prev_value = match current {
+            Some(entry) if options.get => Some(entry.data.as_string()?.clone()),
+            _ => None,
+        };
+
+        let allowed = match options.condition {
+            Some(SetCondition::Nx) => current.is_none(),
+            Some(SetCondition::Xx) => current.is_some(),
+            None => true,
+        };
+
+        if !allowed {
+            return Ok((false, prev_value));
+        }
+
+        let expires_at = if options.keep_ttl {
+            current.and_then(|entry| entry.expires_at)
+        } else {
+            options.expires_at
+        };
+
+        // An expiration in the past, given as a Unix time, deletes the key.
+        if matches!(expires_at, Some(when) if when <= now) {
+            if state.remove(&key).is_some() {
+                state.notify(b'g', "del", &key);
+            }
+            return Ok((true, prev_value));
+        }
+
+        // Get and increment the next insertion ID. Guarded by the lock, this
+        // ensures a unique identifier is associated with each `set` operation.
+        let id = state.next_id;
+        state.next_id += 1;
+
+        // If this `set` becomes the key that expires **next**, the background
+        // task needs to be notified so it can update its state.
+        //
+        // Whether or not the task needs to be notified is computed during the
+        // `set` routine.
+        let mut notify = false;
+
+        if let Some(when) = expires_at {
+            // Only notify the worker task if the newly inserted expiration is the
+            // **next** key to evict. In this case, the worker needs to be woken up
+            // to update its state.
+            notify = state
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true);
+
+            // Track the expiration.
+            state.track_expiration(when, id, key.clone());
+        }
+
+        if !state.entries.contains_key(&key) {
+            state
+                .scan_index
+                .insert((scan_hash(key.as_bytes()), key.clone()));
+        }
+
+        state.modified(&key);
+        state.notify(b'$', "set", &key);
+        if expires_at.is_some() {
+            state.notify(b'g', "expire", &key);
+        }
+
+        // Insert the entry into the `HashMap`.
+        let prev = state.entries.insert(
+            key,
+            Entry {
+                id,
+                data: Value::String(value),
+                expires_at,
+                last_access: Instant::now(),
+                frequency: LFU_INIT,
+                size: 0,
+            },
+        );
+
+        // If there was a value previously associated with the key **and** it
+        // had an expiration time. The associated entry in the `expirations` index
+        // must also be removed. This avoids leaking data.
+        if let Some(prev) = prev {
+            state.used_memory -= prev.size;
+            if let Some(when) = prev.expires_at {
+                // clear expiration
+                state.expirations.remove(when, prev.id);
+            }
+        }
+
+        // Release the mutex before notifying the background task. This helps
+        // reduce contention by avoiding the background task waking up only to
+        // be unable to acquire the mutex due to this function still holding it.
+        drop(state);
+
+        if notify {
+            // Finally, only notify the background task if it needs to update
+            // its state to reflect a new expiration.
+            self.shared.background_task.notify_one();
+        }
+
+        Ok((true, prev_value))
+    }
+
+    /// Get the string value associated with a key and remove the key.
+    ///
+    /// Returns `Err` without removing the key if it does not hold a string.
+    pub(crate) fn getdel(&self, key: &str) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.lock();
+
+        let value = match state.entries.get(key) {
+            Some(entry) => entry.data.as_string()?.clone(),
+            None => return Ok(None),
+        };
+
+        state.remove(key);
+        Ok(Some(value))
+    }
+
+    /// Get the string value associated with a key and update its expiration.
+    ///
+    /// When `expiration` is `Some`, the expiration of the key is replaced: with
+    /// `Some(None)`, the key is made persistent, and an instant in the past
+    /// deletes the key. With `None`, the expiration is left untouched.
+    pub(crate) fn getex(
+        &self,
+        key: &str,
+        expiration: Option<Option<Instant>>,
+    ) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.lock();
+        let now = Instant::now();
+
+        let value = match state.entries.get(key) {
+            // The key expired but has not been purged yet.
+            Some(entry) if matches!(entry.expires_at, Some(when) if when <= now) => {
+                return Ok(None)
+            }
+            Some(entry) => entry.data.as_string()?.clone(),
+            None => return Ok(None),
+        };
+
+        let notify = match expiration {
+            Some(Some(when)) if when <= now => {
+                state.remove(key);
+                false
+            }
+            Some(when) => state.set_expiration(key, when),
+            None => false,
+        };
+
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Get the values of all the specified keys.
+    ///
+    /// `None` is returned for keys that do not exist or that do not hold a
+    /// string value, so the operation never fails.
+    pub(crate) fn mget(&self, keys: &[String]) -> Vec<Option<Bytes>> {
+        let state = self.lock();
+
+        keys.iter()
+            .map(|key| {
+                let entry = state.entries.get(key)?;
+                entry.data.as_string().ok().cloned()
+            })
+            .collect()
+    }
+
+    /// Set each key to its value, replacing existing values and discarding
+    /// their expiration. All the pairs are set atomically.
+    pub(crate) fn mset(&self, pairs: Vec<(String, Bytes)>) {
+        let mut state = self.lock();
+
+        for (key, value) in pairs {
+            state.remove(&key);
+            state.notify(b'$', "set", &key);
+            state.entry_or_insert_with(key, || Value::String(value));
+        }
+    }
+
+    /// Set each key to its value, only if none of the keys exist.
+    ///
+    /// Returns `true` if the keys were set.
+    pub(crate) fn msetnx(&self, pairs: Vec<(String, Bytes)>) -> bool {
+        let mut state = self.lock();
+
+        if pairs.iter().any(|(key, _)| state.entries.contains_key(key)) {
+            return false;
+        }
+
+        for (key, value) in pairs {
+            // A key may be given more than once, the last value wins.
+            state.remove(&key);
+            state.notify(b'$', "set", &key);
+            state.entry_or_insert_with(key, || Value::String(value));
+        }
+
+        true
+    }
+
+    /// Append `value` to the string stored at `key`. If `key` does not exist,
+    /// it is created holding `value`.
+    ///
+    /// Returns the length of the string after the append.
+    pub(crate) fn append(&self, key: String, value: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.lock();
+        let data = state
+            .entry_or_insert_with(key, || Value::String(Bytes::new()))
+            .data
+            .as_string_mut()?;
+
+        let mut buf = Vec::with_capacity(data.len() + value.len());
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(value);
+        *data = Bytes::from(buf);
+
+        Ok(data.len())
+    }
+
+    /// Returns the length of the string stored at `key`, `0` if the key does
+    /// not exist.
+    pub(crate) fn strlen(&self, key: &str) -> Result<usize, DbError> {
+        let state = self.lock();
+
+        match state.entries.get(key) {
+            Some(entry) => Ok(entry.data.as_string()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the substring of the string stored at `key` between the
+    /// `start` and `end` offsets, both inclusive.
+    ///
+    /// Negative offsets count from the end of the string. Unlike `lrange`, an
+    /// `end` before the start of the string is clamped to the first byte.
+    pub(crate) fn getrange(&self, key: &str, start: i64, end: i64) -> Result<Bytes, DbError> {
+        let state = self.lock();
+
+        let data = match state.entries.get(key) {
+            Some(entry) => entry.data.as_string()?,
+            None => return Ok(Bytes::new()),
+        };
+
+        let end = end.max(-(data.len() as i64));
+
+        match normalize_range(start, end, data.len()) {
+            Some((start, end)) => Ok(data.slice(start..=end)),
+            None => Ok(Bytes::new()),
+        }
+    }
+
+    /// Compute the longest common subsequence of the strings stored at `key1`
+    /// and `key2`. Keys that do not exist are treated as empty strings.
+    pub(crate) fn lcs(&self, key1: &str, key2: &str) -> Result<Lcs, DbError> {
+        let (a, b) = self.lcs_strings(key1, key2)?;
+        lcs::lcs(&a, &b).ok_or(DbError::LcsTooLong)
+    }
+
+    /// Compute the length of the longest common subsequence of the strings
+    /// stored at `key1` and `key2`, as `lcs` does.
+    pub(crate) fn lcs_len(&self, key1: &str, key2: &str) -> Result<usize, DbError> {
+        let (a, b) = self.lcs_strings(key1, key2)?;
+        Ok(lcs::lcs_len(&a, &b))
+    }
+
+    /// Returns the strings stored at `key1` and `key2`, empty if the keys do
+    /// not exist.
+    ///
+    /// Cloning `Bytes` is cheap, the subsequence is computed without holding
+    /// the lock.
+    fn lcs_strings(&self, key1: &str, key2: &str) -> Result<(Bytes, Bytes), DbError> {
+        let state = self.lock();
+
+        let get = |key| match state.entries.get(key) {
+            Some(entry) => entry.data.as_string().cloned(),
+            None => Ok(Bytes::new()),
+        };
+
+        Ok((get(key1)?, get(key2)?))
+    }
+
+    /// Overwrite part of the string stored at `key` with `value`, starting at
+    /// `offset`. The string is padded with zero bytes if it is shorter than
+    /// `offset`, and `key` is created if it does not exist, unless `value` is
+    /// empty.
+    ///
+    /// Returns the length of the string after it was modified.
+    pub(crate) fn setrange(
+        &self,
+        key: String,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<usize, DbError> {
+        let mut state = self.lock();
+
+        if value.is_empty() {
+            // Nothing to write, the key is not created.
+            return match state.entries.get(&key) {
+                Some(entry) => Ok(entry.data.as_string()?.len()),
+                None => Ok(0),
+            };
+        }
+
+        let data = state
+            .entry_or_insert_with(key, || Value::String(Bytes::new()))
+            .data
+            .as_string_mut()?;
+
+        let mut buf = data.to_vec();
+        if buf.len() < offset + value.len() {
+            buf.resize(offset + value.len(), 0);
+        }
+        buf[offset..offset + value.len()].copy_from_slice(value);
+        *data = Bytes::from(buf);
+
+        Ok(data.len())
+    }
+
+    /// Increment the integer stored at `key` by `delta`. If `key` does not
+    /// exist, it is set to `0` before performing the operation. The expiration
+    /// of the key, if any, is retained.
+    ///
+    /// Returns the value after the increment.
+    pub(crate) fn incr_by(&self, key: String, delta: i64) -> Result<i64, DbError> {
+        let mut state = self.lock();
+        let data = state
+            .entry_or_insert_with(key, || Value::String(Bytes::from_static(b"0")))
+            .data
+            .as_string_mut()?;
+
+        let value = parse_integer(data).ok_or(DbError::NotInteger)?;
+        let value = value.checked_add(delta).ok_or(DbError::IncrOverflow)?;
+        *data = Bytes::from(value.to_string().into_bytes());
+
+        Ok(value)
+    }
+
+    /// Increment the float stored at `key` by `delta`. If `key` does not
+    /// exist, it is set to `0` before performing the operation. The expiration
+    /// of the key, if any, is retained.
+    ///
+    /// Returns the value after the increment.
+    pub(crate) fn incr_by_float(&self, key: String, delta: f64) -> Result<f64, DbError> {
+        let mut state = self.lock();
+
+        let current = match state.entries.get(&key) {
+            Some(entry) => parse_float(entry.data.as_string()?).ok_or(DbError::NotFloat)?,
+            None => 0.0,
+        };
+
+        // Compute the new value before creating the key, so that it is not
+        // created when the increment fails.
+        let value = current + delta;
+        if !value.is_finite() {
+            return Err(DbError::NanOrInfinity);
+        }
+
+        *state
+            .entry_or_insert_with(key, || Value::String(Bytes::new()))
+            .data
+            .as_string_mut()? = Bytes::from(value.to_string().into_bytes());
+
+        Ok(value)
+    }
+}
@@ -1,7 +1,7 @@
 //! Provides a type representing a Redis protocol frame as well as utilities for
 //! parsing frames from a byte array.
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::convert::TryInto;
 use std::fmt;
 use std::io::Cursor;
@@ -9,14 +9,23 @@ use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
 
 /// A frame in the Redis protocol.
+///
+/// `Double`, `Boolean`, `Map`, `Set` and `Push` frames are only part of RESP3.
+/// They are written as their RESP2 equivalent to the connections which did
+/// not switch to RESP3 with `HELLO`, see `Connection::write_frame`.
 #[derive(Clone, Debug)]
 pub enum Frame {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
     Null,
     Array(Vec<Frame>),
+    Double(f64),
+    Boolean(bool),
+    Map(Vec<(Frame, Frame)>),
+    Set(Vec<Frame>),
+    Push(Vec<Frame>),
 }
 
 #[derive(Debug)]
@@ -34,28 +43,30 @@ impl Frame {
         Frame::Array(vec![])
     }
 
-    /// Push a "bulk" frame into the array. `self` must be an Array frame.
+    /// Push a "bulk" frame into the array. `self` must be an Array frame, or a
+    /// Push frame.
     ///
     /// # Panics
     ///
     /// panics if `self` is not an array
     pub(crate) fn push_bulk(&mut self, bytes: Bytes) {
         match self {
-            Frame::Array(vec) => {
+            Frame::Array(vec) | Frame::Push(vec) => {
                 vec.push(Frame::Bulk(bytes));
             }
             _ => panic!("not an array frame"),
         }
     }
 
-    /// Push an "integer" frame into the array. `self` must be an Array frame.
+    /// Push an "integer" frame into the array. `self` must be an Array frame,
+    /// or a Push frame.
     ///
     /// # Panics
     ///
     /// panics if `self` is not an array
-    pub(crate) fn push_int(&mut self, value: u64) {
+    pub(crate) fn push_int(&mut self, value: i64) {
         match self {
-            Frame::Array(vec) => {
+            Frame::Array(vec) | Frame::Push(vec) => {
                 vec.push(Frame::Integer(value));
             }
             _ => panic!("not an array frame"),
@@ -74,7 +85,7 @@ impl Frame {
                 Ok(())
             }
             b':' => {
-                let _ = get_decimal(src)?;
+                let _ = get_signed_decimal(src)?;
                 Ok(())
             }
             b'$' => {
@@ -89,7 +100,7 @@ impl Frame {
                     skip(src, len + 2)
                 }
             }
-            b'*' => {
+            b'*' | b'~' | b'>' => {
                 let len = get_decimal(src)?;
 
                 for _ in 0..len {
@@ -98,6 +109,19 @@ impl Frame {
 
                 Ok(())
             }
+            b'%' => {
+                let len = get_decimal(src)?;
+
+                for _ in 0..2 * len {
+                    Frame::check(src)?;
+                }
+
+                Ok(())
+            }
+            b',' | b'#' | b'_' => {
+                get_line(src)?;
+                Ok(())
+            }
             actual => Err(format!("protocol error; invalid frame type byte `{}`", actual).into()),
         }
     }
@@ -124,8 +148,8 @@ impl Frame {
                 Ok(Frame::Error(string))
             }
             b':' => {
-                let len = get_decimal(src)?;
-                Ok(Frame::Integer(len))
+                let value = get_signed_decimal(src)?;
+                Ok(Frame::Integer(value))
             }
             b'$' => {
                 if b'-' == peek_u8(src)? {
@@ -153,20 +177,95 @@ impl Frame {
                     Ok(Frame::Bulk(data))
                 }
             }
-            b'*' => {
+            b'*' => Ok(Frame::Array(parse_entries(src)?)),
+            b'~' => Ok(Frame::Set(parse_entries(src)?)),
+            b'>' => Ok(Frame::Push(parse_entries(src)?)),
+            b'%' => {
                 let len = get_decimal(src)?.try_into()?;
                 let mut out = Vec::with_capacity(len);
 
                 for _ in 0..len {
-                    out.push(Frame::parse(src)?);
+                    let key = Frame::parse(src)?;
+                    let value = Frame::parse(src)?;
+                    out.push((key, value));
                 }
 
-                Ok(Frame::Array(out))
+                Ok(Frame::Map(out))
+            }
+            b',' => {
+                let value = std::str::from_utf8(get_line(src)?)
+                    .ok()
+                    .and_then(|line| line.parse().ok())
+                    .ok_or("protocol error; invalid frame format")?;
+                Ok(Frame::Double(value))
+            }
+            b'#' => match get_line(src)? {
+                b"t" => Ok(Frame::Boolean(true)),
+                b"f" => Ok(Frame::Boolean(false)),
+                _ => Err("protocol error; invalid frame format".into()),
+            },
+            b'_' => {
+                get_line(src)?;
+                Ok(Frame::Null)
             }
             _ => unimplemented!(),
         }
     }
 
+    /// Encodes the frame into `dst`, as `Connection::write_frame` writes it to
+    /// a RESP2 connection.
+    pub(crate) fn encode(&self, dst: &mut BytesMut) {
+        match self {
+            Frame::Array(val) | Frame::Set(val) | Frame::Push(val) => {
+                dst.put_u8(b'*');
+                put_decimal(dst, val.len() as i64);
+                for entry in val {
+                    entry.encode(dst);
+                }
+            }
+            Frame::Map(val) => {
+                dst.put_u8(b'*');
+                put_decimal(dst, 2 * val.len() as i64);
+                for (key, value) in val {
+                    key.encode(dst);
+                    value.encode(dst);
+                }
+            }
+            Frame::Simple(val) => {
+                dst.put_u8(b'+');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Error(val) => {
+                dst.put_u8(b'-');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Integer(val) => {
+                dst.put_u8(b':');
+                put_decimal(dst, *val);
+            }
+            Frame::Null => dst.put_slice(b"$-1\r\n"),
+            Frame::Bulk(val) => {
+                dst.put_u8(b'$');
+                put_decimal(dst, val.len() as i64);
+                dst.put_slice(val);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Double(val) => {
+                let val = format_double(*val);
+                dst.put_u8(b'$');
+                put_decimal(dst, val.len() as i64);
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Boolean(val) => {
+                dst.put_u8(b':');
+                put_decimal(dst, *val as i64);
+            }
+        }
+    }
+
     /// Converts the frame to an "unexpected frame" error
     pub(crate) fn to_error(&self) -> crate::Error {
         format!("unexpected frame: {}", self).into()
@@ -196,7 +295,7 @@ impl fmt::Display for Frame {
                 Err(_) => write!(fmt, "{:?}", msg),
             },
             Frame::Null => "(nil)".fmt(fmt),
-            Frame::Array(parts) => {
+            Frame::Array(parts) | Frame::Set(parts) | Frame::Push(parts) => {
                 for (i, part) in parts.iter().enumerate() {
                     if i > 0 {
                         write!(fmt, " ")?;
@@ -204,12 +303,51 @@ impl fmt::Display for Frame {
                     }
                 }
 
+                Ok(())
+            }
+            Frame::Double(num) => format_double(*num).fmt(fmt),
+            Frame::Boolean(val) => val.fmt(fmt),
+            Frame::Map(entries) => {
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, " ")?;
+                    }
+                    write!(fmt, "{} {}", key, value)?;
+                }
+
                 Ok(())
             }
         }
     }
 }
 
+/// Returns `true` if `byte` is the prefix of a frame type. Inline commands do
+/// not start with one.
+pub(crate) fn is_frame_type(byte: u8) -> bool {
+    b"+-:$*,#_%~>".contains(&byte)
+}
+
+/// Formats a double as written by RESP3, and as a bulk string by RESP2.
+pub(crate) fn format_double(val: f64) -> String {
+    if val.is_nan() {
+        "nan".to_string()
+    } else {
+        val.to_string()
+    }
+}
+
+/// Parse the length of an aggregate frame, followed by its entries.
+fn parse_entries(src: &mut Cursor<&[u8]>) -> Result<Vec<Frame>, Error> {
+    let len = get_decimal(src)?.try_into()?;
+    let mut out = Vec::with_capacity(len);
+
+    for _ in 0..len {
+        out.push(Frame::parse(src)?);
+    }
+
+    Ok(out)
+}
+
 fn peek_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
     if !src.has_remaining() {
         return Err(Error::Incomplete);
@@ -244,7 +382,22 @@ fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
     atoi::<u64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
 }
 
+/// Read a signed decimal. Unlike lengths, integer frames may be negative.
+fn get_signed_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, Error> {
+    let line = get_line(src)?;
+
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|line| line.parse().ok())
+        .ok_or_else(|| "protocol error; invalid frame format".into())
+}
+
 /// Find a line
+fn put_decimal(dst: &mut BytesMut, val: i64) {
+    dst.put_slice(val.to_string().as_bytes());
+    dst.put_slice(b"\r\n");
+}
+
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     // Scan the bytes directly
     let start = src.position() as usize;
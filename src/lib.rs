@@ -37,11 +37,29 @@ pub mod frame;
 pub use frame::Frame;
 
 mod db;
-use db::Db;
+use db::{Db, ListEnd};
 
 mod parse;
 use parse::{Parse, ParseError};
 
+mod persistence;
+
+mod replication;
+
+mod cluster;
+
+mod acl;
+
+mod script;
+
+#[cfg(feature = "tls")]
+mod tls;
+
+/// The TLS implementation the server and the clients use, with which the
+/// configuration of `client::connect_tls` is built.
+#[cfg(feature = "tls")]
+pub use tokio_rustls::rustls;
+
 pub mod server;
 
 mod buffer;
@@ -55,6 +73,11 @@ use shutdown::Shutdown;
 /// Used if no port is specified.
 pub const DEFAULT_PORT: &str = "6379";
 
+/// Default number of databases of a redis server.
+///
+/// Used if no number is specified.
+pub const DEFAULT_DATABASES: usize = 16;
+
 /// Error returned by most functions.
 ///
 /// When writing a real application, one might want to consider a specialized
@@ -1,6 +1,7 @@
 use crate::Frame;
 
 use bytes::Bytes;
+use std::convert::TryFrom;
 use std::{fmt, str, vec};
 
 /// Utility for parsing a command
@@ -105,8 +106,9 @@ impl Parse {
         const MSG: &str = "protocol error; invalid number";
 
         match self.next()? {
-            // An integer frame type is already stored as an integer.
-            Frame::Integer(v) => Ok(v),
+            // An integer frame type is already stored as an integer, it only
+            // has to be non-negative.
+            Frame::Integer(v) => u64::try_from(v).map_err(|_| MSG.into()),
             // Simple and bulk frames must be parsed as integers. If the parsing
             // fails, an error is returned.
             Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| MSG.into()),
@@ -115,6 +117,60 @@ impl Parse {
         }
     }
 
+    /// Return the next entry as a signed integer.
+    ///
+    /// Same as `next_int`, but negative values are accepted. This is used for
+    /// arguments such as list indexes, where negative values count from the
+    /// end.
+    ///
+    /// If the next entry cannot be represented as a signed integer, then an
+    /// error is returned.
+    pub(crate) fn next_signed_int(&mut self) -> Result<i64, ParseError> {
+        const MSG: &str = "protocol error; invalid number";
+
+        match self.next()? {
+            Frame::Integer(v) => Ok(v),
+            Frame::Simple(data) => data.parse::<i64>().map_err(|_| MSG.into()),
+            Frame::Bulk(data) => str::from_utf8(&data[..])
+                .ok()
+                .and_then(|s| s.parse::<i64>().ok())
+                .ok_or_else(|| MSG.into()),
+            frame => Err(format!("protocol error; expected int frame but got {:?}", frame).into()),
+        }
+    }
+
+    /// Return the next entry as a floating point number.
+    ///
+    /// `inf`, `+inf` and `-inf` are accepted, matching the values Redis accepts
+    /// for scores. `NaN` is not a number as far as Redis is concerned and is
+    /// rejected.
+    ///
+    /// If the next entry cannot be represented as a float, then an error is
+    /// returned.
+    pub(crate) fn next_float(&mut self) -> Result<f64, ParseError> {
+        const MSG: &str = "protocol error; invalid float";
+
+        let value = match self.next()? {
+            Frame::Integer(v) => v as f64,
+            Frame::Simple(data) => data.parse::<f64>().map_err(|_| MSG)?,
+            Frame::Bulk(data) => str::from_utf8(&data[..])
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or(MSG)?,
+            frame => {
+                return Err(
+                    format!("protocol error; expected float frame but got {:?}", frame).into(),
+                )
+            }
+        };
+
+        if value.is_nan() {
+            return Err(MSG.into());
+        }
+
+        Ok(value)
+    }
+
     /// Ensure there are no more entries in the array
     pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
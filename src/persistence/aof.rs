@@ -0,0 +1,298 @@
+use crate::cmd::Command;
+use crate::db::Snapshot;
+use crate::frame::{self, Frame};
+use crate::{Connection, Db, Shutdown};
+
+use bytes::{Bytes, BytesMut};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task;
+use tokio::time::{self, Duration};
+use tracing::{error, warn};
+
+/// When the AOF is flushed to disk with `fsync`.
+///
+/// Commands are always written to the file before replying, the policy only
+/// determines how many of them may be lost if the machine crashes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fsync {
+    /// After every command, before replying. The safest and slowest policy.
+    Always,
+
+    /// Once per second, by a background task. At most a second of commands
+    /// may be lost. This is the default policy.
+    #[default]
+    EverySec,
+
+    /// Never, the operating system flushes the file when it sees fit.
+    No,
+}
+
+impl FromStr for Fsync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Fsync, String> {
+        match &s.to_lowercase()[..] {
+            "always" => Ok(Fsync::Always),
+            "everysec" => Ok(Fsync::EverySec),
+            "no" => Ok(Fsync::No),
+            _ => Err(format!("invalid fsync policy `{}`", s)),
+        }
+    }
+}
+
+/// Writer appending the commands modifying the databases to the AOF.
+///
+/// Commands are appended as the frames they were received as, preceded by a
+/// `SELECT` whenever they apply to another database than the previous one.
+/// The expirations they set are followed by a `PEXPIREAT`, so that keys expire
+/// at the same time once replayed. Commands whose effect is random, such as
+/// `SPOP`, may have another effect when replayed.
+#[derive(Debug)]
+pub(crate) struct Aof {
+    file: File,
+
+    path: PathBuf,
+
+    fsync: Fsync,
+
+    /// Database the commands appended so far apply to, `None` until a
+    /// `SELECT` is appended.
+    index: Option<usize>,
+
+    /// Frames appended since the last call to `flush`.
+    buffer: BytesMut,
+
+    /// Frames appended since the rewrite in progress started, if any. They
+    /// are appended to the rewritten file once the keyspace is written to it.
+    rewrite: Option<BytesMut>,
+}
+
+impl Aof {
+    /// Open the AOF at `path`, creating it if it does not exist.
+    pub(crate) fn open(path: &Path, fsync: Fsync) -> io::Result<Aof> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Aof {
+            file,
+            path: path.to_path_buf(),
+            fsync,
+            index: None,
+            buffer: BytesMut::new(),
+            rewrite: None,
+        })
+    }
+
+    /// Returns the policy determining when the file is flushed to disk.
+    pub(crate) fn fsync(&self) -> Fsync {
+        self.fsync
+    }
+
+    /// Returns the path of the file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a new handle to the file, with which the background task flushes
+    /// it to disk.
+    pub(crate) fn try_clone_file(&self) -> io::Result<File> {
+        self.file.try_clone()
+    }
+
+    /// Append `frame`, a command applying to the database at `index`.
+    ///
+    /// The frame is buffered until `flush` is called.
+    pub(crate) fn append(&mut self, index: usize, frame: &Frame) {
+        if self.index != Some(index) {
+            let mut select = Frame::array();
+            select.push_bulk(Bytes::from("select".as_bytes()));
+            select.push_bulk(Bytes::from(index.to_string().into_bytes()));
+            select.encode(&mut self.buffer);
+
+            self.index = Some(index);
+        }
+
+        frame.encode(&mut self.buffer);
+    }
+
+    /// Write the appended frames to the file, flushing it to disk if the
+    /// policy is `Always`.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        let buffer = self.buffer.split();
+        if let Some(rewrite) = &mut self.rewrite {
+            rewrite.extend_from_slice(&buffer);
+        }
+        self.file.write_all(&buffer)?;
+
+        if self.fsync == Fsync::Always {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if a rewrite is in progress.
+    pub(crate) fn rewriting(&self) -> bool {
+        self.rewrite.is_some()
+    }
+
+    /// Start buffering the appended frames, until `finish_rewrite` is called.
+    ///
+    /// The next frame appended is preceded by a `SELECT`, as the rewritten
+    /// file does not end with the database the frames apply to.
+    pub(crate) fn start_rewrite(&mut self) {
+        self.rewrite = Some(BytesMut::new());
+        self.index = None;
+    }
+
+    /// Complete the rewrite in progress. `rewritten` is the file holding the
+    /// rewritten keyspace and its path, or `None` if the rewrite failed.
+    ///
+    /// The frames appended since the rewrite started are appended to the
+    /// rewritten file, which then atomically replaces the AOF.
+    pub(crate) fn finish_rewrite(&mut self, rewritten: Option<(File, &Path)>) -> io::Result<()> {
+        let buffer = match self.rewrite.take() {
+            Some(buffer) => buffer,
+            None => return Ok(()),
+        };
+        let (mut file, path) = match rewritten {
+            Some(rewritten) => rewritten,
+            None => return Ok(()),
+        };
+
+        file.write_all(&buffer)?;
+        file.sync_data()?;
+        fs::rename(path, &self.path)?;
+
+        self.file = file;
+        Ok(())
+    }
+}
+
+/// Replay the commands of the AOF at `path`, applying them to `db`.
+///
+/// The commands are applied through the same dispatcher as the commands of
+/// the clients, all within a single transaction. Returns `false` if there is
+/// no AOF at `path`, and `Err` if the file is not a valid AOF.
+///
+/// A command cut short at the end of the file, as happens when the server
+/// crashes while appending it, is removed from the file.
+pub(crate) async fn load_aof(db: &Db, path: &Path) -> crate::Result<bool> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+
+    // Replies are discarded, the connection never reads from nor writes to
+    // its stream.
+    let mut dst = Connection::from_stream(Cursor::new(Vec::new()));
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let mut shutdown = Shutdown::new(notify_shutdown.subscribe());
+
+    // Commands never wait within a transaction, blocking commands complete
+    // as they did when they were appended.
+    let mut transaction = db.transaction();
+
+    let mut src = Cursor::new(&data[..]);
+    while (src.position() as usize) < data.len() {
+        let start = src.position();
+
+        match Frame::check(&mut src) {
+            Ok(()) => {}
+            Err(frame::Error::Incomplete) => {
+                warn!(path = %path.display(), "AOF truncated, removing the last command");
+                OpenOptions::new().write(true).open(path)?.set_len(start)?;
+                break;
+            }
+            Err(err) => return Err(format!("invalid AOF: {}", err).into()),
+        }
+
+        src.set_position(start);
+        let frame = Frame::parse(&mut src)?;
+        let cmd = match Command::from_frame(frame) {
+            Ok(Command::Unknown(cmd)) => {
+                return Err(format!("invalid AOF: unknown command `{}`", cmd.get_name()).into())
+            }
+            Ok(cmd) => cmd,
+            Err(err) => return Err(format!("invalid AOF: {}", err).into()),
+        };
+
+        dst.replace_replies(Some(vec![]));
+        match cmd.apply_now(transaction.db(), &mut dst, &mut shutdown) {
+            Some(res) => res?,
+            None => return Err("invalid AOF: a command blocked the transaction".into()),
+        }
+    }
+
+    Ok(true)
+}
+
+/// Rewrite the AOF as the commands recreating `snapshot`, taken by
+/// `Db::begin_aof_rewrite`.
+///
+/// The commands are written to a temporary file next to the AOF, which
+/// replaces it once the commands appended in the meantime are written to it
+/// as well.
+pub(crate) async fn rewrite_aof(db: &Db, snapshot: Snapshot, path: &Path) -> crate::Result<()> {
+    let tmp_path = path.with_extension("rewrite");
+
+    let rewritten = {
+        let tmp_path = tmp_path.clone();
+        task::spawn_blocking(move || -> io::Result<File> {
+            let mut buffer = BytesMut::new();
+            for frame in snapshot.commands() {
+                frame.encode(&mut buffer);
+            }
+
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&buffer)?;
+            Ok(file)
+        })
+        .await?
+    };
+
+    match rewritten {
+        Ok(file) => db.end_aof_rewrite(Some((file, &tmp_path)))?,
+        Err(err) => {
+            db.end_aof_rewrite(None)?;
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err.into());
+        }
+    }
+    Ok(())
+}
+
+/// Routine executed by the task flushing the AOF to disk once per second,
+/// when the policy is `EverySec`.
+///
+/// The file is flushed a last time when the server shuts down.
+pub(crate) async fn aof_task(db: Db, mut shutdown: Shutdown, _shutdown_complete: mpsc::Sender<()>) {
+    loop {
+        let done = tokio::select! {
+            _ = time::sleep(Duration::from_secs(1)) => false,
+            _ = shutdown.recv() => true,
+        };
+
+        if let Err(err) = sync_aof(&db).await {
+            error!(cause = %err, "failed to flush the AOF");
+        }
+
+        if done {
+            return;
+        }
+    }
+}
+
+/// Flush the AOF of `db` to disk.
+///
+/// The file is fetched each time, as it is replaced when the AOF is rewritten.
+async fn sync_aof(db: &Db) -> io::Result<()> {
+    let file = match db.aof_file() {
+        Some(file) => file?,
+        None => return Ok(()),
+    };
+    task::spawn_blocking(move || file.sync_data()).await?
+}
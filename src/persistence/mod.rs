@@ -0,0 +1,18 @@
+//! Persistence of the databases to disk.
+//!
+//! Snapshots hold the whole content of the databases, along with the loaded
+//! libraries. They are saved periodically, and once more when the server shuts
+//! down, then loaded when the server starts. They may also be saved on demand
+//! with `SAVE` and `BGSAVE`.
+//!
+//! The AOF, or append-only file, logs every command modifying the databases
+//! as it is applied. When the server starts, the commands are replayed to
+//! rebuild the databases. `BGREWRITEAOF` compacts it into the commands
+//! recreating the current content of the databases.
+
+mod aof;
+pub use aof::Fsync;
+pub(crate) use aof::{aof_task, load_aof, rewrite_aof, Aof};
+
+mod snapshot;
+pub(crate) use snapshot::{crc64, load_snapshot, save_snapshot, snapshot_task, write_snapshot};
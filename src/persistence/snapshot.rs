@@ -0,0 +1,204 @@
+use crate::db::Snapshot;
+use crate::{Db, Shutdown};
+
+use bytes::{BufMut, BytesMut};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task;
+use tokio::time::{self, Duration};
+use tracing::{error, info};
+
+/// Interval between two checks of the save points, unless one of them is
+/// shorter.
+const SAVE_POINTS_CHECK: Duration = Duration::from_secs(1);
+
+/// Bytes at the start of every snapshot file.
+const MAGIC: &[u8] = b"MINIREDIS";
+
+/// Version of the format of the snapshot files, incremented each time the
+/// format changes.
+const VERSION: u32 = 1;
+
+/// Save a snapshot of the databases of `db` to `path`.
+///
+/// The databases are copied while holding the lock, then written to disk by
+/// `write_snapshot`. Only one snapshot is saved at a time: `Err` is returned
+/// if another one is being saved.
+pub(crate) async fn save_snapshot(db: &Db, path: &Path) -> crate::Result<()> {
+    match db.begin_snapshot() {
+        Some(snapshot) => write_snapshot(db, snapshot, path).await,
+        None => Err("ERR Background save already in progress".into()),
+    }
+}
+
+/// Write `snapshot`, returned by `Db::begin_snapshot`, to `path`.
+///
+/// The snapshot is written on a blocking thread, or on the current thread
+/// within a transaction, which may not yield. It is first written to a
+/// temporary file, renamed once complete, so that a failure while saving never
+/// corrupts the previous snapshot.
+pub(crate) async fn write_snapshot(db: &Db, snapshot: Snapshot, path: &Path) -> crate::Result<()> {
+    let path = path.to_path_buf();
+    let res = if db.in_transaction() {
+        let res = write(&snapshot, &path);
+        Ok((snapshot, res))
+    } else {
+        task::spawn_blocking(move || {
+            let res = write(&snapshot, &path);
+            (snapshot, res)
+        })
+        .await
+    };
+
+    match res {
+        Ok((snapshot, Ok(()))) => {
+            db.end_snapshot(Some(&snapshot));
+            Ok(())
+        }
+        Ok((_, Err(err))) => {
+            db.end_snapshot(None);
+            Err(err.into())
+        }
+        Err(err) => {
+            db.end_snapshot(None);
+            Err(err.into())
+        }
+    }
+}
+
+/// Load the snapshot saved to `path` into `db`.
+///
+/// Returns `false` if there is no snapshot at `path`, and `Err` if the file is
+/// not a valid snapshot.
+pub(crate) fn load_snapshot(db: &Db, path: &Path) -> crate::Result<bool> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+
+    let header_len = MAGIC.len() + 4;
+    if data.len() < header_len + 8 || !data.starts_with(MAGIC) {
+        return Err("invalid snapshot: not a snapshot file".into());
+    }
+
+    let (data, checksum) = data.split_at(data.len() - 8);
+    if crc64(data).to_be_bytes() != checksum {
+        return Err("invalid snapshot: wrong checksum".into());
+    }
+
+    let mut version = [0; 4];
+    version.copy_from_slice(&data[MAGIC.len()..header_len]);
+    let version = u32::from_be_bytes(version);
+    if version != VERSION {
+        return Err(format!("invalid snapshot: unsupported version {}", version).into());
+    }
+
+    db.restore(Snapshot::decode(&data[header_len..])?)?;
+    Ok(true)
+}
+
+/// Routine executed by the task saving the snapshots.
+///
+/// A snapshot is saved to `path` once one of the `save_points` is reached:
+/// the duration of the save point elapsed since the last snapshot, and the
+/// databases were modified at least its number of times. A last snapshot is
+/// saved when the server shuts down, if the databases were modified.
+pub(crate) async fn snapshot_task(
+    db: Db,
+    path: PathBuf,
+    save_points: Vec<(Duration, u64)>,
+    mut shutdown: Shutdown,
+    _shutdown_complete: mpsc::Sender<()>,
+) {
+    loop {
+        tokio::select! {
+            _ = sleep(&save_points) => {}
+            _ = shutdown.recv() => break,
+        }
+
+        let changes = db.unsaved_changes();
+        let elapsed = db.last_save().elapsed().unwrap_or_default();
+        if !save_points
+            .iter()
+            .any(|(interval, min_changes)| elapsed >= *interval && changes >= *min_changes)
+        {
+            continue;
+        }
+
+        match save_snapshot(&db, &path).await {
+            Ok(()) => info!(path = %path.display(), "snapshot saved"),
+            Err(err) => error!(cause = %err, "failed to save snapshot"),
+        }
+    }
+
+    // `SHUTDOWN NOSAVE` skips the last snapshot.
+    if db.unsaved_changes() == 0 || db.shutdown_nosave() {
+        return;
+    }
+
+    match save_snapshot(&db, &path).await {
+        Ok(()) => info!(path = %path.display(), "snapshot saved before shutting down"),
+        Err(err) => error!(cause = %err, "failed to save snapshot"),
+    }
+}
+
+/// Waits until the save points are checked again, or forever if there are
+/// none.
+async fn sleep(save_points: &[(Duration, u64)]) {
+    match save_points.iter().map(|(interval, _)| *interval).min() {
+        Some(interval) => time::sleep(interval.min(SAVE_POINTS_CHECK)).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Writes `snapshot` to `path`, through a temporary file.
+fn write(snapshot: &Snapshot, path: &Path) -> io::Result<()> {
+    let mut data = BytesMut::new();
+    data.put_slice(MAGIC);
+    data.put_u32(VERSION);
+    snapshot.encode(&mut data);
+    let checksum = crc64(&data);
+    data.put_u64(checksum);
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    let mut file = File::create(&tmp)?;
+    file.write_all(&data)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)
+}
+
+/// Table of the CRC-64/XZ checksum, indexed by byte.
+const CRC64_TABLE: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xc96c_5795_d787_0f42
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Computes the CRC-64/XZ checksum of `data`, detecting corrupted snapshots.
+pub(crate) fn crc64(data: &[u8]) -> u64 {
+    let crc = data.iter().fold(!0, |crc, &b| {
+        CRC64_TABLE[((crc ^ b as u64) & 0xff) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
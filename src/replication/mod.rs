@@ -0,0 +1,47 @@
+//! Replication of the databases to replicas.
+//!
+//! A replica connects to its primary and sends `PSYNC`. The primary replies
+//! with a snapshot of the databases, which replaces the content of the
+//! databases of the replica. The primary then streams the commands modifying
+//! its databases, in the order they are applied, which the replica applies in
+//! turn. The commands are streamed as they are appended to the AOF.
+//!
+//! The primary keeps the last commands it streamed in a backlog. When a
+//! replica reconnects after losing its connection, `PSYNC` gives the offset in
+//! the stream it reached, and it only receives the commands it missed if they
+//! are still in the backlog.
+//!
+//! Replication is asynchronous: clients get their replies before the commands
+//! reach the replicas. `WAIT` streams `REPLCONF GETACK`, to which the replicas
+//! reply with `REPLCONF ACK` and the offset they reached, so that a client
+//! waits for its commands to be applied by a number of replicas.
+//!
+//! Clients may not modify the databases of a replica. `REPLICAOF` makes a
+//! server the replica of another one, or promotes it back to a primary.
+
+mod primary;
+pub(crate) use primary::{Replicas, Resync};
+
+mod replica;
+pub(crate) use replica::{replica_task, Link};
+
+/// Role of the server in the replication, as reported by `ROLE`.
+#[derive(Debug)]
+pub(crate) enum Role {
+    /// The server is a primary, whose stream reached `offset`. Each
+    /// connected replica is given as the host and port it listens at, and
+    /// the offset it acknowledged.
+    Primary {
+        offset: u64,
+        replicas: Vec<(String, u16, u64)>,
+    },
+
+    /// The server replicates the primary at `addr`, as `host:port`. `offset`
+    /// is the offset it reached in the stream of the primary, if it ever
+    /// synchronized with it.
+    Replica {
+        addr: String,
+        link: Link,
+        offset: Option<u64>,
+    },
+}
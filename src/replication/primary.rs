@@ -0,0 +1,263 @@
+use crate::cmd::ReplConf;
+use crate::db::random_u64;
+use crate::Frame;
+
+use bytes::{Bytes, BytesMut};
+use std::collections::VecDeque;
+use tokio::sync::{broadcast, mpsc};
+
+/// Streams the commands modifying the databases to the connected replicas.
+///
+/// As in the AOF, commands are streamed as the frames they were received as,
+/// preceded by a `SELECT` whenever they apply to another database than the
+/// previous one, and the expirations they set are followed by a `PEXPIREAT`.
+///
+/// The stream is identified by a replication ID, and each byte of it by its
+/// offset from the start of the stream. Once a replica connects, the last
+/// bytes of the stream are kept in a backlog, so that a replica which lost its
+/// connection continues from the offset it reached instead of being fully
+/// synchronized again.
+#[derive(Debug)]
+pub(crate) struct Replicas {
+    /// The connected replicas.
+    replicas: Vec<Replica>,
+
+    /// Identifier of the next replica to connect.
+    next_id: u64,
+
+    /// Notified when a replica acknowledges an offset.
+    acks: broadcast::Sender<()>,
+
+    /// Database the commands streamed so far apply to, `None` until a
+    /// `SELECT` is streamed.
+    index: Option<usize>,
+
+    /// Frames appended since the last call to `flush`.
+    buffer: BytesMut,
+
+    /// Random ID identifying the stream.
+    replication_id: String,
+
+    /// Offset of the end of the stream.
+    offset: u64,
+
+    /// Last bytes of the stream, at most `repl-backlog-size`, or `None` until the
+    /// first replica connects. Commands are only streamed once there is a
+    /// backlog.
+    backlog: Option<VecDeque<u8>>,
+}
+
+/// A replica connected to the server.
+#[derive(Debug)]
+struct Replica {
+    id: u64,
+
+    /// Host and port the replica listens at.
+    addr: (String, u16),
+
+    /// Sender of the stream of the replica. The replica is removed once the
+    /// receiver is dropped.
+    sender: mpsc::UnboundedSender<Bytes>,
+
+    /// Offset the replica last acknowledged.
+    acked: u64,
+}
+
+/// How a replica is synchronized, as decided by `Replicas::add`.
+#[derive(Debug)]
+pub(crate) enum Resync {
+    /// The replica replaces its databases with a copy of the databases taken
+    /// at `offset` of the stream identified by `replication_id`.
+    Full { replication_id: String, offset: u64 },
+
+    /// The replica continues from the offset it reached, the bytes of the
+    /// stream it missed were sent from the backlog.
+    Partial,
+}
+
+impl Replicas {
+    /// Create a new stream, with a random replication ID.
+    pub(crate) fn new() -> Replicas {
+        let (acks, _) = broadcast::channel(1);
+
+        Replicas {
+            replicas: vec![],
+            next_id: 0,
+            acks,
+            index: None,
+            buffer: BytesMut::new(),
+            replication_id: new_replication_id(),
+            offset: 0,
+            backlog: None,
+        }
+    }
+
+    /// Stream the commands appended from now on to `sender`, on behalf of a
+    /// replica which reached `offset` of the stream identified by
+    /// `replication_id`, or `-1` if it never synchronized.
+    ///
+    /// If the backlog still holds the bytes of the stream following the
+    /// offset of the replica, they are sent to `sender` first. Otherwise, the
+    /// replica must be fully synchronized.
+    ///
+    /// Returns the identifier of the replica along with how it is
+    /// synchronized. `addr` is the host and port the replica listens at.
+    pub(crate) fn add(
+        &mut self,
+        sender: mpsc::UnboundedSender<Bytes>,
+        addr: (String, u16),
+        replication_id: &str,
+        offset: i64,
+    ) -> (u64, Resync) {
+        let backlog = self.backlog.get_or_insert_with(VecDeque::new);
+        let start = self.offset - backlog.len() as u64;
+
+        let (resync, acked) = if replication_id == self.replication_id
+            && offset >= start as i64
+            && offset <= self.offset as i64
+        {
+            let missed = backlog.range((offset as u64 - start) as usize..);
+            let _ = sender.send(missed.copied().collect::<Vec<_>>().into());
+            (Resync::Partial, offset as u64)
+        } else {
+            // The next command is preceded by a `SELECT`, so that the replica
+            // applies it to the right database.
+            self.index = None;
+            let resync = Resync::Full {
+                replication_id: self.replication_id.clone(),
+                offset: self.offset,
+            };
+            (resync, self.offset)
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.replicas.push(Replica {
+            id,
+            addr,
+            sender,
+            acked,
+        });
+        (id, resync)
+    }
+
+    /// Remove the replica `id`, once it disconnects.
+    pub(crate) fn remove(&mut self, id: u64) {
+        self.replicas.retain(|replica| replica.id != id);
+    }
+
+    /// Returns the number of connected replicas.
+    pub(crate) fn len(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Returns the host and port each connected replica listens at, along
+    /// with the offset it acknowledged.
+    pub(crate) fn list(&self) -> Vec<(String, u16, u64)> {
+        self.replicas
+            .iter()
+            .map(|replica| (replica.addr.0.clone(), replica.addr.1, replica.acked))
+            .collect()
+    }
+
+    /// Returns the random ID identifying the stream.
+    pub(crate) fn replication_id(&self) -> &str {
+        &self.replication_id
+    }
+
+    /// Give a new random ID to the stream. Replicas resuming from the previous
+    /// ID are fully synchronized again.
+    pub(crate) fn change_replication_id(&mut self) {
+        self.replication_id = new_replication_id();
+    }
+
+    /// Returns the offset of the end of the stream.
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Record that the replica `id` acknowledged `offset`.
+    pub(crate) fn ack(&mut self, id: u64, offset: u64) {
+        for replica in &mut self.replicas {
+            if replica.id == id {
+                replica.acked = replica.acked.max(offset);
+            }
+        }
+
+        // There may be no client waiting for acknowledgments.
+        let _ = self.acks.send(());
+    }
+
+    /// Returns the number of replicas which acknowledged `offset`.
+    pub(crate) fn acked(&self, offset: u64) -> usize {
+        self.replicas
+            .iter()
+            .filter(|replica| replica.acked >= offset)
+            .count()
+    }
+
+    /// Returns a receiver notified when a replica acknowledges an offset.
+    pub(crate) fn subscribe_acks(&self) -> broadcast::Receiver<()> {
+        self.acks.subscribe()
+    }
+
+    /// Ask the replicas to acknowledge the offset they reached, by streaming
+    /// `REPLCONF GETACK`. The backlog holds up to `backlog_size` bytes, as with
+    /// `flush`.
+    pub(crate) fn request_acks(&mut self, backlog_size: usize) {
+        if self.backlog.is_some() {
+            ReplConf::getack().into_frame().encode(&mut self.buffer);
+            self.flush(backlog_size);
+        }
+    }
+
+    /// Append `frame`, a command applying to the database at `index`.
+    ///
+    /// The frame is buffered until `flush` is called.
+    pub(crate) fn append(&mut self, index: usize, frame: &Frame) {
+        if self.backlog.is_none() {
+            return;
+        }
+
+        if self.index != Some(index) {
+            let mut select = Frame::array();
+            select.push_bulk(Bytes::from("select".as_bytes()));
+            select.push_bulk(Bytes::from(index.to_string().into_bytes()));
+            select.encode(&mut self.buffer);
+
+            self.index = Some(index);
+        }
+
+        frame.encode(&mut self.buffer);
+    }
+
+    /// Send the appended frames to the replicas, removing the disconnected
+    /// ones, and keep them in the backlog, which holds up to `backlog_size`
+    /// bytes.
+    pub(crate) fn flush(&mut self, backlog_size: usize) {
+        let backlog = match &mut self.backlog {
+            Some(backlog) if !self.buffer.is_empty() => backlog,
+            _ => return,
+        };
+
+        let stream = self.buffer.split().freeze();
+        backlog.extend(&stream[..]);
+        if backlog.len() > backlog_size {
+            backlog.drain(..backlog.len() - backlog_size);
+        }
+        self.offset += stream.len() as u64;
+
+        self.replicas
+            .retain(|replica| replica.sender.send(stream.clone()).is_ok());
+    }
+}
+
+/// Returns a random replication ID, made of 40 hexadecimal digits.
+fn new_replication_id() -> String {
+    format!(
+        "{:016x}{:016x}{:08x}",
+        random_u64(),
+        random_u64(),
+        random_u64() as u32
+    )
+}
@@ -0,0 +1,226 @@
+use crate::cmd::{PSync, ReplConf};
+use crate::db::Snapshot;
+use crate::{Command, Connection, Db, Frame, Shutdown};
+
+use bytes::BytesMut;
+use std::io::Cursor;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::time::{self, Duration};
+use tracing::{info, warn};
+
+/// State of the connection of a replica with its primary, as reported by
+/// `ROLE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Link {
+    /// The replica is not connected, it connects shortly.
+    Connect,
+
+    /// The replica is connecting to the primary.
+    Connecting,
+
+    /// The replica is being synchronized with the primary.
+    Sync,
+
+    /// The replica applies the commands streamed by the primary.
+    Connected,
+}
+
+impl Link {
+    /// Returns the name of the state, as reported by `ROLE`.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Link::Connect => "connect",
+            Link::Connecting => "connecting",
+            Link::Sync => "sync",
+            Link::Connected => "connected",
+        }
+    }
+}
+
+/// Position of a replica in the stream of commands of its primary, kept
+/// across connections so that the replica continues from where it was.
+#[derive(Debug)]
+struct Position {
+    /// Replication ID of the stream
+    replication_id: String,
+
+    /// Offset the replica reached in the stream
+    offset: u64,
+
+    /// Database the last commands received apply to
+    index: usize,
+}
+
+/// Routine executed by the task replicating the primary set with
+/// `Db::set_primary`, if any.
+///
+/// When the connection with the primary is lost, the task reconnects after a
+/// second, continuing from the offset it reached if the primary still holds
+/// the commands it missed. When the primary changes, the task disconnects from
+/// the previous one and synchronizes the databases with the new one. The task
+/// exits when the server shuts down.
+///
+/// `port` is the port the server listens at, given to the primary so that
+/// the replicas it reports are reachable.
+pub(crate) async fn replica_task(db: Db, port: u16, mut shutdown: Shutdown) {
+    let mut position = None;
+
+    loop {
+        let addr = match db.primary() {
+            Some(addr) => addr,
+            None => {
+                tokio::select! {
+                    _ = db.primary_changed() => {
+                        position = None;
+                        continue;
+                    }
+                    _ = shutdown.recv() => return,
+                }
+            }
+        };
+
+        db.set_primary_link(Link::Connecting, offset(&position));
+        let res = tokio::select! {
+            res = replicate(db.clone(), &addr, port, &mut position) => res,
+            _ = db.primary_changed() => {
+                position = None;
+                continue;
+            }
+            _ = shutdown.recv() => return,
+        };
+
+        match res {
+            Ok(()) => info!(%addr, "connection with the primary closed"),
+            Err(err) => warn!(%addr, cause = %err, "replication failed"),
+        }
+        db.set_primary_link(Link::Connect, offset(&position));
+
+        tokio::select! {
+            _ = time::sleep(Duration::from_secs(1)) => {}
+            _ = db.primary_changed() => position = None,
+            _ = shutdown.recv() => return,
+        }
+    }
+}
+
+/// Synchronize `db` with the primary at `addr`, then apply the commands it
+/// streams until the connection is closed.
+///
+/// `position` is the position of the replica in the stream of the primary, or
+/// `None` if the replica never synchronized with it. It is updated as the
+/// commands are applied.
+async fn replicate(
+    db: Db,
+    addr: &str,
+    port: u16,
+    position: &mut Option<Position>,
+) -> crate::Result<()> {
+    let socket = TcpStream::connect(addr).await?;
+    let mut connection = Connection::new(socket);
+
+    let replconf = ReplConf::listening_port(port);
+    connection.write_frame(&replconf.into_frame()).await?;
+    match connection.read_frame().await? {
+        Some(Frame::Simple(_)) => {}
+        Some(frame) => return Err(frame.to_error()),
+        None => return Ok(()),
+    }
+
+    db.set_primary_link(Link::Sync, offset(position));
+
+    let psync = match position {
+        Some(position) => PSync::new(&position.replication_id, position.offset as i64),
+        None => PSync::new("?", -1),
+    };
+    connection.write_frame(&psync.into_frame()).await?;
+
+    let response = match connection.read_frame().await? {
+        Some(Frame::Simple(response)) => response,
+        Some(frame) => return Err(frame.to_error()),
+        None => return Ok(()),
+    };
+    let mut parts = response.split(' ');
+
+    match (parts.next(), parts.next(), parts.next(), &position) {
+        (Some("CONTINUE"), None, None, Some(_)) => {
+            info!(%addr, "continuing from the last offset");
+        }
+        (Some("FULLRESYNC"), Some(replication_id), Some(offset), _) => {
+            let replication_id = replication_id.to_string();
+            let offset = offset.parse()?;
+
+            let snapshot = match connection.read_frame().await? {
+                Some(Frame::Bulk(data)) => Snapshot::decode(&data)?,
+                Some(frame) => return Err(frame.to_error()),
+                None => return Ok(()),
+            };
+            db.restore(snapshot)?;
+            info!(%addr, "synchronized with the primary");
+
+            *position = Some(Position {
+                replication_id,
+                offset,
+                index: 0,
+            });
+        }
+        _ => return Err(format!("unexpected reply to PSYNC: {}", response).into()),
+    }
+    let position = position.as_mut().unwrap();
+    let mut db = db.select(position.index)?;
+    db.set_primary_link(Link::Connected, Some(position.offset));
+
+    // Replies are discarded, the connection never reads from nor writes to
+    // its stream.
+    let mut dst = Connection::from_stream(Cursor::new(Vec::new()));
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let mut shutdown = Shutdown::new(notify_shutdown.subscribe());
+    let mut encoded = BytesMut::new();
+
+    while let Some(frame) = connection.read_frame().await? {
+        // The offset counts the bytes of the stream, the primary encodes the
+        // frames as they are encoded here.
+        frame.encode(&mut encoded);
+        let len = encoded.split().len() as u64;
+
+        // The commands are logged in turn, so that the replica has its own
+        // AOF and replicas.
+        let logged = frame.clone();
+        let cmd = Command::from_frame(frame)?;
+        let logged = Some(logged).filter(|_| cmd.is_write());
+
+        // The primary asks for the offset reached, which includes the request.
+        if let Command::ReplConf(cmd) = &cmd {
+            if cmd.is_getack() {
+                position.offset += len;
+                db.set_primary_link(Link::Connected, Some(position.offset));
+                let ack = ReplConf::ack(position.offset);
+                connection.write_frame(&ack.into_frame()).await?;
+                continue;
+            }
+        }
+
+        // Commands never wait within a transaction, blocking commands complete
+        // as they did on the primary.
+        let mut transaction = db.transaction();
+        transaction.db().set_logged_command(logged);
+        dst.replace_replies(Some(vec![]));
+        match cmd.apply_now(transaction.db(), &mut dst, &mut shutdown) {
+            Some(res) => res?,
+            None => return Err("a command of the primary blocked the transaction".into()),
+        }
+        transaction.db().set_logged_command(None);
+        db = transaction.commit();
+
+        position.offset += len;
+        position.index = db.index();
+        db.set_primary_link(Link::Connected, Some(position.offset));
+    }
+
+    Ok(())
+}
+
+/// Returns the offset reached at `position`, if any.
+fn offset(position: &Option<Position>) -> Option<u64> {
+    position.as_ref().map(|position| position.offset)
+}
@@ -0,0 +1,387 @@
+//! Lua scripting.
+//!
+//! Scripts are written in a subset of Lua 5.1, the version embedded by Redis,
+//! and evaluated by a tree-walking interpreter. Numbers are doubles, as in Lua
+//! 5.1.
+//!
+//! Metatables, coroutines and string patterns are not supported, nor are the
+//! `cjson` and `cmsgpack` libraries of Redis. Their functions raise an error
+//! saying so when called: `setmetatable`, `getmetatable`, those of the
+//! `coroutine`, `cjson` and `cmsgpack` tables, `string.match`,
+//! `string.gmatch`, `string.gsub`, and `string.find` unless the pattern holds
+//! no special character or the `plain` argument is set. Of the other
+//! libraries, only the functions scripts commonly use are available.
+//!
+//! As in Redis, scripts access the keys and arguments they are given through
+//! the `KEYS` and `ARGV` tables and execute commands with `redis.call` and
+//! `redis.pcall`. They may not create global variables.
+//!
+//! Libraries, loaded by `FUNCTION LOAD`, are scripts registering functions
+//! with `redis.register_function`. The functions of a library are called by
+//! name with `FCALL`, receiving the keys and arguments as parameters.
+
+mod interp;
+mod lexer;
+mod parser;
+mod sha1;
+mod value;
+
+pub(crate) use sha1::sha1_hex;
+
+use interp::{Interp, Registered};
+use parser::FuncBody;
+use value::{Table, Value};
+
+use crate::Frame;
+
+use bytes::Bytes;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Size of the stack of the thread running the scripts.
+///
+/// The interpreter recurses for each nested call and expression, up to a
+/// bounded depth, which may need more than the stack of a worker thread.
+const STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Longest time the main chunk of a library may run when loaded, as with
+/// Redis.
+const LOAD_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sends the jobs to the thread running the scripts, started when the first
+/// script runs, and started again if it exited.
+static SCRIPT_THREAD: Mutex<Option<mpsc::Sender<Job>>> = Mutex::new(None);
+
+/// A script run by the script thread.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Message sent by a job to the thread waiting for it.
+enum Message<T> {
+    /// The script calls `redis.call` or `redis.pcall` with these arguments,
+    /// the waiting thread replies with the reply of the command.
+    Call(Vec<Bytes>),
+
+    /// The job completed, or panicked.
+    Done(thread::Result<T>),
+}
+
+/// Engine running the libraries, the only one supported.
+pub(crate) const ENGINE: &str = "LUA";
+
+/// A compiled script.
+#[derive(Debug)]
+pub(crate) struct Script {
+    body: Arc<FuncBody>,
+}
+
+impl Script {
+    /// Compiles the script `source`.
+    ///
+    /// Returns `Err` with a description of the syntax error if `source` is not
+    /// a valid script.
+    pub(crate) fn compile(source: &[u8]) -> Result<Script, String> {
+        let body = parser::parse(source)?;
+        Ok(Script {
+            body: Arc::new(body),
+        })
+    }
+
+    /// Runs the script with the given `KEYS` and `ARGV`, returning its reply.
+    ///
+    /// The commands of `redis.call` and `redis.pcall` are executed by
+    /// `execute`, which returns their reply. Errors raised by the script are
+    /// converted to error replies. The script is stopped with the error
+    /// returned by `interrupt`, checked as it runs.
+    ///
+    /// The script runs on the script thread, the caller waits until it
+    /// completes, executing its commands.
+    pub(crate) fn run(
+        &self,
+        keys: &[String],
+        args: &[Bytes],
+        interrupt: impl Fn() -> Option<String> + Send + 'static,
+        execute: &mut dyn FnMut(Vec<Bytes>) -> Frame,
+    ) -> Frame {
+        let body = self.body.clone();
+        let keys = keys.to_vec();
+        let args = args.to_vec();
+
+        let run = move |execute: &mut dyn FnMut(Vec<Bytes>) -> Frame| {
+            let mut interp = Interp::new(execute);
+            interp.set_interrupt(interrupt);
+
+            let keys = keys.into_iter().map(Value::str).collect();
+            let keys = interp.new_table(Table::from_array(keys));
+            interp.set_global("KEYS", keys);
+
+            let args = args.into_iter().map(Value::Str).collect();
+            let args = interp.new_table(Table::from_array(args));
+            interp.set_global("ARGV", args);
+
+            match interp.run(body) {
+                Ok(values) => interp::to_frame(values.first().unwrap_or(&Value::Nil)),
+                Err(err) => interp::error_frame(&err),
+            }
+        };
+
+        on_script_thread(run, execute).unwrap_or_else(Frame::Error)
+    }
+}
+
+/// A library of functions, loaded by `FUNCTION LOAD`.
+#[derive(Debug)]
+pub(crate) struct Library {
+    /// Name of the library, given by the first line of its code.
+    name: String,
+
+    /// Source of the library.
+    code: Bytes,
+
+    body: Arc<FuncBody>,
+
+    /// Functions registered by the library.
+    functions: Vec<FunctionInfo>,
+}
+
+/// A function registered by a library.
+#[derive(Debug, Clone)]
+pub(crate) struct FunctionInfo {
+    pub(crate) name: String,
+    pub(crate) flags: Vec<String>,
+    pub(crate) description: Option<Bytes>,
+}
+
+impl Library {
+    /// Loads the library `code`.
+    ///
+    /// The first line of the code is `#!lua name=<library name>`. The code is
+    /// compiled then run, registering the functions of the library.
+    ///
+    /// Returns `Err` with the error reply if the code is not a valid library.
+    pub(crate) fn load(code: Bytes) -> Result<Library, String> {
+        let first_line = code.split(|&b| b == b'\n').next().unwrap_or_default();
+        let shebang = String::from_utf8_lossy(first_line);
+        let mut metadata = match shebang.trim_end().strip_prefix("#!") {
+            Some(metadata) => metadata.split(' ').filter(|s| !s.is_empty()),
+            None => return Err("ERR Missing library metadata".to_string()),
+        };
+
+        let engine = metadata.next().unwrap_or_default();
+        if !engine.eq_ignore_ascii_case(ENGINE) {
+            return Err(format!("ERR Engine '{}' not found", engine));
+        }
+
+        let mut name = None;
+        for field in metadata {
+            match field.strip_prefix("name=") {
+                Some(value) => name = Some(value.to_string()),
+                None => return Err(format!("ERR Invalid metadata value given: {}", field)),
+            }
+        }
+        let name = match name {
+            Some(name) => name,
+            None => return Err("ERR Library name was not given".to_string()),
+        };
+        if !is_valid_name(&name) {
+            return Err("ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string());
+        }
+
+        // The first line is replaced by an empty one, keeping the line numbers
+        // of errors.
+        let mut source = code[first_line.len()..].to_vec();
+        if source.is_empty() {
+            source.push(b'\n');
+        }
+        let body = parser::parse(&source)
+            .map_err(|err| format!("ERR Error compiling function: {}", err))?;
+        let body = Arc::new(body);
+
+        // Libraries may not call commands while loaded, nor run for longer
+        // than `LOAD_TIMEOUT`.
+        let load = {
+            let body = body.clone();
+            move |execute: &mut dyn FnMut(Vec<Bytes>) -> Frame| {
+                let mut interp = Interp::new(execute);
+                let deadline = Instant::now() + LOAD_TIMEOUT;
+                interp.set_interrupt(move || {
+                    Some("ERR FUNCTION LOAD timeout".to_string())
+                        .filter(|_| Instant::now() >= deadline)
+                });
+
+                match interp.load(body) {
+                    Ok(registered) => Ok(registered
+                        .into_iter()
+                        .map(FunctionInfo::from)
+                        .collect::<Vec<_>>()),
+                    Err(err) => Err(interp::error_message(&err)),
+                }
+            }
+        };
+        let functions = on_script_thread(load, &mut |_| Frame::Null)??;
+        if functions.is_empty() {
+            return Err("ERR No functions registered".to_string());
+        }
+
+        Ok(Library {
+            name,
+            code,
+            body,
+            functions,
+        })
+    }
+
+    /// Returns the name of the library.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the source of the library.
+    pub(crate) fn code(&self) -> &Bytes {
+        &self.code
+    }
+
+    /// Returns the functions registered by the library.
+    pub(crate) fn functions(&self) -> &[FunctionInfo] {
+        &self.functions
+    }
+
+    /// Returns the function of the library named `name`.
+    pub(crate) fn function(&self, name: &str) -> Option<&FunctionInfo> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    /// Calls the function `name` with the given keys and arguments, returning
+    /// its reply.
+    ///
+    /// The main chunk of the library runs again, registering the functions,
+    /// before the function is called. As with `Script::run`, the commands are
+    /// executed by `execute` and the function is stopped with the error
+    /// returned by `interrupt`.
+    pub(crate) fn call(
+        &self,
+        name: &str,
+        keys: &[String],
+        args: &[Bytes],
+        interrupt: impl Fn() -> Option<String> + Send + 'static,
+        execute: &mut dyn FnMut(Vec<Bytes>) -> Frame,
+    ) -> Frame {
+        let body = self.body.clone();
+        let name = name.to_string();
+        let keys = keys.to_vec();
+        let args = args.to_vec();
+
+        let call = move |execute: &mut dyn FnMut(Vec<Bytes>) -> Frame| {
+            let mut interp = Interp::new(execute);
+            interp.set_interrupt(interrupt);
+
+            let registered = match interp.load(body) {
+                Ok(registered) => registered,
+                Err(err) => return interp::error_frame(&err),
+            };
+            let callback = match registered.into_iter().find(|f| f.name == name) {
+                Some(function) => function.callback,
+                None => return Frame::Error("ERR Function not found".to_string()),
+            };
+
+            let keys = keys.into_iter().map(Value::str).collect();
+            let keys = interp.new_table(Table::from_array(keys));
+            let args = args.into_iter().map(Value::Str).collect();
+            let args = interp.new_table(Table::from_array(args));
+
+            match interp.call(&callback, vec![keys, args]) {
+                Ok(values) => interp::to_frame(values.first().unwrap_or(&Value::Nil)),
+                Err(err) => interp::error_frame(&err),
+            }
+        };
+
+        on_script_thread(call, execute).unwrap_or_else(Frame::Error)
+    }
+}
+
+impl From<Registered> for FunctionInfo {
+    fn from(registered: Registered) -> FunctionInfo {
+        FunctionInfo {
+            name: registered.name,
+            flags: registered.flags,
+            description: registered.description,
+        }
+    }
+}
+
+/// Returns `true` if `name` is a valid name for a library or a function: one
+/// or more letters, digits or underscores.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// Runs `f` on the script thread, returning its result. The caller waits
+/// until it completes, executing the commands called by `f` with `execute`.
+///
+/// A single thread runs the scripts, one at a time, so that each script does
+/// not need a thread of its own. As the script thread cannot borrow the state
+/// of the caller, the commands are sent back to the caller, which replies
+/// with their reply.
+///
+/// Returns `Err` with the error to reply if the script thread cannot be
+/// started. A panic of `f` is resumed by the caller.
+fn on_script_thread<T: Send + 'static>(
+    f: impl FnOnce(&mut dyn FnMut(Vec<Bytes>) -> Frame) -> T + Send + 'static,
+    execute: &mut dyn FnMut(Vec<Bytes>) -> Frame,
+) -> Result<T, String> {
+    let (messages_tx, messages) = mpsc::channel();
+    let (replies_tx, replies) = mpsc::channel();
+
+    send_job(Box::new(move || {
+        let mut call = |args| {
+            // The caller receives the messages until the job completes.
+            let _ = messages_tx.send(Message::Call(args));
+            replies.recv().unwrap_or(Frame::Null)
+        };
+        let res = panic::catch_unwind(AssertUnwindSafe(|| f(&mut call)));
+        let _ = messages_tx.send(Message::Done(res));
+    }))?;
+
+    loop {
+        match messages.recv() {
+            Ok(Message::Call(args)) => {
+                let _ = replies_tx.send(execute(args));
+            }
+            Ok(Message::Done(Ok(value))) => return Ok(value),
+            Ok(Message::Done(Err(panic))) => panic::resume_unwind(panic),
+            Err(_) => return Err("ERR the script thread exited".to_string()),
+        }
+    }
+}
+
+/// Sends `job` to the script thread, starting it if needed.
+///
+/// Returns `Err` with the error to reply if the thread cannot be started.
+fn send_job(job: Job) -> Result<(), String> {
+    let mut thread = SCRIPT_THREAD.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let job = match thread.as_ref() {
+        Some(jobs) => match jobs.send(job) {
+            Ok(()) => return Ok(()),
+            Err(mpsc::SendError(job)) => job,
+        },
+        None => job,
+    };
+
+    let (jobs, received) = mpsc::channel::<Job>();
+    thread::Builder::new()
+        .name("script".to_string())
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            for job in received {
+                job();
+            }
+        })
+        .map_err(|err| format!("ERR failed to start the script thread: {}", err))?;
+
+    jobs.send(job)
+        .map_err(|_| "ERR the script thread exited".to_string())?;
+    *thread = Some(jobs);
+    Ok(())
+}
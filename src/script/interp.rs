@@ -0,0 +1,1800 @@
+//! Evaluates the syntax tree of a script.
+
+use super::parser::{BinOp, Block, Expr, Field, FuncBody, Stat, UnOp};
+use super::sha1::sha1_hex;
+use super::value::{format_g, format_number, Function, Table, Value};
+use crate::frame::format_double;
+use crate::Frame;
+
+use bytes::Bytes;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+/// Maximum depth of nested calls and expressions, bounding the stack used by
+/// the evaluation.
+const MAX_DEPTH: usize = 200;
+
+/// Maximum length of the strings built by scripts, the default value of the
+/// `proto-max-bulk-len` parameter, so that a script cannot exhaust the memory
+/// of the server.
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// Maximum number of values returned by `unpack`, the size of the stack of
+/// Lua 5.1.
+const MAX_UNPACK: i64 = 8000;
+
+/// A Lua function, along with the scope it was created in.
+#[derive(Debug)]
+pub(super) struct Closure {
+    body: Arc<FuncBody>,
+
+    /// The scope is released once the script completes, which breaks the
+    /// reference cycles between closures and the scopes holding them.
+    scope: RefCell<Option<Rc<Scope>>>,
+}
+
+/// Local variables declared in a block.
+#[derive(Debug, Default)]
+struct Scope {
+    vars: RefCell<Vec<(String, Rc<RefCell<Value>>)>>,
+    parent: Option<Rc<Scope>>,
+}
+
+/// A function registered by a library with `redis.register_function`.
+#[derive(Debug)]
+pub(super) struct Registered {
+    pub(super) name: String,
+    pub(super) callback: Value,
+    pub(super) flags: Vec<String>,
+    pub(super) description: Option<Bytes>,
+}
+
+/// Flags a function may be registered with.
+const FUNCTION_FLAGS: &[&str] = &[
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// Functions implemented by the interpreter.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Builtin {
+    Assert,
+    Error,
+    Ipairs,
+    IpairsNext,
+    Next,
+    Pairs,
+    Pcall,
+    RawEqual,
+    RawGet,
+    RawSet,
+    Select,
+    ToNumber,
+    ToString,
+    Type,
+    Unpack,
+
+    RedisCall,
+    RedisPcall,
+    RedisSha1Hex,
+    RedisErrorReply,
+    RedisStatusReply,
+    RedisLog,
+    RedisRegisterFunction,
+
+    StringByte,
+    StringChar,
+    StringFind,
+    StringFormat,
+    StringLen,
+    StringLower,
+    StringRep,
+    StringReverse,
+    StringSub,
+    StringUpper,
+
+    TableConcat,
+    TableInsert,
+    TableRemove,
+
+    MathAbs,
+    MathCeil,
+    MathFloor,
+    MathFmod,
+    MathMax,
+    MathMin,
+    MathPow,
+    MathSqrt,
+
+    // Functions of the features which are not implemented, raising an error
+    // when called.
+    Metatables,
+    Coroutines,
+    Cjson,
+    Cmsgpack,
+    StringPatterns,
+}
+
+/// Outcome of the execution of a statement.
+enum Flow {
+    Normal,
+    Break,
+    Return(Vec<Value>),
+}
+
+/// Variables accessible while evaluating an expression.
+struct Env<'a> {
+    scope: Rc<Scope>,
+    varargs: &'a [Value],
+}
+
+/// Errors are Lua values, usually strings.
+type Result<T> = std::result::Result<T, Value>;
+
+/// Runs scripts.
+pub(super) struct Interp<'a> {
+    globals: Rc<RefCell<Table>>,
+
+    /// The `string` library, holding the methods of strings.
+    string: Rc<RefCell<Table>>,
+
+    /// Executes the commands of `redis.call`, returning their reply.
+    execute: &'a mut dyn FnMut(Vec<Bytes>) -> Frame,
+    depth: usize,
+
+    /// Tables and closures created by the script, cleared once it completes.
+    tables: Vec<Weak<RefCell<Table>>>,
+    closures: Vec<Weak<Closure>>,
+
+    /// Functions registered while loading a library. `None` unless the main
+    /// chunk of a library is running.
+    registered: Option<Vec<Registered>>,
+
+    /// Checked as the script runs, returning the error reply stopping it,
+    /// see `set_interrupt`.
+    interrupt: Option<Box<dyn Fn() -> Option<String> + 'a>>,
+}
+
+impl<'a> Interp<'a> {
+    /// Creates an interpreter whose `redis.call` executes commands with
+    /// `execute`.
+    pub(super) fn new(execute: &'a mut dyn FnMut(Vec<Bytes>) -> Frame) -> Interp<'a> {
+        let mut globals = Table::default();
+        for (name, builtin) in [
+            ("assert", Builtin::Assert),
+            ("error", Builtin::Error),
+            ("ipairs", Builtin::Ipairs),
+            ("next", Builtin::Next),
+            ("pairs", Builtin::Pairs),
+            ("pcall", Builtin::Pcall),
+            ("rawequal", Builtin::RawEqual),
+            ("rawget", Builtin::RawGet),
+            ("rawset", Builtin::RawSet),
+            ("select", Builtin::Select),
+            ("tonumber", Builtin::ToNumber),
+            ("tostring", Builtin::ToString),
+            ("type", Builtin::Type),
+            ("unpack", Builtin::Unpack),
+            ("getmetatable", Builtin::Metatables),
+            ("setmetatable", Builtin::Metatables),
+        ] {
+            globals.set_str(name, builtin.into());
+        }
+
+        // Coroutines, and the libraries encoding JSON and MessagePack, are
+        // not implemented.
+        for (name, functions) in [
+            (
+                "coroutine",
+                &[
+                    ("create", Builtin::Coroutines),
+                    ("resume", Builtin::Coroutines),
+                    ("running", Builtin::Coroutines),
+                    ("status", Builtin::Coroutines),
+                    ("wrap", Builtin::Coroutines),
+                    ("yield", Builtin::Coroutines),
+                ][..],
+            ),
+            (
+                "cjson",
+                &[("encode", Builtin::Cjson), ("decode", Builtin::Cjson)],
+            ),
+            (
+                "cmsgpack",
+                &[("pack", Builtin::Cmsgpack), ("unpack", Builtin::Cmsgpack)],
+            ),
+        ] {
+            globals.set_str(name, Value::table(library(functions)));
+        }
+
+        let mut redis = library(&[
+            ("call", Builtin::RedisCall),
+            ("pcall", Builtin::RedisPcall),
+            ("sha1hex", Builtin::RedisSha1Hex),
+            ("error_reply", Builtin::RedisErrorReply),
+            ("status_reply", Builtin::RedisStatusReply),
+            ("log", Builtin::RedisLog),
+            ("register_function", Builtin::RedisRegisterFunction),
+        ]);
+        for (level, name) in ["LOG_DEBUG", "LOG_VERBOSE", "LOG_NOTICE", "LOG_WARNING"]
+            .iter()
+            .enumerate()
+        {
+            redis.set_str(name, Value::Number(level as f64));
+        }
+        globals.set_str("redis", Value::table(redis));
+
+        let string = Rc::new(RefCell::new(library(&[
+            ("byte", Builtin::StringByte),
+            ("char", Builtin::StringChar),
+            ("find", Builtin::StringFind),
+            ("format", Builtin::StringFormat),
+            ("len", Builtin::StringLen),
+            ("lower", Builtin::StringLower),
+            ("rep", Builtin::StringRep),
+            ("reverse", Builtin::StringReverse),
+            ("sub", Builtin::StringSub),
+            ("upper", Builtin::StringUpper),
+            // String patterns are not implemented.
+            ("gmatch", Builtin::StringPatterns),
+            ("gsub", Builtin::StringPatterns),
+            ("match", Builtin::StringPatterns),
+        ])));
+        globals.set_str("string", Value::Table(string.clone()));
+
+        globals.set_str(
+            "table",
+            Value::table(library(&[
+                ("concat", Builtin::TableConcat),
+                ("insert", Builtin::TableInsert),
+                ("remove", Builtin::TableRemove),
+            ])),
+        );
+
+        let mut math = library(&[
+            ("abs", Builtin::MathAbs),
+            ("ceil", Builtin::MathCeil),
+            ("floor", Builtin::MathFloor),
+            ("fmod", Builtin::MathFmod),
+            ("max", Builtin::MathMax),
+            ("min", Builtin::MathMin),
+            ("pow", Builtin::MathPow),
+            ("sqrt", Builtin::MathSqrt),
+        ]);
+        math.set_str("huge", Value::Number(f64::INFINITY));
+        math.set_str("pi", Value::Number(std::f64::consts::PI));
+        globals.set_str("math", Value::table(math));
+
+        Interp {
+            globals: Rc::new(RefCell::new(globals)),
+            string,
+            execute,
+            depth: 0,
+            tables: vec![],
+            closures: vec![],
+            registered: None,
+            interrupt: None,
+        }
+    }
+
+    /// Sets the function checked before each block and call of the script,
+    /// which returns the error reply stopping the script, if it must stop.
+    ///
+    /// The error is raised again by each following check, so that `pcall`
+    /// does not keep the script running.
+    pub(super) fn set_interrupt(&mut self, interrupt: impl Fn() -> Option<String> + 'a) {
+        self.interrupt = Some(Box::new(interrupt));
+    }
+
+    /// Raises the error of the interrupt, if the script must stop.
+    fn check_interrupt(&mut self) -> Result<()> {
+        match self.interrupt.as_ref().and_then(|interrupt| interrupt()) {
+            Some(msg) => {
+                let mut table = Table::default();
+                table.set_str("err", Value::str(msg));
+                Err(self.new_table(table))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the global variable `name`.
+    pub(super) fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().set_str(name, value);
+    }
+
+    /// Creates a table tracked by the interpreter.
+    pub(super) fn new_table(&mut self, table: Table) -> Value {
+        let table = Rc::new(RefCell::new(table));
+        track(&mut self.tables, &table);
+        Value::Table(table)
+    }
+
+    /// Runs `body`, the main chunk of a library, returning the functions it
+    /// registers.
+    pub(super) fn load(&mut self, body: Arc<FuncBody>) -> Result<Vec<Registered>> {
+        self.registered = Some(vec![]);
+        let res = self.run(body);
+        let registered = self.registered.take().unwrap_or_default();
+        res.map(|_| registered)
+    }
+
+    /// Runs `body`, the main chunk of a script, returning the values it
+    /// returns.
+    pub(super) fn run(&mut self, body: Arc<FuncBody>) -> Result<Vec<Value>> {
+        let closure = self.closure(body, Rc::new(Scope::default()));
+        self.call(&closure, vec![])
+    }
+
+    /// Calls `function` with `args`.
+    pub(super) fn call(&mut self, function: &Value, args: Vec<Value>) -> Result<Vec<Value>> {
+        let function = match function {
+            Value::Function(function) => function,
+            value => {
+                return Err(error(format!(
+                    "attempt to call a {} value",
+                    value.type_name()
+                )))
+            }
+        };
+
+        self.check_interrupt()?;
+        self.enter()?;
+        let res = match function {
+            Function::Builtin(builtin) => self.builtin(*builtin, args),
+            Function::Closure(closure) => self.call_closure(closure, args),
+        };
+        self.depth -= 1;
+
+        res
+    }
+
+    fn call_closure(&mut self, closure: &Closure, mut args: Vec<Value>) -> Result<Vec<Value>> {
+        let parent = closure.scope.borrow().clone();
+        let scope = Rc::new(Scope {
+            vars: RefCell::default(),
+            parent,
+        });
+
+        let body = &closure.body;
+        let varargs = if body.is_vararg && args.len() > body.params.len() {
+            args.split_off(body.params.len())
+        } else {
+            vec![]
+        };
+
+        let mut args = args.into_iter();
+        for param in &body.params {
+            scope.declare(param.clone(), args.next().unwrap_or(Value::Nil));
+        }
+
+        let env = Env {
+            scope,
+            varargs: &varargs,
+        };
+
+        match self.exec_block(&body.block, &env)? {
+            Flow::Return(values) => Ok(values),
+            Flow::Normal | Flow::Break => Ok(vec![]),
+        }
+    }
+
+    fn closure(&mut self, body: Arc<FuncBody>, scope: Rc<Scope>) -> Value {
+        let closure = Rc::new(Closure {
+            body,
+            scope: RefCell::new(Some(scope)),
+        });
+        track(&mut self.closures, &closure);
+        Value::Function(Function::Closure(closure))
+    }
+
+    /// Increments the depth of the evaluation, failing when it is too deep.
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= MAX_DEPTH {
+            return Err(error("stack overflow"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Executes `block` in a new scope nested in the scope of `env`.
+    fn exec_block(&mut self, block: &Block, env: &Env<'_>) -> Result<Flow> {
+        self.check_interrupt()?;
+
+        let env = Env {
+            scope: Scope::child(&env.scope),
+            varargs: env.varargs,
+        };
+
+        for stat in block {
+            match self.exec(stat, &env)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec(&mut self, stat: &Stat, env: &Env<'_>) -> Result<Flow> {
+        match stat {
+            Stat::Local(names, exprs) => {
+                let mut values = self.eval_list(exprs, env)?.into_iter();
+                for name in names {
+                    env.scope
+                        .declare(name.clone(), values.next().unwrap_or(Value::Nil));
+                }
+            }
+            Stat::LocalFunction(name, body) => {
+                // The function is declared before being created, so that it
+                // can call itself.
+                env.scope.declare(name.clone(), Value::Nil);
+                let closure = self.closure(body.clone(), env.scope.clone());
+                *env.scope.lookup(name).unwrap().borrow_mut() = closure;
+            }
+            Stat::Assign(targets, exprs) => {
+                let mut values = self.eval_list(exprs, env)?.into_iter();
+                for target in targets {
+                    let value = values.next().unwrap_or(Value::Nil);
+                    self.assign(target, value, env)?;
+                }
+            }
+            Stat::Call(expr) => {
+                self.eval_multi(expr, env)?;
+            }
+            Stat::Do(block) => return self.exec_block(block, env),
+            Stat::While(cond, block) => {
+                while self.eval(cond, env)?.truthy() {
+                    match self.exec_block(block, env)? {
+                        Flow::Normal => {}
+                        Flow::Break => break,
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stat::Repeat(block, cond) => loop {
+                self.check_interrupt()?;
+
+                // The condition may refer to the locals of the block.
+                let env = Env {
+                    scope: Scope::child(&env.scope),
+                    varargs: env.varargs,
+                };
+
+                let mut flow = Flow::Normal;
+                for stat in block {
+                    flow = self.exec(stat, &env)?;
+                    if !matches!(flow, Flow::Normal) {
+                        break;
+                    }
+                }
+
+                match flow {
+                    Flow::Normal => {}
+                    Flow::Break => break,
+                    flow => return Ok(flow),
+                }
+
+                if self.eval(cond, &env)?.truthy() {
+                    break;
+                }
+            },
+            Stat::If(branches, otherwise) => {
+                for (cond, block) in branches {
+                    if self.eval(cond, env)?.truthy() {
+                        return self.exec_block(block, env);
+                    }
+                }
+
+                if let Some(block) = otherwise {
+                    return self.exec_block(block, env);
+                }
+            }
+            Stat::NumericFor {
+                var,
+                start,
+                limit,
+                step,
+                block,
+            } => {
+                let number = |value: Value, what: &str| {
+                    value
+                        .to_number()
+                        .ok_or_else(|| error(format!("'for' {} must be a number", what)))
+                };
+
+                let start = number(self.eval(start, env)?, "initial value")?;
+                let limit = number(self.eval(limit, env)?, "limit")?;
+                let step = match step {
+                    Some(step) => number(self.eval(step, env)?, "step")?,
+                    None => 1.0,
+                };
+
+                if step == 0.0 {
+                    return Err(error("'for' step is zero"));
+                }
+
+                let mut i = start;
+                while (step > 0.0 && i <= limit) || (step < 0.0 && i >= limit) {
+                    let env = Env {
+                        scope: Scope::child(&env.scope),
+                        varargs: env.varargs,
+                    };
+                    env.scope.declare(var.clone(), Value::Number(i));
+
+                    match self.exec_block(block, &env)? {
+                        Flow::Normal => {}
+                        Flow::Break => break,
+                        flow => return Ok(flow),
+                    }
+
+                    i += step;
+                }
+            }
+            Stat::GenericFor { vars, exprs, block } => {
+                let mut values = self.eval_list(exprs, env)?.into_iter();
+                let function = values.next().unwrap_or(Value::Nil);
+                let state = values.next().unwrap_or(Value::Nil);
+                let mut control = values.next().unwrap_or(Value::Nil);
+
+                loop {
+                    let mut values = self
+                        .call(&function, vec![state.clone(), control.clone()])?
+                        .into_iter();
+
+                    control = values.next().unwrap_or(Value::Nil);
+                    if matches!(control, Value::Nil) {
+                        break;
+                    }
+
+                    let env = Env {
+                        scope: Scope::child(&env.scope),
+                        varargs: env.varargs,
+                    };
+                    env.scope.declare(vars[0].clone(), control.clone());
+                    for var in &vars[1..] {
+                        env.scope
+                            .declare(var.clone(), values.next().unwrap_or(Value::Nil));
+                    }
+
+                    match self.exec_block(block, &env)? {
+                        Flow::Normal => {}
+                        Flow::Break => break,
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stat::Return(exprs) => {
+                // A call in tail position returns all its values.
+                return Ok(Flow::Return(self.eval_list(exprs, env)?));
+            }
+            Stat::Break => return Ok(Flow::Break),
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value, env: &Env<'_>) -> Result<()> {
+        match target {
+            Expr::Name(name) => match env.scope.lookup(name) {
+                Some(var) => *var.borrow_mut() = value,
+                None => return Err(error("Attempt to modify a readonly table")),
+            },
+            Expr::Index(table, key) => {
+                let table = self.eval(table, env)?;
+                let key = self.eval(key, env)?;
+                match table {
+                    Value::Table(table) => table.borrow_mut().set(key, value).map_err(error)?,
+                    value => {
+                        return Err(error(format!(
+                            "attempt to index a {} value",
+                            value.type_name()
+                        )))
+                    }
+                }
+            }
+            // The parser only accepts names and indexes as targets.
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates `exprs`, the last one being expanded to all its values.
+    fn eval_list(&mut self, exprs: &[Expr], env: &Env<'_>) -> Result<Vec<Value>> {
+        let mut values = Vec::with_capacity(exprs.len());
+
+        if let Some((last, exprs)) = exprs.split_last() {
+            for expr in exprs {
+                values.push(self.eval(expr, env)?);
+            }
+            values.extend(self.eval_multi(last, env)?);
+        }
+
+        Ok(values)
+    }
+
+    /// Evaluates `expr` to all its values. Only calls and `...` may have more
+    /// than one value.
+    fn eval_multi(&mut self, expr: &Expr, env: &Env<'_>) -> Result<Vec<Value>> {
+        match expr {
+            Expr::Vararg => Ok(env.varargs.to_vec()),
+            Expr::Call(function, args) => {
+                let function = self.eval(function, env)?;
+                let args = self.eval_list(args, env)?;
+                self.call(&function, args)
+            }
+            Expr::Method(object, name, args) => {
+                let object = self.eval(object, env)?;
+                let function = match &object {
+                    Value::Str(_) => self.string.borrow().get(&Value::Str(name.clone())),
+                    Value::Table(table) => table.borrow().get(&Value::Str(name.clone())),
+                    value => {
+                        return Err(error(format!(
+                            "attempt to index a {} value",
+                            value.type_name()
+                        )))
+                    }
+                };
+
+                let mut values = vec![object];
+                values.extend(self.eval_list(args, env)?);
+                self.call(&function, values)
+            }
+            expr => Ok(vec![self.eval(expr, env)?]),
+        }
+    }
+
+    /// Evaluates `expr` to its first value.
+    fn eval(&mut self, expr: &Expr, env: &Env<'_>) -> Result<Value> {
+        self.enter()?;
+        let res = self.eval_inner(expr, env);
+        self.depth -= 1;
+        res
+    }
+
+    fn eval_inner(&mut self, expr: &Expr, env: &Env<'_>) -> Result<Value> {
+        let value = match expr {
+            Expr::Nil => Value::Nil,
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Function(body) => self.closure(body.clone(), env.scope.clone()),
+            Expr::Name(name) => match env.scope.lookup(name) {
+                Some(var) => var.borrow().clone(),
+                None => {
+                    let value = self.globals.borrow().get_str(name);
+                    if matches!(value, Value::Nil) {
+                        return Err(error(format!(
+                            "Script attempted to access nonexistent global variable '{}'",
+                            name
+                        )));
+                    }
+                    value
+                }
+            },
+            Expr::Index(table, key) => {
+                let table = self.eval(table, env)?;
+                let key = self.eval(key, env)?;
+                match table {
+                    Value::Table(table) => table.borrow().get(&key),
+                    Value::Str(_) => self.string.borrow().get(&key),
+                    value => {
+                        return Err(error(format!(
+                            "attempt to index a {} value",
+                            value.type_name()
+                        )))
+                    }
+                }
+            }
+            Expr::Binary(BinOp::And, lhs, rhs) => {
+                let lhs = self.eval(lhs, env)?;
+                if lhs.truthy() {
+                    self.eval(rhs, env)?
+                } else {
+                    lhs
+                }
+            }
+            Expr::Binary(BinOp::Or, lhs, rhs) => {
+                let lhs = self.eval(lhs, env)?;
+                if lhs.truthy() {
+                    lhs
+                } else {
+                    self.eval(rhs, env)?
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, env)?;
+                let rhs = self.eval(rhs, env)?;
+                binary(*op, lhs, rhs)?
+            }
+            Expr::Unary(op, operand) => {
+                let operand = self.eval(operand, env)?;
+                match op {
+                    UnOp::Not => Value::Bool(!operand.truthy()),
+                    UnOp::Neg => match operand.to_number() {
+                        Some(n) => Value::Number(-n),
+                        None => return Err(arithmetic_error(&operand)),
+                    },
+                    UnOp::Len => match &operand {
+                        Value::Str(s) => Value::Number(s.len() as f64),
+                        Value::Table(table) => Value::Number(table.borrow().len() as f64),
+                        value => {
+                            return Err(error(format!(
+                                "attempt to get length of a {} value",
+                                value.type_name()
+                            )))
+                        }
+                    },
+                }
+            }
+            Expr::Table(fields) => {
+                let mut table = Table::default();
+                let mut positional = Vec::new();
+
+                for (i, field) in fields.iter().enumerate() {
+                    match field {
+                        Field::Named(key, value) => {
+                            let key = self.eval(key, env)?;
+                            let value = self.eval(value, env)?;
+                            table.set(key, value).map_err(error)?;
+                        }
+                        // The last positional field is expanded to all its
+                        // values.
+                        Field::Positional(value) if i == fields.len() - 1 => {
+                            positional.extend(self.eval_multi(value, env)?);
+                        }
+                        Field::Positional(value) => positional.push(self.eval(value, env)?),
+                    }
+                }
+
+                // Positional fields take precedence over named ones.
+                for (i, value) in positional.into_iter().enumerate() {
+                    table.set(Value::Number((i + 1) as f64), value).unwrap();
+                }
+
+                self.new_table(table)
+            }
+            Expr::Paren(expr) => self.eval(expr, env)?,
+            expr => self
+                .eval_multi(expr, env)?
+                .into_iter()
+                .next()
+                .unwrap_or(Value::Nil),
+        };
+
+        Ok(value)
+    }
+
+    fn builtin(&mut self, builtin: Builtin, args: Vec<Value>) -> Result<Vec<Value>> {
+        let name = builtin.name();
+
+        let value = match builtin {
+            Builtin::Assert => {
+                if args.first().map(Value::truthy).unwrap_or(false) {
+                    return Ok(args);
+                }
+
+                return Err(match args.get(1) {
+                    Some(msg) => msg.clone(),
+                    None => error("assertion failed!"),
+                });
+            }
+            Builtin::Error => return Err(args.into_iter().next().unwrap_or(Value::Nil)),
+            Builtin::Ipairs => {
+                let table = arg_table(&args, 0, name)?;
+                return Ok(vec![
+                    Builtin::IpairsNext.into(),
+                    Value::Table(table),
+                    Value::Number(0.0),
+                ]);
+            }
+            Builtin::IpairsNext => {
+                let table = arg_table(&args, 0, name)?;
+                let i = arg_number(&args, 1, name)? + 1.0;
+                let value = table.borrow().get(&Value::Number(i));
+
+                if matches!(value, Value::Nil) {
+                    Value::Nil
+                } else {
+                    return Ok(vec![Value::Number(i), value]);
+                }
+            }
+            Builtin::Next => {
+                let table = arg_table(&args, 0, name)?;
+                let key = args.get(1).cloned().unwrap_or(Value::Nil);
+
+                let next = table.borrow().next(&key).map_err(error)?;
+                match next {
+                    Some((key, value)) => return Ok(vec![key, value]),
+                    None => Value::Nil,
+                }
+            }
+            Builtin::Pairs => {
+                let table = arg_table(&args, 0, name)?;
+                return Ok(vec![Builtin::Next.into(), Value::Table(table), Value::Nil]);
+            }
+            Builtin::Pcall => {
+                let mut args = args.into_iter();
+                let function = args.next().unwrap_or(Value::Nil);
+
+                return match self.call(&function, args.collect()) {
+                    Ok(mut values) => {
+                        values.insert(0, Value::Bool(true));
+                        Ok(values)
+                    }
+                    Err(err) => Ok(vec![Value::Bool(false), err]),
+                };
+            }
+            Builtin::RawEqual => {
+                let a = args.first().cloned().unwrap_or(Value::Nil);
+                let b = args.get(1).cloned().unwrap_or(Value::Nil);
+                Value::Bool(a.raw_equal(&b))
+            }
+            Builtin::RawGet => {
+                let table = arg_table(&args, 0, name)?;
+                let key = args.get(1).cloned().unwrap_or(Value::Nil);
+                let value = table.borrow().get(&key);
+                value
+            }
+            Builtin::RawSet => {
+                let table = arg_table(&args, 0, name)?;
+                let key = args.get(1).cloned().unwrap_or(Value::Nil);
+                let value = args.get(2).cloned().unwrap_or(Value::Nil);
+                table.borrow_mut().set(key, value).map_err(error)?;
+                Value::Table(table)
+            }
+            Builtin::Select => {
+                if matches!(args.first(), Some(Value::Str(s)) if &s[..] == b"#") {
+                    Value::Number((args.len() - 1) as f64)
+                } else {
+                    let n = arg_int(&args, 0, name)?;
+                    let count = args.len() as i64 - 1;
+
+                    let start = if n < 0 { count + n } else { n - 1 };
+                    if n == 0 || start < 0 {
+                        return Err(arg_error(1, name, "index out of range"));
+                    }
+
+                    return Ok(args.into_iter().skip(start as usize + 1).collect());
+                }
+            }
+            Builtin::ToNumber => {
+                let value = args.first().cloned().unwrap_or(Value::Nil);
+
+                match args.get(1) {
+                    None | Some(Value::Nil) => {
+                        value.to_number().map(Value::Number).unwrap_or(Value::Nil)
+                    }
+                    Some(_) => {
+                        let base = arg_int(&args, 1, name)?;
+                        if !(2..=36).contains(&base) {
+                            return Err(arg_error(2, name, "base out of range"));
+                        }
+
+                        let digits = arg_str(&args, 0, name)?;
+                        std::str::from_utf8(&digits)
+                            .ok()
+                            .and_then(|digits| i64::from_str_radix(digits.trim(), base as u32).ok())
+                            .map(|n| Value::Number(n as f64))
+                            .unwrap_or(Value::Nil)
+                    }
+                }
+            }
+            Builtin::ToString => Value::str(to_string(args.first().unwrap_or(&Value::Nil))),
+            Builtin::Type => match args.first() {
+                Some(value) => Value::str(value.type_name()),
+                None => return Err(arg_error(1, name, "value expected")),
+            },
+            Builtin::Unpack => {
+                let table = arg_table(&args, 0, name)?;
+                let table = table.borrow();
+
+                let start = opt_int(&args, 1, name)?.unwrap_or(1);
+                let end = opt_int(&args, 2, name)?.unwrap_or(table.len() as i64);
+                if end.checked_sub(start).is_none_or(|n| n >= MAX_UNPACK) {
+                    return Err(error("too many results to unpack"));
+                }
+
+                return Ok((start..=end)
+                    .map(|i| table.get(&Value::Number(i as f64)))
+                    .collect());
+            }
+
+            Builtin::RedisCall | Builtin::RedisPcall => {
+                if self.registered.is_some() {
+                    return Err(error(format!(
+                        "redis.{} can not be called while loading a library",
+                        name
+                    )));
+                }
+                if args.is_empty() {
+                    return Err(error(
+                        "Please specify at least one argument for this redis lib call",
+                    ));
+                }
+
+                let mut command = Vec::with_capacity(args.len());
+                for arg in &args {
+                    match arg {
+                        Value::Str(s) => command.push(s.clone()),
+                        Value::Number(n) => command.push(Bytes::from(format_g(*n, 17))),
+                        _ => {
+                            return Err(error(
+                                "Lua redis lib command arguments must be strings or integers",
+                            ))
+                        }
+                    }
+                }
+
+                let reply = (self.execute)(command);
+                let value = self.frame_value(reply);
+
+                if matches!(builtin, Builtin::RedisCall) && is_error_reply(&value) {
+                    return Err(value);
+                }
+                value
+            }
+            Builtin::RedisSha1Hex => Value::str(sha1_hex(&arg_str(&args, 0, name)?)),
+            Builtin::RedisErrorReply => {
+                let mut table = Table::default();
+                table.set_str("err", Value::Str(arg_str(&args, 0, name)?));
+                self.new_table(table)
+            }
+            Builtin::RedisStatusReply => {
+                let mut table = Table::default();
+                table.set_str("ok", Value::Str(arg_str(&args, 0, name)?));
+                self.new_table(table)
+            }
+            // Scripts have no access to the server log.
+            Builtin::RedisLog => Value::Nil,
+            Builtin::RedisRegisterFunction => {
+                let function = register_args(args)?;
+                let registered =
+                    match &mut self.registered {
+                        Some(registered) => registered,
+                        None => return Err(error(
+                            "redis.register_function can only be called on FUNCTION LOAD command",
+                        )),
+                    };
+
+                if registered.iter().any(|other| other.name == function.name) {
+                    return Err(error("Function already exists in the library"));
+                }
+                registered.push(function);
+                Value::Nil
+            }
+
+            Builtin::StringByte => {
+                let s = arg_str(&args, 0, name)?;
+                let start = opt_int(&args, 1, name)?.unwrap_or(1);
+                let end = opt_int(&args, 2, name)?.unwrap_or(start);
+
+                let (start, end) = str_range(s.len(), start, end);
+                return Ok(s[start..end]
+                    .iter()
+                    .map(|&b| Value::Number(b as f64))
+                    .collect());
+            }
+            Builtin::StringChar => {
+                let mut s = Vec::with_capacity(args.len());
+                for i in 0..args.len() {
+                    match arg_int(&args, i, name)? {
+                        c @ 0..=255 => s.push(c as u8),
+                        _ => return Err(arg_error(i + 1, name, "invalid value")),
+                    }
+                }
+                Value::str(s)
+            }
+            Builtin::StringFind => {
+                let s = arg_str(&args, 0, name)?;
+                let pattern = arg_str(&args, 1, name)?;
+                let init = opt_int(&args, 2, name)?.unwrap_or(1);
+                let plain = args.get(3).map(Value::truthy).unwrap_or(false);
+
+                if !plain && pattern.iter().any(|c| b"^$*+?.([%-".contains(c)) {
+                    return Err(error("string patterns are not supported, use plain find"));
+                }
+
+                let (start, _) = str_range(s.len(), init, -1);
+                let found = if pattern.is_empty() {
+                    Some(start)
+                } else {
+                    s[start..]
+                        .windows(pattern.len())
+                        .position(|window| window == &pattern[..])
+                        .map(|i| start + i)
+                };
+
+                match found {
+                    Some(i) => {
+                        return Ok(vec![
+                            Value::Number((i + 1) as f64),
+                            Value::Number((i + pattern.len()) as f64),
+                        ])
+                    }
+                    None => Value::Nil,
+                }
+            }
+            Builtin::StringFormat => Value::str(format(&args)?),
+            Builtin::StringLen => Value::Number(arg_str(&args, 0, name)?.len() as f64),
+            Builtin::StringLower => Value::str(arg_str(&args, 0, name)?.to_ascii_lowercase()),
+            Builtin::StringRep => {
+                let s = arg_str(&args, 0, name)?;
+                let n = arg_int(&args, 1, name)?.max(0) as usize;
+                if s.len()
+                    .checked_mul(n)
+                    .is_none_or(|len| len > MAX_STRING_LEN)
+                {
+                    return Err(error("resulting string too large"));
+                }
+                Value::str(s.repeat(n))
+            }
+            Builtin::StringReverse => {
+                let mut s = arg_str(&args, 0, name)?.to_vec();
+                s.reverse();
+                Value::str(s)
+            }
+            Builtin::StringSub => {
+                let s = arg_str(&args, 0, name)?;
+                let start = opt_int(&args, 1, name)?.unwrap_or(1);
+                let end = opt_int(&args, 2, name)?.unwrap_or(-1);
+
+                let (start, end) = str_range(s.len(), start, end);
+                Value::Str(s.slice(start..end))
+            }
+            Builtin::StringUpper => Value::str(arg_str(&args, 0, name)?.to_ascii_uppercase()),
+
+            Builtin::TableConcat => {
+                let table = arg_table(&args, 0, name)?;
+                let table = table.borrow();
+                let sep = match args.get(1) {
+                    None | Some(Value::Nil) => Bytes::new(),
+                    Some(_) => arg_str(&args, 1, name)?,
+                };
+                let start = opt_int(&args, 2, name)?.unwrap_or(1);
+                let end = opt_int(&args, 3, name)?.unwrap_or(table.len() as i64);
+
+                let mut s = vec![];
+                for i in start..=end {
+                    let value = table.get(&Value::Number(i as f64));
+                    match value.to_str() {
+                        Some(value) if s.len() + value.len() + sep.len() > MAX_STRING_LEN => {
+                            return Err(error("resulting string too large"))
+                        }
+                        Some(value) => s.extend_from_slice(&value),
+                        None => {
+                            return Err(error(format!(
+                                "invalid value (at index {}) in table for 'concat'",
+                                i
+                            )))
+                        }
+                    }
+                    if i < end {
+                        s.extend_from_slice(&sep);
+                    }
+                }
+                Value::str(s)
+            }
+            Builtin::TableInsert => {
+                let table = arg_table(&args, 0, name)?;
+                let mut table = table.borrow_mut();
+
+                match args.len() {
+                    2 => table.push(args[1].clone()),
+                    3 => {
+                        let pos = arg_int(&args, 1, name)?;
+                        if pos < 1 || pos as usize > table.len() + 1 {
+                            return Err(arg_error(2, name, "position out of bounds"));
+                        }
+                        table.insert(pos as usize - 1, args[2].clone());
+                    }
+                    _ => return Err(error("wrong number of arguments to 'insert'")),
+                }
+                Value::Nil
+            }
+            Builtin::TableRemove => {
+                let table = arg_table(&args, 0, name)?;
+                let mut table = table.borrow_mut();
+                let len = table.len() as i64;
+                let pos = opt_int(&args, 1, name)?.unwrap_or(len);
+
+                if len == 0 || pos < 1 || pos > len {
+                    Value::Nil
+                } else {
+                    table.remove(pos as usize - 1)
+                }
+            }
+
+            Builtin::MathAbs => Value::Number(arg_number(&args, 0, name)?.abs()),
+            Builtin::MathCeil => Value::Number(arg_number(&args, 0, name)?.ceil()),
+            Builtin::MathFloor => Value::Number(arg_number(&args, 0, name)?.floor()),
+            Builtin::MathFmod => {
+                Value::Number(arg_number(&args, 0, name)? % arg_number(&args, 1, name)?)
+            }
+            Builtin::MathMax | Builtin::MathMin => {
+                let mut n = arg_number(&args, 0, name)?;
+                for i in 1..args.len() {
+                    let other = arg_number(&args, i, name)?;
+                    if matches!(builtin, Builtin::MathMax) == (other > n) {
+                        n = other;
+                    }
+                }
+                Value::Number(n)
+            }
+            Builtin::MathPow => {
+                Value::Number(arg_number(&args, 0, name)?.powf(arg_number(&args, 1, name)?))
+            }
+            Builtin::MathSqrt => Value::Number(arg_number(&args, 0, name)?.sqrt()),
+            Builtin::Metatables => return Err(error("metatables are not supported")),
+            Builtin::Coroutines => return Err(error("coroutines are not supported")),
+            Builtin::Cjson => return Err(error("cjson is not supported")),
+            Builtin::Cmsgpack => return Err(error("cmsgpack is not supported")),
+            Builtin::StringPatterns => return Err(error("string patterns are not supported")),
+        };
+
+        Ok(vec![value])
+    }
+
+    /// Converts the reply of a command to a Lua value.
+    ///
+    /// Status and error replies are converted to tables with a single `ok`
+    /// or `err` field, as returned by `redis.status_reply` and
+    /// `redis.error_reply`. Nil replies are converted to `false`.
+    pub(super) fn frame_value(&mut self, frame: Frame) -> Value {
+        match frame {
+            Frame::Simple(s) => {
+                let mut table = Table::default();
+                table.set_str("ok", Value::str(s));
+                self.new_table(table)
+            }
+            Frame::Error(s) => {
+                let mut table = Table::default();
+                table.set_str("err", Value::str(s));
+                self.new_table(table)
+            }
+            Frame::Integer(n) => Value::Number(n as f64),
+            Frame::Bulk(s) => Value::Str(s),
+            Frame::Null => Value::Bool(false),
+            Frame::Array(frames) | Frame::Set(frames) | Frame::Push(frames) => {
+                let values = frames
+                    .into_iter()
+                    .map(|frame| self.frame_value(frame))
+                    .collect();
+                self.new_table(Table::from_array(values))
+            }
+            // Scripts see the replies of RESP2, in which maps are flat arrays
+            // of keys and values, doubles are strings and booleans integers.
+            Frame::Map(entries) => {
+                let values = entries
+                    .into_iter()
+                    .flat_map(|(key, value)| vec![key, value])
+                    .map(|frame| self.frame_value(frame))
+                    .collect();
+                self.new_table(Table::from_array(values))
+            }
+            Frame::Double(n) => Value::str(format_double(n)),
+            Frame::Boolean(b) => Value::Number(b as i64 as f64),
+        }
+    }
+}
+
+impl Drop for Interp<'_> {
+    fn drop(&mut self) {
+        // Values may reference each other, clearing the tables and the scopes
+        // of the closures breaks the cycles.
+        for closure in self
+            .closures
+            .drain(..)
+            .filter_map(|closure| closure.upgrade())
+        {
+            closure.scope.borrow_mut().take();
+        }
+        for table in self.tables.drain(..).filter_map(|table| table.upgrade()) {
+            let cleared = std::mem::take(&mut *table.borrow_mut());
+            drop(cleared);
+        }
+    }
+}
+
+impl Scope {
+    fn child(parent: &Rc<Scope>) -> Rc<Scope> {
+        Rc::new(Scope {
+            vars: RefCell::default(),
+            parent: Some(parent.clone()),
+        })
+    }
+
+    fn declare(&self, name: String, value: Value) {
+        self.vars
+            .borrow_mut()
+            .push((name, Rc::new(RefCell::new(value))));
+    }
+
+    /// Returns the local variable `name`, the innermost one if several have
+    /// the same name.
+    fn lookup(&self, name: &str) -> Option<Rc<RefCell<Value>>> {
+        let mut scope = self;
+        loop {
+            let var = scope
+                .vars
+                .borrow()
+                .iter()
+                .rev()
+                .find(|(var, _)| var == name)
+                .map(|(_, value)| value.clone());
+
+            if var.is_some() {
+                return var;
+            }
+
+            scope = scope.parent.as_deref()?;
+        }
+    }
+}
+
+impl Builtin {
+    fn name(self) -> &'static str {
+        match self {
+            Builtin::Assert => "assert",
+            Builtin::Error => "error",
+            Builtin::Ipairs | Builtin::IpairsNext => "ipairs",
+            Builtin::Next => "next",
+            Builtin::Pairs => "pairs",
+            Builtin::Pcall => "pcall",
+            Builtin::RawEqual => "rawequal",
+            Builtin::RawGet => "rawget",
+            Builtin::RawSet => "rawset",
+            Builtin::Select => "select",
+            Builtin::ToNumber => "tonumber",
+            Builtin::ToString => "tostring",
+            Builtin::Type => "type",
+            Builtin::Unpack => "unpack",
+            Builtin::RedisCall => "call",
+            Builtin::RedisPcall => "pcall",
+            Builtin::RedisSha1Hex => "sha1hex",
+            Builtin::RedisErrorReply => "error_reply",
+            Builtin::RedisStatusReply => "status_reply",
+            Builtin::RedisLog => "log",
+            Builtin::RedisRegisterFunction => "register_function",
+            Builtin::StringByte => "byte",
+            Builtin::StringChar => "char",
+            Builtin::StringFind => "find",
+            Builtin::StringFormat => "format",
+            Builtin::StringLen => "len",
+            Builtin::StringLower => "lower",
+            Builtin::StringRep => "rep",
+            Builtin::StringReverse => "reverse",
+            Builtin::StringSub => "sub",
+            Builtin::StringUpper => "upper",
+            Builtin::TableConcat => "concat",
+            Builtin::TableInsert => "insert",
+            Builtin::TableRemove => "remove",
+            Builtin::MathAbs => "abs",
+            Builtin::MathCeil => "ceil",
+            Builtin::MathFloor => "floor",
+            Builtin::MathFmod => "fmod",
+            Builtin::MathMax => "max",
+            Builtin::MathMin => "min",
+            Builtin::MathPow => "pow",
+            Builtin::MathSqrt => "sqrt",
+            Builtin::Metatables => "metatable",
+            Builtin::Coroutines => "coroutine",
+            Builtin::Cjson => "cjson",
+            Builtin::Cmsgpack => "cmsgpack",
+            Builtin::StringPatterns => "pattern",
+        }
+    }
+}
+
+impl From<Builtin> for Value {
+    fn from(builtin: Builtin) -> Value {
+        Value::Function(Function::Builtin(builtin))
+    }
+}
+
+/// Converts a value returned by a script to the reply of the script.
+///
+/// Numbers are truncated to integers, `true` is converted to 1 and `false` to
+/// a nil reply. Tables with an `ok` or `err` field are converted to status and
+/// error replies, other tables to arrays of their values at keys 1 to the
+/// first nil value.
+pub(super) fn to_frame(value: &Value) -> Frame {
+    match value {
+        Value::Nil | Value::Bool(false) | Value::Function(_) => Frame::Null,
+        Value::Bool(true) => Frame::Integer(1),
+        Value::Number(n) => Frame::Integer(*n as i64),
+        Value::Str(s) => Frame::Bulk(s.clone()),
+        Value::Table(table) => {
+            let table = table.borrow();
+
+            if let Value::Str(err) = table.get_str("err") {
+                return Frame::Error(String::from_utf8_lossy(&err).into_owned());
+            }
+            if let Value::Str(ok) = table.get_str("ok") {
+                return Frame::Simple(String::from_utf8_lossy(&ok).into_owned());
+            }
+
+            Frame::Array(table.array().iter().map(to_frame).collect())
+        }
+    }
+}
+
+/// Converts an error raised by a script to an error reply.
+pub(super) fn error_frame(err: &Value) -> Frame {
+    Frame::Error(error_message(err))
+}
+
+/// Returns the message of the error reply for an error raised by a script.
+pub(super) fn error_message(err: &Value) -> String {
+    if let Value::Table(table) = err {
+        if let Value::Str(err) = table.borrow().get_str("err") {
+            return String::from_utf8_lossy(&err).into_owned();
+        }
+    }
+
+    let msg = match err.to_str() {
+        Some(msg) => String::from_utf8_lossy(&msg).into_owned(),
+        None => format!("(error object is a {} value)", err.type_name()),
+    };
+    format!("ERR Error running script: {}", msg)
+}
+
+/// Returns `true` if `value` is a table with an `err` field, as created by
+/// `redis.error_reply`.
+fn is_error_reply(value: &Value) -> bool {
+    match value {
+        Value::Table(table) => matches!(table.borrow().get_str("err"), Value::Str(_)),
+        _ => false,
+    }
+}
+
+/// Parses the arguments of `redis.register_function`, either a name and a
+/// callback or a table with the `function_name`, `callback`, `flags` and
+/// `description` fields.
+fn register_args(args: Vec<Value>) -> Result<Registered> {
+    let mut function = Registered {
+        name: String::new(),
+        callback: Value::Nil,
+        flags: vec![],
+        description: None,
+    };
+
+    let (name, callback) = match &args[..] {
+        [Value::Table(table)] => {
+            let table = table.borrow();
+
+            let mut key = Value::Nil;
+            while let Some((next, value)) = table.next(&key).map_err(error)? {
+                match next.to_str().as_deref() {
+                    Some(b"function_name") | Some(b"callback") => {}
+                    Some(b"flags") => {
+                        let flags = match &value {
+                            Value::Table(flags) => flags.borrow().array().to_vec(),
+                            value => vec![value.clone()],
+                        };
+                        for flag in flags {
+                            match flag.to_str() {
+                                Some(flag)
+                                    if FUNCTION_FLAGS.iter().any(|f| f.as_bytes() == flag) =>
+                                {
+                                    function
+                                        .flags
+                                        .push(String::from_utf8_lossy(&flag).into_owned())
+                                }
+                                _ => return Err(error("unknown flag given")),
+                            }
+                        }
+                    }
+                    Some(b"description") => match value {
+                        Value::Str(description) => function.description = Some(description),
+                        _ => {
+                            return Err(error(
+                                "description given to redis.register_function must be a string",
+                            ))
+                        }
+                    },
+                    _ => return Err(error("unknown argument given to redis.register_function")),
+                }
+                key = next;
+            }
+
+            (table.get_str("function_name"), table.get_str("callback"))
+        }
+        [name, callback] => (name.clone(), callback.clone()),
+        _ => {
+            return Err(error(
+                "wrong number of arguments to redis.register_function",
+            ))
+        }
+    };
+
+    match name {
+        Value::Str(name) => function.name = String::from_utf8_lossy(&name).into_owned(),
+        _ => {
+            return Err(error(
+                "function_name argument given to redis.register_function must be a string",
+            ))
+        }
+    }
+    if !super::is_valid_name(&function.name) {
+        return Err(error("Function names can only contain letters, numbers, or underscores(_) and must be at least one character long"));
+    }
+
+    match callback {
+        Value::Function(_) => function.callback = callback,
+        _ => {
+            return Err(error(
+                "callback argument given to redis.register_function must be a function",
+            ))
+        }
+    }
+
+    Ok(function)
+}
+
+/// Adds `rc` to the values tracked in `list`, dropping the references to the
+/// values already released.
+fn track<T>(list: &mut Vec<Weak<T>>, rc: &Rc<T>) {
+    if list.len() == list.capacity() {
+        list.retain(|weak| weak.strong_count() > 0);
+    }
+    list.push(Rc::downgrade(rc));
+}
+
+fn library(functions: &[(&str, Builtin)]) -> Table {
+    let mut table = Table::default();
+    for (name, builtin) in functions {
+        table.set_str(name, (*builtin).into());
+    }
+    table
+}
+
+fn binary(op: BinOp, lhs: Value, rhs: Value) -> Result<Value> {
+    let value = match op {
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::Pow => {
+            let a = lhs.to_number().ok_or_else(|| arithmetic_error(&lhs))?;
+            let b = rhs.to_number().ok_or_else(|| arithmetic_error(&rhs))?;
+
+            Value::Number(match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Mod => a - (a / b).floor() * b,
+                _ => a.powf(b),
+            })
+        }
+        BinOp::Concat => {
+            let mut a = match lhs.to_str() {
+                Some(a) => a.to_vec(),
+                None => return Err(concat_error(&lhs)),
+            };
+            match rhs.to_str() {
+                Some(b) if a.len() + b.len() > MAX_STRING_LEN => {
+                    return Err(error("resulting string too large"))
+                }
+                Some(b) => a.extend_from_slice(&b),
+                None => return Err(concat_error(&rhs)),
+            }
+            Value::str(a)
+        }
+        BinOp::Eq => Value::Bool(lhs.raw_equal(&rhs)),
+        BinOp::Ne => Value::Bool(!lhs.raw_equal(&rhs)),
+        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+            let ordering = match (&lhs, &rhs) {
+                (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+                (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+                _ => {
+                    return Err(error(format!(
+                        "attempt to compare {} with {}",
+                        lhs.type_name(),
+                        rhs.type_name()
+                    )))
+                }
+            };
+
+            Value::Bool(match ordering {
+                Some(ordering) => match op {
+                    BinOp::Lt => ordering.is_lt(),
+                    BinOp::Le => ordering.is_le(),
+                    BinOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                },
+                // Comparisons with NaN are false.
+                None => false,
+            })
+        }
+        // Short-circuit operators are evaluated by `eval`.
+        BinOp::And | BinOp::Or => unreachable!(),
+    };
+
+    Ok(value)
+}
+
+/// Converts a value to a string, as `tostring`.
+fn to_string(value: &Value) -> Bytes {
+    match value {
+        Value::Nil => Bytes::from_static(b"nil"),
+        Value::Bool(b) => Bytes::from(b.to_string()),
+        Value::Number(n) => Bytes::from(format_number(*n)),
+        Value::Str(s) => s.clone(),
+        Value::Table(table) => Bytes::from(format!("table: {:p}", Rc::as_ptr(table))),
+        Value::Function(Function::Closure(closure)) => {
+            Bytes::from(format!("function: {:p}", Rc::as_ptr(closure)))
+        }
+        Value::Function(Function::Builtin(builtin)) => {
+            Bytes::from(format!("function: builtin: {}", builtin.name()))
+        }
+    }
+}
+
+/// Formats `args` as `string.format`.
+fn format(args: &[Value]) -> Result<Vec<u8>> {
+    let name = "format";
+    let spec = arg_str(args, 0, name)?;
+    let mut out = vec![];
+    let mut arg = 1;
+    let mut i = 0;
+
+    while i < spec.len() {
+        if spec[i] != b'%' {
+            out.push(spec[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+
+        if spec.get(i) == Some(&b'%') {
+            out.push(b'%');
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while matches!(spec.get(i), Some(b'-' | b'0' | b'+' | b' ' | b'#')) {
+            i += 1;
+        }
+        let flags = &spec[start..i];
+        let left = flags.contains(&b'-');
+        let zero = flags.contains(&b'0');
+        let plus = flags.contains(&b'+');
+
+        // As in Lua, the width and the precision have at most two digits.
+        let digits = |i: &mut usize| {
+            let mut n = 0;
+            for _ in 0..2 {
+                match spec.get(*i) {
+                    Some(d @ b'0'..=b'9') => n = n * 10 + (d - b'0') as usize,
+                    _ => break,
+                }
+                *i += 1;
+            }
+            match spec.get(*i) {
+                Some(b'0'..=b'9') => Err(error("invalid format (width or precision too long)")),
+                _ => Ok(n),
+            }
+        };
+
+        let width = digits(&mut i)?;
+
+        let mut precision = None;
+        if spec.get(i) == Some(&b'.') {
+            i += 1;
+            precision = Some(digits(&mut i)?);
+        }
+
+        let conversion = match spec.get(i) {
+            Some(c) => *c,
+            None => return Err(error("invalid option '%' to 'format'")),
+        };
+        i += 1;
+
+        let mut numeric = true;
+        let formatted: Vec<u8> = match conversion {
+            b'd' | b'i' => {
+                let n = arg_number(args, arg, name)? as i64;
+                let digits = n.unsigned_abs().to_string();
+                let sign = if n < 0 {
+                    "-"
+                } else if plus {
+                    "+"
+                } else {
+                    ""
+                };
+                format!("{}{}", sign, digits).into_bytes()
+            }
+            b'u' => (arg_number(args, arg, name)? as u64)
+                .to_string()
+                .into_bytes(),
+            b'c' => vec![arg_number(args, arg, name)? as u8],
+            b'x' => format!("{:x}", arg_number(args, arg, name)? as i64).into_bytes(),
+            b'X' => format!("{:X}", arg_number(args, arg, name)? as i64).into_bytes(),
+            b'o' => format!("{:o}", arg_number(args, arg, name)? as i64).into_bytes(),
+            b'f' | b'F' => {
+                let n = arg_number(args, arg, name)?;
+                let s = format!("{:.*}", precision.unwrap_or(6), n);
+                signed(s, plus).into_bytes()
+            }
+            b'e' | b'E' => {
+                let n = arg_number(args, arg, name)?;
+                let s = format!("{:.*e}", precision.unwrap_or(6), n);
+                // C prints at least two digits of exponent.
+                let (mantissa, exponent) = s.split_once('e').unwrap();
+                let exponent: i32 = exponent.parse().unwrap();
+                let sign = if exponent < 0 { '-' } else { '+' };
+                let e = if conversion == b'e' { 'e' } else { 'E' };
+                let s = format!("{}{}{}{:02}", mantissa, e, sign, exponent.abs());
+                signed(s, plus).into_bytes()
+            }
+            b'g' | b'G' => {
+                let n = arg_number(args, arg, name)?;
+                let s = format_g(n, precision.unwrap_or(6).max(1));
+                let s = if conversion == b'G' {
+                    s.to_uppercase()
+                } else {
+                    s
+                };
+                signed(s, plus).into_bytes()
+            }
+            b's' => {
+                numeric = false;
+                let s = to_string(args.get(arg).unwrap_or(&Value::Nil));
+                match precision {
+                    Some(p) if p < s.len() => s[..p].to_vec(),
+                    _ => s.to_vec(),
+                }
+            }
+            b'q' => {
+                numeric = false;
+                let s = arg_str(args, arg, name)?;
+                let mut quoted = vec![b'"'];
+                for &c in s.iter() {
+                    match c {
+                        b'"' | b'\\' | b'\n' => quoted.extend_from_slice(&[b'\\', c]),
+                        b'\r' => quoted.extend_from_slice(b"\\r"),
+                        0 => quoted.extend_from_slice(b"\\000"),
+                        c => quoted.push(c),
+                    }
+                }
+                quoted.push(b'"');
+                quoted
+            }
+            c => {
+                return Err(error(format!(
+                    "invalid option '%{}' to 'format'",
+                    c as char
+                )))
+            }
+        };
+        arg += 1;
+
+        if out.len() + formatted.len().max(width) > MAX_STRING_LEN {
+            return Err(error("resulting string too large"));
+        }
+
+        let padding = width.saturating_sub(formatted.len());
+        if left {
+            out.extend_from_slice(&formatted);
+            out.extend(std::iter::repeat_n(b' ', padding));
+        } else if zero && numeric {
+            // Zeros are inserted after the sign.
+            let sign = matches!(formatted.first(), Some(b'-' | b'+')) as usize;
+            out.extend_from_slice(&formatted[..sign]);
+            out.extend(std::iter::repeat_n(b'0', padding));
+            out.extend_from_slice(&formatted[sign..]);
+        } else {
+            out.extend(std::iter::repeat_n(b' ', padding));
+            out.extend_from_slice(&formatted);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Prefixes positive numbers with `+` when `plus` is set.
+fn signed(s: String, plus: bool) -> String {
+    if plus && !s.starts_with('-') {
+        format!("+{}", s)
+    } else {
+        s
+    }
+}
+
+/// Converts Lua string positions, starting at 1 and negative from the end, to
+/// a range of bytes of a string of length `len`.
+fn str_range(len: usize, start: i64, end: i64) -> (usize, usize) {
+    let len = len as i64;
+    let position = |i: i64| if i < 0 { (len + i + 1).max(0) } else { i };
+
+    let start = position(start).max(1);
+    let end = position(end).min(len);
+
+    if start > end {
+        (0, 0)
+    } else {
+        (start as usize - 1, end as usize)
+    }
+}
+
+pub(super) fn error(msg: impl Into<String>) -> Value {
+    Value::str(msg.into())
+}
+
+fn arithmetic_error(value: &Value) -> Value {
+    error(format!(
+        "attempt to perform arithmetic on a {} value",
+        value.type_name()
+    ))
+}
+
+fn concat_error(value: &Value) -> Value {
+    error(format!(
+        "attempt to concatenate a {} value",
+        value.type_name()
+    ))
+}
+
+fn arg_error(position: usize, function: &str, msg: &str) -> Value {
+    error(format!(
+        "bad argument #{} to '{}' ({})",
+        position, function, msg
+    ))
+}
+
+fn arg_table(args: &[Value], i: usize, function: &str) -> Result<Rc<RefCell<Table>>> {
+    match args.get(i) {
+        Some(Value::Table(table)) => Ok(table.clone()),
+        value => Err(arg_error(
+            i + 1,
+            function,
+            &format!(
+                "table expected, got {}",
+                value.map(Value::type_name).unwrap_or("no value")
+            ),
+        )),
+    }
+}
+
+fn arg_number(args: &[Value], i: usize, function: &str) -> Result<f64> {
+    match args.get(i).and_then(Value::to_number) {
+        Some(n) => Ok(n),
+        None => Err(arg_error(
+            i + 1,
+            function,
+            &format!(
+                "number expected, got {}",
+                args.get(i).map(Value::type_name).unwrap_or("no value")
+            ),
+        )),
+    }
+}
+
+fn arg_int(args: &[Value], i: usize, function: &str) -> Result<i64> {
+    Ok(arg_number(args, i, function)? as i64)
+}
+
+fn opt_int(args: &[Value], i: usize, function: &str) -> Result<Option<i64>> {
+    match args.get(i) {
+        None | Some(Value::Nil) => Ok(None),
+        Some(_) => arg_int(args, i, function).map(Some),
+    }
+}
+
+fn arg_str(args: &[Value], i: usize, function: &str) -> Result<Bytes> {
+    match args.get(i).and_then(Value::to_str) {
+        Some(s) => Ok(s),
+        None => Err(arg_error(
+            i + 1,
+            function,
+            &format!(
+                "string expected, got {}",
+                args.get(i).map(Value::type_name).unwrap_or("no value")
+            ),
+        )),
+    }
+}
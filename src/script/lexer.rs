@@ -0,0 +1,370 @@
+//! Splits the source of a script into tokens.
+
+use bytes::Bytes;
+
+/// A token of the Lua language.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Name(String),
+    Number(f64),
+    Str(Bytes),
+
+    // Keywords
+    And,
+    Break,
+    Do,
+    Else,
+    ElseIf,
+    End,
+    False,
+    For,
+    Function,
+    If,
+    In,
+    Local,
+    Nil,
+    Not,
+    Or,
+    Repeat,
+    Return,
+    Then,
+    True,
+    Until,
+    While,
+
+    // Symbols
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Hash,
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Assign,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Semicolon,
+    Colon,
+    Comma,
+    Dot,
+    Concat,
+    Dots,
+
+    Eof,
+}
+
+/// Splits `source` into tokens, each one paired with the line it starts on.
+pub(super) fn tokenize(source: &[u8]) -> Result<Vec<(Token, usize)>, String> {
+    let mut lexer = Lexer {
+        source,
+        pos: 0,
+        line: 1,
+    };
+
+    let mut tokens = vec![];
+    loop {
+        lexer.skip_whitespace()?;
+        let line = lexer.line;
+        let token = lexer.next_token()?;
+        let eof = token == Token::Eof;
+        tokens.push((token, line));
+
+        if eof {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'a> {
+    source: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.source.get(self.pos + offset).copied()
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("line {}: {}", self.line, msg)
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<(), String> {
+        while let Some(c) = self.peek() {
+            match c {
+                b'\n' => {
+                    self.line += 1;
+                    self.pos += 1;
+                }
+                b' ' | b'\t' | b'\r' => self.pos += 1,
+                b'-' if self.peek_at(1) == Some(b'-') => {
+                    self.pos += 2;
+
+                    if let Some(level) = self.long_bracket_level() {
+                        self.long_string(level)?;
+                    } else {
+                        while !matches!(self.peek(), None | Some(b'\n')) {
+                            self.pos += 1;
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<Token, String> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Ok(Token::Eof),
+        };
+
+        if c.is_ascii_alphabetic() || c == b'_' {
+            return Ok(self.name());
+        }
+
+        if c.is_ascii_digit()
+            || (c == b'.' && matches!(self.peek_at(1), Some(d) if d.is_ascii_digit()))
+        {
+            return self.number();
+        }
+
+        if c == b'"' || c == b'\'' {
+            return self.string(c);
+        }
+
+        if c == b'[' {
+            if let Some(level) = self.long_bracket_level() {
+                return Ok(Token::Str(Bytes::from(self.long_string(level)?)));
+            }
+        }
+
+        let two = self.peek_at(1);
+        let (token, len) = match (c, two) {
+            (b'.', Some(b'.')) if self.peek_at(2) == Some(b'.') => (Token::Dots, 3),
+            (b'.', Some(b'.')) => (Token::Concat, 2),
+            (b'=', Some(b'=')) => (Token::Eq, 2),
+            (b'~', Some(b'=')) => (Token::Ne, 2),
+            (b'<', Some(b'=')) => (Token::Le, 2),
+            (b'>', Some(b'=')) => (Token::Ge, 2),
+            (b'+', _) => (Token::Plus, 1),
+            (b'-', _) => (Token::Minus, 1),
+            (b'*', _) => (Token::Star, 1),
+            (b'/', _) => (Token::Slash, 1),
+            (b'%', _) => (Token::Percent, 1),
+            (b'^', _) => (Token::Caret, 1),
+            (b'#', _) => (Token::Hash, 1),
+            (b'<', _) => (Token::Lt, 1),
+            (b'>', _) => (Token::Gt, 1),
+            (b'=', _) => (Token::Assign, 1),
+            (b'(', _) => (Token::LParen, 1),
+            (b')', _) => (Token::RParen, 1),
+            (b'{', _) => (Token::LBrace, 1),
+            (b'}', _) => (Token::RBrace, 1),
+            (b'[', _) => (Token::LBracket, 1),
+            (b']', _) => (Token::RBracket, 1),
+            (b';', _) => (Token::Semicolon, 1),
+            (b':', _) => (Token::Colon, 1),
+            (b',', _) => (Token::Comma, 1),
+            (b'.', _) => (Token::Dot, 1),
+            _ => return Err(self.error(&format!("unexpected symbol '{}'", c as char))),
+        };
+
+        self.pos += len;
+        Ok(token)
+    }
+
+    fn name(&mut self) -> Token {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == b'_') {
+            self.pos += 1;
+        }
+
+        let name = String::from_utf8_lossy(&self.source[start..self.pos]).into_owned();
+        match &name[..] {
+            "and" => Token::And,
+            "break" => Token::Break,
+            "do" => Token::Do,
+            "else" => Token::Else,
+            "elseif" => Token::ElseIf,
+            "end" => Token::End,
+            "false" => Token::False,
+            "for" => Token::For,
+            "function" => Token::Function,
+            "if" => Token::If,
+            "in" => Token::In,
+            "local" => Token::Local,
+            "nil" => Token::Nil,
+            "not" => Token::Not,
+            "or" => Token::Or,
+            "repeat" => Token::Repeat,
+            "return" => Token::Return,
+            "then" => Token::Then,
+            "true" => Token::True,
+            "until" => Token::Until,
+            "while" => Token::While,
+            _ => Token::Name(name),
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let exponent_sign = (c == b'+' || c == b'-')
+                && matches!(self.source[self.pos - 1], b'e' | b'E')
+                && !self.source[start..].starts_with(b"0x");
+
+            if c.is_ascii_alphanumeric() || c == b'.' || exponent_sign {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        let text = std::str::from_utf8(&self.source[start..self.pos]).unwrap();
+        match super::value::parse_number(text) {
+            Some(n) => Ok(Token::Number(n)),
+            None => Err(self.error(&format!("malformed number near '{}'", text))),
+        }
+    }
+
+    fn string(&mut self, quote: u8) -> Result<Token, String> {
+        self.pos += 1;
+        let mut value = vec![];
+
+        loop {
+            let c = match self.peek() {
+                Some(b'\n') | None => return Err(self.error("unfinished string")),
+                Some(c) => c,
+            };
+            self.pos += 1;
+
+            if c == quote {
+                return Ok(Token::Str(Bytes::from(value)));
+            }
+
+            if c != b'\\' {
+                value.push(c);
+                continue;
+            }
+
+            let escaped = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.error("unfinished string")),
+            };
+            self.pos += 1;
+
+            match escaped {
+                b'n' => value.push(b'\n'),
+                b't' => value.push(b'\t'),
+                b'r' => value.push(b'\r'),
+                b'a' => value.push(7),
+                b'b' => value.push(8),
+                b'f' => value.push(12),
+                b'v' => value.push(11),
+                b'\n' => {
+                    self.line += 1;
+                    value.push(b'\n');
+                }
+                b'x' => {
+                    let hex = self.source.get(self.pos..self.pos + 2).unwrap_or_default();
+                    let byte = std::str::from_utf8(hex)
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        .ok_or_else(|| self.error("hexadecimal digit expected"))?;
+                    self.pos += 2;
+                    value.push(byte);
+                }
+                c if c.is_ascii_digit() => {
+                    let mut code = (c - b'0') as u32;
+                    for _ in 0..2 {
+                        match self.peek() {
+                            Some(d) if d.is_ascii_digit() => {
+                                code = code * 10 + (d - b'0') as u32;
+                                self.pos += 1;
+                            }
+                            _ => break,
+                        }
+                    }
+
+                    if code > 255 {
+                        return Err(self.error("escape sequence too large"));
+                    }
+                    value.push(code as u8);
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    /// Returns the level of the long bracket starting at the current
+    /// position, such as `[[` or `[==[`, if any.
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.peek() != Some(b'[') {
+            return None;
+        }
+
+        let mut level = 0;
+        while self.peek_at(level + 1) == Some(b'=') {
+            level += 1;
+        }
+
+        if self.peek_at(level + 1) == Some(b'[') {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    /// Reads a long string or comment opened by a long bracket of `level`.
+    fn long_string(&mut self, level: usize) -> Result<Vec<u8>, String> {
+        self.pos += level + 2;
+
+        // A newline directly following the opening bracket is skipped.
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.line += 1;
+            self.pos += 1;
+        }
+
+        let mut close = vec![b']'];
+        close.extend(std::iter::repeat_n(b'=', level));
+        close.push(b']');
+
+        let start = self.pos;
+        loop {
+            if self.source[self.pos..].starts_with(&close) {
+                let value = self.source[start..self.pos].to_vec();
+                self.pos += close.len();
+                return Ok(value);
+            }
+
+            match self.peek() {
+                Some(b'\n') => self.line += 1,
+                Some(_) => {}
+                None => return Err(self.error("unfinished long string")),
+            }
+            self.pos += 1;
+        }
+    }
+}
@@ -0,0 +1,627 @@
+//! Parses the tokens of a script into a syntax tree.
+
+use super::lexer::{tokenize, Token};
+
+use bytes::Bytes;
+use std::sync::Arc;
+
+/// Body of a function, or of the main chunk of a script.
+#[derive(Debug)]
+pub(super) struct FuncBody {
+    pub(super) params: Vec<String>,
+    pub(super) is_vararg: bool,
+    pub(super) block: Block,
+}
+
+pub(super) type Block = Vec<Stat>;
+
+#[derive(Debug)]
+pub(super) enum Stat {
+    Local(Vec<String>, Vec<Expr>),
+    Assign(Vec<Expr>, Vec<Expr>),
+    Call(Expr),
+    Do(Block),
+    While(Expr, Block),
+    Repeat(Block, Expr),
+    If(Vec<(Expr, Block)>, Option<Block>),
+    NumericFor {
+        var: String,
+        start: Expr,
+        limit: Expr,
+        step: Option<Expr>,
+        block: Block,
+    },
+    GenericFor {
+        vars: Vec<String>,
+        exprs: Vec<Expr>,
+        block: Block,
+    },
+    LocalFunction(String, Arc<FuncBody>),
+    Return(Vec<Expr>),
+    Break,
+}
+
+#[derive(Debug)]
+pub(super) enum Expr {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Bytes),
+    Vararg,
+    Function(Arc<FuncBody>),
+    Name(String),
+    Index(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    Method(Box<Expr>, Bytes, Vec<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Unary(UnOp, Box<Expr>),
+    Table(Vec<Field>),
+    Paren(Box<Expr>),
+}
+
+#[derive(Debug)]
+pub(super) enum Field {
+    Positional(Expr),
+    Named(Expr, Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum UnOp {
+    Neg,
+    Not,
+    Len,
+}
+
+/// Priority of unary operators.
+const UNARY_PRIORITY: u8 = 8;
+
+/// Maximum nesting of blocks and expressions, bounding the stack used by the
+/// parser.
+const MAX_LEVELS: usize = 200;
+
+/// Parses `source` into the body of the main chunk, which takes a variable
+/// number of arguments.
+pub(super) fn parse(source: &[u8]) -> Result<FuncBody, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+        vararg: vec![true],
+        levels: 0,
+    };
+
+    let block = parser.block()?;
+    if parser.peek() != &Token::Eof {
+        return Err(parser.error("'<eof>' expected"));
+    }
+
+    Ok(FuncBody {
+        params: vec![],
+        is_vararg: true,
+        block,
+    })
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+
+    /// Whether `...` may be used, for each function being parsed.
+    vararg: Vec<bool>,
+
+    /// Nesting of the block or expression being parsed.
+    levels: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn check(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), String> {
+        if self.check(&token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}' expected", what)))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        match self.next() {
+            Token::Name(name) => Ok(name),
+            _ => Err(self.error("<name> expected")),
+        }
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("line {}: {}", self.tokens[self.pos].1, msg)
+    }
+
+    /// Returns `true` if the next token ends a block.
+    fn block_end(&self) -> bool {
+        matches!(
+            self.peek(),
+            Token::Eof | Token::End | Token::Else | Token::ElseIf | Token::Until
+        )
+    }
+
+    /// Increments the nesting level, failing when it is too deep.
+    fn enter(&mut self) -> Result<(), String> {
+        if self.levels >= MAX_LEVELS {
+            return Err(self.error("chunk has too many syntax levels"));
+        }
+        self.levels += 1;
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<Block, String> {
+        self.enter()?;
+        let block = self.block_inner();
+        self.levels -= 1;
+        block
+    }
+
+    fn block_inner(&mut self) -> Result<Block, String> {
+        let mut block = vec![];
+
+        while !self.block_end() {
+            if self.check(&Token::Return) {
+                let exprs = if self.block_end() || self.peek() == &Token::Semicolon {
+                    vec![]
+                } else {
+                    self.expr_list()?
+                };
+                self.check(&Token::Semicolon);
+                block.push(Stat::Return(exprs));
+
+                // `return` must be the last statement of a block.
+                if !self.block_end() {
+                    return Err(self.error("'end' expected"));
+                }
+                break;
+            }
+
+            let stat = self.statement()?;
+            let is_break = matches!(stat, Stat::Break);
+            block.push(stat);
+            self.check(&Token::Semicolon);
+
+            if is_break && !self.block_end() {
+                return Err(self.error("'end' expected"));
+            }
+        }
+
+        Ok(block)
+    }
+
+    fn statement(&mut self) -> Result<Stat, String> {
+        match self.peek() {
+            Token::If => {
+                self.next();
+                let mut branches = vec![];
+                let cond = self.expr()?;
+                self.expect(Token::Then, "then")?;
+                branches.push((cond, self.block()?));
+
+                let mut otherwise = None;
+                loop {
+                    match self.next() {
+                        Token::ElseIf => {
+                            let cond = self.expr()?;
+                            self.expect(Token::Then, "then")?;
+                            branches.push((cond, self.block()?));
+                        }
+                        Token::Else => {
+                            otherwise = Some(self.block()?);
+                            self.expect(Token::End, "end")?;
+                            break;
+                        }
+                        Token::End => break,
+                        _ => return Err(self.error("'end' expected")),
+                    }
+                }
+
+                Ok(Stat::If(branches, otherwise))
+            }
+            Token::While => {
+                self.next();
+                let cond = self.expr()?;
+                self.expect(Token::Do, "do")?;
+                let block = self.block()?;
+                self.expect(Token::End, "end")?;
+                Ok(Stat::While(cond, block))
+            }
+            Token::Do => {
+                self.next();
+                let block = self.block()?;
+                self.expect(Token::End, "end")?;
+                Ok(Stat::Do(block))
+            }
+            Token::Repeat => {
+                self.next();
+                let block = self.block()?;
+                self.expect(Token::Until, "until")?;
+                Ok(Stat::Repeat(block, self.expr()?))
+            }
+            Token::For => {
+                self.next();
+                let var = self.name()?;
+
+                if self.check(&Token::Assign) {
+                    let start = self.expr()?;
+                    self.expect(Token::Comma, ",")?;
+                    let limit = self.expr()?;
+                    let step = if self.check(&Token::Comma) {
+                        Some(self.expr()?)
+                    } else {
+                        None
+                    };
+                    self.expect(Token::Do, "do")?;
+                    let block = self.block()?;
+                    self.expect(Token::End, "end")?;
+
+                    return Ok(Stat::NumericFor {
+                        var,
+                        start,
+                        limit,
+                        step,
+                        block,
+                    });
+                }
+
+                let mut vars = vec![var];
+                while self.check(&Token::Comma) {
+                    vars.push(self.name()?);
+                }
+                self.expect(Token::In, "in")?;
+                let exprs = self.expr_list()?;
+                self.expect(Token::Do, "do")?;
+                let block = self.block()?;
+                self.expect(Token::End, "end")?;
+
+                Ok(Stat::GenericFor { vars, exprs, block })
+            }
+            Token::Function => {
+                self.next();
+
+                // `function a.b:c() end` assigns the function to `a.b.c`.
+                let mut target = Expr::Name(self.name()?);
+                let mut is_method = false;
+                loop {
+                    if self.check(&Token::Dot) {
+                        let key = Expr::Str(Bytes::from(self.name()?));
+                        target = Expr::Index(Box::new(target), Box::new(key));
+                    } else if self.check(&Token::Colon) {
+                        let key = Expr::Str(Bytes::from(self.name()?));
+                        target = Expr::Index(Box::new(target), Box::new(key));
+                        is_method = true;
+                        break;
+                    } else {
+                        break;
+                    }
+                }
+
+                let body = self.function_body(is_method)?;
+                Ok(Stat::Assign(vec![target], vec![Expr::Function(body)]))
+            }
+            Token::Local => {
+                self.next();
+
+                if self.check(&Token::Function) {
+                    let name = self.name()?;
+                    let body = self.function_body(false)?;
+                    return Ok(Stat::LocalFunction(name, body));
+                }
+
+                let mut names = vec![self.name()?];
+                while self.check(&Token::Comma) {
+                    names.push(self.name()?);
+                }
+
+                let exprs = if self.check(&Token::Assign) {
+                    self.expr_list()?
+                } else {
+                    vec![]
+                };
+
+                Ok(Stat::Local(names, exprs))
+            }
+            Token::Break => {
+                self.next();
+                Ok(Stat::Break)
+            }
+            _ => {
+                let expr = self.suffixed_expr()?;
+
+                if self.peek() == &Token::Assign || self.peek() == &Token::Comma {
+                    let mut targets = vec![expr];
+                    while self.check(&Token::Comma) {
+                        targets.push(self.suffixed_expr()?);
+                    }
+                    self.expect(Token::Assign, "=")?;
+
+                    if !targets
+                        .iter()
+                        .all(|target| matches!(target, Expr::Name(_) | Expr::Index(..)))
+                    {
+                        return Err(self.error("syntax error near '='"));
+                    }
+
+                    let exprs = self.expr_list()?;
+                    return Ok(Stat::Assign(targets, exprs));
+                }
+
+                match expr {
+                    Expr::Call(..) | Expr::Method(..) => Ok(Stat::Call(expr)),
+                    _ => Err(self.error("syntax error")),
+                }
+            }
+        }
+    }
+
+    /// Parses the parameters and the body of a function. Methods take an
+    /// implicit `self` first parameter.
+    fn function_body(&mut self, is_method: bool) -> Result<Arc<FuncBody>, String> {
+        let mut params = vec![];
+        if is_method {
+            params.push("self".to_string());
+        }
+
+        let mut is_vararg = false;
+        self.expect(Token::LParen, "(")?;
+        if !self.check(&Token::RParen) {
+            loop {
+                if self.check(&Token::Dots) {
+                    is_vararg = true;
+                    break;
+                }
+                params.push(self.name()?);
+                if !self.check(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::RParen, ")")?;
+        }
+
+        self.vararg.push(is_vararg);
+        let block = self.block();
+        self.vararg.pop();
+        let block = block?;
+        self.expect(Token::End, "end")?;
+
+        Ok(Arc::new(FuncBody {
+            params,
+            is_vararg,
+            block,
+        }))
+    }
+
+    fn expr_list(&mut self) -> Result<Vec<Expr>, String> {
+        let mut exprs = vec![self.expr()?];
+        while self.check(&Token::Comma) {
+            exprs.push(self.expr()?);
+        }
+        Ok(exprs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.binary_expr(0)
+    }
+
+    /// Parses an expression whose binary operators have a priority greater
+    /// than `limit`.
+    fn binary_expr(&mut self, limit: u8) -> Result<Expr, String> {
+        self.enter()?;
+        let expr = self.binary_expr_inner(limit);
+        self.levels -= 1;
+        expr
+    }
+
+    fn binary_expr_inner(&mut self, limit: u8) -> Result<Expr, String> {
+        let mut expr = match self.peek() {
+            Token::Not => self.unary_expr(UnOp::Not)?,
+            Token::Minus => self.unary_expr(UnOp::Neg)?,
+            Token::Hash => self.unary_expr(UnOp::Len)?,
+            _ => self.simple_expr()?,
+        };
+
+        while let Some((op, left, right)) = binary_op(self.peek()) {
+            if left <= limit {
+                break;
+            }
+            self.next();
+            let rhs = self.binary_expr(right)?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary_expr(&mut self, op: UnOp) -> Result<Expr, String> {
+        self.next();
+        let operand = self.binary_expr(UNARY_PRIORITY)?;
+        Ok(Expr::Unary(op, Box::new(operand)))
+    }
+
+    fn simple_expr(&mut self) -> Result<Expr, String> {
+        let expr = match self.peek().clone() {
+            Token::Nil => Expr::Nil,
+            Token::True => Expr::Bool(true),
+            Token::False => Expr::Bool(false),
+            Token::Number(n) => Expr::Number(n),
+            Token::Str(s) => Expr::Str(s),
+            Token::Dots => {
+                if !self.vararg.last().copied().unwrap_or(false) {
+                    return Err(self.error("cannot use '...' outside a vararg function"));
+                }
+                Expr::Vararg
+            }
+            Token::Function => {
+                self.next();
+                return Ok(Expr::Function(self.function_body(false)?));
+            }
+            Token::LBrace => return self.table(),
+            _ => return self.suffixed_expr(),
+        };
+
+        self.next();
+        Ok(expr)
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr, String> {
+        match self.peek().clone() {
+            Token::Name(name) => {
+                self.next();
+                Ok(Expr::Name(name))
+            }
+            Token::LParen => {
+                self.next();
+                let expr = self.expr()?;
+                self.expect(Token::RParen, ")")?;
+                Ok(Expr::Paren(Box::new(expr)))
+            }
+            _ => Err(self.error("unexpected symbol")),
+        }
+    }
+
+    fn suffixed_expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary_expr()?;
+
+        loop {
+            match self.peek() {
+                Token::Dot => {
+                    self.next();
+                    let key = Expr::Str(Bytes::from(self.name()?));
+                    expr = Expr::Index(Box::new(expr), Box::new(key));
+                }
+                Token::LBracket => {
+                    self.next();
+                    let key = self.expr()?;
+                    self.expect(Token::RBracket, "]")?;
+                    expr = Expr::Index(Box::new(expr), Box::new(key));
+                }
+                Token::Colon => {
+                    self.next();
+                    let name = Bytes::from(self.name()?);
+                    let args = self.call_args()?;
+                    expr = Expr::Method(Box::new(expr), name, args);
+                }
+                Token::LParen | Token::Str(_) | Token::LBrace => {
+                    let args = self.call_args()?;
+                    expr = Expr::Call(Box::new(expr), args);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn call_args(&mut self) -> Result<Vec<Expr>, String> {
+        match self.peek().clone() {
+            Token::Str(s) => {
+                self.next();
+                Ok(vec![Expr::Str(s)])
+            }
+            Token::LBrace => Ok(vec![self.table()?]),
+            Token::LParen => {
+                self.next();
+                if self.check(&Token::RParen) {
+                    return Ok(vec![]);
+                }
+                let args = self.expr_list()?;
+                self.expect(Token::RParen, ")")?;
+                Ok(args)
+            }
+            _ => Err(self.error("function arguments expected")),
+        }
+    }
+
+    fn table(&mut self) -> Result<Expr, String> {
+        self.expect(Token::LBrace, "{")?;
+        let mut fields = vec![];
+
+        while !self.check(&Token::RBrace) {
+            let field = match self.peek() {
+                Token::LBracket => {
+                    self.next();
+                    let key = self.expr()?;
+                    self.expect(Token::RBracket, "]")?;
+                    self.expect(Token::Assign, "=")?;
+                    Field::Named(key, self.expr()?)
+                }
+                Token::Name(name) if self.tokens[self.pos + 1].0 == Token::Assign => {
+                    let key = Expr::Str(Bytes::from(name.clone()));
+                    self.pos += 2;
+                    Field::Named(key, self.expr()?)
+                }
+                _ => Field::Positional(self.expr()?),
+            };
+            fields.push(field);
+
+            if !self.check(&Token::Comma) && !self.check(&Token::Semicolon) {
+                self.expect(Token::RBrace, "}")?;
+                break;
+            }
+        }
+
+        Ok(Expr::Table(fields))
+    }
+}
+
+/// Returns a binary operator along with its left and right priorities.
+/// Concatenation and exponentiation are right associative.
+fn binary_op(token: &Token) -> Option<(BinOp, u8, u8)> {
+    let op = match token {
+        Token::Or => (BinOp::Or, 1, 1),
+        Token::And => (BinOp::And, 2, 2),
+        Token::Lt => (BinOp::Lt, 3, 3),
+        Token::Gt => (BinOp::Gt, 3, 3),
+        Token::Le => (BinOp::Le, 3, 3),
+        Token::Ge => (BinOp::Ge, 3, 3),
+        Token::Ne => (BinOp::Ne, 3, 3),
+        Token::Eq => (BinOp::Eq, 3, 3),
+        Token::Concat => (BinOp::Concat, 5, 4),
+        Token::Plus => (BinOp::Add, 6, 6),
+        Token::Minus => (BinOp::Sub, 6, 6),
+        Token::Star => (BinOp::Mul, 7, 7),
+        Token::Slash => (BinOp::Div, 7, 7),
+        Token::Percent => (BinOp::Mod, 7, 7),
+        Token::Caret => (BinOp::Pow, 10, 9),
+        _ => return None,
+    };
+    Some(op)
+}
@@ -0,0 +1,380 @@
+//! Values manipulated by scripts.
+
+use super::interp::{Builtin, Closure};
+
+use bytes::Bytes;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A Lua value.
+///
+/// Tables and functions are reference types: cloning the value clones the
+/// reference.
+#[derive(Debug, Clone)]
+pub(super) enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Bytes),
+    Table(Rc<RefCell<Table>>),
+    Function(Function),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Function {
+    Closure(Rc<Closure>),
+    Builtin(Builtin),
+}
+
+/// A Lua table.
+///
+/// Values at integer keys from 1 up to the length of the table are stored in
+/// `array`. Other entries are stored in `entries`, in insertion order, so that
+/// `next` can resume an iteration from any key. Removing an entry only sets
+/// its value to nil, which keeps iterations in progress valid.
+#[derive(Debug, Default)]
+pub(super) struct Table {
+    array: Vec<Value>,
+    entries: Vec<(Value, Value)>,
+    positions: HashMap<Key, usize>,
+}
+
+/// Key of an entry of a table, compared by value for numbers, strings and
+/// booleans and by reference for tables and functions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Bool(bool),
+    Number(u64),
+    Str(Bytes),
+    Ref(usize),
+}
+
+impl Value {
+    pub(super) fn str(value: impl Into<Bytes>) -> Value {
+        Value::Str(value.into())
+    }
+
+    pub(super) fn table(table: Table) -> Value {
+        Value::Table(Rc::new(RefCell::new(table)))
+    }
+
+    /// Returns `false` for `nil` and `false`, `true` for any other value.
+    pub(super) fn truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    pub(super) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Table(_) => "table",
+            Value::Function(_) => "function",
+        }
+    }
+
+    /// Converts the value to a number, as arithmetic operators do. Strings
+    /// holding a number are converted.
+    pub(super) fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Str(s) => std::str::from_utf8(s).ok().and_then(parse_number),
+            _ => None,
+        }
+    }
+
+    /// Converts the value to a string, as the concatenation operator does.
+    /// Numbers are converted.
+    pub(super) fn to_str(&self) -> Option<Bytes> {
+        match self {
+            Value::Str(s) => Some(s.clone()),
+            Value::Number(n) => Some(Bytes::from(format_number(*n))),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if both values are equal, tables and functions being
+    /// compared by reference.
+    pub(super) fn raw_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Table(a), Value::Table(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => a.address() == b.address(),
+            _ => false,
+        }
+    }
+
+    /// Returns the address identifying a table or a function.
+    fn address(&self) -> usize {
+        match self {
+            Value::Table(table) => Rc::as_ptr(table) as *const u8 as usize,
+            Value::Function(function) => function.address(),
+            _ => 0,
+        }
+    }
+
+    fn key(&self) -> Result<Key, String> {
+        match self {
+            Value::Nil => Err("table index is nil".to_string()),
+            Value::Bool(b) => Ok(Key::Bool(*b)),
+            Value::Number(n) if n.is_nan() => Err("table index is NaN".to_string()),
+            // `0.0` and `-0.0` are the same key.
+            Value::Number(n) => Ok(Key::Number((n + 0.0).to_bits())),
+            Value::Str(s) => Ok(Key::Str(s.clone())),
+            Value::Table(_) | Value::Function(_) => Ok(Key::Ref(self.address())),
+        }
+    }
+}
+
+impl Function {
+    fn address(&self) -> usize {
+        match self {
+            Function::Closure(closure) => Rc::as_ptr(closure) as *const u8 as usize,
+            // Builtins are stateless, the discriminant identifies them.
+            Function::Builtin(builtin) => *builtin as usize,
+        }
+    }
+}
+
+impl Table {
+    /// Creates a table holding `values` at keys 1 to `values.len()`.
+    pub(super) fn from_array(values: Vec<Value>) -> Table {
+        let mut table = Table {
+            array: values,
+            ..Table::default()
+        };
+        table.trim();
+        table
+    }
+
+    /// Returns the array index of `key`, if it is stored in `array` or would
+    /// be appended to it.
+    fn array_index(&self, key: &Value) -> Option<usize> {
+        match key {
+            Value::Number(n) if n.fract() == 0.0 && *n >= 1.0 => {
+                let index = *n as usize - 1;
+                if index <= self.array.len() {
+                    Some(index)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn get(&self, key: &Value) -> Value {
+        if let Some(index) = self.array_index(key) {
+            return self.array.get(index).cloned().unwrap_or(Value::Nil);
+        }
+
+        match key.key() {
+            Ok(key) => match self.positions.get(&key) {
+                Some(&position) => self.entries[position].1.clone(),
+                None => Value::Nil,
+            },
+            Err(_) => Value::Nil,
+        }
+    }
+
+    pub(super) fn get_str(&self, key: &str) -> Value {
+        self.get(&Value::str(key.to_string()))
+    }
+
+    pub(super) fn set(&mut self, key: Value, value: Value) -> Result<(), String> {
+        if let Some(index) = self.array_index(&key) {
+            if index < self.array.len() {
+                self.array[index] = value;
+                self.trim();
+                return Ok(());
+            }
+
+            if !matches!(value, Value::Nil) {
+                self.array.push(value);
+                self.migrate();
+                return Ok(());
+            }
+        }
+
+        let hashed = key.key()?;
+        match self.positions.get(&hashed) {
+            Some(&position) => self.entries[position].1 = value,
+            None if matches!(value, Value::Nil) => {}
+            None => {
+                self.positions.insert(hashed, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn set_str(&mut self, key: &str, value: Value) {
+        self.set(Value::str(key.to_string()), value).unwrap();
+    }
+
+    /// Appends `value` at the end of the array part.
+    pub(super) fn push(&mut self, value: Value) {
+        let key = Value::Number((self.array.len() + 1) as f64);
+        // The key is never nil.
+        self.set(key, value).unwrap();
+    }
+
+    /// Returns the length of the table, as the `#` operator.
+    pub(super) fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    /// Returns the values at keys 1 up to the length of the table.
+    pub(super) fn array(&self) -> &[Value] {
+        &self.array
+    }
+
+    /// Inserts `value` at `index`, shifting up the following values.
+    pub(super) fn insert(&mut self, index: usize, value: Value) {
+        self.array.insert(index, value);
+        self.trim();
+        self.migrate();
+    }
+
+    /// Removes the value at `index`, shifting down the following values.
+    pub(super) fn remove(&mut self, index: usize) -> Value {
+        let value = self.array.remove(index);
+        self.trim();
+        value
+    }
+
+    /// Returns the entry following `key` in the iteration order, `None` once
+    /// all the entries were returned.
+    pub(super) fn next(&self, key: &Value) -> Result<Option<(Value, Value)>, String> {
+        let mut start = match key {
+            Value::Nil => 0,
+            _ => match self.array_index(key) {
+                Some(index) if index < self.array.len() => index + 1,
+                // The key was at the end of the array part, which shrank
+                // when its value was set to nil.
+                Some(_) => self.array.len(),
+                None => {
+                    let position = key
+                        .key()
+                        .ok()
+                        .and_then(|key| self.positions.get(&key).copied())
+                        .ok_or_else(|| "invalid key to 'next'".to_string())?;
+                    self.array.len() + position + 1
+                }
+            },
+        };
+
+        while start < self.array.len() {
+            if !matches!(self.array[start], Value::Nil) {
+                let key = Value::Number((start + 1) as f64);
+                return Ok(Some((key, self.array[start].clone())));
+            }
+            start += 1;
+        }
+
+        Ok(self.entries[start - self.array.len()..]
+            .iter()
+            .find(|(_, value)| !matches!(value, Value::Nil))
+            .cloned())
+    }
+
+    /// Removes the nil values at the end of the array part, so that its
+    /// length is a border of the table.
+    fn trim(&mut self) {
+        while matches!(self.array.last(), Some(Value::Nil)) {
+            self.array.pop();
+        }
+    }
+
+    /// Moves the entries following the array part into it.
+    fn migrate(&mut self) {
+        loop {
+            let key = Value::Number((self.array.len() + 1) as f64);
+            let position = match self.positions.remove(&key.key().unwrap()) {
+                Some(position) => position,
+                None => return,
+            };
+
+            let value = std::mem::replace(&mut self.entries[position].1, Value::Nil);
+            if matches!(value, Value::Nil) {
+                return;
+            }
+            self.array.push(value);
+        }
+    }
+}
+
+/// Parses a number as Lua does, accepting decimal and hexadecimal notations.
+pub(super) fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        let n = u64::from_str_radix(hex, 16).ok()? as f64;
+        return Some(if negative { -n } else { n });
+    }
+
+    // Rust accepts spellings Lua rejects, such as "inf" or "nan".
+    if !text
+        .bytes()
+        .all(|c| c.is_ascii_digit() || matches!(c, b'.' | b'e' | b'E' | b'+' | b'-'))
+    {
+        return None;
+    }
+
+    text.parse().ok()
+}
+
+/// Formats a number as Lua does, with the `%.14g` format.
+pub(super) fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        return format!("{}", n as i64);
+    }
+
+    format_g(n, 14)
+}
+
+/// Formats a number with the `%g` format of C, with `precision` significant
+/// digits.
+pub(super) fn format_g(n: f64, precision: usize) -> String {
+    if n.is_nan() {
+        return "nan".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    let scientific = format!("{:.*e}", precision - 1, n);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    if exponent < -4 || exponent >= precision as i32 {
+        let mantissa = trim_zeros(mantissa);
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    } else {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        trim_zeros(&format!("{:.*}", decimals, n)).to_string()
+    }
+}
+
+/// Removes the trailing zeros of the decimal part of a number.
+fn trim_zeros(n: &str) -> &str {
+    if n.contains('.') {
+        n.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        n
+    }
+}
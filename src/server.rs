@@ -3,14 +3,41 @@
 //! Provides an async `run` function that listens for inbound connections,
 //! spawning a task per connection.
 
-use crate::{Command, Connection, Db, Shutdown};
+use crate::cmd::{Client, ClientReply, Renames, Reset, Watch};
+use crate::connection::ProtocolError;
+use crate::db::{self, DbError, Watched};
+use crate::persistence::{aof_task, load_aof, load_snapshot, snapshot_task, Aof};
+use crate::replication::replica_task;
+#[cfg(feature = "tls")]
+use crate::tls::Acceptor;
+use crate::{Command, Connection, Db, Frame, Shutdown, DEFAULT_DATABASES};
 
-use std::future::Future;
+use bytes::Bytes;
+use libc::c_int;
+use std::future::{self, Future};
+use std::io;
+use std::mem;
+use std::net::{IpAddr, SocketAddr};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, instrument};
+use std::task::Poll;
+use tokio::net::{TcpListener, TcpStream, UnixListener};
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, error, info, instrument, warn};
+
+pub use crate::persistence::Fsync;
+#[cfg(feature = "tls")]
+pub use crate::tls::{ClientAuth, TlsConfig};
+
+/// Error replied to the clients rejected in protected mode.
+const PROTECTED_MODE_ERROR: &str = "DENIED Redis is running in protected mode because protected \
+    mode is enabled and no password is set for the default user. In this mode connections are \
+    only accepted from the loopback interface. If you want to connect from external computers, \
+    you may either disable protected mode by sending 'CONFIG SET protected-mode no' from the \
+    loopback interface, set a password for the default user by sending \
+    'ACL SETUSER default >password', or bind the server to a loopback address only.";
 
 /// Server listener state. Created in the `run` call. It includes a `run` method
 /// which performs the TCP listening and initialization of per-connection state.
@@ -25,18 +52,18 @@ struct Listener {
     /// passed into the per connection state (`Handler`).
     db: Db,
 
-    /// TCP listener supplied by the `run` caller.
-    listener: TcpListener,
+    /// TCP listeners supplied by the `run` caller, bound to IPv4 or IPv6
+    /// addresses. Connections are accepted from all of them.
+    listeners: Vec<TcpListener>,
 
-    /// Limit the max number of connections.
-    ///
-    /// A `Semaphore` is used to limit the max number of connections. Before
-    /// attempting to accept a new connection, a permit is acquired from the
-    /// semaphore. If none are available, the listener waits for one.
-    ///
-    /// When handlers complete processing a connection, the permit is returned
-    /// to the semaphore.
-    limit_connections: Arc<Semaphore>,
+    /// Performs the TLS handshake with the accepted clients, `None` if the
+    /// connections are not encrypted.
+    #[cfg(feature = "tls")]
+    tls: Option<Acceptor>,
+
+    /// Listener of the Unix domain socket, if the server listens on one as
+    /// well.
+    unix_listener: Option<UnixListener>,
 
     /// Broadcasts a shutdown signal to all active connections.
     ///
@@ -68,11 +95,14 @@ struct Listener {
 /// commands to `db`.
 #[derive(Debug)]
 struct Handler {
-    /// Shared database handle.
+    /// Shared database handle, through which the connection accesses the
+    /// database it selected.
     ///
     /// When a command is received from `connection`, it is applied with `db`.
     /// The implementation of the command is in the `cmd` module. Each command
-    /// will need to interact with `db` in order to complete the work.
+    /// will need to interact with `db` in order to complete the work. `SELECT`
+    /// replaces the handle with one selecting another database, which only
+    /// affects this connection.
     db: Db,
 
     /// The TCP connection decorated with the redis protocol encoder / decoder
@@ -84,13 +114,6 @@ struct Handler {
     /// the byte level protocol parsing details encapsulated in `Connection`.
     connection: Connection,
 
-    /// Max connection semaphore.
-    ///
-    /// When the handler is dropped, a permit is returned to this semaphore. If
-    /// the listener is waiting for connections to close, it will be notified of
-    /// the newly available permit and resume accepting connections.
-    limit_connections: Arc<Semaphore>,
-
     /// Listen for shutdown notifications.
     ///
     /// A wrapper around the `broadcast::Receiver` paired with the sender in
@@ -101,34 +124,244 @@ struct Handler {
     /// which point the connection is terminated.
     shutdown: Shutdown,
 
+    /// Commands queued since `MULTI`, or `None` when the connection is not in
+    /// a transaction.
+    ///
+    /// While in a transaction, commands are queued instead of being applied.
+    /// `EXEC` applies them all at once and `DISCARD` drops them. Each command
+    /// is queued along with the frame it was received as.
+    transaction: Option<Vec<(Command, Frame)>>,
+
+    /// Set when a command of the transaction could not be queued, in which
+    /// case `EXEC` discards the transaction instead of executing it.
+    transaction_aborted: bool,
+
+    /// Keys watched with `WATCH`. `EXEC` does not execute the transaction if
+    /// any of them was modified.
+    watched: Vec<Watched>,
+
+    /// Address of the client, as shown by `MONITOR`.
+    addr: String,
+
+    /// Whether the commands are replied to, as set by `CLIENT REPLY`.
+    reply: ClientReply,
+
+    /// Notified when the client is killed with `CLIENT KILL`, in which case
+    /// the connection is closed.
+    killed: Arc<Notify>,
+
+    /// Set by `ASKING`, in cluster mode, so that the next command is served
+    /// if its keys belong to a slot being imported.
+    asking: bool,
+
+    /// Set when tracking is enabled with `CLIENT TRACKING`, in which case the
+    /// keys read by the connection are recorded so that it is notified of
+    /// their modification.
+    tracking: bool,
+
+    /// Receives the keys invalidated for the connection, when tracking is
+    /// enabled without `REDIRECT`. They are pushed to the client as soon as
+    /// they are received.
+    invalidations: Option<broadcast::Receiver<Bytes>>,
+
     /// Not used directly. Instead, when `Handler` is dropped...?
     _shutdown_complete: mpsc::Sender<()>,
 }
 
-/// Maximum number of concurrent connections the redis server will accept.
-///
-/// When this limit is reached, the server will stop accepting connections until
-/// an active connection terminates.
-///
-/// A real application will want to make this value configurable, but for this
-/// example, it is hard coded.
-///
-/// This is also set to a pretty low value to discourage using this in
-/// production (you'd think that all the disclaimers would make it obvious that
-/// this is not a serious project... but I thought that about mini-http as
-/// well).
-const MAX_CONNECTIONS: usize = 250;
+/// Configuration of the server, see `run_with_config`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Number of databases, numbered from `0`.
+    pub databases: usize,
+
+    /// File the snapshots of the databases are saved to, and loaded from when
+    /// the server starts. `None` disables snapshots.
+    pub snapshot_path: Option<PathBuf>,
+
+    /// Save points, as pairs of a duration and a number of changes, as set
+    /// by the `save` directive of `redis.conf`. A snapshot is saved once the
+    /// duration of one of them elapsed since the last snapshot, if the
+    /// databases were modified at least its number of times meanwhile.
+    /// Without save points, a snapshot is only saved when the server shuts
+    /// down.
+    pub save_points: Vec<(Duration, u64)>,
+
+    /// File the commands modifying the databases are appended to, and
+    /// replayed from when the server starts. `None` disables the AOF.
+    ///
+    /// When the AOF is enabled, the databases are loaded from it instead of
+    /// from the last snapshot.
+    pub aof_path: Option<PathBuf>,
+
+    /// When the AOF is flushed to disk.
+    pub aof_fsync: Fsync,
+
+    /// Address of the primary the server replicates, as `host:port`. `None`
+    /// if the server is not a replica.
+    ///
+    /// A replica replaces the content of its databases with the content of
+    /// the databases of its primary, then applies the commands modifying
+    /// them as the primary streams them. `REPLICAOF` changes the primary
+    /// while the server runs.
+    pub replica_of: Option<String>,
+
+    /// Whether the server runs in cluster mode.
+    ///
+    /// In cluster mode, the server only serves the keys of the hash slots
+    /// assigned to it, and redirects the clients to the other nodes of the
+    /// cluster for the other keys. Only the first database is used.
+    pub cluster_enabled: bool,
+
+    /// Password clients must authenticate with using `AUTH` before sending
+    /// other commands. `None` lets clients send commands without
+    /// authenticating.
+    pub requirepass: Option<String>,
+
+    /// Certificates and key the connections with clients are encrypted with.
+    /// `None` lets clients connect without TLS.
+    #[cfg(feature = "tls")]
+    pub tls: Option<TlsConfig>,
+
+    /// Path of a Unix domain socket the server accepts connections on, in
+    /// addition to the TCP listener. An existing file at this path is
+    /// replaced, and the socket is removed when the server shuts down.
+    pub unixsocket: Option<PathBuf>,
+
+    /// Commands renamed, as pairs of the name of a command and its new name.
+    /// A renamed command is only known by its new name, and a command renamed
+    /// to the empty string is disabled, so that the clients may not run
+    /// dangerous commands such as `FLUSHALL` unless they know their new name.
+    pub rename_commands: Vec<(String, String)>,
+
+    /// Whether the server runs in protected mode, as set by the
+    /// `protected-mode` parameter.
+    ///
+    /// In protected mode, while clients may authenticate as the default user
+    /// without a password, a listener bound to an address other than a
+    /// loopback address only accepts the clients connected from the loopback
+    /// interface. The other clients are replied with an error explaining
+    /// how to reach the server, then disconnected.
+    pub protected_mode: bool,
+
+    /// Seconds a client connected over TCP may stay idle before TCP
+    /// keepalive probes are sent to check it is still alive, as set by the
+    /// `tcp-keepalive` parameter. Clients which do not reply to them are
+    /// disconnected. `0` sends no probes.
+    pub tcp_keepalive: u64,
+
+    /// Whether `TCP_NODELAY` is set on the sockets of the clients connected
+    /// over TCP, as set by the `tcp-nodelay` parameter, so that the replies
+    /// are sent right away rather than coalesced.
+    pub tcp_nodelay: bool,
+
+    /// Seconds after which idle clients are disconnected, as set by the
+    /// `timeout` parameter. `0` never disconnects them.
+    pub timeout: u64,
+
+    /// Values of the other parameters of `CONFIG SET`, such as `maxmemory`
+    /// or `notify-keyspace-events`, as pairs of the name of a parameter and
+    /// its value. They are set in order when the server starts, see
+    /// `check_parameter`.
+    pub parameters: Vec<(String, String)>,
+}
 
 /// Run the mini-redis server.
 ///
-/// Accepts connections from the supplied listener. For each inbound connection,
-/// a task is spawned to handle that connection. The server runs until the
-/// `shutdown` future completes, at which point the server shuts down
-/// gracefully.
+/// Accepts connections from the supplied listeners, see `bind` to bind them to
+/// a list of addresses. For each inbound connection, a task is spawned to
+/// handle that connection. The server runs until the
+/// `shutdown` future completes, or until a client sends `SHUTDOWN`, at which
+/// point the server shuts down gracefully.
 ///
 /// `tokio::signal::ctrl_c()` can be used as the `shutdown` argument. This will
 /// listen for a SIGINT signal.
-pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<()> {
+///
+/// The server has `DEFAULT_DATABASES` databases, see `run_with_databases` to
+/// configure their number.
+pub async fn run(listeners: Vec<TcpListener>, shutdown: impl Future) -> crate::Result<()> {
+    run_with_databases(listeners, DEFAULT_DATABASES, shutdown).await
+}
+
+/// Returns `Err` with the reason the parameter `name` of `CONFIG SET` may not
+/// be set to `value` by `Config::parameters`: there is no such parameter, it
+/// cannot be modified, or `value` is invalid.
+pub fn check_parameter(name: &str, value: &str) -> Result<(), &'static str> {
+    db::Config::check(name, value)
+}
+
+/// Bind a TCP listener to each of `hosts` at `port`, to be passed to `run`.
+///
+/// The hosts are IPv4 or IPv6 addresses, which may be enclosed in brackets as
+/// in `[::1]`, or host names. Returns `Err` if one of them cannot be bound.
+///
+/// As in `redis.conf`, hosts prefixed with `-`, as in `-::1`, are optional:
+/// they are skipped if the address is not available on the machine.
+pub async fn bind(hosts: &[String], port: u16) -> crate::Result<Vec<TcpListener>> {
+    let mut listeners = Vec::with_capacity(hosts.len());
+    for host in hosts {
+        let (host, optional) = match host.strip_prefix('-') {
+            Some(host) => (host, true),
+            None => (&host[..], false),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        match TcpListener::bind((host, port)).await {
+            Ok(listener) => listeners.push(listener),
+            Err(err) if optional && is_unavailable(&err) => {
+                warn!(host, cause = ?err, "skipping unavailable optional address");
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(listeners)
+}
+
+/// Returns `true` if `err` reports an address missing from the machine, or of
+/// a family it does not support.
+fn is_unavailable(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::AddrNotAvailable
+        || matches!(
+            err.raw_os_error(),
+            Some(libc::EAFNOSUPPORT) | Some(libc::EPROTONOSUPPORT)
+        )
+}
+
+/// Run the mini-redis server with `databases` databases, numbered from `0`.
+///
+/// Behaves like `run` otherwise.
+pub async fn run_with_databases(
+    listeners: Vec<TcpListener>,
+    databases: usize,
+    shutdown: impl Future,
+) -> crate::Result<()> {
+    let config = Config {
+        databases,
+        ..Config::default()
+    };
+    run_with_config(listeners, config, shutdown).await
+}
+
+/// Run the mini-redis server configured by `config`.
+///
+/// If the AOF is enabled, the databases are loaded by replaying it before
+/// accepting connections. Otherwise, if snapshots are enabled, they are loaded
+/// from the last snapshot. Returns `Err` if the AOF or the snapshot cannot be
+/// loaded, if the TLS certificates or key cannot be read, if a parameter of
+/// `Config::parameters` cannot be set, or if there is no listener.
+///
+/// Behaves like `run` otherwise.
+pub async fn run_with_config(
+    listeners: Vec<TcpListener>,
+    config: Config,
+    shutdown: impl Future,
+) -> crate::Result<()> {
+    // The server is reached by the other nodes and the primary at the
+    // address of its first listener.
+    let addr = match listeners.first() {
+        Some(listener) => listener.local_addr()?,
+        None => return Err("no address to listen on".into()),
+    };
+
     // When the provided `shutdown` future completes, we must send a shutdown
     // message to all active connections. We use a broadcast channel for this
     // purpose. The call below ignores the receiver of the broadcast pair, and when
@@ -137,11 +370,98 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, shutdown_complete_rx) = mpsc::channel(1);
 
+    // The certificates are read before anything else, so that the server does
+    // not start without TLS if they are invalid.
+    #[cfg(feature = "tls")]
+    let tls = match &config.tls {
+        Some(tls) => Some(tls.acceptor()?),
+        None => None,
+    };
+
+    let db = Db::new(config.databases);
+    db.update_config(|params| {
+        params.protected_mode = config.protected_mode;
+        params.tcp_keepalive = config.tcp_keepalive;
+        params.tcp_nodelay = config.tcp_nodelay;
+        params.timeout = config.timeout;
+    });
+    for (name, value) in &config.parameters {
+        db.config_set(name, value)?;
+    }
+
+    if let Some(path) = &config.aof_path {
+        if load_aof(&db, path).await? {
+            info!(path = %path.display(), "AOF loaded");
+        }
+    } else if let Some(path) = &config.snapshot_path {
+        if load_snapshot(&db, path)? {
+            info!(path = %path.display(), "snapshot loaded");
+        }
+    }
+
+    if let Some(path) = config.snapshot_path {
+        db.set_snapshot_path(path.clone());
+
+        // The task saving the snapshots saves a last one when the server
+        // shuts down, the server waits for it to complete.
+        tokio::spawn(snapshot_task(
+            db.clone(),
+            path,
+            config.save_points,
+            Shutdown::new(notify_shutdown.subscribe()),
+            shutdown_complete_tx.clone(),
+        ));
+    }
+
+    if let Some(path) = config.aof_path {
+        let aof = Aof::open(&path, config.aof_fsync)?;
+
+        // As for snapshots, the server waits for the task to flush the AOF a
+        // last time when it shuts down.
+        if aof.fsync() == Fsync::EverySec {
+            tokio::spawn(aof_task(
+                db.clone(),
+                Shutdown::new(notify_shutdown.subscribe()),
+                shutdown_complete_tx.clone(),
+            ));
+        }
+
+        db.set_aof(aof);
+    }
+
+    // Other nodes and clients reach the node at the address it listens on.
+    if config.cluster_enabled {
+        db.enable_cluster(addr.ip().to_string(), addr.port());
+    }
+
+    db.set_requirepass(config.requirepass);
+    db.set_renames(Renames::new(&config.rename_commands)?);
+
+    // A socket left behind by a previous run would prevent binding.
+    let unix_listener = match &config.unixsocket {
+        Some(path) => {
+            let _ = std::fs::remove_file(path);
+            Some(UnixListener::bind(path)?)
+        }
+        None => None,
+    };
+
+    // The server becomes a replica when `REPLICAOF` is received, if it is not
+    // one already.
+    db.set_primary(config.replica_of);
+    tokio::spawn(replica_task(
+        db.clone(),
+        addr.port(),
+        Shutdown::new(notify_shutdown.subscribe()),
+    ));
+
     // Initialize the listener state
     let mut server = Listener {
-        listener,
-        db: Db::new(),
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        listeners,
+        #[cfg(feature = "tls")]
+        tls,
+        unix_listener,
+        db,
         notify_shutdown,
         shutdown_complete_tx,
         shutdown_complete_rx,
@@ -166,6 +486,9 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<
     // asynchronous Rust. See the API docs for more details:
     //
     // https://docs.rs/tokio/*/tokio/macro.select.html
+    //
+    // Clients may also request the server to shut down with `SHUTDOWN`.
+    let db = server.db.clone();
     tokio::select! {
         res = server.run() => {
             // If an error is received here, accepting connections from the TCP
@@ -182,6 +505,10 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<
             // The shutdown signal has been received.
             info!("shutting down");
         }
+        _ = db.shutdown_requested() => {
+            // A client sent `SHUTDOWN`.
+            info!("shutting down on request");
+        }
     }
 
     // Extract the `shutdown_complete` receiver and transmitter
@@ -205,9 +532,37 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<
     // the `mpsc` channel will close and `recv()` will return `None`.
     let _ = shutdown_complete_rx.recv().await;
 
+    if let Some(path) = config.unixsocket {
+        let _ = std::fs::remove_file(path);
+    }
+
     Ok(())
 }
 
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            databases: DEFAULT_DATABASES,
+            snapshot_path: None,
+            save_points: vec![],
+            aof_path: None,
+            aof_fsync: Fsync::default(),
+            replica_of: None,
+            cluster_enabled: false,
+            requirepass: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+            unixsocket: None,
+            rename_commands: vec![],
+            protected_mode: true,
+            tcp_keepalive: 300,
+            tcp_nodelay: true,
+            timeout: 0,
+            parameters: vec![],
+        }
+    }
+}
+
 impl Listener {
     /// Run the server
     ///
@@ -228,49 +583,77 @@ impl Listener {
         info!("accepting inbound connections");
 
         loop {
-            // Wait for a permit to become available
-            //
-            // `acquire` returns a permit that is bound via a lifetime to the
-            // semaphore. When the permit value is dropped, it is automatically
-            // returned to the semaphore. This is convenient in many cases.
-            // However, in this case, the permit must be returned in a different
-            // task than it is acquired in (the handler task). To do this, we
-            // "forget" the permit, which drops the permit value **without**
-            // incrementing the semaphore's permits. Then, in the handler task
-            // we manually add a new permit when processing completes.
-            self.limit_connections.acquire().await.forget();
-
             // Accept a new socket. This will attempt to perform error handling.
             // The `accept` method internally attempts to recover errors, so an
             // error here is non-recoverable.
-            let socket = self.accept().await?;
+            let (connection, addr, over_tcp) = self.accept().await?;
+
+            // In protected mode, only local clients are accepted. The clients
+            // of a listener bound to a loopback address are local anyway.
+            if !is_local(&addr) && self.db.is_protected() {
+                debug!(%addr, "rejecting connection in protected mode");
+                self.reject(connection, over_tcp, PROTECTED_MODE_ERROR);
+                continue;
+            }
+
+            // Once the `maxclients` parameter is reached, connections are
+            // either queued, in which case the listener stops accepting them
+            // until another client disconnects, or rejected.
+            if !self.db.admit_client().await {
+                debug!(%addr, "rejecting connection over maxclients");
+                self.reject(connection, over_tcp, "ERR max number of clients reached");
+                continue;
+            }
+
+            // Get a handle to the shared database. Internally, this is an
+            // `Arc`, so a clone only increments the ref count.
+            let db = self.db.connect(addr.clone());
+            let killed = db.client_killed();
 
             // Create the necessary per-connection handler state.
             let mut handler = Handler {
-                // Get a handle to the shared database. Internally, this is an
-                // `Arc`, so a clone only increments the ref count.
-                db: self.db.clone(),
-
-                // Initialize the connection state. This allocates read/write
-                // buffers to perform redis protocol frame parsing.
-                connection: Connection::new(socket),
+                db,
 
-                // The connection state needs a handle to the max connections
-                // semaphore. When the handler is done processing the
-                // connection, a permit is added back to the semaphore.
-                limit_connections: self.limit_connections.clone(),
+                // The connection state, initialized by `accept`.
+                connection,
+                addr,
+                killed,
+                reply: ClientReply::On,
 
                 // Receive shutdown notifications.
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
 
+                // Connections start outside of any transaction.
+                transaction: None,
+                transaction_aborted: false,
+                watched: Vec::new(),
+
+                asking: false,
+                tracking: false,
+                invalidations: None,
+
                 // Notifies the receiver half once all clones are
                 // dropped.
                 _shutdown_complete: self.shutdown_complete_tx.clone(),
             };
 
+            // Connections over the Unix domain socket are not encrypted.
+            #[cfg(feature = "tls")]
+            let tls = self.tls.clone().filter(|_| over_tcp);
+
             // Spawn a new task to process the connections. Tokio tasks are like
             // asynchronous green threads and are executed concurrently.
             tokio::spawn(async move {
+                // The TLS handshake is performed by the task of the connection,
+                // so that a slow client does not hold up accepting others.
+                #[cfg(feature = "tls")]
+                if let Some(tls) = tls {
+                    if let Err(err) = handler.connection.accept_tls(&tls).await {
+                        error!(cause = ?err, "TLS handshake failed");
+                        return;
+                    }
+                }
+
                 // Process the connection. If an error is encountered, log it.
                 if let Err(err) = handler.run().await {
                     error!(cause = ?err, "connection error");
@@ -279,22 +662,81 @@ impl Listener {
         }
     }
 
-    /// Accept an inbound connection.
+    /// Reply `error` to a client which is not accepted, then close the
+    /// connection. `over_tcp` is whether the client connected to one of the
+    /// TCP listeners, in which case it is replied to over TLS if enabled.
+    ///
+    /// The client is not waited for, while it reads the error.
+    #[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+    fn reject(&self, mut connection: Connection, over_tcp: bool, error: &str) {
+        #[cfg(feature = "tls")]
+        let tls = self.tls.clone().filter(|_| over_tcp);
+        let response = Frame::Error(error.to_string());
+
+        tokio::spawn(async move {
+            #[cfg(feature = "tls")]
+            if let Some(tls) = tls {
+                if connection.accept_tls(&tls).await.is_err() {
+                    return;
+                }
+            }
+
+            let _ = connection.write_frame(&response).await;
+        });
+    }
+
+    /// Accept an inbound connection, from one of the TCP listeners or the Unix
+    /// domain socket, and initialize its state. This allocates read/write buffers to
+    /// perform redis protocol frame parsing. The connection is returned along
+    /// with the address of the client, and whether it was accepted from one of
+    /// the TCP listeners.
     ///
     /// Errors are handled by backing off and retrying. An exponential backoff
     /// strategy is used. After the first failure, the task waits for 1 second.
     /// After the second failure, the task waits for 2 seconds. Each subsequent
     /// failure doubles the wait time. If accepting fails on the 6th try after
     /// waiting for 64 seconds, then this function returns with an error.
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
+    async fn accept(&mut self) -> crate::Result<(Connection, String, bool)> {
         let mut backoff = 1;
 
         // Try to accept a few times
         loop {
-            // Perform the accept operation. If a socket is successfully
-            // accepted, return it. Otherwise, save the error.
-            match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+            // The sockets accepted over TCP are tuned as set by the
+            // parameters when they are accepted.
+            let config = self.db.config();
+
+            // Perform the accept operation on all the listeners at once. If a
+            // socket is successfully accepted, return it. Otherwise, save the
+            // error.
+            let res = future::poll_fn(|cx| {
+                for listener in &self.listeners {
+                    if let Poll::Ready(res) = listener.poll_accept(cx) {
+                        return Poll::Ready(res.map(|(socket, addr)| {
+                            if let Err(err) =
+                                tune_socket(&socket, config.tcp_keepalive, config.tcp_nodelay)
+                            {
+                                warn!(%addr, cause = %err, "failed to set socket options");
+                            }
+                            (Connection::new(socket), addr.to_string(), true)
+                        }));
+                    }
+                }
+
+                if let Some(unix_listener) = &self.unix_listener {
+                    if let Poll::Ready(res) = unix_listener.poll_accept(cx) {
+                        let addr = unix_addr(unix_listener);
+                        return Poll::Ready(
+                            res.map(|(socket, _)| (Connection::from_stream(socket), addr, false)),
+                        );
+                    }
+                }
+
+                Poll::Pending
+            })
+            .await;
+
+            match res {
+                Ok(accepted) => return Ok(accepted),
                 Err(err) => {
                     if backoff > 64 {
                         // Accept has failed too many times. Return the error.
@@ -312,6 +754,114 @@ impl Listener {
     }
 }
 
+/// Returns `true` if the client at `addr` connected from the loopback
+/// interface, or to the Unix domain socket.
+fn is_local(addr: &str) -> bool {
+    match addr.parse::<SocketAddr>() {
+        Ok(addr) => match addr.ip() {
+            IpAddr::V4(ip) => ip.is_loopback(),
+            IpAddr::V6(ip) => {
+                ip.is_loopback() || ip.to_ipv4_mapped().is_some_and(|ip| ip.is_loopback())
+            }
+        },
+        Err(_) => true,
+    }
+}
+
+/// Set the options of the socket of a client connected over TCP: TCP
+/// keepalive probes are sent once it is idle for `keepalive` seconds, unless
+/// `0`, and `TCP_NODELAY` is set if `nodelay` is.
+fn tune_socket(socket: &TcpStream, keepalive: u64, nodelay: bool) -> io::Result<()> {
+    socket.set_nodelay(nodelay)?;
+
+    let fd = socket.as_raw_fd();
+    setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_KEEPALIVE,
+        (keepalive > 0) as c_int,
+    )?;
+    if keepalive > 0 {
+        set_keepalive_interval(fd, keepalive.min(c_int::MAX as u64) as c_int)?;
+    }
+    Ok(())
+}
+
+/// Send the keepalive probes once the connection is idle for `interval`
+/// seconds. As in Redis, the probes are then sent every third of the
+/// interval, the peer being considered dead after 3 probes without reply.
+#[cfg(target_os = "linux")]
+fn set_keepalive_interval(fd: RawFd, interval: c_int) -> io::Result<()> {
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, interval)?;
+    setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_KEEPINTVL,
+        (interval / 3).max(1),
+    )?;
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_KEEPCNT, 3)
+}
+
+/// The interval of the keepalive probes is left to the system elsewhere.
+#[cfg(not(target_os = "linux"))]
+fn set_keepalive_interval(_fd: RawFd, _interval: c_int) -> io::Result<()> {
+    Ok(())
+}
+
+/// Set the option `name` of the socket `fd` at `level` to `value`.
+fn setsockopt(fd: RawFd, level: c_int, name: c_int, value: c_int) -> io::Result<()> {
+    // SAFETY: `fd` is an open socket, and `value` outlives the call.
+    let res = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const c_int as *const libc::c_void,
+            mem::size_of::<c_int>() as libc::socklen_t,
+        )
+    };
+
+    if res == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns the address of the clients connected to the Unix domain socket of
+/// `listener`, made of the path of the socket.
+fn unix_addr(listener: &UnixListener) -> String {
+    match listener.local_addr() {
+        Ok(addr) => format!(
+            "unix:{}",
+            addr.as_pathname().unwrap_or(Path::new("")).display()
+        ),
+        Err(_) => "unix".to_string(),
+    }
+}
+
+/// Returns `true` if `cmd` may modify the data set, which scripts may do, in
+/// which case it is held by `CLIENT PAUSE WRITE`.
+fn may_write(cmd: &Command) -> bool {
+    cmd.is_write()
+        || matches!(
+            cmd,
+            Command::Eval(_) | Command::EvalSha(_) | Command::FCall(_)
+        )
+}
+
+/// Returns the error replied to a command which could not be parsed. Errors
+/// not starting with an error code, as `ERR`, are given the `ERR` code.
+fn parse_error(err: &crate::Error) -> Frame {
+    let message = err.to_string();
+    let code = message.split(' ').next().unwrap_or("");
+
+    if !code.is_empty() && code.bytes().all(|byte| byte.is_ascii_uppercase()) {
+        Frame::Error(message)
+    } else {
+        Frame::Error(format!("ERR {}", message))
+    }
+}
+
 impl Handler {
     /// Process a single connection.
     ///
@@ -330,10 +880,59 @@ impl Handler {
         // As long as the shutdown signal has not been received, try to read a
         // new request frame.
         while !self.shutdown.is_shutdown() {
+            // Clients idle for longer than the `timeout` parameter are
+            // disconnected.
+            let timeout = self.db.config().timeout;
+            let idle = async {
+                match timeout {
+                    0 => std::future::pending().await,
+                    timeout => time::sleep(Duration::from_secs(timeout)).await,
+                }
+            };
+
+            // Keys invalidated for the connection are pushed in between the
+            // replies.
+            let invalidations = &mut self.invalidations;
+            let invalidated = async {
+                match invalidations {
+                    Some(invalidations) => invalidations.recv().await,
+                    None => future::pending().await,
+                }
+            };
+
             // While reading a request frame, also listen for the shutdown
             // signal.
+            let bytes_read = self.connection.bytes_read();
             let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => res?,
+                res = self.connection.read_frame() => match res {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        // Malformed inline commands are replied to before
+                        // the connection is closed.
+                        if let Some(err) = err.downcast_ref::<ProtocolError>() {
+                            let response = Frame::Error(err.to_string());
+                            debug!(?response);
+                            self.connection.write_frame(&response).await?;
+                        }
+                        return Err(err);
+                    }
+                },
+                res = invalidated => {
+                    match res {
+                        Ok(key) => self.push_invalidation(key).await?,
+                        Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => self.invalidations = None,
+                    }
+                    continue;
+                }
+                _ = idle => {
+                    debug!("closing idle connection");
+                    return Ok(());
+                }
+                _ = self.killed.notified() => {
+                    debug!("closing killed connection");
+                    return Ok(());
+                }
                 _ = self.shutdown.recv() => {
                     // If a shutdown signal is received, return from `run`.
                     // This will result in the task terminating.
@@ -349,10 +948,95 @@ impl Handler {
                 None => return Ok(()),
             };
 
+            // The frame of the command is kept as it was received, under the
+            // original name of the command if it was renamed. It is streamed
+            // to the monitors and recorded by the slow log, and logged, that
+            // is appended to the AOF and streamed to the replicas, if the
+            // command modifies the databases.
+            let renames = self.db.renames();
+            let mut received = frame.clone();
+            renames.restore_name(&mut received);
+
             // Convert the redis frame into a command struct. This returns an
-            // error if the frame is not a valid redis command or it is an
-            // unsupported command.
-            let cmd = Command::from_frame(frame)?;
+            // error if the frame is not a valid redis command, which is
+            // replied to without closing the connection. A transaction is
+            // aborted if one of its commands is invalid.
+            let cmd = match Command::from_renamed_frame(frame, &renames) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    if self.transaction.is_some() {
+                        self.transaction_aborted = true;
+                    }
+
+                    let response = parse_error(&err);
+                    debug!(?response);
+                    self.connection.write_frame(&response).await?;
+                    continue;
+                }
+            };
+
+            // While a script runs, the commands of the other clients wait
+            // for it to end. Once it runs for longer than `lua-time-limit`,
+            // they are rejected with a `BUSY` error, except for those killing
+            // the script or shutting down the server.
+            if let Err(busy) = self.db.wait_script().await {
+                let response = {
+                    let kill = |function| {
+                        let killed = self
+                            .db
+                            .acl_check(&cmd)
+                            .and_then(|_| self.db.kill_script(function));
+                        Some(match killed {
+                            Ok(()) => Frame::Simple("OK".to_string()),
+                            Err(err) => Frame::Error(err),
+                        })
+                    };
+
+                    // `SHUTDOWN NOSAVE` stops the script, and is then applied.
+                    match &cmd {
+                        Command::Script(script) if script.is_kill() => kill(false),
+                        Command::Function(function) if function.is_kill() => kill(true),
+                        Command::Shutdown(shutdown) if shutdown.is_nosave() => {
+                            match self.db.acl_check(&cmd) {
+                                Ok(()) => {
+                                    self.db.abort_script();
+                                    None
+                                }
+                                Err(err) => Some(Frame::Error(err)),
+                            }
+                        }
+                        _ => {
+                            if self.transaction.is_some() {
+                                self.transaction_aborted = true;
+                            }
+                            Some(Frame::Error(busy))
+                        }
+                    }
+                };
+
+                if let Some(response) = response {
+                    debug!(?response);
+                    self.connection.write_frame(&response).await?;
+                    continue;
+                }
+            }
+            self.db.count_command(cmd.get_name());
+
+            // The replies are dropped after `CLIENT REPLY OFF`, and for the
+            // command following `CLIENT REPLY SKIP`.
+            let muted = match self.reply {
+                ClientReply::On => false,
+                ClientReply::Off => true,
+                ClientReply::Skip => {
+                    self.reply = ClientReply::On;
+                    true
+                }
+            };
+            self.connection.set_muted(muted);
+
+            // The commands are streamed to the connections running `MONITOR`,
+            // with their passwords redacted.
+            self.db.feed_monitors(&self.addr, &received);
 
             // Logs the `cmd` object. The syntax here is a shorthand provided by
             // the `tracing` crate. It can be thought of as similar to:
@@ -365,33 +1049,409 @@ impl Handler {
             // as key-value pairs.
             debug!(?cmd);
 
-            // Perform the work needed to apply the command. This may mutate the
-            // database state as a result.
-            //
-            // The connection is passed into the apply function which allows the
-            // command to write response frames directly to the connection. In
-            // the case of pub/sub, multiple frames may be send back to the
-            // peer.
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown)
-                .await?;
+            // Commands exceeding the rate limits of the client, or of its
+            // user, are rejected.
+            let len = self.connection.bytes_read() - bytes_read;
+            if let Err(err) = self.db.throttle(len as usize) {
+                if self.transaction.is_some() {
+                    self.transaction_aborted = true;
+                }
+
+                let response = Frame::Error(err);
+                debug!(?response);
+                self.connection.write_frame(&response).await?;
+                continue;
+            }
+
+            // Until the client authenticates, only `AUTH`, `HELLO` with its
+            // `AUTH` option, and `RESET` are accepted. Once authenticated, the
+            // ACL rules of its user decide which commands it may run, and the
+            // keys and channels they may access.
+            let allowed = match &cmd {
+                Command::Auth(_) | Command::Reset(_) => Ok(()),
+                Command::Hello(hello) if hello.authenticates() => Ok(()),
+                _ => self.db.acl_check(&cmd),
+            };
+            if let Err(err) = allowed {
+                if self.transaction.is_some() {
+                    self.transaction_aborted = true;
+                }
+
+                let response = Frame::Error(err);
+                debug!(?response);
+                self.connection.write_frame(&response).await?;
+                continue;
+            }
+
+            // In cluster mode, commands accessing keys of a slot the node does
+            // not serve are redirected to the node serving it. A transaction
+            // is aborted if one of its commands is redirected.
+            let asking = std::mem::take(&mut self.asking);
+            if let Err(redirect) = self.db.route(&cmd.keys(), asking) {
+                if self.transaction.is_some() {
+                    self.transaction_aborted = true;
+                }
+
+                let response = Frame::Error(redirect.to_string());
+                debug!(?response);
+                self.connection.write_frame(&response).await?;
+                continue;
+            }
+
+            // While the clients are paused by `CLIENT PAUSE`, commands are
+            // held until the pause ends. `CLIENT` is not, so that the clients
+            // may be unpaused, nor are the commands queued in a transaction,
+            // `EXEC` being held instead if one of them may write.
+            let held = match &cmd {
+                Command::Client(_) => false,
+                Command::Exec(_) => true,
+                _ => self.transaction.is_none(),
+            };
+            if held {
+                let write = match &cmd {
+                    Command::Exec(_) => self
+                        .transaction
+                        .iter()
+                        .flatten()
+                        .any(|(cmd, _)| may_write(cmd)),
+                    cmd => may_write(cmd),
+                };
+                tokio::select! {
+                    _ = self.db.wait_unpaused(write) => {}
+                    _ = self.shutdown.recv() => return Ok(()),
+                }
+            }
+
+            // Once the data set uses more than `maxmemory`, keys are evicted
+            // before applying a command which may use more memory. If none may
+            // be evicted, the command is rejected, as is `EXEC` if one of the
+            // queued commands may use more memory.
+            let denyoom = match &cmd {
+                Command::Exec(_) => self
+                    .transaction
+                    .iter()
+                    .flatten()
+                    .any(|(cmd, _)| cmd.is_denyoom()),
+                cmd => cmd.is_denyoom(),
+            };
+            if denyoom {
+                if let Err(err) = self.db.evict() {
+                    if self.transaction.is_some() {
+                        self.transaction_aborted = true;
+                    }
+
+                    let response = Frame::Error(err.to_string());
+                    debug!(?response);
+                    self.connection.write_frame(&response).await?;
+                    continue;
+                }
+            }
+
+            // Transaction commands update the state of the connection, and
+            // commands received in a transaction are queued until `EXEC`.
+            match cmd {
+                Command::Reset(cmd) => self.reset(cmd).await?,
+                Command::Multi(_) => self.multi().await?,
+                Command::Exec(_) => self.exec().await?,
+                Command::Discard(_) => self.discard().await?,
+                Command::Watch(cmd) => self.watch(cmd).await?,
+                Command::Unwatch(_) if self.transaction.is_none() => self.unwatch().await?,
+                Command::Asking(_) if self.transaction.is_none() => self.asking().await?,
+                Command::Client(cmd) if self.transaction.is_none() => self.client(cmd).await?,
+                cmd if self.transaction.is_some() => self.queue(cmd, received).await?,
+                // Clients may not modify the databases of a replica, they are
+                // only modified by the commands streamed by the primary.
+                cmd if cmd.is_write() && self.db.is_replica() => {
+                    let response = Frame::Error(DbError::ReadOnly.to_string());
+                    debug!(?response);
+                    self.connection.write_frame(&response).await?;
+                }
+                // Perform the work needed to apply the command. This may mutate
+                // the database state as a result.
+                //
+                // The connection is passed into the apply function which allows
+                // the command to write response frames directly to the
+                // connection. In the case of pub/sub, multiple frames may be
+                // send back to the peer.
+                cmd => {
+                    if !cmd.is_write() {
+                        self.db.count_lookups(&cmd.keys());
+                        if self.tracking {
+                            self.db.track(&cmd.keys());
+                        }
+                    }
+
+                    // Commands which may block are not recorded by the slow
+                    // log, as their execution time includes the time they
+                    // waited.
+                    let timed = !cmd.may_block();
+                    let logged = Some(&received).filter(|_| cmd.is_write()).cloned();
+
+                    self.db.set_logged_command(logged);
+                    let start = Instant::now();
+                    let res = cmd
+                        .apply(&mut self.db, &mut self.connection, &mut self.shutdown)
+                        .await;
+                    self.db.set_logged_command(None);
+
+                    if timed {
+                        let elapsed = start.elapsed();
+                        self.db.log_if_slow(&received, elapsed, &self.addr);
+                        self.db.add_latency_sample("command", elapsed);
+                    }
+                    res?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start a transaction.
+    async fn multi(&mut self) -> crate::Result<()> {
+        let response = if self.transaction.is_some() {
+            Frame::Error("ERR MULTI calls can not be nested".to_string())
+        } else {
+            self.transaction = Some(Vec::new());
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Queue a command received in a transaction.
+    ///
+    /// Unknown commands and commands that cannot run in a transaction are not
+    /// queued, and the transaction is aborted.
+    async fn queue(&mut self, cmd: Command, received: Frame) -> crate::Result<()> {
+        let response = match cmd {
+            Command::Unknown(cmd) => {
+                // The error is the same as outside of a transaction.
+                self.transaction_aborted = true;
+                return cmd.apply(&mut self.connection).await;
+            }
+            cmd if !cmd.allowed_in_transaction() => {
+                self.transaction_aborted = true;
+                Frame::Error("ERR Command not allowed inside a transaction".to_string())
+            }
+            cmd if cmd.is_write() && self.db.is_replica() => {
+                self.transaction_aborted = true;
+                Frame::Error(DbError::ReadOnly.to_string())
+            }
+            cmd => {
+                self.transaction.as_mut().unwrap().push((cmd, received));
+                Frame::Simple("QUEUED".to_string())
+            }
+        };
+
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Execute the commands of the transaction.
+    ///
+    /// The commands are applied while holding the transaction, no other
+    /// connection accesses the databases in the meantime. Their replies are
+    /// buffered, then sent as an array once the transaction completes.
+    async fn exec(&mut self) -> crate::Result<()> {
+        let commands = match self.transaction.take() {
+            Some(commands) => commands,
+            None => {
+                let response = Frame::Error("ERR EXEC without MULTI".to_string());
+                debug!(?response);
+                self.connection.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let watched = std::mem::take(&mut self.watched);
+
+        if std::mem::take(&mut self.transaction_aborted) {
+            self.db.unwatch(watched);
+
+            let response = Frame::Error(
+                "EXECABORT Transaction discarded because of previous errors.".to_string(),
+            );
+            debug!(?response);
+            self.connection.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        let mut transaction = self.db.transaction();
+
+        // Watched keys are checked once the transaction started, so that they
+        // cannot be modified until it completes.
+        let modified = transaction.db().watched_modified(&watched);
+        transaction.db().unwatch(watched);
+
+        if modified {
+            drop(transaction);
+            let response = Frame::Null;
+            debug!(?response);
+            self.connection.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        self.connection.buffer_replies();
+
+        for (cmd, received) in commands {
+            if !cmd.is_write() {
+                transaction.db().count_lookups(&cmd.keys());
+                if self.tracking {
+                    transaction.db().track(&cmd.keys());
+                }
+            }
+
+            // Commands do not block in a transaction, which never yields so
+            // that the connections waiting for it cannot hold up its task.
+            // Blocking commands run as if their timeout had already elapsed,
+            // replying with a null reply when there is nothing to pop or read.
+            let logged = Some(&received).filter(|_| cmd.is_write()).cloned();
+            transaction.db().set_logged_command(logged);
+            let start = Instant::now();
+            match cmd.apply_now(transaction.db(), &mut self.connection, &mut self.shutdown) {
+                Some(res) => res?,
+                None => {
+                    let mut replies = self.connection.take_replies();
+                    replies.push(Frame::Null);
+                    self.connection.replace_replies(Some(replies));
+                }
+            }
+            let elapsed = start.elapsed();
+            transaction.db().log_if_slow(&received, elapsed, &self.addr);
+            transaction.db().add_latency_sample("command", elapsed);
         }
+        transaction.db().set_logged_command(None);
+
+        // `SELECT` may have changed the selected database, which remains
+        // selected after the transaction.
+        self.db = transaction.commit();
+
+        // `CLIENT TRACKING` may have been queued in the transaction.
+        self.tracking = self.db.is_tracking();
+        self.invalidations = self.db.tracking_invalidations();
+
+        let response = Frame::Array(self.connection.take_replies());
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Abort the transaction, dropping the queued commands.
+    async fn discard(&mut self) -> crate::Result<()> {
+        let response = match self.transaction.take() {
+            Some(_) => {
+                self.transaction_aborted = false;
+                self.db.unwatch(std::mem::take(&mut self.watched));
+                Frame::Simple("OK".to_string())
+            }
+            None => Frame::Error("ERR DISCARD without MULTI".to_string()),
+        };
+
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Watch keys of the selected database until the next `EXEC`.
+    async fn watch(&mut self, cmd: Watch) -> crate::Result<()> {
+        let response = if self.transaction.is_some() {
+            Frame::Error("ERR WATCH inside MULTI is not allowed".to_string())
+        } else {
+            let watched = self.db.watch(cmd.keys());
+            self.watched.extend(watched);
+            Frame::Simple("OK".to_string())
+        };
 
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Stop watching all the keys.
+    async fn unwatch(&mut self) -> crate::Result<()> {
+        self.db.unwatch(std::mem::take(&mut self.watched));
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Serve the next command even if its keys belong to a slot being
+    /// imported.
+    async fn asking(&mut self) -> crate::Result<()> {
+        let response = match self.db.cluster_myself() {
+            Ok(_) => {
+                self.asking = true;
+                Frame::Simple("OK".to_string())
+            }
+            Err(err) => Frame::Error(err),
+        };
+
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Return the connection to the state of a new connection, discarding
+    /// its transaction and watched keys, then apply the `RESET` command.
+    async fn reset(&mut self, cmd: Reset) -> crate::Result<()> {
+        self.transaction = None;
+        self.transaction_aborted = false;
+        self.db.unwatch(std::mem::take(&mut self.watched));
+        self.asking = false;
+        self.reply = ClientReply::On;
+        self.tracking = false;
+        self.invalidations = None;
+
+        cmd.apply(&mut self.db, &mut self.connection).await
+    }
+
+    /// Apply a `CLIENT` command, which may enable or disable tracking or the
+    /// replies.
+    async fn client(&mut self, cmd: Client) -> crate::Result<()> {
+        // `CLIENT REPLY` itself is only replied to if replies are enabled.
+        if let Some(mode) = cmd.reply_mode() {
+            self.reply = mode;
+            self.connection.set_muted(mode != ClientReply::On);
+        }
+
+        cmd.apply(&self.db, &mut self.connection).await?;
+        self.tracking = self.db.is_tracking();
+        self.invalidations = self.db.tracking_invalidations();
+        Ok(())
+    }
+
+    /// Push an invalidated `key` to the client, even if its replies are
+    /// disabled.
+    async fn push_invalidation(&mut self, key: Bytes) -> crate::Result<()> {
+        let frame = Frame::Push(vec![
+            Frame::Bulk(Bytes::from_static(b"invalidate")),
+            Frame::Array(vec![Frame::Bulk(key)]),
+        ]);
+
+        self.connection.set_muted(false);
+        self.connection.write_frame(&frame).await?;
         Ok(())
     }
 }
 
 impl Drop for Handler {
     fn drop(&mut self) {
-        // Add a permit back to the semaphore.
-        //
-        // Doing so unblocks the listener if the max number of
-        // connections has been reached.
+        // Stop watching the keys, so that the database no longer tracks their
+        // modifications.
+        self.db.unwatch(std::mem::take(&mut self.watched));
+
+        // Forgetting the client unblocks the listener if the connections over
+        // `maxclients` are queued.
         //
         // This is done in a `Drop` implementation in order to guarantee that
-        // the permit is added even if the task handling the connection panics.
-        // If `add_permit` was called at the end of the `run` function and some
-        // bug causes a panic. The permit would never be returned to the
-        // semaphore.
-        self.limit_connections.add_permits(1);
+        // the client is forgotten even if the task handling the connection
+        // panics.
+        self.db.disconnect();
     }
 }
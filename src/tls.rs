@@ -0,0 +1,157 @@
+//! TLS termination of the connections with clients, enabled with the `tls`
+//! feature.
+//!
+//! The certificates and the private key are read from PEM files when the
+//! server starts. Connections are then encrypted with the `rustls`
+//! implementation of TLS, through `tokio-rustls`.
+
+use crate::connection::Stream;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::rustls::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, NoClientAuth, PrivateKey,
+    RootCertStore, ServerConfig,
+};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+/// TLS configuration of the server, see `server::Config::tls`.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM file holding the certificate chain presented to the clients,
+    /// starting with the certificate of the server.
+    pub cert_path: PathBuf,
+
+    /// PEM file holding the private key of the certificate of the server, in
+    /// the PKCS #8 or the RSA format.
+    pub key_path: PathBuf,
+
+    /// PEM file holding the certificates of the authorities the certificates
+    /// of the clients are verified against. Required unless `auth_clients` is
+    /// `ClientAuth::No`.
+    pub ca_cert_path: Option<PathBuf>,
+
+    /// Whether clients present a certificate.
+    pub auth_clients: ClientAuth,
+}
+
+/// Whether clients present a certificate, which is verified against the
+/// certificates of `TlsConfig::ca_cert_path`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// Clients do not present a certificate.
+    No,
+
+    /// Clients may present a certificate, which is rejected if it cannot be
+    /// verified.
+    Optional,
+
+    /// Clients must present a certificate which can be verified. This is the
+    /// default, as with Redis.
+    #[default]
+    Required,
+}
+
+impl FromStr for ClientAuth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ClientAuth, String> {
+        match &s.to_lowercase()[..] {
+            "no" => Ok(ClientAuth::No),
+            "optional" => Ok(ClientAuth::Optional),
+            "yes" => Ok(ClientAuth::Required),
+            _ => Err(format!("invalid client authentication `{}`", s)),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Returns the acceptor performing the TLS handshake with the clients.
+    ///
+    /// Returns `Err` if a file cannot be read, or if its content is not valid.
+    pub(crate) fn acceptor(&self) -> crate::Result<Acceptor> {
+        let certs = read_pem(&self.cert_path, pemfile::certs)?;
+        let key = read_private_key(&self.key_path)?;
+
+        let verifier = match (self.auth_clients, &self.ca_cert_path) {
+            (ClientAuth::No, _) => NoClientAuth::new(),
+            (_, None) => return Err("authenticating TLS clients requires a CA certificate".into()),
+            (ClientAuth::Optional, Some(path)) => {
+                AllowAnyAnonymousOrAuthenticatedClient::new(read_roots(path)?)
+            }
+            (ClientAuth::Required, Some(path)) => {
+                AllowAnyAuthenticatedClient::new(read_roots(path)?)
+            }
+        };
+
+        let mut config = ServerConfig::new(verifier);
+        config.set_single_cert(certs, key)?;
+
+        Ok(Acceptor(TlsAcceptor::from(Arc::new(config))))
+    }
+}
+
+/// Performs the TLS handshake with the clients, built from a `TlsConfig`.
+#[derive(Clone)]
+pub(crate) struct Acceptor(TlsAcceptor);
+
+impl Acceptor {
+    /// Performs the TLS handshake with the client connected through `stream`.
+    pub(crate) async fn accept(
+        &self,
+        stream: Box<dyn Stream>,
+    ) -> io::Result<TlsStream<Box<dyn Stream>>> {
+        self.0.accept(stream).await
+    }
+}
+
+impl fmt::Debug for Acceptor {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Acceptor").finish()
+    }
+}
+
+/// Reads the entries of the PEM file at `path` with `parse`, which returns
+/// the entries of one kind.
+///
+/// Returns `Err` if the file has no entry of this kind.
+fn read_pem<T>(
+    path: &Path,
+    parse: fn(&mut dyn BufRead) -> Result<Vec<T>, ()>,
+) -> crate::Result<Vec<T>> {
+    let file =
+        File::open(path).map_err(|err| format!("failed to open `{}`: {}", path.display(), err))?;
+
+    match parse(&mut BufReader::new(file)) {
+        Ok(entries) if !entries.is_empty() => Ok(entries),
+        _ => Err(format!("no valid PEM entry in `{}`", path.display()).into()),
+    }
+}
+
+/// Reads the first private key of the PEM file at `path`, in the PKCS #8 or
+/// the RSA format.
+fn read_private_key(path: &Path) -> crate::Result<PrivateKey> {
+    let mut keys = read_pem(path, pemfile::pkcs8_private_keys)
+        .or_else(|_| read_pem(path, pemfile::rsa_private_keys))?;
+
+    Ok(keys.swap_remove(0))
+}
+
+/// Reads the certificates of the authorities of the PEM file at `path`.
+fn read_roots(path: &Path) -> crate::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+
+    for cert in read_pem(path, pemfile::certs)? {
+        roots
+            .add(&cert)
+            .map_err(|err| format!("invalid CA certificate in `{}`: {}", path.display(), err))?;
+    }
+
+    Ok(roots)
+}
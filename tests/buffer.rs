@@ -24,7 +24,8 @@ async fn start_server() -> (SocketAddr, JoinHandle<mini_redis::Result<()>>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
-    let handle = tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    let handle =
+        tokio::spawn(async move { server::run(vec![listener], tokio::signal::ctrl_c()).await });
 
     (addr, handle)
 }
@@ -1,7 +1,18 @@
-use mini_redis::{client, server};
+use bytes::Bytes;
+use mini_redis::client::{LcsMatch, ReplicationRole};
+use mini_redis::cmd::{
+    BitFieldOp, BitOperation, BitUnit, ExpireOption, GeoOrigin, GeoShape, GeoUnit, InsertPosition,
+    ListEnd, Overflow, SetCondition, ZAddOption,
+};
+use mini_redis::{client, server, Connection, Frame};
+use std::collections::HashSet;
+use std::io::Write;
 use std::net::SocketAddr;
-use tokio::net::TcpListener;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::stream::StreamExt;
 use tokio::task::JoinHandle;
+use tokio::time::{self, Duration, Instant};
 
 /// A basic "hello world" style test. A server instance is started in a
 /// background task. A client instance is then established and set and get
@@ -23,7 +34,7 @@ async fn key_value_get_set() {
 async fn receive_message_subscribed_channel() {
     let (addr, _) = start_server().await;
 
-    let client = client::connect(addr.clone()).await.unwrap();
+    let client = client::connect(addr).await.unwrap();
     let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
 
     tokio::spawn(async move {
@@ -41,7 +52,7 @@ async fn receive_message_subscribed_channel() {
 async fn receive_message_multiple_subscribed_channels() {
     let (addr, _) = start_server().await;
 
-    let client = client::connect(addr.clone()).await.unwrap();
+    let client = client::connect(addr).await.unwrap();
     let mut subscriber = client
         .subscribe(vec!["hello".into(), "world".into()])
         .await
@@ -72,7 +83,7 @@ async fn receive_message_multiple_subscribed_channels() {
 async fn unsubscribes_from_channels() {
     let (addr, _) = start_server().await;
 
-    let client = client::connect(addr.clone()).await.unwrap();
+    let client = client::connect(addr).await.unwrap();
     let mut subscriber = client
         .subscribe(vec!["hello".into(), "world".into()])
         .await
@@ -82,11 +93,5988 @@ async fn unsubscribes_from_channels() {
     assert_eq!(subscriber.get_subscribed().len(), 0);
 }
 
+/// Fields are stored, fetched and removed from a hash. Applying a string
+/// command to a key holding a hash is rejected with a `WRONGTYPE` error.
+#[tokio::test]
+async fn hash_set_get_del() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let added = client
+        .hset(
+            "user",
+            vec![
+                ("name".into(), "ferris".into()),
+                ("lang".into(), "rust".into()),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, added);
+
+    // Updating an existing field does not count as adding it
+    let added = client
+        .hset("user", vec![("lang".into(), "Rust".into())])
+        .await
+        .unwrap();
+    assert_eq!(0, added);
+
+    let value = client.hget("user", "lang").await.unwrap().unwrap();
+    assert_eq!(b"Rust", &value[..]);
+    assert!(client.hget("user", "age").await.unwrap().is_none());
+
+    let values = client
+        .hmget("user", vec!["name".into(), "age".into()])
+        .await
+        .unwrap();
+    assert_eq!(vec![Some("ferris".into()), None], values);
+
+    let mut fields = client.hgetall("user").await.unwrap();
+    fields.sort();
+    assert_eq!(
+        vec![
+            ("lang".to_string(), "Rust".into()),
+            ("name".to_string(), "ferris".into())
+        ],
+        fields
+    );
+
+    let err = client.get("user").await.unwrap_err();
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+
+    let removed = client
+        .hdel("user", vec!["name".into(), "lang".into(), "age".into()])
+        .await
+        .unwrap();
+    assert_eq!(2, removed);
+
+    // Removing the last field removes the key
+    assert!(client.hgetall("user").await.unwrap().is_empty());
+    assert_eq!(0, client.del(vec!["user".into()]).await.unwrap());
+}
+
+/// Values pushed and popped at both ends of a list, with negative indexes used
+/// to read ranges relative to the end of the list.
+#[tokio::test]
+async fn list_push_pop_range() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(
+        2,
+        client
+            .rpush("list", vec!["b".into(), "c".into()])
+            .await
+            .unwrap()
+    );
+    assert_eq!(3, client.lpush("list", vec!["a".into()]).await.unwrap());
+    assert_eq!(3, client.llen("list").await.unwrap());
+
+    let all = client.lrange("list", 0, -1).await.unwrap();
+    assert_eq!(vec!["a", "b", "c"], all);
+
+    let tail = client.lrange("list", -2, 100).await.unwrap();
+    assert_eq!(vec!["b", "c"], tail);
+
+    assert!(client.lrange("list", 5, 10).await.unwrap().is_empty());
+
+    assert_eq!(Some("a".into()), client.lpop("list").await.unwrap());
+    assert_eq!(Some("c".into()), client.rpop("list").await.unwrap());
+    assert_eq!(Some("b".into()), client.rpop("list").await.unwrap());
+
+    // Popping the last value removes the key
+    assert_eq!(None, client.lpop("list").await.unwrap());
+    assert_eq!(0, client.llen("list").await.unwrap());
+}
+
+/// Members added to and removed from a set. Set commands applied to a key
+/// holding a string are rejected with a `WRONGTYPE` error.
+#[tokio::test]
+async fn set_add_remove_members() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = vec!["a".into(), "b".into(), "a".into()];
+    assert_eq!(2, client.sadd("set", members).await.unwrap());
+    assert_eq!(2, client.scard("set").await.unwrap());
+
+    assert!(client.sismember("set", "a".into()).await.unwrap());
+    assert!(!client.sismember("set", "c".into()).await.unwrap());
+
+    let mut members = client.smembers("set").await.unwrap();
+    members.sort();
+    assert_eq!(vec!["a", "b"], members);
+
+    let removed = client
+        .srem("set", vec!["a".into(), "c".into()])
+        .await
+        .unwrap();
+    assert_eq!(1, removed);
+    assert_eq!(1, client.scard("set").await.unwrap());
+
+    client.set("string", "value".into()).await.unwrap();
+    let err = client.sadd("string", vec!["a".into()]).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+/// Members of a sorted set are kept ordered by score, regardless of insertion
+/// order, and scores can be updated in place.
+#[tokio::test]
+async fn sorted_set_add_range_rank() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = vec![(3.0, "c".into()), (1.0, "a".into()), (2.5, "b".into())];
+    assert_eq!(3, client.zadd("zset", members).await.unwrap());
+
+    let members = client.zrange("zset", 0, -1).await.unwrap();
+    assert_eq!(vec!["a", "b", "c"], members);
+
+    // Updating a score moves the member without adding it
+    assert_eq!(
+        0,
+        client.zadd("zset", vec![(0.5, "c".into())]).await.unwrap()
+    );
+
+    let members = client.zrange_withscores("zset", 0, 1).await.unwrap();
+    assert_eq!(vec![("c".into(), 0.5), ("a".into(), 1.0)], members);
+
+    assert_eq!(Some(2.5), client.zscore("zset", "b".into()).await.unwrap());
+    assert_eq!(None, client.zscore("zset", "d".into()).await.unwrap());
+    assert_eq!(Some(2), client.zrank("zset", "b".into()).await.unwrap());
+    assert_eq!(None, client.zrank("zset", "d".into()).await.unwrap());
+
+    let removed = client
+        .zrem("zset", vec!["a".into(), "d".into()])
+        .await
+        .unwrap();
+    assert_eq!(1, removed);
+    assert_eq!(vec!["c", "b"], client.zrange("zset", 0, -1).await.unwrap());
+}
+
+#[tokio::test]
+async fn stream_add_range_len() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let fields = vec![("temp".into(), "20".into())];
+    assert_eq!("1-1", client.xadd("sensor", "1-1", fields).await.unwrap());
+
+    let fields = vec![("temp".into(), "21".into())];
+    assert_eq!("1-2", client.xadd("sensor", "1-*", fields).await.unwrap());
+
+    // IDs must keep increasing
+    let fields = vec![("temp".into(), "22".into())];
+    let err = client.xadd("sensor", "1-2", fields).await.unwrap_err();
+    assert!(err.to_string().contains("equal or smaller"));
+
+    let fields = vec![("temp".into(), "23".into())];
+    let id = client.xadd("sensor", "*", fields).await.unwrap();
+
+    assert_eq!(3, client.xlen("sensor").await.unwrap());
+
+    let entries = client.xrange("sensor", "-", "+", None).await.unwrap();
+    let ids: Vec<_> = entries.iter().map(|entry| &entry.id[..]).collect();
+    assert_eq!(vec!["1-1", "1-2", &id[..]], ids);
+    assert_eq!(vec![("temp".to_string(), "21".into())], entries[1].fields);
+
+    // Exclusive start, omitted sequence number and count
+    let entries = client.xrange("sensor", "(1-1", "1", None).await.unwrap();
+    assert_eq!(1, entries.len());
+    assert_eq!("1-2", entries[0].id);
+
+    let entries = client.xrange("sensor", "-", "+", Some(2)).await.unwrap();
+    assert_eq!(2, entries.len());
+
+    let streams = vec![("sensor".to_string(), "1-1".to_string())];
+    let read = client.xread(streams, None, None).await.unwrap();
+    assert_eq!(1, read.len());
+    assert_eq!("sensor", read[0].0);
+    assert_eq!(2, read[0].1.len());
+
+    // Nothing past the last entry, without blocking
+    let streams = vec![("sensor".to_string(), "$".to_string())];
+    assert!(client.xread(streams, None, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn stream_blocking_read() {
+    let (addr, _) = start_server().await;
+
+    let mut reader = client::connect(addr).await.unwrap();
+    let mut writer = client::connect(addr).await.unwrap();
+
+    // The block duration elapses without any new entry
+    let streams = vec![("events".to_string(), "$".to_string())];
+    let read = reader
+        .xread(streams, None, Some(Duration::from_millis(10)))
+        .await
+        .unwrap();
+    assert!(read.is_empty());
+
+    let blocked = tokio::spawn(async move {
+        let streams = vec![("events".to_string(), "$".to_string())];
+        reader
+            .xread(streams, None, Some(Duration::from_millis(0)))
+            .await
+            .unwrap()
+    });
+
+    // Give the reader time to block before adding the entry
+    time::sleep(Duration::from_millis(50)).await;
+
+    let fields = vec![("kind".into(), "login".into())];
+    let id = writer.xadd("events", "*", fields).await.unwrap();
+
+    let read = blocked.await.unwrap();
+    assert_eq!(1, read.len());
+    assert_eq!("events", read[0].0);
+    assert_eq!(id, read[0].1[0].id);
+}
+
+#[tokio::test]
+async fn list_blocking_pop() {
+    let (addr, _) = start_server().await;
+
+    let mut first = client::connect(addr).await.unwrap();
+    let mut second = client::connect(addr).await.unwrap();
+    let mut writer = client::connect(addr).await.unwrap();
+
+    // The timeout elapses without any value
+    let popped = first
+        .blpop(vec!["a".into()], Duration::from_millis(10))
+        .await
+        .unwrap();
+    assert_eq!(None, popped);
+
+    // Timeouts are counted in milliseconds as an `i64`
+    let err = first
+        .blpop(vec!["a".into()], Duration::from_secs(i64::MAX as u64))
+        .await
+        .unwrap_err();
+    assert_eq!("ERR timeout is out of range", err.to_string());
+    let err = first
+        .brpop(vec!["a".into()], Duration::MAX)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR timeout is out of range", err.to_string());
+
+    // A value is available right away, keys are checked in order
+    writer
+        .rpush("b", vec!["1".into(), "2".into()])
+        .await
+        .unwrap();
+    let popped = first
+        .brpop(vec!["a".into(), "b".into()], Duration::from_secs(0))
+        .await
+        .unwrap();
+    assert_eq!(Some(("b".into(), "2".into())), popped);
+
+    let first = tokio::spawn(async move {
+        first
+            .blpop(vec!["a".into(), "c".into()], Duration::from_secs(0))
+            .await
+            .unwrap()
+    });
+
+    // Give each client time to block, so that they are queued in order
+    time::sleep(Duration::from_millis(50)).await;
+
+    let second = tokio::spawn(async move {
+        second
+            .blpop(vec!["a".into()], Duration::from_secs(0))
+            .await
+            .unwrap()
+    });
+
+    time::sleep(Duration::from_millis(50)).await;
+
+    // The longest waiting client is served first
+    writer.rpush("a", vec!["x".into()]).await.unwrap();
+    assert_eq!(Some(("a".into(), "x".into())), first.await.unwrap());
+
+    writer.rpush("a", vec!["y".into()]).await.unwrap();
+    assert_eq!(Some(("a".into(), "y".into())), second.await.unwrap());
+
+    assert_eq!(0, writer.llen("a").await.unwrap());
+}
+
+#[tokio::test]
+async fn list_move() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let mut writer = client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.rpoplpush("missing", "dest").await.unwrap());
+
+    client
+        .rpush("src", vec!["a".into(), "b".into(), "c".into()])
+        .await
+        .unwrap();
+
+    let moved = client.rpoplpush("src", "dest").await.unwrap();
+    assert_eq!(Some("c".into()), moved);
+
+    let moved = client
+        .lmove("src", "dest", ListEnd::Left, ListEnd::Right)
+        .await
+        .unwrap();
+    assert_eq!(Some("a".into()), moved);
+    assert_eq!(vec!["c", "a"], client.lrange("dest", 0, -1).await.unwrap());
+
+    // Moving within the same list rotates it
+    client
+        .lmove("dest", "dest", ListEnd::Left, ListEnd::Right)
+        .await
+        .unwrap();
+    assert_eq!(vec!["a", "c"], client.lrange("dest", 0, -1).await.unwrap());
+
+    // The value is not popped when the destination has the wrong type
+    client.set("string", "v".into()).await.unwrap();
+    let err = client
+        .lmove("src", "string", ListEnd::Left, ListEnd::Left)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+    assert_eq!(vec!["b"], client.lrange("src", 0, -1).await.unwrap());
+
+    // Blocking forms
+    client.del(vec!["src".into()]).await.unwrap();
+    let moved = client
+        .brpoplpush("src", "dest", Duration::from_millis(10))
+        .await
+        .unwrap();
+    assert_eq!(None, moved);
+    let err = client
+        .brpoplpush("src", "dest", Duration::from_secs(i64::MAX as u64))
+        .await
+        .unwrap_err();
+    assert_eq!("ERR timeout is out of range", err.to_string());
+
+    let blocked = tokio::spawn(async move {
+        client
+            .blmove(
+                "src",
+                "dest",
+                ListEnd::Right,
+                ListEnd::Left,
+                Duration::from_secs(0),
+            )
+            .await
+            .unwrap()
+    });
+
+    // Give the client time to block before pushing the value
+    time::sleep(Duration::from_millis(50)).await;
+
+    writer.lpush("src", vec!["x".into()]).await.unwrap();
+    assert_eq!(Some("x".into()), blocked.await.unwrap());
+    assert_eq!(0, writer.llen("src").await.unwrap());
+    assert_eq!(
+        vec!["x", "a", "c"],
+        writer.lrange("dest", 0, -1).await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn stream_consumer_groups() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    // The stream must exist unless MKSTREAM is given
+    assert!(client
+        .xgroup_create("jobs", "workers", "$", false)
+        .await
+        .is_err());
+    client
+        .xgroup_create("jobs", "workers", "$", true)
+        .await
+        .unwrap();
+    let err = client
+        .xgroup_create("jobs", "workers", "$", false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("BUSYGROUP"));
+
+    for task in &["a", "b", "c"] {
+        let fields = vec![("task".into(), Bytes::from(*task))];
+        client.xadd("jobs", "*", fields).await.unwrap();
+    }
+
+    // Each new entry is delivered to a single consumer
+    let new = || vec![("jobs".to_string(), ">".to_string())];
+    let alice = client
+        .xreadgroup("workers", "alice", new(), Some(2), None)
+        .await
+        .unwrap();
+    assert_eq!(2, alice[0].1.len());
+    let bob = client
+        .xreadgroup("workers", "bob", new(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(1, bob[0].1.len());
+    assert!(client
+        .xreadgroup("workers", "bob", new(), None, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let ids: Vec<_> = alice[0].1.iter().map(|entry| entry.id.clone()).collect();
+
+    let summary = client.xpending("jobs", "workers").await.unwrap();
+    assert_eq!(3, summary.count);
+    assert_eq!(Some(ids[0].clone()), summary.smallest_id);
+    assert_eq!(
+        vec![("alice".to_string(), 2), ("bob".to_string(), 1)],
+        summary.consumers
+    );
+
+    // Reading the history returns the consumer's pending entries
+    let history = vec![("jobs".to_string(), "0".to_string())];
+    let read = client
+        .xreadgroup("workers", "alice", history, None, None)
+        .await
+        .unwrap();
+    assert_eq!(2, read[0].1.len());
+
+    assert_eq!(
+        1,
+        client
+            .xack("jobs", "workers", vec![ids[0].clone()])
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        0,
+        client
+            .xack("jobs", "workers", vec![ids[0].clone()])
+            .await
+            .unwrap()
+    );
+
+    // Claiming an entry transfers it and increments its delivery count
+    let claimed = client
+        .xclaim(
+            "jobs",
+            "workers",
+            "bob",
+            Duration::from_millis(0),
+            vec![ids[1].clone()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(ids[1], claimed[0].id);
+
+    let pending = client
+        .xpending_range("jobs", "workers", "-", "+", 10)
+        .await
+        .unwrap();
+    assert_eq!(2, pending.len());
+    assert_eq!(ids[1], pending[0].id);
+    assert_eq!("bob", pending[0].consumer);
+    assert_eq!(2, pending[0].delivery_count);
+
+    // Deleting a consumer drops its pending entries
+    assert_eq!(
+        2,
+        client
+            .xgroup_delconsumer("jobs", "workers", "bob")
+            .await
+            .unwrap()
+    );
+    assert_eq!(0, client.xpending("jobs", "workers").await.unwrap().count);
+
+    // Rewinding the group delivers the entries again
+    client.xgroup_setid("jobs", "workers", "0").await.unwrap();
+    let read = client
+        .xreadgroup("workers", "carol", new(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(3, read[0].1.len());
+
+    assert!(client.xgroup_destroy("jobs", "workers").await.unwrap());
+    assert!(client
+        .xreadgroup("workers", "carol", new(), None, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn stream_trim_delete() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for seq in 1..=5 {
+        let fields = vec![("n".into(), Bytes::from(seq.to_string()))];
+        let id = format!("1-{}", seq);
+        client.xadd("log", &id, fields).await.unwrap();
+    }
+
+    assert_eq!(
+        2,
+        client
+            .xdel("log", vec!["1-2".into(), "1-9".into(), "1-4".into()])
+            .await
+            .unwrap()
+    );
+    assert_eq!(3, client.xlen("log").await.unwrap());
+
+    assert_eq!(1, client.xtrim("log", 2).await.unwrap());
+    assert_eq!(0, client.xtrim("log", 2).await.unwrap());
+
+    assert_eq!(1, client.xtrim_minid("log", "1-5").await.unwrap());
+
+    let entries = client.xrange("log", "-", "+", None).await.unwrap();
+    assert_eq!(1, entries.len());
+    assert_eq!("1-5", entries[0].id);
+
+    // The last ID is kept once entries are removed
+    client.xtrim("log", 0).await.unwrap();
+    assert_eq!(0, client.xlen("log").await.unwrap());
+    let fields = vec![("n".into(), "6".into())];
+    assert!(client.xadd("log", "1-5", fields).await.is_err());
+}
+
+#[tokio::test]
+async fn bitmap_set_count_pos() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    // Setting a bit far away grows the string with zero bytes
+    assert!(!client.setbit("bits", 1, true).await.unwrap());
+    assert!(!client.setbit("bits", 23, true).await.unwrap());
+    assert!(client.setbit("bits", 23, true).await.unwrap());
+    assert_eq!(
+        &[0x40, 0x00, 0x01][..],
+        &client.get("bits").await.unwrap().unwrap()[..]
+    );
+
+    assert!(client.getbit("bits", 1).await.unwrap());
+    assert!(!client.getbit("bits", 2).await.unwrap());
+    assert!(!client.getbit("bits", 1000).await.unwrap());
+    assert!(!client.getbit("missing", 0).await.unwrap());
+
+    assert_eq!(2, client.bitcount("bits", None).await.unwrap());
+    let range = Some((1, -1, BitUnit::Byte));
+    assert_eq!(1, client.bitcount("bits", range).await.unwrap());
+    let range = Some((2, 22, BitUnit::Bit));
+    assert_eq!(0, client.bitcount("bits", range).await.unwrap());
+
+    let pos = client.bitpos("bits", true, None, None, BitUnit::Byte);
+    assert_eq!(Some(1), pos.await.unwrap());
+    let pos = client.bitpos("bits", true, Some(1), None, BitUnit::Byte);
+    assert_eq!(Some(23), pos.await.unwrap());
+    let pos = client.bitpos("bits", true, Some(2), Some(22), BitUnit::Bit);
+    assert_eq!(None, pos.await.unwrap());
+    let pos = client.bitpos("bits", false, None, None, BitUnit::Byte);
+    assert_eq!(Some(0), pos.await.unwrap());
+
+    // Clear bits past the end are only found without an explicit end
+    client.set("ones", Bytes::from(&[0xff][..])).await.unwrap();
+    let pos = client.bitpos("ones", false, None, None, BitUnit::Byte);
+    assert_eq!(Some(8), pos.await.unwrap());
+    let pos = client.bitpos("ones", false, Some(0), Some(-1), BitUnit::Byte);
+    assert_eq!(None, pos.await.unwrap());
+
+    client
+        .hset("hash", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+    assert!(client.setbit("hash", 0, true).await.is_err());
+}
+
+#[tokio::test]
+async fn bitfield_counters() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let incr = |ty: &str, offset: &str, increment| BitFieldOp::IncrBy {
+        ty: ty.into(),
+        offset: offset.into(),
+        increment,
+    };
+    let get = |ty: &str, offset: &str| BitFieldOp::Get {
+        ty: ty.into(),
+        offset: offset.into(),
+    };
+
+    // Only reading does not create the key
+    let replies = client.bitfield("c", vec![get("u8", "0")]).await.unwrap();
+    assert_eq!(vec![Some(0)], replies);
+    assert!(client.get("c").await.unwrap().is_none());
+
+    let ops = vec![
+        BitFieldOp::Set {
+            ty: "u8".into(),
+            offset: "#0".into(),
+            value: 255,
+        },
+        incr("u8", "#0", 1),
+        incr("u8", "#1", 10),
+        get("i8", "#1"),
+    ];
+    let replies = client.bitfield("c", ops).await.unwrap();
+    assert_eq!(vec![Some(0), Some(0), Some(10), Some(10)], replies);
+    assert_eq!(&[0, 10][..], &client.get("c").await.unwrap().unwrap()[..]);
+
+    let ops = vec![
+        BitFieldOp::Overflow(Overflow::Sat),
+        incr("i4", "8", 100),
+        incr("u4", "8", -100),
+        BitFieldOp::Overflow(Overflow::Fail),
+        incr("u4", "12", 100),
+        get("u4", "12"),
+    ];
+    let replies = client.bitfield("c", ops).await.unwrap();
+    assert_eq!(vec![Some(7), Some(0), None, Some(10)], replies);
+
+    // Signed fields wrap around by default
+    let ops = vec![incr("i4", "12", -3), get("u4", "12")];
+    let replies = client.bitfield("c", ops).await.unwrap();
+    assert_eq!(vec![Some(7), Some(7)], replies);
+
+    assert!(client.bitfield("c", vec![get("u64", "0")]).await.is_err());
+    assert!(client.bitfield("c", vec![get("u8", "x")]).await.is_err());
+}
+
+#[tokio::test]
+async fn bitop_combine_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .set("a", Bytes::from(&[0xf0, 0xff][..]))
+        .await
+        .unwrap();
+    client.set("b", Bytes::from(&[0x3c][..])).await.unwrap();
+
+    let keys = vec!["a".to_string(), "b".to_string(), "missing".to_string()];
+    let cases = vec![
+        (BitOperation::And, &[0x00, 0x00][..]),
+        (BitOperation::Or, &[0xfc, 0xff][..]),
+        (BitOperation::Xor, &[0xcc, 0xff][..]),
+    ];
+
+    for (op, expected) in cases {
+        assert_eq!(2, client.bitop(op, "dest", keys.clone()).await.unwrap());
+        assert_eq!(expected, &client.get("dest").await.unwrap().unwrap()[..]);
+    }
+
+    let keys = vec!["b".to_string()];
+    assert_eq!(
+        1,
+        client.bitop(BitOperation::Not, "dest", keys).await.unwrap()
+    );
+    assert_eq!(&[0xc3][..], &client.get("dest").await.unwrap().unwrap()[..]);
+
+    // An empty result removes the destination
+    let keys = vec!["missing".to_string()];
+    assert_eq!(
+        0,
+        client.bitop(BitOperation::Or, "dest", keys).await.unwrap()
+    );
+    assert!(client.get("dest").await.unwrap().is_none());
+
+    let keys = vec!["a".to_string(), "b".to_string()];
+    assert!(client.bitop(BitOperation::Not, "dest", keys).await.is_err());
+}
+
+#[tokio::test]
+async fn hyperloglog_count_merge() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let elements = |range: std::ops::Range<u32>| {
+        range
+            .map(|i| Bytes::from(format!("element:{}", i)))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(client.pfadd("a", elements(0..6000)).await.unwrap());
+    assert!(client.pfadd("b", elements(4000..10000)).await.unwrap());
+    assert!(!client.pfadd("a", elements(0..10)).await.unwrap());
+
+    // The standard error is 0.81%, allow for three times as much
+    let assert_close = |expected: u64, count: u64| {
+        let error = (count as f64 - expected as f64).abs() / expected as f64;
+        assert!(error < 0.025, "expected ~{}, got {}", expected, count);
+    };
+
+    assert_close(6000, client.pfcount(vec!["a".into()]).await.unwrap());
+    let keys = vec!["a".into(), "b".into(), "missing".into()];
+    assert_close(10000, client.pfcount(keys).await.unwrap());
+
+    client
+        .pfmerge("union", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    assert_close(10000, client.pfcount(vec!["union".into()]).await.unwrap());
+
+    // Small cardinalities are exact in practice
+    assert!(client.pfadd("small", elements(0..3)).await.unwrap());
+    assert_eq!(3, client.pfcount(vec!["small".into()]).await.unwrap());
+    assert_eq!(0, client.pfcount(vec!["missing".into()]).await.unwrap());
+
+    client.set("string", "not a hll".into()).await.unwrap();
+    assert!(client.pfcount(vec!["string".into()]).await.is_err());
+    assert!(client.pfadd("string", vec![]).await.is_err());
+
+    // Registers are validated, a corrupted HyperLogLog is reported as such
+    client.setrange("small", 100, "x".into()).await.unwrap();
+    let err = client.pfcount(vec!["small".into()]).await.unwrap_err();
+    assert_eq!("INVALIDOBJ Corrupted HLL object detected", err.to_string());
+    let err = client.pfadd("small", elements(0..1)).await.unwrap_err();
+    assert_eq!("INVALIDOBJ Corrupted HLL object detected", err.to_string());
+    assert_close(6000, client.pfcount(vec!["b".into()]).await.unwrap());
+
+    // Whether it is counted along other keys, merged or merged into
+    let err = client
+        .pfcount(vec!["a".into(), "small".into()])
+        .await
+        .unwrap_err();
+    assert_eq!("INVALIDOBJ Corrupted HLL object detected", err.to_string());
+    let err = client
+        .pfmerge("merged", vec!["a".into(), "small".into()])
+        .await
+        .unwrap_err();
+    assert_eq!("INVALIDOBJ Corrupted HLL object detected", err.to_string());
+    assert_eq!(0, client.exists(vec!["merged".into()]).await.unwrap());
+    let err = client.pfmerge("small", vec!["a".into()]).await.unwrap_err();
+    assert_eq!("INVALIDOBJ Corrupted HLL object detected", err.to_string());
+}
+
+#[tokio::test]
+async fn integer_counters() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(1, client.incr("counter").await.unwrap());
+    assert_eq!(11, client.incrby("counter", 10).await.unwrap());
+    assert_eq!(10, client.decr("counter").await.unwrap());
+    assert_eq!(-5, client.decrby("counter", 15).await.unwrap());
+    assert_eq!(b"-5", &client.get("counter").await.unwrap().unwrap()[..]);
+
+    client
+        .set("max", i64::MAX.to_string().into())
+        .await
+        .unwrap();
+    assert!(client.incr("max").await.is_err());
+    assert!(client.decrby("max", i64::MIN).await.is_err());
+
+    client.set("text", "ten".into()).await.unwrap();
+    assert!(client.incr("text").await.is_err());
+    client.set("text", " 10".into()).await.unwrap();
+    assert!(client.incr("text").await.is_err());
+}
+
+#[tokio::test]
+async fn float_counters() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(10.5, client.incrbyfloat("f", 10.5).await.unwrap());
+    assert_eq!(10.6, client.incrbyfloat("f", 0.1).await.unwrap());
+    assert_eq!(b"10.6", &client.get("f").await.unwrap().unwrap()[..]);
+
+    // Integral results have no trailing zeros, large ones no exponent
+    client.set("f", "5.0e3".into()).await.unwrap();
+    assert_eq!(5200.0, client.incrbyfloat("f", 200.0).await.unwrap());
+    assert_eq!(b"5200", &client.get("f").await.unwrap().unwrap()[..]);
+    client.incrbyfloat("f", 1e17).await.unwrap();
+    assert_eq!(
+        b"100000000000005200",
+        &client.get("f").await.unwrap().unwrap()[..]
+    );
+
+    assert!(client.incrbyfloat("f", f64::INFINITY).await.is_err());
+    assert!(client.incrbyfloat("missing", f64::INFINITY).await.is_err());
+    assert!(client.get("missing").await.unwrap().is_none());
+    client.set("text", "abc".into()).await.unwrap();
+    assert!(client.incrbyfloat("text", 1.0).await.is_err());
+
+    assert_eq!(1.5, client.hincrbyfloat("h", "x", 1.5).await.unwrap());
+    assert_eq!(-0.5, client.hincrbyfloat("h", "x", -2.0).await.unwrap());
+    assert_eq!(b"-0.5", &client.hget("h", "x").await.unwrap().unwrap()[..]);
+    client
+        .hset("h", vec![("y".into(), "abc".into())])
+        .await
+        .unwrap();
+    assert!(client.hincrbyfloat("h", "y", 1.0).await.is_err());
+}
+
+#[tokio::test]
+async fn string_append_range() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(5, client.append("s", "Hello".into()).await.unwrap());
+    assert_eq!(11, client.append("s", " World".into()).await.unwrap());
+    assert_eq!(11, client.strlen("s").await.unwrap());
+    assert_eq!(0, client.strlen("missing").await.unwrap());
+
+    assert_eq!(
+        &b"Hello"[..],
+        &client.getrange("s", 0, 4).await.unwrap()[..]
+    );
+    assert_eq!(
+        &b"rld"[..],
+        &client.getrange("s", -3, -1).await.unwrap()[..]
+    );
+    assert_eq!(&b"H"[..], &client.getrange("s", 0, -100).await.unwrap()[..]);
+    assert!(client.getrange("s", 5, 3).await.unwrap().is_empty());
+    assert!(client.getrange("missing", 0, -1).await.unwrap().is_empty());
+
+    assert_eq!(11, client.setrange("s", 6, "Redis".into()).await.unwrap());
+    assert_eq!(
+        &b"Hello Redis"[..],
+        &client.get("s").await.unwrap().unwrap()[..]
+    );
+
+    // Writing past the end pads with zero bytes
+    assert_eq!(5, client.setrange("padded", 3, "ab".into()).await.unwrap());
+    assert_eq!(
+        &b"\0\0\0ab"[..],
+        &client.get("padded").await.unwrap().unwrap()[..]
+    );
+
+    // An empty value does not create the key
+    assert_eq!(0, client.setrange("empty", 10, Bytes::new()).await.unwrap());
+    assert!(client.get("empty").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn multi_key_get_set() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let pairs = vec![("a".into(), "1".into()), ("b".into(), "2".into())];
+    client.mset(pairs).await.unwrap();
+    client
+        .hset("h", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+
+    let keys = vec!["a".into(), "missing".into(), "h".into(), "b".into()];
+    let values = client.mget(keys).await.unwrap();
+    assert_eq!(vec![Some("1".into()), None, None, Some("2".into())], values);
+
+    // Nothing is set if one of the keys exists
+    let pairs = vec![("c".into(), "3".into()), ("a".into(), "4".into())];
+    assert!(!client.msetnx(pairs).await.unwrap());
+    assert!(client.get("c").await.unwrap().is_none());
+    assert_eq!(b"1", &client.get("a").await.unwrap().unwrap()[..]);
+
+    let pairs = vec![("c".into(), "3".into()), ("d".into(), "4".into())];
+    assert!(client.msetnx(pairs).await.unwrap());
+    let values = client.mget(vec!["c".into(), "d".into()]).await.unwrap();
+    assert_eq!(vec![Some("3".into()), Some("4".into())], values);
+
+    // Existing values are replaced whatever their type
+    client.mset(vec![("h".into(), "5".into())]).await.unwrap();
+    assert_eq!(b"5", &client.get("h").await.unwrap().unwrap()[..]);
+}
+
+#[tokio::test]
+async fn exists_counts_duplicates() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("a", "1".into()).await.unwrap();
+    client.sadd("s", vec!["x".into()]).await.unwrap();
+
+    assert_eq!(0, client.exists(vec!["missing".into()]).await.unwrap());
+    let keys = vec!["a".into(), "s".into(), "missing".into(), "a".into()];
+    assert_eq!(3, client.exists(keys).await.unwrap());
+
+    client.del(vec!["a".into()]).await.unwrap();
+    assert_eq!(0, client.exists(vec!["a".into()]).await.unwrap());
+}
+
+#[tokio::test]
+async fn ttl_and_persist() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(-2, client.ttl("missing").await.unwrap());
+    assert_eq!(-2, client.pttl("missing").await.unwrap());
+
+    client.set("plain", "v".into()).await.unwrap();
+    assert_eq!(-1, client.ttl("plain").await.unwrap());
+    assert!(!client.persist("plain").await.unwrap());
+
+    let expiration = Duration::from_secs(100);
+    client
+        .set_expires("volatile", "v".into(), expiration)
+        .await
+        .unwrap();
+    assert_eq!(100, client.ttl("volatile").await.unwrap());
+    let pttl = client.pttl("volatile").await.unwrap();
+    assert!(pttl > 99_000 && pttl <= 100_000, "got {}", pttl);
+
+    assert!(client.persist("volatile").await.unwrap());
+    assert_eq!(-1, client.ttl("volatile").await.unwrap());
+
+    // A persisted key is not purged when its former expiration passes
+    let expiration = Duration::from_millis(50);
+    client
+        .set_expires("short", "v".into(), expiration)
+        .await
+        .unwrap();
+    assert!(client.persist("short").await.unwrap());
+    time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(b"v", &client.get("short").await.unwrap().unwrap()[..]);
+}
+
+#[tokio::test]
+async fn expire_with_options() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(!client.expire("missing", 100, None).await.unwrap());
+
+    client.set("key", "v".into()).await.unwrap();
+
+    // XX and GT need an existing expiration, LT treats none as infinite
+    assert!(!client
+        .expire("key", 100, Some(ExpireOption::Xx))
+        .await
+        .unwrap());
+    assert!(!client
+        .expire("key", 100, Some(ExpireOption::Gt))
+        .await
+        .unwrap());
+    assert!(client
+        .expire("key", 200, Some(ExpireOption::Lt))
+        .await
+        .unwrap());
+    assert_eq!(200, client.ttl("key").await.unwrap());
+
+    assert!(!client
+        .expire("key", 100, Some(ExpireOption::Nx))
+        .await
+        .unwrap());
+    assert!(!client
+        .expire("key", 100, Some(ExpireOption::Gt))
+        .await
+        .unwrap());
+    assert!(client
+        .pexpire("key", 300_000, Some(ExpireOption::Gt))
+        .await
+        .unwrap());
+    assert_eq!(300, client.ttl("key").await.unwrap());
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    assert!(client.expireat("key", now + 1000, None).await.unwrap());
+    let ttl = client.ttl("key").await.unwrap();
+    assert!(ttl > 990 && ttl <= 1000, "got {}", ttl);
+
+    // Shortening the expiration reschedules the purge of the key
+    assert!(client.pexpire("key", 50, None).await.unwrap());
+    time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(None, client.get("key").await.unwrap());
+
+    // A time in the past deletes the key right away
+    client.set("key", "v".into()).await.unwrap();
+    assert!(client.pexpireat("key", 1, None).await.unwrap());
+    assert_eq!(None, client.get("key").await.unwrap());
+
+    client.set("key", "v".into()).await.unwrap();
+    assert!(client.expire("key", -1, None).await.unwrap());
+    assert_eq!(-2, client.ttl("key").await.unwrap());
+
+    let err = client
+        .expire("key", i64::MAX, None)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert_eq!("ERR invalid expire time in 'expire' command", err);
+}
+
+#[tokio::test]
+async fn keys_glob_pattern() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for key in &["hello", "hallo", "hxllo", "hllo", "heeeello", "h*llo"] {
+        client.set(key, "v".into()).await.unwrap();
+    }
+
+    let all = vec!["h*llo", "hallo", "heeeello", "hello", "hllo", "hxllo"];
+    let cases = vec![
+        ("*", all.clone()),
+        ("h?llo", vec!["h*llo", "hallo", "hello", "hxllo"]),
+        ("h*llo", all),
+        ("h[ae]llo", vec!["hallo", "hello"]),
+        ("h[^ae]llo", vec!["h*llo", "hxllo"]),
+        ("h[a-e]llo", vec!["hallo", "hello"]),
+        ("h\\*llo", vec!["h*llo"]),
+        ("nothing*", vec![]),
+    ];
+
+    for (pattern, expected) in cases {
+        let mut keys = client.keys(pattern).await.unwrap();
+        keys.sort();
+        assert_eq!(expected, keys, "pattern {}", pattern);
+    }
+}
+
+#[tokio::test]
+async fn scan_keys_incrementally() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for i in 0..100 {
+        client.set(&format!("key:{}", i), "v".into()).await.unwrap();
+    }
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+
+    // Each page visits about `count` keys, until the cursor wraps to 0
+    let mut keys = HashSet::new();
+    let mut cursor = 0;
+    let mut pages = 0;
+    loop {
+        let (next, page) = client.scan(cursor, None, Some(10), None).await.unwrap();
+        assert!(page.len() <= 10, "got {} keys", page.len());
+
+        // Keys removed during the iteration do not prevent the others from
+        // being returned
+        if pages == 3 {
+            client.del(vec!["key:42".into()]).await.unwrap();
+            client.set("added", "v".into()).await.unwrap();
+        }
+
+        keys.extend(page);
+        pages += 1;
+
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    assert!(pages >= 10, "got {} pages", pages);
+
+    for i in 0..100 {
+        if i != 42 {
+            assert!(keys.contains(&format!("key:{}", i)), "missing key:{}", i);
+        }
+    }
+    assert!(keys.contains("list"));
+
+    let keys = client.scan_iter(Some("key:1*"), None, None);
+    let matched: Vec<String> = keys.map(|key| key.unwrap()).collect().await;
+    let matched: HashSet<String> = matched.into_iter().collect();
+    assert_eq!(11, matched.len());
+
+    let keys = client.scan_iter(None, Some(1000), Some("list"));
+    let lists: Vec<String> = keys.map(|key| key.unwrap()).collect().await;
+    assert_eq!(vec!["list".to_string()], lists);
+
+    let err = client.scan(0, None, Some(0), None).await.unwrap_err();
+    assert_eq!("ERR syntax error", err.to_string());
+}
+
+#[tokio::test]
+async fn scan_collections_incrementally() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let fields = (0..50)
+        .map(|i| (format!("f{}", i), Bytes::from(format!("v{}", i))))
+        .collect();
+    client.hset("hash", fields).await.unwrap();
+
+    let mut seen = HashSet::new();
+    let mut cursor = 0;
+    loop {
+        let (next, page) = client.hscan("hash", cursor, None, Some(5)).await.unwrap();
+        for (field, value) in page {
+            assert_eq!(format!("v{}", &field[1..]).as_bytes(), &value[..]);
+            seen.insert(field);
+        }
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    assert_eq!(50, seen.len());
+
+    let members = (0..50).map(|i| Bytes::from(format!("m{}", i))).collect();
+    client.sadd("set", members).await.unwrap();
+
+    let mut seen = HashSet::new();
+    let mut cursor = 0;
+    loop {
+        let (next, page) = client
+            .sscan("set", cursor, Some("m1*"), None)
+            .await
+            .unwrap();
+        seen.extend(page);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    assert_eq!(11, seen.len());
+
+    client
+        .zadd("zset", vec![(1.5, "a".into()), (2.0, "b".into())])
+        .await
+        .unwrap();
+    let (cursor, mut page) = client.zscan("zset", 0, None, None).await.unwrap();
+    page.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(0, cursor);
+    assert_eq!(vec![("a".into(), 1.5), ("b".into(), 2.0)], page);
+
+    assert_eq!(
+        (0, vec![]),
+        client.sscan("missing", 0, None, None).await.unwrap()
+    );
+
+    let err = client.zscan("set", 0, None, None).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn type_of_values() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("string", "v".into()).await.unwrap();
+    client.rpush("list", vec!["v".into()]).await.unwrap();
+    client
+        .hset("hash", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+    client.sadd("set", vec!["v".into()]).await.unwrap();
+    client.zadd("zset", vec![(1.0, "v".into())]).await.unwrap();
+    client
+        .xadd("stream", "*", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+
+    for key in &["string", "list", "hash", "set", "zset", "stream"] {
+        assert_eq!(*key, client.key_type(key).await.unwrap());
+    }
+    assert_eq!("none", client.key_type("missing").await.unwrap());
+}
+
+#[tokio::test]
+async fn rename_keeps_expiration() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let err = client.rename("missing", "other").await.unwrap_err();
+    assert_eq!("ERR no such key", err.to_string());
+
+    client
+        .set_expires("old", "v".into(), Duration::from_millis(100))
+        .await
+        .unwrap();
+    client.rpush("new", vec!["a".into()]).await.unwrap();
+    client.rename("old", "new").await.unwrap();
+
+    assert_eq!(None, client.get("old").await.unwrap());
+    assert_eq!(b"v", &client.get("new").await.unwrap().unwrap()[..]);
+    let pttl = client.pttl("new").await.unwrap();
+    assert!(pttl > 0 && pttl <= 100, "got {}", pttl);
+
+    // The renamed key is purged when the original expiration passes
+    time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(None, client.get("new").await.unwrap());
+
+    client.set("a", "1".into()).await.unwrap();
+    client.set("b", "2".into()).await.unwrap();
+    assert!(!client.renamenx("a", "b").await.unwrap());
+    assert!(!client.renamenx("a", "a").await.unwrap());
+    assert!(client.renamenx("a", "c").await.unwrap());
+    assert_eq!(b"1", &client.get("c").await.unwrap().unwrap()[..]);
+
+    client.rename("c", "c").await.unwrap();
+    assert_eq!(b"1", &client.get("c").await.unwrap().unwrap()[..]);
+
+    let (cursor, mut keys) = client.scan(0, None, Some(100), None).await.unwrap();
+    keys.sort();
+    assert_eq!(0, cursor);
+    assert_eq!(vec!["b", "c"], keys);
+}
+
+#[tokio::test]
+async fn dbsize_and_randomkey() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.dbsize().await.unwrap());
+    assert_eq!(None, client.randomkey().await.unwrap());
+
+    for key in &["a", "b", "c"] {
+        client.set(key, "v".into()).await.unwrap();
+    }
+    assert_eq!(3, client.dbsize().await.unwrap());
+
+    // Every key is eventually picked
+    let mut picked = HashSet::new();
+    for _ in 0..200 {
+        picked.insert(client.randomkey().await.unwrap().unwrap());
+    }
+    assert_eq!(3, picked.len());
+
+    client.del(vec!["a".into()]).await.unwrap();
+    assert_eq!(2, client.dbsize().await.unwrap());
+}
+
+#[tokio::test]
+async fn flush_keyspace() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("a", "v".into()).await.unwrap();
+    client
+        .set_expires("b", "v".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    client.flushdb(false).await.unwrap();
+    assert_eq!(0, client.dbsize().await.unwrap());
+    assert_eq!((0, vec![]), client.scan(0, None, None, None).await.unwrap());
+
+    // The key space is usable right after an asynchronous flush
+    for i in 0..1000 {
+        client.set(&format!("key:{}", i), "v".into()).await.unwrap();
+    }
+    client.flushall(true).await.unwrap();
+    assert_eq!(0, client.dbsize().await.unwrap());
+
+    client.set("a", "v".into()).await.unwrap();
+    assert_eq!(b"v", &client.get("a").await.unwrap().unwrap()[..]);
+    assert_eq!(1, client.dbsize().await.unwrap());
+}
+
+#[tokio::test]
+async fn set_options() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(!client
+        .set_if("key", "a".into(), SetCondition::Xx, None)
+        .await
+        .unwrap());
+    assert_eq!(None, client.get("key").await.unwrap());
+
+    let expiration = Some(Duration::from_secs(100));
+    assert!(client
+        .set_if("key", "a".into(), SetCondition::Nx, expiration)
+        .await
+        .unwrap());
+    assert!(!client
+        .set_if("key", "b".into(), SetCondition::Nx, None)
+        .await
+        .unwrap());
+    assert_eq!(b"a", &client.get("key").await.unwrap().unwrap()[..]);
+    assert_eq!(100, client.ttl("key").await.unwrap());
+
+    // KEEPTTL retains the expiration, a plain SET discards it
+    client.set_keepttl("key", "b".into()).await.unwrap();
+    assert_eq!(100, client.ttl("key").await.unwrap());
+    assert_eq!(
+        b"b",
+        &client.set_get("key", "c".into()).await.unwrap().unwrap()[..]
+    );
+    assert_eq!(-1, client.ttl("key").await.unwrap());
+    assert_eq!(None, client.set_get("other", "v".into()).await.unwrap());
+
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    let err = client.set_get("list", "v".into()).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert_eq!("list", client.key_type("list").await.unwrap());
+
+    let when = SystemTime::now() + Duration::from_secs(1000);
+    client
+        .set_expires_at("key", "v".into(), when)
+        .await
+        .unwrap();
+    let ttl = client.ttl("key").await.unwrap();
+    assert!(ttl > 990 && ttl <= 1000, "got {}", ttl);
+
+    // A time in the past deletes the key
+    let when = SystemTime::now() - Duration::from_secs(1000);
+    client
+        .set_expires_at("key", "v".into(), when)
+        .await
+        .unwrap();
+    assert_eq!(None, client.get("key").await.unwrap());
+}
+
+#[tokio::test]
+async fn legacy_set_commands() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(client.setnx("key", "a".into()).await.unwrap());
+    assert!(!client.setnx("key", "b".into()).await.unwrap());
+    assert_eq!(b"a", &client.get("key").await.unwrap().unwrap()[..]);
+
+    client.setex("key", 100, "b".into()).await.unwrap();
+    assert_eq!(b"b", &client.get("key").await.unwrap().unwrap()[..]);
+    assert_eq!(100, client.ttl("key").await.unwrap());
+
+    client.psetex("key", 50, "c".into()).await.unwrap();
+    let pttl = client.pttl("key").await.unwrap();
+    assert!(pttl > 0 && pttl <= 50, "got {}", pttl);
+    time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(None, client.get("key").await.unwrap());
+
+    let err = client.setex("key", 0, "v".into()).await.unwrap_err();
+    assert_eq!(
+        "ERR invalid expire time in 'setex' command",
+        err.to_string()
+    );
+    let err = client.psetex("key", -5, "v".into()).await.unwrap_err();
+    assert_eq!(
+        "ERR invalid expire time in 'psetex' command",
+        err.to_string()
+    );
+    assert_eq!(None, client.get("key").await.unwrap());
+}
+
+#[tokio::test]
+async fn getdel_and_getex() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.getdel("key").await.unwrap());
+
+    client.set("key", "v".into()).await.unwrap();
+    assert_eq!(b"v", &client.getdel("key").await.unwrap().unwrap()[..]);
+    assert_eq!(None, client.get("key").await.unwrap());
+
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    let err = client.getdel("list").await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert_eq!("list", client.key_type("list").await.unwrap());
+
+    client.set("key", "v".into()).await.unwrap();
+    let expiration = Some(Duration::from_secs(100));
+    let value = client.getex("key", expiration).await.unwrap();
+    assert_eq!(b"v", &value.unwrap()[..]);
+    assert_eq!(100, client.ttl("key").await.unwrap());
+
+    client.getex("key", None).await.unwrap();
+    assert_eq!(-1, client.ttl("key").await.unwrap());
+
+    // Shortening the expiration reschedules the purge of the key
+    let expiration = Some(Duration::from_millis(50));
+    client.getex("key", expiration).await.unwrap();
+    time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(None, client.get("key").await.unwrap());
+    assert_eq!(None, client.getex("key", None).await.unwrap());
+}
+
+#[tokio::test]
+async fn copy_values() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(!client.copy("missing", "dest", false).await.unwrap());
+
+    client
+        .set_expires("src", "v".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    assert!(client.copy("src", "dest", false).await.unwrap());
+    assert_eq!(b"v", &client.get("dest").await.unwrap().unwrap()[..]);
+    assert_eq!(100, client.ttl("dest").await.unwrap());
+
+    // Collections are copied, not shared
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    assert!(!client.copy("list", "dest", false).await.unwrap());
+    assert!(client.copy("list", "dest", true).await.unwrap());
+    assert_eq!(-1, client.ttl("dest").await.unwrap());
+    client.rpop("list").await.unwrap();
+    assert_eq!(2, client.llen("dest").await.unwrap());
+    assert_eq!(1, client.llen("list").await.unwrap());
+
+    let err = client.copy("src", "src", false).await.unwrap_err();
+    assert_eq!(
+        "ERR source and destination objects are the same",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn unlink_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("small", "v".into()).await.unwrap();
+    let members = (0..1000).map(|i| i.to_string().into()).collect();
+    client.sadd("large", members).await.unwrap();
+
+    let removed = client
+        .unlink(vec!["small".into(), "large".into(), "missing".into()])
+        .await
+        .unwrap();
+    assert_eq!(2, removed);
+    assert_eq!(
+        0,
+        client
+            .exists(vec!["small".into(), "large".into()])
+            .await
+            .unwrap()
+    );
+    assert_eq!(0, client.scard("large").await.unwrap());
+}
+
+#[tokio::test]
+async fn touch_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    client.sadd("set", vec!["a".into()]).await.unwrap();
+
+    let touched = client
+        .touch(vec![
+            "foo".into(),
+            "set".into(),
+            "missing".into(),
+            "foo".into(),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(3, touched);
+}
+
+#[tokio::test]
+async fn sort_values() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(client
+        .sort("missing", false, false, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    client
+        .rpush(
+            "list",
+            vec!["10".into(), "2".into(), "-1.5".into(), "2".into()],
+        )
+        .await
+        .unwrap();
+    let sorted = client.sort("list", false, false, None).await.unwrap();
+    assert_eq!(vec!["-1.5", "2", "2", "10"], sorted);
+    let sorted = client
+        .sort("list", false, true, Some((1, 2)))
+        .await
+        .unwrap();
+    assert_eq!(vec!["2", "2"], sorted);
+    let sorted = client.sort("list", true, false, None).await.unwrap();
+    assert_eq!(vec!["-1.5", "10", "2", "2"], sorted);
+
+    client
+        .sadd("set", vec!["b".into(), "c".into(), "a".into()])
+        .await
+        .unwrap();
+    let err = client.sort("set", false, false, None).await.unwrap_err();
+    assert_eq!(
+        "ERR One or more scores can't be converted into double",
+        err.to_string()
+    );
+    let sorted = client
+        .sort("set", true, true, Some((-5, -1)))
+        .await
+        .unwrap();
+    assert_eq!(vec!["c", "b", "a"], sorted);
+
+    // STORE replaces the destination with a list
+    client.set("dest", "v".into()).await.unwrap();
+    assert_eq!(
+        3,
+        client
+            .sort_store("set", "dest", true, false, None)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        vec!["a", "b", "c"],
+        client.lrange("dest", 0, -1).await.unwrap()
+    );
+
+    assert_eq!(
+        0,
+        client
+            .sort_store("missing", "dest", false, false, None)
+            .await
+            .unwrap()
+    );
+    assert_eq!(0, client.exists(vec!["dest".into()]).await.unwrap());
+
+    client.set("string", "v".into()).await.unwrap();
+    let err = client.sort("string", false, false, None).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn lpos_values() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(
+        None,
+        client
+            .lpos("missing", "a".into(), None, None)
+            .await
+            .unwrap()
+    );
+
+    let values = vec!["a", "b", "c", "1", "2", "3", "c", "c"];
+    client
+        .rpush("list", values.into_iter().map(Bytes::from).collect())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        Some(2),
+        client.lpos("list", "c".into(), None, None).await.unwrap()
+    );
+    assert_eq!(
+        Some(6),
+        client
+            .lpos("list", "c".into(), Some(2), None)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        Some(7),
+        client
+            .lpos("list", "c".into(), Some(-1), None)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        client
+            .lpos("list", "c".into(), Some(4), None)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        client
+            .lpos("list", "c".into(), None, Some(2))
+            .await
+            .unwrap()
+    );
+
+    let all = client
+        .lpos_count("list", "c".into(), 0, None, None)
+        .await
+        .unwrap();
+    assert_eq!(vec![2, 6, 7], all);
+    let last = client
+        .lpos_count("list", "c".into(), 2, Some(-1), None)
+        .await
+        .unwrap();
+    assert_eq!(vec![7, 6], last);
+    let none = client
+        .lpos_count("list", "x".into(), 1, None, None)
+        .await
+        .unwrap();
+    assert!(none.is_empty());
+
+    let err = client
+        .lpos("list", "c".into(), Some(0), None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("ERR RANK can't be zero"));
+}
+
+#[tokio::test]
+async fn sorted_set_range_queries() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = vec![
+        (1.0, "a".into()),
+        (2.0, "b".into()),
+        (2.0, "c".into()),
+        (3.5, "d".into()),
+    ];
+    client.zadd("scores", members).await.unwrap();
+
+    let range = client
+        .zrangebyscore("scores", "2", "+inf", None)
+        .await
+        .unwrap();
+    assert_eq!(vec!["b", "c", "d"], range);
+    let range = client
+        .zrangebyscore("scores", "(1", "(3.5", None)
+        .await
+        .unwrap();
+    assert_eq!(vec!["b", "c"], range);
+    let range = client
+        .zrangebyscore("scores", "-inf", "+inf", Some((1, 2)))
+        .await
+        .unwrap();
+    assert_eq!(vec!["b", "c"], range);
+    let range = client
+        .zrangebyscore_withscores("scores", "3", "inf", Some((0, -1)))
+        .await
+        .unwrap();
+    assert_eq!(vec![(Bytes::from("d"), 3.5)], range);
+    assert!(client
+        .zrangebyscore("scores", "5", "1", None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(4, client.zcount("scores", "-inf", "+inf").await.unwrap());
+    assert_eq!(1, client.zcount("scores", "(2", "4").await.unwrap());
+    assert_eq!(0, client.zcount("missing", "-inf", "+inf").await.unwrap());
+
+    let err = client.zcount("scores", "one", "2").await.unwrap_err();
+    assert_eq!("ERR min or max is not a float", err.to_string());
+
+    let members = ["apple", "banana", "cherry", "date"]
+        .iter()
+        .map(|member| (0.0, Bytes::from(*member)))
+        .collect();
+    client.zadd("fruits", members).await.unwrap();
+
+    let range = client
+        .zrangebylex("fruits", "[b".into(), "(d".into(), None)
+        .await
+        .unwrap();
+    assert_eq!(vec!["banana", "cherry"], range);
+    let range = client
+        .zrangebylex("fruits", "-".into(), "+".into(), Some((2, 5)))
+        .await
+        .unwrap();
+    assert_eq!(vec!["cherry", "date"], range);
+    let range = client
+        .zrangebylex("fruits", "(banana".into(), "[cherry".into(), None)
+        .await
+        .unwrap();
+    assert_eq!(vec!["cherry"], range);
+
+    let err = client
+        .zrangebylex("fruits", "b".into(), "+".into(), None)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR min or max not valid string range item",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn sorted_set_pop() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let mut writer = client::connect(addr).await.unwrap();
+
+    assert!(client.zpopmin("missing", None).await.unwrap().is_empty());
+
+    let members = vec![(1.0, "a".into()), (2.0, "b".into()), (3.0, "c".into())];
+    client.zadd("zset", members).await.unwrap();
+
+    let popped = client.zpopmin("zset", None).await.unwrap();
+    assert_eq!(vec![(Bytes::from("a"), 1.0)], popped);
+    let popped = client.zpopmax("zset", Some(5)).await.unwrap();
+    assert_eq!(
+        vec![(Bytes::from("c"), 3.0), (Bytes::from("b"), 2.0)],
+        popped
+    );
+    assert_eq!(0, client.exists(vec!["zset".into()]).await.unwrap());
+
+    // The timeout elapses without any member
+    let popped = client
+        .bzpopmin(vec!["zset".into()], Duration::from_millis(10))
+        .await
+        .unwrap();
+    assert_eq!(None, popped);
+    let err = client
+        .bzpopmin(vec!["zset".into()], Duration::MAX)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR timeout is out of range", err.to_string());
+
+    let blocked = tokio::spawn(async move {
+        client
+            .bzpopmax(vec!["other".into(), "zset".into()], Duration::from_secs(0))
+            .await
+            .unwrap()
+    });
+
+    time::sleep(Duration::from_millis(50)).await;
+
+    let members = vec![(1.0, "x".into()), (5.0, "y".into())];
+    writer.zadd("zset", members).await.unwrap();
+    assert_eq!(
+        Some(("zset".into(), "y".into(), 5.0)),
+        blocked.await.unwrap()
+    );
+    assert_eq!(vec!["x"], writer.zrange("zset", 0, -1).await.unwrap());
+}
+
+#[tokio::test]
+async fn sorted_set_updates() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(2.5, client.zincrby("zset", 2.5, "a".into()).await.unwrap());
+    assert_eq!(1.5, client.zincrby("zset", -1.0, "a".into()).await.unwrap());
+
+    let added = client
+        .zadd_with(
+            "zset",
+            vec![(5.0, "a".into()), (1.0, "b".into())],
+            &[ZAddOption::Nx],
+        )
+        .await
+        .unwrap();
+    assert_eq!(1, added);
+    assert_eq!(Some(1.5), client.zscore("zset", "a".into()).await.unwrap());
+
+    let changed = client
+        .zadd_with(
+            "zset",
+            vec![(3.0, "a".into()), (0.5, "b".into()), (1.0, "c".into())],
+            &[ZAddOption::Xx, ZAddOption::Gt, ZAddOption::Ch],
+        )
+        .await
+        .unwrap();
+    assert_eq!(1, changed);
+    assert_eq!(Some(3.0), client.zscore("zset", "a".into()).await.unwrap());
+    assert_eq!(Some(1.0), client.zscore("zset", "b".into()).await.unwrap());
+    assert_eq!(None, client.zscore("zset", "c".into()).await.unwrap());
+
+    let added = client
+        .zadd_with("zset", vec![(0.0, "b".into())], &[ZAddOption::Lt])
+        .await
+        .unwrap();
+    assert_eq!(0, added);
+    assert_eq!(Some(0.0), client.zscore("zset", "b".into()).await.unwrap());
+
+    // `XX` does not create the key
+    client
+        .zadd_with("missing", vec![(1.0, "a".into())], &[ZAddOption::Xx])
+        .await
+        .unwrap();
+    assert_eq!(0, client.exists(vec!["missing".into()]).await.unwrap());
+
+    let err = client
+        .zadd_with(
+            "zset",
+            vec![(1.0, "a".into())],
+            &[ZAddOption::Nx, ZAddOption::Gt],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR GT, LT, and/or NX options at the same time are not compatible",
+        err.to_string()
+    );
+
+    client
+        .zadd("inf", vec![(f64::INFINITY, "a".into())])
+        .await
+        .unwrap();
+    let err = client
+        .zincrby("inf", f64::NEG_INFINITY, "a".into())
+        .await
+        .unwrap_err();
+    assert_eq!("ERR resulting score is not a number (NaN)", err.to_string());
+}
+
+#[tokio::test]
+async fn geo_commands() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let added = client
+        .geoadd(
+            "sicily",
+            vec![
+                (13.361389, 38.115556, "Palermo".into()),
+                (15.087269, 37.502669, "Catania".into()),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, added);
+
+    // Positions are decoded from the geohash
+    let positions = client
+        .geopos("sicily", vec!["Palermo".into(), "Rome".into()])
+        .await
+        .unwrap();
+    let (lon, lat) = positions[0].unwrap();
+    assert!((lon - 13.361389).abs() < 1e-5);
+    assert!((lat - 38.115556).abs() < 1e-5);
+    assert_eq!(None, positions[1]);
+
+    let distance = client
+        .geodist("sicily", "Palermo".into(), "Catania".into(), GeoUnit::Km)
+        .await
+        .unwrap();
+    assert_eq!(Some(166.2742), distance);
+    let distance = client
+        .geodist("sicily", "Palermo".into(), "Rome".into(), GeoUnit::M)
+        .await
+        .unwrap();
+    assert_eq!(None, distance);
+
+    let origin = GeoOrigin::LonLat(15.0, 37.0);
+    let members = client
+        .geosearch(
+            "sicily",
+            origin.clone(),
+            GeoShape::Radius(200.0, GeoUnit::Km),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec!["Catania", "Palermo"], members);
+
+    let members = client
+        .geosearch(
+            "sicily",
+            origin.clone(),
+            GeoShape::Radius(100.0, GeoUnit::Km),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec!["Catania"], members);
+
+    let members = client
+        .geosearch(
+            "sicily",
+            origin.clone(),
+            GeoShape::Box(400.0, 400.0, GeoUnit::Km),
+            true,
+            Some(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec!["Palermo"], members);
+
+    let members = client
+        .geosearch_detailed(
+            "sicily",
+            GeoOrigin::Member("Palermo".into()),
+            GeoShape::Radius(10.0, GeoUnit::Km),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(1, members.len());
+    assert_eq!("Palermo", members[0].0);
+    assert_eq!(0.0, members[0].1);
+
+    let err = client
+        .geoadd("sicily", vec![(0.0, 86.0, "North".into())])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR invalid longitude,latitude pair 0.000000,86.000000",
+        err.to_string()
+    );
+
+    let err = client
+        .geosearch(
+            "sicily",
+            GeoOrigin::Member("Rome".into()),
+            GeoShape::Radius(10.0, GeoUnit::Km),
+            false,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR could not decode requested zset member",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn lcs_strings() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("key1", "ohmytext".into()).await.unwrap();
+    client.set("key2", "mynewtext".into()).await.unwrap();
+
+    assert_eq!("mytext", client.lcs("key1", "key2").await.unwrap());
+    assert_eq!(6, client.lcs_len("key1", "key2").await.unwrap());
+
+    let (matches, len) = client.lcs_idx("key1", "key2", 0).await.unwrap();
+    assert_eq!(
+        vec![
+            LcsMatch {
+                first: (4, 7),
+                second: (5, 8)
+            },
+            LcsMatch {
+                first: (2, 3),
+                second: (0, 1)
+            },
+        ],
+        matches
+    );
+    assert_eq!(6, len);
+
+    let (matches, len) = client.lcs_idx("key1", "key2", 4).await.unwrap();
+    assert_eq!(1, matches.len());
+    assert_eq!(6, len);
+
+    // Missing keys are empty strings
+    assert_eq!("", client.lcs("key1", "missing").await.unwrap());
+
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    let err = client.lcs("key1", "list").await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+
+    // The table of the subsequence is bounded
+    let long = "a".repeat(12_000);
+    client.set("long1", long.clone().into()).await.unwrap();
+    client.set("long2", long.into()).await.unwrap();
+    let err = client.lcs("long1", "long2").await.unwrap_err();
+    assert_eq!("ERR String too long for LCS", err.to_string());
+    let err = client.lcs_idx("long1", "long2", 0).await.unwrap_err();
+    assert_eq!("ERR String too long for LCS", err.to_string());
+
+    // Its length only keeps two rows of the table
+    assert_eq!(12_000, client.lcs_len("long1", "long2").await.unwrap());
+}
+
+#[tokio::test]
+async fn set_membership_and_intersection() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .sadd("set1", vec!["a".into(), "b".into(), "c".into(), "d".into()])
+        .await
+        .unwrap();
+    client
+        .sadd("set2", vec!["b".into(), "c".into(), "d".into(), "e".into()])
+        .await
+        .unwrap();
+
+    let is_member = client
+        .smismember("set1", vec!["a".into(), "e".into(), "d".into()])
+        .await
+        .unwrap();
+    assert_eq!(vec![true, false, true], is_member);
+    let is_member = client
+        .smismember("missing", vec!["a".into()])
+        .await
+        .unwrap();
+    assert_eq!(vec![false], is_member);
+
+    let keys = vec!["set1".to_string(), "set2".to_string()];
+    assert_eq!(3, client.sintercard(keys.clone(), None).await.unwrap());
+    assert_eq!(2, client.sintercard(keys.clone(), Some(2)).await.unwrap());
+    assert_eq!(3, client.sintercard(keys, Some(0)).await.unwrap());
+
+    let keys = vec!["set1".to_string(), "missing".to_string()];
+    assert_eq!(0, client.sintercard(keys, None).await.unwrap());
+
+    client.set("string", "a".into()).await.unwrap();
+    let keys = vec!["missing".to_string(), "string".to_string()];
+    let err = client.sintercard(keys, None).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn set_random_members() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members: HashSet<Bytes> = vec!["a".into(), "b".into(), "c".into()]
+        .into_iter()
+        .collect();
+    client
+        .sadd("set", members.iter().cloned().collect())
+        .await
+        .unwrap();
+
+    let member = client.srandmember("set").await.unwrap().unwrap();
+    assert!(members.contains(&member));
+
+    // A positive count returns distinct members
+    let picked = client.srandmember_count("set", 2).await.unwrap();
+    assert_eq!(2, picked.iter().collect::<HashSet<_>>().len());
+    let picked = client.srandmember_count("set", 10).await.unwrap();
+    assert_eq!(members, picked.into_iter().collect());
+
+    // A negative count may repeat members
+    let picked = client.srandmember_count("set", -10).await.unwrap();
+    assert_eq!(10, picked.len());
+    assert!(picked.iter().all(|member| members.contains(member)));
+    assert_eq!(3, client.scard("set").await.unwrap());
+
+    let popped = client.spop("set").await.unwrap().unwrap();
+    assert!(members.contains(&popped));
+    assert!(!client.sismember("set", popped).await.unwrap());
+
+    let popped = client.spop_count("set", 5).await.unwrap();
+    assert_eq!(2, popped.len());
+
+    // The key is removed along with its last member
+    assert_eq!(0, client.exists(vec!["set".into()]).await.unwrap());
+    assert_eq!(None, client.spop("set").await.unwrap());
+    assert_eq!(None, client.srandmember("set").await.unwrap());
+    assert!(client
+        .srandmember_count("set", -3)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Replies too large to be allocated are rejected
+    client.sadd("set", vec!["a".into()]).await.unwrap();
+    for count in [-100_000_000_000, i64::MIN] {
+        let err = client.srandmember_count("set", count).await.unwrap_err();
+        assert_eq!("ERR value is out of range", err.to_string());
+    }
+    assert_eq!(
+        vec!["a"],
+        client.srandmember_count("set", -1).await.unwrap()
+    );
+
+    // Counts over `i64::MAX` pop distinct members rather than repeating them
+    client
+        .sadd("set", vec!["b".into(), "c".into()])
+        .await
+        .unwrap();
+    let popped = client.spop_count("set", u64::MAX).await.unwrap();
+    assert_eq!(members, popped.into_iter().collect());
+    assert_eq!(0, client.exists(vec!["set".into()]).await.unwrap());
+}
+
+#[tokio::test]
+async fn hash_and_sorted_set_random_members() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .hset(
+            "hash",
+            vec![("a".into(), "1".into()), ("b".into(), "2".into())],
+        )
+        .await
+        .unwrap();
+
+    let field = client.hrandfield("hash").await.unwrap().unwrap();
+    assert!(field == "a" || field == "b");
+
+    let mut fields = client.hrandfield_count("hash", 5).await.unwrap();
+    fields.sort();
+    assert_eq!(vec!["a", "b"], fields);
+
+    let fields = client.hrandfield_count("hash", -5).await.unwrap();
+    assert_eq!(5, fields.len());
+
+    let mut fields = client.hrandfield_withvalues("hash", 2).await.unwrap();
+    fields.sort();
+    assert_eq!(
+        vec![
+            ("a".to_string(), Bytes::from("1")),
+            ("b".to_string(), Bytes::from("2"))
+        ],
+        fields
+    );
+
+    assert_eq!(None, client.hrandfield("missing").await.unwrap());
+
+    client
+        .zadd("zset", vec![(1.0, "a".into()), (2.0, "b".into())])
+        .await
+        .unwrap();
+
+    let member = client.zrandmember("zset").await.unwrap().unwrap();
+    assert!(member == "a" || member == "b");
+
+    let members = client.zrandmember_count("zset", 1).await.unwrap();
+    assert_eq!(1, members.len());
+
+    let members = client.zrandmember_count("zset", -4).await.unwrap();
+    assert_eq!(4, members.len());
+
+    let mut members = client.zrandmember_withscores("zset", -10).await.unwrap();
+    assert_eq!(10, members.len());
+    members.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    members.dedup();
+    assert!(members
+        .iter()
+        .all(|member| *member == ("a".into(), 1.0) || *member == ("b".into(), 2.0)));
+
+    assert!(client
+        .zrandmember_count("missing", 3)
+        .await
+        .unwrap()
+        .is_empty());
+    // Replies too large to be allocated are rejected
+    for count in [-100_000_000_000, i64::MIN] {
+        let err = client.hrandfield_count("hash", count).await.unwrap_err();
+        assert_eq!("ERR value is out of range", err.to_string());
+        let err = client.zrandmember_count("zset", count).await.unwrap_err();
+        assert_eq!("ERR value is out of range", err.to_string());
+    }
+    let err = client
+        .zrandmember_withscores("zset", -(i64::MAX / 2) - 1)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR value is out of range", err.to_string());
+    assert_eq!(1, client.hrandfield_count("hash", -1).await.unwrap().len());
+
+    // Field-value pairs count twice
+    for count in [-(i64::MAX / 2) - 1, -(i64::MAX / 2)] {
+        let err = client
+            .hrandfield_withvalues("hash", count)
+            .await
+            .unwrap_err();
+        assert_eq!("ERR value is out of range", err.to_string());
+    }
+    assert_eq!(
+        3,
+        client
+            .hrandfield_withvalues("hash", -3)
+            .await
+            .unwrap()
+            .len()
+    );
+}
+
+#[tokio::test]
+async fn hash_incrby_and_setnx() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(5, client.hincrby("hash", "counter", 5).await.unwrap());
+    assert_eq!(-2, client.hincrby("hash", "counter", -7).await.unwrap());
+    assert_eq!(
+        Some("-2".into()),
+        client.hget("hash", "counter").await.unwrap()
+    );
+
+    assert!(client.hsetnx("hash", "field", "a".into()).await.unwrap());
+    assert!(!client.hsetnx("hash", "field", "b".into()).await.unwrap());
+    assert_eq!(
+        Some("a".into()),
+        client.hget("hash", "field").await.unwrap()
+    );
+
+    let err = client.hincrby("hash", "field", 1).await.unwrap_err();
+    assert_eq!("ERR hash value is not an integer", err.to_string());
+
+    client
+        .hset("hash", vec![("big".into(), i64::MAX.to_string().into())])
+        .await
+        .unwrap();
+    let err = client.hincrby("hash", "big", 1).await.unwrap_err();
+    assert_eq!("ERR increment or decrement would overflow", err.to_string());
+}
+
+#[tokio::test]
+async fn set_algebra() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .sadd("set1", vec!["a".into(), "b".into(), "c".into()])
+        .await
+        .unwrap();
+    client
+        .sadd("set2", vec!["b".into(), "c".into(), "d".into()])
+        .await
+        .unwrap();
+
+    let members = |members: Vec<Bytes>| members.into_iter().collect::<HashSet<_>>();
+    let expected = |expected: &[&'static str]| {
+        expected
+            .iter()
+            .map(|member| Bytes::from(*member))
+            .collect::<HashSet<_>>()
+    };
+    let keys = vec!["set1".to_string(), "set2".to_string()];
+
+    let inter = client.sinter(keys.clone()).await.unwrap();
+    assert_eq!(expected(&["b", "c"]), members(inter));
+    let union = client.sunion(keys.clone()).await.unwrap();
+    assert_eq!(expected(&["a", "b", "c", "d"]), members(union));
+    let diff = client.sdiff(keys.clone()).await.unwrap();
+    assert_eq!(expected(&["a"]), members(diff));
+
+    // Missing keys are empty sets
+    let missing = vec!["set1".to_string(), "missing".to_string()];
+    assert!(client.sinter(missing.clone()).await.unwrap().is_empty());
+    let diff = client.sdiff(missing).await.unwrap();
+    assert_eq!(expected(&["a", "b", "c"]), members(diff));
+
+    assert_eq!(2, client.sinterstore("dest", keys.clone()).await.unwrap());
+    let dest = client.smembers("dest").await.unwrap();
+    assert_eq!(expected(&["b", "c"]), members(dest));
+
+    assert_eq!(4, client.sunionstore("dest", keys.clone()).await.unwrap());
+    assert_eq!(4, client.scard("dest").await.unwrap());
+
+    // The destination may be one of the sources
+    let keys = vec!["dest".to_string(), "set2".to_string()];
+    assert_eq!(1, client.sdiffstore("dest", keys).await.unwrap());
+    let dest = client.smembers("dest").await.unwrap();
+    assert_eq!(expected(&["a"]), members(dest));
+
+    // An empty result removes the destination
+    let keys = vec!["set1".to_string(), "missing".to_string()];
+    assert_eq!(0, client.sinterstore("dest", keys).await.unwrap());
+    assert_eq!(0, client.exists(vec!["dest".into()]).await.unwrap());
+
+    client.set("string", "a".into()).await.unwrap();
+    let keys = vec!["set1".to_string(), "string".to_string()];
+    let err = client.sunion(keys).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+#[tokio::test]
+async fn list_editing() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .rpush("list", vec!["a".into(), "c".into(), "a".into()])
+        .await
+        .unwrap();
+
+    let len = client
+        .linsert("list", InsertPosition::After, "a".into(), "b".into())
+        .await
+        .unwrap();
+    assert_eq!(Some(4), len);
+    let len = client
+        .linsert("list", InsertPosition::Before, "x".into(), "b".into())
+        .await
+        .unwrap();
+    assert_eq!(None, len);
+    let len = client
+        .linsert("missing", InsertPosition::Before, "a".into(), "b".into())
+        .await
+        .unwrap();
+    assert_eq!(Some(0), len);
+    assert_eq!(
+        vec!["a", "b", "c", "a"],
+        client.lrange("list", 0, -1).await.unwrap()
+    );
+
+    client.lset("list", -1, "d".into()).await.unwrap();
+    assert_eq!(
+        vec!["a", "b", "c", "d"],
+        client.lrange("list", 0, -1).await.unwrap()
+    );
+    let err = client.lset("list", 4, "e".into()).await.unwrap_err();
+    assert_eq!("ERR index out of range", err.to_string());
+    let err = client.lset("missing", 0, "e".into()).await.unwrap_err();
+    assert_eq!("ERR no such key", err.to_string());
+
+    client
+        .rpush("list", vec!["a".into(), "b".into(), "a".into()])
+        .await
+        .unwrap();
+    assert_eq!(1, client.lrem("list", -1, "a".into()).await.unwrap());
+    assert_eq!(
+        vec!["a", "b", "c", "d", "a", "b"],
+        client.lrange("list", 0, -1).await.unwrap()
+    );
+    assert_eq!(2, client.lrem("list", 0, "b".into()).await.unwrap());
+    assert_eq!(1, client.lrem("list", 1, "a".into()).await.unwrap());
+    assert_eq!(
+        vec!["c", "d", "a"],
+        client.lrange("list", 0, -1).await.unwrap()
+    );
+
+    client.ltrim("list", 1, -1).await.unwrap();
+    assert_eq!(vec!["d", "a"], client.lrange("list", 0, -1).await.unwrap());
+
+    // An empty range removes the key
+    client.ltrim("list", 5, 10).await.unwrap();
+    assert_eq!(0, client.exists(vec!["list".into()]).await.unwrap());
+}
+
+#[tokio::test]
+async fn multiple_databases() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    client.set("hello", "zero".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    assert_eq!(None, client.get("hello").await.unwrap());
+    client.set("hello", "one".into()).await.unwrap();
+    client.set("world", "one".into()).await.unwrap();
+    assert_eq!(2, client.dbsize().await.unwrap());
+
+    // The selection only applies to the connection that made it
+    assert_eq!(Some("zero".into()), other.get("hello").await.unwrap());
+    assert_eq!(1, other.dbsize().await.unwrap());
+
+    let err = client.select(16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+    assert_eq!(Some("one".into()), client.get("hello").await.unwrap());
+
+    // FLUSHDB only removes the keys of the selected database
+    client.flushdb(false).await.unwrap();
+    assert_eq!(0, client.dbsize().await.unwrap());
+    assert_eq!(1, other.dbsize().await.unwrap());
+
+    client.set("hello", "one".into()).await.unwrap();
+    client.flushall(false).await.unwrap();
+    assert_eq!(0, client.dbsize().await.unwrap());
+    assert_eq!(0, other.dbsize().await.unwrap());
+}
+
+#[tokio::test]
+async fn swap_and_move_between_databases() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+    other.select(1).await.unwrap();
+
+    client.set("hello", "zero".into()).await.unwrap();
+    client.set("world", "zero".into()).await.unwrap();
+    other.set("world", "one".into()).await.unwrap();
+
+    assert!(client.move_key("hello", 1).await.unwrap());
+    assert!(!client.move_key("hello", 1).await.unwrap());
+    assert!(!client.move_key("world", 1).await.unwrap());
+    assert_eq!(None, client.get("hello").await.unwrap());
+    assert_eq!(Some("zero".into()), other.get("hello").await.unwrap());
+
+    let err = client.move_key("world", 0).await.unwrap_err();
+    assert_eq!(
+        "ERR source and destination objects are the same",
+        err.to_string()
+    );
+    let err = client.move_key("world", 16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+
+    // The expiration moves along with the key
+    client
+        .set_expires("ttl", "zero".into(), Duration::from_millis(100))
+        .await
+        .unwrap();
+    assert!(client.move_key("ttl", 1).await.unwrap());
+    assert!(other.pttl("ttl").await.unwrap() > 0);
+    time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(None, other.get("ttl").await.unwrap());
+
+    assert!(client.copy_to_db("world", "copy", 1, false).await.unwrap());
+    assert_eq!(Some("zero".into()), other.get("copy").await.unwrap());
+
+    // Connections keep their selected database, which now holds the keys of
+    // the other one
+    client.swapdb(0, 1).await.unwrap();
+    assert_eq!(Some("one".into()), client.get("world").await.unwrap());
+    assert_eq!(Some("zero".into()), client.get("hello").await.unwrap());
+    assert_eq!(Some("zero".into()), other.get("world").await.unwrap());
+    assert_eq!(1, other.dbsize().await.unwrap());
+
+    let err = client.swapdb(0, 16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+}
+
+#[tokio::test]
+async fn transactions() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    client.set("counter", "1".into()).await.unwrap();
+
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["incr".into(), "counter".into()])
+        .await
+        .unwrap();
+    client
+        .queue(vec!["lpush".into(), "counter".into(), "a".into()])
+        .await
+        .unwrap();
+    client
+        .queue(vec!["blpop".into(), "list".into(), "0".into()])
+        .await
+        .unwrap();
+    client
+        .queue(vec!["select".into(), "1".into()])
+        .await
+        .unwrap();
+
+    // Queued commands are not executed before `EXEC`
+    assert_eq!(Some("1".into()), other.get("counter").await.unwrap());
+
+    let replies = client.exec().await.unwrap().unwrap();
+    assert_eq!(4, replies.len());
+    assert!(matches!(replies[0], Frame::Integer(2)));
+    assert!(matches!(&replies[1], Frame::Error(err) if err.starts_with("WRONGTYPE")));
+    // Blocking commands do not wait in a transaction
+    assert!(matches!(replies[2], Frame::Null));
+    assert_eq!(replies[3], "OK");
+
+    // The database selected in the transaction remains selected
+    assert_eq!(None, client.get("counter").await.unwrap());
+    client.select(0).await.unwrap();
+    assert_eq!(Some("2".into()), client.get("counter").await.unwrap());
+
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["incr".into(), "counter".into()])
+        .await
+        .unwrap();
+    client.discard().await.unwrap();
+    assert_eq!(Some("2".into()), client.get("counter").await.unwrap());
+
+    // An unknown command aborts the transaction
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["incr".into(), "counter".into()])
+        .await
+        .unwrap();
+    let err = client.queue(vec!["foo".into()]).await.unwrap_err();
+    assert_eq!("ERR unknown command 'foo'", err.to_string());
+    let err = client.exec().await.unwrap_err();
+    assert_eq!(
+        "EXECABORT Transaction discarded because of previous errors.",
+        err.to_string()
+    );
+    assert_eq!(Some("2".into()), client.get("counter").await.unwrap());
+
+    let err = client.exec().await.unwrap_err();
+    assert_eq!("ERR EXEC without MULTI", err.to_string());
+    let err = client.discard().await.unwrap_err();
+    assert_eq!("ERR DISCARD without MULTI", err.to_string());
+    client.multi().await.unwrap();
+    let err = client.multi().await.unwrap_err();
+    assert_eq!("ERR MULTI calls can not be nested", err.to_string());
+}
+
+#[tokio::test]
+async fn watch_transactions() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    client.set("counter", "1".into()).await.unwrap();
+
+    // No watched key is modified, the transaction is executed
+    client.watch(vec!["counter".into()]).await.unwrap();
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["set".into(), "counter".into(), "2".into()])
+        .await
+        .unwrap();
+    assert!(client.exec().await.unwrap().is_some());
+
+    // Another connection modifies a watched key, the transaction is not
+    // executed
+    client
+        .watch(vec!["counter".into(), "missing".into()])
+        .await
+        .unwrap();
+    other.set("counter", "10".into()).await.unwrap();
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["set".into(), "counter".into(), "3".into()])
+        .await
+        .unwrap();
+    assert!(client.exec().await.unwrap().is_none());
+    assert_eq!(Some("10".into()), client.get("counter").await.unwrap());
+
+    // `EXEC` stops watching the keys
+    other.set("counter", "11".into()).await.unwrap();
+    client.multi().await.unwrap();
+    assert!(client.exec().await.unwrap().is_some());
+
+    // Creating a watched key is a modification
+    client.watch(vec!["missing".into()]).await.unwrap();
+    other.lpush("missing", vec!["a".into()]).await.unwrap();
+    client.multi().await.unwrap();
+    assert!(client.exec().await.unwrap().is_none());
+
+    // Unwatched keys can be modified
+    client.watch(vec!["counter".into()]).await.unwrap();
+    client.unwatch().await.unwrap();
+    other.set("counter", "12".into()).await.unwrap();
+    client.multi().await.unwrap();
+    assert!(client.exec().await.unwrap().is_some());
+
+    client.multi().await.unwrap();
+    let err = client.watch(vec!["counter".into()]).await.unwrap_err();
+    assert_eq!("ERR WATCH inside MULTI is not allowed", err.to_string());
+    client.discard().await.unwrap();
+}
+
+#[tokio::test]
+async fn scripting() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let reply = client
+        .eval(
+            "return redis.call('set', KEYS[1], ARGV[1])",
+            vec!["a".into()],
+            vec!["1".into()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply, "OK");
+    client.set("b", "2".into()).await.unwrap();
+
+    let sum = r#"
+        local total = 0
+        for _, key in ipairs(KEYS) do
+            -- Missing keys are converted to false
+            total = total + tonumber(redis.call('get', key) or '0')
+        end
+        return total
+    "#;
+    let reply = client
+        .eval(sum, vec!["a".into(), "b".into(), "c".into()], vec![])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(3)));
+
+    let reply = client
+        .eval("return {1, 'two', {3.5}, nil, 5}", vec![], vec![])
+        .await
+        .unwrap();
+    match reply {
+        Frame::Array(values) => {
+            assert_eq!(3, values.len());
+            assert!(matches!(values[0], Frame::Integer(1)));
+            assert_eq!(values[1], "two");
+            assert!(
+                matches!(&values[2], Frame::Array(inner) if matches!(inner[..], [Frame::Integer(3)]))
+            );
+        }
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+
+    let reply = client
+        .eval(
+            "local s = string.format('%s-%03d', ARGV[1], #ARGV) return s:upper()",
+            vec![],
+            vec!["key".into()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply, "KEY-001");
+
+    // Errors of `redis.call` abort the script, `redis.pcall` returns them
+    client.lpush("list", vec!["x".into()]).await.unwrap();
+    let err = client
+        .eval("return redis.call('get', 'list')", vec![], vec![])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+    let reply = client
+        .eval(
+            "local reply = redis.pcall('get', 'list') return reply.err ~= nil",
+            vec![],
+            vec![],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(1)));
+
+    let err = client.eval("return (", vec![], vec![]).await.unwrap_err();
+    assert!(err.to_string().starts_with("ERR Error compiling script"));
+    let err = client.eval("x = 1", vec![], vec![]).await.unwrap_err();
+    assert!(err.to_string().starts_with("ERR Error running script"));
+    let err = client
+        .eval(
+            "local function f(n) return f(n + 1) + 1 end return f(0)",
+            vec![],
+            vec![],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Error running script: stack overflow", err.to_string());
+
+    // Scripts may not build values large enough to exhaust the memory
+    for (script, msg) in [
+        ("return string.rep('x', 1e15)", "resulting string too large"),
+        (
+            "local s = string.rep('x', 3e8) return table.concat({s, s})",
+            "resulting string too large",
+        ),
+        (
+            "return string.format('%999999999d', 1)",
+            "invalid format (width or precision too long)",
+        ),
+        (
+            "return unpack({}, 1, 9223372036854775807)",
+            "too many results to unpack",
+        ),
+    ] {
+        let err = client.eval(script, vec![], vec![]).await.unwrap_err();
+        assert_eq!(
+            format!("ERR Error running script: {}", msg),
+            err.to_string()
+        );
+    }
+    let reply = client
+        .eval("return #string.format('%99d', 1)", vec![], vec![])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(99)));
+
+    // Unsupported features fail with an error naming them
+    for (script, msg) in [
+        (
+            "return setmetatable({}, {})",
+            "metatables are not supported",
+        ),
+        (
+            "return coroutine.create(function() end)",
+            "coroutines are not supported",
+        ),
+        ("return cjson.encode({1})", "cjson is not supported"),
+        ("return cmsgpack.pack(1)", "cmsgpack is not supported"),
+        (
+            "return string.match('key:1', '%d+')",
+            "string patterns are not supported",
+        ),
+        (
+            "return ('a.b'):gsub('%.', ':')",
+            "string patterns are not supported",
+        ),
+        (
+            "return string.find('a.b', '.')",
+            "string patterns are not supported, use plain find",
+        ),
+    ] {
+        let err = client.eval(script, vec![], vec![]).await.unwrap_err();
+        assert_eq!(
+            format!("ERR Error running script: {}", msg),
+            err.to_string()
+        );
+    }
+    let reply = client
+        .eval("return string.find('a.b', '.', 1, true)", vec![], vec![])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(2)));
+
+    let err = client
+        .eval("return redis.call('multi')", vec![], vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR This Redis command is not allowed from script",
+        err.to_string()
+    );
+
+    // Scripts are cached by their SHA1 digest
+    let sha = client.script_load("return 'hello moto'").await.unwrap();
+    assert_eq!("232fd51614574cf0867b83d384a5e898cfd24e5a", sha);
+    let reply = client.evalsha(&sha, vec![], vec![]).await.unwrap();
+    assert_eq!(reply, "hello moto");
+    assert_eq!(
+        vec![true, false],
+        client
+            .script_exists(vec![sha.clone(), "0".repeat(40)])
+            .await
+            .unwrap()
+    );
+
+    client.script_flush().await.unwrap();
+    let err = client.evalsha(&sha, vec![], vec![]).await.unwrap_err();
+    assert_eq!(
+        "NOSCRIPT No matching script. Please use EVAL.",
+        err.to_string()
+    );
+}
+
+/// Scripts running for longer than `lua-time-limit` make the server busy for
+/// the other clients, which may kill them until they write.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn script_time_limit() {
+    let (addr, _) = start_server().await;
+
+    let mut other = client::connect(addr).await.unwrap();
+    other.config_set("lua-time-limit", "100").await.unwrap();
+    let err = other.script_kill().await.unwrap_err();
+    assert_eq!(
+        "NOTBUSY No scripts in execution right now.",
+        err.to_string()
+    );
+
+    let mut client = client::connect(addr).await.unwrap();
+    let script =
+        tokio::spawn(async move { client.eval("while true do end", vec![], vec![]).await });
+    let err = wait_busy(&mut other).await;
+    assert_eq!(
+        "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.",
+        err
+    );
+    // Clients may still connect to kill the script.
+    let mut killer = client::connect(addr).await.unwrap();
+    let err = killer.function_kill().await.unwrap_err();
+    assert!(err.to_string().starts_with("BUSY"));
+    killer.script_kill().await.unwrap();
+    let err = script.await.unwrap().unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Script killed by user with SCRIPT KILL"));
+    other.set("a", "1".into()).await.unwrap();
+
+    // Functions are killed with `FUNCTION KILL`.
+    let mut client = client::connect(addr).await.unwrap();
+    client
+        .function_load(
+            "#!lua name=spin\nredis.register_function('spin', function() while true do end end)",
+            false,
+        )
+        .await
+        .unwrap();
+    let function = tokio::spawn(async move { client.fcall("spin", vec![], vec![]).await });
+    let err = wait_busy(&mut other).await;
+    assert_eq!(
+        "BUSY Redis is busy running a script. You can only call FUNCTION KILL or SHUTDOWN NOSAVE.",
+        err
+    );
+    other.function_kill().await.unwrap();
+    let err = function.await.unwrap().unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Script killed by user with FUNCTION KILL"));
+
+    // The code loading a library may not run forever.
+    let err = other
+        .function_load("#!lua name=forever\nwhile true do end", false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("FUNCTION LOAD timeout"));
+
+    // Scripts which wrote may only be stopped by shutting the server down.
+    let mut client = client::connect(addr).await.unwrap();
+    let script = tokio::spawn(async move {
+        client
+            .eval(
+                "redis.call('set', KEYS[1], '2') while true do end",
+                vec!["a".into()],
+                vec![],
+            )
+            .await
+    });
+    wait_busy(&mut other).await;
+    let err = other.script_kill().await.unwrap_err();
+    assert!(err.to_string().starts_with("UNKILLABLE"));
+    let _ = other.shutdown(Some(false)).await;
+    assert!(script.await.unwrap().is_err());
+}
+
+/// Sends commands with `client` until the server replies with a `BUSY` error,
+/// returning it.
+async fn wait_busy(client: &mut client::Client) -> String {
+    loop {
+        match client.get("a").await {
+            Err(err) if err.to_string().starts_with("BUSY") => return err.to_string(),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => time::sleep(Duration::from_millis(10)).await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn functions() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let code = r#"#!lua name=counters
+        local function incr(keys, args)
+            return redis.call('incrby', keys[1], args[1])
+        end
+
+        redis.register_function('incr', incr)
+        redis.register_function{
+            function_name = 'peek',
+            callback = function(keys) return redis.call('get', keys[1]) end,
+            flags = {'no-writes'},
+            description = 'Returns a counter',
+        }
+        redis.register_function{
+            function_name = 'sneaky',
+            callback = function(keys) return redis.call('del', keys[1]) end,
+            flags = {'no-writes'},
+        }
+    "#;
+    assert_eq!("counters", client.function_load(code, false).await.unwrap());
+    let err = client.function_load(code, false).await.unwrap_err();
+    assert_eq!("ERR Library 'counters' already exists", err.to_string());
+    client.function_load(code, true).await.unwrap();
+
+    let reply = client
+        .fcall("incr", vec!["counter".into()], vec!["5".into()])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(5)));
+    let reply = client
+        .fcall_ro("peek", vec!["counter".into()], vec![])
+        .await
+        .unwrap();
+    assert_eq!(reply, "5");
+
+    let err = client
+        .fcall_ro("incr", vec!["counter".into()], vec!["1".into()])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR Can not execute a script with write flag using *_ro command.",
+        err.to_string()
+    );
+    let err = client
+        .fcall("sneaky", vec!["counter".into()], vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR Write commands are not allowed from read-only scripts.",
+        err.to_string()
+    );
+    let err = client.fcall("missing", vec![], vec![]).await.unwrap_err();
+    assert_eq!("ERR Function not found", err.to_string());
+
+    // Function names are unique across libraries
+    let err = client
+        .function_load(
+            "#!lua name=other\nredis.register_function('incr', function() end)",
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Function incr already exists", err.to_string());
+    let err = client
+        .function_load("redis.register_function('f', function() end)", false)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Missing library metadata", err.to_string());
+    let err = client
+        .function_load("#!lua name=empty\nreturn 1", false)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR No functions registered", err.to_string());
+
+    let libraries = client.function_list(None, true).await.unwrap();
+    assert_eq!(1, libraries.len());
+    match &libraries[0] {
+        Frame::Array(fields) => {
+            assert_eq!(fields[0], "library_name");
+            assert_eq!(fields[1], "counters");
+            assert_eq!(fields[3], "LUA");
+            match &fields[5] {
+                Frame::Array(functions) => assert_eq!(3, functions.len()),
+                frame => panic!("unexpected frame {:?}", frame),
+            }
+            assert_eq!(fields[7], code);
+        }
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+    assert!(client
+        .function_list(Some("other*"), false)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Libraries are restored from a dump
+    let payload = client.function_dump().await.unwrap();
+    client.function_flush().await.unwrap();
+    client.fcall("incr", vec![], vec![]).await.unwrap_err();
+    client.function_restore(payload.clone()).await.unwrap();
+    let reply = client
+        .fcall("incr", vec!["counter".into()], vec!["1".into()])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(6)));
+    client.function_restore(payload).await.unwrap_err();
+    client
+        .function_restore(Bytes::from("invalid"))
+        .await
+        .unwrap_err();
+
+    client.function_delete("counters").await.unwrap();
+    let err = client.function_delete("counters").await.unwrap_err();
+    assert_eq!("ERR Library not found", err.to_string());
+}
+
+/// Snapshots are saved once a save point is reached, and the parameters of
+/// `CONFIG SET` are set when the server starts.
+#[tokio::test]
+async fn save_points() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-points.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (addr, _shutdown, _) = start_server_with_config(server::Config {
+        snapshot_path: Some(path.clone()),
+        save_points: vec![(Duration::from_millis(100), 2)],
+        parameters: vec![
+            ("notify-keyspace-events".into(), "KEA".into()),
+            ("slowlog-max-len".into(), "10".into()),
+        ],
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert_eq!(
+        vec![
+            ("notify-keyspace-events".to_string(), "AKE".to_string()),
+            ("slowlog-max-len".to_string(), "10".to_string()),
+        ],
+        [
+            client.config_get("notify-keyspace-events").await.unwrap(),
+            client.config_get("slowlog-max-len").await.unwrap(),
+        ]
+        .concat()
+    );
+
+    // A single change does not reach the save point.
+    client.set("a", "1".into()).await.unwrap();
+    time::sleep(Duration::from_millis(1200)).await;
+    assert!(!path.exists());
+
+    client.set("b", "2".into()).await.unwrap();
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while !path.exists() {
+        assert!(Instant::now() < deadline, "no snapshot saved");
+        time::sleep(Duration::from_millis(50)).await;
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    // The server does not start with invalid parameters.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = server::Config {
+        parameters: vec![("slowlog-max-len".into(), "lots".into())],
+        ..server::Config::default()
+    };
+    assert!(
+        server::run_with_config(vec![listener], config, std::future::pending::<()>())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn snapshot_persistence() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-snapshot.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (addr, shutdown, handle) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("string", "hello".into()).await.unwrap();
+    client.setex("expiring", 100, "soon".into()).await.unwrap();
+    client.setex("expired", 1, "gone".into()).await.unwrap();
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client.sadd("set", vec!["x".into()]).await.unwrap();
+    client
+        .hset("hash", vec![("field".into(), "value".into())])
+        .await
+        .unwrap();
+    client
+        .zadd("zset", vec![(1.5, "one".into()), (-2.0, "two".into())])
+        .await
+        .unwrap();
+    let id = client
+        .xadd("stream", "*", vec![("k".into(), "v".into())])
+        .await
+        .unwrap();
+    client
+        .xgroup_create("stream", "group", "0", false)
+        .await
+        .unwrap();
+    client
+        .xreadgroup(
+            "group",
+            "alice",
+            vec![("stream".into(), ">".into())],
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    client
+        .function_load(
+            "#!lua name=lib\nredis.register_function('hi', function() return 'hi' end)",
+            false,
+        )
+        .await
+        .unwrap();
+    client.select(1).await.unwrap();
+    client.set("other", "db".into()).await.unwrap();
+    drop(client);
+
+    // A snapshot is saved when the server shuts down
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+    time::sleep(Duration::from_millis(1100)).await;
+
+    let (addr, shutdown, handle) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(Some("hello".into()), client.get("string").await.unwrap());
+    assert!(client.ttl("expiring").await.unwrap() > 90);
+    assert_eq!(None, client.get("expired").await.unwrap());
+    assert_eq!(
+        vec![Bytes::from("a"), Bytes::from("b")],
+        client.lrange("list", 0, -1).await.unwrap()
+    );
+    assert_eq!(
+        vec![Bytes::from("x")],
+        client.smembers("set").await.unwrap()
+    );
+    assert_eq!(
+        vec![("field".to_string(), Bytes::from("value"))],
+        client.hgetall("hash").await.unwrap()
+    );
+    assert_eq!(
+        vec![Bytes::from("two"), Bytes::from("one")],
+        client.zrange("zset", 0, -1).await.unwrap()
+    );
+    let pending = client.xpending("stream", "group").await.unwrap();
+    assert_eq!(1, pending.count);
+    assert_eq!(Some(id), pending.smallest_id);
+    assert_eq!(vec![("alice".to_string(), 1)], pending.consumers);
+    assert_eq!(client.fcall("hi", vec![], vec![]).await.unwrap(), "hi");
+    client.select(1).await.unwrap();
+    assert_eq!(Some("db".into()), client.get("other").await.unwrap());
+    drop(client);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    // A corrupted snapshot is not loaded
+    let mut data = std::fs::read(&path).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    std::fs::write(&path, data).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = server::Config {
+        snapshot_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+    assert!(
+        server::run_with_config(vec![listener], config, std::future::pending::<()>())
+            .await
+            .is_err()
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// `SAVE` and `BGSAVE` save snapshots on demand, and `LASTSAVE` reports when
+/// the last one was saved.
+#[tokio::test]
+async fn save_bgsave_lastsave() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert!(client.save().await.is_err());
+    assert!(client.bgsave().await.is_err());
+    assert!(client.lastsave().await.unwrap() > 0);
+
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-save.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (addr, _shutdown, _) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+    let started = client.lastsave().await.unwrap();
+
+    client.set("saved", "1".into()).await.unwrap();
+    client.save().await.unwrap();
+    assert!(client.lastsave().await.unwrap() >= started);
+
+    // A server started on the same file loads the snapshot.
+    let (other, _shutdown_other, _) = start_server_with_snapshot(&path).await;
+    let mut other = client::connect(other).await.unwrap();
+    assert_eq!(Some("1".into()), other.get("saved").await.unwrap());
+    drop(other);
+
+    client.set("background", "2".into()).await.unwrap();
+    assert_eq!("Background saving started", client.bgsave().await.unwrap());
+
+    // `SAVE` fails while the background save is in progress.
+    while client.save().await.is_err() {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let (other, _shutdown_other, _) = start_server_with_snapshot(&path).await;
+    let mut other = client::connect(other).await.unwrap();
+    assert_eq!(Some("2".into()), other.get("background").await.unwrap());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Commands modifying the databases are appended to the AOF and replayed when
+/// the server restarts.
+#[tokio::test]
+async fn aof_persistence() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}.aof", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        aof_fsync: server::Fsync::Always,
+        ..server::Config::default()
+    };
+
+    let (addr, shutdown, handle) = start_server_with_config(config.clone()).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("string", "hello".into()).await.unwrap();
+    assert!(!client.setnx("string", "ignored".into()).await.unwrap());
+    client.setex("expiring", 100, "soon".into()).await.unwrap();
+    client.setex("expired", 1, "gone".into()).await.unwrap();
+    client.incr("counter").await.unwrap();
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client.lpop("list").await.unwrap();
+
+    // A blocked command is appended once it completes.
+    let mut blocked = client::connect(addr).await.unwrap();
+    let pop = tokio::spawn(async move {
+        blocked
+            .blpop(vec!["queue".into()], Duration::from_secs(5))
+            .await
+    });
+    time::sleep(Duration::from_millis(50)).await;
+    client
+        .rpush("queue", vec!["1".into(), "2".into()])
+        .await
+        .unwrap();
+    assert!(pop.await.unwrap().unwrap().is_some());
+
+    // The commands of scripts and transactions are appended.
+    client
+        .eval(
+            "redis.call('incrby', KEYS[1], 10) return 1",
+            vec!["counter".into()],
+            vec![],
+        )
+        .await
+        .unwrap();
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["incr".into(), "counter".into()])
+        .await
+        .unwrap();
+    client.exec().await.unwrap();
+    client
+        .function_load(
+            "#!lua name=lib\nredis.register_function('get', function(keys) return redis.call('get', keys[1]) end)",
+            false,
+        )
+        .await
+        .unwrap();
+
+    client.select(1).await.unwrap();
+    client.set("other", "db".into()).await.unwrap();
+
+    time::sleep(Duration::from_millis(1100)).await;
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let (addr, shutdown, handle) = start_server_with_config(config.clone()).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(Some("hello".into()), client.get("string").await.unwrap());
+    assert_eq!(None, client.get("expired").await.unwrap());
+    let ttl = client.ttl("expiring").await.unwrap();
+    assert!(ttl > 90 && ttl <= 100);
+    assert_eq!(Some("12".into()), client.get("counter").await.unwrap());
+    assert_eq!(Some("b".into()), client.lpop("list").await.unwrap());
+    assert_eq!(Some("2".into()), client.lpop("queue").await.unwrap());
+    assert_eq!(None, client.lpop("queue").await.unwrap());
+    let reply = client
+        .fcall("get", vec!["string".into()], vec![])
+        .await
+        .unwrap();
+    assert_eq!(reply, "hello");
+    client.select(1).await.unwrap();
+    assert_eq!(Some("db".into()), client.get("other").await.unwrap());
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    // A command cut short at the end of the file is removed.
+    let len = std::fs::metadata(&path).unwrap().len();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"*3\r\n$3\r\nset\r\n$3\r\nkey")
+        .unwrap();
+
+    let (addr, shutdown, handle) = start_server_with_config(config.clone()).await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert_eq!(Some("hello".into()), client.get("string").await.unwrap());
+    assert_eq!(len, std::fs::metadata(&path).unwrap().len());
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    // Other invalid content prevents the server from starting.
+    std::fs::write(&path, b"*1\r\n$7\r\nunknown\r\n").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    assert!(
+        server::run_with_config(vec![listener], config, std::future::pending::<()>())
+            .await
+            .is_err()
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn bgrewriteaof() {
+    let path = std::env::temp_dir().join(format!("mini-redis-rewrite-{}.aof", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, shutdown, handle) = start_server_with_config(config.clone()).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    // The AOF must be enabled
+    let (disabled_addr, _disabled_shutdown, _) =
+        start_server_with_config(server::Config::default()).await;
+    let mut disabled = client::connect(disabled_addr).await.unwrap();
+    assert!(disabled.bgrewriteaof().await.is_err());
+
+    for _ in 0..100 {
+        client.incr("counter").await.unwrap();
+        client.set("overwritten", "value".into()).await.unwrap();
+    }
+    client
+        .rpush("list", vec!["a".into(), "b".into(), "c".into()])
+        .await
+        .unwrap();
+    client
+        .sadd("set", vec!["x".into(), "y".into()])
+        .await
+        .unwrap();
+    client
+        .hset("hash", vec![("field".into(), "value".into())])
+        .await
+        .unwrap();
+    client
+        .zadd(
+            "zset",
+            vec![(1.5, "one".into()), (f64::INFINITY, "inf".into())],
+        )
+        .await
+        .unwrap();
+    client.setex("expiring", 100, "soon".into()).await.unwrap();
+    client
+        .xadd("stream", "1-1", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+    client
+        .xadd("stream", "5-0", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+    client.xdel("stream", vec!["5-0".into()]).await.unwrap();
+    client
+        .xgroup_create("stream", "group", "0", false)
+        .await
+        .unwrap();
+    let new = || vec![("stream".to_string(), ">".to_string())];
+    client
+        .xreadgroup("group", "alice", new(), None, None)
+        .await
+        .unwrap();
+    client
+        .function_load(
+            "#!lua name=lib\nredis.register_function('get', function(keys) return redis.call('get', keys[1]) end)",
+            false,
+        )
+        .await
+        .unwrap();
+    client.select(1).await.unwrap();
+    client.set("other", "db".into()).await.unwrap();
+
+    let len = std::fs::metadata(&path).unwrap().len();
+    assert_eq!(
+        "Background append only file rewriting started",
+        client.bgrewriteaof().await.unwrap()
+    );
+    // Commands applied during and after the rewrite are kept
+    client.set("after", "rewrite".into()).await.unwrap();
+
+    let mut rewritten = false;
+    for _ in 0..100 {
+        if std::fs::metadata(&path).unwrap().len() < len {
+            rewritten = true;
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(rewritten);
+    client.select(0).await.unwrap();
+    client.incr("counter").await.unwrap();
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let (addr, shutdown, handle) = start_server_with_config(config).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(Some("101".into()), client.get("counter").await.unwrap());
+    assert_eq!(
+        Some("value".into()),
+        client.get("overwritten").await.unwrap()
+    );
+    assert_eq!(
+        vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")],
+        client.lrange("list", 0, -1).await.unwrap()
+    );
+    assert_eq!(2, client.scard("set").await.unwrap());
+    assert_eq!(
+        Some("value".into()),
+        client.hget("hash", "field").await.unwrap()
+    );
+    assert_eq!(
+        Some(f64::INFINITY),
+        client.zscore("zset", "inf".into()).await.unwrap()
+    );
+    let ttl = client.ttl("expiring").await.unwrap();
+    assert!(ttl > 90 && ttl <= 100);
+
+    assert_eq!(1, client.xlen("stream").await.unwrap());
+    // The last ID of the stream is restored
+    assert!(client
+        .xadd("stream", "4-0", vec![("f".into(), "v".into())])
+        .await
+        .is_err());
+    let summary = client.xpending("stream", "group").await.unwrap();
+    assert_eq!(vec![("alice".to_string(), 1)], summary.consumers);
+
+    let reply = client
+        .fcall("get", vec!["counter".into()], vec![])
+        .await
+        .unwrap();
+    assert_eq!(reply, "101");
+    client.select(1).await.unwrap();
+    assert_eq!(Some("db".into()), client.get("other").await.unwrap());
+    assert_eq!(Some("rewrite".into()), client.get("after").await.unwrap());
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn replication() {
+    let (primary_addr, _primary_shutdown, _) =
+        start_server_with_config(server::Config::default()).await;
+    let mut primary = client::connect(primary_addr).await.unwrap();
+    primary.set("before", "sync".into()).await.unwrap();
+
+    let config = server::Config {
+        replica_of: Some(primary_addr.to_string()),
+        ..server::Config::default()
+    };
+    let (replica_addr, _replica_shutdown, _) = start_server_with_config(config).await;
+    let mut replica = client::connect(replica_addr).await.unwrap();
+
+    // The replica first loads a snapshot of the primary
+    let mut synced = false;
+    for _ in 0..100 {
+        if replica.get("before").await.unwrap().is_some() {
+            synced = true;
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(synced);
+
+    // Then applies the commands modifying the primary as they are applied
+    primary.incr("counter").await.unwrap();
+    primary.setex("expiring", 100, "soon".into()).await.unwrap();
+    assert!(!primary.setnx("before", "ignored".into()).await.unwrap());
+    primary
+        .eval(
+            "redis.call('incrby', KEYS[1], 10) return 1",
+            vec!["counter".into()],
+            vec![],
+        )
+        .await
+        .unwrap();
+    primary.select(1).await.unwrap();
+    primary.set("last", "write".into()).await.unwrap();
+
+    replica.select(1).await.unwrap();
+    let mut replicated = false;
+    for _ in 0..100 {
+        if replica.get("last").await.unwrap().is_some() {
+            replicated = true;
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(replicated);
+
+    replica.select(0).await.unwrap();
+    assert_eq!(Some("11".into()), replica.get("counter").await.unwrap());
+    assert_eq!(Some("sync".into()), replica.get("before").await.unwrap());
+    let ttl = replica.ttl("expiring").await.unwrap();
+    assert!(ttl > 90 && ttl <= 100);
+}
+
+#[tokio::test]
+async fn replicaof() {
+    let (primary_addr, _primary_shutdown, _) =
+        start_server_with_config(server::Config::default()).await;
+    let mut primary = client::connect(primary_addr).await.unwrap();
+    primary.set("key", "primary".into()).await.unwrap();
+
+    let (replica_addr, _replica_shutdown, _) =
+        start_server_with_config(server::Config::default()).await;
+    let mut replica = client::connect(replica_addr).await.unwrap();
+    replica.set("key", "replica".into()).await.unwrap();
+    replica.set("dropped", "on sync".into()).await.unwrap();
+
+    replica
+        .replicaof(&primary_addr.ip().to_string(), primary_addr.port())
+        .await
+        .unwrap();
+
+    // The databases of the replica are replaced by those of the primary
+    let mut synced = false;
+    for _ in 0..100 {
+        if replica.get("key").await.unwrap() == Some("primary".into()) {
+            synced = true;
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(synced);
+    assert_eq!(None, replica.get("dropped").await.unwrap());
+
+    // Clients may not modify the databases of a replica
+    let err = replica.set("key", "replica".into()).await.unwrap_err();
+    assert!(err.to_string().starts_with("READONLY"));
+    replica.multi().await.unwrap();
+    assert!(replica
+        .queue(vec!["set".into(), "key".into(), "replica".into()])
+        .await
+        .is_err());
+    assert!(replica.exec().await.is_err());
+
+    // Once promoted, the replica no longer applies the commands of the primary
+    replica.replicaof_no_one().await.unwrap();
+    replica.set("promoted", "yes".into()).await.unwrap();
+    primary.set("key", "changed".into()).await.unwrap();
+    time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(Some("primary".into()), replica.get("key").await.unwrap());
+    assert_eq!(Some("yes".into()), replica.get("promoted").await.unwrap());
+}
+
+#[tokio::test]
+async fn partial_resync() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let psync = |replication_id: &str, offset: i64| {
+        Frame::Array(vec![
+            Frame::Bulk("psync".into()),
+            Frame::Bulk(Bytes::from(replication_id.to_string())),
+            Frame::Bulk(Bytes::from(offset.to_string())),
+        ])
+    };
+
+    let args = |frame: Option<Frame>| match frame {
+        Some(Frame::Array(parts)) => parts
+            .iter()
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>(),
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+
+    // A new replica is fully synchronized
+    let mut replica = Connection::new(TcpStream::connect(addr).await.unwrap());
+    replica.write_frame(&psync("?", -1)).await.unwrap();
+    let response = replica.read_frame().await.unwrap().unwrap().to_string();
+    let parts: Vec<_> = response.split(' ').collect();
+    assert_eq!("FULLRESYNC", parts[0]);
+    let replication_id = parts[1].to_string();
+    let offset: i64 = parts[2].parse().unwrap();
+    assert!(matches!(
+        replica.read_frame().await.unwrap(),
+        Some(Frame::Bulk(_))
+    ));
+
+    // The stream starts by selecting the database: 23 bytes, then 27 bytes
+    // for the `SET`.
+    client.set("a", "1".into()).await.unwrap();
+    assert_eq!(
+        vec!["select", "0"],
+        args(replica.read_frame().await.unwrap())
+    );
+    assert_eq!(
+        vec!["set", "a", "1"],
+        args(replica.read_frame().await.unwrap())
+    );
+    drop(replica);
+
+    client.set("b", "2".into()).await.unwrap();
+
+    // A replica reconnecting only receives the commands it missed
+    let mut replica = Connection::new(TcpStream::connect(addr).await.unwrap());
+    replica
+        .write_frame(&psync(&replication_id, offset + 50))
+        .await
+        .unwrap();
+    assert_eq!(
+        "CONTINUE",
+        replica.read_frame().await.unwrap().unwrap().to_string()
+    );
+    assert_eq!(
+        vec!["set", "b", "2"],
+        args(replica.read_frame().await.unwrap())
+    );
+
+    // Unless the offset is not in the backlog, or the stream is another one
+    for (replication_id, offset) in [(&replication_id[..], offset + 1000), ("other", offset)] {
+        let mut replica = Connection::new(TcpStream::connect(addr).await.unwrap());
+        replica
+            .write_frame(&psync(replication_id, offset))
+            .await
+            .unwrap();
+        let response = replica.read_frame().await.unwrap().unwrap().to_string();
+        assert!(response.starts_with("FULLRESYNC"));
+    }
+}
+
+#[tokio::test]
+async fn wait() {
+    let (primary_addr, _primary_shutdown, _) =
+        start_server_with_config(server::Config::default()).await;
+    let mut primary = client::connect(primary_addr).await.unwrap();
+
+    // Without replicas, no replica acknowledges the writes
+    primary.set("key", "value".into()).await.unwrap();
+    assert_eq!(0, primary.wait(1, Duration::from_millis(50)).await.unwrap());
+
+    let mut replicas = vec![];
+    for _ in 0..2 {
+        let (replica_addr, replica_shutdown, _) = start_server_with_config(server::Config {
+            replica_of: Some(primary_addr.to_string()),
+            ..server::Config::default()
+        })
+        .await;
+        let mut replica = client::connect(replica_addr).await.unwrap();
+
+        let mut synced = false;
+        for _ in 0..100 {
+            if replica.get("key").await.unwrap() == Some("value".into()) {
+                synced = true;
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(synced);
+        replicas.push((replica, replica_shutdown));
+    }
+
+    // Both replicas acknowledge the writes, the command returns as soon as
+    // they do
+    primary.set("key", "changed".into()).await.unwrap();
+    assert_eq!(2, primary.wait(2, Duration::from_secs(5)).await.unwrap());
+    for (replica, _) in &mut replicas {
+        assert_eq!(Some("changed".into()), replica.get("key").await.unwrap());
+    }
+
+    // Waiting for more replicas than connected times out
+    assert_eq!(2, primary.wait(3, Duration::from_millis(50)).await.unwrap());
+
+    // Replicas may not wait
+    let (replica, _) = &mut replicas[0];
+    assert!(replica.wait(1, Duration::from_millis(50)).await.is_err());
+}
+
+#[tokio::test]
+async fn cluster() {
+    let config = server::Config {
+        cluster_enabled: true,
+        ..server::Config::default()
+    };
+    let (a_addr, _a_shutdown, _) = start_server_with_config(config.clone()).await;
+    let (b_addr, _b_shutdown, _) = start_server_with_config(config).await;
+    let mut a = client::connect(a_addr).await.unwrap();
+    let mut b = client::connect(b_addr).await.unwrap();
+    let a_id = a.cluster_myid().await.unwrap();
+    let b_id = b.cluster_myid().await.unwrap();
+
+    // Until the slots are assigned, keys are served by no node
+    let err = a.set("bar", "a".into()).await.unwrap_err();
+    assert!(err.to_string().starts_with("CLUSTERDOWN"));
+
+    a.cluster_addslotsrange(0, 8191).await.unwrap();
+    b.cluster_addslotsrange(8192, 16383).await.unwrap();
+    assert!(b.cluster_addslotsrange(8000, 8192).await.is_err());
+    a.cluster_meet(&b_addr.ip().to_string(), b_addr.port())
+        .await
+        .unwrap();
+    b.cluster_meet(&a_addr.ip().to_string(), a_addr.port())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(0, 8191, a_id.clone()), (8192, 16383, b_id.clone())],
+        a.cluster_slots().await.unwrap()
+    );
+    assert_eq!(
+        a.cluster_slots().await.unwrap(),
+        b.cluster_slots().await.unwrap()
+    );
+
+    // `bar` belongs to slot 5061 and `foo` to slot 12182
+    a.set("bar", "a".into()).await.unwrap();
+    b.set("foo", "b".into()).await.unwrap();
+    let err = a.get("foo").await.unwrap_err();
+    assert_eq!(format!("MOVED 12182 {}", b_addr), err.to_string());
+    let err = b.get("bar").await.unwrap_err();
+    assert_eq!(format!("MOVED 5061 {}", a_addr), err.to_string());
+
+    // Only the first database is used
+    assert!(a.select(1).await.is_err());
+
+    // While slot 5061 migrates, the source serves the keys it holds and
+    // redirects the clients to the target for the others
+    b.cluster_setslot_importing(5061, &a_id).await.unwrap();
+    a.cluster_setslot_migrating(5061, &b_id).await.unwrap();
+    assert_eq!(Some("a".into()), a.get("bar").await.unwrap());
+    a.del(vec!["bar".into()]).await.unwrap();
+    let err = a.get("bar").await.unwrap_err();
+    assert_eq!(format!("ASK 5061 {}", b_addr), err.to_string());
+
+    // The target only serves the keys of the slot after `ASKING`
+    assert!(b.set("bar", "b".into()).await.is_err());
+    b.asking().await.unwrap();
+    b.set("bar", "b".into()).await.unwrap();
+    assert!(b.get("bar").await.is_err());
+
+    // Once migrated, the slot is served by the target
+    a.cluster_setslot_node(5061, &b_id).await.unwrap();
+    b.cluster_setslot_node(5061, &b_id).await.unwrap();
+    assert_eq!(Some("b".into()), b.get("bar").await.unwrap());
+    let err = a.get("bar").await.unwrap_err();
+    assert_eq!(format!("MOVED 5061 {}", b_addr), err.to_string());
+
+    // Cluster commands are rejected outside of cluster mode
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert!(client.cluster_myid().await.is_err());
+    client.select(1).await.unwrap();
+}
+
+#[tokio::test]
+async fn cluster_keyslot() {
+    let (addr, _shutdown, _) = start_server_with_config(server::Config {
+        cluster_enabled: true,
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = client::connect(addr).await.unwrap();
+    client.cluster_addslotsrange(0, 16383).await.unwrap();
+
+    assert_eq!(12182, client.cluster_keyslot("foo").await.unwrap());
+    assert_eq!(5061, client.cluster_keyslot("bar").await.unwrap());
+
+    // Only the hash tag is hashed
+    let slot = client.cluster_keyslot("user1").await.unwrap();
+    assert_eq!(
+        slot,
+        client.cluster_keyslot("{user1}.following").await.unwrap()
+    );
+    assert_eq!(slot, client.cluster_keyslot("x{user1}y{z}").await.unwrap());
+
+    // Empty or unterminated tags are not hash tags, the first `}` ends a tag
+    let slot = client.cluster_keyslot("{}user1").await.unwrap();
+    assert_ne!(slot, client.cluster_keyslot("user1").await.unwrap());
+    assert_eq!(12182, client.cluster_keyslot("{foo}}").await.unwrap());
+    assert_eq!(
+        client.cluster_keyslot("{foo").await.unwrap(),
+        client.cluster_keyslot("x{{foo}").await.unwrap()
+    );
+
+    // The keys of a command must all map to the same slot
+    let err = client
+        .mset(vec![("foo".into(), "1".into()), ("bar".into(), "2".into())])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("CROSSSLOT"));
+    assert!(client
+        .del(vec!["foo".into(), "bar".into()])
+        .await
+        .unwrap_err()
+        .to_string()
+        .starts_with("CROSSSLOT"));
+    client
+        .mset(vec![
+            ("{user1}.following".into(), "1".into()),
+            ("{user1}.followers".into(), "2".into()),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        2,
+        client
+            .del(vec!["{user1}.following".into(), "{user1}.followers".into()])
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn keyspace_notifications() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    // Disabled by default
+    assert_eq!(
+        vec![("notify-keyspace-events".to_string(), "".to_string())],
+        client.config_get("notify-*").await.unwrap()
+    );
+    assert!(client
+        .config_set("notify-keyspace-events", "Kq")
+        .await
+        .is_err());
+
+    client
+        .config_set("notify-keyspace-events", "KEA")
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![("notify-keyspace-events".to_string(), "AKE".to_string())],
+        client.config_get("notify-keyspace-events").await.unwrap()
+    );
+
+    let mut subscriber = client::connect(addr)
+        .await
+        .unwrap()
+        .subscribe(vec![
+            "__keyspace@0__:foo".into(),
+            "__keyevent@0__:expired".into(),
+        ])
+        .await
+        .unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    client.set("other", "bar".into()).await.unwrap();
+    client.pexpire("foo", 10, None).await.unwrap();
+
+    // Messages of different channels may be received in any order
+    let mut messages = vec![];
+    for _ in 0..4 {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        messages.push((message.channel, message.content));
+    }
+    let (keyspace, keyevent): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .partition(|(channel, _)| channel == "__keyspace@0__:foo");
+    assert_eq!(
+        vec!["set", "expire", "expired"],
+        keyspace
+            .iter()
+            .map(|(_, event)| std::str::from_utf8(event).unwrap())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![("__keyevent@0__:expired".to_string(), "foo".into())],
+        keyevent
+    );
+
+    // Only the events of the enabled classes are published
+    client
+        .config_set("notify-keyspace-events", "Kg")
+        .await
+        .unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    client.del(vec!["foo".into()]).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyspace@0__:foo", message.channel);
+    assert_eq!(b"del", &message.content[..]);
+}
+
+#[tokio::test]
+async fn pattern_subscriptions() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let mut subscriber = client::connect(addr)
+        .await
+        .unwrap()
+        .psubscribe(vec!["news.*".into()])
+        .await
+        .unwrap();
+    subscriber.subscribe(&["news.tech".into()]).await.unwrap();
+    assert_eq!(
+        &["news.*".to_string()],
+        subscriber.get_subscribed_patterns()
+    );
+
+    // The message is received once per matching subscription
+    assert_eq!(2, client.publish("news.tech", "rust".into()).await.unwrap());
+    let mut messages = vec![];
+    for _ in 0..2 {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!("news.tech", message.channel);
+        assert_eq!(b"rust", &message.content[..]);
+        messages.push(message.pattern);
+    }
+    messages.sort();
+    assert_eq!(vec![None, Some("news.*".to_string())], messages);
+
+    assert_eq!(0, client.publish("weather", "sunny".into()).await.unwrap());
+    assert_eq!(1, client.publish("news.art", "paint".into()).await.unwrap());
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("news.art", message.channel);
+    assert_eq!(Some("news.*"), message.pattern.as_deref());
+
+    subscriber.punsubscribe(&[]).await.unwrap();
+    assert!(subscriber.get_subscribed_patterns().is_empty());
+    assert_eq!(0, client.publish("news.art", "paint".into()).await.unwrap());
+    assert_eq!(1, client.publish("news.tech", "rust".into()).await.unwrap());
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(None, message.pattern);
+}
+
+#[tokio::test]
+async fn pubsub_introspection() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(client.pubsub_channels(None).await.unwrap().is_empty());
+    assert_eq!(0, client.pubsub_numpat().await.unwrap());
+
+    let mut subscriber1 = client::connect(addr)
+        .await
+        .unwrap()
+        .subscribe(vec!["news.tech".into(), "weather".into()])
+        .await
+        .unwrap();
+    let subscriber2 = client::connect(addr)
+        .await
+        .unwrap()
+        .subscribe(vec!["news.tech".into()])
+        .await
+        .unwrap();
+    let mut psubscriber = client::connect(addr)
+        .await
+        .unwrap()
+        .psubscribe(vec!["news.*".into(), "*".into()])
+        .await
+        .unwrap();
+
+    let mut channels = client.pubsub_channels(None).await.unwrap();
+    channels.sort();
+    assert_eq!(vec!["news.tech", "weather"], channels);
+    assert_eq!(
+        vec!["news.tech"],
+        client.pubsub_channels(Some("news.*")).await.unwrap()
+    );
+    assert_eq!(
+        vec![
+            ("news.tech".to_string(), 2),
+            ("weather".to_string(), 1),
+            ("news.art".to_string(), 0)
+        ],
+        client
+            .pubsub_numsub(vec![
+                "news.tech".into(),
+                "weather".into(),
+                "news.art".into()
+            ])
+            .await
+            .unwrap()
+    );
+    assert_eq!(2, client.pubsub_numpat().await.unwrap());
+
+    // Unsubscribing or disconnecting removes the subscriptions
+    subscriber1.unsubscribe(&["weather".into()]).await.unwrap();
+    psubscriber.punsubscribe(&["*".into()]).await.unwrap();
+    assert_eq!(1, client.pubsub_numpat().await.unwrap());
+    drop(subscriber2);
+
+    let mut numsub = vec![];
+    for _ in 0..100 {
+        numsub = client
+            .pubsub_numsub(vec!["news.tech".into(), "weather".into()])
+            .await
+            .unwrap();
+        if numsub[0].1 == 1 {
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(
+        vec![("news.tech".to_string(), 1), ("weather".to_string(), 0)],
+        numsub
+    );
+    assert_eq!(
+        vec!["news.tech"],
+        client.pubsub_channels(None).await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn sharded_pubsub() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let mut subscriber = client::connect(addr)
+        .await
+        .unwrap()
+        .ssubscribe(vec!["orders".into()])
+        .await
+        .unwrap();
+    let global = client::connect(addr)
+        .await
+        .unwrap()
+        .subscribe(vec!["orders".into()])
+        .await
+        .unwrap();
+
+    // Shard channels are separate from the global ones
+    assert_eq!(
+        vec!["orders"],
+        client.pubsub_shardchannels(None).await.unwrap()
+    );
+    assert_eq!(
+        vec![("orders".to_string(), 1)],
+        client
+            .pubsub_shardnumsub(vec!["orders".into()])
+            .await
+            .unwrap()
+    );
+    assert_eq!(1, client.spublish("orders", "1".into()).await.unwrap());
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("orders", message.channel);
+    assert_eq!(b"1", &message.content[..]);
+
+    assert_eq!(1, client.publish("orders", "2".into()).await.unwrap());
+    subscriber.sunsubscribe(&[]).await.unwrap();
+    assert!(subscriber.get_subscribed_shard_channels().is_empty());
+    assert_eq!(0, client.spublish("orders", "3".into()).await.unwrap());
+    drop(global);
+
+    // In cluster mode, shard channels are routed as keys
+    let config = server::Config {
+        cluster_enabled: true,
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _) = start_server_with_config(config).await;
+    let mut client = client::connect(addr).await.unwrap();
+    client.cluster_addslotsrange(0, 8191).await.unwrap();
+
+    let mut subscriber = client::connect(addr)
+        .await
+        .unwrap()
+        .ssubscribe(vec!["bar".into()])
+        .await
+        .unwrap();
+    assert!(subscriber
+        .ssubscribe(&["foo".into()])
+        .await
+        .unwrap_err()
+        .to_string()
+        .starts_with("CLUSTERDOWN"));
+    assert!(client
+        .spublish("foo", "1".into())
+        .await
+        .unwrap_err()
+        .to_string()
+        .starts_with("CLUSTERDOWN"));
+    assert_eq!(1, client.spublish("bar", "1".into()).await.unwrap());
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("bar", message.channel);
+}
+
+#[tokio::test]
+async fn client_tracking() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    let mut redirect = client::connect(addr).await.unwrap();
+    let id = redirect.client_id().await.unwrap();
+    assert_ne!(id, client.client_id().await.unwrap());
+    let mut subscriber = redirect
+        .subscribe(vec!["__redis__:invalidate".into()])
+        .await
+        .unwrap();
+
+    assert!(client
+        .client_tracking(true, Some(id + 100))
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("does not exist"));
+    assert!(client
+        .client_tracking(true, None)
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("RESP3"));
+    client.client_tracking(true, Some(id)).await.unwrap();
+
+    // Only the keys read are tracked, until their next modification
+    other.set("foo", "1".into()).await.unwrap();
+    other.set("bar", "1".into()).await.unwrap();
+    client.get("foo").await.unwrap();
+    other.set("bar", "2".into()).await.unwrap();
+    other.set("foo", "2".into()).await.unwrap();
+    other.set("foo", "3".into()).await.unwrap();
+    client.get("bar").await.unwrap();
+    other.del(vec!["bar".into()]).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__redis__:invalidate", message.channel);
+    assert_eq!(b"foo", &message.content[..]);
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"bar", &message.content[..]);
+
+    // Publishing to the channel does not reach the redirected connections
+    assert_eq!(
+        0,
+        other
+            .publish("__redis__:invalidate", "baz".into())
+            .await
+            .unwrap()
+    );
+
+    client.client_tracking(false, None).await.unwrap();
+    client.get("foo").await.unwrap();
+    other.set("foo", "4".into()).await.unwrap();
+    client.client_tracking(true, Some(id)).await.unwrap();
+    client.get("baz").await.unwrap();
+    other.set("baz", "1".into()).await.unwrap();
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"baz", &message.content[..]);
+}
+
+#[tokio::test]
+async fn client_tracking_resp3() {
+    let (addr, _) = start_server().await;
+    let mut other = client::connect(addr).await.unwrap();
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let command = |args: &[&str]| {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes())))
+                .collect(),
+        )
+    };
+
+    connection
+        .write_frame(&command(&["hello", "3"]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        connection.read_frame().await.unwrap(),
+        Some(Frame::Map(_))
+    ));
+    connection
+        .write_frame(&command(&["client", "tracking", "on"]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        connection.read_frame().await.unwrap(),
+        Some(Frame::Simple(reply)) if reply == "OK"
+    ));
+    connection
+        .write_frame(&command(&["get", "foo"]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        connection.read_frame().await.unwrap(),
+        Some(Frame::Null)
+    ));
+
+    // The invalidated keys are pushed to the tracking connection itself
+    other.set("foo", "1".into()).await.unwrap();
+    match connection.read_frame().await.unwrap() {
+        Some(Frame::Push(parts)) => match &parts[..] {
+            [Frame::Bulk(kind), Frame::Array(keys)] => {
+                assert_eq!(b"invalidate", &kind[..]);
+                assert!(matches!(&keys[..], [Frame::Bulk(key)] if key == "foo"));
+            }
+            parts => panic!("unexpected push {:?}", parts),
+        },
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn auth_requirepass() {
+    let config = server::Config {
+        requirepass: Some("secret".into()),
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _) = start_server_with_config(config).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(client
+        .get("foo")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("NOAUTH"));
+    assert!(client
+        .auth("wrong")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("WRONGPASS"));
+    assert!(client
+        .multi()
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("NOAUTH"));
+
+    client.auth("secret").await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    let mut other = client::connect_with_password(addr, "secret").await.unwrap();
+    assert_eq!(Some("bar".into()), other.get("foo").await.unwrap());
+    assert!(client::connect_with_password(addr, "wrong").await.is_err());
+
+    // Without a password, `AUTH` is an error
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert!(client
+        .auth("secret")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("without any password configured"));
+    client.set("foo", "bar".into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn hello_auth_setname() {
+    let config = server::Config {
+        requirepass: Some("secret".into()),
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _) = start_server_with_config(config).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let command = |args: &[&str]| {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes())))
+                .collect(),
+        )
+    };
+    let error = |frame: Option<Frame>| match frame {
+        Some(Frame::Error(err)) => err,
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+
+    // `HELLO` is only accepted before authenticating if it authenticates
+    connection
+        .write_frame(&command(&["hello", "3"]))
+        .await
+        .unwrap();
+    assert!(error(connection.read_frame().await.unwrap()).starts_with("NOAUTH"));
+    connection
+        .write_frame(&command(&["hello", "3", "auth", "default", "wrong"]))
+        .await
+        .unwrap();
+    assert!(error(connection.read_frame().await.unwrap()).starts_with("WRONGPASS"));
+    connection
+        .write_frame(&command(&["hello", "3", "setname", "a b"]))
+        .await
+        .unwrap();
+    assert!(error(connection.read_frame().await.unwrap()).starts_with("NOAUTH"));
+
+    connection
+        .write_frame(&command(&[
+            "hello", "3", "auth", "default", "secret", "setname", "cache",
+        ]))
+        .await
+        .unwrap();
+    match connection.read_frame().await.unwrap() {
+        Some(Frame::Map(fields)) => assert!(fields.iter().any(|field| matches!(
+            field,
+            (Frame::Bulk(name), Frame::Integer(3)) if name == "proto"
+        ))),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+    connection
+        .write_frame(&command(&["client", "getname"]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        connection.read_frame().await.unwrap(),
+        Some(Frame::Bulk(name)) if name == "cache"
+    ));
+
+    // The protocol is left unchanged if the name is invalid
+    connection
+        .write_frame(&command(&["hello", "2", "setname", "a b"]))
+        .await
+        .unwrap();
+    assert!(error(connection.read_frame().await.unwrap()).contains("Client names"));
+    connection.write_frame(&command(&["hello"])).await.unwrap();
+    assert!(matches!(
+        connection.read_frame().await.unwrap(),
+        Some(Frame::Map(_))
+    ));
+}
+
+#[tokio::test]
+async fn acl_users() {
+    let (addr, _) = start_server().await;
+    let mut admin = client::connect(addr).await.unwrap();
+    assert_eq!("default", admin.acl_whoami().await.unwrap());
+
+    let rules = vec!["on", ">secret", "+@read", "+set", "~cache:*", "&news"];
+    admin
+        .acl_setuser("alice", rules.into_iter().map(String::from).collect())
+        .await
+        .unwrap();
+    admin.set("cache:a", "1".into()).await.unwrap();
+    admin.set("other", "2".into()).await.unwrap();
+
+    let mut alice = client::connect(addr).await.unwrap();
+    assert!(alice
+        .auth_user("alice", "wrong")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("WRONGPASS"));
+    alice.auth_user("alice", "secret").await.unwrap();
+
+    assert_eq!(Some("1".into()), alice.get("cache:a").await.unwrap());
+    alice.set("cache:b", "3".into()).await.unwrap();
+    let err = alice.get("other").await.unwrap_err().to_string();
+    assert_eq!("NOPERM No permissions to access a key", err);
+    let err = alice
+        .del(vec!["cache:a".into()])
+        .await
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        "NOPERM User alice has no permissions to run the 'del' command",
+        err
+    );
+    alice.publish("news", "hi".into()).await.unwrap_err();
+
+    admin
+        .acl_setuser("alice", vec!["+publish".into()])
+        .await
+        .unwrap();
+    alice.publish("news", "hi".into()).await.unwrap();
+    let err = alice.publish("sports", "hi".into()).await.unwrap_err();
+    assert_eq!("NOPERM No permissions to access a channel", err.to_string());
+
+    let user = admin.acl_getuser("alice").await.unwrap().unwrap();
+    assert_eq!(
+        vec!["flags", "passwords", "commands", "keys", "channels"],
+        user.iter()
+            .map(|(field, _)| field.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(user[2].1, "-@all +@read +set +publish");
+    assert_eq!(user[3].1, "~cache:*");
+    assert!(admin.acl_getuser("bob").await.unwrap().is_none());
+
+    let list = admin.acl_list().await.unwrap();
+    assert_eq!("user default on nopass ~* &* +@all", list[1]);
+    assert!(list[0].starts_with("user alice on #"));
+
+    // Invalid rules leave the user unchanged
+    assert!(admin
+        .acl_setuser("alice", vec!["off".into(), "+@nothing".into()])
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("Unknown command or category"));
+    assert_eq!(Some("1".into()), alice.get("cache:a").await.unwrap());
+    assert!(admin
+        .acl_setuser("alice", vec!["+nosuchcmd".into()])
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("Unknown command or category"));
+
+    // Passwords are stored as their SHA-256 digest, so that the listed rules
+    // recreate the user.
+    let hash = "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b";
+    assert!(matches!(&user[1].1, Frame::Array(passwords) if passwords[..] == [hash]));
+    let rules = list[0]["user alice ".len()..]
+        .split(' ')
+        .map(String::from)
+        .collect();
+    admin.acl_setuser("carol", rules).await.unwrap();
+    let list = admin.acl_list().await.unwrap();
+    assert_eq!(
+        list[0]["user alice ".len()..],
+        list[1]["user carol ".len()..]
+    );
+    let mut carol = client::connect(addr).await.unwrap();
+    carol.auth_user("carol", "secret").await.unwrap();
+
+    admin
+        .acl_setuser("carol", vec![format!("!{}", hash)])
+        .await
+        .unwrap();
+    assert!(carol.auth_user("carol", "secret").await.is_err());
+    for rule in [
+        format!("!{}", hash),
+        "#abc".to_string(),
+        "#".to_string() + &"A".repeat(64),
+    ] {
+        assert!(admin.acl_setuser("carol", vec![rule]).await.is_err());
+    }
+
+    // Commands belong to several categories.
+    admin
+        .acl_setuser(
+            "carol",
+            vec![format!("#{}", hash), "+@all".into(), "-@write".into()],
+        )
+        .await
+        .unwrap();
+    carol.auth_user("carol", "secret").await.unwrap();
+    carol.function_list(None, false).await.unwrap();
+    let err = carol
+        .function_load("#!lua name=lib\nreturn", false)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "NOPERM User carol has no permissions to run the 'function' command",
+        err.to_string()
+    );
+
+    assert!(admin.acl_deluser(vec!["default".into()]).await.is_err());
+    assert_eq!(
+        1,
+        admin
+            .acl_deluser(vec!["alice".into(), "bob".into()])
+            .await
+            .unwrap()
+    );
+    let err = alice.get("cache:a").await.unwrap_err().to_string();
+    assert!(err.contains("NOAUTH"));
+}
+
+#[tokio::test]
+async fn unix_socket() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}.sock", std::process::id()));
+    let config = server::Config {
+        unixsocket: Some(path.clone()),
+        ..server::Config::default()
+    };
+    let (addr, shutdown, handle) = start_server_with_config(config).await;
+
+    // The socket is bound once the server task starts
+    while !path.exists() {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let mut client = client::connect_unix(&path).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    // Both listeners serve the same databases
+    let mut other = client::connect(addr).await.unwrap();
+    assert_eq!(Some("bar".into()), other.get("foo").await.unwrap());
+
+    drop(client);
+    drop(other);
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn config_parameters() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(
+        vec![
+            ("maxclients".to_string(), "10000".to_string()),
+            ("maxclients-policy".to_string(), "reject".to_string()),
+            ("maxmemory".to_string(), "0".to_string()),
+            ("maxmemory-policy".to_string(), "noeviction".to_string()),
+            ("maxmemory-samples".to_string(), "5".to_string()),
+            ("proto-max-bulk-len".to_string(), "536870912".to_string()),
+            ("slowlog-max-len".to_string(), "128".to_string()),
+        ],
+        client.config_get("*MAX*").await.unwrap()
+    );
+
+    client.config_set("maxmemory", "2mb").await.unwrap();
+    client
+        .config_set("proto-max-bulk-len", "1mb")
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            ("maxclients".to_string(), "10000".to_string()),
+            ("maxclients-policy".to_string(), "reject".to_string()),
+            ("maxmemory".to_string(), "2097152".to_string()),
+            ("maxmemory-policy".to_string(), "noeviction".to_string()),
+            ("maxmemory-samples".to_string(), "5".to_string()),
+            ("proto-max-bulk-len".to_string(), "1048576".to_string()),
+            ("slowlog-max-len".to_string(), "128".to_string()),
+        ],
+        client.config_get("*max*").await.unwrap()
+    );
+    assert!(client
+        .setrange("foo", 1048576, "bar".into())
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("proto-max-bulk-len"));
+
+    assert!(client
+        .config_set("databases", "4")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("immutable"));
+    assert!(client.config_set("maxmemory", "1tb").await.is_err());
+    assert_eq!(
+        vec![("databases".to_string(), "16".to_string())],
+        client.config_get("databases").await.unwrap()
+    );
+
+    // Idle clients are disconnected once the timeout elapses
+    client.config_set("timeout", "1").await.unwrap();
+    time::sleep(Duration::from_millis(1500)).await;
+    assert!(client.get("foo").await.is_err());
+}
+
+#[tokio::test]
+async fn info_sections() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    client
+        .set_expires("baz", "qux".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client.get("foo").await.unwrap();
+    client.get("missing").await.unwrap();
+
+    let info = client.info(None).await.unwrap();
+    for section in &["# Server", "# Clients", "# Memory", "# Stats", "# Keyspace"] {
+        assert!(info.contains(section), "{}", info);
+    }
+    assert!(info.contains("role:master\r\n"));
+    assert!(info.contains("connected_clients:1\r\n"));
+    assert!(info.contains("keyspace_hits:1\r\n"));
+    assert!(info.contains("keyspace_misses:1\r\n"));
+    assert!(info.contains("db0:keys=2,expires=1\r\n"));
+
+    let stats = client.info(Some("stats")).await.unwrap();
+    assert!(stats.starts_with("# Stats\r\n"));
+    assert!(stats.contains("total_commands_processed:6\r\n"));
+    assert!(!stats.contains("# Keyspace"));
+}
+
+#[tokio::test]
+async fn command_introspection() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let count = client.command_count().await.unwrap();
+    assert!(count > 100);
+    assert_eq!(
+        count as usize,
+        client.command_info(&[]).await.unwrap().len()
+    );
+
+    let info = client
+        .command_info(&["GET", "mset", "nosuch"])
+        .await
+        .unwrap();
+    let get = info[0].as_ref().unwrap();
+    assert_eq!("get", get.name);
+    assert_eq!(2, get.arity);
+    assert_eq!(vec!["readonly", "fast"], get.flags);
+    assert_eq!((1, 1, 1), (get.first_key, get.last_key, get.step));
+    let mset = info[1].as_ref().unwrap();
+    assert_eq!(-3, mset.arity);
+    assert_eq!((1, -1, 2), (mset.first_key, mset.last_key, mset.step));
+    assert!(info[2].is_none());
+
+    let docs = client.command_docs(&["get", "nosuch"]).await.unwrap();
+    assert_eq!(1, docs.len());
+    assert_eq!("get", docs[0].0);
+    assert!(docs[0]
+        .1
+        .contains(&("group".to_string(), "string".to_string())));
+}
+
+#[tokio::test]
+async fn monitor_commands() {
+    let (addr, _) = start_server().await;
+    let mut monitor = client::connect(addr)
+        .await
+        .unwrap()
+        .monitor()
+        .await
+        .unwrap();
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("foo", "with \"quotes\"\n".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    client.get("foo").await.unwrap();
+
+    let set = monitor.next_command().await.unwrap().unwrap();
+    let (time, set) = set.split_once(' ').unwrap();
+    assert!(time.parse::<f64>().unwrap() > 0.0);
+    let local = &set[3..set.find(']').unwrap()];
+    assert!(local.starts_with("127.0.0.1:"));
+    assert_eq!(
+        format!("[0 {}] \"set\" \"foo\" \"with \\\"quotes\\\"\\n\"", local),
+        set
+    );
+
+    let select = monitor.next_command().await.unwrap().unwrap();
+    assert!(select.ends_with(&format!("[0 {}] \"select\" \"1\"", local)));
+    let get = monitor.next_command().await.unwrap().unwrap();
+    assert!(get.ends_with(&format!("[1 {}] \"get\" \"foo\"", local)));
+}
+
+#[tokio::test]
+async fn slowlog_entries() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.slowlog_len().await.unwrap());
+    client
+        .config_set("slowlog-log-slower-than", "0")
+        .await
+        .unwrap();
+    client.config_set("slowlog-max-len", "2").await.unwrap();
+
+    client.set("foo", "bar".into()).await.unwrap();
+    client.get("foo").await.unwrap();
+    client.del(vec!["foo".to_string()]).await.unwrap();
+
+    let entries = client.slowlog_get(None).await.unwrap();
+    assert_eq!(2, entries.len());
+    assert!(entries[0].id > entries[1].id);
+    assert_eq!(vec!["del", "foo"], entries[0].args);
+    assert_eq!(vec!["get", "foo"], entries[1].args);
+    assert!(entries[0].addr.starts_with("127.0.0.1:"));
+    assert_eq!(1, client.slowlog_get(Some(1)).await.unwrap().len());
+    assert_eq!(2, client.slowlog_len().await.unwrap());
+
+    client.slowlog_reset().await.unwrap();
+    // `SLOWLOG RESET` itself is recorded once it completes.
+    assert_eq!(1, client.slowlog_len().await.unwrap());
+
+    client
+        .config_set("slowlog-log-slower-than", "-1")
+        .await
+        .unwrap();
+    client.slowlog_reset().await.unwrap();
+    client.get("foo").await.unwrap();
+    assert_eq!(0, client.slowlog_len().await.unwrap());
+}
+
+#[tokio::test]
+async fn client_list_and_kill() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+    let other_id = other.client_id().await.unwrap();
+
+    assert_eq!(None, client.client_getname().await.unwrap());
+    assert!(client.client_setname("with space").await.is_err());
+    client.client_setname("main").await.unwrap();
+    assert_eq!(Some("main".into()), client.client_getname().await.unwrap());
+    other.select(2).await.unwrap();
+
+    let list = client.client_list().await.unwrap();
+    let lines: Vec<&str> = list.lines().collect();
+    assert_eq!(2, lines.len());
+    assert!(lines[0].contains(" name=main "));
+    assert!(lines[0].contains(" cmd=client "));
+    assert!(lines[0].contains(" user=default"));
+    assert!(lines[1].starts_with(&format!("id={} addr=127.0.0.1:", other_id)));
+    assert!(lines[1].contains(" db=2 cmd=select "));
+
+    // The address of the other client, as listed
+    let other_addr = lines[1].split(' ').nth(1).unwrap()["addr=".len()..].to_string();
+
+    assert_eq!(
+        0,
+        client
+            .client_kill(Some(other_id + 100), None, true)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        0,
+        client
+            .client_kill(Some(other_id), Some("1.2.3.4:5"), true)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        1,
+        client
+            .client_kill(Some(other_id), Some(&other_addr), true)
+            .await
+            .unwrap()
+    );
+    assert!(other.get("foo").await.is_err());
+
+    assert!(client.client_kill_addr(&other_addr).await.is_err());
+    let mut other = client::connect(addr).await.unwrap();
+    assert_eq!(1, client.client_kill(None, None, true).await.unwrap());
+    assert!(other.get("foo").await.is_err());
+    assert!(client.get("foo").await.is_ok());
+}
+
+#[tokio::test]
+async fn client_pause() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    // Only the writes are held
+    client
+        .client_pause(Duration::from_secs(10), true)
+        .await
+        .unwrap();
+    assert_eq!(None, other.get("foo").await.unwrap());
+    let set = tokio::spawn(async move {
+        other.set("foo", "bar".into()).await.unwrap();
+        other
+    });
+    time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(None, client.get("foo").await.unwrap());
+
+    client.client_unpause().await.unwrap();
+    let mut other = set.await.unwrap();
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    // Every command is held until the pause expires
+    client
+        .client_pause(Duration::from_millis(100), false)
+        .await
+        .unwrap();
+    let start = Instant::now();
+    assert_eq!(Some("bar".into()), other.get("foo").await.unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(90));
+}
+
+#[tokio::test]
+async fn debug_commands() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("num", "12".into()).await.unwrap();
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    client
+        .set_expires("str", "abc".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        "Value type:string encoding:int serializedlength:2 ttl:-1",
+        client.debug_object("num").await.unwrap()
+    );
+    assert!(client
+        .debug_object("list")
+        .await
+        .unwrap()
+        .starts_with("Value type:list encoding:quicklist serializedlength:1 "));
+    let object = client.debug_object("str").await.unwrap();
+    let ttl: u64 = object.rsplit("ttl:").next().unwrap().parse().unwrap();
+    assert!(ttl > 90_000 && ttl <= 100_000);
+    assert!(client.debug_object("missing").await.is_err());
+
+    // Expired keys stay until the purge is enabled again, or until they are
+    // accessed
+    client.debug_set_active_expire(false).await.unwrap();
+    client
+        .set_expires("foo", "bar".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    client
+        .set_expires("baz", "qux".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    time::sleep(Duration::from_millis(50)).await;
+    assert!(client.debug_object("foo").await.is_ok());
+    assert_eq!(None, client.get("foo").await.unwrap());
+    assert!(client.debug_object("foo").await.is_err());
+    assert!(client.debug_object("baz").await.is_ok());
+    client.debug_set_active_expire(true).await.unwrap();
+    time::sleep(Duration::from_millis(10)).await;
+    assert!(client.debug_object("baz").await.is_err());
+
+    let start = Instant::now();
+    client.debug_sleep(Duration::from_millis(50)).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let sleep = |seconds: &str| {
+        Frame::Array(vec![
+            Frame::Bulk("debug".into()),
+            Frame::Bulk("sleep".into()),
+            Frame::Bulk(Bytes::copy_from_slice(seconds.as_bytes())),
+        ])
+    };
+    for seconds in &["1e300", "1e20", "-1", "-0.5", "inf", "-inf"] {
+        connection.write_frame(&sleep(seconds)).await.unwrap();
+        assert!(matches!(
+            connection.read_frame().await.unwrap(),
+            Some(Frame::Error(err)) if err == "ERR value is out of range"
+        ));
+    }
+
+    // Neither the connection nor the server went down
+    connection.write_frame(&sleep("0")).await.unwrap();
+    assert!(matches!(
+        connection.read_frame().await.unwrap(),
+        Some(Frame::Simple(ok)) if ok == "OK"
+    ));
+    assert_eq!(Some("12".into()), client.get("num").await.unwrap());
+
+    let info = client.info(Some("replication")).await.unwrap();
+    client.debug_change_repl_id().await.unwrap();
+    assert_ne!(info, client.info(Some("replication")).await.unwrap());
+}
+
+#[tokio::test]
+async fn shutdown_command() {
+    // Without snapshots, `SHUTDOWN SAVE` fails and the server keeps running
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert!(client.shutdown(Some(true)).await.is_err());
+    client.set("foo", "bar".into()).await.unwrap();
+
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-shutdown.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (addr, _shutdown, handle) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    client.shutdown(Some(false)).await.unwrap();
+    handle.await.unwrap().unwrap();
+    assert!(other.get("foo").await.is_err());
+    assert!(!path.exists());
+
+    // By default, the last snapshot is saved
+    let (addr, _shutdown, handle) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    client.shutdown(None).await.unwrap();
+    handle.await.unwrap().unwrap();
+
+    let (addr, _shutdown, _) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn latency_monitor() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    // Monitoring is disabled by default
+    client.debug_sleep(Duration::from_millis(20)).await.unwrap();
+    assert!(client.latency_latest().await.unwrap().is_empty());
+    assert!(client.latency_doctor().await.unwrap().contains("disabled"));
+
+    client
+        .config_set("latency-monitor-threshold", "10")
+        .await
+        .unwrap();
+    client.get("foo").await.unwrap();
+    assert!(client.latency_latest().await.unwrap().is_empty());
+
+    client.debug_sleep(Duration::from_millis(20)).await.unwrap();
+    let latest = client.latency_latest().await.unwrap();
+    assert_eq!(1, latest.len());
+    assert_eq!("command", latest[0].event);
+    assert!(latest[0].latency >= Duration::from_millis(20));
+    assert_eq!(latest[0].latency, latest[0].max);
+
+    let history = client.latency_history("command").await.unwrap();
+    assert_eq!(1, history.len());
+    assert_eq!(latest[0].latency, history[0].1);
+    assert!(client.latency_history("snapshot").await.unwrap().is_empty());
+    assert!(client.latency_doctor().await.unwrap().contains("command"));
+
+    assert_eq!(0, client.latency_reset(&["snapshot"]).await.unwrap());
+    assert_eq!(1, client.latency_reset(&[]).await.unwrap());
+    assert!(client.latency_latest().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn memory_usage() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.memory_usage("foo", None).await.unwrap());
+
+    client.set("foo", vec![b'a'; 1000].into()).await.unwrap();
+    let string = client.memory_usage("foo", None).await.unwrap().unwrap();
+    assert!(string > 1000 && string < 1500);
+
+    let values: Vec<Bytes> = (0..100).map(|_| vec![b'b'; 100].into()).collect();
+    client.rpush("list", values).await.unwrap();
+    let sampled = client.memory_usage("list", None).await.unwrap().unwrap();
+    let measured = client.memory_usage("list", Some(0)).await.unwrap().unwrap();
+    assert!(measured > 100 * 100);
+    assert_eq!(sampled, measured);
+
+    let stats = client.memory_stats().await.unwrap();
+    let stat = |name: &str| match stats.iter().find(|(field, _)| field == name) {
+        Some((_, Frame::Integer(value))) => *value as u64,
+        stat => panic!("unexpected {:?}", stat),
+    };
+    assert_eq!(2, stat("keys.count"));
+    assert!(stat("total.allocated") >= string + measured);
+    assert!(stat("peak.allocated") >= stat("total.allocated"));
+    assert!(stats.iter().any(|(field, _)| field == "db.0"));
+
+    // The memory used is tracked as keys are written
+    client.del(vec!["list".into()]).await.unwrap();
+    let stats = client.memory_stats().await.unwrap();
+    let total = match stats.iter().find(|(field, _)| field == "total.allocated") {
+        Some((_, Frame::Integer(total))) => *total as u64,
+        stat => panic!("unexpected {:?}", stat),
+    };
+    assert!(total >= string && total < string + 1000);
+}
+
+#[tokio::test]
+async fn maxmemory_eviction() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    // By default, commands which may use more memory are rejected
+    client.set("foo", "bar".into()).await.unwrap();
+    client.config_set("maxmemory", "1").await.unwrap();
+    let err = client.set("baz", "qux".into()).await.unwrap_err();
+    assert!(err.to_string().starts_with("OOM "));
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+    assert_eq!(1, client.del(vec!["foo".into()]).await.unwrap());
+
+    client.config_set("maxmemory", "20kb").await.unwrap();
+    client
+        .config_set("maxmemory-policy", "allkeys-lru")
+        .await
+        .unwrap();
+    assert!(client
+        .config_set("maxmemory-policy", "unknown")
+        .await
+        .is_err());
+    for i in 0..100 {
+        client
+            .set(&format!("key:{}", i), vec![b'a'; 1000].into())
+            .await
+            .unwrap();
+    }
+    let keys = client.dbsize().await.unwrap();
+    assert!(keys > 10 && keys < 30);
+    assert_eq!(
+        Some(vec![b'a'; 1000].into()),
+        client.get("key:99").await.unwrap()
+    );
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(info.contains(&format!("evicted_keys:{}\r\n", 100 - keys)));
+
+    // Only the keys with an expiration are evicted by the volatile policies
+    client.flushdb(false).await.unwrap();
+    client
+        .config_set("maxmemory-policy", "volatile-ttl")
+        .await
+        .unwrap();
+    client
+        .set("persistent", vec![b'a'; 1000].into())
+        .await
+        .unwrap();
+    for i in 0..100 {
+        client
+            .set_expires(
+                &format!("key:{}", i),
+                vec![b'a'; 1000].into(),
+                Duration::from_secs(1000 - i),
+            )
+            .await
+            .unwrap();
+    }
+    assert!(client.dbsize().await.unwrap() < 30);
+    assert!(client.get("persistent").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn active_and_lazy_expiration() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let key_count = || async {
+        let stats = client::connect(addr)
+            .await
+            .unwrap()
+            .memory_stats()
+            .await
+            .unwrap();
+        match stats.iter().find(|(field, _)| field == "keys.count") {
+            Some((_, Frame::Integer(count))) => *count,
+            stat => panic!("unexpected {:?}", stat),
+        }
+    };
+
+    // A burst of expirations is purged over several batches
+    for i in 0..1000 {
+        client
+            .set_expires(&format!("key:{}", i), "a".into(), Duration::from_millis(10))
+            .await
+            .unwrap();
+    }
+    client.set("persistent", "a".into()).await.unwrap();
+    time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(1, key_count().await);
+
+    // Without the background task, expired keys are removed when accessed
+    client.debug_set_active_expire(false).await.unwrap();
+    for key in &["foo", "bar"] {
+        client
+            .set_expires(key, "a".into(), Duration::from_millis(10))
+            .await
+            .unwrap();
+    }
+    time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(3, key_count().await);
+    assert_eq!(0, client.exists(vec!["foo".into()]).await.unwrap());
+    assert_eq!(2, key_count().await);
+    assert_eq!(-2, client.ttl("bar").await.unwrap());
+    assert_eq!(1, key_count().await);
+}
+
+#[tokio::test]
+async fn expirations_far_and_near() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let day = Duration::from_secs(24 * 60 * 60);
+    for (key, ttl) in &[
+        ("soon", Duration::from_millis(20)),
+        ("later", Duration::from_millis(150)),
+        ("far", 3 * day),
+        ("moved", 3 * day),
+        ("persisted", Duration::from_millis(50)),
+    ] {
+        client.set_expires(key, "a".into(), *ttl).await.unwrap();
+    }
+    assert!(client.pexpire("moved", 100, None).await.unwrap());
+    assert!(client.persist("persisted").await.unwrap());
+    assert_eq!(5, client.dbsize().await.unwrap());
+
+    // The keys expiring first are purged, whatever expiration they had
+    time::sleep(Duration::from_millis(300)).await;
+    let stats = client.memory_stats().await.unwrap();
+    let keys = stats.iter().find(|(field, _)| field == "keys.count");
+    assert!(matches!(keys, Some((_, Frame::Integer(2)))));
+    assert_eq!(2, client.dbsize().await.unwrap());
+    assert!(client.ttl("far").await.unwrap() > 2 * 24 * 60 * 60);
+    assert_eq!(-1, client.ttl("persisted").await.unwrap());
+}
+
+#[tokio::test]
+async fn dump_and_restore() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client
+        .hset("hash", vec![("f".into(), "v".into())])
+        .await
+        .unwrap();
+    assert_eq!(None, client.dump("missing").await.unwrap());
+
+    // Values are restored along with their type
+    let list = client.dump("list").await.unwrap().unwrap();
+    let hash = client.dump("hash").await.unwrap().unwrap();
+    client
+        .restore("list2", None, list.clone(), false)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![Bytes::from("a"), Bytes::from("b")],
+        client.lrange("list2", 0, -1).await.unwrap()
+    );
+    assert_eq!(-1, client.ttl("list2").await.unwrap());
+    client
+        .restore("hash2", Some(Duration::from_secs(100)), hash, false)
+        .await
+        .unwrap();
+    assert_eq!(Some("v".into()), client.hget("hash2", "f").await.unwrap());
+    assert!(client.ttl("hash2").await.unwrap() > 90);
+
+    // Existing keys are only overwritten with `REPLACE`
+    let err = client
+        .restore("hash2", None, list.clone(), false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("BUSYKEY"));
+    client
+        .restore("hash2", None, list.clone(), true)
+        .await
+        .unwrap();
+    assert_eq!(2, client.llen("hash2").await.unwrap());
+    assert_eq!(-1, client.ttl("hash2").await.unwrap());
+
+    // An absolute expiration in the past removes the key
+    client
+        .restore_at("hash2", UNIX_EPOCH, list.clone(), true)
+        .await
+        .unwrap();
+    assert_eq!(0, client.exists(vec!["hash2".into()]).await.unwrap());
+
+    // Corrupted values are rejected
+    let mut corrupted = list.to_vec();
+    corrupted[0] ^= 1;
+    let err = client
+        .restore("corrupted", None, corrupted.into(), false)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR DUMP payload version or checksum are wrong",
+        err.to_string()
+    );
+
+    // So are values with a valid checksum which cannot be deserialized, such
+    // as sorted sets with NaN scores
+    client.zadd("zset", vec![(1.0, "m".into())]).await.unwrap();
+    let mut nan = client.dump("zset").await.unwrap().unwrap().to_vec();
+    let len = nan.len();
+    nan[len - 18..len - 10].copy_from_slice(&f64::NAN.to_be_bytes());
+    let checksum = crc64(&nan[..len - 8]);
+    nan[len - 8..].copy_from_slice(&checksum.to_be_bytes());
+    let err = client
+        .restore("nan", None, nan.clone().into(), false)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Bad data format", err.to_string());
+    assert_eq!(0, client.exists(vec!["nan".into()]).await.unwrap());
+
+    // Replacing a key with such a value leaves the key untouched
+    let err = client
+        .restore("zset", None, nan.clone().into(), true)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Bad data format", err.to_string());
+    assert_eq!(Some(1.0), client.zscore("zset", "m".into()).await.unwrap());
+
+    // Values followed by trailing bytes are not valid either
+    let dump = client.dump("zset").await.unwrap().unwrap();
+    let len = dump.len();
+    let mut trailing = dump[..len - 10].to_vec();
+    trailing.push(0);
+    trailing.extend_from_slice(&dump[len - 10..len - 8]);
+    let checksum = crc64(&trailing);
+    trailing.extend_from_slice(&checksum.to_be_bytes());
+    let err = client
+        .restore("trailing", None, trailing.into(), false)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Bad data format", err.to_string());
+}
+
+#[tokio::test]
+async fn migrate_keys() {
+    let (addr, _) = start_server().await;
+    let (target_addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    let mut target = client::connect(target_addr).await.unwrap();
+    let host = target_addr.ip().to_string();
+    let port = target_addr.port();
+    let timeout = Duration::from_secs(1);
+
+    client.set("foo", "1".into()).await.unwrap();
+    client
+        .set_expires("bar", "2".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    client.set("baz", "3".into()).await.unwrap();
+
+    // Keys are removed once transferred, along with their expiration
+    let keys = vec!["foo".into(), "bar".into(), "missing".into()];
+    assert!(client
+        .migrate(&host, port, keys, 1, timeout, false)
+        .await
+        .unwrap());
+    assert_eq!(
+        0,
+        client
+            .exists(vec!["foo".into(), "bar".into()])
+            .await
+            .unwrap()
+    );
+    target.select(1).await.unwrap();
+    assert_eq!(Some("1".into()), target.get("foo").await.unwrap());
+    assert!(target.ttl("bar").await.unwrap() > 90);
+    assert!(!client
+        .migrate(&host, port, vec!["foo".into()], 1, timeout, false)
+        .await
+        .unwrap());
+
+    // Existing keys are only overwritten with `REPLACE`, copied keys are kept
+    target.set("baz", "old".into()).await.unwrap();
+    let err = client
+        .migrate_copy(&host, port, vec!["baz".into()], 1, timeout, false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("BUSYKEY"));
+    assert!(client
+        .migrate_copy(&host, port, vec!["baz".into()], 1, timeout, true)
+        .await
+        .unwrap());
+    assert_eq!(Some("3".into()), target.get("baz").await.unwrap());
+    assert_eq!(Some("3".into()), client.get("baz").await.unwrap());
+
+    // Nothing is removed when the target server cannot be reached
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed = listener.local_addr().unwrap().port();
+    drop(listener);
+    let err = client
+        .migrate(&host, closed, vec!["baz".into()], 0, timeout, false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("IOERR"));
+    assert_eq!(Some("3".into()), client.get("baz").await.unwrap());
+}
+
+#[tokio::test]
+async fn reset_connection() {
+    let config = server::Config {
+        requirepass: Some("secret".into()),
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _) = start_server_with_config(config).await;
+    let mut client = client::connect_with_password(addr, "secret").await.unwrap();
+    let mut other = client::connect_with_password(addr, "secret").await.unwrap();
+
+    // The selected database, the name, the watched keys and the transaction
+    // are reset
+    client.select(1).await.unwrap();
+    client.client_setname("pooled").await.unwrap();
+    client.watch(vec!["foo".into()]).await.unwrap();
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["set".into(), "foo".into(), "1".into()])
+        .await
+        .unwrap();
+    client.reset().await.unwrap();
+    client.auth("secret").await.unwrap();
+
+    assert_eq!(None, client.client_getname().await.unwrap());
+    client.set("foo", "2".into()).await.unwrap();
+    assert_eq!(Some("2".into()), other.get("foo").await.unwrap());
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["set".into(), "foo".into(), "3".into()])
+        .await
+        .unwrap();
+    other.set("foo", "4".into()).await.unwrap();
+    assert!(client.exec().await.unwrap().is_some());
+
+    // The subscribed state and `MONITOR` are left
+    let subscriber = client.subscribe(vec!["news".into()]).await.unwrap();
+    other.publish("news", "hello".into()).await.unwrap();
+    let mut client = subscriber.reset().await.unwrap();
+    assert_eq!(0, other.publish("news", "hello".into()).await.unwrap());
+
+    client.auth("secret").await.unwrap();
+    let monitor = client.monitor().await.unwrap();
+    other.set("bar", "1".into()).await.unwrap();
+    let mut client = monitor.reset().await.unwrap();
+
+    // The client is no longer authenticated, but may still reset
+    let err = client.get("bar").await.unwrap_err();
+    assert!(err.to_string().contains("NOAUTH"));
+    client.reset().await.unwrap();
+    client.auth("secret").await.unwrap();
+    assert_eq!(Some("1".into()), client.get("bar").await.unwrap());
+}
+
+#[tokio::test]
+async fn role() {
+    let (primary_addr, _primary_shutdown, _) =
+        start_server_with_config(server::Config::default()).await;
+    let mut primary = client::connect(primary_addr).await.unwrap();
+    assert_eq!(
+        ReplicationRole::Primary {
+            offset: 0,
+            replicas: vec![],
+        },
+        primary.role().await.unwrap()
+    );
+
+    let config = server::Config {
+        replica_of: Some(primary_addr.to_string()),
+        ..server::Config::default()
+    };
+    let (replica_addr, _replica_shutdown, _) = start_server_with_config(config).await;
+    let mut replica = client::connect(replica_addr).await.unwrap();
+
+    // The replica reports its primary once connected, and reaches the offset
+    // of the primary as it applies the commands it streams
+    let mut connected = false;
+    for _ in 0..100 {
+        if let ReplicationRole::Replica { state, .. } = replica.role().await.unwrap() {
+            if state == "connected" {
+                connected = true;
+                break;
+            }
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(connected);
+
+    primary.set("foo", "bar".into()).await.unwrap();
+    let mut role = None;
+    for _ in 0..100 {
+        let primary_role = primary.role().await.unwrap();
+        let replica_role = replica.role().await.unwrap();
+        match (&primary_role, &replica_role) {
+            (
+                ReplicationRole::Primary { offset, .. },
+                ReplicationRole::Replica {
+                    offset: Some(reached),
+                    ..
+                },
+            ) if offset == reached && *offset > 0 => {
+                role = Some((primary_role, replica_role));
+                break;
+            }
+            _ => time::sleep(Duration::from_millis(10)).await,
+        }
+    }
+    let (primary_role, replica_role) = role.unwrap();
+
+    assert_eq!(
+        ReplicationRole::Replica {
+            host: primary_addr.ip().to_string(),
+            port: primary_addr.port(),
+            state: "connected".into(),
+            offset: match &primary_role {
+                ReplicationRole::Primary { offset, .. } => Some(*offset),
+                _ => unreachable!(),
+            },
+        },
+        replica_role
+    );
+    match primary_role {
+        ReplicationRole::Primary { replicas, .. } => {
+            assert_eq!(1, replicas.len());
+            assert_eq!(replica_addr.ip().to_string(), replicas[0].host);
+            assert_eq!(replica_addr.port(), replicas[0].port);
+        }
+        _ => unreachable!(),
+    }
+
+    // Once promoted, the replica is a primary
+    replica.replicaof_no_one().await.unwrap();
+    assert!(matches!(
+        replica.role().await.unwrap(),
+        ReplicationRole::Primary { .. }
+    ));
+}
+
+/// `maxmemory` and `maxmemory-policy` may be set when the server starts, as
+/// from a configuration file.
+#[tokio::test]
+async fn maxmemory_config() {
+    let (addr, _shutdown, _) = start_server_with_config(server::Config {
+        parameters: vec![
+            ("maxmemory".into(), "1".into()),
+            ("maxmemory-policy".into(), "allkeys-lru".into()),
+        ],
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    // Every key is evicted to make room for the new one.
+    client.set("foo", "bar".into()).await.unwrap();
+    client.set("baz", "qux".into()).await.unwrap();
+    assert_eq!(None, client.get("foo").await.unwrap());
+    assert_eq!(
+        vec![("maxmemory-policy".to_string(), "allkeys-lru".to_string())],
+        client.config_get("maxmemory-policy").await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn maxclients() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+    client.config_set("maxclients", "2").await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+    other.get("foo").await.unwrap();
+
+    // Connections over `maxclients` are rejected by default
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let mut rejected = Connection::new(socket);
+    match rejected.read_frame().await.unwrap() {
+        Some(Frame::Error(err)) => assert_eq!("ERR max number of clients reached", err),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+    let info = client.info(None).await.unwrap();
+    assert!(info.contains("maxclients:2\r\n"));
+    assert!(info.contains("connected_clients:2\r\n"));
+    assert!(info.contains("rejected_connections:1\r\n"));
+
+    // Or queued until a client disconnects
+    client
+        .config_set("maxclients-policy", "queue")
+        .await
+        .unwrap();
+    let mut queued = tokio::spawn(async move {
+        let mut client = client::connect(addr).await.unwrap();
+        client.get("foo").await.unwrap()
+    });
+    assert!(time::timeout(Duration::from_millis(100), &mut queued)
+        .await
+        .is_err());
+
+    drop(other);
+    assert_eq!(Some("bar".into()), queued.await.unwrap());
+}
+
+/// `maxclients` and `maxclients-policy` may be set when the server starts, as
+/// from a configuration file.
+#[tokio::test]
+async fn maxclients_config() {
+    let (addr, _shutdown, _) = start_server_with_config(server::Config {
+        parameters: vec![
+            ("maxclients".into(), "1".into()),
+            ("maxclients-policy".into(), "queue".into()),
+        ],
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    let mut queued = tokio::spawn(async move {
+        let mut client = client::connect(addr).await.unwrap();
+        client.get("foo").await.unwrap()
+    });
+    assert!(time::timeout(Duration::from_millis(100), &mut queued)
+        .await
+        .is_err());
+
+    drop(client);
+    assert_eq!(Some("bar".into()), queued.await.unwrap());
+}
+
+#[tokio::test]
+async fn rate_limits() {
+    let (addr, _) = start_server().await;
+    let mut admin = client::connect(addr).await.unwrap();
+
+    // Each client may send at most `ratelimit-commands` commands per second
+    admin.config_set("ratelimit-commands", "5").await.unwrap();
+    let mut client = client::connect(addr).await.unwrap();
+    for _ in 0..5 {
+        client.get("foo").await.unwrap();
+    }
+    let err = loop {
+        if let Err(err) = client.get("foo").await {
+            break err;
+        }
+    };
+    assert_eq!(
+        "THROTTLED max number of commands per second reached",
+        err.to_string()
+    );
+    admin.config_set("ratelimit-commands", "0").await.unwrap();
+
+    // And at most `ratelimit-bytes` bytes, a command over the limit delaying
+    // the next ones
+    admin.config_set("ratelimit-bytes", "100").await.unwrap();
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("foo", vec![b'x'; 200].into()).await.unwrap();
+    assert_eq!(
+        "THROTTLED max number of bytes per second reached",
+        client.get("foo").await.unwrap_err().to_string()
+    );
+    admin.config_set("ratelimit-bytes", "0").await.unwrap();
+
+    // The clients of a user share its limits
+    let rules = vec![
+        "on",
+        "nopass",
+        "allcommands",
+        "allkeys",
+        "ratelimit-commands=4",
+    ];
+    admin
+        .acl_setuser("alice", rules.into_iter().map(String::from).collect())
+        .await
+        .unwrap();
+    assert!(admin.acl_list().await.unwrap()[0].ends_with(" ratelimit-commands=4"));
+
+    let mut alice = client::connect(addr).await.unwrap();
+    alice.auth_user("alice", "").await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+    other.auth_user("alice", "").await.unwrap();
+    other.get("foo").await.unwrap();
+    other.get("foo").await.unwrap();
+
+    let mut sent = 0;
+    let err = loop {
+        match alice.get("foo").await {
+            Ok(_) => sent += 1,
+            Err(err) => break err,
+        }
+    };
+    assert!(sent <= 3);
+    assert_eq!(
+        "THROTTLED max number of commands per second of user 'alice' reached",
+        err.to_string()
+    );
+    admin.get("foo").await.unwrap();
+
+    let info = admin.info(Some("stats")).await.unwrap();
+    assert!(info.contains("throttled_commands:3\r\n"));
+}
+
+#[tokio::test]
+async fn rename_commands() {
+    let (addr, _shutdown, _) = start_server_with_config(server::Config {
+        rename_commands: vec![
+            ("FLUSHALL".into(), "".into()),
+            ("flushdb".into(), "secret-flushdb".into()),
+        ],
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    // Disabled commands, and the original name of renamed ones, are unknown
+    assert_eq!(
+        "ERR unknown command 'flushall'",
+        client.flushall(false).await.unwrap_err().to_string()
+    );
+    assert_eq!(
+        "ERR unknown command 'flushdb'",
+        client.flushdb(false).await.unwrap_err().to_string()
+    );
+    assert!(client
+        .eval("return redis.call('flushall')", vec![], vec![])
+        .await
+        .is_err());
+    assert_eq!(Some("bar".into()), client.get("foo").await.unwrap());
+
+    // Renamed commands are run by their new name
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let mut connection = Connection::new(socket);
+    let frame = Frame::Array(vec![Frame::Bulk("SECRET-FLUSHDB".into())]);
+    connection.write_frame(&frame).await.unwrap();
+    match connection.read_frame().await.unwrap() {
+        Some(Frame::Simple(response)) => assert_eq!("OK", response),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+    assert_eq!(None, client.get("foo").await.unwrap());
+}
+
+#[tokio::test]
+async fn protected_mode() {
+    let listener = TcpListener::bind("0.0.0.0:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { server::run(vec![listener], tokio::signal::ctrl_c()).await });
+
+    // Local clients are accepted
+    let mut client = client::connect(("127.0.0.1", port)).await.unwrap();
+    assert_eq!(
+        vec![("protected-mode".to_string(), "yes".to_string())],
+        client.config_get("protected-mode").await.unwrap()
+    );
+
+    // The other ones are rejected, if the host has a non-loopback address
+    let ip = std::net::UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("192.0.2.1:9")?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip());
+    let ip = match ip {
+        Ok(ip) if !ip.is_loopback() && !ip.is_unspecified() => ip,
+        _ => return,
+    };
+    let socket = TcpStream::connect((ip, port)).await.unwrap();
+    let mut rejected = Connection::new(socket);
+    match rejected.read_frame().await.unwrap() {
+        Some(Frame::Error(err)) => assert!(err.starts_with("DENIED ")),
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+
+    // Unless protected mode is disabled
+    client.config_set("protected-mode", "no").await.unwrap();
+    let mut remote = client::connect((ip, port)).await.unwrap();
+    remote.set("foo", "bar".into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn multiple_listeners() {
+    let listeners = server::bind(&["127.0.0.1".into(), "[::1]".into()], 0)
+        .await
+        .unwrap();
+    let addrs: Vec<SocketAddr> = listeners
+        .iter()
+        .map(|listener| listener.local_addr().unwrap())
+        .collect();
+    assert!(addrs[1].is_ipv6());
+
+    // Optional addresses are skipped if they are not available
+    let optional = server::bind(&["127.0.0.1".into(), "-192.0.2.1".into()], 0)
+        .await
+        .unwrap();
+    assert_eq!(1, optional.len());
+    drop(optional);
+    assert!(server::bind(&["192.0.2.1".into()], 0).await.is_err());
+
+    let handle = tokio::spawn(async move { server::run(listeners, tokio::signal::ctrl_c()).await });
+
+    // The listeners serve the same databases
+    let mut client = client::connect(addrs[0]).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    let mut other = client::connect(addrs[1]).await.unwrap();
+    assert_eq!(Some("bar".into()), other.get("foo").await.unwrap());
+
+    // And shut down together
+    drop(other);
+    client.shutdown(None).await.unwrap();
+    handle.await.unwrap().unwrap();
+    assert!(TcpStream::connect(addrs[1]).await.is_err());
+}
+
+#[tokio::test]
+async fn socket_options() {
+    let (addr, _shutdown, _) = start_server_with_config(server::Config {
+        tcp_keepalive: 60,
+        tcp_nodelay: false,
+        timeout: 1,
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = client::connect(addr).await.unwrap();
+    assert_eq!(
+        vec![
+            ("tcp-keepalive".to_string(), "60".to_string()),
+            ("tcp-nodelay".to_string(), "no".to_string()),
+            ("timeout".to_string(), "1".to_string()),
+        ],
+        client.config_get("t*").await.unwrap()
+    );
+
+    // The options apply to the clients connecting from now on
+    client.config_set("tcp-keepalive", "0").await.unwrap();
+    client.config_set("tcp-nodelay", "yes").await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+    other.set("foo", "bar".into()).await.unwrap();
+
+    // Idle clients are disconnected once the timeout elapses
+    time::sleep(Duration::from_millis(1500)).await;
+    assert!(client.get("foo").await.is_err());
+    assert!(other.get("foo").await.is_err());
+}
+
+/// A transaction never yields while the other connections wait for it, even
+/// on a runtime with a single worker thread, and commands waiting on another
+/// node may not be queued.
+#[tokio::test]
+async fn transactions_never_yield() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-multi.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (addr, _shutdown, _) = start_server_with_snapshot(&path).await;
+    let mut client = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["set".into(), "key".into(), "1".into()])
+        .await
+        .unwrap();
+    client.queue(vec!["save".into()]).await.unwrap();
+    let replies = time::timeout(Duration::from_secs(5), client.exec())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(replies, vec!["OK", "OK"]);
+    assert!(path.exists());
+
+    let value = time::timeout(Duration::from_secs(5), other.get("key"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some("1".into()), value);
+
+    client.multi().await.unwrap();
+    let err = client
+        .queue(vec![
+            "migrate".into(),
+            "127.0.0.1".into(),
+            "1".into(),
+            "key".into(),
+            "0".into(),
+            "1000".into(),
+        ])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR Command not allowed inside a transaction",
+        err.to_string()
+    );
+    let err = client
+        .queue(vec![
+            "cluster".into(),
+            "meet".into(),
+            "127.0.0.1".into(),
+            "1".into(),
+        ])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR Command not allowed inside a transaction",
+        err.to_string()
+    );
+    assert!(client.exec().await.is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// The passwords of the commands are redacted before the commands are streamed
+/// to the monitors or recorded in the slow log.
+#[tokio::test]
+async fn monitor_redacts_passwords() {
+    let (addr, _) = start_server().await;
+    let mut monitor = client::connect(addr)
+        .await
+        .unwrap()
+        .monitor()
+        .await
+        .unwrap();
+    let mut client = client::connect(addr).await.unwrap();
+    client
+        .config_set("slowlog-log-slower-than", "0")
+        .await
+        .unwrap();
+    monitor.next_command().await.unwrap().unwrap();
+
+    client
+        .acl_setuser("eve", vec!["on".into(), ">hunter2".into()])
+        .await
+        .unwrap();
+    let _ = client.config_set("requirepass", "topsecret").await;
+    let _ = client.auth("hunter2").await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let args = [
+        "migrate",
+        "127.0.0.1",
+        "1",
+        "",
+        "0",
+        "10",
+        "auth",
+        "hunter2",
+        "keys",
+        "a",
+    ];
+    let frame = Frame::Array(
+        args.iter()
+            .map(|arg| Frame::Bulk(Bytes::from(*arg)))
+            .collect(),
+    );
+    connection.write_frame(&frame).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap();
+
+    let expected = [
+        "\"acl\" \"setuser\" \"eve\" \"(redacted)\" \"(redacted)\"",
+        "\"config\" \"set\" \"requirepass\" \"(redacted)\"",
+        "\"auth\" \"(redacted)\"",
+        "\"auth\" \"(redacted)\" \"keys\" \"a\"",
+    ];
+    for expected in &expected {
+        let line = monitor.next_command().await.unwrap().unwrap();
+        assert!(line.ends_with(expected), "{}", line);
+    }
+
+    for entry in client.slowlog_get(None).await.unwrap() {
+        for arg in &entry.args {
+            assert!(arg != "hunter2" && arg != "topsecret", "{:?}", entry.args);
+        }
+    }
+}
+
+/// Blocking commands behave as their non-blocking variant in a transaction or
+/// a script, replying right away when no data is available.
+#[tokio::test]
+async fn blocking_commands_in_transaction() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client
+        .xgroup_create("stream", "group", "$", true)
+        .await
+        .unwrap();
+
+    client.multi().await.unwrap();
+    for command in [
+        &["blpop", "list", "0"][..],
+        &["brpop", "list", "0"],
+        &["brpoplpush", "list", "other", "0"],
+        &["blmove", "list", "other", "left", "right", "0"],
+        &["bzpopmin", "zset", "0"],
+        &["bzpopmax", "zset", "0"],
+        &["xread", "block", "0", "streams", "stream", "$"],
+        &[
+            "xreadgroup",
+            "group",
+            "group",
+            "consumer",
+            "block",
+            "0",
+            "streams",
+            "stream",
+            ">",
+        ],
+        &["wait", "1", "0"],
+    ] {
+        let args = command
+            .iter()
+            .map(|arg| Bytes::from(arg.to_string()))
+            .collect();
+        client.queue(args).await.unwrap();
+    }
+
+    let replies = time::timeout(Duration::from_secs(5), client.exec())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(9, replies.len());
+    for reply in &replies[..8] {
+        assert!(matches!(reply, Frame::Null), "{:?}", reply);
+    }
+    assert!(matches!(replies[8], Frame::Integer(0)));
+
+    // Values available are popped as usual
+    client
+        .rpush("list", vec!["1".into(), "2".into()])
+        .await
+        .unwrap();
+    client.multi().await.unwrap();
+    client
+        .queue(vec!["blpop".into(), "list".into(), "0".into()])
+        .await
+        .unwrap();
+    client
+        .queue(vec!["blpop".into(), "list".into(), "0".into()])
+        .await
+        .unwrap();
+    client
+        .queue(vec!["blpop".into(), "list".into(), "0".into()])
+        .await
+        .unwrap();
+
+    let replies = client.exec().await.unwrap().unwrap();
+    assert!(matches!(&replies[0], Frame::Array(popped) if popped.len() == 2));
+    assert!(matches!(&replies[1], Frame::Array(popped) if popped.len() == 2));
+    assert!(matches!(replies[2], Frame::Null));
+
+    // As do scripts
+    let reply = client
+        .eval(
+            "return redis.call('blpop', KEYS[1], 0)",
+            vec!["list".into()],
+            vec![],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Null));
+}
+
 async fn start_server() -> (SocketAddr, JoinHandle<mini_redis::Result<()>>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
-    let handle = tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    let handle =
+        tokio::spawn(async move { server::run(vec![listener], tokio::signal::ctrl_c()).await });
 
     (addr, handle)
 }
+
+/// Starts a server saving its snapshots to `path`, returning its address, a
+/// channel to shut it down and its handle.
+async fn start_server_with_snapshot(
+    path: &std::path::Path,
+) -> (
+    SocketAddr,
+    tokio::sync::oneshot::Sender<()>,
+    JoinHandle<mini_redis::Result<()>>,
+) {
+    start_server_with_config(server::Config {
+        snapshot_path: Some(path.to_path_buf()),
+        ..server::Config::default()
+    })
+    .await
+}
+
+/// Starts a server configured by `config`, returning its address, a channel to
+/// shut it down and its handle.
+async fn start_server_with_config(
+    config: server::Config,
+) -> (
+    SocketAddr,
+    tokio::sync::oneshot::Sender<()>,
+    JoinHandle<mini_redis::Result<()>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let handle =
+        tokio::spawn(async move { server::run_with_config(vec![listener], config, rx).await });
+
+    (addr, tx, handle)
+}
+
+/// Computes the CRC-64/XZ checksum of `data`, as `DUMP` does.
+fn crc64(data: &[u8]) -> u64 {
+    let crc = data.iter().fold(!0u64, |mut crc, &byte| {
+        crc ^= byte as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xc96c_5795_d787_0f42
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    });
+    !crc
+}
@@ -119,6 +119,48 @@ async fn key_value_timeout() {
     assert_eq!(b"$-1\r\n", &response);
 }
 
+/// Keys are also expired lazily when accessed, against the same clock as the
+/// background purge, so that pausing time applies to both.
+#[tokio::test]
+async fn key_value_timeout_on_access() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Disable the background purge
+    stream
+        .write_all(b"*3\r\n$5\r\nDEBUG\r\n$17\r\nSET-ACTIVE-EXPIRE\r\n$1\r\n0\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nEX\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Wait for the key to expire
+    time::advance(Duration::from_secs(1)).await;
+
+    // Get the key, it is expired on access
+    stream
+        .write_all(b"*2\r\n$6\r\nEXISTS\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+}
+
 #[tokio::test]
 async fn pub_sub() {
     let addr = start_server().await;
@@ -397,11 +439,397 @@ async fn send_error_get_set_after_subscribe() {
     assert_eq!(b"-ERR unknown command \'get\'\r\n", &response);
 }
 
+/// Trimming options of XADD are applied once the entry is added.
+#[tokio::test]
+async fn stream_add_with_maxlen() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    for seq in b"123" {
+        let mut cmd = b"*7\r\n$4\r\nXADD\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n2\r\n\
+                        $3\r\n1-?\r\n$1\r\nf\r\n$1\r\nv\r\n"
+            .to_vec();
+        let pos = cmd.iter().position(|b| *b == b'?').unwrap();
+        cmd[pos] = *seq;
+
+        stream.write_all(&cmd).await.unwrap();
+
+        let mut response = [0; 9];
+        stream.read_exact(&mut response).await.unwrap();
+        assert_eq!(
+            format!("$3\r\n1-{}\r\n", *seq as char).as_bytes(),
+            &response
+        );
+    }
+
+    stream
+        .write_all(b"*2\r\n$4\r\nXLEN\r\n$1\r\ns\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    // LIMIT requires approximate trimming
+    stream
+        .write_all(
+            b"*6\r\n$5\r\nXTRIM\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n0\r\n\
+              $5\r\nLIMIT\r\n$1\r\n1\r\n",
+        )
+        .await
+        .unwrap();
+
+    let mut response = [0; 18];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-ERR syntax error,", &response);
+}
+
+#[tokio::test]
+async fn bitmap_negative_position() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // No set bit in a missing key is reported as a negative integer
+    stream
+        .write_all(b"*3\r\n$6\r\nBITPOS\r\n$3\r\nfoo\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":-1\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$6\r\nSETBIT\r\n$3\r\nfoo\r\n$1\r\n7\r\n$1\r\n2\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 20];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-ERR bit is not an i", &response);
+}
+
+/// `SET` options are case insensitive and may be combined. With `GET`, the old
+/// value is returned even when the `NX` condition prevents the write.
+#[tokio::test]
+async fn set_nx_get() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(
+            b"*6\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n$2\r\nnx\r\n$2\r\nex\r\n$2\r\n10\r\n",
+        )
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbaz\r\n$2\r\nNX\r\n$3\r\nGET\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\nbar\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbaz\r\n$2\r\nXX\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // A zero expiration is rejected without closing the connection
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n$2\r\nEX\r\n$1\r\n0\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 43];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-ERR invalid expire time in 'set' command\r\n"[..],
+        &response[..]
+    );
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\nbaz\r\n", &response);
+}
+
+/// Once `HELLO 3` switches the connection to RESP3, replies use its types,
+/// while they are downgraded to RESP2 before.
+#[tokio::test]
+async fn hello_resp3() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*4\r\n$4\r\nZADD\r\n$1\r\nz\r\n$3\r\n1.5\r\n$1\r\nm\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nZSCORE\r\n$1\r\nz\r\n$1\r\nm\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\n1.5\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")
+        .await
+        .unwrap();
+
+    let version = env!("CARGO_PKG_VERSION");
+    let expected = format!(
+        "%7\r\n$6\r\nserver\r\n$5\r\nredis\r\n$7\r\nversion\r\n${}\r\n{}\r\n\
+         $5\r\nproto\r\n:3\r\n$2\r\nid\r\n:1\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n\
+         $4\r\nrole\r\n$6\r\nmaster\r\n$7\r\nmodules\r\n*0\r\n",
+        version.len(),
+        version
+    );
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(expected.as_bytes(), &response[..]);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nZSCORE\r\n$1\r\nz\r\n$1\r\nm\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 6];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b",1.5\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 3];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"_\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n4\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 8];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-NOPROTO", &response);
+}
+
+/// Commands may be sent inline, as a line of space separated arguments, as
+/// typed in `telnet`.
+#[tokio::test]
+async fn inline_commands() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"SET foo \"bar baz\\x21\"\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Empty lines are skipped, and lines may end with a single `\n`
+    stream.write_all(b"\r\n  get   foo\n").await.unwrap();
+
+    let mut response = [0; 14];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$8\r\nbar baz!\r\n", &response);
+
+    // Inline commands and frames may be mixed
+    stream
+        .write_all(b"APPEND foo '?'\r\n*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 19];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":9\r\n$9\r\nbar baz!?\r\n", &response);
+
+    // Unbalanced quotes are replied to with an error, then the connection is
+    // closed
+    stream.write_all(b"GET \"foo\r\n").await.unwrap();
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-ERR Protocol error: unbalanced quotes in request\r\n"[..],
+        &response[..]
+    );
+}
+
+/// Any number of empty lines may precede an inline command.
+#[tokio::test]
+async fn inline_commands_after_empty_lines() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    let mut request = vec![b'\n'; 5 * 1024 * 1024];
+    request.extend_from_slice(b"GET foo\r\n");
+    stream.write_all(&request).await.unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+/// Every form of unbalanced quotes is replied to with a protocol error before
+/// the connection is closed, and the server keeps serving other clients.
+#[tokio::test]
+async fn inline_commands_unbalanced_quotes() {
+    let addr = start_server().await;
+
+    for request in [
+        &b"GET 'foo\r\n"[..],
+        b"GET \"foo\"bar\r\n",
+        b"GET 'foo'bar\r\n",
+        b"SET foo \"bar\\\"\r\n",
+    ] {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await.unwrap();
+        assert_eq!(
+            &b"-ERR Protocol error: unbalanced quotes in request\r\n"[..],
+            &response[..]
+        );
+    }
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET foo\r\n").await.unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+/// After `CLIENT REPLY OFF`, commands are applied without being replied to
+/// until `CLIENT REPLY ON`. `CLIENT REPLY SKIP` drops the reply to the next
+/// command only.
+#[tokio::test]
+async fn client_reply() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"CLIENT REPLY OFF\r\nSET foo 1\r\nINCR foo\r\nCLIENT REPLY ON\r\nGET foo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 12];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n$1\r\n2\r\n", &response);
+
+    stream
+        .write_all(b"CLIENT REPLY SKIP\r\nINCR foo\r\nINCR foo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":4\r\n", &response);
+}
+
+/// The summary of a consumer group without pending entries holds no IDs and an
+/// empty array of consumers.
+#[tokio::test]
+async fn xpending_summary_without_pending_entries() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"XGROUP CREATE jobs workers $ MKSTREAM\r\nXPENDING jobs workers\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 27];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n*4\r\n:0\r\n$-1\r\n$-1\r\n*0\r\n", &response);
+}
+
+/// Commands with invalid arguments are replied to with an error, and the
+/// connection stays open. A transaction is aborted by its invalid commands.
+#[tokio::test]
+async fn invalid_arguments_keep_connection_open() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    for command in [
+        &b"BLPOP k -1\r\n"[..],
+        b"SET k v NX XX\r\n",
+        b"INCRBY k abc\r\n",
+        b"ZADD zz nan x\r\n",
+    ] {
+        stream.write_all(command).await.unwrap();
+
+        let line = read_line(&mut stream).await;
+        assert!(line.starts_with("-ERR "), "{}", line);
+    }
+
+    stream
+        .write_all(b"MULTI\r\nSET k v NX XX\r\nEXEC\r\nGET k\r\n")
+        .await
+        .unwrap();
+
+    assert_eq!("+OK", read_line(&mut stream).await);
+    assert!(read_line(&mut stream).await.starts_with("-ERR "));
+    assert!(read_line(&mut stream).await.starts_with("-EXECABORT "));
+    assert_eq!("$-1", read_line(&mut stream).await);
+}
+
+/// Reads a line of the response, without its trailing `\r\n`.
+async fn read_line(stream: &mut TcpStream) -> String {
+    let mut line = vec![];
+
+    while !line.ends_with(b"\r\n") {
+        line.push(stream.read_u8().await.unwrap());
+    }
+
+    line.truncate(line.len() - 2);
+    String::from_utf8(line).unwrap()
+}
+
 async fn start_server() -> SocketAddr {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
-    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    tokio::spawn(async move { server::run(vec![listener], tokio::signal::ctrl_c()).await });
 
     addr
 }
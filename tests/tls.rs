@@ -0,0 +1,249 @@
+#![cfg(feature = "tls")]
+
+use mini_redis::server::{self, ClientAuth, TlsConfig};
+use mini_redis::{buffer, client};
+
+use bytes::Bytes;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::webpki::DNSNameRef;
+use tokio_rustls::TlsConnector;
+
+/// Commands are encrypted once the TLS handshake completes.
+#[tokio::test]
+async fn tls_get() {
+    let addr = start_server(ClientAuth::No).await;
+
+    let mut stream = connect(addr, client_config(false)).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    // The server does not speak plain text on the same port.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    assert!(!response.starts_with(b"$-1"));
+}
+
+/// Clients without a certificate signed by the CA are rejected when client
+/// authentication is required.
+#[tokio::test]
+async fn tls_client_auth() {
+    let addr = start_server(ClientAuth::Required).await;
+
+    assert!(!get(addr, client_config(false)).await);
+    assert!(get(addr, client_config(true)).await);
+
+    let addr = start_server(ClientAuth::Optional).await;
+
+    assert!(get(addr, client_config(false)).await);
+    assert!(get(addr, client_config(true)).await);
+}
+
+/// The server does not start if the certificates cannot be read.
+#[tokio::test]
+async fn tls_invalid_config() {
+    for tls in [
+        TlsConfig {
+            cert_path: cert_path("missing.crt"),
+            ..tls_config(ClientAuth::No)
+        },
+        TlsConfig {
+            key_path: cert_path("server.crt"),
+            ..tls_config(ClientAuth::No)
+        },
+        TlsConfig {
+            ca_cert_path: None,
+            ..tls_config(ClientAuth::Required)
+        },
+    ] {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = server::Config {
+            tls: Some(tls),
+            ..server::Config::default()
+        };
+
+        assert!(
+            server::run_with_config(vec![listener], config, std::future::pending::<()>())
+                .await
+                .is_err()
+        );
+    }
+}
+
+/// Clients, subscribers and buffered clients connect over TLS with
+/// `client::connect_tls`.
+#[tokio::test]
+async fn client_connect_tls() {
+    let addr = start_server(ClientAuth::Required).await;
+    let config = Arc::new(client_config(true));
+
+    let mut client = client::connect_tls(addr, "localhost", config.clone())
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(
+        Some(Bytes::from("world")),
+        client.get("hello").await.unwrap()
+    );
+
+    let subscriber = client::connect_tls(addr, "localhost", config.clone())
+        .await
+        .unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["channel".into()]).await.unwrap();
+    assert_eq!(
+        1,
+        client.publish("channel", "message".into()).await.unwrap()
+    );
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("message", message.content);
+
+    let mut buffer = buffer(
+        client::connect_tls(addr, "localhost", config.clone())
+            .await
+            .unwrap(),
+    );
+    assert_eq!(
+        Some(Bytes::from("world")),
+        buffer.get("hello").await.unwrap()
+    );
+
+    // The certificate of the server is only valid for `localhost`.
+    assert!(client::connect_tls(addr, "example.com", config.clone())
+        .await
+        .is_err());
+    assert!(client::connect_tls(addr, "not a domain", config)
+        .await
+        .is_err());
+
+    // The server is not trusted without the test CA.
+    let config = Arc::new(mini_redis::rustls::ClientConfig::new());
+    assert!(client::connect_tls(addr, "localhost", config)
+        .await
+        .is_err());
+}
+
+/// Connections over the Unix domain socket are not encrypted.
+#[tokio::test]
+async fn unix_socket_without_tls() {
+    let path = std::env::temp_dir().join(format!("mini-redis-tls-{}.sock", std::process::id()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = server::Config {
+        tls: Some(tls_config(ClientAuth::Required)),
+        unixsocket: Some(path.clone()),
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(vec![listener], config, tokio::signal::ctrl_c()).await
+    });
+
+    while !path.exists() {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    let mut client = client::connect_unix(&path).await.unwrap();
+    assert_eq!(None, client.get("hello").await.unwrap());
+}
+
+/// Gets a missing key over TLS, returning whether the server replies.
+async fn get(addr: SocketAddr, config: ClientConfig) -> bool {
+    let mut stream = match connect(addr, config).await {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+
+    // With TLS 1.3, the server rejects the certificate of the client after
+    // the client completes the handshake.
+    if stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .is_err()
+    {
+        return false;
+    }
+
+    let mut response = [0; 5];
+    match stream.read_exact(&mut response).await {
+        Ok(_) => &response == b"$-1\r\n",
+        Err(_) => false,
+    }
+}
+
+async fn connect(
+    addr: SocketAddr,
+    config: ClientConfig,
+) -> std::io::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let socket = TcpStream::connect(addr).await?;
+    let domain = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+
+    TlsConnector::from(Arc::new(config))
+        .connect(domain, socket)
+        .await
+}
+
+/// Returns the configuration of a client trusting the test CA, presenting
+/// the test client certificate if `with_cert` is `true`.
+fn client_config(with_cert: bool) -> ClientConfig {
+    let mut config = ClientConfig::new();
+    let mut ca = BufReader::new(File::open(cert_path("ca.crt")).unwrap());
+    config.root_store.add_pem_file(&mut ca).unwrap();
+
+    if with_cert {
+        let mut cert = BufReader::new(File::open(cert_path("client.crt")).unwrap());
+        let mut key = BufReader::new(File::open(cert_path("client.key")).unwrap());
+        let cert = pemfile::certs(&mut cert).unwrap();
+        let key = pemfile::pkcs8_private_keys(&mut key).unwrap().remove(0);
+        config.set_single_client_cert(cert, key).unwrap();
+    }
+
+    config
+}
+
+fn tls_config(auth_clients: ClientAuth) -> TlsConfig {
+    TlsConfig {
+        cert_path: cert_path("server.crt"),
+        key_path: cert_path("server.key"),
+        ca_cert_path: Some(cert_path("ca.crt")),
+        auth_clients,
+    }
+}
+
+/// Returns the path of a file generated by `tests/tls/gen-certs.sh`.
+fn cert_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/tls")
+        .join(name)
+}
+
+async fn start_server(auth_clients: ClientAuth) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        tls: Some(tls_config(auth_clients)),
+        ..server::Config::default()
+    };
+
+    tokio::spawn(async move {
+        server::run_with_config(vec![listener], config, tokio::signal::ctrl_c()).await
+    });
+
+    addr
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Generates the certificates used by the TLS tests: a CA, a certificate for
+# `localhost` used by the server, and a certificate used by the clients, both
+# signed by the CA.
+set -e
+cd "$(dirname "$0")"
+
+openssl req -x509 -new -nodes -newkey ec -pkeyopt ec_paramgen_curve:P-256 \
+    -keyout ca.key -out ca.crt -days 36500 -subj "/O=mini-redis/CN=Certificate Authority" \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign"
+
+for name in server client; do
+    openssl req -new -nodes -newkey ec -pkeyopt ec_paramgen_curve:P-256 \
+        -keyout "$name.key" -out "$name.csr" -subj "/O=mini-redis/CN=$name"
+    openssl x509 -req -in "$name.csr" -CA ca.crt -CAkey ca.key -CAcreateserial \
+        -out "$name.crt" -days 36500 -extfile /dev/stdin <<EXT
+basicConstraints=critical,CA:FALSE
+keyUsage=critical,digitalSignature
+extendedKeyUsage=serverAuth,clientAuth
+subjectAltName=DNS:localhost
+EXT
+    rm "$name.csr"
+done
+rm -f ca.srl ca.key